@@ -0,0 +1,21 @@
+//! Benchmarks the simulations headless, e.g. `cargo bench` for every workload, or `cargo bench -- rk4` for
+//! those whose names contain "rk4". Build with `--no-default-features` to leave out the renderer.
+use feriphys::simulation::bench;
+
+use std::time::Duration;
+
+const WARM_UP: Duration = Duration::from_millis(500);
+const BUDGET: Duration = Duration::from_secs(2);
+
+fn main() {
+    // cargo passes --bench, which isn't a filter.
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    for mut workload in bench::suite() {
+        if filter
+            .as_ref()
+            .is_none_or(|filter| workload.get_name().contains(filter.as_str()))
+        {
+            println!("{}", bench::measure(&mut workload, WARM_UP, BUDGET));
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, ValueEnum};
+
+use crate::graphics::settings::WindowMode;
 
 #[derive(ValueEnum, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Demos {
@@ -9,11 +13,57 @@ pub enum Demos {
     Cloth,
     RigidBody,
     Sph,
+    WindTunnel,
+    Vortex,
+    Lbm,
+    Charged,
+    Billiards,
+    Bridge,
+    TargetRange,
+    Slosh,
 }
 
+/// Interactive demos of physics simulations.
 #[derive(Parser)]
 pub struct FeriphysArgs {
     /// The first argument!
     #[clap(value_enum)]
     pub demo: Demos,
+    /// Rather than opening the demo, fits its parameters to the reference trajectory in the CSV file, of a time and x, y, and z positions on each line.
+    #[clap(long, value_name = "CSV")]
+    pub fit: Option<PathBuf>,
+    /// Opens the cloth demo in the scenario in the file, of key = value lines like the presets in res/scenarios/cloth.
+    #[clap(long, value_name = "FILE")]
+    pub scenario: Option<PathBuf>,
+    #[clap(flatten)]
+    pub window: WindowArgs,
+    #[cfg(feature = "network")]
+    #[clap(flatten)]
+    pub network: NetworkArgs,
+}
+
+// Overrides for the persisted graphics settings, for this run only.
+// This isn't a doc comment, since clap would use it as the about text of the whole command.
+#[derive(Args)]
+pub struct WindowArgs {
+    /// The initial window width, in physical pixels.
+    #[clap(long)]
+    pub width: Option<u32>,
+    /// The initial window height, in physical pixels.
+    #[clap(long)]
+    pub height: Option<u32>,
+    #[clap(long, value_enum)]
+    pub window_mode: Option<WindowMode>,
+}
+
+// Networking the demo with a twin instance, which is built with the network feature.
+#[cfg(feature = "network")]
+#[derive(Args)]
+pub struct NetworkArgs {
+    /// Streams the demo's simulation to the instances viewing it at the address, e.g. 0.0.0.0:7878.
+    #[clap(long, value_name = "ADDRESS", conflicts_with = "view")]
+    pub host: Option<String>,
+    /// Renders the simulation streamed by the host at the address, rather than simulating it.
+    #[clap(long, value_name = "ADDRESS")]
+    pub view: Option<String>,
 }
@@ -0,0 +1,616 @@
+/// A demo of billiards and air hockey, discs bouncing in 2D off each other and the table's cushions.
+use crate::simulation::configurable::Configurable;
+use crate::{
+    args::WindowArgs,
+    graphics::{
+        self,
+        camera::CameraBundle,
+        debug_draw::{self, DebugDraw},
+        entity::ColoredMeshEntity,
+        forms,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        interpolation::{self, Interpolator},
+        light,
+        palette::Theme,
+        scene::{ColoredMeshEntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, InputAction, Parameter},
+    },
+    simulation::billiards::{DiscKind, Simulation},
+};
+
+use cgmath::{InnerSpace, Rotation3, Vector2, Vector3, Zero};
+use winit::{
+    dpi::PhysicalPosition,
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+use super::utils;
+
+/// The most discs, cushions, and pockets of any scenario's table.
+const MAX_DISCS: usize = 16;
+const MAX_CUSHIONS: usize = 8;
+const MAX_POCKETS: usize = 6;
+
+const CUSHION_HEIGHT: f32 = 0.04;
+const CUSHION_THICKNESS: f32 = 0.05;
+/// Pucks and mallets are drawn as flattened balls.
+const PUCK_HEIGHT: f32 = 0.4;
+// The markers on the discs' rims, which turn as they spin.
+const SPIN_MARKER_RADIUS: f32 = 0.2;
+
+/// How near to a disc's rim the cue must be drawn from to strike it.
+const CUE_REACH: f32 = 0.1;
+/// The cue can't be drawn back further than this, in m.
+const MAX_CUE_DRAW: f32 = 1.0;
+const MAX_DEBUG_LINES: usize = 8;
+
+const CUE_BALL_COLOR: [f32; 3] = [0.95, 0.95, 0.9];
+const POCKET_COLOR: [f32; 3] = [0.05, 0.05, 0.05];
+const SPIN_MARKER_COLOR: [f32; 3] = [0.1, 0.1, 0.1];
+
+/// The point in the world over the point on the table, which lies in the xz plane with its y towards -z.
+fn to_world(point: Vector2<f32>, height: f32) -> Vector3<f32> {
+    Vector3::new(point.x, height, -point.y)
+}
+
+/// The height of the disc as drawn, relative to its radius; balls are round, and pucks and mallets flattened.
+fn get_flattening(kind: DiscKind) -> f32 {
+    match kind {
+        DiscKind::Cue | DiscKind::Object => 1.0,
+        DiscKind::Puck | DiscKind::Mallet => PUCK_HEIGHT,
+    }
+}
+
+struct State {
+    simulation: Simulation,
+    gpu: GPUInterface,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+    camera_bundle: CameraBundle,
+    light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    scene: Scene,
+    theme: Theme,
+    table_entity: ColoredMeshEntityHandle,
+    cushion_entity: ColoredMeshEntityHandle,
+    pocket_entity: ColoredMeshEntityHandle,
+    disc_entity: ColoredMeshEntityHandle,
+    spin_marker_entity: ColoredMeshEntityHandle,
+    debug_draw: DebugDraw,
+    mouse_pressed: bool,
+    cursor_position: PhysicalPosition<f64>,
+    // The disc the cue is being drawn back from, while right dragging.
+    cued_disc: Option<usize>,
+    time_accumulator: std::time::Duration,
+    disc_interpolator: Interpolator,
+    spin_marker_interpolator: Interpolator,
+}
+
+impl State {
+    fn new(window: &Window) -> Self {
+        let gpu: GPUInterface = GPUInterface::new(window);
+        let camera_bundle = CameraBundle::new(
+            &gpu,
+            (0.0, 2.0, 1.6),
+            cgmath::Deg(-90.0),
+            cgmath::Deg(-50.0),
+        );
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
+
+        let light_uniform = light::LightUniform::new([1.0, 3.0, 1.0], [1.0, 1.0, 1.0]);
+        let (light_bind_group_layout, light_bind_group) =
+            light::create_light_bind_group(&gpu, light_uniform);
+
+        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+            &gpu,
+            &camera_bundle,
+            &light_bind_group_layout,
+        );
+
+        let theme = Theme::default();
+        let simulation = Simulation::new();
+
+        let mut scene = Scene::new();
+        let table = forms::get_quad(&gpu.device, theme.obstacle);
+        let table_entity =
+            scene.add_colored_mesh_entity(ColoredMeshEntity::new(&gpu, table, Vec::new(), Some(1)));
+        let cushion = forms::get_cube(&gpu.device, theme.container);
+        let cushion_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            cushion,
+            Vec::new(),
+            Some(MAX_CUSHIONS),
+        ));
+        let ball = forms::generate_sphere(&gpu.device, theme.body, 1.0, 16, 16);
+        let pocket_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::generate_sphere(&gpu.device, POCKET_COLOR, 1.0, 16, 4),
+            Vec::new(),
+            Some(MAX_POCKETS),
+        ));
+        let disc_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            ball,
+            Vec::new(),
+            Some(MAX_DISCS),
+        ));
+        let spin_marker_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::generate_sphere(&gpu.device, SPIN_MARKER_COLOR, 1.0, 8, 8),
+            Vec::new(),
+            Some(MAX_DISCS),
+        ));
+        let debug_draw = DebugDraw::new(&gpu, &camera_bundle, MAX_DEBUG_LINES);
+
+        Self {
+            simulation,
+            gpu,
+            render_pipeline,
+            depth_texture,
+            camera_bundle,
+            light_bind_group,
+            light_bind_group_layout,
+            scene,
+            theme,
+            table_entity,
+            cushion_entity,
+            pocket_entity,
+            disc_entity,
+            spin_marker_entity,
+            debug_draw,
+            mouse_pressed: false,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            cued_disc: None,
+            time_accumulator: std::time::Duration::from_millis(0),
+            disc_interpolator: Interpolator::new(),
+            spin_marker_interpolator: Interpolator::new(),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        graphics::util::resize(
+            new_size,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle.projection,
+        );
+    }
+
+    /// The table and discs are colored by their instances, so they're recolored as they're next drawn.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.debug_draw
+                .rebuild_pipeline(&self.gpu, &self.camera_bundle);
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state,
+                ..
+            } => {
+                let cursor = self.get_cursor_on_table();
+                if *state == ElementState::Pressed {
+                    self.cued_disc = cursor
+                        .and_then(|cursor| self.simulation.get_nearest_disc(cursor, CUE_REACH));
+                } else if let (Some(index), Some(cursor)) = (self.cued_disc.take(), cursor) {
+                    if index < self.simulation.get_discs().len() {
+                        let impulse = self.get_cue_draw(index, cursor)
+                            * self.simulation.get_config().cue_strength;
+                        self.simulation.strike(index, impulse);
+                    }
+                }
+                true
+            }
+            _ => {
+                utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+            }
+        }
+    }
+
+    /// Where the ray through the cursor hits the table, if it does.
+    fn get_cursor_on_table(&self) -> Option<Vector2<f32>> {
+        let (origin, direction) = self.camera_bundle.camera.cursor_ray(
+            &self.camera_bundle.projection,
+            self.cursor_position,
+            self.gpu.config.width,
+            self.gpu.config.height,
+        );
+        if direction.y >= 0.0 {
+            return None;
+        }
+        let hit = origin + direction * (-origin.y / direction.y);
+        Some(Vector2::new(hit.x, -hit.z))
+    }
+
+    /// The vector the cue's drawn back along, from the cursor to the disc, which it'll strike the disc along.
+    fn get_cue_draw(&self, index: usize, cursor: Vector2<f32>) -> Vector2<f32> {
+        let draw = self.simulation.get_discs()[index].position - cursor;
+        if draw.magnitude() > MAX_CUE_DRAW {
+            draw.normalize() * MAX_CUE_DRAW
+        } else {
+            draw
+        }
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration) {
+        self.time_accumulator += frame_time;
+        self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
+
+        while self.time_accumulator >= self.simulation.get_timestep() {
+            self.disc_interpolator
+                .record(&get_disc_instances(&self.simulation, self.theme));
+            self.spin_marker_interpolator
+                .record(&get_spin_marker_instances(&self.simulation));
+            let elapsed_sim_time = self.simulation.step();
+            self.time_accumulator -= elapsed_sim_time;
+        }
+        // Potting a disc may have renumbered the one being cued.
+        if self
+            .cued_disc
+            .is_some_and(|index| index >= self.simulation.get_discs().len())
+        {
+            self.cued_disc = None;
+        }
+
+        self.update_instances();
+        if let (Some(index), Some(cursor)) = (self.cued_disc, self.get_cursor_on_table()) {
+            let disc = self.simulation.get_discs()[index].position;
+            let draw = self.get_cue_draw(index, cursor);
+            self.debug_draw.line(
+                to_world(disc - draw, CUSHION_HEIGHT),
+                to_world(disc, CUSHION_HEIGHT),
+                debug_draw::RED,
+            );
+            self.debug_draw.arrow(
+                to_world(disc, CUSHION_HEIGHT),
+                to_world(draw, 0.0),
+                debug_draw::YELLOW,
+            );
+        }
+    }
+
+    fn update_instances(&mut self) {
+        let half_extents = self.simulation.get_half_extents();
+        let table = Instance {
+            position: Vector3::zero(),
+            scale: Vector3::new(
+                2.0 * (half_extents.x + CUSHION_THICKNESS),
+                1.0,
+                2.0 * (half_extents.y + CUSHION_THICKNESS),
+            ),
+            color: Some(self.theme.obstacle),
+            ..Default::default()
+        };
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.table_entity, vec![table]);
+
+        // Each cushion's box lies along it, behind it, facing the table's center.
+        let cushions = self
+            .simulation
+            .get_cushions()
+            .iter()
+            .map(|cushion| {
+                let along = cushion.end - cushion.start;
+                let outward = Vector2::new(along.y, -along.x).normalize();
+                let center =
+                    (cushion.start + cushion.end) / 2.0 + outward * CUSHION_THICKNESS / 2.0;
+                Instance {
+                    position: to_world(center, CUSHION_HEIGHT / 2.0),
+                    rotation: cgmath::Quaternion::from_angle_y(cgmath::Rad(along.y.atan2(along.x))),
+                    scale: Vector3::new(along.magnitude(), CUSHION_HEIGHT, CUSHION_THICKNESS),
+                    color: Some(self.theme.container),
+                }
+            })
+            .collect();
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.cushion_entity, cushions);
+
+        let pocket_radius = self.simulation.get_pocket_radius();
+        let pockets = self
+            .simulation
+            .get_pockets()
+            .iter()
+            .map(|pocket| Instance {
+                // Just above the table, so they don't z-fight.
+                position: to_world(*pocket, 0.001),
+                scale: Vector3::new(pocket_radius, 0.001, pocket_radius),
+                ..Default::default()
+            })
+            .collect();
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.pocket_entity, pockets);
+
+        let alpha = interpolation::get_alpha(self.time_accumulator, self.simulation.get_timestep());
+        let mut disc_instances = get_disc_instances(&self.simulation, self.theme);
+        self.disc_interpolator.blend(&mut disc_instances, alpha);
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.disc_entity,
+            disc_instances,
+        );
+
+        let mut spin_markers = get_spin_marker_instances(&self.simulation);
+        self.spin_marker_interpolator
+            .blend(&mut spin_markers, alpha);
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.spin_marker_entity,
+            spin_markers,
+        );
+    }
+
+    fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // We'll use a CommandEncoder to create the commands to send to the GPU.
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.debug_draw.prepare(&self.gpu);
+
+        {
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
+            );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                self.debug_draw
+                    .draw(&mut render_pass, &camera_view.camera_bind_group);
+            }
+        }
+
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
+        encoder.finish()
+    }
+}
+
+/// The instances of the discs, with the objects colored in turn along the theme's colormap.
+fn get_disc_instances(simulation: &Simulation, theme: Theme) -> Vec<Instance> {
+    let discs = simulation.get_discs();
+    let object_count = discs
+        .iter()
+        .filter(|disc| disc.kind == DiscKind::Object)
+        .count()
+        .max(2);
+    let mut object_index = 0;
+    discs
+        .iter()
+        .map(|disc| {
+            let height = get_flattening(disc.kind);
+            let color = match disc.kind {
+                DiscKind::Cue => CUE_BALL_COLOR,
+                DiscKind::Object => {
+                    object_index += 1;
+                    theme
+                        .colormap
+                        .sample((object_index - 1) as f32 / (object_count - 1) as f32)
+                }
+                DiscKind::Puck => theme.accent,
+                DiscKind::Mallet => theme.body,
+            };
+            Instance {
+                position: to_world(disc.position, disc.radius * height),
+                scale: Vector3::new(disc.radius, disc.radius * height, disc.radius),
+                color: Some(color),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// The instances of the markers on top of each disc, towards its rim, so that spin shows.
+fn get_spin_marker_instances(simulation: &Simulation) -> Vec<Instance> {
+    simulation
+        .get_discs()
+        .iter()
+        .map(|disc| {
+            let rim = disc.position
+                + Vector2::new(disc.angle.cos(), disc.angle.sin()) * disc.radius * 0.7;
+            // The height of the disc's surface 0.7 of the way to its rim.
+            let height = get_flattening(disc.kind) * (1.0 + (1.0f32 - 0.49).sqrt());
+            Instance {
+                position: to_world(rim, disc.radius * height),
+                scale: Vector3::new(1.0, 1.0, 1.0) * disc.radius * SPIN_MARKER_RADIUS,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn help() -> Help {
+    Help {
+        title: "Billiards",
+        description: "Discs sliding on a table in 2D, bouncing off each other and the cushions. Friction at each contact throws struck discs and passes spin between them, and spinning discs bounce sideways off the cushions, like english in billiards.",
+        parameters: &[
+        Parameter {
+            name: "Scenario",
+            description: "The table: a racked pool table with pockets, or an air hockey table with a puck, two mallets, and a goal in each end.",
+        },
+        Parameter {
+            name: "Rolling and Spin Friction",
+            description: "How quickly the table slows moving and spinning discs.",
+        },
+        Parameter {
+            name: "Restitution",
+            description: "The fraction of the closing speed discs bounce off each other and the cushions with.",
+        },
+        Parameter {
+            name: "Disc and Cushion Friction",
+            description: "The friction between touching discs, and between discs and the cushions. It turns spin into a sideways bounce, and a glancing blow into spin.",
+        },
+        Parameter {
+            name: "Cue Strength",
+            description: "The impulse of the cue per meter it's drawn back.",
+        },
+        Parameter {
+            name: "English",
+            description: "How far right of center the cue strikes, as a fraction of the disc's radius, spinning it counterclockwise seen from above; negative english strikes left of center.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS
+            .into_iter()
+            .chain([InputAction {
+                input: "Right Mouse Drag",
+                description: "Draw the cue back from a disc, and release to strike it",
+            }])
+            .collect(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = utils::build_window(&event_loop, window_args);
+
+    let mut state = State::new(&window);
+
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
+    let mut ui = gui::billiards::BilliardsUi::new();
+
+    let mut current_time = std::time::SystemTime::now();
+    event_loop.run(move |event, _, control_flow| {
+        gui.handle_events(&event);
+
+        *control_flow = utils::control_flow(&state.gpu);
+        match event {
+            Event::MainEventsCleared => {
+                let new_time = std::time::SystemTime::now();
+                let frame_time = new_time.duration_since(current_time).unwrap();
+                current_time = new_time;
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
+                let simulation_render_command_buffer = state.render(&output);
+                let gui_render_command_buffer = gui.render(
+                    &mut ui,
+                    frame_time,
+                    &state.gpu.device,
+                    &state.gpu.config,
+                    &state.gpu.queue,
+                    &window,
+                    &output
+                );
+
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
+                output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, },
+                .. // We're not using device_id currently
+            } if state.mouse_pressed => {
+                state.camera_bundle.camera_controller.process_mouse(delta.0, delta.1)
+            }
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+}
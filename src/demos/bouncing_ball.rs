@@ -1,20 +1,28 @@
+use crate::args::WindowArgs;
 use crate::graphics;
 use crate::graphics::camera::CameraBundle;
 use crate::graphics::forms;
 use crate::graphics::gpu_interface::GPUInterface;
 use crate::graphics::instance::{Instance, InstanceRaw};
+use crate::graphics::interpolation::{self, Interpolator};
 use crate::graphics::model::{ColoredMesh, DrawColoredMesh, DrawLight, Model, ModelVertex, Vertex};
+use crate::graphics::palette::Theme;
+use crate::graphics::settings::GraphicsSettings;
 use crate::graphics::{light, resources, texture};
 use crate::gui;
+use crate::gui::help::{Help, Parameter};
+#[cfg(feature = "network")]
+use crate::network::Twin;
 use crate::simulation;
+use crate::simulation::collidable_mesh::CollidableMesh;
+use crate::simulation::container::Container;
 
 use cgmath::prelude::*;
-use wgpu::util::DeviceExt;
+use wgpu::{util::DeviceExt, BindGroupLayout, RenderPipeline};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
 };
 
 use super::utils;
@@ -29,6 +37,7 @@ const DYNAMIC_INSTANCE_INDEX_BALL: u32 = 0;
 struct State {
     gpu: GPUInterface,
     time_accumulator: std::time::Duration,
+    interpolator: Interpolator,
     #[allow(dead_code)]
     render_pipeline: wgpu::RenderPipeline,
     obj_model: Model,
@@ -44,12 +53,19 @@ struct State {
     dynamic_instance_buffer: wgpu::Buffer,
     depth_texture: texture::Texture,
     light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
     light_render_pipeline: wgpu::RenderPipeline,
     mouse_pressed: bool,
     colored_render_pipeline: wgpu::RenderPipeline,
     bounding_box_mesh: ColoredMesh,
+    /// The walls bounding_box_mesh was built for, which it's rebuilt from as they move.
+    container: Container,
     sphere_mesh: ColoredMesh,
     simulation_state: simulation::bounce::State,
+    /// The instance this one hosts the simulation for, or views the simulation of.
+    #[cfg(feature = "network")]
+    twin: Option<Twin>,
 }
 
 impl State {
@@ -66,70 +82,21 @@ impl State {
         let (light_bind_group_layout, light_bind_group) =
             light::create_light_bind_group(&gpu, light_uniform);
 
-        let depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth texture");
-
-        let render_pipeline_layout =
-            gpu.device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render Pipeline Layout"),
-                    bind_group_layouts: &[
-                        &texture_bind_group_layout,
-                        &camera_bundle.camera_bind_group_layout,
-                        &light_bind_group_layout,
-                    ],
-                    push_constant_ranges: &[],
-                });
-
-        // Render pipeline for textured models
-        let render_pipeline = {
-            let shader = wgpu::ShaderModuleDescriptor {
-                label: Some("Normal Shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shader.wgsl").into()),
-            };
-            graphics::util::create_render_pipeline(
-                &gpu.device,
-                &render_pipeline_layout,
-                gpu.config.format,
-                Some(texture::Texture::DEPTH_FORMAT),
-                &[ModelVertex::desc(), InstanceRaw::desc::<5>()],
-                shader,
-            )
-        };
-
-        // Render pipeline for our physical light object in the scene.
-        let light_render_pipeline = {
-            let layout = gpu
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Light Pipeline Layout"),
-                    bind_group_layouts: &[
-                        &camera_bundle.camera_bind_group_layout,
-                        &light_bind_group_layout,
-                    ],
-                    push_constant_ranges: &[],
-                });
-            let shader = wgpu::ShaderModuleDescriptor {
-                label: Some("Light Shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/light.wgsl").into()),
-            };
-            graphics::util::create_render_pipeline(
-                &gpu.device,
-                &layout,
-                gpu.config.format,
-                Some(texture::Texture::DEPTH_FORMAT),
-                &[ModelVertex::desc()],
-                shader,
-            )
-        };
-
-        // Render pipeline for colored meshes without any textures.
-        let colored_render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
-            &gpu,
-            &camera_bundle,
-            &light_bind_group_layout,
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
         );
 
+        let (render_pipeline, light_render_pipeline, colored_render_pipeline) =
+            create_render_pipelines(
+                &gpu,
+                &camera_bundle,
+                &texture_bind_group_layout,
+                &light_bind_group_layout,
+            );
+
         let lightbulb_model = resources::load_model(
             "cube.obj",
             &gpu.device,
@@ -138,8 +105,9 @@ impl State {
         )
         .unwrap();
 
-        let bounding_box_mesh = forms::get_cube_interior_normals(&gpu.device, [0.5, 0.0, 0.5]);
-        let sphere_mesh = forms::generate_sphere(&gpu.device, [0.2, 0.8, 0.2], 1.0, 32, 32);
+        let theme = Theme::default();
+        let bounding_box_mesh = forms::get_cube_interior_normals(&gpu.device, theme.container);
+        let sphere_mesh = forms::generate_sphere(&gpu.device, theme.accent, 1.0, 32, 32);
 
         // Create the static instances and its buffer. We'll use this for the bounding box, which won't move.
         let static_instances = vec![
@@ -154,7 +122,8 @@ impl State {
                     cgmath::Vector3::unit_z(),
                     cgmath::Deg(0.0),
                 ),
-                scale: 1.0,
+                scale: cgmath::Vector3::new(1.0, 1.0, 1.0),
+                color: None,
             },
             // STATIC_INSTANCE_INDEX_BOUNDING_BOX
             Instance {
@@ -167,7 +136,8 @@ impl State {
                     cgmath::Vector3::unit_z(),
                     cgmath::Deg(0.0),
                 ),
-                scale: 2.0,
+                scale: cgmath::Vector3::new(2.0, 2.0, 2.0),
+                color: None,
             },
         ];
         let static_instance_data = static_instances
@@ -195,7 +165,8 @@ impl State {
                     cgmath::Vector3::unit_z(),
                     cgmath::Deg(0.0),
                 ),
-                scale: 1.0,
+                scale: cgmath::Vector3::new(1.0, 1.0, 1.0),
+                color: None,
             },
         ];
         let dynamic_instance_data = dynamic_instances
@@ -215,6 +186,7 @@ impl State {
         Self {
             gpu,
             time_accumulator: std::time::Duration::from_millis(0),
+            interpolator: Interpolator::new(),
             render_pipeline,
             obj_model: lightbulb_model,
             camera_bundle,
@@ -224,12 +196,17 @@ impl State {
             dynamic_instance_buffer,
             depth_texture,
             light_bind_group,
+            light_bind_group_layout,
+            texture_bind_group_layout,
             light_render_pipeline,
             mouse_pressed: false,
             colored_render_pipeline,
             bounding_box_mesh,
+            container: *simulation_state.get_container(),
             sphere_mesh,
             simulation_state,
+            #[cfg(feature = "network")]
+            twin: None,
         }
     }
 
@@ -242,6 +219,35 @@ impl State {
         );
     }
 
+    /// Recolors the meshes with the theme.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.bounding_box_mesh
+            .set_color(&self.gpu.queue, theme.container);
+        self.sphere_mesh.set_color(&self.gpu.queue, theme.accent);
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            (
+                self.render_pipeline,
+                self.light_render_pipeline,
+                self.colored_render_pipeline,
+            ) = create_render_pipelines(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.texture_bind_group_layout,
+                &self.light_bind_group_layout,
+            );
+        }
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
         utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
     }
@@ -251,21 +257,49 @@ impl State {
         self.time_accumulator = self.time_accumulator + frame_time;
 
         self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
+
+        // A viewer shows the host's latest snapshot rather than simulating.
+        #[cfg(feature = "network")]
+        if let Some(Twin::Viewer(viewer)) = &mut self.twin {
+            viewer.receive(&mut self.simulation_state);
+            self.time_accumulator = std::time::Duration::ZERO;
+        }
 
         // SIMULATE until our simulation has "consumed" the accumulated time in discrete, fixed timesteps.
         while self.time_accumulator >= self.simulation_state.get_timestep() {
+            self.dynamic_instances[DYNAMIC_INSTANCE_INDEX_BALL as usize].position =
+                self.simulation_state.get_position();
+            self.interpolator.record(&self.dynamic_instances);
             // Note that our elapsed simulation time might be less than SIMULATION_DT if a collision occured.
             // That's OK, just continue simulating the next time step from the collision next iteration.
             let elapsed_sim_time = self.simulation_state.step();
             self.time_accumulator = self.time_accumulator - elapsed_sim_time;
         }
 
-        // TODO we may want to add the last step of https://gafferongames.com/post/fix_your_timestep/
-        //   to interpolate the state if the basic accumulator implementation is jumpy.
+        #[cfg(feature = "network")]
+        if let Some(Twin::Host(host)) = &mut self.twin {
+            host.send(&self.simulation_state);
+        }
+
+        if *self.simulation_state.get_container() != self.container {
+            self.container = *self.simulation_state.get_container();
+            let (vertex_positions, indices) = self.container.get_mesh();
+            self.bounding_box_mesh = ColoredMesh::from_collidable_mesh(
+                &self.gpu.device,
+                "container".to_string(),
+                &CollidableMesh::new(vertex_positions, indices),
+                self.bounding_box_mesh.color,
+            );
+        }
 
-        // Update the sphere position for DISPLAY from the simulation state.
+        // Update the sphere position for DISPLAY from the simulation state, blended from the step before.
         self.dynamic_instances[DYNAMIC_INSTANCE_INDEX_BALL as usize].position =
             self.simulation_state.get_position();
+        self.interpolator.blend(
+            &mut self.dynamic_instances,
+            interpolation::get_alpha(self.time_accumulator, self.simulation_state.get_timestep()),
+        );
         let new_ball_instance_data =
             self.dynamic_instances[DYNAMIC_INSTANCE_INDEX_BALL as usize].to_raw();
 
@@ -294,66 +328,228 @@ impl State {
         // begin_render_pass borrows encoder mutably, so we start a new block
         // so that we drop render_pass, so that we can use encoder later.
         {
-            let mut render_pass =
-                utils::begin_default_render_pass(&mut encoder, &view, &self.depth_texture.view);
-
-            render_pass.set_vertex_buffer(1, self.static_instance_buffer.slice(..));
-            render_pass.set_pipeline(&self.light_render_pipeline);
-            render_pass.draw_light_model_instanced(
-                &self.obj_model,
-                STATIC_INSTANCE_INDEX_LIGHT..STATIC_INSTANCE_INDEX_LIGHT + 1,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
             );
 
-            render_pass.set_pipeline(&self.colored_render_pipeline);
-            render_pass.draw_colored_mesh_instanced(
-                &self.bounding_box_mesh,
-                STATIC_INSTANCE_INDEX_BOUNDING_BOX..STATIC_INSTANCE_INDEX_BOUNDING_BOX + 1,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_vertex_buffer(1, self.static_instance_buffer.slice(..));
+                render_pass.set_pipeline(&self.light_render_pipeline);
+                render_pass.draw_light_model_instanced(
+                    &self.obj_model,
+                    STATIC_INSTANCE_INDEX_LIGHT..STATIC_INSTANCE_INDEX_LIGHT + 1,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
 
-            render_pass.set_vertex_buffer(1, self.dynamic_instance_buffer.slice(..));
-            render_pass.draw_colored_mesh_instanced(
-                &self.sphere_mesh,
-                DYNAMIC_INSTANCE_INDEX_BALL..DYNAMIC_INSTANCE_INDEX_BALL + 1,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
+                render_pass.set_pipeline(&self.colored_render_pipeline);
+                render_pass.draw_colored_mesh_instanced(
+                    &self.bounding_box_mesh,
+                    STATIC_INSTANCE_INDEX_BOUNDING_BOX..STATIC_INSTANCE_INDEX_BOUNDING_BOX + 1,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+
+                render_pass.set_vertex_buffer(1, self.dynamic_instance_buffer.slice(..));
+                render_pass.draw_colored_mesh_instanced(
+                    &self.sphere_mesh,
+                    DYNAMIC_INSTANCE_INDEX_BALL..DYNAMIC_INSTANCE_INDEX_BALL + 1,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
         }
 
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
         // Finish up the command buffer in finish(), and submit to the gpu's queue!
         encoder.finish()
     }
 }
 
-pub fn run() {
+/// Creates the pipelines for textured models, the light, and colored meshes, respectively.
+fn create_render_pipelines(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+    texture_bind_group_layout: &BindGroupLayout,
+    light_bind_group_layout: &BindGroupLayout,
+) -> (RenderPipeline, RenderPipeline, RenderPipeline) {
+    let render_pipeline_layout =
+        gpu.device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    texture_bind_group_layout,
+                    &camera_bundle.camera_bind_group_layout,
+                    light_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+    // Render pipeline for textured models
+    let render_pipeline = {
+        let shader = wgpu::ShaderModuleDescriptor {
+            label: Some("Normal Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shader.wgsl").into()),
+        };
+        graphics::util::create_render_pipeline(
+            &gpu.device,
+            &render_pipeline_layout,
+            gpu.config.format,
+            Some(texture::Texture::DEPTH_FORMAT),
+            gpu.settings.msaa_samples,
+            &[ModelVertex::desc(), InstanceRaw::desc::<5>()],
+            shader,
+        )
+    };
+
+    // Render pipeline for our physical light object in the scene.
+    let light_render_pipeline = {
+        let layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Light Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bundle.camera_bind_group_layout,
+                    light_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let shader = wgpu::ShaderModuleDescriptor {
+            label: Some("Light Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/light.wgsl").into()),
+        };
+        graphics::util::create_render_pipeline(
+            &gpu.device,
+            &layout,
+            gpu.config.format,
+            Some(texture::Texture::DEPTH_FORMAT),
+            gpu.settings.msaa_samples,
+            &[ModelVertex::desc()],
+            shader,
+        )
+    };
+
+    // Render pipeline for colored meshes without any textures.
+    let colored_render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+        gpu,
+        camera_bundle,
+        light_bind_group_layout,
+    );
+
+    (
+        render_pipeline,
+        light_render_pipeline,
+        colored_render_pipeline,
+    )
+}
+
+fn help() -> Help {
+    Help {
+        title: "Bouncing Ball",
+        description: "A sphere falling under gravity, drag, and wind, bouncing off of the inside of a container whose walls can be moved.",
+        parameters: &[
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Gravity",
+            description: "The downward acceleration of the sphere.",
+        },
+        Parameter {
+            name: "Drag",
+            description: "Air resistance, slowing the sphere in proportion to its velocity relative to the wind.",
+        },
+        Parameter {
+            name: "Wind",
+            description: "The velocity of the air the sphere moves through.",
+        },
+        Parameter {
+            name: "Coefficient of Restitution",
+            description: "The fraction of the normal velocity kept after bouncing.",
+        },
+        Parameter {
+            name: "Coefficient of Friction",
+            description: "How much tangential velocity is lost when bouncing.",
+        },
+        Parameter {
+            name: "Static Coefficient of Friction",
+            description: "How strongly a wall holds the sphere at rest against the other forces on it.",
+        },
+        Parameter {
+            name: "Container",
+            description: "The shape and size of the container. Its walls move toward the size set at a limited speed, pushing the sphere along if they reach it.",
+        },
+        Parameter {
+            name: "Impulse Spectrogram",
+            description: "Plots the impulses of the sphere's bounces, and their spectrum over time, which rises as the bounces come faster while the sphere comes to rest.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS.to_vec(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
+    run_with(window_args, |_| {});
+}
+
+/// Runs the demo hosting its simulation for, or viewing its simulation from, the twin.
+#[cfg(feature = "network")]
+pub fn run_twin(window_args: &WindowArgs, twin: Twin) {
+    run_with(window_args, |state| state.twin = Some(twin));
+}
+
+/// Runs the demo, setting up its state before the first frame.
+fn run_with(window_args: &WindowArgs, setup: impl FnOnce(&mut State)) {
     env_logger::init();
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = utils::build_window(&event_loop, window_args);
 
     // Our game loop follows the famous "fix your timestep!" model:
     // https://gafferongames.com/post/fix_your_timestep/
-    // The state holds the accumulator.
+    // The state holds the accumulator, and the interpolator which blends the ball between steps.
     let mut state = State::new(&window);
-
-    let mut gui = gui::Gui::new(&state.gpu.device, &state.gpu.config, &window);
+    setup(&mut state);
+
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
     let mut bouncing_ball_ui = gui::bounce::BouncingBallUi::new();
 
     let mut current_time = std::time::SystemTime::now();
     event_loop.run(move |event, _, control_flow| {
         gui.handle_events(&event);
 
-        *control_flow = ControlFlow::Poll;
+        *control_flow = utils::control_flow(&state.gpu);
         match event {
             Event::MainEventsCleared => {
                 let new_time = std::time::SystemTime::now();
                 let frame_time = new_time.duration_since(current_time).unwrap();
                 current_time = new_time;
-                state.update(frame_time);
-                state.simulation_state.sync_state_from_ui(&mut bouncing_ball_ui);
-                let output = state.gpu.surface.get_current_texture().unwrap();
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                bouncing_ball_ui.sync_simulation(&mut state.simulation_state);
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
                 let simulation_render_command_buffer = state.render(&output);
                 let gui_render_command_buffer = gui.render(
                     &mut bouncing_ball_ui,
@@ -365,8 +561,21 @@ pub fn run() {
                     &output
                 );
 
-                state.gpu.queue.submit([simulation_render_command_buffer, gui_render_command_buffer]);
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
                 output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
             }
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
@@ -0,0 +1,471 @@
+/// A demo of a rope bridge, planks tied together by strands of rope over a gap, which loads can be dropped onto.
+use crate::simulation::configurable::Configurable;
+use crate::{
+    args::WindowArgs,
+    graphics::{
+        self,
+        camera::CameraBundle,
+        debug_draw::DebugDraw,
+        entity::ColoredMeshEntity,
+        forms,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light,
+        palette::Theme,
+        scene::{ColoredMeshEntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, InputAction, Parameter},
+    },
+    simulation::bridge::{self, Simulation},
+};
+
+use winit::{
+    dpi::PhysicalPosition,
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+use super::utils;
+
+/// Enough for each segment of each strand.
+const MAX_DEBUG_LINES: usize = 128;
+
+struct State {
+    simulation: Simulation,
+    gpu: GPUInterface,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+    camera_bundle: CameraBundle,
+    light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    scene: Scene,
+    theme: Theme,
+    obstacle_entity: ColoredMeshEntityHandle,
+    plank_entity: ColoredMeshEntityHandle,
+    load_entity: ColoredMeshEntityHandle,
+    debug_draw: DebugDraw,
+    mouse_pressed: bool,
+    cursor_position: PhysicalPosition<f64>,
+    time_accumulator: std::time::Duration,
+}
+
+impl State {
+    fn new(window: &Window) -> Self {
+        let gpu: GPUInterface = GPUInterface::new(window);
+        let camera_bundle = CameraBundle::new(
+            &gpu,
+            (0.0, -0.5, 8.0),
+            cgmath::Deg(-90.0),
+            cgmath::Deg(-10.0),
+        );
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
+
+        let light_uniform = light::LightUniform::new([2.0, 4.0, 4.0], [1.0, 1.0, 1.0]);
+        let (light_bind_group_layout, light_bind_group) =
+            light::create_light_bind_group(&gpu, light_uniform);
+
+        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+            &gpu,
+            &camera_bundle,
+            &light_bind_group_layout,
+        );
+
+        let theme = Theme::default();
+        let simulation = Simulation::new();
+
+        let mut scene = Scene::new();
+        let obstacles = bridge::get_obstacle_boxes()
+            .into_iter()
+            .map(|(center, dimensions)| Instance {
+                position: center,
+                scale: dimensions,
+                ..Default::default()
+            })
+            .collect();
+        let obstacle_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::get_cube(&gpu.device, theme.obstacle),
+            obstacles,
+            None,
+        ));
+        let plank_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::get_cube(&gpu.device, theme.body),
+            Vec::new(),
+            Some(bridge::PLANK_COUNT),
+        ));
+        let load_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::get_cube(&gpu.device, theme.body),
+            Vec::new(),
+            Some(bridge::MAX_LOADS),
+        ));
+        let debug_draw = DebugDraw::new(&gpu, &camera_bundle, MAX_DEBUG_LINES);
+
+        Self {
+            simulation,
+            gpu,
+            render_pipeline,
+            depth_texture,
+            camera_bundle,
+            light_bind_group,
+            light_bind_group_layout,
+            scene,
+            theme,
+            obstacle_entity,
+            plank_entity,
+            load_entity,
+            debug_draw,
+            mouse_pressed: false,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            time_accumulator: std::time::Duration::from_millis(0),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        graphics::util::resize(
+            new_size,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle.projection,
+        );
+    }
+
+    /// The planks and loads are colored by their instances, so they're recolored as they're next drawn.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.obstacle_entity, theme.obstacle);
+        self.theme = theme;
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.debug_draw
+                .rebuild_pipeline(&self.gpu, &self.camera_bundle);
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state: ElementState::Pressed,
+                ..
+            } => {
+                if let Some(x) = self.get_cursor_over_gap() {
+                    self.simulation
+                        .drop_load(x, 0.0)
+                        .expect("Loads have volume and mass");
+                }
+                true
+            }
+            _ => {
+                utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+            }
+        }
+    }
+
+    /// Where along the gap the ray through the cursor crosses the bridge's plane, if it does over the gap.
+    fn get_cursor_over_gap(&self) -> Option<f32> {
+        let (origin, direction) = self.camera_bundle.camera.cursor_ray(
+            &self.camera_bundle.projection,
+            self.cursor_position,
+            self.gpu.config.width,
+            self.gpu.config.height,
+        );
+        if direction.z.abs() < f32::EPSILON {
+            return None;
+        }
+        let distance = -origin.z / direction.z;
+        let x = origin.x + direction.x * distance;
+        let reach = bridge::GAP_HALF_WIDTH - bridge::LOAD_DIMENSIONS.x / 2.0;
+        (distance > 0.0 && x.abs() < reach).then_some(x)
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration) {
+        self.time_accumulator += frame_time;
+        self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
+
+        while self.time_accumulator >= self.simulation.get_timestep() {
+            let elapsed_sim_time = self.simulation.step();
+            self.time_accumulator -= elapsed_sim_time;
+        }
+
+        self.update_instances();
+        // Strands are drawn from slack to about to snap.
+        let break_tension = self.simulation.get_config().break_tension;
+        let rigidbodies = self.simulation.get_rigidbodies();
+        for strand in self.simulation.get_strands() {
+            let points = strand.get_points(rigidbodies);
+            for (segment, tension) in points.windows(2).zip(strand.get_tensions()) {
+                let color = self.theme.colormap.sample(tension / break_tension);
+                self.debug_draw.line(segment[0], segment[1], color);
+            }
+        }
+    }
+
+    fn update_instances(&mut self) {
+        let rigidbodies = self.simulation.get_rigidbodies();
+        let planks = self
+            .simulation
+            .get_planks()
+            .iter()
+            .map(|plank| {
+                let rigidbody = &rigidbodies[*plank];
+                Instance {
+                    position: rigidbody.get_position(),
+                    rotation: rigidbody.get_rotation(),
+                    scale: rigidbody.get_dimensions(),
+                    color: Some(
+                        self.theme
+                            .colormap
+                            .sample(self.simulation.get_stress(*plank)),
+                    ),
+                }
+            })
+            .collect();
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.plank_entity, planks);
+
+        let loads = self
+            .simulation
+            .get_loads()
+            .iter()
+            .map(|load| {
+                let rigidbody = &rigidbodies[*load];
+                Instance {
+                    position: rigidbody.get_position(),
+                    rotation: rigidbody.get_rotation(),
+                    scale: rigidbody.get_dimensions(),
+                    color: Some(self.theme.body),
+                }
+            })
+            .collect();
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.load_entity, loads);
+    }
+
+    fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // We'll use a CommandEncoder to create the commands to send to the GPU.
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.debug_draw.prepare(&self.gpu);
+
+        {
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
+            );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                self.debug_draw
+                    .draw(&mut render_pass, &camera_view.camera_bind_group);
+            }
+        }
+
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
+        encoder.finish()
+    }
+}
+
+fn help() -> Help {
+    Help {
+        title: "Rope Bridge",
+        description: "Planks hung over a gap by strands of rope, tied to each other and to posts on the ledges. Loads dropped onto the bridge press on the planks, and the strands carry their weight to the posts. Strands and planks are colored by their tension, from slack to about to snap.",
+        parameters: &[
+        Parameter {
+            name: "Strand Stiffness",
+            description: "The tension which would stretch a strand to twice its length. Stiffer strands need a smaller dt to stay stable.",
+        },
+        Parameter {
+            name: "Strand Damping",
+            description: "How strongly strands resist stretching quickly, which damps the bridge's bouncing.",
+        },
+        Parameter {
+            name: "Strand Slack",
+            description: "How much longer the strands are than the gaps they span, which sets how low the bridge hangs. Changing it rebuilds the bridge.",
+        },
+        Parameter {
+            name: "Break Tension",
+            description: "The tension strands snap at, if they're breakable. Planks colored brightest are tied by strands near it.",
+        },
+        Parameter {
+            name: "Load Mass",
+            description: "The mass of each dropped load. Heavy enough loads snap the strands and fall through the bridge.",
+        },
+        Parameter {
+            name: "Position Correction",
+            description: "The fraction of each overlap between boxes corrected each step by moving them apart directly. Lower values let stacks sag into each other, and higher ones jitter.",
+        },
+        Parameter {
+            name: "Solver Iterations",
+            description: "The most iterations the contact solver runs each step. It stops sooner once an iteration changes no contact's impulse by more than the solver tolerance. More iterations let stacks stand more stiffly, but take longer.",
+        },
+        Parameter {
+            name: "Time Budget",
+            description: "Stops the contact solver once it's run for the budget each step, however many iterations it's run, trading accuracy for a steady step time.",
+        },
+        Parameter {
+            name: "Solver Convergence",
+            description: "Plots the residual of each of the contact solver's iterations over the last step, the largest change it made to any contact's impulse, and why the solver stopped.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS
+            .into_iter()
+            .chain([InputAction {
+                input: "Right Mouse Click",
+                description: "Drop a load onto the bridge below the cursor",
+            }])
+            .collect(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = utils::build_window(&event_loop, window_args);
+
+    let mut state = State::new(&window);
+
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
+    let mut ui = gui::bridge::BridgeUi::new();
+
+    let mut current_time = std::time::SystemTime::now();
+    event_loop.run(move |event, _, control_flow| {
+        gui.handle_events(&event);
+
+        *control_flow = utils::control_flow(&state.gpu);
+        match event {
+            Event::MainEventsCleared => {
+                let new_time = std::time::SystemTime::now();
+                let frame_time = new_time.duration_since(current_time).unwrap();
+                current_time = new_time;
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
+                let simulation_render_command_buffer = state.render(&output);
+                let gui_render_command_buffer = gui.render(
+                    &mut ui,
+                    frame_time,
+                    &state.gpu.device,
+                    &state.gpu.config,
+                    &state.gpu.queue,
+                    &window,
+                    &output
+                );
+
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
+                output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, },
+                .. // We're not using device_id currently
+            } if state.mouse_pressed => {
+                state.camera_bundle.camera_controller.process_mouse(delta.0, delta.1)
+            }
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+}
@@ -1,96 +1,185 @@
-/// A demo of the spring-mass-damper simulation.
+/// A demo of cloth, in a scenario chosen from the presets or opened from a scenario file.
+use std::path::Path;
+
+use crate::simulation::configurable::Configurable;
 use crate::{
+    args::WindowArgs,
     graphics::{
-        self, camera::CameraBundle, entity::ColoredMeshEntity, gpu_interface::GPUInterface,
-        instance::Instance, light, model::ColoredMesh, scene::Scene, texture,
+        self,
+        camera::CameraBundle,
+        debug_draw::DebugDraw,
+        entity::{ColoredMeshEntity, Entity},
+        forms,
+        gizmo::{Gizmo, GizmoEdit, GizmoMode},
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light,
+        model::{ColoredMesh, Material, Mesh, Model},
+        normals::{GpuNormals, NormalsPipeline},
+        palette::{Color, Theme},
+        scene::{ColoredMeshEntityHandle, EntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, InputAction, Parameter},
     },
-    gui,
-    simulation::collidable_mesh::CollidableMesh,
-    simulation::springy::cloth::Cloth,
-    simulation::springy::simulation::Simulation,
+    localization::tr,
+    simulation::springy::scenario::{self, Preset, Scenario},
+    simulation::springy::springy_mesh::SpringyMesh,
 };
 
-use cgmath::{Vector3, Zero};
+use cgmath::Vector3;
 use winit::{
+    dpi::PhysicalPosition,
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
 };
 
 use super::utils;
 
+const MAX_DEBUG_LINES: usize = 128;
+/// The gizmo's handles are about the size of the object they're placed on, within these lengths.
+const GIZMO_MIN_SIZE: f32 = 0.3;
+const GIZMO_MAX_SIZE: f32 = 1.0;
+
 struct State {
-    simulation: Simulation,
+    cloth_scene: scenario::Scene,
     gpu: GPUInterface,
-    render_pipeline: wgpu::RenderPipeline,
+    model_render_pipeline: wgpu::RenderPipeline,
+    colored_mesh_render_pipeline: wgpu::RenderPipeline,
     depth_texture: texture::Texture,
     camera_bundle: CameraBundle,
     light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
     scene: Scene,
+    theme: Theme,
+    cloth_entity: EntityHandle,
+    obstacle_entities: Vec<ColoredMeshEntityHandle>,
+    body_entity: ColoredMeshEntityHandle,
+    rain_entity: ColoredMeshEntityHandle,
+    fluid_surface_entity: ColoredMeshEntityHandle,
+    fan_entity: ColoredMeshEntityHandle,
+    debug_draw: DebugDraw,
+    gizmo: Gizmo,
     mouse_pressed: bool,
+    cursor_position: PhysicalPosition<f64>,
     time_accumulator: std::time::Duration,
+    normals_pipeline: NormalsPipeline,
+    cloth_normals: GpuNormals,
 }
 
 impl State {
-    fn new(window: &Window) -> Self {
+    fn new(window: &Window, cloth_scene: scenario::Scene) -> Self {
         let gpu: GPUInterface = GPUInterface::new(&window);
         let camera_bundle =
             CameraBundle::new(&gpu, (0.0, 0.0, 5.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
-        let depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth texture");
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
 
         let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
         let (light_bind_group_layout, light_bind_group) =
             light::create_light_bind_group(&gpu, light_uniform);
 
-        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+        let model_render_pipeline = graphics::util::create_model_render_pipeline(
             &gpu,
             &camera_bundle,
             &light_bind_group_layout,
         );
-
-        let rows = 20 as usize;
-        let cols = 20 as usize;
-        let cloth = Cloth::new(
-            rows,
-            cols,
-            0.1,
-            Vector3::<f32>::zero(),
-            10.0,
-            2000.0,
-            200.0,
-            500.0,
-            20.0,
-            5.0,
-            2.0,
-            vec![
-                rows * cols - 1,
-                (rows * cols) - cols,
-                (rows * cols) - (cols / 2),
-            ],
+        let colored_mesh_render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+            &gpu,
+            &camera_bundle,
+            &light_bind_group_layout,
         );
-        let cloth_mesh = cloth.mesh;
-        let obstacles = get_obstacles();
-        let simulation = Simulation::new(vec![cloth_mesh], obstacles);
+        let texture_bind_group_layout = graphics::util::create_texture_bind_group_layout(&gpu);
+        // The cloth deforms every frame, so its normals are recomputed on the GPU.
+        let normals_pipeline = NormalsPipeline::new(&gpu);
 
-        // Note we're keeping the scene around since we'll probably have some static obstacles that we'd like to draw
-        // for the springy mesh to interact with.
-        let scene = Scene::new(None, None, None);
+        let theme = Theme::default();
+        let mut scene = Scene::new();
+        let (cloth_entity, cloth_normals) = create_cloth_entity(
+            &gpu,
+            &texture_bind_group_layout,
+            &normals_pipeline,
+            &cloth_scene.get_cloth().get_meshes()[0],
+        );
+        let cloth_entity = scene.add_entity(cloth_entity);
+        let obstacle_entities = create_obstacle_entities(&gpu, &cloth_scene, theme.obstacle)
+            .into_iter()
+            .map(|entity| scene.add_colored_mesh_entity(entity))
+            .collect();
+        let body_entity =
+            scene.add_colored_mesh_entity(create_body_entity(&gpu, &cloth_scene, theme.body));
+        let rain_entity = scene.add_colored_mesh_entity(utils::create_rain_entity(&gpu));
+        let fluid_surface_entity =
+            scene.add_colored_mesh_entity(utils::create_fluid_surface_entity(&gpu));
+        let fan_entity = scene.add_colored_mesh_entity(utils::create_fan_entity(&gpu));
+        let debug_draw = DebugDraw::new(&gpu, &camera_bundle, MAX_DEBUG_LINES);
 
         Self {
-            simulation,
+            cloth_scene,
             gpu,
-            render_pipeline,
+            model_render_pipeline,
+            colored_mesh_render_pipeline,
             depth_texture,
             camera_bundle,
             light_bind_group,
+            light_bind_group_layout,
+            texture_bind_group_layout,
             scene,
+            theme,
+            cloth_entity,
+            obstacle_entities,
+            body_entity,
+            rain_entity,
+            fluid_surface_entity,
+            fan_entity,
+            debug_draw,
+            gizmo: Gizmo::new(GizmoMode::Translate),
             mouse_pressed: false,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
             time_accumulator: std::time::Duration::from_millis(0),
+            normals_pipeline,
+            cloth_normals,
         }
     }
 
+    /// Replaces the cloth, obstacle, and body entities with ones for the rebuilt scene.
+    fn rebuild(&mut self) {
+        self.scene.remove_entity(self.cloth_entity);
+        let (cloth_entity, cloth_normals) = create_cloth_entity(
+            &self.gpu,
+            &self.texture_bind_group_layout,
+            &self.normals_pipeline,
+            &self.cloth_scene.get_cloth().get_meshes()[0],
+        );
+        self.cloth_entity = self.scene.add_entity(cloth_entity);
+        self.cloth_normals = cloth_normals;
+
+        for handle in self.obstacle_entities.drain(..) {
+            self.scene.remove_colored_mesh_entity(handle);
+        }
+        self.obstacle_entities =
+            create_obstacle_entities(&self.gpu, &self.cloth_scene, self.theme.obstacle)
+                .into_iter()
+                .map(|entity| self.scene.add_colored_mesh_entity(entity))
+                .collect();
+        self.scene.remove_colored_mesh_entity(self.body_entity);
+        self.body_entity = self.scene.add_colored_mesh_entity(create_body_entity(
+            &self.gpu,
+            &self.cloth_scene,
+            self.theme.body,
+        ));
+        self.time_accumulator = std::time::Duration::from_millis(0);
+    }
+
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         graphics::util::resize(
             new_size,
@@ -100,18 +189,166 @@ impl State {
         );
     }
 
-    fn input(&mut self, event: &WindowEvent) -> bool {
-        utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+    /// Recolors the meshes with the theme.
+    fn apply_theme(&mut self, theme: Theme) {
+        for handle in self.obstacle_entities.iter() {
+            self.scene
+                .set_colored_mesh_entity_color(&self.gpu, *handle, theme.obstacle);
+        }
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.body_entity, theme.body);
+        self.theme = theme;
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.model_render_pipeline = graphics::util::create_model_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.colored_mesh_render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.debug_draw
+                .rebuild_pipeline(&self.gpu, &self.camera_bundle);
+        }
+    }
+
+    /// Right clicking selects the object under the cursor, or grabs a handle of the selected object's gizmo,
+    /// which arranges the object in the scenario as it's dragged. The scene's rebuilt once it's let go.
+    fn input(&mut self, event: &WindowEvent, ui: &mut gui::cloth::ClothUi) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                if let Some(selected) = ui.get_selected().filter(|_| self.gizmo.is_dragging()) {
+                    let (origin, direction) = self.get_cursor_ray();
+                    let scenario = ui.get_scenario_mut();
+                    match self.gizmo.update_drag(origin, direction) {
+                        Some(GizmoEdit::Translate(translation)) => {
+                            scenario.translate(selected, translation)
+                        }
+                        Some(GizmoEdit::Rotate(axis, angle)) => {
+                            scenario.rotate(selected, axis, angle)
+                        }
+                        Some(GizmoEdit::Scale(factor)) => scenario.scale(selected, factor),
+                        None => {}
+                    }
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state,
+                ..
+            } => {
+                if *state == ElementState::Pressed {
+                    let (origin, direction) = self.get_cursor_ray();
+                    if !self.gizmo.begin_drag(origin, direction) {
+                        ui.select(ui.get_scenario().pick(origin, direction));
+                    }
+                } else if self.gizmo.is_dragging() {
+                    self.gizmo.end_drag();
+                    ui.request_rebuild();
+                }
+                // The whole drag is undone at once.
+                ui.set_arranging(self.gizmo.is_dragging());
+                true
+            }
+            _ => {
+                utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+            }
+        }
+    }
+
+    fn get_cursor_ray(&self) -> (Vector3<f32>, Vector3<f32>) {
+        self.camera_bundle.camera.cursor_ray(
+            &self.camera_bundle.projection,
+            self.cursor_position,
+            self.gpu.config.width,
+            self.gpu.config.height,
+        )
     }
 
-    fn update(&mut self, frame_time: std::time::Duration) {
+    /// Places the gizmo on the selected object as it is in the UI's scenario, with handles along the axes it
+    /// may be arranged along in the UI's gizmo mode, and draws it.
+    fn draw_gizmo(&mut self, ui: &gui::cloth::ClothUi) {
+        if !self.gizmo.is_dragging() {
+            self.gizmo.mode = ui.get_gizmo_mode();
+        }
+        let Some(selected) = ui.get_selected() else {
+            self.gizmo
+                .place(Vector3::new(0.0, 0.0, 0.0), 1.0, [false; 3]);
+            return;
+        };
+        let placement = ui.get_scenario().get_placement(selected);
+        let axes = match self.gizmo.mode {
+            GizmoMode::Translate => placement.translation_axes,
+            GizmoMode::Rotate => placement.rotation_axes,
+            GizmoMode::Scale => [placement.scalable; 3],
+        };
+        self.gizmo.place(
+            placement.center,
+            placement.radius.clamp(GIZMO_MIN_SIZE, GIZMO_MAX_SIZE),
+            axes,
+        );
+        self.gizmo.draw(&mut self.debug_draw);
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration, ui: &gui::cloth::ClothUi) {
         self.time_accumulator = self.time_accumulator + frame_time;
         self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
 
-        while self.time_accumulator >= self.simulation.get_timestep() {
-            let elapsed_sim_time = self.simulation.step();
+        while self.time_accumulator >= self.cloth_scene.get_timestep() {
+            let elapsed_sim_time = self.cloth_scene.step();
             self.time_accumulator = self.time_accumulator - elapsed_sim_time;
         }
+
+        let cloth = self.cloth_scene.get_cloth();
+        self.scene
+            .update_entity_springy_mesh(&self.gpu, self.cloth_entity, &cloth.get_meshes()[0]);
+        let body_instances = self
+            .cloth_scene
+            .get_body()
+            .map(|body| Instance {
+                position: body.get_position(),
+                rotation: body.get_rotation(),
+                scale: body.get_dimensions(),
+                ..Default::default()
+            })
+            .into_iter()
+            .collect();
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.body_entity,
+            body_instances,
+        );
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.rain_entity,
+            utils::get_rain_instances(cloth.get_rain()),
+        );
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.fluid_surface_entity,
+            utils::get_fluid_surface_instances(cloth.get_config()),
+        );
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.fan_entity,
+            utils::get_fan_instances(cloth.get_fan()),
+        );
+        self.draw_gizmo(ui);
     }
 
     fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
@@ -127,76 +364,211 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        // TODO handle rendering *all* springy meshes in simulation
-        let cube_mesh = ColoredMesh::from_springy_mesh(
-            &self.gpu.device,
-            "springy cube".to_string(),
-            &self.simulation.get_meshes()[0],
-            [0.9, 0.1, 0.1],
-        );
-        let cube_instances = vec![Instance::default()];
-        let cube_entity = ColoredMeshEntity::new(&self.gpu, cube_mesh, cube_instances, None);
-
-        // TODO handle rendering *all* obstacles in simulation
-        let obstacle_mesh = ColoredMesh::from_collidable_mesh(
-            &self.gpu.device,
-            "floor".to_string(),
-            &self.simulation.get_obstacles()[0],
-            [0.1, 0.9, 0.1],
-        );
-        let obstacle_instances = vec![Instance::default()];
-        let obstacle_entity =
-            ColoredMeshEntity::new(&self.gpu, obstacle_mesh, obstacle_instances, None);
+        self.cloth_normals
+            .dispatch(&mut encoder, &self.normals_pipeline);
+        self.debug_draw.prepare(&self.gpu);
 
         {
-            let mut render_pass =
-                utils::begin_default_render_pass(&mut encoder, &view, &self.depth_texture.view);
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            self.scene.draw_colored_mesh_entities(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            cube_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            obstacle_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
             );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.model_render_pipeline);
+                self.scene.draw_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                render_pass.set_pipeline(&self.colored_mesh_render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                self.debug_draw
+                    .draw(&mut render_pass, &camera_view.camera_bind_group);
+            }
         }
 
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
         encoder.finish()
     }
 }
 
-pub fn run() {
+fn help() -> Help {
+    Help {
+        title: "Cloth",
+        description: "A cloth of springy triangles in a scenario, pinned along its edges, draped over obstacles or catching a dropped box or ball.",
+        parameters: &[
+        Parameter {
+            name: "Preset",
+            description: "Loads a scenario: a hanging cloth, a tablecloth draped over a cube, a flag, a curtain, or a trampoline bouncing a ball.",
+        },
+        Parameter {
+            name: "Rebuild",
+            description: "Rebuilds the scene from the scenario's values. Scenarios are also opened from files with --scenario.",
+        },
+        Parameter {
+            name: "Gizmo",
+            description: "The object whose gizmo is shown, which moves, rotates, or scales it as its handles are dragged. The scene is rebuilt once a handle is let go.",
+        },
+        Parameter {
+            name: "Save Scenario",
+            description: "Saves the scenario back to the file it was opened from, or to cloth_scenario.txt if it's a preset.",
+        },
+        Parameter {
+            name: "Undo",
+            description: "Undoes the last edit of the scenario or the config, e.g. a drag of a gizmo or a slider, which Redo reapplies.",
+        },
+        Parameter {
+            name: "Integration",
+            description: "The numerical integration method; RK4 is more accurate and stable than Euler, but slower. Implicit Euler stays stable with stiff struts at much larger timesteps, at the cost of damping their motion. Velocity Verlet and leapfrog neither gain nor lose energy over long runs. RK45 estimates each step's error, so that dt can adapt to it.",
+        },
+        Parameter {
+            name: "Adaptive dt",
+            description: "Adapts dt to RK45's error estimate after each step, within the min and max dt: shrinking it while the motion's hard to follow, and growing it while it's easy. A lower error tolerance takes smaller steps.",
+        },
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Gravity",
+            description: "The acceleration of each point mass.",
+        },
+        Parameter {
+            name: "Wind",
+            description: "The velocity of the air the cloth moves through.",
+        },
+        Parameter {
+            name: "Fan",
+            description: "Blows a jet of wind where it's aimed, which is stronger the faster its blades spin, and spreads and weakens away from the fan.",
+        },
+        Parameter {
+            name: "Drag",
+            description: "Air resistance on the faces of the cloth, opposing their velocity relative to the wind.",
+        },
+        Parameter {
+            name: "Lift",
+            description: "Force on the faces of the cloth perpendicular to their velocity relative to the wind.",
+        },
+        Parameter {
+            name: "Restitution",
+            description: "The fraction of the normal velocity kept after a point collides with an obstacle.",
+        },
+        Parameter {
+            name: "Friction",
+            description: "How much tangential velocity is lost when a point collides with an obstacle.",
+        },
+        Parameter {
+            name: "Floor Conveyor",
+            description: "The velocity the floor's surface slides at, like a conveyor belt's, which friction drags the points resting on it towards.",
+        },
+        Parameter {
+            name: "Rain",
+            description: "Drops of rain falling per second. Drops push the faces they hit, then splash.",
+        },
+        Parameter {
+            name: "Drop Mass",
+            description: "The mass of each drop; heavier drops push harder on the faces they hit.",
+        },
+        Parameter {
+            name: "Fluid",
+            description: "Submerges the mesh below a surface in a still fluid, which buoys its faces and resists their motion.",
+        },
+        Parameter {
+            name: "Fluid Density",
+            description: "The density of the fluid; lift and drag in the fluid are scaled from the air's by the ratio of their densities.",
+        },
+        Parameter {
+            name: "Face Thickness",
+            description: "The thickness of the mesh's faces, which determines how much fluid they displace.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS
+            .into_iter()
+            .chain([
+                InputAction {
+                    input: "Right Mouse Drag",
+                    description: "Select an object, or drag its gizmo's handles to arrange it",
+                },
+                InputAction {
+                    input: "Ctrl+Z / Ctrl+Y",
+                    description: "Undo or redo the last edit",
+                },
+            ])
+            .collect(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs, path: Option<&Path>) {
     env_logger::init();
+    // Scenarios opened from files aren't any preset.
+    let (preset, scenario) = match path {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(contents) => (None, Ok(Scenario::from_key_values(&contents))),
+            Err(_) => (None, Err("Couldn't read the scenario!")),
+        },
+        None => (Some(Preset::Hanging), Ok(Preset::Hanging.get_scenario())),
+    };
+    let built = scenario.and_then(|scenario| {
+        scenario::Scene::new(scenario.clone()).map(|cloth_scene| (scenario, cloth_scene))
+    });
+    let (scenario, cloth_scene) = match built {
+        Ok(built) => built,
+        Err(message) => {
+            eprintln!("{}", tr(message));
+            std::process::exit(1);
+        }
+    };
+
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = utils::build_window(&event_loop, window_args);
 
-    let mut state = State::new(&window);
+    let mut state = State::new(&window, cloth_scene);
 
-    let mut gui = gui::Gui::new(&state.gpu.device, &state.gpu.config, &window);
-    let mut ui = gui::spring_mass_damper::SpringMassDamperUi::new();
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
+    let mut ui = gui::cloth::ClothUi::new(preset, scenario, path);
 
     let mut current_time = std::time::SystemTime::now();
     event_loop.run(move |event, _, control_flow| {
         gui.handle_events(&event);
 
-        *control_flow = ControlFlow::Poll;
+        *control_flow = utils::control_flow(&state.gpu);
         match event {
             Event::MainEventsCleared => {
                 let new_time = std::time::SystemTime::now();
                 let frame_time = new_time.duration_since(current_time).unwrap();
                 current_time = new_time;
-                state.update(frame_time);
-                state.simulation.sync_sim_config_from_ui(&mut ui);
-                let output = state.gpu.surface.get_current_texture().unwrap();
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time, &ui);
+                }
+                if ui.sync_scene(&mut state.cloth_scene) {
+                    state.rebuild();
+                }
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
                 let simulation_render_command_buffer = state.render(&output);
                 let gui_render_command_buffer = gui.render(
                     &mut ui,
@@ -208,8 +580,21 @@ pub fn run() {
                     &output
                 );
 
-                state.gpu.queue.submit([simulation_render_command_buffer, gui_render_command_buffer]);
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
                 output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
             }
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
@@ -220,7 +605,7 @@ pub fn run() {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == window.id() && !state.input(event) => {
+            } if window_id == window.id() && !state.input(event, &mut ui) => {
                 match event {
                     #[cfg(not(target_arch="wasm32"))]
                     WindowEvent::CloseRequested
@@ -247,13 +632,78 @@ pub fn run() {
     });
 }
 
-fn get_obstacles() -> Vec<CollidableMesh> {
-    let vertex_positions = vec![
-        -Vector3::<f32>::unit_x() + Vector3::<f32>::unit_z() - Vector3::<f32>::unit_y() * 2.0,
-        Vector3::<f32>::unit_x() + Vector3::<f32>::unit_z() - Vector3::<f32>::unit_y() * 2.0,
-        Vector3::<f32>::unit_x() - Vector3::<f32>::unit_z() - Vector3::<f32>::unit_y() * 2.0,
-        -Vector3::<f32>::unit_x() - Vector3::<f32>::unit_z() - Vector3::<f32>::unit_y() * 2.0,
-    ];
-    let indices = vec![0, 1, 2, 0, 2, 3];
-    vec![CollidableMesh::new(vertex_positions, indices)]
+/// Creates the checkered cloth entity for the mesh, and the GPU normals that keep its shading current.
+fn create_cloth_entity(
+    gpu: &GPUInterface,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    normals_pipeline: &NormalsPipeline,
+    springy_mesh: &SpringyMesh,
+) -> (Entity, GpuNormals) {
+    // The checkered texture makes the cloth's stretching and shearing visible.
+    let checkerboard = texture::Texture::checkerboard(
+        &gpu.device,
+        &gpu.queue,
+        8,
+        [[230, 230, 230], [180, 30, 30]],
+        "checkerboard",
+    )
+    .unwrap();
+    let material = Material::new(
+        &gpu.device,
+        "checkerboard".to_string(),
+        checkerboard,
+        texture_bind_group_layout,
+    );
+    let mut cloth_mesh = Mesh::from_springy_mesh(&gpu.device, "cloth".to_string(), springy_mesh, 0);
+    let (vertex_positions, vertex_indices) = springy_mesh.get_vertices();
+    let cloth_normals = GpuNormals::from_textured_mesh(
+        gpu,
+        normals_pipeline,
+        &mut cloth_mesh,
+        &vertex_indices.iter().map(|i| *i as u32).collect::<Vec<_>>(),
+        vertex_positions.len(),
+    );
+    let cloth_model = Model {
+        meshes: vec![cloth_mesh],
+        materials: vec![material],
+    };
+    (
+        Entity::new(gpu, cloth_model, vec![Instance::default()], None),
+        cloth_normals,
+    )
+}
+
+fn create_obstacle_entities(
+    gpu: &GPUInterface,
+    cloth_scene: &scenario::Scene,
+    color: Color,
+) -> Vec<ColoredMeshEntity> {
+    cloth_scene
+        .get_cloth()
+        .get_obstacles()
+        .iter()
+        .map(|obstacle| {
+            let mesh = ColoredMesh::from_collidable_mesh(
+                &gpu.device,
+                "obstacle".to_string(),
+                obstacle,
+                color,
+            );
+            ColoredMeshEntity::new(gpu, mesh, vec![Instance::default()], None)
+        })
+        .collect()
+}
+
+/// The dropped body's entity, a unit cube or ball which is scaled to the body's dimensions.
+fn create_body_entity(
+    gpu: &GPUInterface,
+    cloth_scene: &scenario::Scene,
+    color: Color,
+) -> ColoredMeshEntity {
+    let mesh = if cloth_scene.get_scenario().body_sphere {
+        forms::generate_sphere(&gpu.device, color, 0.5, 16, 16)
+    } else {
+        forms::get_cube(&gpu.device, color)
+    };
+    ColoredMeshEntity::new(gpu, mesh, vec![], Some(1))
 }
@@ -0,0 +1,38 @@
+/// Fits a demo's simulation parameters to a reference trajectory, without opening a window.
+use std::path::Path;
+
+use crate::{
+    args::Demos,
+    localization::{tr, tr_format},
+    simulation::{bounce, fitting::Trajectory},
+};
+
+use super::spring_mass_damper;
+
+/// Fits the demo to the trajectory in the CSV file, printing the fitted parameters and their error.
+/// Exits with an error if the file can't be read or parsed, or the demo can't be fit.
+pub fn run(demo: Demos, reference: &Path) {
+    let fitted = std::fs::read_to_string(reference)
+        .map_err(|_| "Couldn't read the reference trajectory!")
+        .and_then(|text| Trajectory::from_csv(&text))
+        .and_then(|trajectory| match demo {
+            Demos::BouncingBall => {
+                bounce::State::fit(&trajectory).map(|fit| (&bounce::FIT_PARAMETERS[..], fit))
+            }
+            Demos::SpringMassDamper => spring_mass_damper::fit(&trajectory)
+                .map(|fit| (&spring_mass_damper::FIT_PARAMETERS[..], fit)),
+            _ => Err("Only the bouncing ball and spring-mass-damper demos can be fit!"),
+        });
+    match fitted {
+        Ok((parameters, fit)) => {
+            for (parameter, value) in parameters.iter().zip(fit.values.iter()) {
+                println!("{}: {}", tr(parameter.name), value);
+            }
+            println!("{}", tr_format("RMS error: {} m", &[&fit.error]));
+        }
+        Err(message) => {
+            eprintln!("{}", tr(message));
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,25 +1,43 @@
 use crate::{
+    args::WindowArgs,
     graphics::{
-        self, camera::CameraBundle, entity::Entity, gpu_interface::GPUInterface,
-        instance::Instance, light, resources, scene::Scene, texture,
+        self,
+        camera::CameraBundle,
+        entity::{ColoredMeshEntity, Entity},
+        forms,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light, palette, resources,
+        scene::{ColoredMeshEntityHandle, EntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, InputAction, Parameter},
     },
-    gui,
     simulation::{
         self,
         flocking::{flocking, obstacle::Obstacle},
+        trigger::{Trigger, TriggerAction, TriggerShape},
     },
 };
 
 use cgmath::{Rotation3, Vector3, Zero};
 use winit::{
+    dpi::PhysicalPosition,
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
 };
 
 use super::utils;
 
+/// The most goals which may be queued at once, each drawn with a marker.
+const MAX_GOALS: usize = 32;
+/// The height above the seafloor goals are placed at, so that the fish swim to them rather than into the floor.
+const GOAL_HEIGHT: f32 = 1.0;
+
 struct State {
     gpu: GPUInterface,
     model_render_pipeline: wgpu::RenderPipeline,
@@ -27,11 +45,16 @@ struct State {
     depth_texture: texture::Texture,
     camera_bundle: CameraBundle,
     light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     // TODO use a vec of simulations instead of this.
     simulation: flocking::Simulation,
     simulation_2: flocking::Simulation,
     scene: Scene,
+    boids_entity: EntityHandle,
+    boids_entity_2: EntityHandle,
+    goals_entity: ColoredMeshEntityHandle,
     mouse_pressed: bool,
+    cursor_position: PhysicalPosition<f64>,
     time_accumulator: std::time::Duration,
     // TODO this is used for accumulating simulations for the second simulation.
     //   The time accumulator should likely be associated with a simulation.
@@ -44,8 +67,12 @@ impl State {
         let gpu: GPUInterface = GPUInterface::new(&window);
         let camera_bundle =
             CameraBundle::new(&gpu, (0.0, 1.0, 10.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
-        let depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth texture");
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
 
         let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
         let (light_bind_group_layout, light_bind_group) =
@@ -78,7 +105,8 @@ impl State {
                 cgmath::Vector3::unit_z(),
                 cgmath::Deg(0.0),
             ),
-            scale: 30.0,
+            scale: Vector3::new(30.0, 30.0, 30.0),
+            color: None,
         }];
         let seafloor_entity = Entity::new(&gpu, seafloor_tile_model, seafloor_tile_instances, None);
 
@@ -95,7 +123,8 @@ impl State {
                 cgmath::Vector3::unit_z(),
                 cgmath::Deg(0.0),
             ),
-            scale: 1.0,
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            color: None,
         }];
         let ship_entity = Entity::new(&gpu, ship_model, ship_instances, None);
         let obstacles = Obstacle::from_entity(&ship_entity, 4.0);
@@ -111,7 +140,7 @@ impl State {
 
         let num_boids = if cfg!(debug_assertions) { 30 } else { 110 };
 
-        let simulation = flocking::Simulation::new(
+        let mut simulation = flocking::Simulation::new(
             vec![initial_boids_position],
             num_boids,
             None,
@@ -119,6 +148,14 @@ impl State {
             Some(obstacles),
             None,
         );
+        // An upwelling current which lifts the school as it passes through the middle of its path.
+        simulation.add_trigger(Trigger::new(
+            TriggerShape::Sphere {
+                center: Vector3::<f32>::zero(),
+                radius: 5.0,
+            },
+            TriggerAction::Wind(Vector3::<f32>::unit_y() * 2.0),
+        ));
 
         // Add the first simulation info to the scene
         let fish_model = resources::load_model(
@@ -167,16 +204,15 @@ impl State {
 
         let boids_entity_2 = Entity::new(&gpu, fish_model_2, instances, None);
 
-        let scene = Scene::new(
-            Some(vec![
-                boids_entity,
-                boids_entity_2,
-                seafloor_entity,
-                ship_entity,
-            ]),
-            None,
-            None,
-        );
+        let goal_marker = forms::generate_sphere(&gpu.device, palette::CATEGORICAL[3], 0.3, 12, 12);
+        let goals_entity = ColoredMeshEntity::new(&gpu, goal_marker, Vec::new(), Some(MAX_GOALS));
+
+        let mut scene = Scene::new();
+        let boids_entity = scene.add_entity(boids_entity);
+        let boids_entity_2 = scene.add_entity(boids_entity_2);
+        scene.add_entity(seafloor_entity);
+        scene.add_entity(ship_entity);
+        let goals_entity = scene.add_colored_mesh_entity(goals_entity);
 
         Self {
             gpu,
@@ -185,10 +221,15 @@ impl State {
             depth_texture,
             camera_bundle,
             light_bind_group,
+            light_bind_group_layout,
             simulation,
             simulation_2,
             scene,
+            boids_entity,
+            boids_entity_2,
+            goals_entity,
             mouse_pressed: false,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
             time_accumulator: std::time::Duration::from_millis(0),
             time_accumulator_2: std::time::Duration::from_millis(0),
         }
@@ -203,14 +244,74 @@ impl State {
         );
     }
 
-    fn input(&mut self, event: &WindowEvent) -> bool {
-        utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.model_render_pipeline = graphics::util::create_model_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.colored_mesh_render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+        }
+    }
+
+    /// Handles the input, placing goals for the flocks where right-clicked on the seafloor in goal mode.
+    fn input(&mut self, event: &WindowEvent, goal_mode: bool) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state: ElementState::Pressed,
+                ..
+            } if goal_mode => {
+                if let Some(goal) = self.get_cursor_goal() {
+                    if self.simulation.get_goals().len() < MAX_GOALS {
+                        self.simulation.push_goal(goal);
+                        self.simulation_2.push_goal(goal);
+                    }
+                }
+                true
+            }
+            _ => {
+                utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+            }
+        }
+    }
+
+    /// The goal above where the ray through the cursor hits the seafloor's plane, if it does.
+    fn get_cursor_goal(&self) -> Option<Vector3<f32>> {
+        let (origin, direction) = self.camera_bundle.camera.cursor_ray(
+            &self.camera_bundle.projection,
+            self.cursor_position,
+            self.gpu.config.width,
+            self.gpu.config.height,
+        );
+        if direction.y >= 0.0 {
+            return None;
+        }
+        let distance = -origin.y / direction.y;
+        Some(origin + direction * distance + Vector3::unit_y() * GOAL_HEIGHT)
     }
 
     fn update(&mut self, frame_time: std::time::Duration) {
         self.time_accumulator = self.time_accumulator + frame_time;
         self.time_accumulator_2 = self.time_accumulator_2 + frame_time;
         self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
 
         while self.time_accumulator >= self.simulation.get_timestep() {
             let elapsed_sim_time = self.simulation.step();
@@ -224,11 +325,29 @@ impl State {
 
         let new_instances = self.simulation.get_boid_instances();
         self.scene
-            .update_entity_instances(&self.gpu, 0, new_instances);
+            .update_entity_instances(&self.gpu, self.boids_entity, new_instances);
 
         let new_instances = self.simulation_2.get_boid_instances();
         self.scene
-            .update_entity_instances(&self.gpu, 1, new_instances);
+            .update_entity_instances(&self.gpu, self.boids_entity_2, new_instances);
+
+        // Both flocks are given the same goals, but may reach them at different times, so each's are drawn.
+        let goal_instances = self
+            .simulation
+            .get_goals()
+            .iter()
+            .chain(self.simulation_2.get_goals().iter())
+            .take(MAX_GOALS)
+            .map(|goal| Instance {
+                position: *goal,
+                ..Default::default()
+            })
+            .collect();
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.goals_entity,
+            goal_instances,
+        );
     }
 
     fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
@@ -245,51 +364,158 @@ impl State {
             });
 
         {
-            let mut render_pass =
-                utils::begin_default_render_pass(&mut encoder, &view, &self.depth_texture.view);
-
-            render_pass.set_pipeline(&self.model_render_pipeline);
-            self.scene.draw_entities(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            render_pass.set_pipeline(&self.colored_mesh_render_pipeline);
-            self.scene.draw_colored_mesh_entities(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
             );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.model_render_pipeline);
+                self.scene.draw_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                render_pass.set_pipeline(&self.colored_mesh_render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
         }
 
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
         encoder.finish()
     }
 }
 
-pub fn run() {
+fn help() -> Help {
+    Help {
+        title: "Flocking",
+        description: "Boids flocking around ships, following a lead boid, and avoiding obstacles.",
+        parameters: &[
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Avoidance Factor",
+            description: "How strongly boids steer away from their neighbors.",
+        },
+        Parameter {
+            name: "Centering Factor",
+            description: "How strongly boids steer toward the center of their neighbors.",
+        },
+        Parameter {
+            name: "Velocity Matching Factor",
+            description: "How strongly boids match the velocities of their neighbors.",
+        },
+        Parameter {
+            name: "Distance Weight Threshold",
+            description: "The distance within which neighbors are weighted fully.",
+        },
+        Parameter {
+            name: "Distance Weight Threshold Falloff",
+            description: "The distance beyond the threshold over which the weight of neighbors falls to zero.",
+        },
+        Parameter {
+            name: "Max Sight Angle",
+            description: "How far from straight ahead a boid can see its neighbors.",
+        },
+        Parameter {
+            name: "Max Sight Angle to Lead Boid",
+            description: "How far from straight ahead a boid can see the lead boid and follow it.",
+        },
+        Parameter {
+            name: "Max Speed and Acceleration",
+            description: "The fastest boids fly, and the hardest they steer, however strong the factors pulling on them.",
+        },
+        Parameter {
+            name: "Stamina",
+            description: "Tires boids flying faster than the cruise fraction of the max speed, and rests those flying slower. Tired boids are limited to less of the max speed and acceleration, down to the exhausted fraction, so they lag behind the flock until they've rested.",
+        },
+        Parameter {
+            name: "2D Mode",
+            description: "Locks an axis, holding the boids in the plane across it at the offset, e.g. flocking level with the goals, seen in the top-down view.",
+        },
+        Parameter {
+            name: "Click to Set Goals",
+            description: "Right-clicking the seafloor queues a goal above it, which both flocks seek in turn once they've reached the goals before it.",
+        },
+        Parameter {
+            name: "Goal Factor",
+            description: "How strongly boids steer towards the current goal.",
+        },
+        Parameter {
+            name: "Goal Speed",
+            description: "The speed boids seek goals at.",
+        },
+        Parameter {
+            name: "Slowing Radius",
+            description: "The distance from the goal within which boids slow down, so that they arrive at it rather than overshooting.",
+        },
+        Parameter {
+            name: "Reached Radius",
+            description: "How near the flock's center must come to the goal to reach it and move on to the next.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS
+            .into_iter()
+            .chain([InputAction {
+                input: "Right Click",
+                description: "Queue a goal for the flocks, in goal mode",
+            }])
+            .collect(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
     env_logger::init();
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = utils::build_window(&event_loop, window_args);
 
     let mut state = State::new(&window);
 
-    let mut gui = gui::Gui::new(&state.gpu.device, &state.gpu.config, &window);
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
     let mut flocking_ui = gui::flocking::FlockingUi::new();
 
     let mut current_time = std::time::SystemTime::now();
     event_loop.run(move |event, _, control_flow| {
         gui.handle_events(&event);
 
-        *control_flow = ControlFlow::Poll;
+        *control_flow = utils::control_flow(&state.gpu);
         match event {
             Event::MainEventsCleared => {
                 let new_time = std::time::SystemTime::now();
                 let frame_time = new_time.duration_since(current_time).unwrap();
                 current_time = new_time;
-                state.update(frame_time);
-                state.simulation.sync_sim_config_from_ui(&mut flocking_ui);
-                state.simulation_2.sync_sim_config_from_ui(&mut flocking_ui);
-                let output = state.gpu.surface.get_current_texture().unwrap();
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                flocking_ui.sync_simulation(&mut state.simulation);
+                flocking_ui.sync_simulation(&mut state.simulation_2);
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
                 let simulation_render_command_buffer = state.render(&output);
                 let gui_render_command_buffer = gui.render(
                     &mut flocking_ui,
@@ -301,8 +527,18 @@ pub fn run() {
                     &output
                 );
 
-                state.gpu.queue.submit([simulation_render_command_buffer, gui_render_command_buffer]);
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
                 output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
             }
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
@@ -313,7 +549,7 @@ pub fn run() {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == window.id() && !state.input(event) => {
+            } if window_id == window.id() && !state.input(event, flocking_ui.get_goal_mode()) => {
                 match event {
                     #[cfg(not(target_arch="wasm32"))]
                     WindowEvent::CloseRequested
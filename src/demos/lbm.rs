@@ -0,0 +1,376 @@
+/// A demo of two dimensional flow past a cylinder, simulated with the lattice Boltzmann method.
+use crate::simulation::configurable::Configurable;
+use crate::{
+    args::WindowArgs,
+    graphics::{
+        self,
+        camera::CameraBundle,
+        entity::Entity,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light,
+        model::{Material, Mesh, Model},
+        palette::Theme,
+        scene::{EntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, Parameter},
+    },
+    simulation::lbm::{self, Simulation},
+};
+
+use cgmath::InnerSpace;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+use super::utils;
+
+/// The size each lattice cell is shown at.
+const CELL_SIZE: f32 = 0.025;
+/// Speeds are colored relative to this multiple of the inlet velocity.
+const MAX_SPEED_RATIO: f32 = 1.6;
+
+struct State {
+    simulation: Simulation,
+    gpu: GPUInterface,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+    camera_bundle: CameraBundle,
+    light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    scene: Scene,
+    theme: Theme,
+    /// The lattice's speeds, colored; written to the flow entity's texture each frame.
+    image: image::RgbaImage,
+    flow_entity: EntityHandle,
+    mouse_pressed: bool,
+    time_accumulator: std::time::Duration,
+}
+
+impl State {
+    fn new(window: &Window) -> Self {
+        let gpu: GPUInterface = GPUInterface::new(window);
+        let camera_bundle =
+            CameraBundle::new(&gpu, (0.0, 0.0, 5.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
+
+        let light_uniform = light::LightUniform::new([0.0, 0.0, 10.0], [1.0, 1.0, 1.0]);
+        let (light_bind_group_layout, light_bind_group) =
+            light::create_light_bind_group(&gpu, light_uniform);
+
+        let render_pipeline = graphics::util::create_model_render_pipeline(
+            &gpu,
+            &camera_bundle,
+            &light_bind_group_layout,
+        );
+
+        let theme = Theme::default();
+        let simulation = Simulation::new();
+
+        let image = get_speed_image(&simulation, theme);
+        let flow_entity = create_flow_entity(&gpu, &image);
+
+        let mut scene = Scene::new();
+        let flow_entity = scene.add_entity(flow_entity);
+
+        Self {
+            simulation,
+            gpu,
+            render_pipeline,
+            depth_texture,
+            camera_bundle,
+            light_bind_group,
+            light_bind_group_layout,
+            scene,
+            theme,
+            image,
+            flow_entity,
+            mouse_pressed: false,
+            time_accumulator: std::time::Duration::from_millis(0),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        graphics::util::resize(
+            new_size,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle.projection,
+        );
+    }
+
+    /// The flow is colored by the theme's colormap, and the cylinder with its obstacle color.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_model_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration) {
+        self.time_accumulator += frame_time;
+        self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
+
+        while self.time_accumulator >= self.simulation.get_timestep() {
+            let elapsed_sim_time = self.simulation.step();
+            self.time_accumulator -= elapsed_sim_time;
+        }
+
+        self.image = get_speed_image(&self.simulation, self.theme);
+        self.scene
+            .update_entity_texture(&self.gpu, self.flow_entity, &self.image);
+    }
+
+    fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // We'll use a CommandEncoder to create the commands to send to the GPU.
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
+            );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
+        }
+
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
+        encoder.finish()
+    }
+}
+
+fn help() -> Help {
+    Help {
+        title: "Lattice Boltzmann",
+        description: "Two dimensional flow past a cylinder, simulated on a lattice rather than with particles, colored by speed. Above a Reynolds number of about 50, the cylinder sheds a street of alternating vortices.",
+        parameters: &[
+        Parameter {
+            name: "Simulation dt",
+            description: "The real time each lattice step is shown for; smaller steps play the flow faster.",
+        },
+        Parameter {
+            name: "Inlet Velocity",
+            description: "The speed of the flow entering from the left, in lattice cells per step. Fast flows become inaccurate or unstable.",
+        },
+        Parameter {
+            name: "Reynolds Number",
+            description: "The ratio of the flow's inertia to its viscosity. Higher numbers give a less viscous, more turbulent wake.",
+        },
+        Parameter {
+            name: "Cylinder Radius",
+            description: "The radius of the cylinder, in lattice cells.",
+        },
+        Parameter {
+            name: "Reset",
+            description: "Restarts the flow, uniform around the cylinder.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS.to_vec(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = utils::build_window(&event_loop, window_args);
+
+    let mut state = State::new(&window);
+
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
+    let mut ui = gui::lbm::LbmUi::new();
+
+    let mut current_time = std::time::SystemTime::now();
+    event_loop.run(move |event, _, control_flow| {
+        gui.handle_events(&event);
+
+        *control_flow = utils::control_flow(&state.gpu);
+        match event {
+            Event::MainEventsCleared => {
+                let new_time = std::time::SystemTime::now();
+                let frame_time = new_time.duration_since(current_time).unwrap();
+                current_time = new_time;
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
+                let simulation_render_command_buffer = state.render(&output);
+                let gui_render_command_buffer = gui.render(
+                    &mut ui,
+                    frame_time,
+                    &state.gpu.device,
+                    &state.gpu.config,
+                    &state.gpu.queue,
+                    &window,
+                    &output
+                );
+
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
+                output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, },
+                .. // We're not using device_id currently
+            } if state.mouse_pressed => {
+                state.camera_bundle.camera_controller.process_mouse(delta.0, delta.1)
+            }
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+fn create_flow_entity(gpu: &GPUInterface, image: &image::RgbaImage) -> Entity {
+    let texture_bind_group_layout = graphics::util::create_texture_bind_group_layout(gpu);
+    let texture = texture::Texture::from_image(
+        &gpu.device,
+        &gpu.queue,
+        &image::DynamicImage::ImageRgba8(image.clone()),
+        Some("flow"),
+    )
+    .unwrap();
+    let material = Material::new(
+        &gpu.device,
+        "flow".to_string(),
+        texture,
+        &texture_bind_group_layout,
+    );
+    let mesh = Mesh::quad(
+        &gpu.device,
+        "flow".to_string(),
+        lbm::WIDTH as f32 * CELL_SIZE,
+        lbm::HEIGHT as f32 * CELL_SIZE,
+        0,
+    );
+    let model = Model {
+        meshes: vec![mesh],
+        materials: vec![material],
+    };
+    Entity::new(gpu, model, vec![Instance::default()], None)
+}
+
+/// Colors each cell of the lattice by the speed of its flow, with the cylinder in the obstacle color.
+fn get_speed_image(simulation: &Simulation, theme: Theme) -> image::RgbaImage {
+    let max_speed = MAX_SPEED_RATIO * simulation.get_config().inlet_velocity;
+    let to_pixel = |color: [f32; 3]| {
+        image::Rgba([
+            (color[0] * 255.0) as u8,
+            (color[1] * 255.0) as u8,
+            (color[2] * 255.0) as u8,
+            255,
+        ])
+    };
+    // Images' rows run down, while the lattice's y runs up.
+    image::RgbaImage::from_fn(lbm::WIDTH as u32, lbm::HEIGHT as u32, |x, row| {
+        let (x, y) = (x as usize, lbm::HEIGHT - 1 - row as usize);
+        if simulation.is_solid(x, y) {
+            to_pixel(theme.obstacle)
+        } else {
+            let speed = simulation.get_velocity(x, y).magnitude();
+            to_pixel(theme.colormap.sample(speed / max_speed))
+        }
+    })
+}
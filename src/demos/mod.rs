@@ -1,8 +1,19 @@
+pub(crate) mod billiards;
 pub(crate) mod bouncing_ball;
+pub(crate) mod bridge;
+pub(crate) mod charged;
 pub(crate) mod cloth;
+pub(crate) mod fit;
 pub(crate) mod flocking;
+pub(crate) mod lbm;
 pub(crate) mod particles_cpu;
 pub(crate) mod rigidbody;
+pub(crate) mod slosh;
 pub(crate) mod sph;
 pub(crate) mod spring_mass_damper;
+pub(crate) mod target_range;
+#[cfg(feature = "network")]
+pub(crate) mod twin;
 mod utils;
+pub(crate) mod vortex;
+pub(crate) mod wind_tunnel;
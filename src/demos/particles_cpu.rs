@@ -1,4 +1,5 @@
 use super::utils;
+use crate::args::WindowArgs;
 use crate::graphics;
 use crate::graphics::camera::CameraBundle;
 use crate::graphics::entity::ColoredMeshEntity;
@@ -6,11 +7,16 @@ use crate::graphics::forms;
 use crate::graphics::gpu_interface::GPUInterface;
 use crate::graphics::instance::Instance;
 use crate::graphics::light;
-use crate::graphics::scene::Scene;
+use crate::graphics::palette::Theme;
+use crate::graphics::scene::{ColoredMeshEntityHandle, ParticlesHandle, Scene};
+use crate::graphics::settings::GraphicsSettings;
 use crate::graphics::texture;
 use crate::gui;
+use crate::gui::help::{Help, Parameter};
 use crate::simulation;
+use crate::simulation::trigger::{Trigger, TriggerAction, TriggerShape};
 
+use cgmath::EuclideanSpace;
 use cgmath::Rotation3;
 use cgmath::Vector3;
 use cgmath::Zero;
@@ -18,17 +24,22 @@ use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
 };
 
 struct State {
     gpu: GPUInterface,
     render_pipeline: wgpu::RenderPipeline,
+    /// Writes the scene's depth ahead of render_pipeline, if the depth pre-pass is enabled.
+    depth_prepass_pipeline: Option<wgpu::RenderPipeline>,
     depth_texture: texture::Texture,
     camera_bundle: CameraBundle,
     light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     simulation_state: simulation::particles_cpu::particles::Simulation,
     scene: Scene,
+    particles_entity: ParticlesHandle,
+    obstacle_entity: ColoredMeshEntityHandle,
+    fan_entity: ColoredMeshEntityHandle,
     mouse_pressed: bool,
     time_accumulator: std::time::Duration,
 }
@@ -39,22 +50,47 @@ impl State {
 
         let camera_bundle =
             CameraBundle::new(&gpu, (0.0, 1.0, 10.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
-        let depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth texture");
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
 
         let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
         let (light_bind_group_layout, light_bind_group) =
             light::create_light_bind_group(&gpu, light_uniform);
 
-        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
-            &gpu,
-            &camera_bundle,
-            &light_bind_group_layout,
-        );
+        let (render_pipeline, depth_prepass_pipeline) =
+            graphics::util::create_colored_mesh_render_pipelines(
+                &gpu,
+                &camera_bundle,
+                &light_bind_group_layout,
+            );
 
-        let obstacle = forms::get_cube_kilter(&gpu.device, [0.9, 0.1, 0.1]);
+        // The obstacle is the only mesh the particles interact with, so it takes the body's color.
+        let obstacle = forms::get_cube_kilter(&gpu.device, Theme::default().body);
 
-        let simulation_state = simulation::particles_cpu::particles::Simulation::new(&obstacle);
+        let mut simulation_state = simulation::particles_cpu::particles::Simulation::new(
+            &obstacle.vertex_positions,
+            &obstacle.vertex_indices,
+        );
+        // Counts the particles falling past the obstacle.
+        simulation_state.add_trigger(Trigger::new(
+            TriggerShape::Aabb {
+                min: Vector3::new(-10.0, -1.1, -10.0),
+                max: Vector3::new(10.0, -1.0, 10.0),
+            },
+            TriggerAction::Count,
+        ));
+        // Despawns particles once they've fallen out of view, freeing them in the pool.
+        simulation_state.add_trigger(Trigger::new(
+            TriggerShape::Aabb {
+                min: Vector3::new(-100.0, -100.0, -100.0),
+                max: Vector3::new(100.0, -5.0, 100.0),
+            },
+            TriggerAction::Despawn,
+        ));
 
         let instances = vec![Instance {
             position: Vector3::<f32>::zero(),
@@ -62,25 +98,30 @@ impl State {
                 cgmath::Vector3::unit_z(),
                 cgmath::Deg(0.0),
             ),
-            scale: 1.0,
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            color: None,
         }];
         let obstacle_entity = ColoredMeshEntity::new(&gpu, obstacle, instances, None);
 
         let particles_entity = simulation_state.get_particles_entity(&gpu);
-        let scene = Scene::new(
-            None,
-            Some(vec![obstacle_entity]),
-            Some(vec![particles_entity]),
-        );
+        let mut scene = Scene::new();
+        let obstacle_entity = scene.add_colored_mesh_entity(obstacle_entity);
+        let particles_entity = scene.add_particles(particles_entity);
+        let fan_entity = scene.add_colored_mesh_entity(utils::create_fan_entity(&gpu));
 
         Self {
             gpu,
             render_pipeline,
+            depth_prepass_pipeline,
             depth_texture,
             camera_bundle,
             light_bind_group,
+            light_bind_group_layout,
             simulation_state,
             scene,
+            particles_entity,
+            obstacle_entity,
+            fan_entity,
             mouse_pressed: false,
             time_accumulator: std::time::Duration::from_millis(0),
         }
@@ -95,6 +136,31 @@ impl State {
         );
     }
 
+    /// Recolors the meshes with the theme.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.obstacle_entity, theme.body);
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let depth_prepass_changed = settings.depth_prepass != self.gpu.settings.depth_prepass;
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed || depth_prepass_changed {
+            (self.render_pipeline, self.depth_prepass_pipeline) =
+                graphics::util::create_colored_mesh_render_pipelines(
+                    &self.gpu,
+                    &self.camera_bundle,
+                    &self.light_bind_group_layout,
+                );
+        }
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
         utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
     }
@@ -102,7 +168,10 @@ impl State {
     fn update(&mut self, frame_time: std::time::Duration) {
         self.time_accumulator = self.time_accumulator + frame_time;
         self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
 
+        self.simulation_state
+            .set_viewpoint(self.camera_bundle.camera.position.to_vec());
         // Simulate until our simulation has "consumed" the accumulated time in discrete, fixed timesteps.
         while self.time_accumulator >= self.simulation_state.get_timestep() {
             let elapsed_sim_time = self.simulation_state.step();
@@ -112,10 +181,15 @@ impl State {
         let particle_instances = self.simulation_state.get_particles_instances();
         self.scene.update_particle_instances(
             &self.gpu,
-            0,
+            self.particles_entity,
             particle_instances,
             self.camera_bundle.camera.position,
         );
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.fan_entity,
+            utils::get_fan_instances(self.simulation_state.get_fan()),
+        );
     }
 
     fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
@@ -131,48 +205,180 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
+        if let Some(depth_prepass_pipeline) = &self.depth_prepass_pipeline {
             let mut render_pass =
-                utils::begin_default_render_pass(&mut encoder, &view, &self.depth_texture.view);
+                utils::begin_depth_prepass(&mut encoder, &self.depth_texture.view);
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(depth_prepass_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
+        }
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            self.scene.draw_colored_mesh_entities(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
+        {
+            let begin_render_pass = if self.depth_prepass_pipeline.is_some() {
+                utils::begin_render_pass_after_depth_prepass
+            } else {
+                utils::begin_default_render_pass
+            };
+            let mut render_pass = begin_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
             );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
         }
 
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
         encoder.finish()
     }
 }
 
-pub fn run() {
+fn help() -> Help {
+    Help {
+        title: "Particles",
+        description: "Particles spawned by a generator, falling past an obstacle under gravity, wind, and a y axis attractor. Volumes below the obstacle count and despawn the particles.",
+        parameters: &[
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Particles Generated Per Step",
+            description: "How many particles the generator spawns each step.",
+        },
+        Parameter {
+            name: "Emitter",
+            description: "Whether particles spawn from a disk, or uniformly over the obstacle's surface, moving away from it.",
+        },
+        Parameter {
+            name: "Generator",
+            description: "The position and radius of the disk particles spawn from.",
+        },
+        Parameter {
+            name: "Mean and Range",
+            description: "Each particle's initial speed, mass, drag, and lifetime are sampled uniformly within the range around the mean.",
+        },
+        Parameter {
+            name: "Swirl",
+            description: "Swirls the particles around an axis through the origin and pulls them towards it, both more strongly nearer the axis by the falloff's power of the distance. The swirl stops speeding particles up once they circle at its max speed.",
+        },
+        Parameter {
+            name: "Turbulence",
+            description: "Adds curl noise to the wind, a swirling flow of eddies which evolve over time and neither bunch particles up nor spread them out. It blows on particles through their drag.",
+        },
+        Parameter {
+            name: "2D Mode",
+            description: "Locks an axis, holding the particles in the plane across it at the offset. Pair it with the top-down view, locking Y, for a 2D view.",
+        },
+        Parameter {
+            name: "Fan",
+            description: "Blows a jet of wind where it's aimed, which is stronger the faster its blades spin, and spreads and weakens away from the fan.",
+        },
+        Parameter {
+            name: "Fire",
+            description: "Makes the particles burn, starting them as a campfire beside the obstacle. Burning particles rise faster the hotter they are, glow with their temperature as they cool, and burn out into smoke.",
+        },
+        Parameter {
+            name: "Fireworks",
+            description: "Bursts each generated particle into sparks as it dies or reaches an age, starting them as rockets beside the obstacle. The spark budget caps how many sparks are alive at once.",
+        },
+        Parameter {
+            name: "Coefficient of Restitution",
+            description: "The fraction of the normal velocity kept after bouncing off the obstacle.",
+        },
+        Parameter {
+            name: "Coefficient of Friction",
+            description: "How much tangential velocity is lost when bouncing off the obstacle.",
+        },
+        Parameter {
+            name: "Obstacle Conveyor",
+            description: "The velocity the obstacle's surface slides at, like a conveyor belt's, which friction drags the particles bouncing on it towards.",
+        },
+        Parameter {
+            name: "Obstacle Sides",
+            description: "Whether particles bounce off only the outside of the obstacle, pushing any inside it back out, or off both sides of its faces.",
+        },
+        Parameter {
+            name: "Step Budget",
+            description: "Throttles the generator while steps take longer than the budget, and restores it once they're faster, showing the average step time. Killing the oldest particles also removes particles while steps take half again the budget.",
+        },
+        Parameter {
+            name: "Level of Detail",
+            description: "Merges particles beyond the LOD distance from the camera into larger super-particles, which are split back into particles as the camera nears. Merged particles are cheaper to simulate and draw.",
+        },
+        Parameter {
+            name: "LOD Error",
+            description: "The angle the particles merged together span from the camera. Larger errors merge more particles.",
+        },
+        Parameter {
+            name: "Max Particles Merged",
+            description: "The most particles a super-particle may represent.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS.to_vec(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
     env_logger::init();
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = utils::build_window(&event_loop, window_args);
 
     // Our game loop follows the famous "fix your timestep!" model:
     // https://gafferongames.com/post/fix_your_timestep/
     // The state holds the accumulator.
     let mut state = State::new(&window);
 
-    let mut gui = gui::Gui::new(&state.gpu.device, &state.gpu.config, &window);
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
     let mut particles_ui = gui::particles::ParticlesUi::new();
 
     let mut current_time = std::time::SystemTime::now();
     event_loop.run(move |event, _, control_flow| {
         gui.handle_events(&event);
 
-        *control_flow = ControlFlow::Poll;
+        *control_flow = utils::control_flow(&state.gpu);
         match event {
             Event::MainEventsCleared => {
                 let new_time = std::time::SystemTime::now();
                 let frame_time = new_time.duration_since(current_time).unwrap();
                 current_time = new_time;
-                state.update(frame_time);
-                state.simulation_state.sync_sim_config_from_ui(&mut particles_ui);
-                let output = state.gpu.surface.get_current_texture().unwrap();
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                particles_ui.sync_simulation(&mut state.simulation_state);
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
                 let simulation_render_command_buffer = state.render(&output);
                 let gui_render_command_buffer = gui.render(
                     &mut particles_ui,
@@ -184,8 +390,21 @@ pub fn run() {
                     &output
                 );
 
-                state.gpu.queue.submit([simulation_render_command_buffer, gui_render_command_buffer]);
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
                 output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
             }
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
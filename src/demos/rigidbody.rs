@@ -1,27 +1,107 @@
 /// A demo of the spring-mass-damper simulation.
 use crate::{
+    args::WindowArgs,
     graphics::{
-        self, camera::CameraBundle, entity::ColoredMeshEntity, gpu_interface::GPUInterface,
-        instance::Instance, light, model::ColoredMesh, scene::Scene, texture,
+        self,
+        camera::CameraBundle,
+        debug_draw,
+        debug_draw::DebugDraw,
+        entity::ColoredMeshEntity,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        interpolation::{self, Interpolator},
+        light,
+        model::ColoredMesh,
+        palette::Theme,
+        scene::{ColoredMeshEntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
     },
-    gui,
+    gui::{
+        self,
+        help::{Help, InputAction, Parameter},
+        rigidbody::{AnimatedObstacle, Structure},
+        undo::{EditTracker, UndoAction, UndoStack},
+        ConfigUi,
+    },
+    localization::{tr, tr_format},
+    script::{self, Command, Sandbox, Script},
     simulation::{
-        collidable_mesh::CollidableMesh,
-        rigidbody::{rigidbody::RigidBody, simulation::Simulation},
+        animation::{Animation, KinematicObstacle, Transform},
+        collidable_mesh::{CollidableMesh, Sidedness},
+        ik,
+        quantity::{self, Mass},
+        rigidbody::{
+            config::Config,
+            joint::{JointKind, Motor, MotorMode},
+            rigidbody::RigidBody,
+            simulation::{JointHandle, KinematicObstacleHandle, RigidBodyHandle, Simulation},
+        },
+        trigger::{Trigger, TriggerAction, TriggerShape},
     },
 };
 
-use cgmath::Vector3;
+use std::collections::VecDeque;
+
+use cgmath::{ElementWise, Vector3, Zero};
 use itertools::Itertools;
 use winit::{
+    dpi::PhysicalPosition,
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
 };
 
 use super::utils;
 
+// Dropped rigidbodies beyond this count despawn the oldest dropped rigidbody.
+const MAX_RIGIDBODIES: usize = 32;
+const RIGIDBODY_MASS: Mass = quantity::kilograms(1.0);
+// Dropped rigidbodies start at a random position in a square of this half-width, near the top of the room.
+const DROP_HALF_WIDTH: f32 = 1.0;
+const DROP_HEIGHT: f32 = 1.0;
+
+// Structures stand on the floor near the back of the room. The wall is the largest.
+const STRUCTURE_COLUMNS: usize = 4;
+const STRUCTURE_ROWS: usize = 4;
+const BLOCK_SIZE: f32 = 0.25;
+const BLOCK_MASS: Mass = quantity::kilograms(0.25);
+const STRUCTURE_Z: f32 = -0.7;
+const FLOOR_HEIGHT: f32 = -1.0;
+// A small gap above the floor so that the bottom row doesn't start in contact with it.
+const STRUCTURE_CLEARANCE: f32 = 0.01;
+// The bases of structures are heavy enough that the parts jointed to them barely move them.
+const BASE_MASS: Mass = quantity::kilograms(1000.0);
+const WINDMILL_HUB_HEIGHT: f32 = -0.1;
+const WINDMILL_BEAM_WIDTH: f32 = 0.08;
+const WINDMILL_BLADE_LENGTH: f32 = 0.8;
+// In rad/s.
+const WINDMILL_SPEED: f32 = 2.0;
+const ARM_BASE_WIDTH: f32 = 0.3;
+const ARM_BASE_HEIGHT: f32 = 0.1;
+const ARM_SEGMENT_WIDTH: f32 = 0.08;
+// The upper arm, then the forearm.
+const ARM_SEGMENT_LENGTHS: [f32; 2] = [0.5, 0.4];
+// In seconds.
+const ANIMATION_PERIOD: f32 = 4.0;
+const IK_TOLERANCE: f32 = 0.001;
+const IK_MAX_ITERATIONS: usize = 16;
+const IK_TARGET_MARKER_SIZE: f32 = 0.1;
+
+const MAX_DEBUG_LINES: usize = 512;
+const CENTER_OF_MASS_MARKER_SIZE: f32 = 0.2;
+const PRINCIPAL_AXIS_LENGTH: f32 = 0.75;
+const JOINT_MARKER_SIZE: f32 = 0.05;
+// Scales the angular velocity (rad/s) to a length (m) for display.
+const ANGULAR_VELOCITY_DISPLAY_SCALE: f32 = 0.25;
+
+struct RobotArm {
+    hinges: Vec<JointHandle>,
+    end_effector: RigidBodyHandle,
+    // The tip of the end effector, in its local space.
+    local_tip: Vector3<f32>,
+}
+
 struct State {
     simulation: Simulation,
     gpu: GPUInterface,
@@ -29,9 +109,47 @@ struct State {
     depth_texture: texture::Texture,
     camera_bundle: CameraBundle,
     light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     scene: Scene,
+    rigidbody_entity: ColoredMeshEntityHandle,
+    obstacle_entity: ColoredMeshEntityHandle,
+    kinematic_obstacle_entity: ColoredMeshEntityHandle,
+    // The animated obstacle the user chose, and its handle and dimensions once it's spawned.
+    animated_obstacle: AnimatedObstacle,
+    kinematic_obstacle: Option<(KinematicObstacleHandle, Vector3<f32>)>,
+    debug_draw: DebugDraw,
     mouse_pressed: bool,
+    cursor_position: PhysicalPosition<f64>,
+    // The distance from the camera at which the mouse spring holds its target, while grabbing.
+    grab_distance: Option<f32>,
+    // The rigidbodies dropped so far, from oldest to newest, which haven't been despawned.
+    dropped_rigidbodies: VecDeque<RigidBodyHandle>,
+    // The blocks of the last structure built, which are replaced when a new one is built.
+    structure_rigidbodies: Vec<RigidBodyHandle>,
+    // The robot arm's hinges from the base outwards, and its end effector, if the last structure built was a robot arm.
+    robot_arm: Option<RobotArm>,
+    time_since_drop: std::time::Duration,
     time_accumulator: std::time::Duration,
+    interpolator: Interpolator<RigidBodyHandle>,
+    // The callbacks registered from the console.
+    script: Script,
+    // The edits made from the GUI, and the UI's config as of the last of them.
+    history: UndoStack<SandboxEdit>,
+    config_tracker: EditTracker<Config>,
+}
+
+/// An edit made from the GUI, which can be undone from the Config window.
+enum SandboxEdit {
+    Config {
+        before: Config,
+        after: Config,
+    },
+    /// A rigidbody spawned or despawned from the console. Undoing or redoing it toggles whether the rigidbody's
+    /// spawned, keeping it while it's despawned to respawn it. Its joints and scheduled loads aren't restored.
+    Rigidbody {
+        handle: RigidBodyHandle,
+        despawned: Option<RigidBody>,
+    },
 }
 
 impl State {
@@ -39,8 +157,12 @@ impl State {
         let gpu: GPUInterface = GPUInterface::new(&window);
         let camera_bundle =
             CameraBundle::new(&gpu, (0.0, 0.0, 5.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
-        let depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth texture");
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
 
         let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
         let (light_bind_group_layout, light_bind_group) =
@@ -52,14 +174,67 @@ impl State {
             &light_bind_group_layout,
         );
 
-        let rigidbody =
-            RigidBody::new(Vector3::<f32>::new(0.0, 0.0, 0.0), 1.0).expect("Non-invertible!");
+        let theme = Theme::default();
+        let rigidbody = RigidBody::new(Vector3::<f32>::new(0.0, 0.0, 0.0), RIGIDBODY_MASS)
+            .expect("Non-invertible!");
+        // All rigidbodies share the same mesh, which is uploaded once in its local space;
+        // each rigidbody's position and rotation are applied each frame through its instance transform.
+        let rigidbody_mesh = ColoredMesh::from_collidable_mesh(
+            &gpu.device,
+            "rigidbody cube".to_string(),
+            rigidbody.get_mesh(),
+            theme.body,
+        );
+
         let obstacles = get_obstacles();
-        let simulation = Simulation::new(vec![rigidbody], obstacles);
+        let obstacle_mesh = ColoredMesh::from_collidable_mesh(
+            &gpu.device,
+            "floor".to_string(),
+            &obstacles[0],
+            theme.obstacle,
+        );
+
+        let mut simulation = Simulation::new(vec![rigidbody], obstacles);
+        // Despawns any rigidbodies which escape the room, e.g. by tunneling through its walls.
+        simulation.add_trigger(Trigger::new(
+            TriggerShape::Aabb {
+                min: Vector3::new(-100.0, -100.0, -100.0),
+                max: Vector3::new(100.0, -5.0, 100.0),
+            },
+            TriggerAction::Despawn,
+        ));
+
+        let rigidbody_instances = get_rigidbody_instances(&simulation);
+        let rigidbody_entity = ColoredMeshEntity::new(
+            &gpu,
+            rigidbody_mesh,
+            rigidbody_instances,
+            Some(MAX_RIGIDBODIES + 1 + STRUCTURE_COLUMNS * STRUCTURE_ROWS),
+        );
+
+        let obstacle_instances = vec![Instance::default()];
+        let obstacle_entity = ColoredMeshEntity::new(&gpu, obstacle_mesh, obstacle_instances, None);
 
-        // Note we're keeping the scene around since we'll probably have some static obstacles that we'd like to draw
-        // for the springy mesh to interact with.
-        let scene = Scene::new(None, None, None);
+        // Animated obstacles are boxes, which share the unit cube mesh scaled through their instance.
+        let kinematic_obstacle_mesh = ColoredMesh::from_collidable_mesh(
+            &gpu.device,
+            "animated obstacle".to_string(),
+            simulation
+                .get_rigidbodies()
+                .values()
+                .next()
+                .unwrap()
+                .get_mesh(),
+            theme.obstacle,
+        );
+        let kinematic_obstacle_entity =
+            ColoredMeshEntity::new(&gpu, kinematic_obstacle_mesh, Vec::new(), Some(1));
+
+        let mut scene = Scene::new();
+        let rigidbody_entity = scene.add_colored_mesh_entity(rigidbody_entity);
+        let obstacle_entity = scene.add_colored_mesh_entity(obstacle_entity);
+        let kinematic_obstacle_entity = scene.add_colored_mesh_entity(kinematic_obstacle_entity);
+        let debug_draw = DebugDraw::new(&gpu, &camera_bundle, MAX_DEBUG_LINES);
 
         Self {
             simulation,
@@ -68,9 +243,26 @@ impl State {
             depth_texture,
             camera_bundle,
             light_bind_group,
+            light_bind_group_layout,
             scene,
+            rigidbody_entity,
+            obstacle_entity,
+            kinematic_obstacle_entity,
+            animated_obstacle: AnimatedObstacle::Off,
+            kinematic_obstacle: None,
+            debug_draw,
             mouse_pressed: false,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            grab_distance: None,
+            dropped_rigidbodies: VecDeque::with_capacity(MAX_RIGIDBODIES + 1),
+            structure_rigidbodies: Vec::with_capacity(STRUCTURE_COLUMNS * STRUCTURE_ROWS),
+            robot_arm: None,
+            time_since_drop: std::time::Duration::ZERO,
             time_accumulator: std::time::Duration::from_millis(0),
+            interpolator: Interpolator::new(),
+            script: Script::new(),
+            history: UndoStack::new(),
+            config_tracker: EditTracker::new(&Config::default()),
         }
     }
 
@@ -83,17 +275,550 @@ impl State {
         );
     }
 
+    /// Recolors the meshes with the theme.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.rigidbody_entity, theme.body);
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.obstacle_entity, theme.obstacle);
+        self.scene.set_colored_mesh_entity_color(
+            &self.gpu,
+            self.kinematic_obstacle_entity,
+            theme.obstacle,
+        );
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.debug_draw
+                .rebuild_pipeline(&self.gpu, &self.camera_bundle);
+        }
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
-        utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state,
+                ..
+            } => {
+                if *state == ElementState::Pressed {
+                    let (origin, direction) = self.get_cursor_ray();
+                    self.grab_distance = self.simulation.grab(origin, direction);
+                } else {
+                    self.grab_distance = None;
+                    self.simulation.release();
+                }
+                true
+            }
+            _ => {
+                utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+            }
+        }
     }
 
-    fn update(&mut self, frame_time: std::time::Duration) {
+    fn get_cursor_ray(&self) -> (Vector3<f32>, Vector3<f32>) {
+        self.camera_bundle.camera.cursor_ray(
+            &self.camera_bundle.projection,
+            self.cursor_position,
+            self.gpu.config.width,
+            self.gpu.config.height,
+        )
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration, ui: &mut gui::rigidbody::RigidBodyUi) {
+        self.run_console(ui);
+        self.track_edits(ui);
         self.time_accumulator = self.time_accumulator + frame_time;
         self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
 
+        // Keep the grabbed point under the cursor as either the cursor or the camera moves.
+        if let Some(grab_distance) = self.grab_distance {
+            let (origin, direction) = self.get_cursor_ray();
+            self.simulation
+                .set_grab_target(origin + direction * grab_distance);
+        }
+
+        let mut callback_errors = Vec::new();
+        let config_before_callbacks = ui.get_config().clone();
         while self.time_accumulator >= self.simulation.get_timestep() {
+            self.interpolator.record_keyed(
+                get_rigidbody_handles(&self.simulation),
+                &get_rigidbody_instances(&self.simulation),
+            );
             let elapsed_sim_time = self.simulation.step();
             self.time_accumulator = self.time_accumulator - elapsed_sim_time;
+            callback_errors.extend(self.script.step(&mut RigidBodySandbox {
+                simulation: &mut self.simulation,
+                ui_config: ui.get_config_mut(),
+                history: None,
+            }));
+        }
+        // The callbacks' tweaks to the config aren't edits to undo.
+        if *ui.get_config() != config_before_callbacks {
+            self.config_tracker.reset(ui.get_config());
+        }
+        for err in callback_errors {
+            ui.print_to_console(tr(err));
+        }
+
+        let mut instances = get_rigidbody_instances(&self.simulation);
+        self.interpolator.blend_keyed(
+            get_rigidbody_handles(&self.simulation),
+            &mut instances,
+            interpolation::get_alpha(self.time_accumulator, self.simulation.get_timestep()),
+        );
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.rigidbody_entity,
+            instances,
+        );
+
+        let instances = self
+            .kinematic_obstacle
+            .and_then(|(handle, dimensions)| {
+                let obstacle = self.simulation.get_kinematic_obstacles().get(handle)?;
+                let transform = obstacle.get_transform();
+                Some(Instance {
+                    position: transform.position,
+                    rotation: transform.rotation,
+                    scale: dimensions,
+                    ..Default::default()
+                })
+            })
+            .into_iter()
+            .collect_vec();
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.kinematic_obstacle_entity,
+            instances,
+        );
+    }
+
+    /// Runs the lines the user entered into the console, printing their output.
+    fn run_console(&mut self, ui: &mut gui::rigidbody::RigidBodyUi) {
+        for line in ui.take_console_lines() {
+            let results = self.script.run(
+                &line,
+                &mut RigidBodySandbox {
+                    simulation: &mut self.simulation,
+                    ui_config: ui.get_config_mut(),
+                    history: Some(&mut self.history),
+                },
+            );
+            ui.print_to_console(&format!("> {}", line));
+            for result in results {
+                match result {
+                    Ok(output) => ui.print_to_console(&output),
+                    Err(err) => ui.print_to_console(tr(err)),
+                }
+            }
+        }
+    }
+
+    /// Records the user's finished edits of the config, and undoes or redoes an edit if they ask to.
+    fn track_edits(&mut self, ui: &mut gui::rigidbody::RigidBodyUi) {
+        if let Some(before) = self.config_tracker.update(ui.get_config(), ui.is_editing()) {
+            self.history.push(SandboxEdit::Config {
+                before,
+                after: ui.get_config().clone(),
+            });
+        }
+        if let Some(action) = ui.get_undo_action() {
+            let edit = match action {
+                UndoAction::Undo => self.history.undo(),
+                UndoAction::Redo => self.history.redo(),
+            };
+            let mut respawned = None;
+            match edit {
+                Some(SandboxEdit::Config { before, after }) => {
+                    let config = if action == UndoAction::Undo {
+                        before
+                    } else {
+                        after
+                    };
+                    ui.get_config_mut().clone_from(config);
+                    self.config_tracker.reset(config);
+                }
+                Some(SandboxEdit::Rigidbody { handle, despawned }) => match despawned.take() {
+                    Some(rigidbody) => {
+                        let old_handle = *handle;
+                        *handle = self.simulation.spawn_rigidbody(rigidbody);
+                        respawned = Some((old_handle, *handle));
+                    }
+                    None => *despawned = self.simulation.despawn_rigidbody(*handle),
+                },
+                None => {}
+            }
+            // The respawned rigidbody has a new handle, which the other edits of it should refer to.
+            if let Some((old_handle, new_handle)) = respawned {
+                for edit in self.history.edits_mut() {
+                    if let SandboxEdit::Rigidbody { handle, .. } = edit {
+                        if *handle == old_handle {
+                            *handle = new_handle;
+                        }
+                    }
+                }
+            }
+        }
+        ui.set_undo_available(self.history.can_undo(), self.history.can_redo());
+    }
+
+    /// Replaces the animated obstacle when the user chooses another.
+    fn update_animated_obstacle(&mut self, ui: &gui::rigidbody::RigidBodyUi) {
+        let animated_obstacle = ui.get_animated_obstacle();
+        if animated_obstacle == self.animated_obstacle {
+            return;
+        }
+        self.animated_obstacle = animated_obstacle;
+        if let Some((handle, _)) = self.kinematic_obstacle.take() {
+            self.simulation.despawn_kinematic_obstacle(handle);
+        }
+        if let Some((dimensions, animation)) = get_animation(animated_obstacle) {
+            let (vertex_positions, indices) = graphics::forms::get_cube_vertices();
+            let vertex_positions = vertex_positions
+                .iter()
+                .map(|v| v.mul_element_wise(dimensions))
+                .collect_vec();
+            let obstacle =
+                KinematicObstacle::new(CollidableMesh::new(vertex_positions, indices), animation);
+            let handle = self.simulation.spawn_kinematic_obstacle(obstacle);
+            self.kinematic_obstacle = Some((handle, dimensions));
+        }
+    }
+
+    /// Periodically drops a new rigidbody into the room while the user has dropping enabled,
+    /// despawning the oldest dropped rigidbody once there are too many.
+    fn drop_rigidbodies(
+        &mut self,
+        ui: &gui::rigidbody::RigidBodyUi,
+        frame_time: std::time::Duration,
+    ) {
+        let drop_interval = match ui.get_drop_interval() {
+            Some(drop_interval) => drop_interval,
+            None => return,
+        };
+        self.time_since_drop += frame_time;
+        if self.time_since_drop < drop_interval {
+            return;
+        }
+        self.time_since_drop = std::time::Duration::ZERO;
+
+        // Some may have been despawned by the simulation already.
+        let rigidbodies = self.simulation.get_rigidbodies();
+        self.dropped_rigidbodies
+            .retain(|handle| rigidbodies.contains(*handle));
+        if self.dropped_rigidbodies.len() >= MAX_RIGIDBODIES {
+            if let Some(oldest) = self.dropped_rigidbodies.pop_front() {
+                self.simulation.despawn_rigidbody(oldest);
+            }
+        }
+
+        let random_offset = || DROP_HALF_WIDTH * (2.0 * rand::random::<f32>() - 1.0);
+        let position = Vector3::new(random_offset(), DROP_HEIGHT, random_offset());
+        let mut rigidbody = RigidBody::new(position, RIGIDBODY_MASS).expect("Non-invertible!");
+        // An off-center impulse so that each rigidbody tumbles differently.
+        rigidbody.apply_impulse(
+            Vector3::new(random_offset(), 0.0, random_offset()) * 0.5,
+            Vector3::new(random_offset(), random_offset(), random_offset()) * 0.5,
+        );
+        self.dropped_rigidbodies
+            .push_back(self.simulation.spawn_rigidbody(rigidbody));
+    }
+
+    /// Builds the structure the user asks for, replacing the last structure built.
+    fn build_structure(&mut self, ui: &gui::rigidbody::RigidBodyUi) {
+        let (structure, kind) = match ui.get_build_structure() {
+            Some(structure) => structure,
+            None => return,
+        };
+        for handle in self.structure_rigidbodies.drain(..) {
+            self.simulation.despawn_rigidbody(handle);
+        }
+        self.robot_arm = None;
+        match structure {
+            Structure::Wall => self.build_wall(kind),
+            Structure::Windmill => self.build_windmill(),
+            Structure::RobotArm => self.build_robot_arm(),
+        }
+    }
+
+    /// Spawns a box as part of the structure.
+    fn spawn_part(
+        &mut self,
+        position: Vector3<f32>,
+        mass: Mass,
+        dimensions: Vector3<f32>,
+    ) -> RigidBodyHandle {
+        let rigidbody = RigidBody::cuboid(position, mass, dimensions).expect("Non-invertible!");
+        let handle = self.simulation.spawn_rigidbody(rigidbody);
+        self.structure_rigidbodies.push(handle);
+        handle
+    }
+
+    /// Builds a wall of blocks, each jointed to its neighbors at the center of their shared face.
+    /// Hinges turn about the wall's normal.
+    fn build_wall(&mut self, kind: JointKind) {
+        let block_position = |column: usize, row: usize| {
+            Vector3::new(
+                (column as f32 - (STRUCTURE_COLUMNS - 1) as f32 / 2.0) * BLOCK_SIZE,
+                FLOOR_HEIGHT + STRUCTURE_CLEARANCE + (row as f32 + 0.5) * BLOCK_SIZE,
+                STRUCTURE_Z,
+            )
+        };
+        for row in 0..STRUCTURE_ROWS {
+            for column in 0..STRUCTURE_COLUMNS {
+                self.spawn_part(
+                    block_position(column, row),
+                    BLOCK_MASS,
+                    Vector3::new(BLOCK_SIZE, BLOCK_SIZE, BLOCK_SIZE),
+                );
+            }
+        }
+
+        let block = |column: usize, row: usize| {
+            self.structure_rigidbodies[row * STRUCTURE_COLUMNS + column]
+        };
+        let mut joints = Vec::new();
+        for row in 0..STRUCTURE_ROWS {
+            for column in 0..STRUCTURE_COLUMNS {
+                let position = block_position(column, row);
+                if column + 1 < STRUCTURE_COLUMNS {
+                    let anchor = position + Vector3::unit_x() * BLOCK_SIZE / 2.0;
+                    joints.push((block(column, row), block(column + 1, row), anchor));
+                }
+                if row + 1 < STRUCTURE_ROWS {
+                    let anchor = position + Vector3::unit_y() * BLOCK_SIZE / 2.0;
+                    joints.push((block(column, row), block(column, row + 1), anchor));
+                }
+            }
+        }
+        for (a, b, anchor) in joints {
+            self.simulation
+                .add_joint(kind, a, b, anchor, Vector3::unit_z());
+        }
+    }
+
+    /// Builds a heavy tower with a pair of welded blades hinged to its front,
+    /// spun about the tower's normal by a motor.
+    fn build_windmill(&mut self) {
+        let tower_height = WINDMILL_HUB_HEIGHT - FLOOR_HEIGHT + WINDMILL_BEAM_WIDTH;
+        let tower = self.spawn_part(
+            Vector3::new(
+                0.0,
+                FLOOR_HEIGHT + STRUCTURE_CLEARANCE + tower_height / 2.0,
+                STRUCTURE_Z,
+            ),
+            BASE_MASS,
+            Vector3::new(WINDMILL_BEAM_WIDTH, tower_height, WINDMILL_BEAM_WIDTH),
+        );
+        let hub = Vector3::new(
+            0.0,
+            WINDMILL_HUB_HEIGHT + STRUCTURE_CLEARANCE,
+            STRUCTURE_Z + WINDMILL_BEAM_WIDTH / 2.0,
+        );
+        let blade_center = hub + Vector3::unit_z() * WINDMILL_BEAM_WIDTH / 2.0;
+        let horizontal_blade = self.spawn_part(
+            blade_center,
+            BLOCK_MASS,
+            Vector3::new(
+                WINDMILL_BLADE_LENGTH,
+                WINDMILL_BEAM_WIDTH,
+                WINDMILL_BEAM_WIDTH,
+            ),
+        );
+        let vertical_blade = self.spawn_part(
+            blade_center,
+            BLOCK_MASS,
+            Vector3::new(
+                WINDMILL_BEAM_WIDTH,
+                WINDMILL_BLADE_LENGTH,
+                WINDMILL_BEAM_WIDTH,
+            ),
+        );
+        self.simulation.add_joint(
+            JointKind::Weld,
+            horizontal_blade,
+            vertical_blade,
+            blade_center,
+            Vector3::unit_z(),
+        );
+        if let Some(hinge) = self.simulation.add_joint(
+            JointKind::Hinge,
+            tower,
+            horizontal_blade,
+            hub,
+            Vector3::unit_z(),
+        ) {
+            self.simulation.set_joint_motor(
+                hinge,
+                Motor {
+                    mode: MotorMode::Velocity,
+                    target: WINDMILL_SPEED,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    /// Builds a heavy base with an upper arm hinged on top of it, and a forearm hinged on top of the upper arm.
+    /// Motors hold each hinge at its target angle, starting upright.
+    fn build_robot_arm(&mut self) {
+        let base_top = FLOOR_HEIGHT + STRUCTURE_CLEARANCE + ARM_BASE_HEIGHT;
+        let base = self.spawn_part(
+            Vector3::new(0.0, base_top - ARM_BASE_HEIGHT / 2.0, STRUCTURE_Z),
+            BASE_MASS,
+            Vector3::new(ARM_BASE_WIDTH, ARM_BASE_HEIGHT, ARM_BASE_WIDTH),
+        );
+        let mut parent = base;
+        let mut joint_height = base_top;
+        let mut hinges = Vec::new();
+        for length in ARM_SEGMENT_LENGTHS {
+            let segment = self.spawn_part(
+                Vector3::new(0.0, joint_height + length / 2.0, STRUCTURE_Z),
+                BLOCK_MASS,
+                Vector3::new(ARM_SEGMENT_WIDTH, length, ARM_SEGMENT_WIDTH),
+            );
+            if let Some(hinge) = self.simulation.add_joint(
+                JointKind::Hinge,
+                parent,
+                segment,
+                Vector3::new(0.0, joint_height, STRUCTURE_Z),
+                Vector3::unit_z(),
+            ) {
+                self.simulation.set_joint_motor(
+                    hinge,
+                    Motor {
+                        mode: MotorMode::Angle,
+                        ..Default::default()
+                    },
+                );
+                hinges.push(hinge);
+            }
+            parent = segment;
+            joint_height += length;
+        }
+        let forearm_length = ARM_SEGMENT_LENGTHS[ARM_SEGMENT_LENGTHS.len() - 1];
+        self.robot_arm = Some(RobotArm {
+            hinges,
+            end_effector: parent,
+            local_tip: Vector3::unit_y() * forearm_length / 2.0,
+        });
+    }
+
+    /// Reaches the robot arm's end effector for the user's target, while inverse kinematics is enabled,
+    /// by solving for the arm's pose and setting its motors' target angles to match.
+    fn drive_robot_arm(&mut self, ui: &gui::rigidbody::RigidBodyUi) {
+        let target = match ui.get_ik_target() {
+            Some(target) => target,
+            None => return,
+        };
+        self.debug_draw
+            .cross(target, IK_TARGET_MARKER_SIZE, debug_draw::GREEN);
+        let robot_arm = match &self.robot_arm {
+            Some(robot_arm) => robot_arm,
+            None => return,
+        };
+
+        // The arm is only driven while it's intact.
+        let mut points = Vec::with_capacity(robot_arm.hinges.len() + 1);
+        for hinge in robot_arm.hinges.iter() {
+            match self.simulation.get_joint_anchors(*hinge) {
+                Some((anchor, _)) => points.push(anchor),
+                None => return,
+            }
+        }
+        let end_effector = match self
+            .simulation
+            .get_rigidbodies()
+            .get(robot_arm.end_effector)
+        {
+            Some(end_effector) => end_effector,
+            None => return,
+        };
+        points.push(end_effector.local_to_world(robot_arm.local_tip));
+
+        ik::fabrik(&mut points, target, IK_TOLERANCE, IK_MAX_ITERATIONS);
+        for segment in points.windows(2) {
+            self.debug_draw
+                .line(segment[0], segment[1], debug_draw::GREEN);
+        }
+
+        // The hinges all share an axis, and the arm stands upright on its base at rest.
+        let joints = self.simulation.get_joints();
+        let rigidbodies = self.simulation.get_rigidbodies();
+        let shoulder = &joints[robot_arm.hinges[0]];
+        let (base, upper_arm) = shoulder.get_bodies();
+        let bodies = (&rigidbodies[base], &rigidbodies[upper_arm]);
+        let up = bodies.0.get_rotation() * Vector3::unit_y();
+        let angles = ik::hinge_angles(&points, shoulder.get_axis(bodies), up);
+        let motors = robot_arm
+            .hinges
+            .iter()
+            .zip(angles)
+            .map(|(hinge, angle)| {
+                let mut motor = joints[*hinge].get_motor();
+                motor.mode = MotorMode::Angle;
+                motor.target = angle;
+                (*hinge, motor)
+            })
+            .collect::<Vec<_>>();
+        for (hinge, motor) in motors {
+            self.simulation.set_joint_motor(hinge, motor);
+        }
+    }
+
+    /// Queues debug lines for the center of mass, principal axes, and angular velocity
+    /// of each rigidbody the user has enabled gizmos for.
+    /// Also draws the mouse spring, while the user is dragging a rigidbody.
+    fn draw_gizmos(&mut self, ui: &gui::rigidbody::RigidBodyUi) {
+        if let Some((anchor, target)) = self.simulation.get_mouse_spring() {
+            self.debug_draw.line(anchor, target, debug_draw::YELLOW);
+        }
+        for (handle, _) in self.simulation.get_joints().iter() {
+            if let Some((anchor_a, anchor_b)) = self.simulation.get_joint_anchors(handle) {
+                self.debug_draw
+                    .cross(anchor_a, JOINT_MARKER_SIZE, debug_draw::WHITE);
+                self.debug_draw.line(anchor_a, anchor_b, debug_draw::RED);
+            }
+        }
+        for (index, rigidbody) in self.simulation.get_rigidbodies().values().enumerate() {
+            if !ui.get_show_gizmos(index) {
+                continue;
+            }
+            let center_of_mass = rigidbody.get_position();
+            self.debug_draw.cross(
+                center_of_mass,
+                CENTER_OF_MASS_MARKER_SIZE,
+                debug_draw::WHITE,
+            );
+            self.debug_draw.basis(
+                center_of_mass,
+                rigidbody.get_principal_axes(),
+                PRINCIPAL_AXIS_LENGTH,
+            );
+            self.debug_draw.arrow(
+                center_of_mass,
+                rigidbody.get_angular_velocity() * ANGULAR_VELOCITY_DISPLAY_SCALE,
+                debug_draw::YELLOW,
+            );
         }
     }
 
@@ -110,75 +835,339 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        let rigidbody_mesh = ColoredMesh::from_rigidbody(
-            &self.gpu.device,
-            "springy cube".to_string(),
-            &self.simulation.get_rigidbodies()[0],
-            [0.9, 0.1, 0.1],
-        );
-        let rigidbody_instances = vec![Instance::default()];
-        let rigidbody_entity =
-            ColoredMeshEntity::new(&self.gpu, rigidbody_mesh, rigidbody_instances, None);
-
-        let obstacle_mesh = ColoredMesh::from_collidable_mesh(
-            &self.gpu.device,
-            "floor".to_string(),
-            &self.simulation.get_obstacles()[0],
-            [0.1, 0.9, 0.1],
-        );
-        let obstacle_instances = vec![Instance::default()];
-        let obstacle_entity =
-            ColoredMeshEntity::new(&self.gpu, obstacle_mesh, obstacle_instances, None);
+        self.debug_draw.prepare(&self.gpu);
 
         {
-            let mut render_pass =
-                utils::begin_default_render_pass(&mut encoder, &view, &self.depth_texture.view);
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            self.scene.draw_colored_mesh_entities(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            rigidbody_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            obstacle_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
             );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                self.debug_draw
+                    .draw(&mut render_pass, &camera_view.camera_bind_group);
+            }
         }
 
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
         encoder.finish()
     }
 }
 
-pub fn run() {
+/// The rigidbody simulation as the console drives it. Rigidbodies are named by their slot in the simulation.
+struct RigidBodySandbox<'a> {
+    simulation: &'a mut Simulation,
+    // The UI's config, which the simulation is synced from each frame, so tweaks are made to both.
+    ui_config: &'a mut Config,
+    // Where spawning and despawning are recorded, when they're run by the user rather than by a callback.
+    history: Option<&'a mut UndoStack<SandboxEdit>>,
+}
+
+impl RigidBodySandbox<'_> {
+    const COMMANDS: &'static [Command] = &[
+        Command {
+            name: "spawn",
+            arguments: "<x> <y> <z> [mass]",
+            description: "Spawns a cube at the position, of 1 kg unless given its mass.",
+        },
+        Command {
+            name: "despawn",
+            arguments: "<rigidbody>",
+            description: "Despawns the rigidbody.",
+        },
+        Command {
+            name: "impulse",
+            arguments: "<rigidbody> <x> <y> <z> [<offset x> <offset y> <offset z>]",
+            description: "Applies the impulse to the rigidbody, at the offset from its center of mass.",
+        },
+        Command {
+            name: "set",
+            arguments: "<name> <values>",
+            description: "Sets a config value, from one number, three for vectors, or 0 or 1 for booleans. List the names with set.",
+        },
+        Command {
+            name: "rigidbodies",
+            arguments: "",
+            description: "Lists the rigidbodies and their positions.",
+        },
+    ];
+
+    fn get_handle(&self, rigidbody: f32) -> Result<RigidBodyHandle, &'static str> {
+        self.simulation
+            .get_rigidbodies()
+            .iter()
+            .map(|(handle, _)| handle)
+            .find(|handle| handle.index() as f32 == rigidbody)
+            .ok_or("No such rigidbody!")
+    }
+}
+
+impl Sandbox for RigidBodySandbox<'_> {
+    fn commands(&self) -> &'static [Command] {
+        RigidBodySandbox::COMMANDS
+    }
+
+    fn run(&mut self, command: &str, arguments: &[&str]) -> Result<String, &'static str> {
+        match command {
+            "spawn" => {
+                let (position, mass) = match script::numbers(arguments)?[..] {
+                    [x, y, z] => (Vector3::new(x, y, z), RIGIDBODY_MASS),
+                    [x, y, z, mass] if mass > 0.0 => {
+                        (Vector3::new(x, y, z), quantity::kilograms(mass))
+                    }
+                    [_, _, _, _] => return Err("The mass must be positive!"),
+                    _ => return Err("Usage: spawn <x> <y> <z> [mass]"),
+                };
+                let rigidbody = RigidBody::new(position, mass)?;
+                let handle = self.simulation.spawn_rigidbody(rigidbody);
+                if let Some(history) = &mut self.history {
+                    history.push(SandboxEdit::Rigidbody {
+                        handle,
+                        despawned: None,
+                    });
+                }
+                Ok(tr_format("Spawned rigidbody {}", &[&handle.index()]))
+            }
+            "despawn" => {
+                let handle = match script::numbers(arguments)?[..] {
+                    [rigidbody] => self.get_handle(rigidbody)?,
+                    _ => return Err("Usage: despawn <rigidbody>"),
+                };
+                let despawned = self.simulation.despawn_rigidbody(handle);
+                if let Some(history) = &mut self.history {
+                    history.push(SandboxEdit::Rigidbody { handle, despawned });
+                }
+                Ok(tr_format("Despawned rigidbody {}", &[&handle.index()]))
+            }
+            "impulse" => {
+                let (handle, impulse, offset) = match script::numbers(arguments)?[..] {
+                    [rigidbody, x, y, z] => (
+                        self.get_handle(rigidbody)?,
+                        Vector3::new(x, y, z),
+                        Vector3::zero(),
+                    ),
+                    [rigidbody, x, y, z, offset_x, offset_y, offset_z] => (
+                        self.get_handle(rigidbody)?,
+                        Vector3::new(x, y, z),
+                        Vector3::new(offset_x, offset_y, offset_z),
+                    ),
+                    _ => return Err(
+                        "Usage: impulse <rigidbody> <x> <y> <z> [<offset x> <offset y> <offset z>]",
+                    ),
+                };
+                if let Some(rigidbody) = self.simulation.get_rigidbodies_mut().get_mut(handle) {
+                    rigidbody.apply_impulse(impulse, offset);
+                }
+                Ok(String::new())
+            }
+            "set" => match arguments.split_first() {
+                Some((name, values)) => {
+                    let values = script::numbers(values)?;
+                    self.ui_config.set(name, &values)?;
+                    self.simulation.get_config_mut().set(name, &values)?;
+                    Ok(String::new())
+                }
+                None => Ok(Config::SETTABLE.join(", ")),
+            },
+            "rigidbodies" => Ok(self
+                .simulation
+                .get_rigidbodies()
+                .iter()
+                .map(|(handle, rigidbody)| {
+                    let position = rigidbody.get_position();
+                    format!(
+                        "{}: ({:.2}, {:.2}, {:.2})",
+                        handle.index(),
+                        position.x,
+                        position.y,
+                        position.z
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            _ => Err("No such command! Try help."),
+        }
+    }
+}
+
+fn help() -> Help {
+    Help {
+        title: "Rigidbody",
+        description: "Rigidbodies tumbling and colliding inside a box, with impulses and torques applied from the config, and structures of jointed blocks to demolish.",
+        parameters: &[
+        Parameter {
+            name: "Integration",
+            description: "The numerical integration method; RK4 is more accurate and stable than Euler, but slower. Velocity Verlet and leapfrog neither gain nor lose energy over long runs. RK45 estimates each step's error, so that dt can adapt to it.",
+        },
+        Parameter {
+            name: "Adaptive dt",
+            description: "Adapts dt to RK45's error estimate after each step, within the min and max dt: shrinking it while the motion's hard to follow, and growing it while it's easy. A lower error tolerance takes smaller steps.",
+        },
+        Parameter {
+            name: "Implicit Gyroscopic Torque",
+            description: "Steps rotations by solving Euler's equations, with the gyroscopic torque of spinning bodies, implicitly. Fast spinners, like tops and gyroscopes, then precess steadily rather than gaining energy until they tumble wildly.",
+        },
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Gravity",
+            description: "The acceleration of each rigidbody.",
+        },
+        Parameter {
+            name: "Wind",
+            description: "The velocity of the air the rigidbodies move through.",
+        },
+        Parameter {
+            name: "Linear and Angular Drag",
+            description: "Viscous drag is proportional to the velocity, and quadratic drag to its square.",
+        },
+        Parameter {
+            name: "Coefficient of Restitution",
+            description: "The fraction of the normal velocity kept after a collision.",
+        },
+        Parameter {
+            name: "Coefficient of Friction",
+            description: "The most friction impulse a collision applies against sliding, as a fraction of its normal impulse.",
+        },
+        Parameter {
+            name: "Anisotropic Friction",
+            description: "Makes friction grip differently along the grain than across it, as brushed metal or fabric does. Sliding along the grain uses the friction along the grain, and sliding across it the coefficient of friction; the grain's direction is set in world space.",
+        },
+        Parameter {
+            name: "Position Correction",
+            description: "The fraction of a body's sinking into the walls, and of a joint's stretch, corrected each step by moving the bodies directly. Without it, resting bodies slowly sink into the floor and joints drift apart.",
+        },
+        Parameter {
+            name: "2D Mode",
+            description: "Locks an axis, holding the rigidbodies in the plane across it at the offset, and only letting them spin about the axis.",
+        },
+        Parameter {
+            name: "Impulse",
+            description: "An impulse applied at a position on the body with Free Impulse.",
+        },
+        Parameter {
+            name: "Torque",
+            description: "A torque about an axis, applied over a duration with a profile by Apply Torque.",
+        },
+        Parameter {
+            name: "Drop Rigidbodies",
+            description: "Drops a new rigidbody into the box every drop interval.",
+        },
+        Parameter {
+            name: "Animated Obstacle",
+            description: "An obstacle moved along keyframes, which pushes the rigidbodies it sweeps into.",
+        },
+        Parameter {
+            name: "Joint Stiffness",
+            description: "How strongly joints hold their bodies' anchors together, and welds their relative rotations.",
+        },
+        Parameter {
+            name: "Breakable Joints",
+            description: "Joints break once they're strained past the break force or torque, e.g. by dragging a body away.",
+        },
+        Parameter {
+            name: "Build Structure",
+            description: "Builds a wall of jointed blocks to demolish, a windmill, or a robot arm.",
+        },
+        Parameter {
+            name: "Inverse Kinematics",
+            description: "Reaches the robot arm for the target, by solving for its pose with FABRIK and driving its motors to match.",
+        },
+        Parameter {
+            name: "Motor",
+            description: "Drives a hinge to a target angle or at a target speed, with a torque of at most the max torque.",
+        },
+        Parameter {
+            name: "Joints",
+            description: "Lists the joints; Break removes one, and Re-anchor makes it hold its bodies where they are now.",
+        },
+        Parameter {
+            name: "Impulse Spectrogram",
+            description: "Plots the contact impulses of the last grabbed rigidbody, or else the oldest, and their spectrum over time.",
+        },
+        Parameter {
+            name: "Console",
+            description: "Runs commands to spawn rigidbodies, apply impulses, and set config values, and callbacks which run them every so many steps. Type help for the commands.",
+        },
+        Parameter {
+            name: "Undo",
+            description: "Undoes the last edit of the config, or the last rigidbody spawned or despawned from the console, which Redo reapplies. A respawned rigidbody's joints aren't restored.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS
+            .into_iter()
+            .chain([
+                InputAction {
+                    input: "Right Mouse Drag",
+                    description: "Grab a rigidbody and drag it around",
+                },
+                InputAction {
+                    input: "Ctrl+Z / Ctrl+Y",
+                    description: "Undo or redo the last edit",
+                },
+            ])
+            .collect(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
     env_logger::init();
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = utils::build_window(&event_loop, window_args);
 
     let mut state = State::new(&window);
 
-    let mut gui = gui::Gui::new(&state.gpu.device, &state.gpu.config, &window);
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
     let mut ui = gui::rigidbody::RigidBodyUi::new();
 
     let mut current_time = std::time::SystemTime::now();
     event_loop.run(move |event, _, control_flow| {
         gui.handle_events(&event);
 
-        *control_flow = ControlFlow::Poll;
+        *control_flow = utils::control_flow(&state.gpu);
         match event {
             Event::MainEventsCleared => {
                 let new_time = std::time::SystemTime::now();
                 let frame_time = new_time.duration_since(current_time).unwrap();
                 current_time = new_time;
-                state.update(frame_time);
-                state.simulation.sync_sim_from_ui(&mut ui);
-                let output = state.gpu.surface.get_current_texture().unwrap();
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time, &mut ui);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                state.drop_rigidbodies(&ui, frame_time);
+                state.build_structure(&ui);
+                state.update_animated_obstacle(&ui);
+                state.drive_robot_arm(&ui);
+                state.draw_gizmos(&ui);
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
                 let simulation_render_command_buffer = state.render(&output);
                 let gui_render_command_buffer = gui.render(
                     &mut ui,
@@ -190,8 +1179,21 @@ pub fn run() {
                     &output
                 );
 
-                state.gpu.queue.submit([simulation_render_command_buffer, gui_render_command_buffer]);
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
                 output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
             }
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
@@ -202,7 +1204,9 @@ pub fn run() {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == window.id() && !state.input(event) => {
+            } if window_id == window.id()
+                && !gui.wants_keyboard_event(event)
+                && !state.input(event) => {
                 match event {
                     #[cfg(not(target_arch="wasm32"))]
                     WindowEvent::CloseRequested
@@ -232,5 +1236,62 @@ pub fn run() {
 fn get_obstacles() -> Vec<CollidableMesh> {
     let (vertex_positions, indices) = graphics::forms::get_cube_interior_normals_vertices();
     let vertex_positions = vertex_positions.iter().map(|v| v * 2.0).collect_vec();
-    vec![CollidableMesh::new(vertex_positions, indices)]
+    // The walls only face inwards, so bodies which sink into them are pushed back into the room.
+    vec![CollidableMesh::new(vertex_positions, indices).with_sidedness(Sidedness::OneSided)]
+}
+
+/// The dimensions of the box for the animated obstacle, and its animation, unless it's off.
+fn get_animation(animated_obstacle: AnimatedObstacle) -> Option<(Vector3<f32>, Animation)> {
+    let transform = |x, y, z| Transform {
+        position: Vector3::new(x, y, z),
+        ..Default::default()
+    };
+    match animated_obstacle {
+        AnimatedObstacle::Off => None,
+        AnimatedObstacle::Platform => Some((
+            Vector3::new(1.2, 0.1, 1.2),
+            Animation::oscillate(
+                transform(0.0, -0.9, 0.0),
+                Vector3::unit_y() * 0.6,
+                ANIMATION_PERIOD,
+            ),
+        )),
+        AnimatedObstacle::Paddle => Some((
+            Vector3::new(1.8, 0.3, 0.1),
+            Animation::spin(
+                transform(0.0, -0.8, 0.0),
+                Vector3::unit_y(),
+                ANIMATION_PERIOD,
+            ),
+        )),
+        AnimatedObstacle::Wall => Some((
+            Vector3::new(0.1, 2.0, 2.0),
+            Animation::oscillate(
+                transform(-0.9, 0.0, 0.0),
+                Vector3::unit_x() * 0.6,
+                ANIMATION_PERIOD,
+            ),
+        )),
+    }
+}
+
+/// The rigidbodies' handles, in the order of their instances.
+fn get_rigidbody_handles(simulation: &Simulation) -> impl Iterator<Item = RigidBodyHandle> + '_ {
+    simulation
+        .get_rigidbodies()
+        .iter()
+        .map(|(handle, _)| handle)
+}
+
+fn get_rigidbody_instances(simulation: &Simulation) -> Vec<Instance> {
+    simulation
+        .get_rigidbodies()
+        .values()
+        .map(|rigidbody| Instance {
+            position: rigidbody.get_position(),
+            rotation: rigidbody.get_rotation(),
+            scale: rigidbody.get_dimensions(),
+            ..Default::default()
+        })
+        .collect_vec()
 }
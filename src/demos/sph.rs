@@ -1,13 +1,30 @@
 /// A demo of the spring-mass-damper simulation.
 use super::utils;
+use crate::simulation::configurable::Configurable;
 use crate::{
+    args::WindowArgs,
     graphics::{
-        self, camera::CameraBundle, entity::ColoredMeshEntity, forms, gpu_interface::GPUInterface,
-        instance::Instance, light, model::ColoredMesh, texture,
+        self,
+        camera::CameraBundle,
+        depth_sort::{DepthSortPipeline, GpuDepthSort},
+        entity::ColoredMeshEntity,
+        fluid_surface::FluidSurface,
+        forms,
+        gpu_interface::GPUInterface,
+        instance::{self, Instance},
+        light,
+        model::ColoredMesh,
+        palette::{self, Color, Theme},
+        settings::{GraphicsSettings, TransparencyMode},
+        texture, transparency,
+    },
+    gui::{
+        self,
+        help::{Help, Parameter},
     },
-    gui,
     simulation::collidable_mesh::CollidableMesh,
-    simulation::sph::Simulation,
+    simulation::container::Container,
+    simulation::sph::{Phase, Simulation},
 };
 
 use cgmath::{Rotation3, Vector3};
@@ -16,19 +33,46 @@ use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
 };
 
+/// The radius of the particles, as drawn.
+const PARTICLE_RADIUS: f32 = 0.05;
+
+/// The most sponges a scenario may have.
+const MAX_SPONGES: usize = 4;
+/// The most steps taken to catch up with a frame. Time past them is dropped, slowing the fluid rather than
+/// freezing the demo.
+const MAX_STEPS_PER_FRAME: usize = 64;
+
 struct State {
     gpu: GPUInterface,
     render_pipeline: wgpu::RenderPipeline,
+    /// Writes the scene's depth ahead of render_pipeline, if the depth pre-pass is enabled.
+    depth_prepass_pipeline: Option<wgpu::RenderPipeline>,
+    /// Draws the particles as transparent into the accumulation targets, if order-independent
+    /// transparency is enabled. The particles are drawn by render_pipeline otherwise.
+    oit_particle_pipeline: Option<wgpu::RenderPipeline>,
+    /// Draws the particles as transparent and alpha blended, from depth_sort's sorted instances,
+    /// if sorted transparency is enabled.
+    sorted_particle_pipeline: Option<wgpu::RenderPipeline>,
+    depth_sort_pipeline: DepthSortPipeline,
+    /// Sorts the particles back to front each frame for sorted_particle_pipeline.
+    depth_sort: GpuDepthSort,
+    /// Draws the particles as a liquid surface in place of the other pipelines, if enabled.
+    fluid_surface: FluidSurface,
+    liquid_surface: bool,
+    color_by_phase: bool,
     depth_texture: texture::Texture,
     camera_bundle: CameraBundle,
     light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     mouse_pressed: bool,
     time_accumulator: std::time::Duration,
     simulation: Simulation,
     particles_entity: ColoredMeshEntity,
+    sponge_entity: ColoredMeshEntity,
+    /// The colors of the particles and obstacle.
+    theme: Theme,
 }
 
 impl State {
@@ -36,48 +80,79 @@ impl State {
         let gpu: GPUInterface = GPUInterface::new(&window);
         let camera_bundle =
             CameraBundle::new(&gpu, (0.0, 0.0, 9.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
-        let depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth texture");
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
 
         let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
         let (light_bind_group_layout, light_bind_group) =
             light::create_light_bind_group(&gpu, light_uniform);
 
-        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
-            &gpu,
-            &camera_bundle,
-            &light_bind_group_layout,
-        );
+        let (render_pipeline, depth_prepass_pipeline) =
+            graphics::util::create_colored_mesh_render_pipelines(
+                &gpu,
+                &camera_bundle,
+                &light_bind_group_layout,
+            );
+        let oit_particle_pipeline =
+            create_oit_particle_pipeline(&gpu, &camera_bundle, &light_bind_group_layout);
+        let sorted_particle_pipeline =
+            create_sorted_particle_pipeline(&gpu, &camera_bundle, &light_bind_group_layout);
+        let fluid_surface = FluidSurface::new(&gpu, &light_bind_group_layout, PARTICLE_RADIUS);
 
-        let min_bounds = Vector3::new(-0.75, -0.75, -0.75);
-        let max_bounds = Vector3::new(0.75, 0.75, 0.75);
-        let simulation = Simulation::new(min_bounds, max_bounds);
+        let simulation = Simulation::new();
 
-        let sphere = forms::generate_sphere(&gpu.device, [0.9, 0.1, 0.1], 0.05, 16, 16);
+        let theme = Theme::default();
+        let sphere = forms::generate_sphere(&gpu.device, theme.body, PARTICLE_RADIUS, 16, 16);
         let particles = simulation.get_particles();
         let particle_instances = particles
             .iter()
             .map(|p| Instance {
-                position: *p.position(),
+                position: p.position(),
                 rotation: cgmath::Quaternion::from_axis_angle(
                     cgmath::Vector3::unit_z(),
                     cgmath::Deg(0.0),
                 ),
-                scale: 1.0,
+                scale: Vector3::new(1.0, 1.0, 1.0),
+                color: None,
             })
             .collect_vec();
+        let particle_count = particle_instances.len();
         let particles_entity = ColoredMeshEntity::new(&gpu, sphere, particle_instances, None);
+        let depth_sort_pipeline = DepthSortPipeline::new(&gpu);
+        let depth_sort = GpuDepthSort::new(
+            &gpu,
+            &depth_sort_pipeline,
+            &particles_entity,
+            particle_count,
+        );
+        let sponge = forms::get_cube(&gpu.device, theme.obstacle);
+        let sponge_entity = ColoredMeshEntity::new(&gpu, sponge, Vec::new(), Some(MAX_SPONGES));
 
         Self {
             gpu,
             render_pipeline,
+            depth_prepass_pipeline,
+            oit_particle_pipeline,
+            sorted_particle_pipeline,
+            depth_sort_pipeline,
+            depth_sort,
+            fluid_surface,
+            liquid_surface: false,
+            color_by_phase: false,
             depth_texture,
             camera_bundle,
             light_bind_group,
+            light_bind_group_layout,
             mouse_pressed: false,
             time_accumulator: std::time::Duration::from_millis(0),
             simulation,
             particles_entity,
+            sponge_entity,
+            theme,
         }
     }
 
@@ -90,6 +165,50 @@ impl State {
         );
     }
 
+    /// Recolors the meshes with the theme.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.particles_entity.set_color(&self.gpu, theme.body);
+        // The obstacle is recreated each frame with the theme's colors.
+        self.theme = theme;
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let depth_prepass_changed = settings.depth_prepass != self.gpu.settings.depth_prepass;
+        let transparency_changed =
+            settings.transparency_mode != self.gpu.settings.transparency_mode;
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed || depth_prepass_changed {
+            (self.render_pipeline, self.depth_prepass_pipeline) =
+                graphics::util::create_colored_mesh_render_pipelines(
+                    &self.gpu,
+                    &self.camera_bundle,
+                    &self.light_bind_group_layout,
+                );
+        }
+        if msaa_changed || transparency_changed {
+            self.oit_particle_pipeline = create_oit_particle_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.sorted_particle_pipeline = create_sorted_particle_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+        }
+        if msaa_changed {
+            self.fluid_surface =
+                FluidSurface::new(&self.gpu, &self.light_bind_group_layout, PARTICLE_RADIUS);
+        }
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
         utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
     }
@@ -97,10 +216,18 @@ impl State {
     fn update(&mut self, frame_time: std::time::Duration) {
         self.time_accumulator = self.time_accumulator + frame_time;
         self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
 
+        let mut steps = 0;
         while self.time_accumulator >= self.simulation.get_timestep() {
+            // An unstable fluid's adaptive timestep shrinks toward its minimum, taking ever more steps to keep up.
+            if steps == MAX_STEPS_PER_FRAME {
+                self.time_accumulator = std::time::Duration::ZERO;
+                break;
+            }
             let elapsed_sim_time = self.simulation.step();
             self.time_accumulator = self.time_accumulator - elapsed_sim_time;
+            steps += 1;
         }
     }
 
@@ -117,79 +244,363 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        let obstacles = get_obstacles();
+        let obstacles = get_obstacles(self.simulation.get_container());
         let obstacle_mesh = ColoredMesh::from_collidable_mesh(
             &self.gpu.device,
             "floor".to_string(),
             &obstacles[0],
-            [0.1, 0.9, 0.1],
+            self.theme.obstacle,
         );
         let obstacle_instances = vec![Instance::default()];
         let obstacle_entity =
             ColoredMeshEntity::new(&self.gpu, obstacle_mesh, obstacle_instances, None);
 
+        let sponge_instances = self
+            .simulation
+            .get_sponges()
+            .iter()
+            .take(MAX_SPONGES)
+            .map(|sponge| Instance {
+                position: (sponge.min() + sponge.max()) / 2.0,
+                scale: sponge.max() - sponge.min(),
+                color: Some(sponge_color(
+                    self.theme.obstacle,
+                    sponge.saturation(self.simulation.get_config()),
+                )),
+                ..Default::default()
+            })
+            .collect_vec();
+        self.sponge_entity
+            .update_instances(&self.gpu, sponge_instances);
+
         let particles = self.simulation.get_particles();
-        let particle_instances = particles
+        let mut particle_instances = particles
             .iter()
             .map(|p| Instance {
-                position: *p.position(),
+                position: p.position(),
                 rotation: cgmath::Quaternion::from_axis_angle(
                     cgmath::Vector3::unit_z(),
                     cgmath::Deg(0.0),
                 ),
-                scale: 1.0,
+                scale: Vector3::new(1.0, 1.0, 1.0),
+                color: if self.color_by_phase {
+                    Some(phase_color(p.phase()))
+                } else {
+                    None
+                },
             })
             .collect_vec();
+        instance::sort_front_to_back(&mut particle_instances, self.camera_bundle.camera.position);
         self.particles_entity
             .update_instances(&self.gpu, particle_instances);
 
         // TODO get other data from simulation to update Instance data to e.g. color by density, pressure, velocity, curl, etc.
         //         That might be a function that takes an Enum for DataRequest and returns a color for it in the simulation, or something.
 
-        {
+        // The liquid surface replaces drawing the particles, whether opaque or transparent.
+        let oit_particle_pipeline = self
+            .oit_particle_pipeline
+            .as_ref()
+            .filter(|_| !self.liquid_surface);
+        let sorted_particle_pipeline = self
+            .sorted_particle_pipeline
+            .as_ref()
+            .filter(|_| !self.liquid_surface);
+        let draw_particle_meshes = !self.liquid_surface
+            && oit_particle_pipeline.is_none()
+            && sorted_particle_pipeline.is_none();
+        if sorted_particle_pipeline.is_some() {
+            self.depth_sort.dispatch(
+                &self.gpu,
+                &mut encoder,
+                &self.depth_sort_pipeline,
+                self.particles_entity.instances().len(),
+                self.camera_bundle.camera.position,
+            );
+        }
+        if self.liquid_surface {
+            self.fluid_surface.encode(
+                &mut encoder,
+                &self.gpu,
+                &self.camera_bundle,
+                &self.particles_entity,
+            );
+        }
+
+        if let Some(depth_prepass_pipeline) = &self.depth_prepass_pipeline {
             let mut render_pass =
-                utils::begin_default_render_pass(&mut encoder, &view, &self.depth_texture.view);
+                utils::begin_depth_prepass(&mut encoder, &self.depth_texture.view);
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(depth_prepass_pipeline);
+                obstacle_entity.draw(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                self.sponge_entity.draw(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                if draw_particle_meshes {
+                    self.particles_entity.draw(
+                        &mut render_pass,
+                        &camera_view.camera_bind_group,
+                        &self.light_bind_group,
+                    );
+                }
+            }
+        }
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            obstacle_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            self.particles_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
+        {
+            let begin_render_pass = if self.depth_prepass_pipeline.is_some() {
+                utils::begin_render_pass_after_depth_prepass
+            } else {
+                utils::begin_default_render_pass
+            };
+            let mut render_pass = begin_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
             );
+
+            for (view_index, camera_view) in self.camera_bundle.views.iter().enumerate() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                obstacle_entity.draw(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                self.sponge_entity.draw(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                if draw_particle_meshes {
+                    self.particles_entity.draw(
+                        &mut render_pass,
+                        &camera_view.camera_bind_group,
+                        &self.light_bind_group,
+                    );
+                }
+                if self.liquid_surface {
+                    self.fluid_surface
+                        .draw(&mut render_pass, view_index, &self.light_bind_group);
+                }
+                // Drawn last, blending over the opaque geometry from back to front.
+                if let Some(sorted_particle_pipeline) = sorted_particle_pipeline {
+                    render_pass.set_pipeline(sorted_particle_pipeline);
+                    self.particles_entity.draw_from_instance_buffer(
+                        &mut render_pass,
+                        self.depth_sort.sorted_instance_buffer(),
+                        &camera_view.camera_bind_group,
+                        &self.light_bind_group,
+                    );
+                }
+            }
+        }
+
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        if let (Some(oit_particle_pipeline), Some(oit_targets)) =
+            (oit_particle_pipeline, self.gpu.oit_targets())
+        {
+            {
+                let mut render_pass = transparency::begin_accumulation_pass(
+                    &mut encoder,
+                    oit_targets,
+                    &self.depth_texture.view,
+                );
+                for camera_view in self.camera_bundle.views.iter() {
+                    camera_view.set_viewport(&mut render_pass, &self.gpu);
+                    render_pass.set_pipeline(oit_particle_pipeline);
+                    self.particles_entity.draw(
+                        &mut render_pass,
+                        &camera_view.camera_bind_group,
+                        &self.light_bind_group,
+                    );
+                }
+            }
+            self.gpu.composite_transparency(&mut encoder, &view);
         }
 
+        self.gpu.upsample(&mut encoder, &view);
+
         encoder.finish()
     }
 }
 
-pub fn run() {
+/// The pipeline drawing transparent particles, if the transparency mode calls for one.
+fn create_oit_particle_pipeline(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+    light_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Option<wgpu::RenderPipeline> {
+    match gpu.settings.transparency_mode {
+        TransparencyMode::Opaque | TransparencyMode::Sorted => None,
+        TransparencyMode::WeightedBlended => Some(transparency::create_particle_pipeline(
+            gpu,
+            camera_bundle,
+            light_bind_group_layout,
+        )),
+    }
+}
+
+/// The pipeline drawing sorted transparent particles, if the transparency mode calls for one.
+fn create_sorted_particle_pipeline(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+    light_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Option<wgpu::RenderPipeline> {
+    match gpu.settings.transparency_mode {
+        TransparencyMode::Sorted => Some(transparency::create_sorted_particle_pipeline(
+            gpu,
+            camera_bundle,
+            light_bind_group_layout,
+        )),
+        TransparencyMode::Opaque | TransparencyMode::WeightedBlended => None,
+    }
+}
+
+fn help() -> Help {
+    Help {
+        title: "SPH",
+        description: "A fluid of particles simulated with smoothed-particle hydrodynamics, sloshing in a container whose walls can be moved to compress it.",
+        parameters: &[
+        Parameter {
+            name: "Scenario",
+            description: "The initial fluid; a block of water, oil beneath water which separate as the lighter oil rises, water falling onto a sponge which soaks it up and drips once saturated, or a ball of water seeded by voxelizing a sphere mesh.",
+        },
+        Parameter {
+            name: "Integration",
+            description: "The numerical integration method; RK4 is more accurate and stable than Euler, but slower. Velocity Verlet and leapfrog neither gain nor lose energy over long runs.",
+        },
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Adaptive Timestep (CFL)",
+            description: "Shortens each step so no particle moves more than the CFL number's fraction of the kernel radius, keeping the fluid stable as parameters change. Simulation dt becomes the maximum step.",
+        },
+        Parameter {
+            name: "CFL Number",
+            description: "The fraction of the kernel radius a particle may move in one step; smaller is more stable but slower.",
+        },
+        Parameter {
+            name: "Particle Mass",
+            description: "The mass of each fluid particle.",
+        },
+        Parameter {
+            name: "Kernal Max Dist",
+            description: "The smoothing radius, beyond which particles do not affect each other.",
+        },
+        Parameter {
+            name: "Pressure Stiffness",
+            description: "How strongly particles push apart when compressed beyond the reference density.",
+        },
+        Parameter {
+            name: "Reference Density",
+            description: "The density the fluid settles toward at rest.",
+        },
+        Parameter {
+            name: "Oil Reference Density",
+            description: "The density the oil settles toward at rest; oil lighter than the water rises above it.",
+        },
+        Parameter {
+            name: "Interface Tension",
+            description: "How strongly oil and water particles repel each other, keeping the phases apart.",
+        },
+        Parameter {
+            name: "Gravity",
+            description: "The acceleration of each particle.",
+        },
+        Parameter {
+            name: "Sponge Capacity",
+            description: "The number of particles the sponge holds before it is saturated and starts to drip.",
+        },
+        Parameter {
+            name: "Absorption Rate",
+            description: "How many particles per second the sponge soaks up from those passing through it.",
+        },
+        Parameter {
+            name: "Drip Rate",
+            description: "How many particles per second a saturated sponge drips from its underside.",
+        },
+        Parameter {
+            name: "Sponge Resistance",
+            description: "How strongly the sponge slows the fluid flowing through it.",
+        },
+        Parameter {
+            name: "Container",
+            description: "The shape and size of the container. Its walls move toward the size set at a limited speed, pushing the particles they pass inward like a piston.",
+        },
+        Parameter {
+            name: "Restitution",
+            description: "The fraction of the normal velocity kept after a particle hits a wall.",
+        },
+        Parameter {
+            name: "Friction",
+            description: "How much tangential velocity is lost when a particle hits a wall.",
+        },
+        Parameter {
+            name: "Liquid Surface",
+            description: "Renders the particles as a smooth liquid surface refracting and reflecting the sky, rather than as spheres.",
+        },
+        Parameter {
+            name: "Color by Phase",
+            description: "Colors water particles blue and oil particles yellow.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS.to_vec(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
     env_logger::init();
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = utils::build_window(&event_loop, window_args);
 
     let mut state = State::new(&window);
 
-    let mut gui = gui::Gui::new(&state.gpu.device, &state.gpu.config, &window);
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
     let mut ui = gui::sph::SphUi::new();
 
     let mut current_time = std::time::SystemTime::now();
     event_loop.run(move |event, _, control_flow| {
         gui.handle_events(&event);
 
-        *control_flow = ControlFlow::Poll;
+        *control_flow = utils::control_flow(&state.gpu);
         match event {
             Event::MainEventsCleared => {
                 let new_time = std::time::SystemTime::now();
                 let frame_time = new_time.duration_since(current_time).unwrap();
                 current_time = new_time;
-                state.update(frame_time);
-                state.simulation.sync_sim_from_ui(&mut ui);
-                let output = state.gpu.surface.get_current_texture().unwrap();
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                ui.set_timestep(state.simulation.timestep());
+                state.liquid_surface = ui.get_liquid_surface();
+                state.color_by_phase = ui.get_color_by_phase();
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
                 let simulation_render_command_buffer = state.render(&output);
                 let gui_render_command_buffer = gui.render(
                     &mut ui,
@@ -201,8 +612,21 @@ pub fn run() {
                     &output
                 );
 
-                state.gpu.queue.submit([simulation_render_command_buffer, gui_render_command_buffer]);
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
                 output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
             }
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
@@ -240,8 +664,20 @@ pub fn run() {
     });
 }
 
-fn get_obstacles() -> Vec<CollidableMesh> {
-    let (vertex_positions, indices) = graphics::forms::get_cube_interior_normals_vertices();
-    let vertex_positions = vertex_positions.iter().map(|v| v * 0.75).collect_vec();
+/// Darkens the sponge's color as it soaks up the fluid.
+fn sponge_color(color: Color, saturation: f32) -> Color {
+    color.map(|channel| channel * (1.0 - 0.6 * saturation))
+}
+
+/// The colors of the phases, which are distinct with color vision deficiencies.
+fn phase_color(phase: Phase) -> Color {
+    match phase {
+        Phase::Water => palette::CATEGORICAL[4],
+        Phase::Oil => palette::CATEGORICAL[3],
+    }
+}
+
+fn get_obstacles(container: &Container) -> Vec<CollidableMesh> {
+    let (vertex_positions, indices) = container.get_mesh();
     vec![CollidableMesh::new(vertex_positions, indices)]
 }
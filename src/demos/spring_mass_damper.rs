@@ -1,11 +1,28 @@
 /// A demo of the spring-mass-damper simulation.
+use crate::simulation::configurable::Configurable;
 use crate::{
+    args::WindowArgs,
     graphics::{
-        self, camera::CameraBundle, entity::ColoredMeshEntity, forms, gpu_interface::GPUInterface,
-        instance::Instance, light, model::ColoredMesh, scene::Scene, texture,
+        self,
+        camera::CameraBundle,
+        entity::ColoredMeshEntity,
+        forms,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light,
+        model::ColoredMesh,
+        palette::Theme,
+        scene::{ColoredMeshEntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, Parameter},
     },
-    gui,
     simulation::collidable_mesh::CollidableMesh,
+    simulation::fitting::{self, Fit, FitParameter, Trajectory},
+    simulation::quantity::{self, Damping, Stiffness},
     simulation::springy::simulation::Simulation,
     simulation::springy::springy_mesh::{self, SpringyMesh},
 };
@@ -15,7 +32,6 @@ use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
 };
 
 use super::utils;
@@ -27,9 +43,17 @@ struct State {
     depth_texture: texture::Texture,
     camera_bundle: CameraBundle,
     light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     scene: Scene,
+    springy_entity: ColoredMeshEntityHandle,
+    obstacle_entity: ColoredMeshEntityHandle,
+    rain_entity: ColoredMeshEntityHandle,
+    fluid_surface_entity: ColoredMeshEntityHandle,
+    fan_entity: ColoredMeshEntityHandle,
     mouse_pressed: bool,
     time_accumulator: std::time::Duration,
+    // The CPU time of the last frame's upload of the springy mesh's vertices.
+    upload_time: std::time::Duration,
 }
 
 impl State {
@@ -37,8 +61,12 @@ impl State {
         let gpu: GPUInterface = GPUInterface::new(&window);
         let camera_bundle =
             CameraBundle::new(&gpu, (0.0, 0.0, 5.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
-        let depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth texture");
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
 
         let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
         let (light_bind_group_layout, light_bind_group) =
@@ -50,13 +78,42 @@ impl State {
             &light_bind_group_layout,
         );
 
-        let springy_cube = get_springy_cube();
+        let springy_cube = get_springy_cube(
+            Vector3::zero(),
+            springy_mesh::STRUT_STIFFNESS_DEFAULT,
+            springy_mesh::STRUT_DAMPING_DEFAULT,
+        );
         let obstacles = get_obstacles();
         let simulation = Simulation::new(vec![springy_cube], obstacles);
 
-        // Note we're keeping the scene around since we'll probably have some static obstacles that we'd like to draw
-        // for the springy mesh to interact with.
-        let scene = Scene::new(None, None, None);
+        // TODO handle rendering *all* springy meshes in simulation
+        let theme = Theme::default();
+        let springy_mesh = ColoredMesh::from_springy_mesh(
+            &gpu.device,
+            "springy cube".to_string(),
+            &simulation.get_meshes()[0],
+            theme.body,
+        );
+        let springy_instances = vec![Instance::default()];
+        let springy_entity = ColoredMeshEntity::new(&gpu, springy_mesh, springy_instances, None);
+
+        // TODO handle rendering *all* obstacles in simulation
+        let obstacle_mesh = ColoredMesh::from_collidable_mesh(
+            &gpu.device,
+            "floor".to_string(),
+            &simulation.get_obstacles()[0],
+            theme.obstacle,
+        );
+        let obstacle_instances = vec![Instance::default()];
+        let obstacle_entity = ColoredMeshEntity::new(&gpu, obstacle_mesh, obstacle_instances, None);
+
+        let mut scene = Scene::new();
+        let springy_entity = scene.add_colored_mesh_entity(springy_entity);
+        let obstacle_entity = scene.add_colored_mesh_entity(obstacle_entity);
+        let rain_entity = scene.add_colored_mesh_entity(utils::create_rain_entity(&gpu));
+        let fluid_surface_entity =
+            scene.add_colored_mesh_entity(utils::create_fluid_surface_entity(&gpu));
+        let fan_entity = scene.add_colored_mesh_entity(utils::create_fan_entity(&gpu));
 
         Self {
             simulation,
@@ -65,9 +122,16 @@ impl State {
             depth_texture,
             camera_bundle,
             light_bind_group,
+            light_bind_group_layout,
             scene,
+            springy_entity,
+            obstacle_entity,
+            rain_entity,
+            fluid_surface_entity,
+            fan_entity,
             mouse_pressed: false,
             time_accumulator: std::time::Duration::from_millis(0),
+            upload_time: std::time::Duration::ZERO,
         }
     }
 
@@ -80,6 +144,31 @@ impl State {
         );
     }
 
+    /// Recolors the meshes with the theme.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.springy_entity, theme.body);
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.obstacle_entity, theme.obstacle);
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+        }
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
         utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
     }
@@ -87,11 +176,38 @@ impl State {
     fn update(&mut self, frame_time: std::time::Duration) {
         self.time_accumulator = self.time_accumulator + frame_time;
         self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
 
         while self.time_accumulator >= self.simulation.get_timestep() {
             let elapsed_sim_time = self.simulation.step();
             self.time_accumulator = self.time_accumulator - elapsed_sim_time;
         }
+
+        let upload_start = std::time::Instant::now();
+        let (vertex_positions, _) = self.simulation.get_meshes()[0].get_vertices();
+        if let Err(err) = self.scene.update_colored_mesh_entity_vertices(
+            &self.gpu,
+            self.springy_entity,
+            vertex_positions,
+        ) {
+            log::warn!("Failed to update the springy mesh: {}", err);
+        }
+        self.upload_time = upload_start.elapsed();
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.rain_entity,
+            utils::get_rain_instances(self.simulation.get_rain()),
+        );
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.fluid_surface_entity,
+            utils::get_fluid_surface_instances(self.simulation.get_config()),
+        );
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.fan_entity,
+            utils::get_fan_instances(self.simulation.get_fan()),
+        );
     }
 
     fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
@@ -107,77 +223,153 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        // TODO handle rendering *all* springy meshes in simulation
-        let cube_mesh = ColoredMesh::from_springy_mesh(
-            &self.gpu.device,
-            "springy cube".to_string(),
-            &self.simulation.get_meshes()[0],
-            [0.9, 0.1, 0.1],
-        );
-        let cube_instances = vec![Instance::default()];
-        let cube_entity = ColoredMeshEntity::new(&self.gpu, cube_mesh, cube_instances, None);
-
-        // TODO handle rendering *all* obstacles in simulation
-        let obstacle_mesh = ColoredMesh::from_collidable_mesh(
-            &self.gpu.device,
-            "floor".to_string(),
-            &self.simulation.get_obstacles()[0],
-            [0.1, 0.9, 0.1],
-        );
-        let obstacle_instances = vec![Instance::default()];
-        let obstacle_entity =
-            ColoredMeshEntity::new(&self.gpu, obstacle_mesh, obstacle_instances, None);
-
         {
-            let mut render_pass =
-                utils::begin_default_render_pass(&mut encoder, &view, &self.depth_texture.view);
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            self.scene.draw_colored_mesh_entities(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            cube_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
-            );
-            obstacle_entity.draw(
-                &mut render_pass,
-                &self.camera_bundle.camera_bind_group,
-                &self.light_bind_group,
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
             );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
         }
 
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
         encoder.finish()
     }
 }
 
-pub fn run() {
+fn help() -> Help {
+    Help {
+        title: "Spring-Mass-Damper",
+        description: "A cube of point masses connected by damped springs, falling onto an obstacle.",
+        parameters: &[
+        Parameter {
+            name: "Integration",
+            description: "The numerical integration method; RK4 is more accurate and stable than Euler, but slower. Implicit Euler stays stable with stiff struts at much larger timesteps, at the cost of damping their motion. Velocity Verlet and leapfrog neither gain nor lose energy over long runs. RK45 estimates each step's error, so that dt can adapt to it.",
+        },
+        Parameter {
+            name: "Adaptive dt",
+            description: "Adapts dt to RK45's error estimate after each step, within the min and max dt: shrinking it while the motion's hard to follow, and growing it while it's easy. A lower error tolerance takes smaller steps.",
+        },
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Gravity",
+            description: "The acceleration of each point mass.",
+        },
+        Parameter {
+            name: "Wind",
+            description: "The velocity of the air the mesh moves through.",
+        },
+        Parameter {
+            name: "Fan",
+            description: "Blows a jet of wind where it's aimed, which is stronger the faster its blades spin, and spreads and weakens away from the fan.",
+        },
+        Parameter {
+            name: "Drag",
+            description: "Air resistance on the faces of the mesh, opposing their velocity relative to the wind.",
+        },
+        Parameter {
+            name: "Lift",
+            description: "Force on the faces of the mesh perpendicular to their velocity relative to the wind.",
+        },
+        Parameter {
+            name: "Restitution",
+            description: "The fraction of the normal velocity kept after a point collides with the obstacle.",
+        },
+        Parameter {
+            name: "Friction",
+            description: "How much tangential velocity is lost when a point collides with the obstacle.",
+        },
+        Parameter {
+            name: "Floor Conveyor",
+            description: "The velocity the floor's surface slides at, like a conveyor belt's, which friction drags the points resting on it towards.",
+        },
+        Parameter {
+            name: "Rain",
+            description: "Drops of rain falling per second. Drops push the faces they hit, then splash.",
+        },
+        Parameter {
+            name: "Drop Mass",
+            description: "The mass of each drop; heavier drops push harder on the faces they hit.",
+        },
+        Parameter {
+            name: "Fluid",
+            description: "Submerges the mesh below a surface in a still fluid, which buoys its faces and resists their motion.",
+        },
+        Parameter {
+            name: "Fluid Density",
+            description: "The density of the fluid; lift and drag in the fluid are scaled from the air's by the ratio of their densities.",
+        },
+        Parameter {
+            name: "Face Thickness",
+            description: "The thickness of the mesh's faces, which determines how much fluid they displace.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS.to_vec(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
     env_logger::init();
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = utils::build_window(&event_loop, window_args);
 
     let mut state = State::new(&window);
 
-    let mut gui = gui::Gui::new(&state.gpu.device, &state.gpu.config, &window);
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
     let mut ui = gui::spring_mass_damper::SpringMassDamperUi::new();
 
     let mut current_time = std::time::SystemTime::now();
     event_loop.run(move |event, _, control_flow| {
         gui.handle_events(&event);
 
-        *control_flow = ControlFlow::Poll;
+        *control_flow = utils::control_flow(&state.gpu);
         match event {
             Event::MainEventsCleared => {
                 let new_time = std::time::SystemTime::now();
                 let frame_time = new_time.duration_since(current_time).unwrap();
                 current_time = new_time;
-                state.update(frame_time);
-                state.simulation.sync_sim_config_from_ui(&mut ui);
-                let output = state.gpu.surface.get_current_texture().unwrap();
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                // The floor is the first obstacle.
+                state
+                    .simulation
+                    .set_obstacle_surface_velocity(0, ui.get_floor_surface_velocity());
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
+                let render_start = std::time::Instant::now();
                 let simulation_render_command_buffer = state.render(&output);
+                gui.record_render_cpu_time(state.upload_time + render_start.elapsed());
                 let gui_render_command_buffer = gui.render(
                     &mut ui,
                     frame_time,
@@ -188,8 +380,21 @@ pub fn run() {
                     &output
                 );
 
-                state.gpu.queue.submit([simulation_render_command_buffer, gui_render_command_buffer]);
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
                 output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
             }
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
@@ -227,47 +432,32 @@ pub fn run() {
     });
 }
 
-fn get_springy_cube() -> springy_mesh::SpringyMesh {
+/// The unit cube, centered at the center, with struts of the stiffness and damping.
+fn get_springy_cube(
+    center: Vector3<f32>,
+    stiffness: Stiffness,
+    damping: Damping,
+) -> springy_mesh::SpringyMesh {
     let (vertex_positions, indices) = forms::get_cube_vertices();
+    let vertex_positions = vertex_positions
+        .into_iter()
+        .map(|position| position + center)
+        .collect();
     let mut cube = SpringyMesh::new(
         vertex_positions,
         indices,
-        8.0 * 10.0,
-        springy_mesh::STRUT_STIFFNESS_DEFAULT,
-        springy_mesh::STRUT_DAMPING_DEFAULT,
+        quantity::kilograms(8.0 * 10.0),
+        stiffness,
+        damping,
         Some(springy_mesh::TorsionalSpringConfig::default()),
         &None,
     );
-    cube.add_strut(
-        (1, 3),
-        springy_mesh::STRUT_STIFFNESS_DEFAULT,
-        springy_mesh::STRUT_DAMPING_DEFAULT,
-    );
-    cube.add_strut(
-        (2, 5),
-        springy_mesh::STRUT_STIFFNESS_DEFAULT,
-        springy_mesh::STRUT_DAMPING_DEFAULT,
-    );
-    cube.add_strut(
-        (4, 6),
-        springy_mesh::STRUT_STIFFNESS_DEFAULT,
-        springy_mesh::STRUT_DAMPING_DEFAULT,
-    );
-    cube.add_strut(
-        (0, 7),
-        springy_mesh::STRUT_STIFFNESS_DEFAULT,
-        springy_mesh::STRUT_DAMPING_DEFAULT,
-    );
-    cube.add_strut(
-        (0, 5),
-        springy_mesh::STRUT_STIFFNESS_DEFAULT,
-        springy_mesh::STRUT_DAMPING_DEFAULT,
-    );
-    cube.add_strut(
-        (2, 7),
-        springy_mesh::STRUT_STIFFNESS_DEFAULT,
-        springy_mesh::STRUT_DAMPING_DEFAULT,
-    );
+    cube.add_strut((1, 3), stiffness, damping);
+    cube.add_strut((2, 5), stiffness, damping);
+    cube.add_strut((4, 6), stiffness, damping);
+    cube.add_strut((0, 7), stiffness, damping);
+    cube.add_strut((0, 5), stiffness, damping);
+    cube.add_strut((2, 7), stiffness, damping);
     cube
 }
 
@@ -282,7 +472,7 @@ fn get_springy_tri() -> springy_mesh::SpringyMesh {
     SpringyMesh::new(
         vertex_positions,
         indices,
-        20.0,
+        quantity::kilograms(20.0),
         springy_mesh::STRUT_STIFFNESS_DEFAULT,
         springy_mesh::STRUT_DAMPING_DEFAULT,
         Some(springy_mesh::TorsionalSpringConfig::default()),
@@ -302,7 +492,7 @@ fn get_springy_quad() -> springy_mesh::SpringyMesh {
     SpringyMesh::new(
         vertex_positions,
         indices,
-        20.0,
+        quantity::kilograms(20.0),
         springy_mesh::STRUT_STIFFNESS_DEFAULT,
         springy_mesh::STRUT_DAMPING_DEFAULT,
         Some(springy_mesh::TorsionalSpringConfig::default()),
@@ -322,7 +512,7 @@ fn get_springy_bent_quad() -> springy_mesh::SpringyMesh {
     SpringyMesh::new(
         vertex_positions,
         indices,
-        20.0,
+        quantity::kilograms(20.0),
         springy_mesh::STRUT_STIFFNESS_DEFAULT,
         springy_mesh::STRUT_DAMPING_DEFAULT,
         Some(springy_mesh::TorsionalSpringConfig::default()),
@@ -330,6 +520,38 @@ fn get_springy_bent_quad() -> springy_mesh::SpringyMesh {
     )
 }
 
+/// The parameters fit to reference trajectories of the cube, in the order of their fitted values.
+pub(crate) const FIT_PARAMETERS: [FitParameter; 2] = [
+    FitParameter {
+        name: "Strut Stiffness",
+        min: 1000.0,
+        max: 2000000.0,
+    },
+    FitParameter {
+        name: "Strut Damping",
+        min: 0.0,
+        max: 2000.0,
+    },
+];
+
+/// Fits the stiffness and damping of the cube's struts to the reference trajectory of its center,
+/// dropped from rest at its first position onto the demo's floor.
+pub(crate) fn fit(reference: &Trajectory) -> Result<Fit, &'static str> {
+    fitting::fit(&FIT_PARAMETERS, reference, |values| {
+        let cube = get_springy_cube(
+            reference.positions[0],
+            quantity::newtons_per_meter(values[0]),
+            quantity::newton_seconds_per_meter(values[1]),
+        );
+        let mut simulation = Simulation::new(vec![cube], get_obstacles());
+        reference.sample(|| {
+            let elapsed = simulation.step().as_secs_f32();
+            let (min, max) = simulation.get_meshes()[0].get_bounds();
+            (elapsed, (min + max) / 2.0)
+        })
+    })
+}
+
 fn get_obstacles() -> Vec<CollidableMesh> {
     let vertex_positions = vec![
         -Vector3::<f32>::unit_x() + Vector3::<f32>::unit_z() - Vector3::<f32>::unit_y() * 2.0,
@@ -0,0 +1,598 @@
+/// A demo of a target range, stacks of blocks on a platform knocked down by projectiles from a slingshot.
+use crate::{
+    args::WindowArgs,
+    graphics::{
+        self,
+        camera::CameraBundle,
+        debug_draw::{self, DebugDraw},
+        entity::ColoredMeshEntity,
+        forms,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light,
+        palette::{Color, Theme},
+        scene::{ColoredMeshEntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, InputAction, Parameter},
+        measure::MeasureTools,
+    },
+    simulation::{
+        fitting::Trajectory,
+        target_range::{self, BodyKind, Simulation},
+    },
+};
+
+use cgmath::{InnerSpace, Vector3, Zero};
+use winit::{
+    dpi::PhysicalPosition,
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+use super::utils;
+
+/// Enough for the slingshot and its band, the measuring tools' points and gates, and the paths of the
+/// current flight and the ghost.
+const MAX_DEBUG_LINES: usize = 288;
+/// How far apart the slingshot's prongs are, either side of its pouch.
+const PRONG_HALF_WIDTH: f32 = 0.3;
+/// Sleeping blocks are drawn this much darker than awake ones.
+const SLEEP_DIMMING: f32 = 0.6;
+const MEASURE_POINT_SIZE: f32 = 0.2;
+/// The ghost's path is drawn this much darker than the current flight's.
+const GHOST_DIMMING: f32 = 0.4;
+const GHOST_MARKER_SIZE: f32 = 0.3;
+/// The stopwatch's gates are drawn up to this high over the ground.
+const GATE_HEIGHT: f32 = 4.0;
+
+struct State {
+    simulation: Simulation,
+    gpu: GPUInterface,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+    camera_bundle: CameraBundle,
+    light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    scene: Scene,
+    theme: Theme,
+    obstacle_entity: ColoredMeshEntityHandle,
+    block_entity: ColoredMeshEntityHandle,
+    projectile_entity: ColoredMeshEntityHandle,
+    debug_draw: DebugDraw,
+    mouse_pressed: bool,
+    cursor_position: PhysicalPosition<f64>,
+    // How far the slingshot's pouch is pulled back from rest, while it's being pulled.
+    pull: Option<Vector3<f32>>,
+    // Where the user last clicked to place a measuring tool's point, until it's placed.
+    measure_point: Option<Vector3<f32>>,
+    time_accumulator: std::time::Duration,
+}
+
+impl State {
+    fn new(window: &Window) -> Self {
+        let gpu: GPUInterface = GPUInterface::new(window);
+        let camera_bundle = CameraBundle::new(
+            &gpu,
+            (1.0, 2.0, 12.0),
+            cgmath::Deg(-90.0),
+            cgmath::Deg(-5.0),
+        );
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
+
+        let light_uniform = light::LightUniform::new([2.0, 6.0, 6.0], [1.0, 1.0, 1.0]);
+        let (light_bind_group_layout, light_bind_group) =
+            light::create_light_bind_group(&gpu, light_uniform);
+
+        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+            &gpu,
+            &camera_bundle,
+            &light_bind_group_layout,
+        );
+
+        let theme = Theme::default();
+        let simulation = Simulation::new();
+
+        let mut scene = Scene::new();
+        let obstacles = target_range::get_obstacle_boxes()
+            .into_iter()
+            .map(|(center, dimensions)| Instance {
+                position: center,
+                scale: dimensions,
+                ..Default::default()
+            })
+            .collect();
+        let obstacle_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::get_cube(&gpu.device, theme.obstacle),
+            obstacles,
+            None,
+        ));
+        let block_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::get_cube(&gpu.device, theme.body),
+            Vec::new(),
+            Some(target_range::get_max_block_count()),
+        ));
+        let projectile_entity = scene.add_colored_mesh_entity(ColoredMeshEntity::new(
+            &gpu,
+            forms::get_cube(&gpu.device, theme.accent),
+            Vec::new(),
+            Some(target_range::MAX_PROJECTILES),
+        ));
+        let debug_draw = DebugDraw::new(&gpu, &camera_bundle, MAX_DEBUG_LINES);
+
+        Self {
+            simulation,
+            gpu,
+            render_pipeline,
+            depth_texture,
+            camera_bundle,
+            light_bind_group,
+            light_bind_group_layout,
+            scene,
+            theme,
+            obstacle_entity,
+            block_entity,
+            projectile_entity,
+            debug_draw,
+            mouse_pressed: false,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            pull: None,
+            measure_point: None,
+            time_accumulator: std::time::Duration::from_millis(0),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        graphics::util::resize(
+            new_size,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle.projection,
+        );
+    }
+
+    /// The blocks and projectiles are colored by their instances, so they're recolored as they're next drawn.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.obstacle_entity, theme.obstacle);
+        self.theme = theme;
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+            self.debug_draw
+                .rebuild_pipeline(&self.gpu, &self.camera_bundle);
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                if self.pull.is_some() {
+                    self.pull = self.get_cursor_pull();
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state: ElementState::Pressed,
+                ..
+            } => {
+                self.pull = self.get_cursor_pull();
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state: ElementState::Released,
+                ..
+            } => {
+                // A release without any pull just lets go of the slingshot.
+                if let Some(pull) = self.pull.take() {
+                    self.simulation.launch(pull).ok();
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Middle,
+                state: ElementState::Pressed,
+                ..
+            } => {
+                self.measure_point = self.get_cursor_on_slingshot_plane();
+                true
+            }
+            _ => {
+                utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+            }
+        }
+    }
+
+    /// How far the slingshot's pouch is pulled back to the point where the ray through the cursor
+    /// crosses the slingshot's plane, at most the max pull, if it does cross it.
+    fn get_cursor_pull(&self) -> Option<Vector3<f32>> {
+        let pull = self.get_cursor_on_slingshot_plane()? - target_range::SLINGSHOT;
+        if pull.magnitude() > target_range::MAX_PULL {
+            Some(pull.normalize_to(target_range::MAX_PULL))
+        } else {
+            Some(pull)
+        }
+    }
+
+    /// Where the ray through the cursor crosses the slingshot's plane, which the projectiles fly in,
+    /// if it does.
+    fn get_cursor_on_slingshot_plane(&self) -> Option<Vector3<f32>> {
+        let (origin, direction) = self.camera_bundle.camera.cursor_ray(
+            &self.camera_bundle.projection,
+            self.cursor_position,
+            self.gpu.config.width,
+            self.gpu.config.height,
+        );
+        if direction.z.abs() < f32::EPSILON {
+            return None;
+        }
+        let distance = (target_range::SLINGSHOT.z - origin.z) / direction.z;
+        (distance > 0.0).then(|| origin + direction * distance)
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration) {
+        self.time_accumulator += frame_time;
+        self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
+
+        while self.time_accumulator >= self.simulation.get_timestep() {
+            let elapsed_sim_time = self.simulation.step();
+            self.time_accumulator -= elapsed_sim_time;
+        }
+
+        self.update_instances();
+        self.draw_slingshot();
+    }
+
+    /// Draws the slingshot's post and prongs, and its band to the pouch, pulled back while it's being pulled.
+    fn draw_slingshot(&mut self) {
+        let rest = target_range::SLINGSHOT;
+        let fork = Vector3::new(rest.x, rest.y - PRONG_HALF_WIDTH, rest.z);
+        let prongs = [
+            Vector3::new(rest.x - PRONG_HALF_WIDTH, rest.y, rest.z),
+            Vector3::new(rest.x + PRONG_HALF_WIDTH, rest.y, rest.z),
+        ];
+        let color = self.theme.obstacle;
+        self.debug_draw
+            .line(Vector3::new(rest.x, 0.0, rest.z), fork, color);
+        for prong in prongs {
+            self.debug_draw.line(fork, prong, color);
+        }
+        let pouch = rest + self.pull.unwrap_or_else(Vector3::zero);
+        for prong in prongs {
+            self.debug_draw.line(prong, pouch, self.theme.accent);
+        }
+    }
+
+    /// Draws the measurement's points and the lines between them, and the stopwatch's gates, while the
+    /// measuring tools are shown.
+    fn draw_measurement(&mut self, tools: &MeasureTools) {
+        if !tools.is_shown() {
+            return;
+        }
+        let points = tools.get_measurement().get_points();
+        for point in points {
+            self.debug_draw
+                .cross(*point, MEASURE_POINT_SIZE, debug_draw::YELLOW);
+        }
+        for pair in points.windows(2) {
+            self.debug_draw.line(pair[0], pair[1], debug_draw::YELLOW);
+        }
+        let (start_gate, stop_gate) = tools.get_gates();
+        let z = target_range::SLINGSHOT.z;
+        for (x, color) in [
+            (start_gate, debug_draw::GREEN),
+            (stop_gate, debug_draw::RED),
+        ] {
+            self.debug_draw.line(
+                Vector3::new(x, 0.0, z),
+                Vector3::new(x, GATE_HEIGHT, z),
+                color,
+            );
+        }
+    }
+
+    /// Draws the path of the most recent projectile's flight, and the ghost's path dimmed, with a marker
+    /// where the ghost was as far into its flight as the projectile is into its own.
+    fn draw_flights(&mut self, ghost: Option<&Trajectory>) {
+        let flight = self.simulation.get_flight();
+        let color = self.theme.accent;
+        if let Some(flight) = flight {
+            for pair in flight.get_trajectory().positions.windows(2) {
+                self.debug_draw.line(pair[0], pair[1], color);
+            }
+        }
+        if let Some(ghost) = ghost {
+            let color = color.map(|channel| channel * GHOST_DIMMING);
+            for pair in ghost.positions.windows(2) {
+                self.debug_draw.line(pair[0], pair[1], color);
+            }
+            if let Some(position) = flight.and_then(|flight| ghost.position_at(flight.get_time())) {
+                self.debug_draw.cross(position, GHOST_MARKER_SIZE, color);
+            }
+        }
+    }
+
+    fn update_instances(&mut self) {
+        let rigidbodies = self.simulation.get_rigidbodies();
+        let get_instance = |handle, color: Color| {
+            let rigidbody = &rigidbodies[handle];
+            Instance {
+                position: rigidbody.get_position(),
+                rotation: rigidbody.get_rotation(),
+                scale: rigidbody.get_dimensions(),
+                color: Some(color),
+            }
+        };
+        let bodies = self.simulation.get_bodies();
+        let blocks = bodies
+            .iter()
+            .filter(|body| body.kind == BodyKind::Block)
+            .map(|body| {
+                let color = if body.asleep {
+                    self.theme.body.map(|channel| channel * SLEEP_DIMMING)
+                } else {
+                    self.theme.body
+                };
+                get_instance(body.handle, color)
+            })
+            .collect();
+        let projectiles = bodies
+            .iter()
+            .filter(|body| body.kind == BodyKind::Projectile)
+            .map(|body| get_instance(body.handle, self.theme.accent))
+            .collect();
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.block_entity, blocks);
+        self.scene.update_colored_mesh_entity_instances(
+            &self.gpu,
+            self.projectile_entity,
+            projectiles,
+        );
+    }
+
+    fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // We'll use a CommandEncoder to create the commands to send to the GPU.
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.debug_draw.prepare(&self.gpu);
+
+        {
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
+            );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+                self.debug_draw
+                    .draw(&mut render_pass, &camera_view.camera_bind_group);
+            }
+        }
+
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
+        encoder.finish()
+    }
+}
+
+fn help() -> Help {
+    Help {
+        title: "Target Range",
+        description: "Stacks of blocks on a platform, knocked down by projectiles launched from a slingshot. Bodies collide as boxes. Still bodies fall asleep, drawn darker, and stand perfectly still until something knocks them. Each block knocked off the platform onto the ground scores.",
+        parameters: &[
+        Parameter {
+            name: "Layout",
+            description: "How the blocks are stacked: towers of cubes, a pyramid of cubes, or a wall of bricks. Changing it restacks the blocks.",
+        },
+        Parameter {
+            name: "Launch Strength",
+            description: "The launch speed of a projectile per meter the slingshot's pulled back.",
+        },
+        Parameter {
+            name: "Projectile Mass",
+            description: "The mass of each projectile. Heavier projectiles knock more blocks down.",
+        },
+        Parameter {
+            name: "Block Mass",
+            description: "The mass of each block. Changing it restacks the blocks.",
+        },
+        Parameter {
+            name: "Air Drag",
+            description: "The quadratic air drag on each body, proportional to the square of its speed and to its cross section. Drag shortens and steepens the projectiles' flights.",
+        },
+        Parameter {
+            name: "Ghost",
+            description: "Freeze Ghost keeps the most recent projectile's flight, drawn as a dimmed path. Relaunch launches another projectile pulled back just as far as the last, so changing the parameters and relaunching overlays the new flight on the ghost, with a marker following the ghost in time with it.",
+        },
+        Parameter {
+            name: "Position Correction",
+            description: "The fraction of each overlap between boxes corrected each step by moving them apart directly. Lower values let stacks sag into each other, and higher ones jitter.",
+        },
+        Parameter {
+            name: "Solver Iterations",
+            description: "The most iterations the contact solver runs each step. It stops sooner once an iteration changes no contact's impulse by more than the solver tolerance. More iterations let stacks stand more stiffly, but take longer.",
+        },
+        Parameter {
+            name: "Time Budget",
+            description: "Stops the contact solver once it's run for the budget each step, however many iterations it's run, trading accuracy for a steady step time.",
+        },
+        Parameter {
+            name: "Solver Convergence",
+            description: "Plots the residual of each of the contact solver's iterations over the last step, the largest change it made to any contact's impulse, and why the solver stopped.",
+        },
+        Parameter {
+            name: "Measure",
+            description: "Shows the measuring tools. The ruler measures the distance between two points, and the angle the angle at the second of three, placed by middle clicking in the plane the projectiles fly in. The stopwatch times each projectile's flight from the start gate, drawn green, to the stop gate, drawn red.",
+        },
+        Parameter {
+            name: "Sleeping",
+            description: "Whether bodies still for the sleep time, slower than the sleep speed, fall asleep. Without it, stacks settle and jitter slightly as they stand.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS
+            .into_iter()
+            .chain([InputAction {
+                input: "Right Mouse Drag",
+                description: "Pull back the slingshot, and release to launch a projectile",
+            },
+            InputAction {
+                input: "Middle Mouse Click",
+                description: "Place a point of the measuring tool, while it's shown",
+            }])
+            .collect(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = utils::build_window(&event_loop, window_args);
+
+    let mut state = State::new(&window);
+
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
+    let mut ui = gui::target_range::TargetRangeUi::new();
+
+    let mut current_time = std::time::SystemTime::now();
+    event_loop.run(move |event, _, control_flow| {
+        gui.handle_events(&event);
+
+        *control_flow = utils::control_flow(&state.gpu);
+        match event {
+            Event::MainEventsCleared => {
+                let new_time = std::time::SystemTime::now();
+                let frame_time = new_time.duration_since(current_time).unwrap();
+                current_time = new_time;
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                if let Some(point) = state.measure_point.take() {
+                    ui.get_measure_tools_mut().place(point);
+                }
+                state.draw_measurement(ui.get_measure_tools());
+                state.draw_flights(ui.get_ghost());
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
+                let simulation_render_command_buffer = state.render(&output);
+                let gui_render_command_buffer = gui.render(
+                    &mut ui,
+                    frame_time,
+                    &state.gpu.device,
+                    &state.gpu.config,
+                    &state.gpu.queue,
+                    &window,
+                    &output
+                );
+
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
+                output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, },
+                .. // We're not using device_id currently
+            } if state.mouse_pressed => {
+                state.camera_bundle.camera_controller.process_mouse(delta.0, delta.1)
+            }
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,29 @@
+/// Runs a demo networked with a twin instance, either hosting its simulation or viewing a host's.
+use crate::{
+    args::{Demos, NetworkArgs, WindowArgs},
+    localization::tr,
+    network::{Host, Twin, Viewer},
+};
+
+use super::bouncing_ball;
+
+/// Hosts the demo if given a host address, or else views the host at the view address.
+/// Exits with an error if the demo can't be networked, or the connection can't be made.
+pub fn run(demo: Demos, window: &WindowArgs, network: &NetworkArgs) {
+    let twin = match (&network.host, &network.view) {
+        (Some(address), _) => Host::bind(address).map(Twin::Host),
+        (None, Some(address)) => Viewer::connect(address).map(Twin::Viewer),
+        (None, None) => return,
+    };
+    let twin = twin.and_then(|twin| match demo {
+        Demos::BouncingBall => Ok(twin),
+        _ => Err("Only the bouncing ball demo can be networked!"),
+    });
+    match twin {
+        Ok(twin) => bouncing_ball::run_twin(window, twin),
+        Err(message) => {
+            eprintln!("{}", tr(message));
+            std::process::exit(1);
+        }
+    }
+}
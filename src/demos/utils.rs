@@ -1,20 +1,157 @@
 use wgpu::{CommandEncoder, RenderPass, TextureView};
-use winit::event::{ElementState, KeyboardInput, MouseButton, WindowEvent};
+use winit::{
+    dpi::PhysicalSize,
+    event::{ElementState, KeyboardInput, MouseButton, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
 
+use crate::args::WindowArgs;
 use crate::graphics::camera::CameraBundle;
+use crate::graphics::entity::ColoredMeshEntity;
+use crate::graphics::forms;
+use crate::graphics::gpu_interface::GPUInterface;
+use crate::graphics::instance::Instance;
+use crate::graphics::model::ColoredMesh;
+use crate::graphics::palette;
+use crate::graphics::settings::GraphicsSettings;
+use crate::gui::{help::InputAction, Gui};
+use crate::simulation::fan::Fan;
+use crate::simulation::springy::config::Config as SpringyConfig;
+use crate::simulation::springy::rain::{self, Rain};
 
+/// The inputs handled by every demo, through handle_input_default() and their event loops.
+pub const DEFAULT_INPUT_ACTIONS: [InputAction; 10] = [
+    InputAction {
+        input: "W / Up",
+        description: "Move the camera forward",
+    },
+    InputAction {
+        input: "S / Down",
+        description: "Move the camera backward",
+    },
+    InputAction {
+        input: "A / Left",
+        description: "Move the camera left",
+    },
+    InputAction {
+        input: "D / Right",
+        description: "Move the camera right",
+    },
+    InputAction {
+        input: "Space",
+        description: "Move the camera up",
+    },
+    InputAction {
+        input: "Left Shift",
+        description: "Move the camera down",
+    },
+    InputAction {
+        input: "Left Mouse Drag",
+        description: "Look around",
+    },
+    InputAction {
+        input: "Scroll",
+        description: "Move the camera along its view",
+    },
+    InputAction {
+        input: "F1",
+        description: "Toggle this help",
+    },
+    InputAction {
+        input: "Escape",
+        description: "Exit",
+    },
+];
+
+/// Builds the window with the persisted graphics settings' resolution and window mode,
+/// or those given on the command line.
+pub fn build_window(event_loop: &EventLoop<()>, window_args: &WindowArgs) -> Window {
+    let settings = GraphicsSettings::load();
+    let size = PhysicalSize::new(
+        window_args.width.unwrap_or(settings.width),
+        window_args.height.unwrap_or(settings.height),
+    );
+    let window_mode = window_args.window_mode.unwrap_or(settings.window_mode);
+    WindowBuilder::new()
+        .with_title("feriphys")
+        .with_inner_size(size)
+        .with_fullscreen(window_mode.fullscreen(event_loop.primary_monitor()))
+        .build(event_loop)
+        .unwrap()
+}
+
+/// Begins a render pass into the view, or into the MSAA framebuffer resolving to the view
+/// if one is given.
 pub fn begin_default_render_pass<'pass>(
     encoder: &'pass mut CommandEncoder,
     view: &'pass TextureView,
+    msaa_framebuffer: Option<&'pass TextureView>,
+    depth_texture_view: &'pass TextureView,
+) -> RenderPass<'pass> {
+    begin_render_pass(
+        encoder,
+        view,
+        msaa_framebuffer,
+        depth_texture_view,
+        wgpu::LoadOp::Clear(1.0),
+    )
+}
+
+/// Begins a render pass which only writes depth, for pipelines with DepthMode::PrePass.
+pub fn begin_depth_prepass<'pass>(
+    encoder: &'pass mut CommandEncoder,
     depth_texture_view: &'pass TextureView,
 ) -> RenderPass<'pass> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Depth Pre-Pass"),
+        color_attachments: &[],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_texture_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    })
+}
+
+/// Begins a render pass like begin_default_render_pass(), but keeping the depth from
+/// a depth pre-pass.
+pub fn begin_render_pass_after_depth_prepass<'pass>(
+    encoder: &'pass mut CommandEncoder,
+    view: &'pass TextureView,
+    msaa_framebuffer: Option<&'pass TextureView>,
+    depth_texture_view: &'pass TextureView,
+) -> RenderPass<'pass> {
+    begin_render_pass(
+        encoder,
+        view,
+        msaa_framebuffer,
+        depth_texture_view,
+        wgpu::LoadOp::Load,
+    )
+}
+
+fn begin_render_pass<'pass>(
+    encoder: &'pass mut CommandEncoder,
+    view: &'pass TextureView,
+    msaa_framebuffer: Option<&'pass TextureView>,
+    depth_texture_view: &'pass TextureView,
+    depth_load: wgpu::LoadOp<f32>,
+) -> RenderPass<'pass> {
+    let (view, resolve_target) = match msaa_framebuffer {
+        Some(msaa_framebuffer) => (msaa_framebuffer, Some(view)),
+        None => (view, None),
+    };
     encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some("Render Pass"),
         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
             // texture to save the colors into
             view: view,
             // The texture that will receive the resolved output; defaults to view.
-            resolve_target: None,
+            resolve_target,
             // Tells wgpu what to do with the colors on the screen (i.e. in view).
             ops: wgpu::Operations {
                 // load tells wgpu how to handle colors from the previous screen.
@@ -31,7 +168,7 @@ pub fn begin_default_render_pass<'pass>(
         depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
             view: depth_texture_view,
             depth_ops: Some(wgpu::Operations {
-                load: wgpu::LoadOp::Clear(1.0),
+                load: depth_load,
                 store: true,
             }),
             stencil_ops: None,
@@ -39,6 +176,56 @@ pub fn begin_default_render_pass<'pass>(
     })
 }
 
+/// Whether to step the simulation this frame: always while the window's shown, and while it's suspended,
+/// e.g. minimized, only if the background stepping setting keeps stepping.
+pub fn steps(gpu: &GPUInterface) -> bool {
+    gpu.settings.background_stepping.steps(gpu.is_suspended())
+}
+
+/// How the event loop should wait for the next frame, for the background stepping setting.
+pub fn control_flow(gpu: &GPUInterface) -> ControlFlow {
+    gpu.settings
+        .background_stepping
+        .control_flow(gpu.is_suspended())
+}
+
+/// Submits the frame's simulation and GUI command buffers, with the copy capturing the frame for the camera path
+/// between them if it's capturing, so that frames are captured before the GUI is drawn over them.
+pub fn submit_frame(
+    camera_bundle: &mut CameraBundle,
+    gpu: &GPUInterface,
+    output: &wgpu::SurfaceTexture,
+    simulation_render_command_buffer: wgpu::CommandBuffer,
+    gui_render_command_buffer: wgpu::CommandBuffer,
+) {
+    let capture_command_buffer = camera_bundle
+        .camera_path
+        .encode_capture(gpu, &output.texture);
+    gpu.queue.submit(
+        std::iter::once(simulation_render_command_buffer)
+            .chain(capture_command_buffer)
+            .chain(std::iter::once(gui_render_command_buffer)),
+    );
+}
+
+/// Saves the frame captured for the camera path, if any, then applies the camera path commands
+/// and the top-down view toggle from the GUI and shows the path's status in it. This should follow
+/// presenting the frame, since commands may reconfigure the surface.
+pub fn update_camera_path(gui: &mut Gui, camera_bundle: &mut CameraBundle, gpu: &mut GPUInterface) {
+    let camera_path = &mut camera_bundle.camera_path;
+    camera_path.save_capture(gpu);
+    if camera_path.finished() {
+        camera_path.stop(gpu);
+    }
+    for command in gui.take_camera_path_commands() {
+        camera_path.apply(command, &camera_bundle.camera, gpu);
+    }
+    gui.set_camera_path_status(camera_path.status(gpu));
+    if let Some(top_down) = gui.take_top_down() {
+        camera_bundle.set_top_down(top_down);
+    }
+}
+
 pub fn handle_input_default(
     event: &WindowEvent,
     camera_bundle: &mut CameraBundle,
@@ -71,3 +258,65 @@ pub fn handle_input_default(
         _ => false,
     }
 }
+
+/// Creates the entity the springy simulation's rain drops are drawn with, one instance per drop.
+pub fn create_rain_entity(gpu: &GPUInterface) -> ColoredMeshEntity {
+    let drop = forms::generate_sphere(&gpu.device, palette::CATEGORICAL[1], 0.02, 6, 6);
+    ColoredMeshEntity::new(gpu, drop, Vec::new(), Some(rain::MAX_DROPS))
+}
+
+pub fn get_rain_instances(rain: &Rain) -> Vec<Instance> {
+    rain.get_drops()
+        .iter()
+        .map(|drop| Instance {
+            position: drop.position,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Creates the entity a simulation's fan blades are drawn with, scaled by the fan's radius.
+pub fn create_fan_entity(gpu: &GPUInterface) -> ColoredMeshEntity {
+    let blades = ColoredMesh::from_collidable_mesh(
+        &gpu.device,
+        "fan".to_string(),
+        &Fan::blade_mesh(1.0),
+        palette::CATEGORICAL[2],
+    );
+    ColoredMeshEntity::new(gpu, blades, Vec::new(), Some(1))
+}
+
+/// The blades turned as they are now. None if there is no fan.
+pub fn get_fan_instances(fan: Option<&Fan>) -> Vec<Instance> {
+    fan.map(|fan| {
+        let transform = fan.get_blades().get_transform();
+        let radius = fan.get_config().radius;
+        Instance {
+            position: transform.position,
+            rotation: transform.rotation,
+            scale: cgmath::Vector3::new(radius, radius, radius),
+            ..Default::default()
+        }
+    })
+    .into_iter()
+    .collect()
+}
+
+/// Creates the entity the surface of the springy simulation's fluid is drawn with.
+pub fn create_fluid_surface_entity(gpu: &GPUInterface) -> ColoredMeshEntity {
+    let surface = forms::get_quad(&gpu.device, palette::CATEGORICAL[4]);
+    ColoredMeshEntity::new(gpu, surface, Vec::new(), Some(1))
+}
+
+/// The fluid's surface spans the floor, at the fluid's height. None if there is no fluid.
+pub fn get_fluid_surface_instances(config: &SpringyConfig) -> Vec<Instance> {
+    config
+        .fluid_surface_height
+        .map(|height| Instance {
+            position: cgmath::Vector3::new(0.0, height, 0.0),
+            scale: cgmath::Vector3::new(2.0, 1.0, 2.0),
+            ..Default::default()
+        })
+        .into_iter()
+        .collect()
+}
@@ -0,0 +1,370 @@
+/// A demo of smoke rings simulated with vortex particles.
+use crate::simulation::configurable::Configurable;
+use crate::{
+    args::WindowArgs,
+    graphics::{
+        self,
+        camera::CameraBundle,
+        entity::ColoredMeshEntity,
+        forms,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light,
+        palette::Theme,
+        scene::{ColoredMeshEntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, Parameter},
+    },
+    simulation::vortex::{self, Simulation},
+};
+
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+use super::utils;
+
+const TRACER_RADIUS: f32 = 0.01;
+const VORTEX_RADIUS: f32 = 0.02;
+
+struct State {
+    simulation: Simulation,
+    gpu: GPUInterface,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+    camera_bundle: CameraBundle,
+    light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    scene: Scene,
+    theme: Theme,
+    tracer_entity: ColoredMeshEntityHandle,
+    vortex_entity: ColoredMeshEntityHandle,
+    show_vortices: bool,
+    mouse_pressed: bool,
+    time_accumulator: std::time::Duration,
+}
+
+impl State {
+    fn new(window: &Window) -> Self {
+        let gpu: GPUInterface = GPUInterface::new(window);
+        let camera_bundle =
+            CameraBundle::new(&gpu, (0.0, 2.0, 6.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
+
+        let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
+        let (light_bind_group_layout, light_bind_group) =
+            light::create_light_bind_group(&gpu, light_uniform);
+
+        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+            &gpu,
+            &camera_bundle,
+            &light_bind_group_layout,
+        );
+
+        let theme = Theme::default();
+        let simulation = Simulation::new();
+
+        let tracer = forms::generate_sphere(&gpu.device, theme.body, TRACER_RADIUS, 4, 4);
+        let tracer_entity =
+            ColoredMeshEntity::new(&gpu, tracer, Vec::new(), Some(vortex::MAX_TRACERS));
+        let vortex = forms::generate_sphere(&gpu.device, theme.accent, VORTEX_RADIUS, 6, 6);
+        let vortex_entity =
+            ColoredMeshEntity::new(&gpu, vortex, Vec::new(), Some(vortex::MAX_VORTICES));
+
+        let mut scene = Scene::new();
+        let tracer_entity = scene.add_colored_mesh_entity(tracer_entity);
+        let vortex_entity = scene.add_colored_mesh_entity(vortex_entity);
+
+        Self {
+            simulation,
+            gpu,
+            render_pipeline,
+            depth_texture,
+            camera_bundle,
+            light_bind_group,
+            light_bind_group_layout,
+            scene,
+            theme,
+            tracer_entity,
+            vortex_entity,
+            show_vortices: false,
+            mouse_pressed: false,
+            time_accumulator: std::time::Duration::from_millis(0),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        graphics::util::resize(
+            new_size,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle.projection,
+        );
+    }
+
+    /// Recolors the vortices with the theme. The tracers are colored by its colormap.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.vortex_entity, theme.accent);
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration) {
+        self.time_accumulator += frame_time;
+        self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
+
+        while self.time_accumulator >= self.simulation.get_timestep() {
+            let elapsed_sim_time = self.simulation.step();
+            self.time_accumulator -= elapsed_sim_time;
+        }
+
+        let instances = get_tracer_instances(&self.simulation, self.theme);
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.tracer_entity, instances);
+        let instances = if self.show_vortices {
+            get_vortex_instances(&self.simulation)
+        } else {
+            Vec::new()
+        };
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.vortex_entity, instances);
+    }
+
+    fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // We'll use a CommandEncoder to create the commands to send to the GPU.
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
+            );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
+        }
+
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
+        encoder.finish()
+    }
+}
+
+fn help() -> Help {
+    Help {
+        title: "Vortex Smoke",
+        description: "Smoke rings puffed upward, simulated without a grid by vortex particles. The velocity the vortices induce carries both them and the smoke's tracer particles.",
+        parameters: &[
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Ring Interval",
+            description: "The time between rings puffed from the emitter.",
+        },
+        Parameter {
+            name: "Ring Circulation",
+            description: "The strength of each ring's swirl. Stronger rings travel faster.",
+        },
+        Parameter {
+            name: "Vortices Per Ring",
+            description: "The number of vortex particles each ring is made of.",
+        },
+        Parameter {
+            name: "Core Radius",
+            description: "The radius each vortex's swirl is smoothed over, which keeps the velocity finite near it. Thicker cores travel slower.",
+        },
+        Parameter {
+            name: "Barnes-Hut Theta",
+            description: "Groups of vortices which appear smaller than this angle are approximated as one. Larger angles are faster but less accurate; 0 sums every vortex.",
+        },
+        Parameter {
+            name: "Vorticity Decay",
+            description: "The rate the vortices weaken at, approximating the air's viscosity.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS.to_vec(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = utils::build_window(&event_loop, window_args);
+
+    let mut state = State::new(&window);
+
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
+    let mut ui = gui::vortex::VortexUi::new();
+
+    let mut current_time = std::time::SystemTime::now();
+    event_loop.run(move |event, _, control_flow| {
+        gui.handle_events(&event);
+
+        *control_flow = utils::control_flow(&state.gpu);
+        match event {
+            Event::MainEventsCleared => {
+                let new_time = std::time::SystemTime::now();
+                let frame_time = new_time.duration_since(current_time).unwrap();
+                current_time = new_time;
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                state.show_vortices = ui.show_vortices;
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
+                let simulation_render_command_buffer = state.render(&output);
+                let gui_render_command_buffer = gui.render(
+                    &mut ui,
+                    frame_time,
+                    &state.gpu.device,
+                    &state.gpu.config,
+                    &state.gpu.queue,
+                    &window,
+                    &output
+                );
+
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
+                output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, },
+                .. // We're not using device_id currently
+            } if state.mouse_pressed => {
+                state.camera_bundle.camera_controller.process_mouse(delta.0, delta.1)
+            }
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Colors each tracer by its age, so that the smoke of each ring can be told apart.
+fn get_tracer_instances(simulation: &Simulation, theme: Theme) -> Vec<Instance> {
+    let config = simulation.get_config();
+    simulation
+        .get_tracers()
+        .iter()
+        .map(|tracer| Instance {
+            position: tracer.position,
+            color: Some(theme.colormap.sample(1.0 - tracer.get_age_fraction(config))),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn get_vortex_instances(simulation: &Simulation) -> Vec<Instance> {
+    simulation
+        .get_vortices()
+        .iter()
+        .map(|vortex| Instance {
+            position: vortex.position,
+            ..Default::default()
+        })
+        .collect()
+}
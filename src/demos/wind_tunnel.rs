@@ -0,0 +1,430 @@
+/// A demo of an object in a wind tunnel, measuring its drag coefficient.
+use crate::simulation::configurable::Configurable;
+use crate::{
+    args::WindowArgs,
+    graphics::{
+        self,
+        camera::CameraBundle,
+        entity::ColoredMeshEntity,
+        forms,
+        gpu_interface::GPUInterface,
+        instance::Instance,
+        light,
+        model::ColoredMesh,
+        palette::Theme,
+        resources,
+        scene::{ColoredMeshEntityHandle, Scene},
+        settings::GraphicsSettings,
+        texture,
+    },
+    gui::{
+        self,
+        help::{Help, Parameter},
+    },
+    simulation::collidable_mesh::CollidableMesh,
+    simulation::wind_tunnel::{self, config::Shape, Simulation},
+};
+
+use cgmath::{InnerSpace, Vector3};
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+use super::utils;
+
+const TRACER_RADIUS: f32 = 0.015;
+
+struct State {
+    simulation: Simulation,
+    gpu: GPUInterface,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+    camera_bundle: CameraBundle,
+    light_bind_group: wgpu::BindGroup,
+    light_bind_group_layout: wgpu::BindGroupLayout,
+    scene: Scene,
+    theme: Theme,
+    /// The shape of the object in the tunnel, which is rebuilt when the selection changes.
+    shape: Shape,
+    object_entity: ColoredMeshEntityHandle,
+    tracer_entity: ColoredMeshEntityHandle,
+    mouse_pressed: bool,
+    time_accumulator: std::time::Duration,
+}
+
+impl State {
+    fn new(window: &Window) -> Self {
+        let gpu: GPUInterface = GPUInterface::new(window);
+        let camera_bundle =
+            CameraBundle::new(&gpu, (0.0, 0.5, 6.0), cgmath::Deg(-90.0), cgmath::Deg(-5.0));
+        let depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth texture",
+        );
+
+        let light_uniform = light::LightUniform::new([6.0, 2.0, 6.0], [1.0, 1.0, 1.0]);
+        let (light_bind_group_layout, light_bind_group) =
+            light::create_light_bind_group(&gpu, light_uniform);
+
+        let render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+            &gpu,
+            &camera_bundle,
+            &light_bind_group_layout,
+        );
+
+        let theme = Theme::default();
+        let shape = Shape::Sphere;
+        let simulation = Simulation::new(get_object(shape));
+
+        let object_entity = create_object_entity(&gpu, simulation.get_object(), theme);
+        let tracer = forms::generate_sphere(&gpu.device, theme.body, TRACER_RADIUS, 6, 6);
+        let tracer_entity =
+            ColoredMeshEntity::new(&gpu, tracer, Vec::new(), Some(wind_tunnel::MAX_TRACERS));
+
+        let mut scene = Scene::new();
+        let object_entity = scene.add_colored_mesh_entity(object_entity);
+        let tracer_entity = scene.add_colored_mesh_entity(tracer_entity);
+
+        Self {
+            simulation,
+            gpu,
+            render_pipeline,
+            depth_texture,
+            camera_bundle,
+            light_bind_group,
+            light_bind_group_layout,
+            scene,
+            theme,
+            shape,
+            object_entity,
+            tracer_entity,
+            mouse_pressed: false,
+            time_accumulator: std::time::Duration::from_millis(0),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        graphics::util::resize(
+            new_size,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle.projection,
+        );
+    }
+
+    /// Recolors the meshes with the theme. The tracers are colored by its colormap.
+    fn apply_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.scene
+            .set_colored_mesh_entity_color(&self.gpu, self.object_entity, theme.obstacle);
+    }
+
+    fn apply_graphics_settings(&mut self, settings: GraphicsSettings, window: &Window) {
+        let msaa_changed = graphics::util::apply_graphics_settings(
+            settings,
+            window,
+            &mut self.gpu,
+            &mut self.depth_texture,
+            &mut self.camera_bundle,
+        );
+        if msaa_changed {
+            self.render_pipeline = graphics::util::create_colored_mesh_render_pipeline(
+                &self.gpu,
+                &self.camera_bundle,
+                &self.light_bind_group_layout,
+            );
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        utils::handle_input_default(event, &mut self.camera_bundle, &mut self.mouse_pressed)
+    }
+
+    fn update(&mut self, frame_time: std::time::Duration) {
+        self.time_accumulator += frame_time;
+        self.camera_bundle.update_gpu(&self.gpu, frame_time);
+        graphics::util::update_render_scale(&mut self.gpu, &mut self.depth_texture, frame_time);
+
+        let shape = self.simulation.get_config().shape;
+        if shape != self.shape {
+            self.shape = shape;
+            self.simulation.set_object(get_object(shape));
+            self.scene.remove_colored_mesh_entity(self.object_entity);
+            self.object_entity = self.scene.add_colored_mesh_entity(create_object_entity(
+                &self.gpu,
+                self.simulation.get_object(),
+                self.theme,
+            ));
+        }
+
+        while self.time_accumulator >= self.simulation.get_timestep() {
+            let elapsed_sim_time = self.simulation.step();
+            self.time_accumulator -= elapsed_sim_time;
+        }
+
+        let instances = get_tracer_instances(&self.simulation, self.theme);
+        self.scene
+            .update_colored_mesh_entity_instances(&self.gpu, self.tracer_entity, instances);
+    }
+
+    fn render(&mut self, output: &wgpu::SurfaceTexture) -> wgpu::CommandBuffer {
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // We'll use a CommandEncoder to create the commands to send to the GPU.
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = utils::begin_default_render_pass(
+                &mut encoder,
+                self.gpu.scene_view(&view),
+                self.gpu.msaa_framebuffer.as_ref(),
+                &self.depth_texture.view,
+            );
+
+            for camera_view in self.camera_bundle.views.iter() {
+                camera_view.set_viewport(&mut render_pass, &self.gpu);
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.scene.draw_colored_mesh_entities(
+                    &mut render_pass,
+                    &camera_view.camera_bind_group,
+                    &self.light_bind_group,
+                );
+            }
+        }
+
+        self.gpu.apply_ambient_occlusion(
+            &mut encoder,
+            &view,
+            &self.depth_texture,
+            &self.camera_bundle,
+        );
+
+        self.gpu.upsample(&mut encoder, &view);
+
+        encoder.finish()
+    }
+}
+
+fn help() -> Help {
+    Help {
+        title: "Wind Tunnel",
+        description: "An object in a uniform wind, with tracer particles following the air around it. The momentum the tracers give the object as they hit it is measured as its drag.",
+        parameters: &[
+        Parameter {
+            name: "Object",
+            description: "The object in the tunnel: a sphere, a cube, or a fish loaded from an OBJ file.",
+        },
+        Parameter {
+            name: "Simulation dt",
+            description: "The simulated time per step; larger steps are faster but less accurate.",
+        },
+        Parameter {
+            name: "Wind Speed",
+            description: "The speed of the wind blowing through the tunnel.",
+        },
+        Parameter {
+            name: "Air Density",
+            description: "The density of the air; the tracers share the mass of the air flowing into the tunnel.",
+        },
+        Parameter {
+            name: "Tracers",
+            description: "Tracers spawned per second. More tracers give a smoother measurement, but are slower.",
+        },
+        Parameter {
+            name: "Restitution",
+            description: "The fraction of the normal velocity a tracer keeps after hitting the object.",
+        },
+        Parameter {
+            name: "Friction",
+            description: "How much tangential velocity a tracer loses when it hits the object.",
+        },
+        Parameter {
+            name: "Relaxation Time",
+            description: "The time for a deflected tracer to return to the wind's velocity.",
+        },
+        Parameter {
+            name: "Drag Coefficient",
+            description: "The drag relative to the wind's dynamic pressure on the object's silhouette, averaged over half a second.",
+        },
+        ],
+        input_actions: utils::DEFAULT_INPUT_ACTIONS.to_vec(),
+    }
+}
+
+pub fn run(window_args: &WindowArgs) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = utils::build_window(&event_loop, window_args);
+
+    let mut state = State::new(&window);
+
+    let mut gui = gui::Gui::new(
+        &state.gpu.device,
+        &state.gpu.config,
+        &window,
+        state.gpu.settings,
+    );
+    gui.set_help(help());
+    let mut ui = gui::wind_tunnel::WindTunnelUi::new();
+
+    let mut current_time = std::time::SystemTime::now();
+    event_loop.run(move |event, _, control_flow| {
+        gui.handle_events(&event);
+
+        *control_flow = utils::control_flow(&state.gpu);
+        match event {
+            Event::MainEventsCleared => {
+                let new_time = std::time::SystemTime::now();
+                let frame_time = new_time.duration_since(current_time).unwrap();
+                current_time = new_time;
+                if utils::steps(&state.gpu) {
+                    state.update(frame_time);
+                }
+                ui.sync_simulation(&mut state.simulation);
+                ui.set_measurements(
+                    state.simulation.get_force(),
+                    state.simulation.get_drag_coefficient(),
+                    state.simulation.get_drag_history().iter().copied(),
+                );
+                let output = match state.gpu.acquire_frame() {
+                    Some(output) => output,
+                    None => return,
+                };
+                let simulation_render_command_buffer = state.render(&output);
+                let gui_render_command_buffer = gui.render(
+                    &mut ui,
+                    frame_time,
+                    &state.gpu.device,
+                    &state.gpu.config,
+                    &state.gpu.queue,
+                    &window,
+                    &output
+                );
+
+                utils::submit_frame(
+                    &mut state.camera_bundle,
+                    &state.gpu,
+                    &output,
+                    simulation_render_command_buffer,
+                    gui_render_command_buffer,
+                );
+                output.present();
+                utils::update_camera_path(&mut gui, &mut state.camera_bundle, &mut state.gpu);
+                if let Some(settings) = gui.take_graphics_settings() {
+                    state.apply_graphics_settings(settings, &window);
+                }
+                if let Some(theme) = gui.take_theme() {
+                    state.apply_theme(theme);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, },
+                .. // We're not using device_id currently
+            } if state.mouse_pressed => {
+                state.camera_bundle.camera_controller.process_mouse(delta.0, delta.1)
+            }
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Gets the collidable mesh for the shape, about a meter across and centered at the origin.
+fn get_object(shape: Shape) -> CollidableMesh {
+    let (vertex_positions, indices) = match shape {
+        Shape::Sphere => forms::get_sphere_vertices(0.5, 16, 16),
+        Shape::Cube => forms::get_cube_vertices(),
+        Shape::Fish => {
+            let (vertex_positions, indices) = resources::load_positions("blue_fish.obj").unwrap();
+            (fit_to_unit_box(vertex_positions), indices)
+        }
+    };
+    CollidableMesh::new(vertex_positions, indices)
+}
+
+/// Centers the positions at the origin, scaled so their largest extent is 1.
+fn fit_to_unit_box(positions: Vec<Vector3<f32>>) -> Vec<Vector3<f32>> {
+    let (min, max) = positions.iter().fold(
+        (
+            Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+            Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+        ),
+        |(min, max), p| {
+            (
+                Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        },
+    );
+    let center = (min + max) / 2.0;
+    let extent = (max - min).x.max((max - min).y).max((max - min).z);
+    positions.iter().map(|p| (p - center) / extent).collect()
+}
+
+fn create_object_entity(
+    gpu: &GPUInterface,
+    object: &CollidableMesh,
+    theme: Theme,
+) -> ColoredMeshEntity {
+    let mesh = ColoredMesh::from_collidable_mesh(
+        &gpu.device,
+        "object".to_string(),
+        object,
+        theme.obstacle,
+    );
+    ColoredMeshEntity::new(gpu, mesh, vec![Instance::default()], None)
+}
+
+/// Colors each tracer by its speed relative to the wind's, so that the air slowed by the
+/// object stands out.
+fn get_tracer_instances(simulation: &Simulation, theme: Theme) -> Vec<Instance> {
+    let wind_speed = simulation.get_config().wind_speed;
+    simulation
+        .get_tracers()
+        .iter()
+        .map(|tracer| Instance {
+            position: tracer.position,
+            color: Some(
+                theme
+                    .colormap
+                    .sample(tracer.velocity.magnitude() / wind_speed),
+            ),
+            ..Default::default()
+        })
+        .collect()
+}
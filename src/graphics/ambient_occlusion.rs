@@ -0,0 +1,387 @@
+/// Screen-space ambient occlusion, darkening creases and contacts, like those within particle piles
+/// and cloth folds, which direct lighting alone leaves flat.
+/// The occlusion is estimated from the scene's depth after it is rendered, then blurred and
+/// multiplied into the scene.
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use rand::Rng;
+use wgpu::BindGroupLayout;
+
+use super::{camera::CameraBundle, texture};
+
+/// The most samples per pixel of any quality.
+const KERNEL_SIZE: usize = 64;
+const OCCLUSION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+const SHADER: &str = include_str!("../shaders/ssao.wgsl");
+const APPLY_SHADER: &str = include_str!("../shaders/ssao_apply.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoUniform {
+    proj: [[f32; 4]; 4],
+    inv_proj: [[f32; 4]; 4],
+    /// The camera view's viewport of the render target in pixels: x, y, width, height.
+    viewport: [f32; 4],
+    radius: f32,
+    sample_count: u32,
+    // Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+    _padding: [u32; 2],
+    kernel: [[f32; 4]; KERNEL_SIZE],
+}
+
+/// Random offsets within the unit hemisphere around +z, scaled so that more lie close to the
+/// center, where occluders matter most.
+fn generate_kernel(rng: &mut impl Rng) -> [[f32; 4]; KERNEL_SIZE] {
+    let mut kernel = [[0.0; 4]; KERNEL_SIZE];
+    for (i, offset) in kernel.iter_mut().enumerate() {
+        let direction = Vector3::new(
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(0.0..=1.0),
+        );
+        let direction = if direction.magnitude2() > 0.0 {
+            direction.normalize()
+        } else {
+            Vector3::unit_z()
+        };
+        let t = i as f32 / KERNEL_SIZE as f32;
+        let scale = 0.1 + 0.9 * t * t;
+        let sample = direction * rng.gen_range(0.0..=1.0f32) * scale;
+        *offset = [sample.x, sample.y, sample.z, 0.0];
+    }
+    kernel
+}
+
+/// The SSAO uniform for one of the camera bundle's views.
+struct ViewBinding {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+pub struct AmbientOcclusion {
+    occlusion_pipeline: wgpu::RenderPipeline,
+    apply_pipeline: wgpu::RenderPipeline,
+    uniform_bind_group_layout: BindGroupLayout,
+    depth_bind_group_layout: BindGroupLayout,
+    occlusion: wgpu::TextureView,
+    apply_bind_group: wgpu::BindGroup,
+    kernel: [[f32; 4]; KERNEL_SIZE],
+    sample_count: u32,
+    radius: f32,
+    views: Vec<ViewBinding>,
+}
+
+impl AmbientOcclusion {
+    /// Creates the pass for a scene of the format and size, with a depth texture of
+    /// depth_sample_count samples, taking sample_count samples per pixel within the radius.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: wgpu::Extent3d,
+        depth_sample_count: u32,
+        sample_count: u32,
+        radius: f32,
+    ) -> AmbientOcclusion {
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("ssao_uniform_bind_group_layout"),
+            });
+        let multisampled = depth_sample_count > 1;
+        let depth_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                }],
+                label: Some("ssao_depth_bind_group_layout"),
+            });
+        let occlusion_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                }],
+                label: Some("ssao_occlusion_bind_group_layout"),
+            });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("SSAO Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source(multisampled).into()),
+        });
+        let occlusion_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("SSAO Pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("SSAO Pipeline Layout"),
+                    bind_group_layouts: &[&uniform_bind_group_layout, &depth_bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(OCCLUSION_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let apply_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("SSAO Apply Shader"),
+            source: wgpu::ShaderSource::Wgsl(APPLY_SHADER.into()),
+        });
+        let apply_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("SSAO Apply Pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("SSAO Apply Pipeline Layout"),
+                    bind_group_layouts: &[&occlusion_bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &apply_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &apply_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    // Multiplies the scene's color by the occlusion.
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Zero,
+                            dst_factor: wgpu::BlendFactor::Src,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Zero,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let occlusion = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("ssao occlusion"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: OCCLUSION_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let apply_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &occlusion_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&occlusion),
+            }],
+            label: Some("ssao_apply_bind_group"),
+        });
+
+        AmbientOcclusion {
+            occlusion_pipeline,
+            apply_pipeline,
+            uniform_bind_group_layout,
+            depth_bind_group_layout,
+            occlusion,
+            apply_bind_group,
+            kernel: generate_kernel(&mut rand::thread_rng()),
+            sample_count: sample_count.min(KERNEL_SIZE as u32),
+            radius,
+            views: Vec::new(),
+        }
+    }
+
+    /// Encodes darkening the scene rendered into the view by its ambient occlusion, computed
+    /// from the depth texture the scene was rendered with.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        depth_texture: &texture::Texture,
+        camera_bundle: &CameraBundle,
+        size: wgpu::Extent3d,
+    ) {
+        while self.views.len() < camera_bundle.views.len() {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("SSAO Buffer"),
+                size: std::mem::size_of::<SsaoUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.uniform_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+                label: Some("ssao_uniform_bind_group"),
+            });
+            self.views.push(ViewBinding { buffer, bind_group });
+        }
+        self.views.truncate(camera_bundle.views.len());
+        for (camera_view, view_binding) in camera_bundle.views.iter().zip(self.views.iter()) {
+            let proj = camera_bundle
+                .projection
+                .calc_viewport_matrix(&camera_view.viewport);
+            let viewport = camera_view.viewport;
+            let uniform = SsaoUniform {
+                proj: proj.into(),
+                inv_proj: proj.invert().unwrap_or_else(Matrix4::identity).into(),
+                viewport: [
+                    viewport.x * size.width as f32,
+                    viewport.y * size.height as f32,
+                    viewport.width * size.width as f32,
+                    viewport.height * size.height as f32,
+                ],
+                radius: self.radius,
+                sample_count: self.sample_count,
+                _padding: [0; 2],
+                kernel: self.kernel,
+            };
+            queue.write_buffer(&view_binding.buffer, 0, bytemuck::cast_slice(&[uniform]));
+        }
+
+        // The depth texture is recreated with the scene's render targets, so it's bound anew.
+        let depth_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.depth_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&depth_texture.view),
+            }],
+            label: Some("ssao_depth_bind_group"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("SSAO Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.occlusion,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.occlusion_pipeline);
+            render_pass.set_bind_group(1, &depth_bind_group, &[]);
+            for (camera_view, view_binding) in camera_bundle.views.iter().zip(self.views.iter()) {
+                camera_view
+                    .viewport
+                    .set(&mut render_pass, size.width, size.height);
+                render_pass.set_bind_group(0, &view_binding.bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("SSAO Apply Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.apply_pipeline);
+        render_pass.set_bind_group(0, &self.apply_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// The SSAO shader, reading a depth texture which is multisampled or not.
+fn shader_source(multisampled: bool) -> String {
+    if multisampled {
+        SHADER.replace("texture_depth_2d", "texture_depth_multisampled_2d")
+    } else {
+        SHADER.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{InnerSpace, Vector3};
+    use rand::SeedableRng;
+
+    use super::{generate_kernel, shader_source, APPLY_SHADER};
+
+    #[test]
+    fn shaders_validate() {
+        for (name, source) in [
+            ("ssao", shader_source(false)),
+            ("multisampled ssao", shader_source(true)),
+            ("ssao apply", APPLY_SHADER.to_string()),
+        ] {
+            let module = naga::front::wgsl::parse_str(&source)
+                .unwrap_or_else(|err| panic!("{} shader should parse: {:?}", name, err));
+            naga::valid::Validator::new(
+                naga::valid::ValidationFlags::all(),
+                naga::valid::Capabilities::empty(),
+            )
+            .validate(&module)
+            .unwrap_or_else(|err| panic!("{} shader should validate: {:?}", name, err));
+        }
+    }
+
+    #[test]
+    fn kernel_in_unit_hemisphere() {
+        let kernel = generate_kernel(&mut rand::rngs::StdRng::seed_from_u64(0));
+        let offsets = kernel.map(|offset| Vector3::new(offset[0], offset[1], offset[2]));
+        for offset in offsets {
+            assert!(offset.magnitude() <= 1.0);
+            assert!(offset.z >= 0.0);
+        }
+        // The first offsets are scaled to lie close to the center.
+        assert!(offsets[0].magnitude() <= 0.1);
+    }
+}
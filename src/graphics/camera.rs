@@ -1,5 +1,5 @@
 /// Basis for this code is from https://sotrh.github.io/learn-wgpu/intermediate/tutorial12-camera/
-use super::gpu_interface::GPUInterface;
+use super::{camera_path::CameraPathTool, gpu_interface::GPUInterface};
 
 use cgmath::*;
 use std::f32::consts::FRAC_PI_2;
@@ -23,6 +23,8 @@ pub struct Camera {
     pub position: Point3<f32>,
     yaw: Rad<f32>,
     pitch: Rad<f32>,
+    /// Looks straight down, with the yaw turning the view about the vertical, ignoring the pitch.
+    top_down: bool,
 }
 
 impl Camera {
@@ -35,15 +37,88 @@ impl Camera {
             position: position.into(),
             yaw: yaw.into(),
             pitch: pitch.into(),
+            top_down: false,
         }
     }
 
+    pub fn yaw(&self) -> Rad<f32> {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> Rad<f32> {
+        self.pitch
+    }
+
+    pub fn top_down(&self) -> bool {
+        self.top_down
+    }
+
+    pub fn set_top_down(&mut self, top_down: bool) {
+        self.top_down = top_down;
+    }
+
+    pub fn set_pose(&mut self, position: Point3<f32>, yaw: Rad<f32>, pitch: Rad<f32>) {
+        self.position = position;
+        self.yaw = yaw;
+        self.pitch = pitch;
+    }
+
     pub fn calc_matrix(&self) -> Matrix4<f32> {
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(self.yaw.0.cos(), self.pitch.0.sin(), self.yaw.0.sin()).normalize(),
-            Vector3::unit_y(),
-        )
+        self.calc_eye_matrix(0.0)
+    }
+
+    /// The view matrix for an eye offset from the camera's position along its right axis,
+    /// e.g. half the eye separation to either side for stereo rendering.
+    pub fn calc_eye_matrix(&self, eye_offset: f32) -> Matrix4<f32> {
+        Matrix4::look_to_rh(self.eye_position(eye_offset), self.forward(), self.up())
+    }
+
+    pub fn eye_position(&self, eye_offset: f32) -> Point3<f32> {
+        self.position + self.right() * eye_offset
+    }
+
+    fn forward(&self) -> Vector3<f32> {
+        if self.top_down {
+            -Vector3::unit_y()
+        } else {
+            Vector3::new(self.yaw.0.cos(), self.pitch.0.sin(), self.yaw.0.sin()).normalize()
+        }
+    }
+
+    /// The top of the view; when looking down, the direction the camera would look level.
+    fn up(&self) -> Vector3<f32> {
+        if self.top_down {
+            Vector3::new(self.yaw.0.cos(), 0.0, self.yaw.0.sin())
+        } else {
+            Vector3::unit_y()
+        }
+    }
+
+    fn right(&self) -> Vector3<f32> {
+        self.forward().cross(self.up()).normalize()
+    }
+
+    /// Gets the ray from the camera through the cursor, as (origin, unit direction) in world space.
+    /// The cursor position is in physical pixels from the top left of a screen of the given size.
+    pub fn cursor_ray(
+        &self,
+        projection: &Projection,
+        cursor: PhysicalPosition<f64>,
+        screen_width: u32,
+        screen_height: u32,
+    ) -> (Vector3<f32>, Vector3<f32>) {
+        let ndc_x = 2.0 * cursor.x as f32 / screen_width as f32 - 1.0;
+        let ndc_y = 1.0 - 2.0 * cursor.y as f32 / screen_height as f32;
+        let inverse_view_proj = (projection.calc_matrix() * self.calc_matrix())
+            .invert()
+            .expect("View projection should be invertible!");
+        let unproject = |depth: f32| {
+            let point = inverse_view_proj * Vector4::new(ndc_x, ndc_y, depth, 1.0);
+            point.truncate() / point.w
+        };
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        (near, (far - near).normalize())
     }
 }
 
@@ -62,9 +137,66 @@ impl CameraUniform {
         }
     }
 
-    pub fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
-        self.view_position = camera.position.to_homogeneous().into();
-        self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).into();
+    /// Updates the uniform for an eye of the camera, rendered into the viewport.
+    pub fn update_eye_view_proj(
+        &mut self,
+        camera: &Camera,
+        projection: &Projection,
+        eye_offset: f32,
+        viewport: &Viewport,
+    ) {
+        self.view_position = camera.eye_position(eye_offset).to_homogeneous().into();
+        self.view_proj =
+            (projection.calc_viewport_matrix(viewport) * camera.calc_eye_matrix(eye_offset)).into();
+    }
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        CameraUniform::new()
+    }
+}
+
+/// A region of the surface to render into, as fractions of the surface's size
+/// from its top left corner.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+    pub const LEFT_HALF: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 0.5,
+        height: 1.0,
+    };
+    pub const RIGHT_HALF: Viewport = Viewport {
+        x: 0.5,
+        y: 0.0,
+        width: 0.5,
+        height: 1.0,
+    };
+
+    /// Restricts rendering in the pass to this viewport of a surface of the given size.
+    pub fn set<'a>(&self, render_pass: &mut wgpu::RenderPass<'a>, width: u32, height: u32) {
+        render_pass.set_viewport(
+            self.x * width as f32,
+            self.y * height as f32,
+            self.width * width as f32,
+            self.height * height as f32,
+            0.0,
+            1.0,
+        );
     }
 }
 
@@ -73,6 +205,8 @@ pub struct Projection {
     fovy: Rad<f32>,
     znear: f32,
     zfar: f32,
+    /// Half the height of the view, in meters, if it's orthographic rather than perspective.
+    orthographic: Option<f32>,
 }
 
 impl Projection {
@@ -82,6 +216,7 @@ impl Projection {
             fovy: fovy.into(),
             znear,
             zfar,
+            orthographic: None,
         }
     }
 
@@ -89,8 +224,38 @@ impl Projection {
         self.aspect = width as f32 / height as f32;
     }
 
+    pub fn set_fovy<F: Into<Rad<f32>>>(&mut self, fovy: F) {
+        self.fovy = fovy.into();
+    }
+
+    /// Projects orthographically, showing the given half height of the view, or in perspective if none.
+    pub fn set_orthographic(&mut self, half_height: Option<f32>) {
+        self.orthographic = half_height;
+    }
+
     pub fn calc_matrix(&self) -> Matrix4<f32> {
-        OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        self.calc_viewport_matrix(&Viewport::FULL)
+    }
+
+    /// The projection for rendering into a viewport of the surface, keeping the same vertical
+    /// field of view but with the viewport's aspect ratio.
+    pub fn calc_viewport_matrix(&self, viewport: &Viewport) -> Matrix4<f32> {
+        let aspect = self.aspect * viewport.width / viewport.height;
+        let projection = match self.orthographic {
+            Some(half_height) => {
+                let half_width = half_height * aspect;
+                ortho(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.znear,
+                    self.zfar,
+                )
+            }
+            None => perspective(self.fovy, aspect, self.znear, self.zfar),
+        };
+        OPENGL_TO_WGPU_MATRIX * projection
     }
 }
 
@@ -217,17 +382,75 @@ impl CameraController {
     }
 }
 
-pub struct CameraBundle {
-    pub camera: Camera,
-    pub projection: Projection,
+/// One of the views of the camera rendered each frame, with its own camera uniform.
+pub struct CameraView {
+    /// The offset of the view's eye from the camera along its right axis.
+    pub eye_offset: f32,
+    pub viewport: Viewport,
     pub camera_uniform: CameraUniform,
     pub camera_buffer: wgpu::Buffer,
     pub camera_bind_group: wgpu::BindGroup,
+}
+
+impl CameraView {
+    fn new(
+        gpu: &GPUInterface,
+        camera_bind_group_layout: &BindGroupLayout,
+        camera: &Camera,
+        projection: &Projection,
+        eye_offset: f32,
+        viewport: Viewport,
+    ) -> CameraView {
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_eye_view_proj(camera, projection, eye_offset, &viewport);
+
+        let camera_buffer = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Camera Buffer"),
+                contents: bytemuck::cast_slice(&[camera_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let camera_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+            label: Some("camera_bind_group"),
+        });
+
+        CameraView {
+            eye_offset,
+            viewport,
+            camera_uniform,
+            camera_buffer,
+            camera_bind_group,
+        }
+    }
+
+    /// Restricts rendering in the pass to this view's viewport of the scene.
+    pub fn set_viewport<'a>(&self, render_pass: &mut wgpu::RenderPass<'a>, gpu: &GPUInterface) {
+        let size = gpu.render_size();
+        self.viewport.set(render_pass, size.width, size.height);
+    }
+}
+
+pub struct CameraBundle {
+    pub camera: Camera,
+    pub projection: Projection,
+    /// The views rendered each frame; one for the whole surface, or one per eye when stereo.
+    pub views: Vec<CameraView>,
     pub camera_controller: CameraController,
     pub camera_bind_group_layout: BindGroupLayout,
+    pub camera_path: CameraPathTool,
 }
 
 impl CameraBundle {
+    /// The distance between the eyes of stereo views, in meters.
+    pub const EYE_SEPARATION: f32 = 0.064;
+
     pub fn new<V: Into<Point3<f32>>, Y: Into<Rad<f32>>, P: Into<Rad<f32>>>(
         gpu: &GPUInterface,
         position: V,
@@ -238,23 +461,12 @@ impl CameraBundle {
         let projection = Projection::new(
             gpu.config.width,
             gpu.config.height,
-            cgmath::Deg(45.0),
+            cgmath::Deg(gpu.settings.fov),
             0.1,
             100.0,
         );
         let camera_controller = CameraController::new(4.0, 0.4);
 
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&camera, &projection);
-
-        let camera_buffer = gpu
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Camera Buffer"),
-                contents: bytemuck::cast_slice(&[camera_uniform]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
-
         let camera_bind_group_layout =
             gpu.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -271,37 +483,151 @@ impl CameraBundle {
                     label: Some("camera_bind_group_layout"),
                 });
 
-        let camera_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
-
-        CameraBundle {
+        let mut camera_bundle = CameraBundle {
             camera,
             projection,
-            camera_uniform,
-            camera_buffer,
-            camera_bind_group,
+            views: Vec::new(),
             camera_controller,
             camera_bind_group_layout,
+            camera_path: CameraPathTool::new(),
+        };
+        camera_bundle.set_stereo(gpu, gpu.settings.stereo);
+        camera_bundle
+    }
+
+    /// Switches to the top-down view, looking straight down with an orthographic projection,
+    /// or back to the perspective view. Scrolling raises and lowers the camera, zooming the top-down view.
+    pub fn set_top_down(&mut self, top_down: bool) {
+        self.camera.set_top_down(top_down);
+        if !top_down {
+            self.projection.set_orthographic(None);
         }
     }
 
+    /// Renders a view per eye side by side if stereo, or a single view of the whole surface.
+    pub fn set_stereo(&mut self, gpu: &GPUInterface, stereo: bool) {
+        let views = if stereo {
+            vec![
+                (-CameraBundle::EYE_SEPARATION / 2.0, Viewport::LEFT_HALF),
+                (CameraBundle::EYE_SEPARATION / 2.0, Viewport::RIGHT_HALF),
+            ]
+        } else {
+            vec![(0.0, Viewport::FULL)]
+        };
+        self.views = views
+            .into_iter()
+            .map(|(eye_offset, viewport)| {
+                CameraView::new(
+                    gpu,
+                    &self.camera_bind_group_layout,
+                    &self.camera,
+                    &self.projection,
+                    eye_offset,
+                    viewport,
+                )
+            })
+            .collect();
+    }
+
     pub fn update_gpu(&mut self, gpu: &GPUInterface, frame_time: std::time::Duration) {
         self.camera_controller
             .update_camera(&mut self.camera, frame_time);
+        // Playing back a camera path overrides the controller.
+        self.camera_path.update(&mut self.camera, frame_time);
+        if self.camera.top_down() {
+            // Shows what the perspective view would at the ground, from the camera's height.
+            let height = self.camera.position.y.max(self.projection.znear);
+            self.projection
+                .set_orthographic(Some(height * (self.projection.fovy / 2.0).tan()));
+        }
         // TODO It's more efficient to have a staging buffer. Possible future improvement.
         // See https://sotrh.github.io/learn-wgpu/beginner/tutorial6-uniforms/#a-controller-for-our-camera
-        self.camera_uniform
-            .update_view_proj(&self.camera, &self.projection);
-        gpu.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
-        );
+        for view in self.views.iter_mut() {
+            view.camera_uniform.update_eye_view_proj(
+                &self.camera,
+                &self.projection,
+                view.eye_offset,
+                &view.viewport,
+            );
+            gpu.queue.write_buffer(
+                &view.camera_buffer,
+                0,
+                bytemuck::cast_slice(&[view.camera_uniform]),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, InnerSpace, Vector3};
+    use winit::dpi::PhysicalPosition;
+
+    use super::{Camera, Projection, Viewport};
+
+    #[test]
+    fn cursor_ray_through_center() {
+        let camera = Camera::new((0.0, 1.0, 5.0), Deg(-90.0), Deg(0.0));
+        let projection = Projection::new(800, 600, Deg(45.0), 0.1, 100.0);
+        let (origin, direction) =
+            camera.cursor_ray(&projection, PhysicalPosition::new(400.0, 300.0), 800, 600);
+        // The camera looks down -z, and the ray starts on the near plane.
+        assert!((direction - -Vector3::unit_z()).magnitude() < 0.0001);
+        assert!((origin - Vector3::new(0.0, 1.0, 4.9)).magnitude() < 0.0001);
+    }
+
+    #[test]
+    fn cursor_ray_top_left() {
+        let camera = Camera::new((0.0, 0.0, 0.0), Deg(-90.0), Deg(0.0));
+        let projection = Projection::new(800, 600, Deg(45.0), 0.1, 100.0);
+        let (_, direction) =
+            camera.cursor_ray(&projection, PhysicalPosition::new(0.0, 0.0), 800, 600);
+        assert!(direction.x < 0.0);
+        assert!(direction.y > 0.0);
+        assert!(direction.z < 0.0);
+    }
+
+    #[test]
+    fn eyes_offset_along_right_axis() {
+        let camera = Camera::new((0.0, 1.0, 5.0), Deg(-90.0), Deg(0.0));
+        // Looking down -z, the right axis is +x.
+        let left = camera.eye_position(-0.032);
+        let right = camera.eye_position(0.032);
+        assert!((left - cgmath::Point3::new(-0.032, 1.0, 5.0)).magnitude() < 0.0001);
+        assert!((right - cgmath::Point3::new(0.032, 1.0, 5.0)).magnitude() < 0.0001);
+        assert_eq!(camera.calc_matrix(), camera.calc_eye_matrix(0.0));
+    }
+
+    #[test]
+    fn top_down_looks_straight_down() {
+        let mut camera = Camera::new((1.0, 10.0, 2.0), Deg(-90.0), Deg(-30.0));
+        camera.set_top_down(true);
+        let mut projection = Projection::new(800, 600, Deg(45.0), 0.1, 100.0);
+        projection.set_orthographic(Some(5.0));
+        // Rays through the cursor are parallel, straight down, from wherever it is.
+        for cursor in [(400.0, 300.0), (0.0, 0.0)] {
+            let (_, direction) = camera.cursor_ray(
+                &projection,
+                PhysicalPosition::new(cursor.0, cursor.1),
+                800,
+                600,
+            );
+            assert!((direction - -Vector3::unit_y()).magnitude() < 0.0001);
+        }
+        // Looking down with the yaw towards -z, the top of the screen is towards -z.
+        let (origin, _) =
+            camera.cursor_ray(&projection, PhysicalPosition::new(400.0, 0.0), 800, 600);
+        assert!((origin.x - 1.0).abs() < 0.0001);
+        assert!((origin.z - (2.0 - 5.0)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn viewport_projection_keeps_vertical_fov() {
+        let projection = Projection::new(1600, 600, Deg(45.0), 0.1, 100.0);
+        let full = projection.calc_matrix();
+        let half = projection.calc_viewport_matrix(&Viewport::LEFT_HALF);
+        // Half the width halves the aspect ratio, doubling the horizontal scale.
+        assert!((half.x.x - 2.0 * full.x.x).abs() < 0.0001);
+        assert!((half.y.y - full.y.y).abs() < 0.0001);
     }
 }
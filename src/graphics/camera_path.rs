@@ -0,0 +1,478 @@
+/// Camera fly-through paths, recorded as keyframes and played back along a smooth spline,
+/// optionally capturing each frame of the playback to produce repeatable demo videos.
+use std::fmt::Write;
+
+use cgmath::{Point3, Rad};
+
+use super::{camera::Camera, frame_capture::FrameCapture, gpu_interface::GPUInterface};
+
+/// The path file, relative to the working directory.
+pub const CAMERA_PATH_FILE: &str = "camera_path.txt";
+
+/// A pose of the camera at a time along the path, in seconds from its start.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub position: Point3<f32>,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+}
+
+impl Keyframe {
+    fn of_camera(camera: &Camera, time: f32) -> Keyframe {
+        Keyframe {
+            time,
+            position: camera.position,
+            yaw: camera.yaw(),
+            pitch: camera.pitch(),
+        }
+    }
+
+    fn to_array(self) -> [f32; 5] {
+        [
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.yaw.0,
+            self.pitch.0,
+        ]
+    }
+
+    fn from_array(time: f32, values: [f32; 5]) -> Keyframe {
+        Keyframe {
+            time,
+            position: Point3::new(values[0], values[1], values[2]),
+            yaw: Rad(values[3]),
+            pitch: Rad(values[4]),
+        }
+    }
+}
+
+/// Keyframes in increasing order of time, interpolated with a Catmull-Rom spline.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Adds the keyframe to the end of the path. Panics if it isn't after the last keyframe.
+    pub fn push(&mut self, keyframe: Keyframe) {
+        assert!(
+            keyframe.time > self.duration() || self.keyframes.is_empty(),
+            "Keyframes must be added in order of time!"
+        );
+        self.keyframes.push(keyframe);
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    /// The pose along the path at the time, clamped to the path's start and end.
+    /// Returns None if the path has no keyframes.
+    pub fn sample(&self, time: f32) -> Option<Keyframe> {
+        let last = self.keyframes.len().checked_sub(1)?;
+        let time = time.clamp(self.keyframes[0].time, self.keyframes[last].time);
+        if last == 0 {
+            return Some(Keyframe {
+                time,
+                ..self.keyframes[0]
+            });
+        }
+        // The segment containing the time, from keyframe i to i + 1.
+        let i = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time)
+            .saturating_sub(1)
+            .min(last - 1);
+        let k0 = self.keyframes[i];
+        let k1 = self.keyframes[i + 1];
+        let before = self.keyframes[i.saturating_sub(1)];
+        let after = self.keyframes[(i + 2).min(last)];
+        let segment_duration = k1.time - k0.time;
+        let t = (time - k0.time) / segment_duration;
+
+        // Catmull-Rom tangents with respect to time, so that speed is continuous across
+        // unevenly spaced keyframes. The endpoints use one-sided differences.
+        let tangent = |a: Keyframe, b: Keyframe| {
+            let (a_values, b_values) = (a.to_array(), b.to_array());
+            let dt = b.time - a.time;
+            std::array::from_fn::<f32, 5, _>(|j| (b_values[j] - a_values[j]) / dt)
+        };
+        let m0 = tangent(before, k1);
+        let m1 = tangent(k0, after);
+
+        // Cubic Hermite basis functions.
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+        let h10 = t3 - 2.0 * t2 + t;
+        let h01 = -2.0 * t3 + 3.0 * t2;
+        let h11 = t3 - t2;
+
+        let (p0, p1) = (k0.to_array(), k1.to_array());
+        let values = std::array::from_fn(|j| {
+            h00 * p0[j]
+                + h10 * segment_duration * m0[j]
+                + h01 * p1[j]
+                + h11 * segment_duration * m1[j]
+        });
+        Some(Keyframe::from_array(time, values))
+    }
+
+    pub fn load() -> anyhow::Result<CameraPath> {
+        let contents = std::fs::read_to_string(CAMERA_PATH_FILE)?;
+        CameraPath::parse(&contents).map_err(anyhow::Error::msg)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        std::fs::write(CAMERA_PATH_FILE, self.file_contents())?;
+        Ok(())
+    }
+
+    /// Parses lines of "time x y z yaw pitch", with angles in radians.
+    /// Blank lines and lines starting with # are ignored.
+    pub fn parse(contents: &str) -> Result<CameraPath, &'static str> {
+        let mut path = CameraPath::default();
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let numbers = line
+                .split_whitespace()
+                .map(str::parse::<f32>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "Camera path keyframes must be numbers")?;
+            if numbers.len() != 6 {
+                return Err("Camera path keyframes must have a time, position, yaw and pitch");
+            }
+            if !path.keyframes.is_empty() && numbers[0] <= path.duration() {
+                return Err("Camera path keyframes must be in increasing order of time");
+            }
+            path.push(Keyframe::from_array(
+                numbers[0],
+                [numbers[1], numbers[2], numbers[3], numbers[4], numbers[5]],
+            ));
+        }
+        Ok(path)
+    }
+
+    pub fn file_contents(&self) -> String {
+        let mut contents = String::from("# time x y z yaw pitch\n");
+        for keyframe in self.keyframes.iter() {
+            let [x, y, z, yaw, pitch] = keyframe.to_array();
+            writeln!(
+                contents,
+                "{} {} {} {} {} {}",
+                keyframe.time, x, y, z, yaw, pitch
+            )
+            .unwrap();
+        }
+        contents
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraPathMode {
+    Idle,
+    /// Recording the camera's live movement, adding a keyframe every RECORD_INTERVAL.
+    Recording {
+        since_keyframe: f32,
+    },
+    /// Moving the camera along the path, capturing each frame if capture_frames.
+    Playing {
+        time: f32,
+        capture_frames: bool,
+    },
+}
+
+impl CameraPathMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraPathMode::Idle => "Idle",
+            CameraPathMode::Recording { .. } => "Recording",
+            CameraPathMode::Playing { .. } => "Playing",
+        }
+    }
+}
+
+/// A request from the GUI to the camera path tool.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraPathCommand {
+    AddKeyframe,
+    Record,
+    Play { capture_frames: bool },
+    Stop,
+    Clear,
+    Save,
+    Load,
+}
+
+/// The state of the camera path tool, shown in the GUI.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraPathStatus {
+    pub mode: CameraPathMode,
+    pub keyframes: usize,
+    pub duration: f32,
+    pub frame_capture_supported: bool,
+}
+
+impl Default for CameraPathStatus {
+    fn default() -> Self {
+        CameraPathStatus {
+            mode: CameraPathMode::Idle,
+            keyframes: 0,
+            duration: 0.0,
+            frame_capture_supported: false,
+        }
+    }
+}
+
+/// Records and plays back a camera path.
+pub struct CameraPathTool {
+    pub path: CameraPath,
+    mode: CameraPathMode,
+    frame_capture: FrameCapture,
+}
+
+impl CameraPathTool {
+    /// The time between keyframes recorded from live movement, in seconds.
+    pub const RECORD_INTERVAL: f32 = 0.25;
+    /// The time between keyframes added one at a time, in seconds.
+    pub const KEYFRAME_INTERVAL: f32 = 2.0;
+    /// Captured playback advances by a fixed step per frame, so that videos are independent of
+    /// how long each frame takes to render and save.
+    pub const CAPTURE_FRAME_RATE: f32 = 30.0;
+
+    pub fn new() -> CameraPathTool {
+        CameraPathTool {
+            path: CameraPath::default(),
+            mode: CameraPathMode::Idle,
+            frame_capture: FrameCapture::new(),
+        }
+    }
+
+    pub fn status(&self, gpu: &GPUInterface) -> CameraPathStatus {
+        CameraPathStatus {
+            mode: self.mode,
+            keyframes: self.path.keyframes.len(),
+            duration: self.path.duration(),
+            frame_capture_supported: gpu.supports_frame_capture,
+        }
+    }
+
+    pub fn apply(&mut self, command: CameraPathCommand, camera: &Camera, gpu: &mut GPUInterface) {
+        match command {
+            CameraPathCommand::AddKeyframe => {
+                self.stop(gpu);
+                let time = if self.path.keyframes.is_empty() {
+                    0.0
+                } else {
+                    self.path.duration() + CameraPathTool::KEYFRAME_INTERVAL
+                };
+                self.path.push(Keyframe::of_camera(camera, time));
+            }
+            CameraPathCommand::Record => {
+                self.stop(gpu);
+                self.path.clear();
+                self.path.push(Keyframe::of_camera(camera, 0.0));
+                self.mode = CameraPathMode::Recording {
+                    since_keyframe: 0.0,
+                };
+            }
+            CameraPathCommand::Play { capture_frames } => {
+                self.stop(gpu);
+                if self.path.keyframes.is_empty() {
+                    return;
+                }
+                let capture_frames = capture_frames && gpu.supports_frame_capture;
+                if capture_frames {
+                    gpu.set_frame_capture(true);
+                    self.frame_capture.start();
+                }
+                self.mode = CameraPathMode::Playing {
+                    time: 0.0,
+                    capture_frames,
+                };
+            }
+            CameraPathCommand::Stop => self.stop(gpu),
+            CameraPathCommand::Clear => {
+                self.stop(gpu);
+                self.path.clear();
+            }
+            CameraPathCommand::Save => {
+                if let Err(err) = self.path.save() {
+                    log::warn!("Failed to save camera path: {}", err);
+                }
+            }
+            CameraPathCommand::Load => {
+                self.stop(gpu);
+                match CameraPath::load() {
+                    Ok(path) => self.path = path,
+                    Err(err) => log::warn!("Failed to load camera path: {}", err),
+                }
+            }
+        }
+    }
+
+    /// Records the camera's movement, or moves the camera along the path, for the frame.
+    pub fn update(&mut self, camera: &mut Camera, frame_time: std::time::Duration) {
+        match &mut self.mode {
+            CameraPathMode::Idle => {}
+            CameraPathMode::Recording { since_keyframe } => {
+                *since_keyframe += frame_time.as_secs_f32();
+                if *since_keyframe >= CameraPathTool::RECORD_INTERVAL {
+                    let time = self.path.duration() + *since_keyframe;
+                    *since_keyframe = 0.0;
+                    self.path.push(Keyframe::of_camera(camera, time));
+                }
+            }
+            CameraPathMode::Playing {
+                time,
+                capture_frames,
+            } => {
+                if let Some(keyframe) = self.path.sample(*time) {
+                    camera.set_pose(keyframe.position, keyframe.yaw, keyframe.pitch);
+                }
+                *time += if *capture_frames {
+                    1.0 / CameraPathTool::CAPTURE_FRAME_RATE
+                } else {
+                    frame_time.as_secs_f32()
+                };
+            }
+        }
+    }
+
+    /// Whether playback has passed the end of the path, and should be stopped.
+    pub fn finished(&self) -> bool {
+        match self.mode {
+            CameraPathMode::Playing { time, .. } => {
+                time > self.path.duration() + 1.0 / CameraPathTool::CAPTURE_FRAME_RATE
+            }
+            _ => false,
+        }
+    }
+
+    pub fn stop(&mut self, gpu: &mut GPUInterface) {
+        if let CameraPathMode::Playing {
+            capture_frames: true,
+            ..
+        } = self.mode
+        {
+            gpu.set_frame_capture(false);
+        }
+        self.mode = CameraPathMode::Idle;
+    }
+
+    /// Encodes copying the frame rendered to the surface texture so far, if capturing.
+    /// The copy should be submitted after the simulation is rendered but before the GUI.
+    pub fn encode_capture(
+        &mut self,
+        gpu: &GPUInterface,
+        texture: &wgpu::Texture,
+    ) -> Option<wgpu::CommandBuffer> {
+        match self.mode {
+            CameraPathMode::Playing {
+                capture_frames: true,
+                ..
+            } => Some(self.frame_capture.encode(gpu, texture)),
+            _ => None,
+        }
+    }
+
+    /// Saves the frame copied by encode_capture(), once its copy has been submitted.
+    pub fn save_capture(&mut self, gpu: &GPUInterface) {
+        if let Err(err) = self.frame_capture.save(gpu) {
+            log::warn!("Failed to save captured frame: {}", err);
+        }
+    }
+}
+
+impl Default for CameraPathTool {
+    fn default() -> Self {
+        CameraPathTool::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{MetricSpace, Point3, Rad};
+
+    use super::{CameraPath, Keyframe};
+
+    fn keyframe(time: f32, x: f32) -> Keyframe {
+        Keyframe {
+            time,
+            position: Point3::new(x, 1.0, 0.0),
+            yaw: Rad(x / 10.0),
+            pitch: Rad(0.0),
+        }
+    }
+
+    #[test]
+    fn sample_passes_through_keyframes() {
+        let mut path = CameraPath::default();
+        path.push(keyframe(0.0, 0.0));
+        path.push(keyframe(1.0, 4.0));
+        path.push(keyframe(3.0, 2.0));
+        path.push(keyframe(4.0, 8.0));
+        for expected in path.keyframes.clone() {
+            let sampled = path.sample(expected.time).unwrap();
+            assert!(sampled.position.distance(expected.position) < 0.0001);
+            assert!((sampled.yaw.0 - expected.yaw.0).abs() < 0.0001);
+        }
+        // Clamped outside of the path.
+        assert_eq!(
+            path.sample(-1.0).unwrap().position,
+            path.keyframes[0].position
+        );
+        assert!(
+            path.sample(10.0)
+                .unwrap()
+                .position
+                .distance(Point3::new(8.0, 1.0, 0.0))
+                < 0.0001
+        );
+    }
+
+    #[test]
+    fn sample_is_linear_for_evenly_spaced_collinear_keyframes() {
+        let mut path = CameraPath::default();
+        for i in 0..4 {
+            path.push(keyframe(i as f32, 2.0 * i as f32));
+        }
+        let sampled = path.sample(1.25).unwrap();
+        assert!((sampled.position.x - 2.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn sample_single_keyframe_and_empty() {
+        let mut path = CameraPath::default();
+        assert_eq!(None, path.sample(0.0));
+        path.push(keyframe(0.0, 3.0));
+        assert_eq!(
+            path.keyframes[0].position,
+            path.sample(5.0).unwrap().position
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut path = CameraPath::default();
+        path.push(keyframe(0.0, 1.5));
+        path.push(keyframe(0.25, -2.0));
+        assert_eq!(Ok(path.clone()), CameraPath::parse(&path.file_contents()));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(CameraPath::parse("0 1 2 3 4").is_err());
+        assert!(CameraPath::parse("0 1 2 3 4 x").is_err());
+        assert!(CameraPath::parse("1 0 0 0 0 0\n0.5 0 0 0 0 0").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use super::camera::CameraBundle;
+use super::gpu_interface::GPUInterface;
+use super::texture;
+
+use cgmath::{Matrix3, Vector3};
+
+pub const RED: [f32; 3] = [1.0, 0.0, 0.0];
+pub const GREEN: [f32; 3] = [0.0, 1.0, 0.0];
+pub const BLUE: [f32; 3] = [0.0, 0.0, 1.0];
+pub const YELLOW: [f32; 3] = [1.0, 1.0, 0.0];
+pub const WHITE: [f32; 3] = [1.0, 1.0, 1.0];
+
+/// A line vertex's position and color, respectively.
+type DebugVertex = [[f32; 3]; 2];
+
+const DEBUG_VERTEX_ATTRIBS: [wgpu::VertexAttribute; 2] =
+    wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+fn debug_vertex_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+    wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &DEBUG_VERTEX_ATTRIBS,
+    }
+}
+
+fn create_render_pipeline(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+) -> wgpu::RenderPipeline {
+    let layout = gpu
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Draw Pipeline Layout"),
+            bind_group_layouts: &[&camera_bundle.camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+    let shader = gpu
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug Lines Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/debug_lines.wgsl").into()),
+        });
+    gpu.device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug Draw Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[debug_vertex_desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: gpu.config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            // The depth attachment is shared with the rest of the scene,
+            // but we always pass the depth test.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: gpu.settings.msaa_samples,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+}
+
+/// Immediate-mode debug drawing of colored lines, e.g. for gizmos visualizing simulation state.
+/// Lines are queued each frame with line() and friends, uploaded with prepare(),
+/// drawn with draw(), and then cleared for the next frame.
+/// Lines are drawn on top of the scene, ignoring depth, so they're never hidden inside meshes.
+pub struct DebugDraw {
+    vertices: Vec<DebugVertex>,
+    vertex_buffer: wgpu::Buffer,
+    num_uploaded_vertices: u32,
+    capacity: usize,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl DebugDraw {
+    /// Capacity is the maximum number of lines which may be drawn each frame.
+    /// Lines beyond the capacity are dropped.
+    pub fn new(gpu: &GPUInterface, camera_bundle: &CameraBundle, capacity: usize) -> DebugDraw {
+        let vertex_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("debug draw vertex buffer"),
+            size: (capacity * 2 * std::mem::size_of::<DebugVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let render_pipeline = create_render_pipeline(gpu, camera_bundle);
+
+        DebugDraw {
+            vertices: Vec::with_capacity(capacity * 2),
+            vertex_buffer,
+            num_uploaded_vertices: 0,
+            capacity,
+            render_pipeline,
+        }
+    }
+
+    /// Recreates the pipeline, e.g. to match a new MSAA sample count.
+    pub fn rebuild_pipeline(&mut self, gpu: &GPUInterface, camera_bundle: &CameraBundle) {
+        self.render_pipeline = create_render_pipeline(gpu, camera_bundle);
+    }
+
+    pub fn line(&mut self, start: Vector3<f32>, end: Vector3<f32>, color: [f32; 3]) {
+        if self.vertices.len() / 2 >= self.capacity {
+            return;
+        }
+        self.vertices.push([start.into(), color]);
+        self.vertices.push([end.into(), color]);
+    }
+
+    /// A line from origin along vector.
+    pub fn arrow(&mut self, origin: Vector3<f32>, vector: Vector3<f32>, color: [f32; 3]) {
+        self.line(origin, origin + vector, color);
+    }
+
+    /// An axis-aligned 3D cross marking a point.
+    pub fn cross(&mut self, center: Vector3<f32>, size: f32, color: [f32; 3]) {
+        let half_size = size / 2.0;
+        for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+            self.line(center - axis * half_size, center + axis * half_size, color);
+        }
+    }
+
+    /// The three columns of the basis drawn from origin in red, green, and blue, respectively.
+    pub fn basis(&mut self, origin: Vector3<f32>, basis: Matrix3<f32>, length: f32) {
+        self.arrow(origin, basis.x * length, RED);
+        self.arrow(origin, basis.y * length, GREEN);
+        self.arrow(origin, basis.z * length, BLUE);
+    }
+
+    /// Schedules the upload of this frame's lines. Must be called before draw().
+    pub fn prepare(&mut self, gpu: &GPUInterface) {
+        gpu.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        self.num_uploaded_vertices = self.vertices.len() as u32;
+        self.vertices.clear();
+    }
+
+    /// Draws the lines uploaded by the last call to prepare().
+    /// Sets its own pipeline, so callers should reset their pipeline if drawing anything afterwards.
+    pub fn draw<'a, 'b>(
+        &'a self,
+        render_pass: &'b mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) where
+        'a: 'b,
+    {
+        if self.num_uploaded_vertices == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.draw(0..self.num_uploaded_vertices, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn shader_validates() {
+        let module = naga::front::wgsl::parse_str(include_str!("../shaders/debug_lines.wgsl"))
+            .expect("debug_lines.wgsl should parse");
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .expect("debug_lines.wgsl should validate");
+    }
+}
@@ -0,0 +1,332 @@
+/// Sorting instances back to front by their distance from the camera on the GPU, so that translucent
+/// particles drawn in order composite correctly without weighted, blended transparency's approximation.
+/// The instances are sorted each frame by a bitonic sort, which is dispatched once per stage of its
+/// sorting network, and gathered into a second instance buffer which the particles are drawn from.
+use super::entity::ColoredMeshEntity;
+use super::gpu_interface::GPUInterface;
+use super::instance::InstanceRaw;
+
+use wgpu::util::DeviceExt;
+
+// Must match the @workgroup_size of the entry points in depth_sort.wgsl.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The size of a key in depth_sort.wgsl: a depth and an index.
+const KEY_SIZE: u64 = 8;
+
+/// The bitonic sorting network's stages for the number of keys, a power of two, in the order they're
+/// dispatched. Each is the size of the blocks sorted in alternating directions, and the stride between
+/// the keys compared.
+fn bitonic_stages(key_count: u32) -> Vec<(u32, u32)> {
+    let mut stages = Vec::new();
+    let mut block = 2;
+    while block <= key_count {
+        let mut stride = block / 2;
+        while stride > 0 {
+            stages.push((block, stride));
+            stride /= 2;
+        }
+        block *= 2;
+    }
+    stages
+}
+
+/// The compute pipelines which sort instances by depth. Shared between all sorted entities;
+/// each entity gets its own GpuDepthSort binding.
+pub struct DepthSortPipeline {
+    keys_pipeline: wgpu::ComputePipeline,
+    bitonic_pipeline: wgpu::ComputePipeline,
+    gather_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    stage_bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl DepthSortPipeline {
+    pub fn new(gpu: &GPUInterface) -> DepthSortPipeline {
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let uniform_entry = |binding, has_dynamic_offset| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset,
+                min_binding_size: wgpu::BufferSize::new(16),
+            },
+            count: None,
+        };
+        let bind_group_layout =
+            gpu.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("depth sort bind group layout"),
+                    entries: &[
+                        storage_entry(0, true),
+                        storage_entry(1, false),
+                        storage_entry(2, false),
+                        uniform_entry(3, false),
+                    ],
+                });
+        let stage_bind_group_layout =
+            gpu.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("depth sort stage bind group layout"),
+                    entries: &[uniform_entry(0, true)],
+                });
+        let layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("depth sort pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout, &stage_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Depth Sort Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/depth_sort.wgsl").into()),
+            });
+        let create_pipeline = |label, entry_point| {
+            gpu.device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&layout),
+                    module: &shader,
+                    entry_point,
+                })
+        };
+
+        DepthSortPipeline {
+            keys_pipeline: create_pipeline("depth sort keys pipeline", "cs_keys"),
+            bitonic_pipeline: create_pipeline("depth sort bitonic pipeline", "cs_bitonic"),
+            gather_pipeline: create_pipeline("depth sort gather pipeline", "cs_gather"),
+            bind_group_layout,
+            stage_bind_group_layout,
+        }
+    }
+}
+
+/// Binds an entity's instance buffer to the DepthSortPipeline, sorting up to the entity's capacity
+/// of instances into a buffer of its own. The entity is then drawn from sorted_instance_buffer().
+pub struct GpuDepthSort {
+    bind_group: wgpu::BindGroup,
+    stage_bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    sorted_instance_buffer: wgpu::Buffer,
+    /// The number of keys sorted, the capacity rounded up to a power of two.
+    key_count: u32,
+    stage_count: u32,
+    /// The distance between stages in the stage buffer, in bytes.
+    stage_offset: u32,
+}
+
+impl GpuDepthSort {
+    pub fn new(
+        gpu: &GPUInterface,
+        depth_sort_pipeline: &DepthSortPipeline,
+        entity: &ColoredMeshEntity,
+        capacity: usize,
+    ) -> GpuDepthSort {
+        let key_count = (capacity.max(1) as u32).next_power_of_two();
+        let instance_size = std::mem::size_of::<InstanceRaw>() as u64;
+
+        let sorted_instance_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sorted instance buffer"),
+            size: capacity.max(1) as u64 * instance_size,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let keys_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth sort keys buffer"),
+            size: key_count as u64 * KEY_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let params_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth sort params buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Each stage is written once, at an offset bound dynamically as it's dispatched, since the
+        // queue's writes all land before the encoder's passes run.
+        let stages = bitonic_stages(key_count);
+        let stage_offset = gpu.device.limits().min_uniform_buffer_offset_alignment;
+        let mut stage_contents = vec![0; stages.len().max(1) * stage_offset as usize];
+        for (index, (block, stride)) in stages.iter().enumerate() {
+            let start = index * stage_offset as usize;
+            stage_contents[start..start + 8]
+                .copy_from_slice(bytemuck::cast_slice(&[*block, *stride]));
+        }
+        let stage_buffer = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("depth sort stage buffer"),
+                contents: &stage_contents,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth sort bind group"),
+            layout: &depth_sort_pipeline.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: entity.instance_buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sorted_instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: keys_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let stage_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth sort stage bind group"),
+            layout: &depth_sort_pipeline.stage_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &stage_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(16),
+                }),
+            }],
+        });
+
+        GpuDepthSort {
+            bind_group,
+            stage_bind_group,
+            params_buffer,
+            sorted_instance_buffer,
+            key_count,
+            stage_count: stages.len() as u32,
+            stage_offset,
+        }
+    }
+
+    /// Records the compute passes sorting the entity's first count instances back to front from the
+    /// camera position into sorted_instance_buffer(). Should be recorded after the entity's instances
+    /// are updated, and before any render pass drawing from the sorted instances.
+    pub fn dispatch(
+        &self,
+        gpu: &GPUInterface,
+        encoder: &mut wgpu::CommandEncoder,
+        depth_sort_pipeline: &DepthSortPipeline,
+        count: usize,
+        camera_position: cgmath::Point3<f32>,
+    ) {
+        let count = (count as u32).min(self.key_count);
+        let params: [u32; 4] = [
+            camera_position.x.to_bits(),
+            camera_position.y.to_bits(),
+            camera_position.z.to_bits(),
+            count,
+        ];
+        gpu.queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&params));
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("depth sort compute pass"),
+        });
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.set_bind_group(1, &self.stage_bind_group, &[0]);
+        let key_workgroups = self.key_count.div_ceil(WORKGROUP_SIZE);
+
+        compute_pass.set_pipeline(&depth_sort_pipeline.keys_pipeline);
+        compute_pass.dispatch_workgroups(key_workgroups, 1, 1);
+
+        compute_pass.set_pipeline(&depth_sort_pipeline.bitonic_pipeline);
+        for stage in 0..self.stage_count {
+            compute_pass.set_bind_group(1, &self.stage_bind_group, &[stage * self.stage_offset]);
+            compute_pass.dispatch_workgroups(key_workgroups, 1, 1);
+        }
+
+        compute_pass.set_pipeline(&depth_sort_pipeline.gather_pipeline);
+        compute_pass.dispatch_workgroups(count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    pub fn sorted_instance_buffer(&self) -> &wgpu::Buffer {
+        &self.sorted_instance_buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bitonic_stages;
+
+    /// Runs the sorting network on the CPU as depth_sort.wgsl's cs_bitonic() does, one stage at a time.
+    fn bitonic_sort(keys: &mut [f32]) {
+        for (block, stride) in bitonic_stages(keys.len() as u32) {
+            for i in 0..keys.len() {
+                let partner = i ^ stride as usize;
+                if partner <= i {
+                    continue;
+                }
+                let descending = i & block as usize == 0;
+                if (descending && keys[i] < keys[partner])
+                    || (!descending && keys[i] > keys[partner])
+                {
+                    keys.swap(i, partner);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stages() {
+        assert_eq!(
+            vec![(2, 1), (4, 2), (4, 1), (8, 4), (8, 2), (8, 1)],
+            bitonic_stages(8)
+        );
+        assert!(bitonic_stages(1).is_empty());
+    }
+
+    #[test]
+    fn sorts_back_to_front() {
+        let mut keys = vec![3.0, 9.0, 0.5, 4.0, 7.0, 1.0, -1.0, -1.0];
+        bitonic_sort(&mut keys);
+        // Padding, at -1, sorts after every instance.
+        assert_eq!(vec![9.0, 7.0, 4.0, 3.0, 1.0, 0.5, -1.0, -1.0], keys);
+
+        let mut keys = (0..64).map(|i| ((i * 37) % 64) as f32).collect::<Vec<_>>();
+        bitonic_sort(&mut keys);
+        assert!(keys.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn shader_validates() {
+        let module = naga::front::wgsl::parse_str(include_str!("../shaders/depth_sort.wgsl"))
+            .expect("depth_sort.wgsl should parse");
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .expect("depth_sort.wgsl should validate");
+    }
+
+    #[test]
+    fn instance_stride_matches() {
+        // depth_sort.wgsl indexes instances by INSTANCE_STRIDE floats.
+        assert_eq!(
+            29 * std::mem::size_of::<f32>(),
+            std::mem::size_of::<crate::graphics::instance::InstanceRaw>()
+        );
+    }
+}
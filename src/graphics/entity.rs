@@ -5,6 +5,8 @@ use super::model::ColoredMesh;
 use super::model::DrawColoredMesh;
 use super::model::DrawModel;
 use super::model::Model;
+use crate::simulation::collidable_mesh::{CollidableMesh, Face};
+use crate::simulation::springy::springy_mesh::SpringyMesh;
 
 use cgmath::{EuclideanSpace, InnerSpace, Vector3};
 use wgpu::{BindGroup, Buffer};
@@ -64,6 +66,79 @@ impl Entity {
     pub fn instances(&self) -> &Vec<Instance> {
         &self.instances
     }
+
+    /// Updates the mesh at mesh_index of the entity's model with the springy mesh's current vertices.
+    /// See Mesh::from_springy_mesh().
+    pub fn update_springy_mesh(
+        &self,
+        gpu: &GPUInterface,
+        mesh_index: usize,
+        springy_mesh: &SpringyMesh,
+    ) {
+        self.model.meshes[mesh_index].update_from_springy_mesh(&gpu.queue, springy_mesh);
+    }
+
+    /// Writes the image over the diffuse texture of the material at material_index of the entity's model.
+    /// See Texture::write().
+    pub fn write_texture(&self, gpu: &GPUInterface, material_index: usize, img: &image::RgbaImage) {
+        self.model.materials[material_index]
+            .diffuse_texture
+            .write(&gpu.queue, img);
+    }
+
+    /// Gets a collision mesh for each instance, simplified from the model's meshes to at most
+    /// target_triangles triangles, since render meshes are usually too dense to collide against.
+    pub fn get_collision_proxies(&self, target_triangles: usize) -> Vec<CollidableMesh> {
+        let (positions, indices) = self.model.get_collision_proxy(target_triangles);
+        self.instances
+            .iter()
+            .map(|instance| {
+                CollidableMesh::new(
+                    positions
+                        .iter()
+                        .map(|p| instance.transform_point(*p))
+                        .collect(),
+                    indices.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Gets the world space faces of each instance, e.g. to cast rays against.
+    pub fn get_faces(&self) -> Vec<Vec<Face>> {
+        self.instances
+            .iter()
+            .map(|instance| {
+                self.model
+                    .meshes
+                    .iter()
+                    .flat_map(|mesh| {
+                        get_instance_faces(instance, &mesh.vertex_positions, &mesh.vertex_indices)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Gets the faces of the mesh, transformed to world space by the instance.
+fn get_instance_faces(
+    instance: &Instance,
+    vertex_positions: &[Vector3<f32>],
+    vertex_indices: &[u32],
+) -> Vec<Face> {
+    let positions = vertex_positions
+        .iter()
+        .map(|p| instance.transform_point(*p))
+        .collect::<Vec<_>>();
+    vertex_indices
+        .chunks_exact(3)
+        .map(|t| Face {
+            v0: positions[t[0] as usize],
+            v1: positions[t[1] as usize],
+            v2: positions[t[2] as usize],
+        })
+        .collect()
 }
 
 pub struct ColoredMeshEntity {
@@ -100,7 +175,26 @@ impl ColoredMeshEntity {
     {
         // TODO don't like the literal int here. Create const *_SLOT values in rendering.rs, for each render pipeline.
         //    Speaking of, move the creation of each type of render pipeline to that file as well (we share the colored render pipeline, e.g.)
-        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        self.draw_from_instance_buffer(
+            render_pass,
+            &self.instance_buffer,
+            camera_bind_group,
+            light_bind_group,
+        );
+    }
+
+    /// Draws the entity's instances from another buffer of them, e.g. the same instances sorted, which
+    /// should have at least as many instances as the entity.
+    pub fn draw_from_instance_buffer<'a, 'b>(
+        &'a self,
+        render_pass: &'b mut wgpu::RenderPass<'a>,
+        instance_buffer: &'a Buffer,
+        camera_bind_group: &'a BindGroup,
+        light_bind_group: &'a BindGroup,
+    ) where
+        'a: 'b,
+    {
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
         render_pass.draw_colored_mesh_instanced(
             &self.mesh,
             0..self.instances.len() as u32,
@@ -109,11 +203,53 @@ impl ColoredMeshEntity {
         );
     }
 
+    /// The buffer of the entity's instances, as InstanceRaw.
+    pub fn instance_buffer(&self) -> &Buffer {
+        &self.instance_buffer
+    }
+
     pub fn update_instances(&mut self, gpu: &GPUInterface, instances: Vec<Instance>) {
         self.instances = instances;
         InstanceRaw::update_buffer_from_vec(gpu, &self.instance_buffer, &self.instances);
     }
 
+    /// Updates the vertex positions of the entity's mesh in place.
+    /// See ColoredMesh::update_vertex_positions().
+    pub fn update_mesh_vertices(
+        &mut self,
+        gpu: &GPUInterface,
+        vertex_positions: Vec<Vector3<f32>>,
+    ) -> Result<(), &'static str> {
+        self.mesh
+            .update_vertex_positions(&gpu.device, &gpu.queue, vertex_positions)
+    }
+
+    /// Gets the world space faces of each instance, e.g. to cast rays against.
+    pub fn get_faces(&self) -> Vec<Vec<Face>> {
+        self.instances
+            .iter()
+            .map(|instance| {
+                get_instance_faces(
+                    instance,
+                    &self.mesh.vertex_positions,
+                    &self.mesh.vertex_indices,
+                )
+            })
+            .collect()
+    }
+
+    /// Sets the color of the entity's mesh. See ColoredMesh::set_color().
+    pub fn set_color(&mut self, gpu: &GPUInterface, color: [f32; 3]) {
+        self.mesh.set_color(&gpu.queue, color);
+    }
+
+    /// Draws a quad per instance in place of the mesh, for pipelines which draw each instance
+    /// as an impostor, e.g. a sphere drawn in screen space.
+    pub fn draw_impostors<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..4, 0..self.instances.len() as u32);
+    }
+
     /// Orients the normal of all the instances to face the position.
     /// This is useful when rendering particles, e.g., by making
     /// their quads face the camera postiion.
@@ -128,7 +264,6 @@ impl ColoredMeshEntity {
         InstanceRaw::update_buffer_from_vec(gpu, &self.instance_buffer, &self.instances);
     }
 
-    #[allow(dead_code)]
     pub fn instances(&self) -> &Vec<Instance> {
         &self.instances
     }
@@ -0,0 +1,558 @@
+/// Screen-space fluid rendering (van der Laan, Green and Sainz 2009), which draws particles as a
+/// continuous liquid surface without extracting a mesh.
+/// The particles are drawn as sphere impostors into a depth target, which a bilateral blur
+/// smooths into a surface. The surface's normals are reconstructed from the smoothed depth, and it
+/// is shaded as water refracting and reflecting the sky.
+use cgmath::{Matrix4, SquareMatrix};
+use wgpu::util::DeviceExt;
+use wgpu::BindGroupLayout;
+
+use super::{
+    camera::{CameraBundle, CameraView},
+    entity::ColoredMeshEntity,
+    gpu_interface::GPUInterface,
+    instance::InstanceRaw,
+    texture,
+};
+
+/// The distance from the camera to the fluid's surface, or 0 where there is no fluid.
+const FLUID_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+const DEPTH_SHADER: &str = concat!(
+    include_str!("../shaders/fluid_common.wgsl"),
+    include_str!("../shaders/fluid_depth.wgsl")
+);
+const BLUR_SHADER: &str = include_str!("../shaders/fluid_blur.wgsl");
+const SHADE_SHADER: &str = concat!(
+    include_str!("../shaders/fluid_common.wgsl"),
+    include_str!("../shaders/fluid_shade.wgsl")
+);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FluidUniform {
+    view: [[f32; 4]; 4],
+    proj: [[f32; 4]; 4],
+    inv_view: [[f32; 4]; 4],
+    eye_position: [f32; 4],
+    /// The camera view's viewport of the render target in pixels: x, y, width, height.
+    viewport: [f32; 4],
+    particle_radius: f32,
+    // Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+    _padding: [f32; 3],
+}
+
+impl FluidUniform {
+    fn new(
+        camera_bundle: &CameraBundle,
+        camera_view: &CameraView,
+        size: wgpu::Extent3d,
+        particle_radius: f32,
+    ) -> FluidUniform {
+        let view = camera_bundle.camera.calc_eye_matrix(camera_view.eye_offset);
+        let proj = camera_bundle
+            .projection
+            .calc_viewport_matrix(&camera_view.viewport);
+        let viewport = camera_view.viewport;
+        FluidUniform {
+            view: view.into(),
+            proj: proj.into(),
+            inv_view: view.invert().unwrap_or_else(Matrix4::identity).into(),
+            eye_position: camera_bundle
+                .camera
+                .eye_position(camera_view.eye_offset)
+                .to_homogeneous()
+                .into(),
+            viewport: [
+                viewport.x * size.width as f32,
+                viewport.y * size.height as f32,
+                viewport.width * size.width as f32,
+                viewport.height * size.height as f32,
+            ],
+            particle_radius,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurUniform {
+    direction: [i32; 2],
+    _padding: [i32; 2],
+}
+
+/// The fluid uniform for one of the camera bundle's views.
+struct ViewBinding {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// The targets the fluid's depth is drawn and smoothed in, at the scene's render size.
+struct Targets {
+    size: wgpu::Extent3d,
+    depth: wgpu::TextureView,
+    /// The nearest impostor's depth, while drawing the particles' depth.
+    impostor_depth: texture::Texture,
+    /// The depth blurred horizontally.
+    blurred: wgpu::TextureView,
+    /// The depth blurred in both directions, which is shaded.
+    smoothed: wgpu::TextureView,
+    horizontal_blur_bind_group: wgpu::BindGroup,
+    vertical_blur_bind_group: wgpu::BindGroup,
+    surface_bind_group: wgpu::BindGroup,
+}
+
+pub struct FluidSurface {
+    particle_radius: f32,
+    depth_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    surface_pipeline: wgpu::RenderPipeline,
+    sky_pipeline: wgpu::RenderPipeline,
+    uniform_bind_group_layout: BindGroupLayout,
+    blur_bind_group_layout: BindGroupLayout,
+    surface_bind_group_layout: BindGroupLayout,
+    /// The blur directions' uniforms; horizontal, then vertical.
+    blur_buffers: [wgpu::Buffer; 2],
+    views: Vec<ViewBinding>,
+    targets: Option<Targets>,
+}
+
+impl FluidSurface {
+    /// Creates the renderer for particles of the radius, drawn into render passes with the
+    /// current MSAA settings. It must be recreated if those change.
+    pub fn new(
+        gpu: &GPUInterface,
+        light_bind_group_layout: &BindGroupLayout,
+        particle_radius: f32,
+    ) -> FluidSurface {
+        let device = &gpu.device;
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("fluid_uniform_bind_group_layout"),
+            });
+        let depth_texture_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        };
+        let blur_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    depth_texture_entry,
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("fluid_blur_bind_group_layout"),
+            });
+        let surface_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[depth_texture_entry],
+                label: Some("fluid_surface_bind_group_layout"),
+            });
+
+        let depth_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fluid Depth Shader"),
+            source: wgpu::ShaderSource::Wgsl(DEPTH_SHADER.into()),
+        });
+        let blur_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fluid Blur Shader"),
+            source: wgpu::ShaderSource::Wgsl(BLUR_SHADER.into()),
+        });
+        let shade_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fluid Shade Shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADE_SHADER.into()),
+        });
+
+        let depth_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Fluid Depth Pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Fluid Depth Pipeline Layout"),
+                    bind_group_layouts: &[&uniform_bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &depth_shader,
+                entry_point: "vs_main",
+                buffers: &[InstanceRaw::desc::<5>()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &depth_shader,
+                entry_point: "fs_main",
+                targets: &[Some(FLUID_DEPTH_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let blur_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Fluid Blur Pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Fluid Blur Pipeline Layout"),
+                    bind_group_layouts: &[&blur_bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &blur_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &blur_shader,
+                entry_point: "fs_main",
+                targets: &[Some(FLUID_DEPTH_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // The surface and sky are drawn into the scene's render pass.
+        let scene_pipeline = |label: &str,
+                              layout: &wgpu::PipelineLayout,
+                              fragment_entry_point: &str,
+                              depth: wgpu::DepthStencilState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: &shade_shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shade_shader,
+                    entry_point: fragment_entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gpu.config.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(depth),
+                multisample: wgpu::MultisampleState {
+                    count: gpu.settings.msaa_samples,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let surface_pipeline = scene_pipeline(
+            "Fluid Surface Pipeline",
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Fluid Surface Pipeline Layout"),
+                bind_group_layouts: &[
+                    &uniform_bind_group_layout,
+                    &surface_bind_group_layout,
+                    light_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }),
+            "fs_surface",
+            wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            },
+        );
+        // The sky is at the far plane, so it's only drawn where nothing else has been.
+        let sky_pipeline = scene_pipeline(
+            "Sky Pipeline",
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Sky Pipeline Layout"),
+                bind_group_layouts: &[&uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            }),
+            "fs_sky",
+            wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            },
+        );
+
+        let blur_buffer = |direction| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Fluid Blur Buffer"),
+                contents: bytemuck::cast_slice(&[BlurUniform {
+                    direction,
+                    _padding: [0; 2],
+                }]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+        };
+        let blur_buffers = [blur_buffer([1, 0]), blur_buffer([0, 1])];
+
+        FluidSurface {
+            particle_radius,
+            depth_pipeline,
+            blur_pipeline,
+            surface_pipeline,
+            sky_pipeline,
+            uniform_bind_group_layout,
+            blur_bind_group_layout,
+            surface_bind_group_layout,
+            blur_buffers,
+            views: Vec::new(),
+            targets: None,
+        }
+    }
+
+    /// Encodes drawing the particles' depth and smoothing it into the fluid's surface, which
+    /// draw() then shades. The particles are drawn as spheres of the fluid's particle radius
+    /// centered on the entity's instances.
+    pub fn encode(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        gpu: &GPUInterface,
+        camera_bundle: &CameraBundle,
+        particles: &ColoredMeshEntity,
+    ) {
+        self.update(gpu, camera_bundle);
+        let targets = self.targets.as_ref().unwrap();
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Fluid Depth Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.depth,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &targets.impostor_depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            render_pass.set_pipeline(&self.depth_pipeline);
+            for (camera_view, view_binding) in camera_bundle.views.iter().zip(self.views.iter()) {
+                camera_view.set_viewport(&mut render_pass, gpu);
+                render_pass.set_bind_group(0, &view_binding.bind_group, &[]);
+                particles.draw_impostors(&mut render_pass);
+            }
+        }
+
+        self.blur(
+            encoder,
+            &targets.blurred,
+            &targets.horizontal_blur_bind_group,
+        );
+        self.blur(
+            encoder,
+            &targets.smoothed,
+            &targets.vertical_blur_bind_group,
+        );
+    }
+
+    /// Draws the fluid's surface for the camera bundle's view at view_index, and the sky
+    /// behind the scene, after the scene's opaque geometry in its render pass.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        view_index: usize,
+        light_bind_group: &'a wgpu::BindGroup,
+    ) {
+        let (targets, view_binding) = match (&self.targets, self.views.get(view_index)) {
+            (Some(targets), Some(view_binding)) => (targets, view_binding),
+            _ => return,
+        };
+        render_pass.set_pipeline(&self.surface_pipeline);
+        render_pass.set_bind_group(0, &view_binding.bind_group, &[]);
+        render_pass.set_bind_group(1, &targets.surface_bind_group, &[]);
+        render_pass.set_bind_group(2, light_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        render_pass.set_pipeline(&self.sky_pipeline);
+        render_pass.set_bind_group(0, &view_binding.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn blur(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Fluid Blur Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.blur_pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Recreates the targets if the render size changed, and updates the views' uniforms.
+    fn update(&mut self, gpu: &GPUInterface, camera_bundle: &CameraBundle) {
+        let size = gpu.render_size();
+        if self.targets.as_ref().map(|targets| targets.size) != Some(size) {
+            self.targets = Some(self.create_targets(gpu, size));
+        }
+        while self.views.len() < camera_bundle.views.len() {
+            let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Fluid Buffer"),
+                size: std::mem::size_of::<FluidUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.uniform_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+                label: Some("fluid_uniform_bind_group"),
+            });
+            self.views.push(ViewBinding { buffer, bind_group });
+        }
+        self.views.truncate(camera_bundle.views.len());
+        for (camera_view, view_binding) in camera_bundle.views.iter().zip(self.views.iter()) {
+            let uniform = FluidUniform::new(camera_bundle, camera_view, size, self.particle_radius);
+            gpu.queue
+                .write_buffer(&view_binding.buffer, 0, bytemuck::cast_slice(&[uniform]));
+        }
+    }
+
+    fn create_targets(&self, gpu: &GPUInterface, size: wgpu::Extent3d) -> Targets {
+        let create_view = |label| {
+            gpu.device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: FLUID_DEPTH_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let depth = create_view("fluid depth");
+        let blurred = create_view("fluid blurred depth");
+        let smoothed = create_view("fluid smoothed depth");
+        let blur_bind_group = |input, buffer: &wgpu::Buffer| {
+            gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.blur_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(input),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("fluid_blur_bind_group"),
+            })
+        };
+        let horizontal_blur_bind_group = blur_bind_group(&depth, &self.blur_buffers[0]);
+        let vertical_blur_bind_group = blur_bind_group(&blurred, &self.blur_buffers[1]);
+        let surface_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.surface_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&smoothed),
+            }],
+            label: Some("fluid_surface_bind_group"),
+        });
+        Targets {
+            size,
+            depth,
+            impostor_depth: texture::Texture::create_depth_texture(
+                &gpu.device,
+                size,
+                1,
+                "fluid impostor depth texture",
+            ),
+            blurred,
+            smoothed,
+            horizontal_blur_bind_group,
+            vertical_blur_bind_group,
+            surface_bind_group,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BLUR_SHADER, DEPTH_SHADER, SHADE_SHADER};
+
+    #[test]
+    fn shaders_validate() {
+        for (name, source) in [
+            ("depth", DEPTH_SHADER),
+            ("blur", BLUR_SHADER),
+            ("shade", SHADE_SHADER),
+        ] {
+            let module = naga::front::wgsl::parse_str(source)
+                .unwrap_or_else(|err| panic!("{} shader should parse: {:?}", name, err));
+            naga::valid::Validator::new(
+                naga::valid::ValidationFlags::all(),
+                naga::valid::Capabilities::empty(),
+            )
+            .validate(&module)
+            .unwrap_or_else(|err| panic!("{} shader should validate: {:?}", name, err));
+        }
+    }
+}
@@ -3,6 +3,8 @@ use super::model::{self, ColoredMesh};
 
 use cgmath::Vector3;
 
+pub use crate::simulation::geometry::{get_cube_vertices, get_sphere_vertices};
+
 #[allow(dead_code)]
 pub fn get_cube_interior_normals(device: &wgpu::Device, color: [f32; 3]) -> model::ColoredMesh {
     // Cubes with averaged vertex normals look bad withoutholding edges. So we'll use non-averaged
@@ -13,7 +15,7 @@ pub fn get_cube_interior_normals(device: &wgpu::Device, color: [f32; 3]) -> mode
         .iter()
         .map(|i| -> Vector3<f32> { vertex_positions[*i] })
         .collect();
-    let vertex_indices = Vec::from_iter(0..vertex_positions.len() as u16);
+    let vertex_indices = Vec::from_iter(0..vertex_positions.len() as u32);
 
     ColoredMesh::new(
         device,
@@ -30,121 +32,19 @@ pub fn generate_sphere(
     device: &wgpu::Device,
     color: [f32; 3],
     radius: f32,
-    sectors: u16,
-    stacks: u16,
+    sectors: u32,
+    stacks: u32,
 ) -> model::ColoredMesh {
-    let sector_step = 2.0 * std::f32::consts::PI / sectors as f32;
-    let stack_step = std::f32::consts::PI / stacks as f32;
-
-    let mut vertex_positions = Vec::new();
-    for i in 0..=stacks {
-        let stack_angle = std::f32::consts::PI / 2.0 - i as f32 * stack_step;
-        let xy = radius * f32::cos(stack_angle);
-        let z = radius * f32::sin(stack_angle);
-
-        for j in 0..=sectors {
-            let sector_angle = j as f32 * sector_step;
-            let x = xy * f32::cos(sector_angle);
-            let y = xy * f32::sin(sector_angle);
-            vertex_positions.push(cgmath::Vector3 { x, y, z });
-        }
-    }
-
-    // generate CCW index list of sphere triangles
-    // k1--k1+1
-    // |  / |
-    // | /  |
-    // k2--k2+1
-    let mut vertex_indices: Vec<u16> = Vec::new();
-
-    for i in 0..stacks {
-        let mut k1 = i * (sectors + 1);
-        let mut k2 = k1 + sectors + 1;
-
-        for _j in 0..sectors {
-            // First and last stacks do not need quads, just tris.
-            if i != 0 {
-                vertex_indices.push(k1);
-                vertex_indices.push(k2);
-                vertex_indices.push(k1 + 1);
-            }
-            if i != (stacks - 1) {
-                vertex_indices.push(k1 + 1);
-                vertex_indices.push(k2);
-                vertex_indices.push(k2 + 1);
-            }
-            k1 = k1 + 1;
-            k2 = k2 + 1;
-        }
-    }
-
+    let (vertex_positions, vertex_indices) = get_sphere_vertices(radius, sectors, stacks);
     ColoredMesh::new(
         device,
         "Colored Sphere".to_string(),
         vertex_positions,
-        vertex_indices,
+        vertex_indices.iter().map(|i| *i as u32).collect(),
         color,
     )
 }
 
-/// Returns the vertices and indices for a 1 x 1 x 1 cube centered around (0,0,0).
-pub fn get_cube_vertices() -> (Vec<Vector3<f32>>, Vec<usize>) {
-    let vertex_positions = vec![
-        // front
-        cgmath::Vector3 {
-            x: -0.5,
-            y: -0.5,
-            z: 0.5,
-        },
-        cgmath::Vector3 {
-            x: 0.5,
-            y: -0.5,
-            z: 0.5,
-        },
-        cgmath::Vector3 {
-            x: 0.5,
-            y: 0.5,
-            z: 0.5,
-        },
-        cgmath::Vector3 {
-            x: -0.5,
-            y: 0.5,
-            z: 0.5,
-        },
-        cgmath::Vector3 {
-            x: -0.5,
-            y: -0.5,
-            z: -0.5,
-        },
-        cgmath::Vector3 {
-            x: 0.5,
-            y: -0.5,
-            z: -0.5,
-        },
-        cgmath::Vector3 {
-            x: 0.5,
-            y: 0.5,
-            z: -0.5,
-        },
-        cgmath::Vector3 {
-            x: -0.5,
-            y: 0.5,
-            z: -0.5,
-        },
-    ];
-
-    let indices: Vec<usize> = vec![
-        0, 1, 2, 2, 3, 0, // front
-        1, 5, 6, 6, 2, 1, // right
-        7, 6, 5, 5, 4, 7, // back
-        4, 0, 3, 3, 7, 4, // left
-        4, 5, 1, 1, 0, 4, // bottom
-        3, 2, 6, 6, 7, 3, // top
-    ];
-
-    (vertex_positions, indices)
-}
-
 pub fn get_cube_interior_normals_vertices() -> (Vec<Vector3<f32>>, Vec<usize>) {
     let vertex_positions = vec![
         // front
@@ -202,7 +102,6 @@ pub fn get_cube_interior_normals_vertices() -> (Vec<Vector3<f32>>, Vec<usize>) {
     (vertex_positions, indices)
 }
 
-#[allow(dead_code)]
 pub fn get_cube(device: &wgpu::Device, color: [f32; 3]) -> model::ColoredMesh {
     let (vertex_positions, indices) = get_cube_vertices();
 
@@ -212,7 +111,7 @@ pub fn get_cube(device: &wgpu::Device, color: [f32; 3]) -> model::ColoredMesh {
         .iter()
         .map(|i| -> cgmath::Vector3<f32> { vertex_positions[*i] })
         .collect();
-    let vertex_indices = Vec::from_iter(0..vertex_positions.len() as u16);
+    let vertex_indices = Vec::from_iter(0..vertex_positions.len() as u32);
 
     ColoredMesh::new(
         device,
@@ -268,7 +167,7 @@ pub fn get_cube_kilter(device: &wgpu::Device, color: [f32; 3]) -> model::Colored
         },
     ];
 
-    let indices: Vec<u16> = vec![
+    let indices: Vec<u32> = vec![
         0, 1, 2, 2, 3, 0, // front
         1, 5, 6, 6, 2, 1, // right
         7, 6, 5, 5, 4, 7, // back
@@ -283,7 +182,7 @@ pub fn get_cube_kilter(device: &wgpu::Device, color: [f32; 3]) -> model::Colored
         .iter()
         .map(|i| -> cgmath::Vector3<f32> { vertex_positions[*i as usize] })
         .collect();
-    let vertex_indices = Vec::from_iter(0..vertex_positions.len() as u16);
+    let vertex_indices = Vec::from_iter(0..vertex_positions.len() as u32);
 
     ColoredMesh::new(
         device,
@@ -319,7 +218,7 @@ pub fn get_quad(device: &wgpu::Device, color: [f32; 3]) -> model::ColoredMesh {
             z: -0.5,
         },
     ];
-    let vertex_indices: Vec<u16> = vec![1, 3, 2, 2, 0, 1];
+    let vertex_indices: Vec<u32> = vec![1, 3, 2, 2, 0, 1];
 
     ColoredMesh::new(
         device,
@@ -359,7 +258,7 @@ pub fn get_hexagon(device: &wgpu::Device, color: [f32; 3]) -> model::ColoredMesh
             z: 0.0,
         },
     ];
-    let vertex_indices: Vec<u16> = vec![0, 1, 4, 1, 2, 4, 2, 3, 4];
+    let vertex_indices: Vec<u32> = vec![0, 1, 4, 1, 2, 4, 2, 3, 4];
 
     ColoredMesh::new(
         device,
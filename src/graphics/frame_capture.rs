@@ -0,0 +1,143 @@
+/// Captures rendered frames to numbered PNG files, which can be combined into a video, e.g.
+/// `ffmpeg -framerate 30 -i captures/capture_0/frame_%05d.png demo.mp4`.
+use std::path::PathBuf;
+
+use super::gpu_interface::GPUInterface;
+
+/// The directory captures are saved in, relative to the working directory.
+pub const CAPTURE_DIRECTORY: &str = "captures";
+
+/// A frame copied from the surface into a buffer, waiting to be mapped and saved.
+struct PendingFrame {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+}
+
+pub struct FrameCapture {
+    directory: PathBuf,
+    next_frame: u32,
+    pending: Option<PendingFrame>,
+}
+
+impl FrameCapture {
+    pub fn new() -> FrameCapture {
+        FrameCapture {
+            directory: PathBuf::from(CAPTURE_DIRECTORY),
+            next_frame: 0,
+            pending: None,
+        }
+    }
+
+    /// Starts a new capture in the first unused numbered directory under CAPTURE_DIRECTORY.
+    pub fn start(&mut self) {
+        let mut index = 0;
+        self.directory = loop {
+            let directory = PathBuf::from(CAPTURE_DIRECTORY).join(format!("capture_{}", index));
+            if !directory.exists() {
+                break directory;
+            }
+            index += 1;
+        };
+        self.next_frame = 0;
+        self.pending = None;
+    }
+
+    /// Encodes copying the texture into a buffer, to be saved by save() once submitted.
+    /// The texture must have been created with COPY_SRC usage.
+    pub fn encode(&mut self, gpu: &GPUInterface, texture: &wgpu::Texture) -> wgpu::CommandBuffer {
+        let width = gpu.config.width;
+        let height = gpu.config.height;
+        let bytes_per_row = width * 4;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = bytes_per_row.div_ceil(alignment) * alignment;
+        let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Capture Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Frame Capture Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.pending = Some(PendingFrame {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra: matches!(
+                gpu.config.format,
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+        });
+        encoder.finish()
+    }
+
+    /// Waits for the frame copied by encode() and saves it as the next PNG in the capture.
+    /// Does nothing if no frame is pending.
+    pub fn save(&mut self, gpu: &GPUInterface) -> anyhow::Result<()> {
+        let frame = match self.pending.take() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let slice = frame.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        gpu.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+
+        let mut pixels = Vec::with_capacity((frame.width * frame.height * 4) as usize);
+        for row in slice
+            .get_mapped_range()
+            .chunks(frame.padded_bytes_per_row as usize)
+        {
+            pixels.extend_from_slice(&row[..(frame.width * 4) as usize]);
+        }
+        frame.buffer.unmap();
+        if frame.bgra {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        let image = image::RgbaImage::from_raw(frame.width, frame.height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("Captured frame has the wrong size"))?;
+
+        std::fs::create_dir_all(&self.directory)?;
+        image.save(
+            self.directory
+                .join(format!("frame_{:05}.png", self.next_frame)),
+        )?;
+        self.next_frame += 1;
+        Ok(())
+    }
+}
+
+impl Default for FrameCapture {
+    fn default() -> Self {
+        FrameCapture::new()
+    }
+}
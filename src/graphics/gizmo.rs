@@ -0,0 +1,338 @@
+/// Gizmos for arranging a scene with the mouse: handles along the world axes, drawn with DebugDraw, which
+/// translate, rotate, or scale whatever they're placed on as they're dragged.
+use super::debug_draw::{self, DebugDraw};
+
+use cgmath::{InnerSpace, Vector3};
+
+/// Handles are picked within this fraction of the gizmo's size of the cursor's ray.
+const PICK_TOLERANCE: f32 = 0.08;
+/// The number of segments each rotation ring is drawn with.
+const RING_SEGMENTS: usize = 32;
+/// Scaling by dragging past the center would flip the object, so the scale handle stops this fraction of
+/// the gizmo's size out from it.
+const MIN_SCALE_DISTANCE: f32 = 0.05;
+
+const AXES: [Vector3<f32>; 3] = [
+    Vector3::new(1.0, 0.0, 0.0),
+    Vector3::new(0.0, 1.0, 0.0),
+    Vector3::new(0.0, 0.0, 1.0),
+];
+const AXIS_COLORS: [[f32; 3]; 3] = [debug_draw::RED, debug_draw::GREEN, debug_draw::BLUE];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub const ALL: [GizmoMode; 3] = [GizmoMode::Translate, GizmoMode::Rotate, GizmoMode::Scale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GizmoMode::Translate => "Translate",
+            GizmoMode::Rotate => "Rotate",
+            GizmoMode::Scale => "Scale",
+        }
+    }
+}
+
+/// How far a gizmo's drag moved what it's placed on since the drag was last updated.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GizmoEdit {
+    Translate(Vector3<f32>),
+    /// An angle in radians, counterclockwise about the axis.
+    Rotate(Vector3<f32>, f32),
+    /// The factor the object's size is scaled by.
+    Scale(f32),
+}
+
+/// A handle being dragged, and where along it the cursor was when the drag was last updated: a distance
+/// along the axis from the gizmo's center, or an angle about it. The center is kept from when the drag
+/// began, since the object moves with the drag.
+#[derive(Debug, Copy, Clone)]
+struct Drag {
+    axis: usize,
+    center: Vector3<f32>,
+    parameter: f32,
+}
+
+/// A gizmo placed on an object, with a handle along each of the axes the object may be edited along.
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    center: Vector3<f32>,
+    /// The length of the translate and scale handles, and the radius of the rotation rings.
+    size: f32,
+    axes: [bool; 3],
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    pub fn new(mode: GizmoMode) -> Gizmo {
+        Gizmo {
+            mode,
+            center: Vector3::new(0.0, 0.0, 0.0),
+            size: 1.0,
+            axes: [true; 3],
+            drag: None,
+        }
+    }
+
+    /// Places the gizmo on the center of an object, with handles along the axes it may be edited along,
+    /// e.g. only the vertical axis to rotate an object by its yaw.
+    pub fn place(&mut self, center: Vector3<f32>, size: f32, axes: [bool; 3]) {
+        self.center = center;
+        self.size = size;
+        self.axes = axes;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Starts dragging the handle under the cursor's ray, if there is one, returning whether there was.
+    pub fn begin_drag(&mut self, origin: Vector3<f32>, direction: Vector3<f32>) -> bool {
+        self.drag = self.pick(origin, direction).and_then(|axis| {
+            self.get_parameter(axis, self.center, origin, direction)
+                .map(|parameter| Drag {
+                    axis,
+                    center: self.center,
+                    parameter,
+                })
+        });
+        self.drag.is_some()
+    }
+
+    /// Follows the cursor's ray with the dragged handle, returning the edit since the last update.
+    pub fn update_drag(
+        &mut self,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+    ) -> Option<GizmoEdit> {
+        let drag = self.drag?;
+        let parameter = self.get_parameter(drag.axis, drag.center, origin, direction)?;
+        self.drag = Some(Drag { parameter, ..drag });
+        let axis = AXES[drag.axis];
+        Some(match self.mode {
+            GizmoMode::Translate => GizmoEdit::Translate(axis * (parameter - drag.parameter)),
+            GizmoMode::Rotate => {
+                // The shortest way around, so crossing from π to -π doesn't spin the object back.
+                let angle = (parameter - drag.parameter + std::f32::consts::PI)
+                    .rem_euclid(std::f32::consts::TAU)
+                    - std::f32::consts::PI;
+                GizmoEdit::Rotate(axis, angle)
+            }
+            GizmoMode::Scale => GizmoEdit::Scale(parameter / drag.parameter),
+        })
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag = None;
+    }
+
+    /// The handle under the cursor's ray, the nearest to it if several are within the tolerance.
+    pub fn pick(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<usize> {
+        let tolerance = PICK_TOLERANCE * self.size;
+        (0..3)
+            .filter(|axis| self.axes[*axis])
+            .filter_map(|axis| {
+                let distance = match self.mode {
+                    GizmoMode::Translate | GizmoMode::Scale => {
+                        ray_segment_distance(origin, direction, self.center, AXES[axis] * self.size)
+                    }
+                    GizmoMode::Rotate => {
+                        let hit =
+                            ray_plane_intersection(origin, direction, self.center, AXES[axis])?;
+                        ((hit - self.center).magnitude() - self.size).abs()
+                    }
+                };
+                (distance <= tolerance).then_some((axis, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(axis, _)| axis)
+    }
+
+    /// Where the cursor's ray is along the handle: its distance along the axis through the center for the
+    /// translate and scale handles, or its angle about the axis for the rotation rings.
+    fn get_parameter(
+        &self,
+        axis: usize,
+        center: Vector3<f32>,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+    ) -> Option<f32> {
+        let axis_direction = AXES[axis];
+        match self.mode {
+            GizmoMode::Translate => closest_parameter(origin, direction, center, axis_direction),
+            GizmoMode::Scale => closest_parameter(origin, direction, center, axis_direction)
+                .map(|t| t.max(MIN_SCALE_DISTANCE * self.size)),
+            GizmoMode::Rotate => {
+                let offset =
+                    ray_plane_intersection(origin, direction, center, axis_direction)? - center;
+                let (u, v) = ring_basis(axis);
+                Some(offset.dot(v).atan2(offset.dot(u)))
+            }
+        }
+    }
+
+    /// Draws the handles, the dragged one in yellow.
+    pub fn draw(&self, debug_draw: &mut DebugDraw) {
+        for axis in (0..3).filter(|axis| self.axes[*axis]) {
+            let color = match self.drag {
+                Some(drag) if drag.axis == axis => debug_draw::YELLOW,
+                _ => AXIS_COLORS[axis],
+            };
+            let tip = self.center + AXES[axis] * self.size;
+            match self.mode {
+                GizmoMode::Translate => {
+                    debug_draw.line(self.center, tip, color);
+                    // Arrowheads, swept back from the tip.
+                    let (u, v) = ring_basis(axis);
+                    let back = -AXES[axis] * self.size * 0.15;
+                    for barb in [u, -u, v, -v] {
+                        debug_draw.arrow(tip, back + barb * self.size * 0.06, color);
+                    }
+                }
+                GizmoMode::Scale => {
+                    debug_draw.line(self.center, tip, color);
+                    debug_draw.cross(tip, self.size * 0.12, color);
+                }
+                GizmoMode::Rotate => {
+                    let (u, v) = ring_basis(axis);
+                    let point = |i: usize| {
+                        let angle = i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                        self.center + (u * angle.cos() + v * angle.sin()) * self.size
+                    };
+                    for i in 0..RING_SEGMENTS {
+                        debug_draw.line(point(i), point(i + 1), color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Two directions spanning the plane perpendicular to the axis, ordered so that angles from the first
+/// towards the second are counterclockwise about the axis.
+fn ring_basis(axis: usize) -> (Vector3<f32>, Vector3<f32>) {
+    (AXES[(axis + 1) % 3], AXES[(axis + 2) % 3])
+}
+
+/// The distance along the line through the point, in the unit direction, of its closest point to the ray.
+/// None if the ray is parallel to the line.
+fn closest_parameter(
+    origin: Vector3<f32>,
+    direction: Vector3<f32>,
+    point: Vector3<f32>,
+    line_direction: Vector3<f32>,
+) -> Option<f32> {
+    let w = origin - point;
+    let b = line_direction.dot(direction);
+    let denominator = 1.0 - b * b;
+    if denominator < 1e-6 {
+        return None;
+    }
+    Some((line_direction.dot(w) - b * direction.dot(w)) / denominator)
+}
+
+/// The distance between the ray and the segment from the start along the vector.
+fn ray_segment_distance(
+    origin: Vector3<f32>,
+    direction: Vector3<f32>,
+    start: Vector3<f32>,
+    segment: Vector3<f32>,
+) -> f32 {
+    let length = segment.magnitude();
+    let segment_direction = segment / length;
+    let t = closest_parameter(origin, direction, start, segment_direction)
+        .unwrap_or(0.0)
+        .clamp(0.0, length);
+    let point = start + segment_direction * t;
+    let s = (point - origin).dot(direction).max(0.0);
+    (origin + direction * s - point).magnitude()
+}
+
+/// Where the ray crosses the plane through the point with the normal, if it does in front of its origin.
+fn ray_plane_intersection(
+    origin: Vector3<f32>,
+    direction: Vector3<f32>,
+    point: Vector3<f32>,
+    normal: Vector3<f32>,
+) -> Option<Vector3<f32>> {
+    let denominator = direction.dot(normal);
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+    let s = (point - origin).dot(normal) / denominator;
+    (s >= 0.0).then(|| origin + direction * s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::assert_relative_eq;
+
+    /// The ray from in front of the gizmo, along -z, through the point in the z = 0 plane.
+    fn ray_through(x: f32, y: f32) -> (Vector3<f32>, Vector3<f32>) {
+        (Vector3::new(x, y, 10.0), Vector3::new(0.0, 0.0, -1.0))
+    }
+
+    #[test]
+    fn picks_the_handle_under_the_cursor() {
+        let mut gizmo = Gizmo::new(GizmoMode::Translate);
+        gizmo.place(Vector3::new(0.0, 0.0, 0.0), 1.0, [true; 3]);
+        let (origin, direction) = ray_through(0.5, 0.02);
+        assert_eq!(gizmo.pick(origin, direction), Some(0));
+        let (origin, direction) = ray_through(0.02, 0.7);
+        assert_eq!(gizmo.pick(origin, direction), Some(1));
+        let (origin, direction) = ray_through(0.5, 0.5);
+        assert_eq!(gizmo.pick(origin, direction), None);
+        // Handles along axes the object can't be edited along aren't picked.
+        gizmo.place(Vector3::new(0.0, 0.0, 0.0), 1.0, [false, true, false]);
+        let (origin, direction) = ray_through(0.5, 0.02);
+        assert_eq!(gizmo.pick(origin, direction), None);
+    }
+
+    #[test]
+    fn drags_translate_along_their_axis() {
+        let mut gizmo = Gizmo::new(GizmoMode::Translate);
+        gizmo.place(Vector3::new(1.0, 0.0, 0.0), 1.0, [true; 3]);
+        let (origin, direction) = ray_through(1.5, 0.0);
+        assert!(gizmo.begin_drag(origin, direction));
+        // The cursor leaving the handle only moves the object along its axis.
+        let (origin, direction) = ray_through(2.0, 0.3);
+        let Some(GizmoEdit::Translate(translation)) = gizmo.update_drag(origin, direction) else {
+            panic!("expected a translation");
+        };
+        assert_relative_eq!(translation, Vector3::new(0.5, 0.0, 0.0), epsilon = 1e-5);
+        gizmo.end_drag();
+        assert!(!gizmo.is_dragging());
+    }
+
+    #[test]
+    fn drags_rotate_and_scale() {
+        let mut gizmo = Gizmo::new(GizmoMode::Rotate);
+        gizmo.place(Vector3::new(0.0, 0.0, 0.0), 1.0, [true; 3]);
+        // Around the z ring, from +x to +y, is a quarter turn counterclockwise.
+        let (origin, direction) = ray_through(1.0, 0.0);
+        assert!(gizmo.begin_drag(origin, direction));
+        let (origin, direction) = ray_through(0.0, 1.0);
+        let Some(GizmoEdit::Rotate(axis, angle)) = gizmo.update_drag(origin, direction) else {
+            panic!("expected a rotation");
+        };
+        assert_eq!(axis, Vector3::unit_z());
+        assert_relative_eq!(angle, std::f32::consts::FRAC_PI_2, epsilon = 1e-5);
+
+        let mut gizmo = Gizmo::new(GizmoMode::Scale);
+        gizmo.place(Vector3::new(0.0, 0.0, 0.0), 1.0, [true; 3]);
+        let (origin, direction) = ray_through(1.0, 0.0);
+        assert!(gizmo.begin_drag(origin, direction));
+        let (origin, direction) = ray_through(2.0, 0.0);
+        assert_eq!(
+            gizmo.update_drag(origin, direction),
+            Some(GizmoEdit::Scale(2.0))
+        );
+    }
+}
@@ -1,16 +1,52 @@
-use winit::window::Window;
+use winit::{dpi::PhysicalSize, window::Window};
+
+use super::ambient_occlusion::AmbientOcclusion;
+use super::camera::CameraBundle;
+use super::render_scale::{self, DynamicResolution, SceneTarget, Upsampler};
+use super::settings::{GraphicsSettings, TransparencyMode, WindowMode};
+use super::texture;
+use super::transparency::{OitCompositor, OitTargets};
 
 pub struct GPUInterface {
     pub surface: wgpu::Surface,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+    /// The surface's configuration, at the last size it could be rendered at.
     pub config: wgpu::SurfaceConfiguration,
-    pub size: winit::dpi::PhysicalSize<u32>,
+    /// The window's size, which is 0×0 on some platforms while it's minimized.
+    pub size: PhysicalSize<u32>,
+    pub settings: GraphicsSettings,
+    /// The multisampled color target which is resolved to the surface, if MSAA is enabled.
+    pub msaa_framebuffer: Option<wgpu::TextureView>,
+    /// Whether the surface can be copied from, to capture frames.
+    pub supports_frame_capture: bool,
+    /// The current resolution of the 3D view relative to the surface's.
+    pub render_scale: f32,
+    /// The target the scene is rendered to when the render scale is below 1.
+    scene_target: Option<SceneTarget>,
+    upsampler: Upsampler,
+    dynamic_resolution: DynamicResolution,
+    /// The targets transparent particles are accumulated in, if order-independent transparency
+    /// is enabled.
+    oit_targets: Option<OitTargets>,
+    oit_compositor: OitCompositor,
+    /// The ambient occlusion pass, if enabled.
+    ambient_occlusion: Option<AmbientOcclusion>,
 }
 
 impl GPUInterface {
+    /// Creates the interface with the persisted graphics settings, updated to match the window
+    /// as it was actually created, e.g. with a resolution given on the command line.
     pub fn new(window: &Window) -> GPUInterface {
         let size = window.inner_size();
+        let mut settings = GraphicsSettings::load();
+        settings.window_mode = WindowMode::of_window(window);
+        // A fullscreen window's size is the monitor's, which shouldn't replace the resolution
+        // to use when windowed.
+        if settings.window_mode == WindowMode::Windowed {
+            settings.width = size.width;
+            settings.height = size.height;
+        }
 
         // The instance is a handle to our GPU.
         // Its main purpose is to create Adapters and Surfaces.
@@ -43,20 +79,266 @@ impl GPUInterface {
         ))
         .unwrap();
 
+        // A window created minimized is configured once it's shown.
+        let surface_size = surface_size(size).unwrap_or(PhysicalSize::new(1, 1));
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface.get_supported_formats(&adapter)[0],
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            width: surface_size.width,
+            height: surface_size.height,
+            present_mode: settings.present_mode(),
         };
-        surface.configure(&device, &config);
-        GPUInterface {
+        // GL surfaces can only be rendered to. Other backends' surfaces can be copied from, and
+        // wgpu 0.13 can't query the supported usages, so configuring an unsupported one panics.
+        let supports_frame_capture = adapter.get_info().backend != wgpu::Backend::Gl;
+        let upsampler = Upsampler::new(&device, config.format);
+        let oit_compositor = OitCompositor::new(&device, config.format);
+        let mut gpu = GPUInterface {
             surface,
             device,
             queue,
             config,
             size,
+            settings,
+            msaa_framebuffer: None,
+            supports_frame_capture,
+            render_scale: settings.render_scale,
+            scene_target: None,
+            upsampler,
+            dynamic_resolution: DynamicResolution::default(),
+            oit_targets: None,
+            oit_compositor,
+            ambient_occlusion: None,
+        };
+        gpu.configure();
+        gpu
+    }
+
+    /// Configures the surface with the current config, and recreates the scene's render targets
+    /// to match it. A suspended surface is configured once its window's resized to be shown again.
+    pub fn configure(&mut self) {
+        if !self.is_suspended() {
+            self.surface.configure(&self.device, &self.config);
+        }
+        self.create_render_targets();
+    }
+
+    /// Whether there's nothing to render to, e.g. while the window's minimized.
+    pub fn is_suspended(&self) -> bool {
+        surface_size(self.size).is_none()
+    }
+
+    /// The surface's next frame to render to, or None if there's nothing to render this frame: while the
+    /// surface is suspended, if acquiring the frame timed out, or if the surface was lost or outdated, in
+    /// which case it's reconfigured to render to next frame.
+    pub fn acquire_frame(&mut self) -> Option<wgpu::SurfaceTexture> {
+        if self.is_suspended() {
+            return None;
+        }
+        match self.surface.get_current_texture() {
+            Ok(frame) => Some(frame),
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.configure();
+                None
+            }
+            Err(wgpu::SurfaceError::Timeout) => None,
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                panic!("Out of memory acquiring the surface's next frame")
+            }
+        }
+    }
+
+    /// The size the scene is rendered at, which depth textures should match.
+    pub fn render_size(&self) -> wgpu::Extent3d {
+        render_scale::scaled_extent(self.config.width, self.config.height, self.render_scale)
+    }
+
+    /// The view to render the scene to; the surface's unless the render scale is reduced.
+    pub fn scene_view<'a>(&'a self, surface_view: &'a wgpu::TextureView) -> &'a wgpu::TextureView {
+        match &self.scene_target {
+            Some(scene_target) => &scene_target.view,
+            None => surface_view,
+        }
+    }
+
+    /// Encodes drawing the scene over the surface, if it was rendered at a reduced scale.
+    /// This should follow rendering the scene, and precede rendering the GUI.
+    pub fn upsample(&self, encoder: &mut wgpu::CommandEncoder, surface_view: &wgpu::TextureView) {
+        if let Some(scene_target) = &self.scene_target {
+            self.upsampler.draw(encoder, scene_target, surface_view);
+        }
+    }
+
+    /// Encodes darkening the scene by its ambient occlusion, computed from the depth texture
+    /// it was rendered with, if enabled.
+    /// This should follow rendering the scene's opaque geometry, and precede upsampling.
+    pub fn apply_ambient_occlusion(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: &wgpu::TextureView,
+        depth_texture: &texture::Texture,
+        camera_bundle: &CameraBundle,
+    ) {
+        let size = self.render_size();
+        let scene_view = match &self.scene_target {
+            Some(scene_target) => &scene_target.view,
+            None => surface_view,
+        };
+        if let Some(ambient_occlusion) = &mut self.ambient_occlusion {
+            ambient_occlusion.apply(
+                &self.device,
+                &self.queue,
+                encoder,
+                scene_view,
+                depth_texture,
+                camera_bundle,
+                size,
+            );
+        }
+    }
+
+    /// The targets to accumulate transparent particles in, if order-independent transparency
+    /// is enabled.
+    pub fn oit_targets(&self) -> Option<&OitTargets> {
+        self.oit_targets.as_ref()
+    }
+
+    /// Encodes compositing the accumulated transparent particles over the scene, if
+    /// order-independent transparency is enabled.
+    /// This should follow the accumulation pass, and precede upsampling.
+    pub fn composite_transparency(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: &wgpu::TextureView,
+    ) {
+        if let Some(oit_targets) = &self.oit_targets {
+            self.oit_compositor
+                .composite(encoder, oit_targets, self.scene_view(surface_view));
+        }
+    }
+
+    /// Adjusts the render scale for the frame time if dynamic resolution is enabled.
+    /// Returns true if the scale changed, in which case depth textures must be recreated
+    /// to match render_size().
+    pub fn update_render_scale(&mut self, frame_time: std::time::Duration) -> bool {
+        // Frames aren't drawn while suspended, so their times say nothing about the render scale.
+        if !self.settings.dynamic_resolution || self.is_suspended() {
+            return false;
+        }
+        let scale = self
+            .dynamic_resolution
+            .update(frame_time, self.render_scale, &self.settings);
+        if scale == self.render_scale {
+            return false;
         }
+        self.render_scale = scale;
+        self.create_render_targets();
+        true
+    }
+
+    /// Recreates the MSAA framebuffer, scene target, transparency targets, and ambient
+    /// occlusion pass at the render size.
+    fn create_render_targets(&mut self) {
+        let size = self.render_size();
+        self.ambient_occlusion =
+            self.settings
+                .ambient_occlusion
+                .sample_count()
+                .map(|sample_count| {
+                    AmbientOcclusion::new(
+                        &self.device,
+                        self.config.format,
+                        size,
+                        self.settings.msaa_samples,
+                        sample_count,
+                        self.settings.ambient_occlusion_radius,
+                    )
+                });
+        self.oit_targets = match self.settings.transparency_mode {
+            TransparencyMode::Opaque | TransparencyMode::Sorted => None,
+            TransparencyMode::WeightedBlended => Some(self.oit_compositor.create_targets(
+                &self.device,
+                size,
+                self.settings.msaa_samples,
+            )),
+        };
+        self.scene_target = if self.render_scale < 1.0 {
+            Some(
+                self.upsampler
+                    .create_target(&self.device, self.config.format, size),
+            )
+        } else {
+            None
+        };
+        self.msaa_framebuffer = if self.settings.msaa_samples > 1 {
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("msaa framebuffer"),
+                size,
+                mip_level_count: 1,
+                sample_count: self.settings.msaa_samples,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.config.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            });
+            Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+        } else {
+            None
+        };
+    }
+
+    /// Allows copying from the surface to capture frames, reconfiguring the surface.
+    /// Does nothing if frame capture isn't supported.
+    pub fn set_frame_capture(&mut self, enabled: bool) {
+        if !self.supports_frame_capture {
+            return;
+        }
+        self.config
+            .usage
+            .set(wgpu::TextureUsages::COPY_SRC, enabled);
+        self.configure();
+    }
+
+    /// Applies the vsync, MSAA, render scale, transparency, and ambient occlusion settings,
+    /// reconfiguring the surface. Depth textures must be recreated to match render_size() afterwards.
+    /// Returns true if the number of MSAA samples changed, in which case render pipelines
+    /// must also be recreated.
+    pub fn apply_settings(&mut self, settings: GraphicsSettings) -> bool {
+        let msaa_changed = settings.msaa_samples != self.settings.msaa_samples;
+        if settings.render_scale != self.settings.render_scale
+            || settings.dynamic_resolution != self.settings.dynamic_resolution
+        {
+            self.render_scale = settings.render_scale;
+        }
+        self.settings = settings;
+        self.config.present_mode = settings.present_mode();
+        self.configure();
+        msaa_changed
+    }
+}
+
+/// The size to configure a surface for a window of the size at, or None if it can't be rendered to, as when
+/// either side is 0.
+pub fn surface_size(window_size: PhysicalSize<u32>) -> Option<PhysicalSize<u32>> {
+    if window_size.width > 0 && window_size.height > 0 {
+        Some(window_size)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::surface_size;
+    use winit::dpi::PhysicalSize;
+
+    #[test]
+    fn zero_sized_windows_have_no_surface() {
+        assert_eq!(None, surface_size(PhysicalSize::new(0, 0)));
+        assert_eq!(None, surface_size(PhysicalSize::new(0, 720)));
+        assert_eq!(None, surface_size(PhysicalSize::new(1280, 0)));
+        assert_eq!(
+            Some(PhysicalSize::new(1280, 720)),
+            surface_size(PhysicalSize::new(1280, 720))
+        );
     }
 }
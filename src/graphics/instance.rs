@@ -1,25 +1,73 @@
 use super::gpu_interface::GPUInterface;
 
-use cgmath::{Rotation3, Vector3, Zero};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix3, Rotation3, SquareMatrix, Vector3, Zero};
 use wgpu::{Buffer, BufferDescriptor};
 
 /// Stores an instance's transformations.
+/// The scale is applied per-axis in the instance's local space, before rotation.
 pub struct Instance {
     pub position: cgmath::Vector3<f32>,
     pub rotation: cgmath::Quaternion<f32>,
-    pub scale: f32,
+    pub scale: cgmath::Vector3<f32>,
+    /// Replaces the colors of a colored mesh's vertices, if set, e.g. to color particles
+    /// by a simulated quantity. Textured meshes ignore it.
+    pub color: Option<[f32; 3]>,
 }
 
 impl Instance {
     pub fn to_raw(&self) -> InstanceRaw {
         let model = cgmath::Matrix4::from_translation(self.position)
             * cgmath::Matrix4::from(self.rotation)
-            * cgmath::Matrix4::from_scale(self.scale);
+            * cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z);
         InstanceRaw {
             model: model.into(),
-            normal: cgmath::Matrix3::from(self.rotation).into(),
+            normal: self.normal_matrix().into(),
+            // The alpha is the weight of the instance's color over the vertex colors.
+            color: match self.color {
+                Some([r, g, b]) => [r, g, b, 1.0],
+                None => [0.0; 4],
+            },
         }
     }
+
+    /// The inverse transpose of the upper 3x3 of the model matrix.
+    /// Since the rotation is orthonormal, this reduces to R * S^-1.
+    /// The result is not unit-length under non-uniform scale; shaders normalize the transformed normal.
+    fn normal_matrix(&self) -> Matrix3<f32> {
+        let inverse_scale = Matrix3::from_diagonal(Vector3::new(
+            1.0 / self.scale.x,
+            1.0 / self.scale.y,
+            1.0 / self.scale.z,
+        ));
+        Matrix3::from(self.rotation) * inverse_scale
+    }
+
+    /// Transforms the point from the instance's local space into world space.
+    pub fn transform_point(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.position
+            + self.rotation
+                * Vector3::new(
+                    point.x * self.scale.x,
+                    point.y * self.scale.y,
+                    point.z * self.scale.z,
+                )
+    }
+
+    /// The largest per-axis scale factor, useful for conservative bounds.
+    pub fn max_scale(&self) -> f32 {
+        self.scale.x.max(self.scale.y).max(self.scale.z)
+    }
+}
+
+/// Sorts the instances by increasing distance from the position, e.g. the camera's, so that
+/// opaque instances drawn in order are occluded by earlier ones and skipped by early depth testing.
+pub fn sort_front_to_back(instances: &mut [Instance], position: cgmath::Point3<f32>) {
+    let position = position.to_vec();
+    instances.sort_unstable_by(|a, b| {
+        (a.position - position)
+            .magnitude2()
+            .total_cmp(&(b.position - position).magnitude2())
+    });
 }
 
 impl Default for Instance {
@@ -30,7 +78,8 @@ impl Default for Instance {
                 cgmath::Vector3::unit_z(),
                 cgmath::Deg(0.0),
             ),
-            scale: 1.0,
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            color: None,
         }
     }
 }
@@ -41,6 +90,7 @@ impl Default for Instance {
 pub struct InstanceRaw {
     model: [[f32; 4]; 4],
     normal: [[f32; 3]; 3],
+    color: [f32; 4],
 }
 
 impl InstanceRaw {
@@ -92,6 +142,11 @@ impl InstanceRaw {
                     shader_location: LOCATION + 6,
                     format: wgpu::VertexFormat::Float32x3,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 25]>() as wgpu::BufferAddress,
+                    shader_location: LOCATION + 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -112,7 +167,10 @@ impl InstanceRaw {
         let buffer = gpu.device.create_buffer(&BufferDescriptor {
             label: Some("Instance Buffer"),
             size: capacity as u64 * std::mem::size_of::<InstanceRaw>() as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            usage: wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST
+                // So the instances can be read by compute passes, e.g. to sort them.
+                | wgpu::BufferUsages::STORAGE,
             mapped_at_creation: false,
         });
         // Note we don't need to declare buffer as mut because this only *schedules*
@@ -146,6 +204,31 @@ impl Default for InstanceRaw {
         InstanceRaw {
             model: [[0.0; 4]; 4],
             normal: [[0.0; 3]; 3],
+            color: [0.0; 4],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Point3, Vector3};
+
+    use super::{sort_front_to_back, Instance};
+
+    #[test]
+    fn sorts_front_to_back() {
+        let mut instances = [5.0, -1.0, 3.0, 0.5]
+            .into_iter()
+            .map(|z| Instance {
+                position: Vector3::new(0.0, 0.0, z),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        sort_front_to_back(&mut instances, Point3::new(0.0, 0.0, 4.0));
+        let order = instances
+            .iter()
+            .map(|instance| instance.position.z)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![5.0, 3.0, 0.5, -1.0], order);
+    }
+}
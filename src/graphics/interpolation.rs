@@ -0,0 +1,163 @@
+/// The last step of "fix your timestep": the simulations step by a fixed dt, so a frame usually falls partway
+/// between two steps, and drawing the latest step's state judders when that dt is large against the frame time.
+/// Instead, the poses of the instances drawn are recorded before each step, and each frame draws them blended from
+/// those toward the latest step's by the fraction of a step left in the accumulator, so drawing a step behind.
+/// https://gafferongames.com/post/fix_your_timestep/
+use super::instance::Instance;
+
+use cgmath::{Quaternion, Vector3, VectorSpace};
+use rustc_hash::FxHashMap;
+use std::{hash::Hash, time::Duration};
+
+/// How far a frame is from the step before last to the last, the fraction of a step left unsimulated.
+pub fn get_alpha(accumulator: Duration, timestep: Duration) -> f32 {
+    if timestep.is_zero() {
+        return 1.0;
+    }
+    (accumulator.as_secs_f32() / timestep.as_secs_f32()).clamp(0.0, 1.0)
+}
+
+/// The poses of a simulation's instances before its latest step, by a key of the body each instance draws.
+/// Keyed by the bodies' handles, a body spawned into a slot freed by another isn't blended from the other's pose.
+pub struct Interpolator<K = usize> {
+    previous: FxHashMap<K, (Vector3<f32>, Quaternion<f32>)>,
+}
+
+impl<K> Default for Interpolator<K> {
+    fn default() -> Self {
+        Self {
+            previous: FxHashMap::default(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash> Interpolator<K> {
+    pub fn new() -> Interpolator<K> {
+        Interpolator::default()
+    }
+
+    /// Records the poses of the instances before a step, by the keys of their bodies, in the same order.
+    pub fn record_keyed(&mut self, keys: impl IntoIterator<Item = K>, instances: &[Instance]) {
+        self.previous.clear();
+        self.previous.extend(
+            keys.into_iter()
+                .zip(instances)
+                .map(|(key, instance)| (key, (instance.position, instance.rotation))),
+        );
+    }
+
+    /// Forgets the recorded poses, so that the next frames draw the latest step unblended, e.g. after a reset.
+    pub fn clear(&mut self) {
+        self.previous.clear();
+    }
+
+    /// Blends the positions and rotations of the instances after the latest step from the poses recorded for
+    /// their keys before it, by the alpha. Their scales and colors are the latest step's. Instances of bodies
+    /// spawned since the poses were recorded are left be.
+    pub fn blend_keyed(
+        &self,
+        keys: impl IntoIterator<Item = K>,
+        instances: &mut [Instance],
+        alpha: f32,
+    ) {
+        for (key, instance) in keys.into_iter().zip(instances.iter_mut()) {
+            if let Some((position, rotation)) = self.previous.get(&key) {
+                instance.position = position.lerp(instance.position, alpha);
+                instance.rotation = rotation.slerp(instance.rotation, alpha);
+            }
+        }
+    }
+}
+
+/// Instances keyed by their order, for bodies which are never spawned or removed.
+impl Interpolator {
+    /// Records the poses of the instances before a step.
+    pub fn record(&mut self, instances: &[Instance]) {
+        self.record_keyed(0..instances.len(), instances);
+    }
+
+    /// Blends the instances after the latest step from their poses before it, by the alpha. If the instances
+    /// changed in number, they're left be, since they mightn't be in the order they were recorded in.
+    pub fn blend(&self, instances: &mut [Instance], alpha: f32) {
+        if instances.len() != self.previous.len() {
+            return;
+        }
+        self.blend_keyed(0..instances.len(), instances, alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::Pool;
+
+    use cgmath::{assert_relative_eq, Deg, Rotation3};
+    use itertools::Itertools;
+
+    #[test]
+    fn blends_from_the_previous_step() {
+        let instance = |x: f32, angle: f32| Instance {
+            position: Vector3::new(x, 0.0, 0.0),
+            rotation: Quaternion::from_angle_y(Deg(angle)),
+            ..Default::default()
+        };
+        let mut interpolator: Interpolator = Interpolator::new();
+        interpolator.record(&[instance(0.0, 0.0)]);
+        let mut instances = [instance(2.0, 90.0)];
+        interpolator.blend(&mut instances, 0.25);
+        assert_relative_eq!(Vector3::new(0.5, 0.0, 0.0), instances[0].position);
+        assert_relative_eq!(
+            Quaternion::from_angle_y(Deg(22.5)),
+            instances[0].rotation,
+            epsilon = 1e-6
+        );
+
+        // Without a previous step to blend from, the instances are drawn where they are.
+        interpolator.clear();
+        let mut instances = [instance(2.0, 90.0)];
+        interpolator.blend(&mut instances, 0.25);
+        assert_eq!(Vector3::new(2.0, 0.0, 0.0), instances[0].position);
+    }
+
+    #[test]
+    fn replaced_bodies_are_not_blended_from_the_old_pose() {
+        // The bodies' positions along X.
+        let mut bodies = Pool::new();
+        let old = bodies.insert(0.0);
+        let kept = bodies.insert(0.0);
+        let mut interpolator = Interpolator::new();
+        let handles = |bodies: &Pool<f32>| bodies.iter().map(|(handle, _)| handle).collect_vec();
+        let instances = |bodies: &Pool<f32>| {
+            bodies
+                .values()
+                .map(|x| Instance {
+                    position: Vector3::new(*x, 0.0, 0.0),
+                    ..Default::default()
+                })
+                .collect_vec()
+        };
+        interpolator.record_keyed(handles(&bodies), &instances(&bodies));
+
+        // The new body takes the old one's slot, and the kept one moves.
+        bodies.remove(old);
+        let new = bodies.insert(4.0);
+        assert_eq!(old.index(), new.index());
+        *bodies.get_mut(kept).unwrap() = 2.0;
+
+        let mut blended = instances(&bodies);
+        interpolator.blend_keyed(handles(&bodies), &mut blended, 0.5);
+        let blended = handles(&bodies).into_iter().zip(blended).collect_vec();
+        for (handle, instance) in blended {
+            let expected = if handle == new { 4.0 } else { 1.0 };
+            assert_relative_eq!(expected, instance.position.x);
+        }
+    }
+
+    #[test]
+    fn alpha_is_the_fraction_of_a_step_left() {
+        let timestep = Duration::from_millis(20);
+        assert_relative_eq!(0.25, get_alpha(Duration::from_millis(5), timestep));
+        assert_eq!(1.0, get_alpha(Duration::from_millis(30), timestep));
+        assert_eq!(1.0, get_alpha(Duration::ZERO, Duration::ZERO));
+    }
+}
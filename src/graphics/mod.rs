@@ -1,11 +1,24 @@
-pub(crate) mod camera;
-pub(crate) mod entity;
-pub(crate) mod forms;
-pub(crate) mod gpu_interface;
-pub(crate) mod instance;
-pub(crate) mod light;
-pub(crate) mod model;
-pub(crate) mod resources;
-pub(crate) mod scene;
-pub(crate) mod texture;
-pub(crate) mod util;
+pub mod ambient_occlusion;
+pub mod camera;
+pub mod camera_path;
+pub mod debug_draw;
+pub mod depth_sort;
+pub mod entity;
+pub mod fluid_surface;
+pub mod forms;
+pub mod frame_capture;
+pub mod gizmo;
+pub mod gpu_interface;
+pub mod instance;
+pub mod interpolation;
+pub mod light;
+pub mod model;
+pub mod normals;
+pub mod palette;
+pub mod render_scale;
+pub mod resources;
+pub mod scene;
+pub mod settings;
+pub mod texture;
+pub mod transparency;
+pub mod util;
@@ -1,7 +1,6 @@
 use crate::{
-    graphics::texture,
+    graphics::texture, simulation::collidable_mesh, simulation::simplification,
     simulation::springy::springy_mesh::SpringyMesh,
-    simulation::{collidable_mesh, rigidbody::rigidbody},
 };
 
 use cgmath::Vector3;
@@ -39,13 +38,31 @@ impl Vertex for ColoredVertex {
     }
 }
 
+/// Where a dynamic mesh's normals are recomputed when its vertex positions are updated.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum NormalRecomputation {
+    /// Normals are averaged on the CPU in update_vertex_positions().
+    Cpu,
+    /// Normals are left for a compute pass to recompute; see graphics::normals::GpuNormals.
+    Gpu,
+}
+
+/// A mesh with a single color per vertex.
+/// The vertex buffer persists for the lifetime of the mesh; meshes whose vertices move
+/// (e.g. springy meshes) can be updated in place via update_vertex_positions(),
+/// rather than being recreated each frame.
 pub struct ColoredMesh {
     pub name: String,
     pub vertex_positions: Vec<Vector3<f32>>,
-    pub vertex_indices: Vec<u16>,
+    pub vertex_indices: Vec<u32>,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
+    /// The format of the index buffer. Meshes small enough to be indexed with u16 use Uint16
+    /// to save memory; larger meshes use Uint32. See index_format_for().
+    pub index_format: wgpu::IndexFormat,
     pub num_elements: u32,
+    pub color: [f32; 3],
+    pub normal_recomputation: NormalRecomputation,
 }
 
 impl ColoredMesh {
@@ -53,10 +70,10 @@ impl ColoredMesh {
         device: &wgpu::Device,
         name: String,
         vertex_positions: Vec<Vector3<f32>>,
-        vertex_indices: Vec<u16>,
+        vertex_indices: Vec<u32>,
         color: [f32; 3],
     ) -> ColoredMesh {
-        let (vertex_buffer, index_buffer) =
+        let (vertex_buffer, index_buffer, index_format) =
             Self::get_buffers(device, &vertex_positions, &vertex_indices, color);
         let num_elements = vertex_indices.len() as u32;
         ColoredMesh {
@@ -65,7 +82,10 @@ impl ColoredMesh {
             vertex_indices,
             vertex_buffer,
             index_buffer,
+            index_format,
             num_elements,
+            color,
+            normal_recomputation: NormalRecomputation::Cpu,
         }
     }
 
@@ -76,8 +96,8 @@ impl ColoredMesh {
         color: [f32; 3],
     ) -> ColoredMesh {
         let (vertex_positions, vertex_indices) = springy_mesh.get_vertices();
-        let vertex_indices = vertex_indices.iter().map(|i| *i as u16).collect_vec();
-        let (vertex_buffer, index_buffer) =
+        let vertex_indices = vertex_indices.iter().map(|i| *i as u32).collect_vec();
+        let (vertex_buffer, index_buffer, index_format) =
             Self::get_buffers(device, &vertex_positions, &vertex_indices, color);
         let num_elements = vertex_indices.len() as u32;
         ColoredMesh {
@@ -86,7 +106,10 @@ impl ColoredMesh {
             vertex_indices,
             vertex_buffer,
             index_buffer,
+            index_format,
             num_elements,
+            color,
+            normal_recomputation: NormalRecomputation::Cpu,
         }
     }
 
@@ -98,8 +121,8 @@ impl ColoredMesh {
     ) -> ColoredMesh {
         // TODO get_vertices() can be made into a Trait so this fn can be generalized with from_springy_mesh
         let (vertex_positions, vertex_indices) = collidable_mesh.get_vertices_to_render();
-        let vertex_indices = vertex_indices.iter().map(|i| *i as u16).collect_vec();
-        let (vertex_buffer, index_buffer) =
+        let vertex_indices = vertex_indices.iter().map(|i| *i as u32).collect_vec();
+        let (vertex_buffer, index_buffer, index_format) =
             Self::get_buffers(device, &vertex_positions, &vertex_indices, color);
         let num_elements = vertex_indices.len() as u32;
         ColoredMesh {
@@ -108,58 +131,108 @@ impl ColoredMesh {
             vertex_indices,
             vertex_buffer,
             index_buffer,
+            index_format,
             num_elements,
+            color,
+            normal_recomputation: NormalRecomputation::Cpu,
         }
     }
 
-    pub fn from_rigidbody(
+    /// Updates the vertex positions of the mesh, recomputing normals and scheduling a write
+    /// of the new vertices into the existing vertex buffer. The topology (indices) is unchanged.
+    /// If normals are recomputed on the GPU, the written normals are zeroed until the compute pass runs.
+    /// If the number of vertices changed, the vertex buffer is recreated to fit them, unless the normals are
+    /// recomputed on the GPU, whose binding is to the existing buffer; that errors, leaving the mesh as it was.
+    pub fn update_vertex_positions(
+        &mut self,
         device: &wgpu::Device,
-        name: String,
-        rigidbody: &rigidbody::RigidBody,
-        color: [f32; 3],
-    ) -> ColoredMesh {
-        let (local_vertex_positions, vertex_indices) =
-            rigidbody.get_mesh().get_vertices_to_render();
-        let world_vertex_positions = local_vertex_positions
-            .iter()
-            .map(|v| rigidbody.get_rotation_matrix() * v + rigidbody.get_position())
-            .collect_vec();
-        let vertex_indices = vertex_indices.iter().map(|i| *i as u16).collect_vec();
-        let (vertex_buffer, index_buffer) =
-            Self::get_buffers(device, &world_vertex_positions, &vertex_indices, color);
-        let num_elements = vertex_indices.len() as u32;
-        ColoredMesh {
-            name,
-            vertex_positions: world_vertex_positions,
-            vertex_indices,
-            vertex_buffer,
-            index_buffer,
-            num_elements,
+        queue: &wgpu::Queue,
+        vertex_positions: Vec<Vector3<f32>>,
+    ) -> Result<(), &'static str> {
+        let resized = vertex_positions.len() != self.vertex_positions.len();
+        if resized && self.normal_recomputation == NormalRecomputation::Gpu {
+            return Err(
+                "Meshes with normals recomputed on the GPU can't change their vertex count!",
+            );
+        }
+        let normals = match self.normal_recomputation {
+            NormalRecomputation::Cpu => get_normals(&vertex_positions, &self.vertex_indices),
+            NormalRecomputation::Gpu => vec![Vector3::new(0.0, 0.0, 0.0); vertex_positions.len()],
+        };
+        let vertices = Self::get_colored_vertices(&vertex_positions, &normals, self.color);
+        if resized {
+            self.vertex_buffer = Self::get_vertex_buffer(device, &vertices);
+        } else {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+        self.vertex_positions = vertex_positions;
+        Ok(())
+    }
+
+    /// Schedules a write of the color into each of the mesh's vertices, leaving their
+    /// positions and normals as they are.
+    pub fn set_color(&mut self, queue: &wgpu::Queue, color: [f32; 3]) {
+        if color == self.color {
+            return;
+        }
+        let stride = std::mem::size_of::<ColoredVertex>() as wgpu::BufferAddress;
+        let color_offset = std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress;
+        for i in 0..self.vertex_positions.len() as wgpu::BufferAddress {
+            queue.write_buffer(
+                &self.vertex_buffer,
+                i * stride + color_offset,
+                bytemuck::cast_slice(&color),
+            );
         }
+        self.color = color;
     }
 
-    /// Gets the vertex buffer and index buffer, respectively.
+    /// Gets the index format needed to index a mesh with vertex_count vertices.
+    pub fn index_format_for(vertex_count: usize) -> wgpu::IndexFormat {
+        if vertex_count <= u16::MAX as usize + 1 {
+            wgpu::IndexFormat::Uint16
+        } else {
+            wgpu::IndexFormat::Uint32
+        }
+    }
+
+    /// Gets the vertex buffer, index buffer, and the index buffer's format, respectively.
     fn get_buffers(
         device: &wgpu::Device,
         vertex_positions: &Vec<Vector3<f32>>,
-        indices: &Vec<u16>,
+        indices: &Vec<u32>,
         color: [f32; 3],
-    ) -> (wgpu::Buffer, wgpu::Buffer) {
+    ) -> (wgpu::Buffer, wgpu::Buffer, wgpu::IndexFormat) {
         let normals = get_normals(&vertex_positions, &indices);
         let vertices = Self::get_colored_vertices(&vertex_positions, &normals, color);
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("mesh colored vertex buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let vertex_buffer = Self::get_vertex_buffer(device, &vertices);
+        let index_format = Self::index_format_for(vertex_positions.len());
+        let index_contents = match index_format {
+            wgpu::IndexFormat::Uint16 => {
+                let indices = indices.iter().map(|i| *i as u16).collect_vec();
+                bytemuck::cast_slice(&indices).to_vec()
+            }
+            wgpu::IndexFormat::Uint32 => bytemuck::cast_slice(indices).to_vec(),
+        };
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("mesh colored index buffer"),
-            contents: bytemuck::cast_slice(&indices),
+            contents: &index_contents,
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        (vertex_buffer, index_buffer)
+        (vertex_buffer, index_buffer, index_format)
+    }
+
+    fn get_vertex_buffer(device: &wgpu::Device, vertices: &[ColoredVertex]) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("mesh colored vertex buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            // STORAGE allows the normals to be recomputed by a compute pass.
+            usage: wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::STORAGE,
+        })
     }
 
     /// Zips the vertex positions with their normals, and adds the color,
@@ -223,7 +296,7 @@ where
         light_bind_group: &'b wgpu::BindGroup,
     ) {
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        self.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
         self.set_bind_group(0, camera_bind_group, &[]);
         self.set_bind_group(1, light_bind_group, &[]);
         self.draw_indexed(0..mesh.num_elements, 0, instances);
@@ -266,18 +339,186 @@ pub struct Model {
     pub materials: Vec<Material>,
 }
 
+impl Model {
+    /// Gets the positions and indices of the model's meshes combined, simplified to at most
+    /// target_triangles triangles. See simplification::simplify().
+    pub fn get_collision_proxy(&self, target_triangles: usize) -> (Vec<Vector3<f32>>, Vec<usize>) {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for mesh in self.meshes.iter() {
+            let offset = positions.len();
+            positions.extend(mesh.vertex_positions.iter());
+            indices.extend(mesh.vertex_indices.iter().map(|i| *i as usize + offset));
+        }
+        simplification::simplify(&positions, &indices, target_triangles)
+    }
+}
+
 pub struct Material {
     pub name: String,
     pub diffuse_texture: texture::Texture,
     pub bind_group: wgpu::BindGroup,
 }
 
+impl Material {
+    /// The layout should be from util::create_texture_bind_group_layout().
+    pub fn new(
+        device: &wgpu::Device,
+        name: String,
+        diffuse_texture: texture::Texture,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Material {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+            ],
+            label: Some(&name),
+        });
+        Material {
+            name,
+            diffuse_texture,
+            bind_group,
+        }
+    }
+}
+
 pub struct Mesh {
     pub name: String,
+    /// The positions and indices the mesh was created with, e.g. to build collision proxies.
+    /// Springy meshes' later deformations aren't reflected.
+    pub vertex_positions: Vec<Vector3<f32>>,
+    pub vertex_indices: Vec<u32>,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub num_elements: u32,
     pub material: usize,
+    pub normal_recomputation: NormalRecomputation,
+}
+
+impl Mesh {
+    /// Creates a textured mesh from the springy mesh. The springy mesh's UVs follow its points,
+    /// so the texture stretches as the mesh deforms. Keep the mesh up to date with update_from_springy_mesh().
+    /// Panics if the springy mesh has no UVs.
+    pub fn from_springy_mesh(
+        device: &wgpu::Device,
+        name: String,
+        springy_mesh: &SpringyMesh,
+        material: usize,
+    ) -> Mesh {
+        let normal_recomputation = NormalRecomputation::Cpu;
+        let (vertices, vertex_indices) =
+            Self::get_springy_vertices(springy_mesh, normal_recomputation);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("springy mesh vertex buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            // STORAGE allows the normals to be recomputed by a compute pass.
+            usage: wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::STORAGE,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("springy mesh index buffer"),
+            contents: bytemuck::cast_slice(&vertex_indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Mesh {
+            name,
+            vertex_positions: vertices.iter().map(|v| v.position.into()).collect(),
+            vertex_indices: vertex_indices.to_vec(),
+            vertex_buffer,
+            index_buffer,
+            num_elements: vertex_indices.len() as u32,
+            material,
+            normal_recomputation,
+        }
+    }
+
+    /// Creates a width x height rectangle in the XY plane, centered at the origin and facing +Z,
+    /// with the texture's top edge along the rectangle's top.
+    pub fn quad(
+        device: &wgpu::Device,
+        name: String,
+        width: f32,
+        height: f32,
+        material: usize,
+    ) -> Mesh {
+        let (x, y) = (width / 2.0, height / 2.0);
+        let vertices = [
+            ([-x, -y], [0.0, 1.0]),
+            ([x, -y], [1.0, 1.0]),
+            ([x, y], [1.0, 0.0]),
+            ([-x, y], [0.0, 0.0]),
+        ]
+        .map(|(position, tex_coords)| ModelVertex {
+            position: [position[0], position[1], 0.0],
+            tex_coords,
+            normal: [0.0, 0.0, 1.0],
+        });
+        let vertex_indices: [u32; 6] = [0, 1, 2, 2, 3, 0];
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("quad vertex buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("quad index buffer"),
+            contents: bytemuck::cast_slice(&vertex_indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Mesh {
+            name,
+            vertex_positions: vertices.iter().map(|v| v.position.into()).collect(),
+            vertex_indices: vertex_indices.to_vec(),
+            vertex_buffer,
+            index_buffer,
+            num_elements: vertex_indices.len() as u32,
+            material,
+            normal_recomputation: NormalRecomputation::Cpu,
+        }
+    }
+
+    /// Schedules a write of the springy mesh's current vertices into the existing vertex buffer.
+    /// The springy mesh must be the one this mesh was created from.
+    pub fn update_from_springy_mesh(&self, queue: &wgpu::Queue, springy_mesh: &SpringyMesh) {
+        let (vertices, _) = Self::get_springy_vertices(springy_mesh, self.normal_recomputation);
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+    }
+
+    /// Gets the textured vertices and u32 indices of the springy mesh.
+    /// Normals are zeroed if they are to be recomputed on the GPU.
+    fn get_springy_vertices(
+        springy_mesh: &SpringyMesh,
+        normal_recomputation: NormalRecomputation,
+    ) -> (Vec<ModelVertex>, Vec<u32>) {
+        let uvs = springy_mesh
+            .get_uvs()
+            .expect("Textured springy meshes require UVs!");
+        let (vertex_positions, vertex_indices) = springy_mesh.get_vertices();
+        let vertex_indices = vertex_indices.iter().map(|i| *i as u32).collect_vec();
+        let normals = match normal_recomputation {
+            NormalRecomputation::Cpu => get_normals(&vertex_positions, &vertex_indices),
+            NormalRecomputation::Gpu => vec![Vector3::new(0.0, 0.0, 0.0); vertex_positions.len()],
+        };
+        let vertices = vertex_positions
+            .iter()
+            .zip(uvs.iter())
+            .zip(normals.iter())
+            .map(|((v, uv), n)| ModelVertex {
+                position: [v.x, v.y, v.z],
+                tex_coords: [uv.x, uv.y],
+                normal: [n.x, n.y, n.z],
+            })
+            .collect_vec();
+        (vertices, vertex_indices)
+    }
 }
 
 pub trait DrawModel<'a> {
@@ -453,3 +694,21 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ColoredMesh;
+
+    #[test]
+    fn index_format_for() {
+        assert_eq!(wgpu::IndexFormat::Uint16, ColoredMesh::index_format_for(3));
+        assert_eq!(
+            wgpu::IndexFormat::Uint16,
+            ColoredMesh::index_format_for(u16::MAX as usize + 1)
+        );
+        assert_eq!(
+            wgpu::IndexFormat::Uint32,
+            ColoredMesh::index_format_for(u16::MAX as usize + 2)
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use super::gpu_interface::GPUInterface;
+use super::model::{ColoredMesh, ColoredVertex, Mesh, ModelVertex, NormalRecomputation};
+
+use itertools::Itertools;
+use wgpu::util::DeviceExt;
+
+// Must match the @workgroup_size of cs_main in normals.wgsl.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Gets the contents of the VertexLayout uniform in normals.wgsl: the vertex stride and the
+/// offset of the normal within a vertex, in units of f32, padded to 16 bytes.
+fn vertex_layout(stride_bytes: usize, normal_offset_bytes: usize) -> [u32; 4] {
+    let float_size = std::mem::size_of::<f32>();
+    [
+        (stride_bytes / float_size) as u32,
+        (normal_offset_bytes / float_size) as u32,
+        0,
+        0,
+    ]
+}
+
+/// A compute pipeline which recomputes smooth vertex normals for deforming meshes on the GPU.
+/// Shared between all meshes; each mesh gets its own GpuNormals binding.
+pub struct NormalsPipeline {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl NormalsPipeline {
+    pub fn new(gpu: &GPUInterface) -> NormalsPipeline {
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout =
+            gpu.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("normals bind group layout"),
+                    entries: &[
+                        storage_entry(0, false),
+                        storage_entry(1, true),
+                        storage_entry(2, true),
+                        storage_entry(3, true),
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 4,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("normals pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Normals Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/normals.wgsl").into()),
+            });
+        let pipeline = gpu
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("normals pipeline"),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: "cs_main",
+            });
+
+        NormalsPipeline {
+            pipeline,
+            bind_group_layout,
+        }
+    }
+}
+
+/// Binds a dynamic mesh's vertex buffer to the NormalsPipeline so its normals can be
+/// recomputed on the GPU after its positions are updated.
+/// The mesh's topology is assumed to be fixed for the lifetime of the binding.
+pub struct GpuNormals {
+    bind_group: wgpu::BindGroup,
+    vertex_count: u32,
+}
+
+impl GpuNormals {
+    /// Creates the binding, and switches the mesh to defer normal recomputation to the GPU.
+    /// The caller is then responsible for calling dispatch() after updating the mesh's vertices.
+    pub fn from_colored_mesh(
+        gpu: &GPUInterface,
+        normals_pipeline: &NormalsPipeline,
+        mesh: &mut ColoredMesh,
+    ) -> GpuNormals {
+        mesh.normal_recomputation = NormalRecomputation::Gpu;
+        Self::new(
+            gpu,
+            normals_pipeline,
+            &mesh.vertex_buffer,
+            &mesh.vertex_indices,
+            mesh.vertex_positions.len(),
+            vertex_layout(
+                std::mem::size_of::<ColoredVertex>(),
+                std::mem::offset_of!(ColoredVertex, normal),
+            ),
+        )
+    }
+
+    /// As from_colored_mesh(), for a textured mesh. Since Mesh doesn't retain its indices,
+    /// they must be passed in, e.g. from the SpringyMesh the mesh was created from.
+    pub fn from_textured_mesh(
+        gpu: &GPUInterface,
+        normals_pipeline: &NormalsPipeline,
+        mesh: &mut Mesh,
+        vertex_indices: &[u32],
+        vertex_count: usize,
+    ) -> GpuNormals {
+        mesh.normal_recomputation = NormalRecomputation::Gpu;
+        Self::new(
+            gpu,
+            normals_pipeline,
+            &mesh.vertex_buffer,
+            vertex_indices,
+            vertex_count,
+            vertex_layout(
+                std::mem::size_of::<ModelVertex>(),
+                std::mem::offset_of!(ModelVertex, normal),
+            ),
+        )
+    }
+
+    fn new(
+        gpu: &GPUInterface,
+        normals_pipeline: &NormalsPipeline,
+        vertex_buffer: &wgpu::Buffer,
+        vertex_indices: &[u32],
+        vertex_count: usize,
+        layout: [u32; 4],
+    ) -> GpuNormals {
+        let (offsets, faces) = vertex_face_adjacency(vertex_indices, vertex_count);
+
+        let storage_buffer = |label, contents: &[u32]| {
+            gpu.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(contents),
+                    usage: wgpu::BufferUsages::STORAGE,
+                })
+        };
+        // The mesh's index buffer may be u16, so we keep a u32 copy for the shader.
+        let indices_buffer = storage_buffer("normals indices buffer", vertex_indices);
+        let offsets_buffer = storage_buffer("normals vertex face offsets buffer", &offsets);
+        let faces_buffer = storage_buffer("normals vertex faces buffer", &faces);
+        let layout_buffer = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("normals vertex layout buffer"),
+                contents: bytemuck::cast_slice(&layout),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("normals bind group"),
+            layout: &normals_pipeline.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: vertex_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: indices_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: offsets_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: faces_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: layout_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        GpuNormals {
+            bind_group,
+            vertex_count: vertex_count as u32,
+        }
+    }
+
+    /// Records the compute pass which recomputes the mesh's normals.
+    /// Should be recorded before any render pass which draws the mesh.
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder, normals_pipeline: &NormalsPipeline) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("normals compute pass"),
+        });
+        compute_pass.set_pipeline(&normals_pipeline.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        let workgroups = self.vertex_count.div_ceil(WORKGROUP_SIZE);
+        compute_pass.dispatch_workgroups(workgroups, 1, 1);
+    }
+}
+
+/// Gets the faces adjacent to each vertex in compressed form, returned as (offsets, faces).
+/// The faces adjacent to vertex v are faces[offsets[v]..offsets[v + 1]], where each face
+/// is the index of the triangle in indices.
+fn vertex_face_adjacency(indices: &[u32], vertex_count: usize) -> (Vec<u32>, Vec<u32>) {
+    let mut adjacent_faces = vec![Vec::new(); vertex_count];
+    for (face, (a, b, c)) in indices.iter().tuples().enumerate() {
+        adjacent_faces[*a as usize].push(face as u32);
+        adjacent_faces[*b as usize].push(face as u32);
+        adjacent_faces[*c as usize].push(face as u32);
+    }
+
+    let mut offsets = Vec::with_capacity(vertex_count + 1);
+    offsets.push(0);
+    for faces in adjacent_faces.iter() {
+        offsets.push(offsets.last().unwrap() + faces.len() as u32);
+    }
+    (offsets, adjacent_faces.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn vertex_face_adjacency() {
+        // Two triangles sharing the edge (1, 2).
+        let indices = vec![0, 1, 2, 2, 1, 3];
+        let (offsets, faces) = super::vertex_face_adjacency(&indices, 4);
+        assert_eq!(vec![0, 1, 3, 5, 6], offsets);
+        assert_eq!(vec![0, 0, 1, 0, 1, 1], faces);
+    }
+
+    #[test]
+    fn vertex_face_adjacency_unreferenced_vertex() {
+        let indices = vec![0, 2, 3];
+        let (offsets, faces) = super::vertex_face_adjacency(&indices, 4);
+        assert_eq!(vec![0, 1, 1, 2, 3], offsets);
+        assert_eq!(vec![0, 0, 0], faces);
+    }
+
+    #[test]
+    fn vertex_layout() {
+        assert_eq!(
+            [9, 6, 0, 0],
+            super::vertex_layout(
+                std::mem::size_of::<super::ColoredVertex>(),
+                std::mem::offset_of!(super::ColoredVertex, normal)
+            )
+        );
+        assert_eq!(
+            [8, 5, 0, 0],
+            super::vertex_layout(
+                std::mem::size_of::<super::ModelVertex>(),
+                std::mem::offset_of!(super::ModelVertex, normal)
+            )
+        );
+    }
+
+    #[test]
+    fn shader_validates() {
+        let module = naga::front::wgsl::parse_str(include_str!("../shaders/normals.wgsl"))
+            .expect("normals.wgsl should parse");
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .expect("normals.wgsl should validate");
+    }
+}
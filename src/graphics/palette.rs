@@ -0,0 +1,226 @@
+/// An RGB color, as used for ColoredMesh vertex colors.
+pub type Color = [f32; 3];
+
+/// Gets the color from a 24 bit 0xRRGGBB hex code.
+const fn hex(code: u32) -> Color {
+    [
+        ((code >> 16) & 0xff) as f32 / 255.0,
+        ((code >> 8) & 0xff) as f32 / 255.0,
+        (code & 0xff) as f32 / 255.0,
+    ]
+}
+
+/// The Okabe-Ito palette, whose colors remain distinct under the common color vision deficiencies.
+/// Suitable for categorical data, e.g. the phases of a fluid. Black is omitted, since it is
+/// indistinguishable from shadow.
+pub const CATEGORICAL: [Color; 7] = [
+    hex(0xe69f00), // Orange
+    hex(0x56b4e9), // Sky blue
+    hex(0x009e73), // Bluish green
+    hex(0xf0e442), // Yellow
+    hex(0x0072b2), // Blue
+    hex(0xd55e00), // Vermillion
+    hex(0xcc79a7), // Reddish purple
+];
+
+/// Gets the categorical color for the index, wrapping if there are more categories than colors.
+pub fn categorical(index: usize) -> Color {
+    CATEGORICAL[index % CATEGORICAL.len()]
+}
+
+/// The color glowing bodies are seen as at temperatures in kelvin, from the dull red of the
+/// Draper point to the white of the sun, approximating the blackbody locus.
+const BLACKBODY: [(f32, Color); 7] = [
+    (500.0, hex(0x0d0d0d)),
+    (800.0, hex(0x4d0500)),
+    (1000.0, hex(0xa01a00)),
+    (1300.0, hex(0xe64000)),
+    (1800.0, hex(0xff8a1f)),
+    (3000.0, hex(0xffc27a)),
+    (6500.0, hex(0xffffff)),
+];
+
+/// Gets the color of a body glowing at the temperature, in kelvin, linearly interpolating
+/// along a blackbody gradient. Bodies too cool to glow are charcoal.
+pub fn blackbody(temperature: f32) -> Color {
+    let index = BLACKBODY
+        .iter()
+        .position(|(stop, _)| *stop > temperature)
+        .unwrap_or(BLACKBODY.len())
+        .clamp(1, BLACKBODY.len() - 1);
+    let (from_temperature, from) = BLACKBODY[index - 1];
+    let (to_temperature, to) = BLACKBODY[index];
+    let fraction =
+        ((temperature - from_temperature) / (to_temperature - from_temperature)).clamp(0.0, 1.0);
+    [
+        from[0] + (to[0] - from[0]) * fraction,
+        from[1] + (to[1] - from[1]) * fraction,
+        from[2] + (to[2] - from[2]) * fraction,
+    ]
+}
+
+/// A perceptually uniform map from [0, 1] to colors, for visualizing scalar fields
+/// such as speed or pressure.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Colormap {
+    /// Blue to green to yellow.
+    Viridis,
+    /// Blue to yellow, designed to be perceived the same with and without red-green deficiencies.
+    Cividis,
+}
+
+impl Colormap {
+    const VIRIDIS: [Color; 9] = [
+        hex(0x440154),
+        hex(0x472d7b),
+        hex(0x3b528b),
+        hex(0x2c728e),
+        hex(0x21918c),
+        hex(0x28ae80),
+        hex(0x5ec962),
+        hex(0xaddc30),
+        hex(0xfde725),
+    ];
+
+    const CIVIDIS: [Color; 9] = [
+        hex(0x00224e),
+        hex(0x1b3569),
+        hex(0x3d4a6c),
+        hex(0x5d5f6e),
+        hex(0x7c7b78),
+        hex(0x9c9377),
+        hex(0xbfac6e),
+        hex(0xe2c85c),
+        hex(0xfee838),
+    ];
+
+    fn stops(&self) -> &'static [Color] {
+        match self {
+            Colormap::Viridis => &Colormap::VIRIDIS,
+            Colormap::Cividis => &Colormap::CIVIDIS,
+        }
+    }
+
+    /// Gets the color at t, linearly interpolating between the map's stops.
+    /// t is clamped to [0, 1].
+    pub fn sample(&self, t: f32) -> Color {
+        let stops = self.stops();
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f32;
+        let (from, to) = (stops[index], stops[index + 1]);
+        [
+            from[0] + (to[0] - from[0]) * fraction,
+            from[1] + (to[1] - from[1]) * fraction,
+            from[2] + (to[2] - from[2]) * fraction,
+        ]
+    }
+}
+
+/// The colors the demos use for each role a mesh can play.
+/// Demos take their colors from a theme rather than hard-coding them, so the user can
+/// switch between themes, e.g. to one which is distinguishable with color vision deficiencies.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Theme {
+    pub name: &'static str,
+    /// The simulated body, e.g. the SPH particles, rigidbody, or springy mesh.
+    pub body: Color,
+    /// Static obstacles the body collides with.
+    pub obstacle: Color,
+    /// Volumes which enclose the simulation, e.g. the bouncing ball's box.
+    pub container: Color,
+    /// Secondary simulated bodies, e.g. the bouncing ball.
+    pub accent: Color,
+    /// The colormap for visualizing scalar fields.
+    pub colormap: Colormap,
+}
+
+impl Theme {
+    /// The colors the demos were originally designed with.
+    pub const CLASSIC: Theme = Theme {
+        name: "Classic",
+        body: [0.9, 0.1, 0.1],
+        obstacle: [0.1, 0.9, 0.1],
+        container: [0.5, 0.0, 0.5],
+        accent: [0.2, 0.8, 0.2],
+        colormap: Colormap::Viridis,
+    };
+
+    /// Colors from the Okabe-Ito palette, which remain distinct under the common color
+    /// vision deficiencies.
+    pub const COLORBLIND: Theme = Theme {
+        name: "Colorblind Safe",
+        body: CATEGORICAL[5],
+        obstacle: CATEGORICAL[4],
+        container: CATEGORICAL[6],
+        accent: CATEGORICAL[0],
+        colormap: Colormap::Cividis,
+    };
+
+    /// Neutral greys, distinguished by lightness alone.
+    pub const MONOCHROME: Theme = Theme {
+        name: "Monochrome",
+        body: [0.85, 0.85, 0.85],
+        obstacle: [0.35, 0.35, 0.35],
+        container: [0.2, 0.2, 0.2],
+        accent: [0.6, 0.6, 0.6],
+        colormap: Colormap::Cividis,
+    };
+
+    pub const ALL: [Theme; 3] = [Theme::CLASSIC, Theme::COLORBLIND, Theme::MONOCHROME];
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::CLASSIC
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colormaps_span_their_stops() {
+        for colormap in [Colormap::Viridis, Colormap::Cividis] {
+            let stops = colormap.stops();
+            assert_eq!(colormap.sample(0.0), stops[0]);
+            assert_eq!(colormap.sample(1.0), stops[stops.len() - 1]);
+            assert_eq!(colormap.sample(-1.0), stops[0]);
+            assert_eq!(colormap.sample(2.0), stops[stops.len() - 1]);
+        }
+    }
+
+    #[test]
+    fn colormaps_increase_in_lightness() {
+        // Perceptually uniform maps are monotonic in lightness, so a scalar field reads
+        // correctly even in greyscale.
+        let luminance = |c: Color| 0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2];
+        for colormap in [Colormap::Viridis, Colormap::Cividis] {
+            let mut previous = luminance(colormap.sample(0.0));
+            for i in 1..=100 {
+                let current = luminance(colormap.sample(i as f32 / 100.0));
+                assert!(current >= previous, "{:?} at {}", colormap, i);
+                previous = current;
+            }
+        }
+    }
+
+    #[test]
+    fn blackbody_brightens_with_temperature() {
+        let luminance = |c: Color| 0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2];
+        let mut previous = luminance(blackbody(0.0));
+        for temperature in (0..8000).step_by(100) {
+            let current = luminance(blackbody(temperature as f32));
+            assert!(current >= previous, "{}", temperature);
+            previous = current;
+        }
+        assert_eq!(blackbody(0.0), BLACKBODY[0].1);
+        assert_eq!(blackbody(10000.0), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn categorical_wraps() {
+        assert_eq!(categorical(0), categorical(CATEGORICAL.len()));
+    }
+}
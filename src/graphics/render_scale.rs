@@ -0,0 +1,291 @@
+/// Rendering the 3D scene at a reduced resolution into an off-screen target, which is upsampled
+/// to the surface before the GUI is drawn at full resolution, and adjusting the resolution
+/// automatically to keep to a target frame rate.
+use std::time::Duration;
+
+use super::settings::GraphicsSettings;
+
+/// The size of a surface of width x height scaled by the render scale, at least 1 x 1.
+pub fn scaled_extent(width: u32, height: u32, scale: f32) -> wgpu::Extent3d {
+    let scaled = |length: u32| ((length as f32 * scale).round() as u32).max(1);
+    wgpu::Extent3d {
+        width: scaled(width),
+        height: scaled(height),
+        depth_or_array_layers: 1,
+    }
+}
+
+/// The off-screen color target the scene is rendered to at a reduced resolution.
+pub struct SceneTarget {
+    pub view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Draws a scene target over the whole surface.
+pub struct Upsampler {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl Upsampler {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Upsampler {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("upsample_bind_group_layout"),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Upsample Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Upsample Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/upsample.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Upsample Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Upsampler {
+            pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    pub fn create_target(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: wgpu::Extent3d,
+    ) -> SceneTarget {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("scene target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some("upsample_bind_group"),
+        });
+        SceneTarget { view, bind_group }
+    }
+
+    /// Encodes drawing the scene target over the surface.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &SceneTarget,
+        surface_view: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Upsample Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Lowers the render scale while frames take longer than the target frame time, and raises it
+/// again once there is headroom.
+#[derive(Debug, Default)]
+pub struct DynamicResolution {
+    /// An exponential moving average of the frame time, in seconds.
+    smoothed_frame_time: Option<f32>,
+    /// The time since the scale last changed, in seconds.
+    since_change: f32,
+}
+
+impl DynamicResolution {
+    /// The weight of each new frame time in the moving average.
+    const SMOOTHING: f32 = 0.1;
+    const SCALE_STEP: f32 = 0.05;
+    /// The time to wait after changing the scale, so the average reflects the new scale.
+    const COOLDOWN: f32 = 0.5;
+    /// The scale is raised once frames take less than this fraction of the target frame time.
+    const HEADROOM: f32 = 0.8;
+
+    /// Returns the render scale to use given the frame's time and the current scale,
+    /// within [RENDER_SCALE_MIN, settings.render_scale].
+    pub fn update(&mut self, frame_time: Duration, scale: f32, settings: &GraphicsSettings) -> f32 {
+        let frame_time = frame_time.as_secs_f32();
+        let smoothed = match self.smoothed_frame_time {
+            Some(smoothed) => smoothed + DynamicResolution::SMOOTHING * (frame_time - smoothed),
+            None => frame_time,
+        };
+        self.smoothed_frame_time = Some(smoothed);
+        self.since_change += frame_time;
+        if self.since_change < DynamicResolution::COOLDOWN {
+            return scale;
+        }
+
+        let target_frame_time = 1.0 / settings.target_frame_rate as f32;
+        let new_scale = if smoothed > target_frame_time {
+            scale - DynamicResolution::SCALE_STEP
+        } else if smoothed < target_frame_time * DynamicResolution::HEADROOM {
+            scale + DynamicResolution::SCALE_STEP
+        } else {
+            scale
+        }
+        .clamp(GraphicsSettings::RENDER_SCALE_MIN, settings.render_scale);
+        if new_scale != scale {
+            self.since_change = 0.0;
+        }
+        new_scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{scaled_extent, DynamicResolution};
+    use crate::graphics::settings::GraphicsSettings;
+
+    #[test]
+    fn shader_validates() {
+        let module = naga::front::wgsl::parse_str(include_str!("../shaders/upsample.wgsl"))
+            .expect("upsample.wgsl should parse");
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .expect("upsample.wgsl should validate");
+    }
+
+    #[test]
+    fn extent() {
+        let extent = scaled_extent(1280, 720, 0.5);
+        assert_eq!((640, 360), (extent.width, extent.height));
+        let extent = scaled_extent(1, 1, 0.25);
+        assert_eq!((1, 1), (extent.width, extent.height));
+    }
+
+    /// Runs the controller for a few seconds of frames of the given time.
+    fn run(
+        dynamic_resolution: &mut DynamicResolution,
+        frame_time: Duration,
+        mut scale: f32,
+        settings: &GraphicsSettings,
+    ) -> f32 {
+        for _ in 0..(3.0 / frame_time.as_secs_f32()) as usize {
+            scale = dynamic_resolution.update(frame_time, scale, settings);
+        }
+        scale
+    }
+
+    #[test]
+    fn lowers_scale_when_slow_and_raises_when_fast() {
+        let settings = GraphicsSettings {
+            target_frame_rate: 60,
+            render_scale: 1.0,
+            ..Default::default()
+        };
+        let mut dynamic_resolution = DynamicResolution::default();
+        let slow = run(
+            &mut dynamic_resolution,
+            Duration::from_millis(30),
+            1.0,
+            &settings,
+        );
+        assert!(slow < 1.0);
+        assert!(slow >= GraphicsSettings::RENDER_SCALE_MIN);
+        let fast = run(
+            &mut dynamic_resolution,
+            Duration::from_millis(5),
+            slow,
+            &settings,
+        );
+        assert!(fast > slow);
+        assert!(fast <= settings.render_scale);
+    }
+
+    #[test]
+    fn keeps_scale_within_target() {
+        let settings = GraphicsSettings {
+            target_frame_rate: 60,
+            render_scale: 0.75,
+            ..Default::default()
+        };
+        let mut dynamic_resolution = DynamicResolution::default();
+        // Between the headroom and the target, the scale is left alone.
+        let scale = run(
+            &mut dynamic_resolution,
+            Duration::from_micros(15_000),
+            0.6,
+            &settings,
+        );
+        assert_eq!(0.6, scale);
+    }
+}
@@ -30,6 +30,37 @@ pub fn load_texture(
     texture::Texture::from_bytes(device, queue, &data, file_name)
 }
 
+/// Loads the positions and triangle indices of every model in the OBJ file, without its materials,
+/// e.g. to build a collidable mesh from.
+pub fn load_positions(file_name: &str) -> anyhow::Result<(Vec<cgmath::Vector3<f32>>, Vec<usize>)> {
+    let obj_text = load_string(file_name)?;
+    let mut obj_reader = BufReader::new(Cursor::new(obj_text));
+    let (models, _) = tobj::load_obj_buf(
+        &mut obj_reader,
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+        |_| Ok(Default::default()),
+    )?;
+
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for model in models {
+        let offset = positions.len();
+        positions.extend(
+            model
+                .mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| cgmath::Vector3::new(p[0], p[1], p[2])),
+        );
+        indices.extend(model.mesh.indices.iter().map(|i| *i as usize + offset));
+    }
+    Ok((positions, indices))
+}
+
 pub fn load_model(
     file_name: &str,
     device: &wgpu::Device,
@@ -110,10 +141,13 @@ pub fn load_model(
 
             model::Mesh {
                 name: file_name.to_string(),
+                vertex_positions: vertices.iter().map(|v| v.position.into()).collect(),
+                vertex_indices: m.mesh.indices.clone(),
                 vertex_buffer,
                 index_buffer,
                 num_elements: m.mesh.indices.len() as u32,
                 material: m.mesh.material_id.unwrap_or(0),
+                normal_recomputation: model::NormalRecomputation::Cpu,
             }
         })
         .collect::<Vec<_>>();
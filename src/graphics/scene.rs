@@ -1,33 +1,125 @@
 use crate::graphics::entity::ColoredMeshEntity;
 use crate::graphics::gpu_interface::GPUInterface;
-use crate::graphics::instance::Instance;
+use crate::graphics::instance::{self, Instance};
+use crate::pool::{Handle, Pool};
+use crate::simulation::raycast::Raycaster;
+use crate::simulation::springy::springy_mesh::SpringyMesh;
 use wgpu::BindGroup;
 
 use super::entity::Entity;
 
+pub type EntityHandle = Handle<Entity>;
+pub type ColoredMeshEntityHandle = Handle<ColoredMeshEntity>;
+
+/// Particles are drawn as instances of a colored mesh, but are oriented towards the camera.
+/// This is its own type so particle handles can't be mixed up with colored mesh entity handles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ParticlesHandle(Handle<ColoredMeshEntity>);
+
+/// An instance of an entity in a scene, e.g. as hit by a ray. See Scene::get_raycaster().
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SceneObject {
+    Entity {
+        handle: EntityHandle,
+        instance: usize,
+    },
+    ColoredMeshEntity {
+        handle: ColoredMeshEntityHandle,
+        instance: usize,
+    },
+}
+
+/// The entities to draw, addressed by the handles returned when they're added.
+/// Demos should keep the handles of entities they update alongside the simulation objects
+/// they're drawn from, rather than relying on the order entities were added in.
 pub struct Scene {
-    // TODO we don't enforce at compile time whether we passed in the correct entities for particles vs
-    //   for the entities field, so we may get bad behavior if order flips. WE should make a type to differentiate.
-    //   Or reeally, Entity might be best as a typed Enum - ParticleEntity, ColoredMeshEntity (or something)
-    //   so that the Scene just stores a list of entities. The behavior for drawing them etc is handled within each Entity variant.
-    entities: Option<Vec<Entity>>,
-    colored_mesh_entities: Option<Vec<ColoredMeshEntity>>,
-    particles: Option<Vec<ColoredMeshEntity>>,
+    entities: Pool<Entity>,
+    colored_mesh_entities: Pool<ColoredMeshEntity>,
+    particles: Pool<ColoredMeshEntity>,
 }
 
 impl Scene {
-    pub fn new(
-        entities: Option<Vec<Entity>>,
-        colored_mesh_entities: Option<Vec<ColoredMeshEntity>>,
-        particles: Option<Vec<ColoredMeshEntity>>,
-    ) -> Scene {
+    pub fn new() -> Scene {
         Scene {
-            entities,
-            colored_mesh_entities,
-            particles,
+            entities: Pool::new(),
+            colored_mesh_entities: Pool::new(),
+            particles: Pool::new(),
         }
     }
 
+    pub fn add_entity(&mut self, entity: Entity) -> EntityHandle {
+        self.entities.insert(entity)
+    }
+
+    pub fn add_colored_mesh_entity(
+        &mut self,
+        entity: ColoredMeshEntity,
+    ) -> ColoredMeshEntityHandle {
+        self.colored_mesh_entities.insert(entity)
+    }
+
+    pub fn add_particles(&mut self, particles: ColoredMeshEntity) -> ParticlesHandle {
+        ParticlesHandle(self.particles.insert(particles))
+    }
+
+    /// Removes the entity, returning it, or None if it was already removed.
+    pub fn remove_entity(&mut self, handle: EntityHandle) -> Option<Entity> {
+        self.entities.remove(handle)
+    }
+
+    pub fn remove_colored_mesh_entity(
+        &mut self,
+        handle: ColoredMeshEntityHandle,
+    ) -> Option<ColoredMeshEntity> {
+        self.colored_mesh_entities.remove(handle)
+    }
+
+    pub fn remove_particles(&mut self, handle: ParticlesHandle) -> Option<ColoredMeshEntity> {
+        self.particles.remove(handle.0)
+    }
+
+    pub fn get_entity(&self, handle: EntityHandle) -> Option<&Entity> {
+        self.entities.get(handle)
+    }
+
+    pub fn get_entity_mut(&mut self, handle: EntityHandle) -> Option<&mut Entity> {
+        self.entities.get_mut(handle)
+    }
+
+    pub fn get_colored_mesh_entity(
+        &self,
+        handle: ColoredMeshEntityHandle,
+    ) -> Option<&ColoredMeshEntity> {
+        self.colored_mesh_entities.get(handle)
+    }
+
+    pub fn get_colored_mesh_entity_mut(
+        &mut self,
+        handle: ColoredMeshEntityHandle,
+    ) -> Option<&mut ColoredMeshEntity> {
+        self.colored_mesh_entities.get_mut(handle)
+    }
+
+    pub fn get_particles(&self, handle: ParticlesHandle) -> Option<&ColoredMeshEntity> {
+        self.particles.get(handle.0)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = (EntityHandle, &Entity)> {
+        self.entities.iter()
+    }
+
+    pub fn colored_mesh_entities(
+        &self,
+    ) -> impl Iterator<Item = (ColoredMeshEntityHandle, &ColoredMeshEntity)> {
+        self.colored_mesh_entities.iter()
+    }
+
+    pub fn particles(&self) -> impl Iterator<Item = (ParticlesHandle, &ColoredMeshEntity)> {
+        self.particles
+            .iter()
+            .map(|(handle, particles)| (ParticlesHandle(handle), particles))
+    }
+
     /// Draws the entities in the scene.
     /// Note, assumes the caller has set the correct render pass for drawing Entity objects.
     pub fn draw_entities<'a, 'b>(
@@ -38,11 +130,9 @@ impl Scene {
     ) where
         'a: 'b,
     {
-        if let Some(entities) = &self.entities {
-            entities
-                .iter()
-                .for_each(|entity| entity.draw(render_pass, camera_bind_group, light_bind_group));
-        }
+        self.entities
+            .values()
+            .for_each(|entity| entity.draw(render_pass, camera_bind_group, light_bind_group));
     }
 
     /// Draws the colored mesh entities, including the particles, which are themselves instances colored
@@ -56,60 +146,140 @@ impl Scene {
     ) where
         'a: 'b,
     {
-        if let Some(entities) = &self.colored_mesh_entities {
-            entities
-                .iter()
-                .for_each(|entity| entity.draw(render_pass, camera_bind_group, light_bind_group));
-        }
+        self.colored_mesh_entities
+            .values()
+            .for_each(|entity| entity.draw(render_pass, camera_bind_group, light_bind_group));
 
-        if let Some(particles) = &self.particles {
-            for particle_group in particles.iter() {
-                particle_group.draw(render_pass, camera_bind_group, light_bind_group)
-            }
+        for particle_group in self.particles.values() {
+            particle_group.draw(render_pass, camera_bind_group, light_bind_group)
         }
     }
 
-    /// Updates the instances of the particle entity at the specific index.
-    /// Panics if the index is out of range of the scene's particles
-    /// TODO - Can we improve this API so we never panic?
+    /// Updates the instances of the particles, oriented towards the camera and sorted
+    /// front to back. Does nothing if the particles have been removed.
     pub fn update_particle_instances(
         &mut self,
         gpu: &GPUInterface,
-        particles_entity_index: usize,
-        instances: Vec<Instance>,
+        handle: ParticlesHandle,
+        mut instances: Vec<Instance>,
         camera_position: cgmath::Point3<f32>,
     ) {
-        if let Some(particles) = &mut self.particles {
-            particles[particles_entity_index].update_instances(gpu, instances);
-            particles[particles_entity_index].orient_instances(&gpu, camera_position);
+        if let Some(particles) = self.particles.get_mut(handle.0) {
+            instance::sort_front_to_back(&mut instances, camera_position);
+            particles.update_instances(gpu, instances);
+            particles.orient_instances(gpu, camera_position);
         }
     }
 
-    /// Updates the instances of the entity at the specific index.
-    /// Panics if the index is out of range of the scene's entities.
-    /// TODO - Can we improve this API so we never panic? And related to comment about entities, can we
-    /// combine with the particles one?
-    /// TODO - and can we combine with the update_colored_mesh_entity_instances()
+    /// Updates the instances of the entity.
+    /// Does nothing if the entity has been removed.
     pub fn update_entity_instances(
         &mut self,
         gpu: &GPUInterface,
-        entity_index: usize,
+        handle: EntityHandle,
         instances: Vec<Instance>,
     ) {
-        if let Some(entities) = &mut self.entities {
-            entities[entity_index].update_instances(gpu, instances);
+        if let Some(entity) = self.entities.get_mut(handle) {
+            entity.update_instances(gpu, instances);
         }
     }
 
-    #[allow(dead_code)]
+    /// Updates the instances of the colored mesh entity.
+    /// Does nothing if the entity has been removed.
     pub fn update_colored_mesh_entity_instances(
         &mut self,
         gpu: &GPUInterface,
-        entity_index: usize,
+        handle: ColoredMeshEntityHandle,
         instances: Vec<Instance>,
     ) {
-        if let Some(entities) = &mut self.colored_mesh_entities {
-            entities[entity_index].update_instances(gpu, instances);
+        if let Some(entity) = self.colored_mesh_entities.get_mut(handle) {
+            entity.update_instances(gpu, instances);
+        }
+    }
+
+    /// Updates the vertex positions of the colored mesh entity.
+    /// Does nothing if the entity has been removed.
+    pub fn update_colored_mesh_entity_vertices(
+        &mut self,
+        gpu: &GPUInterface,
+        handle: ColoredMeshEntityHandle,
+        vertex_positions: Vec<cgmath::Vector3<f32>>,
+    ) -> Result<(), &'static str> {
+        match self.colored_mesh_entities.get_mut(handle) {
+            Some(entity) => entity.update_mesh_vertices(gpu, vertex_positions),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the color of the colored mesh entity.
+    /// Does nothing if the entity has been removed.
+    pub fn set_colored_mesh_entity_color(
+        &mut self,
+        gpu: &GPUInterface,
+        handle: ColoredMeshEntityHandle,
+        color: [f32; 3],
+    ) {
+        if let Some(entity) = self.colored_mesh_entities.get_mut(handle) {
+            entity.set_color(gpu, color);
         }
     }
+
+    /// Updates the entity, whose model's first mesh was created from the springy mesh.
+    /// Does nothing if the entity has been removed.
+    pub fn update_entity_springy_mesh(
+        &mut self,
+        gpu: &GPUInterface,
+        handle: EntityHandle,
+        springy_mesh: &SpringyMesh,
+    ) {
+        if let Some(entity) = self.entities.get(handle) {
+            entity.update_springy_mesh(gpu, 0, springy_mesh);
+        }
+    }
+
+    /// Writes the image over the texture of the entity's model's first material.
+    /// Does nothing if the entity has been removed.
+    pub fn update_entity_texture(
+        &mut self,
+        gpu: &GPUInterface,
+        handle: EntityHandle,
+        img: &image::RgbaImage,
+    ) {
+        if let Some(entity) = self.entities.get(handle) {
+            entity.write_texture(gpu, 0, img);
+        }
+    }
+}
+
+impl Scene {
+    /// Builds a raycaster over the instances of the scene's entities and colored mesh entities,
+    /// as they're currently placed, e.g. to pick the object under the cursor. Particles are excluded.
+    pub fn get_raycaster(&self) -> Raycaster<SceneObject> {
+        let entities = self.entities.iter().flat_map(|(handle, entity)| {
+            entity
+                .get_faces()
+                .into_iter()
+                .enumerate()
+                .map(move |(instance, faces)| (SceneObject::Entity { handle, instance }, faces))
+        });
+        let colored_mesh_entities =
+            self.colored_mesh_entities
+                .iter()
+                .flat_map(|(handle, entity)| {
+                    entity
+                        .get_faces()
+                        .into_iter()
+                        .enumerate()
+                        .map(move |(instance, faces)| {
+                            (SceneObject::ColoredMeshEntity { handle, instance }, faces)
+                        })
+                });
+        Raycaster::new(entities.chain(colored_mesh_entities))
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene::new()
+    }
 }
@@ -0,0 +1,510 @@
+/// Graphics settings shared by all demos, persisted between runs as a simple key = value file.
+use std::{fmt::Write, str::FromStr};
+
+use clap::ValueEnum;
+use winit::{
+    event_loop::ControlFlow,
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window},
+};
+
+/// The settings file, relative to the working directory.
+pub const SETTINGS_FILE: &str = "graphics_settings.txt";
+/// How often simulations are stepped while their window's suspended, if they keep stepping.
+const BACKGROUND_STEP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum WindowMode {
+    Windowed,
+    /// Fullscreen in a borderless window, keeping the desktop's video mode.
+    Borderless,
+    /// Exclusive fullscreen, in the monitor's largest video mode.
+    Fullscreen,
+}
+
+impl WindowMode {
+    pub const ALL: [WindowMode; 3] = [
+        WindowMode::Windowed,
+        WindowMode::Borderless,
+        WindowMode::Fullscreen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowMode::Windowed => "Windowed",
+            WindowMode::Borderless => "Borderless",
+            WindowMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    /// The winit fullscreen mode to use on the monitor, or None if windowed.
+    /// Exclusive fullscreen falls back to borderless if the monitor has no video modes.
+    pub fn fullscreen(&self, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+        match self {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            WindowMode::Fullscreen => {
+                let video_mode = monitor.as_ref().and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|video_mode| {
+                        let size = video_mode.size();
+                        (size.width * size.height, video_mode.refresh_rate())
+                    })
+                });
+                match video_mode {
+                    Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                    None => Some(Fullscreen::Borderless(monitor)),
+                }
+            }
+        }
+    }
+
+    pub fn of_window(window: &Window) -> WindowMode {
+        match window.fullscreen() {
+            None => WindowMode::Windowed,
+            Some(Fullscreen::Borderless(_)) => WindowMode::Borderless,
+            Some(Fullscreen::Exclusive(_)) => WindowMode::Fullscreen,
+        }
+    }
+}
+
+impl FromStr for WindowMode {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        WindowMode::ALL
+            .into_iter()
+            .find(|mode| mode.name() == name)
+            .ok_or("Unknown window mode")
+    }
+}
+
+/// How transparent particles are rendered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransparencyMode {
+    /// Particles are drawn as opaque meshes.
+    Opaque,
+    /// Particles are translucent, blended with weighted, blended order-independent transparency
+    /// so they don't need to be sorted.
+    WeightedBlended,
+    /// Particles are translucent, sorted back to front on the GPU each frame and alpha blended in order.
+    Sorted,
+}
+
+impl TransparencyMode {
+    pub const ALL: [TransparencyMode; 3] = [
+        TransparencyMode::Opaque,
+        TransparencyMode::WeightedBlended,
+        TransparencyMode::Sorted,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransparencyMode::Opaque => "Opaque",
+            TransparencyMode::WeightedBlended => "Weighted Blended",
+            TransparencyMode::Sorted => "Sorted",
+        }
+    }
+}
+
+impl FromStr for TransparencyMode {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        TransparencyMode::ALL
+            .into_iter()
+            .find(|mode| mode.name() == name)
+            .ok_or("Unknown transparency mode")
+    }
+}
+
+/// The number of samples per pixel taken to estimate screen-space ambient occlusion, if any.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AmbientOcclusionQuality {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl AmbientOcclusionQuality {
+    pub const ALL: [AmbientOcclusionQuality; 4] = [
+        AmbientOcclusionQuality::Off,
+        AmbientOcclusionQuality::Low,
+        AmbientOcclusionQuality::Medium,
+        AmbientOcclusionQuality::High,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AmbientOcclusionQuality::Off => "Off",
+            AmbientOcclusionQuality::Low => "Low",
+            AmbientOcclusionQuality::Medium => "Medium",
+            AmbientOcclusionQuality::High => "High",
+        }
+    }
+
+    /// The samples per pixel, or None if ambient occlusion is off.
+    pub fn sample_count(&self) -> Option<u32> {
+        match self {
+            AmbientOcclusionQuality::Off => None,
+            AmbientOcclusionQuality::Low => Some(8),
+            AmbientOcclusionQuality::Medium => Some(16),
+            AmbientOcclusionQuality::High => Some(32),
+        }
+    }
+}
+
+impl FromStr for AmbientOcclusionQuality {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        AmbientOcclusionQuality::ALL
+            .into_iter()
+            .find(|quality| quality.name() == name)
+            .ok_or("Unknown ambient occlusion quality")
+    }
+}
+
+/// What simulations do while their window's suspended, e.g. minimized, when nothing is rendered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BackgroundStepping {
+    /// The simulation pauses, and resumes where it left off once the window's shown again.
+    Pause,
+    /// The simulation keeps stepping in real time, e.g. to let a long run settle while minimized.
+    Continue,
+}
+
+impl BackgroundStepping {
+    pub const ALL: [BackgroundStepping; 2] =
+        [BackgroundStepping::Pause, BackgroundStepping::Continue];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackgroundStepping::Pause => "Pause",
+            BackgroundStepping::Continue => "Keep Stepping",
+        }
+    }
+
+    /// Whether to step the simulation this frame, given whether its window's suspended.
+    pub fn steps(&self, suspended: bool) -> bool {
+        !suspended || *self == BackgroundStepping::Continue
+    }
+
+    /// How the event loop should wait for the next frame. Frames are drawn as fast as they can be while the
+    /// window's shown, and while it's suspended the loop only wakes for events, or to step the simulation.
+    pub fn control_flow(&self, suspended: bool) -> ControlFlow {
+        match (suspended, self) {
+            (false, _) => ControlFlow::Poll,
+            (true, BackgroundStepping::Pause) => ControlFlow::Wait,
+            (true, BackgroundStepping::Continue) => {
+                ControlFlow::WaitUntil(std::time::Instant::now() + BACKGROUND_STEP_INTERVAL)
+            }
+        }
+    }
+}
+
+impl FromStr for BackgroundStepping {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        BackgroundStepping::ALL
+            .into_iter()
+            .find(|stepping| stepping.name() == name)
+            .ok_or("Unknown background stepping")
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GraphicsSettings {
+    pub vsync: bool,
+    pub window_mode: WindowMode,
+    /// The number of samples per pixel; 1 disables MSAA.
+    pub msaa_samples: u32,
+    /// The requested resolution of the window when windowed, in physical pixels.
+    pub width: u32,
+    pub height: u32,
+    /// The vertical field of view, in degrees.
+    pub fov: f32,
+    /// Renders a view per eye side by side, for stereo viewers.
+    pub stereo: bool,
+    /// The resolution of the 3D view relative to the window's; the GUI is always at full
+    /// resolution. This is the largest scale used when dynamic_resolution is enabled.
+    pub render_scale: f32,
+    /// Lowers the render scale automatically while below the target frame rate.
+    pub dynamic_resolution: bool,
+    pub target_frame_rate: u32,
+    /// Renders the depth of opaque particles before shading them, so that each pixel is only
+    /// shaded once in dense particle scenes.
+    pub depth_prepass: bool,
+    pub transparency_mode: TransparencyMode,
+    pub ambient_occlusion: AmbientOcclusionQuality,
+    /// The distance within which surfaces occlude each other, in meters.
+    pub ambient_occlusion_radius: f32,
+    pub background_stepping: BackgroundStepping,
+}
+
+impl GraphicsSettings {
+    /// wgpu guarantees that 1 and 4 samples are supported for all render attachment formats.
+    pub const MSAA_SAMPLES: [u32; 2] = [1, 4];
+    pub const RESOLUTION_MIN: u32 = 320;
+    pub const RESOLUTION_MAX: u32 = 7680;
+    pub const FOV_MIN: f32 = 20.0;
+    pub const FOV_MAX: f32 = 120.0;
+    pub const RENDER_SCALE_MIN: f32 = 0.25;
+    pub const RENDER_SCALE_MAX: f32 = 1.0;
+    pub const TARGET_FRAME_RATE_MIN: u32 = 20;
+    pub const TARGET_FRAME_RATE_MAX: u32 = 240;
+    pub const AMBIENT_OCCLUSION_RADIUS_MIN: f32 = 0.05;
+    pub const AMBIENT_OCCLUSION_RADIUS_MAX: f32 = 2.0;
+
+    /// Loads the settings from SETTINGS_FILE, or the defaults if it doesn't exist.
+    pub fn load() -> GraphicsSettings {
+        match std::fs::read_to_string(SETTINGS_FILE) {
+            Ok(contents) => GraphicsSettings::parse(&contents),
+            Err(_) => GraphicsSettings::default(),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        std::fs::write(SETTINGS_FILE, self.file_contents())?;
+        Ok(())
+    }
+
+    /// Parses the settings from key = value lines.
+    /// Missing, unknown, and invalid entries are ignored, leaving those settings at their defaults.
+    pub fn parse(contents: &str) -> GraphicsSettings {
+        let mut settings = GraphicsSettings::default();
+        for line in contents.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "vsync" => set_parsed(&mut settings.vsync, value),
+                "window_mode" => set_parsed(&mut settings.window_mode, value),
+                "msaa_samples" => set_parsed(&mut settings.msaa_samples, value),
+                "width" => set_parsed(&mut settings.width, value),
+                "height" => set_parsed(&mut settings.height, value),
+                "fov" => set_parsed(&mut settings.fov, value),
+                "stereo" => set_parsed(&mut settings.stereo, value),
+                "render_scale" => set_parsed(&mut settings.render_scale, value),
+                "dynamic_resolution" => set_parsed(&mut settings.dynamic_resolution, value),
+                "target_frame_rate" => set_parsed(&mut settings.target_frame_rate, value),
+                "depth_prepass" => set_parsed(&mut settings.depth_prepass, value),
+                "transparency_mode" => set_parsed(&mut settings.transparency_mode, value),
+                "ambient_occlusion" => set_parsed(&mut settings.ambient_occlusion, value),
+                "ambient_occlusion_radius" => {
+                    set_parsed(&mut settings.ambient_occlusion_radius, value)
+                }
+                "background_stepping" => set_parsed(&mut settings.background_stepping, value),
+                _ => {}
+            }
+        }
+        settings.validated()
+    }
+
+    pub fn file_contents(&self) -> String {
+        let mut contents = String::new();
+        writeln!(contents, "vsync = {}", self.vsync).unwrap();
+        writeln!(contents, "window_mode = {}", self.window_mode.name()).unwrap();
+        writeln!(contents, "msaa_samples = {}", self.msaa_samples).unwrap();
+        writeln!(contents, "width = {}", self.width).unwrap();
+        writeln!(contents, "height = {}", self.height).unwrap();
+        writeln!(contents, "fov = {}", self.fov).unwrap();
+        writeln!(contents, "stereo = {}", self.stereo).unwrap();
+        writeln!(contents, "render_scale = {}", self.render_scale).unwrap();
+        writeln!(contents, "dynamic_resolution = {}", self.dynamic_resolution).unwrap();
+        writeln!(contents, "target_frame_rate = {}", self.target_frame_rate).unwrap();
+        writeln!(contents, "depth_prepass = {}", self.depth_prepass).unwrap();
+        writeln!(
+            contents,
+            "transparency_mode = {}",
+            self.transparency_mode.name()
+        )
+        .unwrap();
+        writeln!(
+            contents,
+            "ambient_occlusion = {}",
+            self.ambient_occlusion.name()
+        )
+        .unwrap();
+        writeln!(
+            contents,
+            "ambient_occlusion_radius = {}",
+            self.ambient_occlusion_radius
+        )
+        .unwrap();
+        writeln!(
+            contents,
+            "background_stepping = {}",
+            self.background_stepping.name()
+        )
+        .unwrap();
+        contents
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        // The Auto modes fall back to whatever the surface supports, ending with Fifo.
+        if self.vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        }
+    }
+
+    /// The settings with any out of range values replaced by their defaults or clamped.
+    fn validated(mut self) -> GraphicsSettings {
+        let defaults = GraphicsSettings::default();
+        if !GraphicsSettings::MSAA_SAMPLES.contains(&self.msaa_samples) {
+            self.msaa_samples = defaults.msaa_samples;
+        }
+        self.width = self.width.clamp(
+            GraphicsSettings::RESOLUTION_MIN,
+            GraphicsSettings::RESOLUTION_MAX,
+        );
+        self.height = self.height.clamp(
+            GraphicsSettings::RESOLUTION_MIN,
+            GraphicsSettings::RESOLUTION_MAX,
+        );
+        self.fov = if self.fov.is_finite() {
+            self.fov
+                .clamp(GraphicsSettings::FOV_MIN, GraphicsSettings::FOV_MAX)
+        } else {
+            defaults.fov
+        };
+        self.render_scale = if self.render_scale.is_finite() {
+            self.render_scale.clamp(
+                GraphicsSettings::RENDER_SCALE_MIN,
+                GraphicsSettings::RENDER_SCALE_MAX,
+            )
+        } else {
+            defaults.render_scale
+        };
+        self.ambient_occlusion_radius = if self.ambient_occlusion_radius.is_finite() {
+            self.ambient_occlusion_radius.clamp(
+                GraphicsSettings::AMBIENT_OCCLUSION_RADIUS_MIN,
+                GraphicsSettings::AMBIENT_OCCLUSION_RADIUS_MAX,
+            )
+        } else {
+            defaults.ambient_occlusion_radius
+        };
+        self.target_frame_rate = self.target_frame_rate.clamp(
+            GraphicsSettings::TARGET_FRAME_RATE_MIN,
+            GraphicsSettings::TARGET_FRAME_RATE_MAX,
+        );
+        self
+    }
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        GraphicsSettings {
+            vsync: true,
+            window_mode: WindowMode::Windowed,
+            msaa_samples: 1,
+            width: 1280,
+            height: 720,
+            fov: 45.0,
+            stereo: false,
+            render_scale: 1.0,
+            dynamic_resolution: false,
+            target_frame_rate: 60,
+            depth_prepass: false,
+            transparency_mode: TransparencyMode::Opaque,
+            ambient_occlusion: AmbientOcclusionQuality::Off,
+            ambient_occlusion_radius: 0.5,
+            background_stepping: BackgroundStepping::Pause,
+        }
+    }
+}
+
+fn set_parsed<T: std::str::FromStr>(setting: &mut T, value: &str) {
+    if let Ok(value) = value.parse() {
+        *setting = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AmbientOcclusionQuality, BackgroundStepping, GraphicsSettings, TransparencyMode, WindowMode,
+    };
+    use winit::event_loop::ControlFlow;
+
+    #[test]
+    fn round_trip() {
+        let settings = GraphicsSettings {
+            vsync: false,
+            window_mode: WindowMode::Borderless,
+            msaa_samples: 4,
+            width: 1920,
+            height: 1080,
+            fov: 60.5,
+            stereo: true,
+            render_scale: 0.5,
+            dynamic_resolution: true,
+            target_frame_rate: 144,
+            depth_prepass: true,
+            transparency_mode: TransparencyMode::WeightedBlended,
+            ambient_occlusion: AmbientOcclusionQuality::Medium,
+            ambient_occlusion_radius: 0.25,
+            background_stepping: BackgroundStepping::Continue,
+        };
+        assert_eq!(settings, GraphicsSettings::parse(&settings.file_contents()));
+    }
+
+    #[test]
+    fn parse_ignores_bad_entries() {
+        let settings = GraphicsSettings::parse(
+            "vsync = maybe\n\
+             window_mode = Maximized\n\
+             msaa_samples = 3\n\
+             width=10\n\
+             fov = 90\n\
+             stereo = 1\n\
+             render_scale = 2.5\n\
+             target_frame_rate = fast\n\
+             transparency_mode = Glass\n\
+             ambient_occlusion = Ultra\n\
+             ambient_occlusion_radius = NaN\n\
+             background_stepping = Sometimes\n\
+             unknown = 1\n\
+             not a setting",
+        );
+        let defaults = GraphicsSettings::default();
+        assert_eq!(defaults.vsync, settings.vsync);
+        assert_eq!(defaults.window_mode, settings.window_mode);
+        assert_eq!(defaults.msaa_samples, settings.msaa_samples);
+        assert_eq!(GraphicsSettings::RESOLUTION_MIN, settings.width);
+        assert_eq!(defaults.height, settings.height);
+        assert_eq!(90.0, settings.fov);
+        assert_eq!(defaults.stereo, settings.stereo);
+        assert_eq!(GraphicsSettings::RENDER_SCALE_MAX, settings.render_scale);
+        assert_eq!(defaults.target_frame_rate, settings.target_frame_rate);
+        assert_eq!(defaults.transparency_mode, settings.transparency_mode);
+        assert_eq!(defaults.ambient_occlusion, settings.ambient_occlusion);
+        assert_eq!(
+            defaults.ambient_occlusion_radius,
+            settings.ambient_occlusion_radius
+        );
+        assert_eq!(defaults.background_stepping, settings.background_stepping);
+    }
+
+    #[test]
+    fn background_stepping_while_suspended() {
+        for stepping in BackgroundStepping::ALL {
+            assert!(stepping.steps(false));
+            assert_eq!(ControlFlow::Poll, stepping.control_flow(false));
+        }
+        assert!(!BackgroundStepping::Pause.steps(true));
+        assert_eq!(
+            ControlFlow::Wait,
+            BackgroundStepping::Pause.control_flow(true)
+        );
+        assert!(BackgroundStepping::Continue.steps(true));
+        assert!(matches!(
+            BackgroundStepping::Continue.control_flow(true),
+            ControlFlow::WaitUntil(_)
+        ));
+    }
+}
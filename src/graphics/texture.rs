@@ -20,6 +20,28 @@ impl Texture {
         Self::from_image(device, queue, &img, Some(label))
     }
 
+    /// Creates a checkerboard texture of checks x checks squares, alternating between the two colors.
+    pub fn checkerboard(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        checks: u32,
+        colors: [[u8; 3]; 2],
+        label: &str,
+    ) -> Result<Self> {
+        const PIXELS_PER_CHECK: u32 = 16;
+        let size = checks * PIXELS_PER_CHECK;
+        let img = image::RgbaImage::from_fn(size, size, |x, y| {
+            let color = colors[((x / PIXELS_PER_CHECK + y / PIXELS_PER_CHECK) % 2) as usize];
+            image::Rgba([color[0], color[1], color[2], 255])
+        });
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some(label),
+        )
+    }
+
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -79,22 +101,43 @@ impl Texture {
         })
     }
 
+    /// Schedules a write of the image over the texture, e.g. to show a changing field.
+    /// The image must match the size of the one the texture was created from.
+    pub fn write(&self, queue: &wgpu::Queue, img: &image::RgbaImage) {
+        let (width, height) = img.dimensions();
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            img,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * width),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// The sample count must match that of the pipelines rendering to it.
     pub fn create_depth_texture(
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
+        size: wgpu::Extent3d,
+        sample_count: u32,
         label: &str,
     ) -> Self {
-        let size = wgpu::Extent3d {
-            width: config.width, // Match the size of the screen
-            height: config.height,
-            depth_or_array_layers: 1,
-        };
-
         let desc = wgpu::TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
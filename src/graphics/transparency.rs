@@ -0,0 +1,399 @@
+/// Weighted, blended order-independent transparency (McGuire and Bavoil 2013), for drawing many
+/// overlapping transparent particles without sorting them.
+/// Transparent geometry is drawn into an accumulation and a revealage target after the opaque
+/// scene, testing against but not writing its depth, and then composited over the scene.
+/// Alternatively, transparent particles sorted back to front are alpha blended over the scene in order.
+use wgpu::{BindGroupLayout, RenderPipeline};
+
+use super::{
+    camera::CameraBundle,
+    gpu_interface::GPUInterface,
+    instance,
+    model::{ColoredVertex, Vertex},
+    texture,
+};
+
+/// The sum of the weighted, premultiplied colors and alphas of the fragments.
+pub const ACCUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// The product of the transparencies of the fragments, i.e. how much of the scene shows through.
+pub const REVEAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// The accumulation and revealage targets, at the scene's render size.
+pub struct OitTargets {
+    accum: wgpu::TextureView,
+    reveal: wgpu::TextureView,
+    /// The multisampled targets resolved into accum and reveal, if MSAA is enabled.
+    msaa: Option<(wgpu::TextureView, wgpu::TextureView)>,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Composites OitTargets over the opaque scene.
+pub struct OitCompositor {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl OitCompositor {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> OitCompositor {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[texture_entry(0), texture_entry(1)],
+            label: Some("oit_composite_bind_group_layout"),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("OIT Composite Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("OIT Composite Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/oit_composite.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("OIT Composite Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        OitCompositor {
+            pipeline,
+            bind_group_layout,
+        }
+    }
+
+    pub fn create_targets(
+        &self,
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+        sample_count: u32,
+    ) -> OitTargets {
+        let create_view = |label, format, sample_count, usage| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size,
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let resolved_usage =
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING;
+        let accum = create_view("oit accum", ACCUM_FORMAT, 1, resolved_usage);
+        let reveal = create_view("oit reveal", REVEAL_FORMAT, 1, resolved_usage);
+        let msaa = if sample_count > 1 {
+            Some((
+                create_view(
+                    "oit accum msaa",
+                    ACCUM_FORMAT,
+                    sample_count,
+                    wgpu::TextureUsages::RENDER_ATTACHMENT,
+                ),
+                create_view(
+                    "oit reveal msaa",
+                    REVEAL_FORMAT,
+                    sample_count,
+                    wgpu::TextureUsages::RENDER_ATTACHMENT,
+                ),
+            ))
+        } else {
+            None
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&accum),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&reveal),
+                },
+            ],
+            label: Some("oit_composite_bind_group"),
+        });
+        OitTargets {
+            accum,
+            reveal,
+            msaa,
+            bind_group,
+        }
+    }
+
+    /// Encodes compositing the transparent fragments over the scene rendered into the view.
+    pub fn composite(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        targets: &OitTargets,
+        view: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("OIT Composite Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &targets.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Begins the pass drawing transparent geometry into the targets, testing against the depth
+/// of the opaque scene.
+pub fn begin_accumulation_pass<'pass>(
+    encoder: &'pass mut wgpu::CommandEncoder,
+    targets: &'pass OitTargets,
+    depth_texture_view: &'pass wgpu::TextureView,
+) -> wgpu::RenderPass<'pass> {
+    let ((accum, accum_resolve), (reveal, reveal_resolve)) = match &targets.msaa {
+        Some((accum, reveal)) => (
+            (accum, Some(&targets.accum)),
+            (reveal, Some(&targets.reveal)),
+        ),
+        None => ((&targets.accum, None), (&targets.reveal, None)),
+    };
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("OIT Accumulation Render Pass"),
+        color_attachments: &[
+            Some(wgpu::RenderPassColorAttachment {
+                view: accum,
+                resolve_target: accum_resolve,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            }),
+            Some(wgpu::RenderPassColorAttachment {
+                view: reveal,
+                resolve_target: reveal_resolve,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: true,
+                },
+            }),
+        ],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_texture_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: false,
+            }),
+            stencil_ops: None,
+        }),
+    })
+}
+
+/// Creates the pipeline drawing lit, colored meshes as transparent particles in the
+/// accumulation pass.
+pub fn create_particle_pipeline(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+    light_bind_group_layout: &BindGroupLayout,
+) -> RenderPipeline {
+    let layout = gpu
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("OIT Particles Pipeline Layout"),
+            bind_group_layouts: &[
+                &camera_bundle.camera_bind_group_layout,
+                light_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+    let shader = gpu
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("OIT Particles Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/oit_particles.wgsl").into()),
+        });
+    gpu.device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("OIT Particles Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[ColoredVertex::desc(), instance::InstanceRaw::desc::<5>()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: ACCUM_FORMAT,
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::One,
+                                dst_factor: wgpu::BlendFactor::One,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::One,
+                                dst_factor: wgpu::BlendFactor::One,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: REVEAL_FORMAT,
+                        // Multiplies the revealage by one minus each fragment's alpha.
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::Zero,
+                                dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent::REPLACE,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            // Transparent fragments are hidden by the opaque scene, but not by each other.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: gpu.settings.msaa_samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+}
+
+/// Creates the pipeline drawing lit, colored meshes as transparent particles alpha blended over the scene,
+/// in the scene's render pass after the opaque geometry. The particles must be drawn back to front,
+/// e.g. from a GpuDepthSort's sorted instances.
+pub fn create_sorted_particle_pipeline(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+    light_bind_group_layout: &BindGroupLayout,
+) -> RenderPipeline {
+    let layout = gpu
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sorted Particles Pipeline Layout"),
+            bind_group_layouts: &[
+                &camera_bundle.camera_bind_group_layout,
+                light_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+    let shader = gpu
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sorted Particles Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/oit_particles.wgsl").into()),
+        });
+    gpu.device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sorted Particles Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[ColoredVertex::desc(), instance::InstanceRaw::desc::<5>()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_sorted",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: gpu.config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            // As in the accumulation pass, particles don't hide each other; their order does.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: gpu.settings.msaa_samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    fn validate(source: &str, name: &str) {
+        let module = naga::front::wgsl::parse_str(source)
+            .unwrap_or_else(|err| panic!("{} should parse: {:?}", name, err));
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap_or_else(|err| panic!("{} should validate: {:?}", name, err));
+    }
+
+    #[test]
+    fn shaders_validate() {
+        validate(
+            include_str!("../shaders/oit_particles.wgsl"),
+            "oit_particles.wgsl",
+        );
+        validate(
+            include_str!("../shaders/oit_composite.wgsl"),
+            "oit_composite.wgsl",
+        );
+    }
+}
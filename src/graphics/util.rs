@@ -1,26 +1,75 @@
 use cgmath::InnerSpace;
 use itertools::Itertools;
 use wgpu::{BindGroupLayout, RenderPipeline};
+use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::{
     graphics::camera::CameraBundle,
-    graphics::gpu_interface::GPUInterface,
+    graphics::gpu_interface::{self, GPUInterface},
     graphics::instance,
     graphics::model::{ColoredVertex, Vertex},
     graphics::texture,
 };
 
-use super::{camera::Projection, model::ModelVertex};
+use super::{camera::Projection, model::ModelVertex, settings::GraphicsSettings};
+
+/// How a pipeline tests and writes depth, relative to a depth pre-pass.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Tests against and writes depth, without a pre-pass.
+    Default,
+    /// Only writes depth, without any color, ahead of the main pass.
+    PrePass,
+    /// Draws color only where the depth matches what the pre-pass wrote, so each pixel is
+    /// shaded once, without writing depth again.
+    AfterPrePass,
+}
 
 pub fn create_render_pipeline(
     device: &wgpu::Device,
     layout: &wgpu::PipelineLayout,
     color_format: wgpu::TextureFormat,
     depth_format: Option<wgpu::TextureFormat>,
+    sample_count: u32,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    create_render_pipeline_with_depth_mode(
+        device,
+        layout,
+        color_format,
+        depth_format,
+        DepthMode::Default,
+        sample_count,
+        vertex_layouts,
+        shader,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_render_pipeline_with_depth_mode(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    depth_mode: DepthMode,
+    sample_count: u32,
     vertex_layouts: &[wgpu::VertexBufferLayout],
     shader: wgpu::ShaderModuleDescriptor,
 ) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(shader);
+    let color_targets = [Some(wgpu::ColorTargetState {
+        format: color_format,
+        blend: Some(wgpu::BlendState {
+            alpha: wgpu::BlendComponent::REPLACE,
+            color: wgpu::BlendComponent::REPLACE,
+        }),
+        write_mask: wgpu::ColorWrites::ALL,
+    })];
+    let (depth_write_enabled, depth_compare) = match depth_mode {
+        DepthMode::Default | DepthMode::PrePass => (true, wgpu::CompareFunction::Less),
+        DepthMode::AfterPrePass => (false, wgpu::CompareFunction::LessEqual),
+    };
 
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Render Pipeline"),
@@ -30,18 +79,15 @@ pub fn create_render_pipeline(
             entry_point: "vs_main",
             buffers: vertex_layouts,
         },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: color_format,
-                blend: Some(wgpu::BlendState {
-                    alpha: wgpu::BlendComponent::REPLACE,
-                    color: wgpu::BlendComponent::REPLACE,
-                }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        }),
+        // The pre-pass has no color attachments, so it doesn't need a fragment shader.
+        fragment: match depth_mode {
+            DepthMode::PrePass => None,
+            _ => Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &color_targets,
+            }),
+        },
         primitive: wgpu::PrimitiveState {
             topology: wgpu::PrimitiveTopology::TriangleList,
             strip_index_format: None,
@@ -56,13 +102,13 @@ pub fn create_render_pipeline(
         },
         depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
             format,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
+            depth_write_enabled,
+            depth_compare,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
         multisample: wgpu::MultisampleState {
-            count: 1,
+            count: sample_count,
             mask: !0,
             alpha_to_coverage_enabled: false,
         },
@@ -74,6 +120,50 @@ pub fn create_colored_mesh_render_pipeline(
     gpu: &GPUInterface,
     camera_bundle: &CameraBundle,
     light_bind_group_layout: &BindGroupLayout,
+) -> RenderPipeline {
+    create_colored_mesh_render_pipeline_with_depth_mode(
+        gpu,
+        camera_bundle,
+        light_bind_group_layout,
+        DepthMode::Default,
+    )
+}
+
+/// Creates the colored mesh pipeline, and the pipeline for its depth pre-pass if enabled in the
+/// graphics settings, respectively.
+pub fn create_colored_mesh_render_pipelines(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+    light_bind_group_layout: &BindGroupLayout,
+) -> (RenderPipeline, Option<RenderPipeline>) {
+    if gpu.settings.depth_prepass {
+        (
+            create_colored_mesh_render_pipeline_with_depth_mode(
+                gpu,
+                camera_bundle,
+                light_bind_group_layout,
+                DepthMode::AfterPrePass,
+            ),
+            Some(create_colored_mesh_render_pipeline_with_depth_mode(
+                gpu,
+                camera_bundle,
+                light_bind_group_layout,
+                DepthMode::PrePass,
+            )),
+        )
+    } else {
+        (
+            create_colored_mesh_render_pipeline(gpu, camera_bundle, light_bind_group_layout),
+            None,
+        )
+    }
+}
+
+fn create_colored_mesh_render_pipeline_with_depth_mode(
+    gpu: &GPUInterface,
+    camera_bundle: &CameraBundle,
+    light_bind_group_layout: &BindGroupLayout,
+    depth_mode: DepthMode,
 ) -> RenderPipeline {
     let layout = gpu
         .device
@@ -89,11 +179,13 @@ pub fn create_colored_mesh_render_pipeline(
         label: Some("Colored Shader"),
         source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/color_shader.wgsl").into()),
     };
-    create_render_pipeline(
+    create_render_pipeline_with_depth_mode(
         &gpu.device,
         &layout,
         gpu.config.format,
         Some(texture::Texture::DEPTH_FORMAT),
+        depth_mode,
+        gpu.settings.msaa_samples,
         &[ColoredVertex::desc(), instance::InstanceRaw::desc::<5>()],
         shader,
     )
@@ -125,6 +217,7 @@ pub fn create_model_render_pipeline(
         &layout,
         gpu.config.format,
         Some(texture::Texture::DEPTH_FORMAT),
+        gpu.settings.msaa_samples,
         &[ModelVertex::desc(), instance::InstanceRaw::desc::<5>()],
         shader,
     )
@@ -161,23 +254,80 @@ pub fn resize(
     depth_texture: &mut texture::Texture,
     projection: &mut Projection,
 ) {
-    if new_size.width > 0 && new_size.height > 0 {
-        gpu.size = new_size;
+    // A zero size suspends rendering, keeping the config at the last size that could be rendered at.
+    gpu.size = new_size;
+    if let Some(new_size) = gpu_interface::surface_size(new_size) {
         gpu.config.width = new_size.width;
         gpu.config.height = new_size.height;
-        gpu.surface.configure(&gpu.device, &gpu.config);
+        gpu.configure();
         // depth_texture must be udpated *after* the config, to get new width and height.
-        *depth_texture =
-            texture::Texture::create_depth_texture(&gpu.device, &gpu.config, "depth_texture");
+        *depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth_texture",
+        );
         projection.resize(new_size.width, new_size.height)
     }
 }
 
+/// Applies and persists the graphics settings, requesting a window resize or fullscreen change
+/// if the resolution or window mode changed; the resize itself is handled by the window's
+/// Resized event.
+/// Returns true if the number of MSAA samples changed, in which case the caller must
+/// recreate its render pipelines.
+pub fn apply_graphics_settings(
+    settings: GraphicsSettings,
+    window: &Window,
+    gpu: &mut GPUInterface,
+    depth_texture: &mut texture::Texture,
+    camera_bundle: &mut CameraBundle,
+) -> bool {
+    if settings.window_mode != gpu.settings.window_mode {
+        window.set_fullscreen(settings.window_mode.fullscreen(window.current_monitor()));
+    }
+    if settings.width != gpu.settings.width || settings.height != gpu.settings.height {
+        window.set_inner_size(PhysicalSize::new(settings.width, settings.height));
+    }
+    camera_bundle.projection.set_fovy(cgmath::Deg(settings.fov));
+    if settings.stereo != gpu.settings.stereo {
+        camera_bundle.set_stereo(gpu, settings.stereo);
+    }
+    let msaa_changed = gpu.apply_settings(settings);
+    *depth_texture = texture::Texture::create_depth_texture(
+        &gpu.device,
+        gpu.render_size(),
+        gpu.settings.msaa_samples,
+        "depth_texture",
+    );
+    if let Err(err) = settings.save() {
+        log::warn!("Failed to save graphics settings: {}", err);
+    }
+    msaa_changed
+}
+
+/// Adjusts the render scale for the frame time if dynamic resolution is enabled,
+/// recreating the depth texture if the scale changed.
+pub fn update_render_scale(
+    gpu: &mut GPUInterface,
+    depth_texture: &mut texture::Texture,
+    frame_time: std::time::Duration,
+) {
+    if gpu.update_render_scale(frame_time) {
+        *depth_texture = texture::Texture::create_depth_texture(
+            &gpu.device,
+            gpu.render_size(),
+            gpu.settings.msaa_samples,
+            "depth_texture",
+        );
+    }
+}
+
 /// Gets the vertex normals corresponding to vertex_positions, using the average of
 /// the normals for all adjacent faces.
 pub fn get_normals(
     vertex_positions: &Vec<cgmath::Vector3<f32>>,
-    indices: &Vec<u16>,
+    indices: &Vec<u32>,
 ) -> Vec<cgmath::Vector3<f32>> {
     let mut normals = Vec::new();
     for _ in 0..vertex_positions.len() {
@@ -0,0 +1,154 @@
+use crate::gui::{ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::billiards::config::{Config, Scenario};
+use crate::simulation::billiards::Simulation;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::units;
+
+use egui::Slider;
+
+pub struct BilliardsUi {
+    sim_config: Config,
+    reset: bool,
+    disc_count: usize,
+    potted: usize,
+    at_rest: bool,
+}
+
+impl Ui for BilliardsUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            let previous_scenario = self.sim_config.scenario;
+            let mut scenario = previous_scenario;
+            egui::ComboBox::from_label(tr("Scenario"))
+                .selected_text(tr(scenario.name()))
+                .show_ui(ui, |ui| {
+                    for option in Scenario::ALL {
+                        ui.selectable_value(&mut scenario, option, tr(option.name()));
+                    }
+                });
+            if scenario != previous_scenario {
+                self.sim_config.set_scenario(scenario);
+            }
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.dt,
+                    BilliardsUi::SIMULATION_DT_MIN.as_secs_f32()
+                        ..=BilliardsUi::SIMULATION_DT_MAX.as_secs_f32(),
+                )
+                .text(tr("Simualtion dt (secs)")),
+            );
+
+            ui.separator();
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.rolling_friction,
+                    0.0..=BilliardsUi::ROLLING_FRICTION_MAX,
+                )
+                .text(units::label("Rolling Friction", units::ACCELERATION)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.spin_friction,
+                    0.0..=BilliardsUi::SPIN_FRICTION_MAX,
+                )
+                .text(units::label("Spin Friction", units::ANGULAR_ACCELERATION)),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.disc_restitution, 0.0..=1.0)
+                    .text(tr("Disc Restitution")),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.cushion_restitution, 0.0..=1.0)
+                    .text(tr("Cushion Restitution")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.disc_friction,
+                    0.0..=BilliardsUi::CONTACT_FRICTION_MAX,
+                )
+                .text(tr("Disc Friction")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.cushion_friction,
+                    0.0..=BilliardsUi::CONTACT_FRICTION_MAX,
+                )
+                .text(tr("Cushion Friction")),
+            );
+
+            ui.separator();
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.cue_strength,
+                    0.0..=BilliardsUi::CUE_STRENGTH_MAX,
+                )
+                .text(units::label("Cue Strength", units::IMPULSE_PER_LENGTH)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.english,
+                    -BilliardsUi::ENGLISH_MAX..=BilliardsUi::ENGLISH_MAX,
+                )
+                .text(tr("English")),
+            );
+            self.reset = ui.button(tr("Reset")).clicked();
+
+            ui.separator();
+            ui.label(tr_format(
+                "{} discs, {} potted",
+                &[&self.disc_count, &self.potted],
+            ));
+            if self.at_rest {
+                ui.label(tr("At rest"));
+            }
+        });
+    }
+}
+
+impl BilliardsUi {
+    const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(10);
+    const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(500);
+
+    const ROLLING_FRICTION_MAX: f32 = 2.0;
+    const SPIN_FRICTION_MAX: f32 = 20.0;
+    const CONTACT_FRICTION_MAX: f32 = 1.0;
+
+    const CUE_STRENGTH_MAX: f32 = 3.0;
+    // Strikes further off center miscue.
+    const ENGLISH_MAX: f32 = 0.5;
+
+    pub fn new() -> BilliardsUi {
+        BilliardsUi {
+            sim_config: Config::default(),
+            reset: false,
+            disc_count: 0,
+            potted: 0,
+            at_rest: true,
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        if self.reset {
+            simulation.reset();
+        }
+        self.disc_count = simulation.get_discs().len();
+        self.potted = simulation.get_potted();
+        self.at_rest = simulation.is_at_rest();
+    }
+}
+
+impl ConfigUi for BilliardsUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for BilliardsUi {
+    fn default() -> Self {
+        BilliardsUi::new()
+    }
+}
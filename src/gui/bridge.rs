@@ -0,0 +1,174 @@
+use crate::gui::{solver::SolverConvergence, ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::bridge::config::Config;
+use crate::simulation::bridge::Simulation;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::units;
+
+use egui::Slider;
+
+pub struct BridgeUi {
+    sim_config: Config,
+    reset: bool,
+    drop_load: bool,
+    load_count: usize,
+    broken_strands: usize,
+    max_tension: f32,
+    contact_solver: SolverConvergence,
+}
+
+impl Ui for BridgeUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.dt,
+                    BridgeUi::SIMULATION_DT_MIN.as_secs_f32()
+                        ..=BridgeUi::SIMULATION_DT_MAX.as_secs_f32(),
+                )
+                .text(tr("Simualtion dt (secs)")),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.gravity, 0.0..=BridgeUi::GRAVITY_MAX)
+                    .text(units::label("Gravity", units::ACCELERATION)),
+            );
+
+            ui.separator();
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.strand_stiffness,
+                    BridgeUi::STRAND_STIFFNESS_MIN..=BridgeUi::STRAND_STIFFNESS_MAX,
+                )
+                .text(units::label("Strand Stiffness", units::FORCE)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.strand_damping,
+                    0.0..=BridgeUi::STRAND_DAMPING_MAX,
+                )
+                .text(units::label("Strand Damping", units::IMPULSE)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.strand_slack,
+                    1.0..=BridgeUi::STRAND_SLACK_MAX,
+                )
+                .text(tr("Strand Slack")),
+            );
+            ui.checkbox(
+                &mut self.sim_config.breakable_strands,
+                tr("Breakable Strands"),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.break_tension,
+                    BridgeUi::BREAK_TENSION_MIN..=BridgeUi::BREAK_TENSION_MAX,
+                )
+                .text(units::label("Break Tension", units::FORCE)),
+            );
+
+            ui.separator();
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.load_mass,
+                    BridgeUi::LOAD_MASS_MIN..=BridgeUi::LOAD_MASS_MAX,
+                )
+                .text(units::label("Load Mass", units::MASS)),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.contact_restitution, 0.0..=1.0)
+                    .text(tr("Contact Restitution")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.contact_friction,
+                    0.0..=BridgeUi::CONTACT_FRICTION_MAX,
+                )
+                .text(tr("Contact Friction")),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.contact_position_correction, 0.0..=1.0)
+                    .text(tr("Position Correction")),
+            );
+            SolverConvergence::budget_controls(ui, &mut self.sim_config.contact_solver);
+            self.contact_solver.checkbox(ui);
+            ui.horizontal(|ui| {
+                self.drop_load = ui.button(tr("Drop Load")).clicked();
+                self.reset = ui.button(tr("Reset")).clicked();
+            });
+
+            ui.separator();
+            ui.label(tr_format(
+                "{} loads, {} broken strands",
+                &[&self.load_count, &self.broken_strands],
+            ));
+            ui.label(tr_format(
+                "Max tension: {} N",
+                &[&format!("{:.0}", self.max_tension)],
+            ));
+        });
+        self.contact_solver.window(ctx);
+    }
+}
+
+impl BridgeUi {
+    const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(1);
+    const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(100);
+
+    const GRAVITY_MAX: f32 = 20.0;
+
+    // Stiffer strands need a smaller dt to stay stable.
+    const STRAND_STIFFNESS_MIN: f32 = 500.0;
+    const STRAND_STIFFNESS_MAX: f32 = 8000.0;
+    const STRAND_DAMPING_MAX: f32 = 40.0;
+    const STRAND_SLACK_MAX: f32 = 1.5;
+    const BREAK_TENSION_MIN: f32 = 100.0;
+    const BREAK_TENSION_MAX: f32 = 5000.0;
+
+    const LOAD_MASS_MIN: f32 = 1.0;
+    const LOAD_MASS_MAX: f32 = 200.0;
+    const CONTACT_FRICTION_MAX: f32 = 1.0;
+
+    pub fn new() -> BridgeUi {
+        BridgeUi {
+            sim_config: Config::default(),
+            reset: false,
+            drop_load: false,
+            load_count: 0,
+            broken_strands: 0,
+            max_tension: 0.0,
+            contact_solver: SolverConvergence::new("Contact Solver"),
+        }
+    }
+
+    /// Loads dropped from the UI fall onto the middle of the bridge.
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        if self.reset {
+            simulation.reset();
+        }
+        if self.drop_load {
+            simulation
+                .drop_load(0.0, 0.0)
+                .expect("Loads have volume and mass");
+        }
+        self.load_count = simulation.get_loads().len();
+        self.broken_strands = simulation.get_broken_strands();
+        self.max_tension = simulation.get_max_tension();
+        self.contact_solver.update(simulation.get_contact_stats());
+    }
+}
+
+impl ConfigUi for BridgeUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for BridgeUi {
+    fn default() -> Self {
+        BridgeUi::new()
+    }
+}
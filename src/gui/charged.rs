@@ -0,0 +1,196 @@
+use crate::gui::{ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::charged::config::{Config, Scenario};
+use crate::simulation::charged::Simulation;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::state::Integration;
+use crate::simulation::units;
+
+use egui::Slider;
+
+pub struct ChargedUi {
+    sim_config: Config,
+    particle_count: usize,
+}
+
+impl Ui for ChargedUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            let previous_scenario = self.sim_config.scenario;
+            let mut scenario = previous_scenario;
+            egui::ComboBox::from_label(tr("Scenario"))
+                .selected_text(tr(scenario.name()))
+                .show_ui(ui, |ui| {
+                    for option in Scenario::ALL {
+                        ui.selectable_value(&mut scenario, option, tr(option.name()));
+                    }
+                });
+            if scenario != previous_scenario {
+                self.sim_config.set_scenario(scenario);
+            }
+            egui::ComboBox::from_label(tr("Integration"))
+                .selected_text(format!("{:?}", self.sim_config.integration))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Rk4,
+                        tr("RK4"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Euler,
+                        tr("Euler"),
+                    );
+                });
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.dt,
+                    ChargedUi::SIMULATION_DT_MIN.as_secs_f32()
+                        ..=ChargedUi::SIMULATION_DT_MAX.as_secs_f32(),
+                )
+                .text(tr("Simualtion dt (secs)")),
+            );
+
+            ui.separator();
+            let magnetic = &mut self.sim_config.magnetic;
+            for (value, name) in [
+                (&mut magnetic.uniform.x, "Magnetic Field X"),
+                (&mut magnetic.uniform.y, "Magnetic Field Y"),
+                (&mut magnetic.uniform.z, "Magnetic Field Z"),
+            ] {
+                ui.add(
+                    Slider::new(value, -ChargedUi::FIELD_MAX..=ChargedUi::FIELD_MAX)
+                        .text(units::label(name, units::MAGNETIC_FIELD)),
+                );
+            }
+            ui.add(
+                Slider::new(
+                    &mut magnetic.dipole_moment.y,
+                    -ChargedUi::DIPOLE_MAX..=ChargedUi::DIPOLE_MAX,
+                )
+                .text(tr("Magnetic Dipole")),
+            );
+            let electric = &mut self.sim_config.electric;
+            for (value, name) in [
+                (&mut electric.uniform.x, "Electric Field X"),
+                (&mut electric.uniform.y, "Electric Field Y"),
+                (&mut electric.uniform.z, "Electric Field Z"),
+            ] {
+                ui.add(
+                    Slider::new(value, -ChargedUi::FIELD_MAX..=ChargedUi::FIELD_MAX)
+                        .text(units::label(name, units::ELECTRIC_FIELD)),
+                );
+            }
+            ui.add(
+                Slider::new(
+                    &mut electric.dipole_moment.y,
+                    -ChargedUi::DIPOLE_MAX..=ChargedUi::DIPOLE_MAX,
+                )
+                .text(tr("Electric Dipole")),
+            );
+
+            ui.separator();
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.charge,
+                    -ChargedUi::CHARGE_MAX..=ChargedUi::CHARGE_MAX,
+                )
+                .text(units::label("Charge", units::CHARGE)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.mass,
+                    ChargedUi::MASS_MIN..=ChargedUi::MASS_MAX,
+                )
+                .text(units::label("Mass", units::MASS)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.emitter_speed,
+                    ChargedUi::EMITTER_SPEED_MIN..=ChargedUi::EMITTER_SPEED_MAX,
+                )
+                .text(units::label("Emitter Speed", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.emitter_spread,
+                    ChargedUi::EMITTER_SPREAD_MIN..=ChargedUi::EMITTER_SPREAD_MAX,
+                )
+                .text(units::label("Emitter Spread", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.emission_rate,
+                    ChargedUi::EMISSION_RATE_MIN..=ChargedUi::EMISSION_RATE_MAX,
+                )
+                .text(tr("Emission Rate (1/s)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.lifetime,
+                    ChargedUi::LIFETIME_MIN..=ChargedUi::LIFETIME_MAX,
+                )
+                .text(units::label("Lifetime", units::TIME)),
+            );
+
+            ui.separator();
+            ui.label(tr_format("{} particles", &[&self.particle_count]));
+        });
+    }
+}
+
+impl ChargedUi {
+    const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(50);
+    const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_millis(1);
+
+    const FIELD_MAX: f32 = 5.0;
+    const DIPOLE_MAX: f32 = 100.0;
+
+    const CHARGE_MAX: f32 = 2.0;
+
+    const MASS_MIN: f32 = 0.1;
+    const MASS_MAX: f32 = 5.0;
+
+    const EMITTER_SPEED_MIN: f32 = 0.0;
+    const EMITTER_SPEED_MAX: f32 = 5.0;
+
+    const EMITTER_SPREAD_MIN: f32 = 0.0;
+    const EMITTER_SPREAD_MAX: f32 = 1.0;
+
+    const EMISSION_RATE_MIN: f32 = 0.0;
+    const EMISSION_RATE_MAX: f32 = 500.0;
+
+    const LIFETIME_MIN: f32 = 1.0;
+    const LIFETIME_MAX: f32 = 30.0;
+
+    pub fn new() -> ChargedUi {
+        ChargedUi {
+            sim_config: Config::default(),
+            particle_count: 0,
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        self.set_particle_count(simulation.get_particles().len());
+    }
+
+    /// Sets the number of particles in the simulation, for display.
+    pub fn set_particle_count(&mut self, particle_count: usize) {
+        self.particle_count = particle_count;
+    }
+}
+
+impl ConfigUi for ChargedUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for ChargedUi {
+    fn default() -> Self {
+        ChargedUi::new()
+    }
+}
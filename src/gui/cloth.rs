@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use crate::graphics::gizmo::GizmoMode;
+use crate::gui::{
+    self,
+    spring_mass_damper::SpringMassDamperUi,
+    undo::{self, EditTracker, UndoAction, UndoStack},
+    ConfigUi, Ui,
+};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::springy::config::Config;
+use crate::simulation::springy::scenario::{
+    Placeable, Preset, Scenario, Scene, SAVED_SCENARIO_FILE,
+};
+
+/// What the cloth's edits change, restored together as they're undone and redone.
+#[derive(Clone, PartialEq)]
+struct ClothSnapshot {
+    preset: Option<Preset>,
+    scenario: Scenario,
+    config: Config,
+}
+
+/// An edit of the scenario or the config, by the GUI or the gizmo.
+struct ClothEdit {
+    before: ClothSnapshot,
+    after: ClothSnapshot,
+}
+
+/// The spring-mass-damper controls, and a window choosing and editing the cloth's scenario, whose objects
+/// can also be arranged with a gizmo and saved back to its file. Edits of either can be undone.
+pub struct ClothUi {
+    springy: SpringMassDamperUi,
+    /// The preset the scenario was chosen from, if it wasn't opened from a file.
+    preset: Option<Preset>,
+    scenario: Scenario,
+    rebuild: bool,
+    /// The file the scenario was opened from, which it's saved back to.
+    path: Option<PathBuf>,
+    /// Where the scenario was last saved to, or why it couldn't be.
+    saved: Option<Result<PathBuf, &'static str>>,
+    selected: Option<Placeable>,
+    gizmo_mode: GizmoMode,
+    history: UndoStack<ClothEdit>,
+    tracker: EditTracker<ClothSnapshot>,
+    /// Whether a gizmo's being dragged, so that its edits are coalesced.
+    arranging: bool,
+}
+
+impl Ui for ClothUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        self.springy.ui(ctx);
+        let mut action = None;
+        egui::Window::new(tr("Scenario")).show(ctx, |ui| {
+            action = undo::controls(ui, self.history.can_undo(), self.history.can_redo());
+            let previous_preset = self.preset;
+            egui::ComboBox::from_label(tr("Preset"))
+                .selected_text(self.preset.map_or(tr("Custom"), |preset| tr(preset.name())))
+                .show_ui(ui, |ui| {
+                    for option in Preset::ALL {
+                        ui.selectable_value(&mut self.preset, Some(option), tr(option.name()));
+                    }
+                });
+            if self.preset != previous_preset {
+                if let Some(preset) = self.preset {
+                    self.scenario = preset.get_scenario();
+                    self.rebuild = true;
+                }
+            }
+            gui::reflect_panel(ui, &mut self.scenario);
+            if ui.button(tr("Rebuild")).clicked() {
+                self.rebuild = true;
+            }
+            ui.separator();
+            self.gizmo_controls(ui);
+            ui.separator();
+            if ui.button(tr("Save Scenario")).clicked() {
+                self.save();
+            }
+            match &self.saved {
+                Some(Ok(path)) => {
+                    ui.label(tr_format("Saved to {}", &[&path.display()]));
+                }
+                Some(Err(message)) => {
+                    ui.label(tr(message));
+                }
+                None => {}
+            }
+        });
+        match action {
+            Some(action) => self.apply(action),
+            None => {
+                let snapshot = self.snapshot();
+                let editing = self.arranging || ctx.is_using_pointer();
+                if let Some(before) = self.tracker.update(&snapshot, editing) {
+                    self.history.push(ClothEdit {
+                        before,
+                        after: snapshot,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl ClothUi {
+    /// The path is the file the scenario was opened from, if it was.
+    pub fn new(preset: Option<Preset>, scenario: Scenario, path: Option<&Path>) -> ClothUi {
+        let mut springy = SpringMassDamperUi::new();
+        springy.set_wind(scenario.wind);
+        let tracker = EditTracker::new(&ClothSnapshot {
+            preset,
+            scenario: scenario.clone(),
+            config: springy.get_config().clone(),
+        });
+        ClothUi {
+            springy,
+            preset,
+            scenario,
+            rebuild: false,
+            path: path.map(Path::to_path_buf),
+            saved: None,
+            selected: None,
+            gizmo_mode: GizmoMode::Translate,
+            history: UndoStack::new(),
+            tracker,
+            arranging: false,
+        }
+    }
+
+    fn snapshot(&self) -> ClothSnapshot {
+        ClothSnapshot {
+            preset: self.preset,
+            scenario: self.scenario.clone(),
+            config: self.springy.get_config().clone(),
+        }
+    }
+
+    /// Undoes or redoes an edit, rebuilding the scene if it changes the scenario.
+    fn apply(&mut self, action: UndoAction) {
+        let snapshot = match action {
+            UndoAction::Undo => self.history.undo().map(|edit| edit.before.clone()),
+            UndoAction::Redo => self.history.redo().map(|edit| edit.after.clone()),
+        };
+        let Some(snapshot) = snapshot else {
+            return;
+        };
+        if snapshot.scenario != self.scenario {
+            self.rebuild = true;
+        }
+        self.preset = snapshot.preset;
+        self.scenario.clone_from(&snapshot.scenario);
+        self.springy.set_config(&snapshot.config);
+        self.tracker.reset(&snapshot);
+    }
+
+    fn gizmo_controls(&mut self, ui: &mut egui::Ui) {
+        let placeables = self.scenario.get_placeables();
+        if self
+            .selected
+            .is_some_and(|selected| !placeables.contains(&selected))
+        {
+            self.selected = None;
+        }
+        egui::ComboBox::from_label(tr("Gizmo"))
+            .selected_text(
+                self.selected
+                    .map_or(tr("None"), |selected| tr(selected.name())),
+            )
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.selected, None, tr("None"));
+                for placeable in placeables {
+                    ui.selectable_value(&mut self.selected, Some(placeable), tr(placeable.name()));
+                }
+            });
+        ui.horizontal(|ui| {
+            for mode in GizmoMode::ALL {
+                ui.selectable_value(&mut self.gizmo_mode, mode, tr(mode.name()));
+            }
+        });
+    }
+
+    /// Saves the scenario back to the file it was opened from, or to SAVED_SCENARIO_FILE if it wasn't.
+    fn save(&mut self) {
+        let path = self
+            .path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(SAVED_SCENARIO_FILE));
+        self.saved = Some(
+            std::fs::write(&path, self.scenario.to_file_contents())
+                .map(|_| path)
+                .map_err(|_| "Couldn't save the scenario!"),
+        );
+    }
+
+    pub fn get_scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    /// For arranging the scenario's objects with the gizmo. The scene isn't rebuilt from it until it's asked to be.
+    pub fn get_scenario_mut(&mut self) -> &mut Scenario {
+        // An arranged scenario is no longer its preset.
+        self.preset = None;
+        &mut self.scenario
+    }
+
+    pub fn request_rebuild(&mut self) {
+        self.rebuild = true;
+    }
+
+    /// The placeable the gizmo's placed on, if any.
+    pub fn get_selected(&self) -> Option<Placeable> {
+        self.selected
+    }
+
+    pub fn select(&mut self, placeable: Option<Placeable>) {
+        self.selected = placeable;
+    }
+
+    pub fn get_gizmo_mode(&self) -> GizmoMode {
+        self.gizmo_mode
+    }
+
+    /// Whether a gizmo's being dragged. The scenario's arranged by it until it's let go are one edit.
+    pub fn set_arranging(&mut self, arranging: bool) {
+        self.arranging = arranging;
+    }
+
+    /// Syncs the scene's cloth config with this UI's, rebuilding the scene from the scenario if it was chosen or
+    /// rebuilt this frame, with the scenario's wind. Returns whether the scene was rebuilt.
+    pub fn sync_scene(&mut self, scene: &mut Scene) -> bool {
+        let mut rebuilt = false;
+        if std::mem::take(&mut self.rebuild) {
+            if let Ok(new_scene) = Scene::new(self.scenario.clone()) {
+                *scene = new_scene;
+                self.springy.set_wind(self.scenario.wind);
+                // Taking the scenario's wind is part of the edit which rebuilt the scene, not one of its own.
+                self.tracker.reset(&self.snapshot());
+                rebuilt = true;
+            }
+        }
+        scene.sync_config(self.get_config());
+        self.springy
+            .set_adapted_dt(scene.get_cloth().get_config().dt);
+        // The floor is the first obstacle.
+        scene
+            .get_cloth_mut()
+            .set_obstacle_surface_velocity(0, self.springy.get_floor_surface_velocity());
+        rebuilt
+    }
+}
+
+impl ConfigUi for ClothUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        self.springy.get_config()
+    }
+}
+
+impl Default for ClothUi {
+    fn default() -> Self {
+        ClothUi::new(Some(Preset::Hanging), Preset::Hanging.get_scenario(), None)
+    }
+}
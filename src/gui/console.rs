@@ -0,0 +1,79 @@
+use crate::localization::tr;
+
+/// A window for typing script statements, showing their output, while its checkbox is checked.
+pub struct Console {
+    show: bool,
+    input: String,
+    output: Vec<String>,
+    submitted: Vec<String>,
+}
+
+impl Console {
+    /// Older output is dropped beyond this many lines.
+    const MAX_OUTPUT_LINES: usize = 200;
+    const OUTPUT_HEIGHT: f32 = 200.0;
+
+    pub fn new() -> Console {
+        Console {
+            show: false,
+            input: String::new(),
+            output: Vec::new(),
+            submitted: Vec::new(),
+        }
+    }
+
+    pub fn checkbox(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show, tr("Console"));
+    }
+
+    /// The lines the user has entered since this was last called, in order.
+    pub fn take_submitted(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.submitted)
+    }
+
+    /// Shows the output of a line, or an error, below the lines before it.
+    pub fn print(&mut self, text: &str) {
+        self.output.extend(text.lines().map(str::to_owned));
+        if self.output.len() > Console::MAX_OUTPUT_LINES {
+            let excess = self.output.len() - Console::MAX_OUTPUT_LINES;
+            self.output.drain(..excess);
+        }
+    }
+
+    pub fn window(&mut self, ctx: &egui::Context) {
+        let input = &mut self.input;
+        let output = &self.output;
+        let submitted = &mut self.submitted;
+        egui::Window::new(tr("Console"))
+            .open(&mut self.show)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(Console::OUTPUT_HEIGHT)
+                    .stick_to_bottom()
+                    .show(ui, |ui| {
+                        for line in output.iter() {
+                            ui.monospace(line);
+                        }
+                    });
+                ui.separator();
+                let response = ui.add(
+                    egui::TextEdit::singleline(input)
+                        .hint_text(tr("Type help for the commands"))
+                        .desired_width(f32::INFINITY),
+                );
+                // Enter submits the line, and keeps the focus for the next one.
+                if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                    if !input.trim().is_empty() {
+                        submitted.push(std::mem::take(input));
+                    }
+                    response.request_focus();
+                }
+            });
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Console::new()
+    }
+}
@@ -1,35 +1,42 @@
-use crate::gui::Ui;
-use crate::simulation::flocking::flocking;
+use crate::gui::{self, ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::flocking::{flocking, limits::StaminaConfig};
+use crate::simulation::planar::{PlanarAxis, PlanarConstraint};
+use crate::simulation::units;
 use egui::{Checkbox, Slider};
 
 pub struct FlockingUi {
     sim_config: flocking::Config,
+    goal_mode: bool,
+    clear_goals: bool,
+    goals_queued: usize,
 }
 
 impl Ui for FlockingUi {
     fn ui(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Config").show(&ctx, |ui| {
+        egui::Window::new(tr("Config")).show(&ctx, |ui| {
             ui.add(
                 Slider::new(
                     &mut self.sim_config.dt,
                     FlockingUi::SIMULATION_DT_MIN.as_secs_f32()
                         ..=FlockingUi::SIMULATION_DT_MAX.as_secs_f32(),
                 )
-                .text("Simualtion dt (secs)"),
+                .text(tr("Simualtion dt (secs)")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.avoidance_factor,
                     FlockingUi::AVOIDANCE_FACTOR_MIN..=FlockingUi::AVOIDANCE_FACTOR_MAX,
                 )
-                .text("Avoidance Factor"),
+                .text(tr("Avoidance Factor")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.centering_factor,
                     FlockingUi::CENTERING_FACTOR_MIN..=FlockingUi::CENTERING_FACTOR_MAX,
                 )
-                .text("Centering Factor"),
+                .text(tr("Centering Factor")),
             );
             ui.add(
                 Slider::new(
@@ -37,7 +44,7 @@ impl Ui for FlockingUi {
                     FlockingUi::VELOCITY_MATCHING_FACTOR_MIN
                         ..=FlockingUi::VELOCITY_MATHCING_FACTOR_MAX,
                 )
-                .text("Velocity Matching Factor"),
+                .text(tr("Velocity Matching Factor")),
             );
             ui.add(
                 Slider::new(
@@ -45,7 +52,7 @@ impl Ui for FlockingUi {
                     FlockingUi::DISTANCE_WEIGHT_THRESHOLD_MIN
                         ..=FlockingUi::DISTANCE_WEIGHT_THRESHOLD_MAX,
                 )
-                .text("Distance Weight Threshold"),
+                .text(tr("Distance Weight Threshold")),
             );
             ui.add(
                 Slider::new(
@@ -53,26 +60,74 @@ impl Ui for FlockingUi {
                     FlockingUi::DISTANCE_WEIGHT_THRESHOLD_FALLOFF_MIN
                         ..=FlockingUi::DISTANCE_WEIGHT_THRESHOLD_FALLOFF_MAX,
                 )
-                .text("Distance Weight Threshold Falloff"),
+                .text(tr("Distance Weight Threshold Falloff")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.max_sight_angle,
                     FlockingUi::MAX_SIGHT_ANGLE_MIN..=FlockingUi::MAX_SIGHT_ANGLE_MAX,
                 )
-                .text("Max Sight Angle"),
+                .text(tr("Max Sight Angle")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.max_sight_angle_to_lead_boid,
                     FlockingUi::MAX_SIGHT_ANGLE_MIN..=FlockingUi::MAX_SIGHT_ANGLE_MAX,
                 )
-                .text("Max Sight Angle to Lead Boid"),
+                .text(tr("Max Sight Angle to Lead Boid")),
             );
             ui.add(Checkbox::new(
                 &mut self.sim_config.steering_overrides,
-                "Steering Overrides",
+                tr("Steering Overrides"),
             ));
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.max_speed,
+                    FlockingUi::MAX_SPEED_MIN..=FlockingUi::MAX_SPEED_MAX,
+                )
+                .text(units::label("Max Speed", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.max_acceleration,
+                    FlockingUi::MAX_ACCELERATION_MIN..=FlockingUi::MAX_ACCELERATION_MAX,
+                )
+                .text(units::label("Max Acceleration", units::ACCELERATION)),
+            );
+            self.stamina_controls(ui);
+            gui::planar_controls(ui, &mut self.sim_config.planar, FlockingUi::PLANAR_DEFAULT);
+            ui.separator();
+            ui.checkbox(&mut self.goal_mode, tr("Click to Set Goals"));
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.goal_factor,
+                    FlockingUi::GOAL_FACTOR_MIN..=FlockingUi::GOAL_FACTOR_MAX,
+                )
+                .text(tr("Goal Factor")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.goal_speed,
+                    FlockingUi::GOAL_SPEED_MIN..=FlockingUi::GOAL_SPEED_MAX,
+                )
+                .text(units::label("Goal Speed", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.goal_slowing_radius,
+                    FlockingUi::GOAL_RADIUS_MIN..=FlockingUi::GOAL_RADIUS_MAX,
+                )
+                .text(units::label("Slowing Radius", units::LENGTH)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.goal_reached_radius,
+                    FlockingUi::GOAL_RADIUS_MIN..=FlockingUi::GOAL_RADIUS_MAX,
+                )
+                .text(units::label("Reached Radius", units::LENGTH)),
+            );
+            ui.label(tr_format("{} goals queued", &[&self.goals_queued]));
+            self.clear_goals = ui.button(tr("Clear Goals")).clicked();
         });
     }
 }
@@ -99,13 +154,105 @@ impl FlockingUi {
     const MAX_SIGHT_ANGLE_MIN: f32 = 0.0;
     const MAX_SIGHT_ANGLE_MAX: f32 = std::f32::consts::PI;
 
+    const MAX_SPEED_MIN: f32 = 0.1;
+    const MAX_SPEED_MAX: f32 = 30.0;
+
+    const MAX_ACCELERATION_MIN: f32 = 0.1;
+    const MAX_ACCELERATION_MAX: f32 = 50.0;
+
+    const FRACTION_MIN: f32 = 0.0;
+    const FRACTION_MAX: f32 = 1.0;
+
+    const STAMINA_RATE_MIN: f32 = 0.0;
+    const STAMINA_RATE_MAX: f32 = 1.0;
+
+    const GOAL_FACTOR_MIN: f32 = 0.0;
+    const GOAL_FACTOR_MAX: f32 = 10.0;
+
+    const GOAL_SPEED_MIN: f32 = 0.0;
+    const GOAL_SPEED_MAX: f32 = 20.0;
+
+    const GOAL_RADIUS_MIN: f32 = 0.0;
+    const GOAL_RADIUS_MAX: f32 = 20.0;
+
+    /// Level with the goals, so the flock can reach them, for viewing from above.
+    const PLANAR_DEFAULT: PlanarConstraint = PlanarConstraint {
+        axis: PlanarAxis::Y,
+        offset: 1.0,
+    };
+
     pub fn new() -> FlockingUi {
         FlockingUi {
             sim_config: flocking::Config::default(),
+            goal_mode: false,
+            clear_goals: false,
+            goals_queued: 0,
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut flocking::Simulation) {
+        simulation.sync_config(self.get_config());
+        if self.clear_goals {
+            simulation.clear_goals();
+        }
+        self.goals_queued = simulation.get_goals().len();
+    }
+
+    /// Shows a checkbox tiring the boids as they fly fast, and the stamina's controls while it's checked.
+    fn stamina_controls(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.sim_config.stamina.is_some();
+        if ui.checkbox(&mut enabled, tr("Stamina")).changed() {
+            self.sim_config.stamina = enabled.then(StaminaConfig::default);
         }
+        let Some(stamina) = &mut self.sim_config.stamina else {
+            return;
+        };
+        ui.add(
+            Slider::new(
+                &mut stamina.cruise_fraction,
+                FlockingUi::FRACTION_MIN..=FlockingUi::FRACTION_MAX,
+            )
+            .text(tr("Cruise Fraction")),
+        );
+        ui.add(
+            Slider::new(
+                &mut stamina.drain_rate,
+                FlockingUi::STAMINA_RATE_MIN..=FlockingUi::STAMINA_RATE_MAX,
+            )
+            .text(tr("Drain Rate (1/s)")),
+        );
+        ui.add(
+            Slider::new(
+                &mut stamina.recovery_rate,
+                FlockingUi::STAMINA_RATE_MIN..=FlockingUi::STAMINA_RATE_MAX,
+            )
+            .text(tr("Recovery Rate (1/s)")),
+        );
+        ui.add(
+            Slider::new(
+                &mut stamina.exhausted_fraction,
+                FlockingUi::FRACTION_MIN..=FlockingUi::FRACTION_MAX,
+            )
+            .text(tr("Exhausted Fraction")),
+        );
     }
 
-    pub fn get_gui_state_mut(&mut self) -> &flocking::Config {
+    /// Whether clicking places goals for the flocks to seek.
+    pub fn get_goal_mode(&self) -> bool {
+        self.goal_mode
+    }
+}
+
+impl ConfigUi for FlockingUi {
+    type Config = flocking::Config;
+
+    fn get_config(&self) -> &flocking::Config {
         &self.sim_config
     }
 }
+
+impl Default for FlockingUi {
+    fn default() -> Self {
+        FlockingUi::new()
+    }
+}
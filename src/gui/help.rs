@@ -0,0 +1,61 @@
+/// An overlay describing a demo's simulation, its parameters, and its controls.
+use winit::event::VirtualKeyCode;
+
+use crate::localization::{tr, tr_format};
+
+/// An input a demo responds to, e.g. a key or mouse button, and what it does.
+#[derive(Debug, Copy, Clone)]
+pub struct InputAction {
+    pub input: &'static str,
+    pub description: &'static str,
+}
+
+/// A parameter in a demo's config UI, and its effect on the simulation.
+#[derive(Debug, Copy, Clone)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub struct Help {
+    pub title: &'static str,
+    pub description: &'static str,
+    pub parameters: &'static [Parameter],
+    pub input_actions: Vec<InputAction>,
+}
+
+impl Help {
+    pub const TOGGLE_KEY: VirtualKeyCode = VirtualKeyCode::F1;
+
+    pub fn ui(&self, ctx: &egui::Context, open: &mut bool) {
+        egui::Window::new(tr_format("Help: {}", &[&tr(self.title)]))
+            .open(open)
+            .show(ctx, |ui| {
+                ui.label(tr(self.description));
+                ui.separator();
+                ui.heading(tr("Controls"));
+                egui::Grid::new("help controls")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in self.input_actions.iter() {
+                            ui.strong(tr(action.input));
+                            ui.label(tr(action.description));
+                            ui.end_row();
+                        }
+                    });
+                if !self.parameters.is_empty() {
+                    ui.separator();
+                    ui.heading(tr("Parameters"));
+                    egui::Grid::new("help parameters")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for parameter in self.parameters {
+                                ui.strong(tr(parameter.name));
+                                ui.label(tr(parameter.description));
+                                ui.end_row();
+                            }
+                        });
+                }
+            });
+    }
+}
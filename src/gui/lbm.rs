@@ -0,0 +1,54 @@
+use crate::gui::{self, ConfigUi, Ui};
+use crate::localization::tr;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::lbm::config::Config;
+use crate::simulation::lbm::Simulation;
+
+pub struct LbmUi {
+    sim_config: Config,
+    reset: bool,
+}
+
+impl Ui for LbmUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            gui::reflect_panel(ui, &mut self.sim_config);
+            self.reset = ui.button(tr("Reset")).clicked();
+        });
+    }
+}
+
+impl LbmUi {
+    pub fn new() -> LbmUi {
+        LbmUi {
+            sim_config: Config::default(),
+            reset: false,
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        if self.get_reset() {
+            simulation.reset();
+        }
+    }
+
+    /// Whether the flow should be reset, if the reset button was clicked this frame.
+    pub fn get_reset(&self) -> bool {
+        self.reset
+    }
+}
+
+impl ConfigUi for LbmUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for LbmUi {
+    fn default() -> Self {
+        LbmUi::new()
+    }
+}
@@ -0,0 +1,130 @@
+use crate::localization::{tr, tr_format};
+use crate::simulation::measure::{MeasureTool, Measurement, Stopwatch};
+use crate::simulation::units;
+
+use egui::Slider;
+use std::ops::RangeInclusive;
+
+/// A window of measuring tools, shown while its checkbox is checked: a ruler and an angle between points the
+/// user places in the world, and a stopwatch timing objects between two gates the user moves along an axis.
+/// The demo places the points the user picks, and syncs the stopwatch.
+pub struct MeasureTools {
+    show: bool,
+    measurement: Measurement,
+    start_gate: f32,
+    stop_gate: f32,
+    gate_range: RangeInclusive<f32>,
+    clear_laps: bool,
+    laps: Vec<f32>,
+    running: usize,
+}
+
+impl MeasureTools {
+    /// The gates start at the positions, and can be moved within the range.
+    pub fn new(start_gate: f32, stop_gate: f32, gate_range: RangeInclusive<f32>) -> MeasureTools {
+        MeasureTools {
+            show: false,
+            measurement: Measurement::new(MeasureTool::Ruler),
+            start_gate,
+            stop_gate,
+            gate_range,
+            clear_laps: false,
+            laps: Vec::new(),
+            running: 0,
+        }
+    }
+
+    pub fn checkbox(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show, tr("Measure"));
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.show
+    }
+
+    /// Places a point of the measurement, e.g. where the user clicked, if the window's shown.
+    pub fn place(&mut self, point: cgmath::Vector3<f32>) {
+        if self.show {
+            self.measurement.place(point);
+        }
+    }
+
+    pub fn get_measurement(&self) -> &Measurement {
+        &self.measurement
+    }
+
+    /// The positions of the start and stop gates along their axis.
+    pub fn get_gates(&self) -> (f32, f32) {
+        (self.start_gate, self.stop_gate)
+    }
+
+    /// True if the user asked to clear the stopwatch's laps this frame.
+    pub fn take_clear_laps(&mut self) -> bool {
+        std::mem::take(&mut self.clear_laps)
+    }
+
+    /// Copies the stopwatch's laps for display while the window is shown.
+    pub fn update(&mut self, stopwatch: &Stopwatch) {
+        if self.show {
+            self.laps.clear();
+            self.laps.extend_from_slice(stopwatch.get_laps());
+            self.running = stopwatch.get_running_count();
+        }
+    }
+
+    pub fn window(&mut self, ctx: &egui::Context) {
+        let (measurement, laps, running) = (&mut self.measurement, &self.laps, self.running);
+        let (start_gate, stop_gate) = (&mut self.start_gate, &mut self.stop_gate);
+        let (gate_range, clear_laps) = (&self.gate_range, &mut self.clear_laps);
+        egui::Window::new(tr("Measure"))
+            .open(&mut self.show)
+            .show(ctx, |ui| {
+                let mut tool = measurement.get_tool();
+                egui::ComboBox::from_label(tr("Tool"))
+                    .selected_text(tr(tool.name()))
+                    .show_ui(ui, |ui| {
+                        for option in MeasureTool::ALL {
+                            ui.selectable_value(&mut tool, option, tr(option.name()));
+                        }
+                    });
+                if tool != measurement.get_tool() {
+                    measurement.set_tool(tool);
+                }
+                if let Some(distance) = measurement.distance() {
+                    ui.label(tr_format("Distance: {} m", &[&format!("{:.3}", distance)]));
+                } else if let Some(angle) = measurement.angle() {
+                    ui.label(tr_format(
+                        "Angle: {}°",
+                        &[&format!("{:.1}", cgmath::Deg::from(angle).0)],
+                    ));
+                } else if !measurement.is_complete() {
+                    ui.label(tr_format(
+                        "Middle click to place {} more points",
+                        &[&(tool.point_count() - measurement.get_points().len())],
+                    ));
+                }
+                if ui.button(tr("Clear Points")).clicked() {
+                    measurement.clear();
+                }
+
+                ui.separator();
+                ui.label(tr("Stopwatch"));
+                ui.add(
+                    Slider::new(start_gate, gate_range.clone())
+                        .text(units::label("Start Gate", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(stop_gate, gate_range.clone())
+                        .text(units::label("Stop Gate", units::LENGTH)),
+                );
+                for (i, lap) in laps.iter().enumerate().rev() {
+                    ui.label(tr_format(
+                        "Lap {}: {} s",
+                        &[&(i + 1), &format!("{:.3}", lap)],
+                    ));
+                }
+                ui.label(tr_format("{} running", &[&running]));
+                *clear_laps = ui.button(tr("Clear Laps")).clicked();
+            });
+    }
+}
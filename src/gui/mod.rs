@@ -1,30 +1,352 @@
+pub mod billiards;
 pub mod bounce;
+pub mod bridge;
+pub mod charged;
+pub mod cloth;
+pub mod console;
 pub mod flocking;
+pub mod help;
+pub mod lbm;
+pub mod measure;
 pub mod particles;
 pub mod rigidbody;
+pub mod slosh;
+pub mod solver;
+pub mod spectrum;
 pub mod sph;
 pub mod spring_mass_damper;
+pub mod target_range;
+pub mod undo;
+pub mod vortex;
+pub mod wind_tunnel;
 
-use egui::FontDefinitions;
+use crate::graphics::camera_path::{CameraPathCommand, CameraPathMode, CameraPathStatus};
+use crate::graphics::palette::Theme;
+use crate::graphics::settings::{
+    AmbientOcclusionQuality, BackgroundStepping, GraphicsSettings, TransparencyMode, WindowMode,
+};
+use crate::localization::{tr, tr_format};
+use crate::simulation::collision_filter::CollisionFilter;
+use crate::simulation::container::{Container, ContainerShape};
+use crate::simulation::fan::FanConfig;
+use crate::simulation::planar::{PlanarAxis, PlanarConstraint};
+use crate::simulation::reflect::{Field, Reflect, Value};
+use crate::simulation::state::{AdaptiveTimestep, Integration};
+use crate::simulation::units;
+use crate::simulation::units::GravityPreset;
+
+use cgmath::Vector3;
+use egui::{FontDefinitions, Slider};
 use egui_wgpu_backend::{RenderPass, ScreenDescriptor};
 use egui_winit_platform::{Platform, PlatformDescriptor};
-use winit::{event::Event, window::Window};
+use help::Help;
+use winit::{
+    event::{ElementState, Event, KeyboardInput, WindowEvent},
+    window::Window,
+};
 
 pub trait Ui {
     fn ui(&mut self, ctx: &egui::Context);
 }
 
+/// A GUI editing a simulation's config, which the simulation syncs from with Configurable::sync_config.
+/// Simulations don't know about their GUIs, so any GUI producing the same config can drive them.
+/// Each demo's GUI syncs its simulation once a frame with its sync_simulation(), which syncs the simulation's
+/// config with the GUI's, and the GUI's readouts, e.g. energies and timesteps, with the simulation.
+pub trait ConfigUi {
+    type Config;
+
+    fn get_config(&self) -> &Self::Config;
+}
+
+/// Shows an editor for each of the config's fields, generated from their declarations: sliders for numbers within
+/// ranges, a slider per component of vectors, and checkboxes for bools. Fields in a group are shown under a
+/// collapsing header for it, after the ungrouped fields, with groups in the order their first fields are declared.
+pub fn reflect_panel(ui: &mut egui::Ui, config: &mut impl Reflect) {
+    let mut fields = config.fields();
+    let mut groups: Vec<&'static str> = Vec::new();
+    for group in fields.iter().filter_map(|field| field.group) {
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    for field in fields.iter_mut().filter(|field| field.group.is_none()) {
+        field_editor(ui, field);
+    }
+    for group in groups {
+        ui.collapsing(tr(group), |ui| {
+            for field in fields.iter_mut().filter(|field| field.group == Some(group)) {
+                field_editor(ui, field);
+            }
+        });
+    }
+}
+
+/// Shows the editor for a field of a reflected config.
+fn field_editor(ui: &mut egui::Ui, field: &mut Field) {
+    let label = |name: &str| match field.units {
+        Some(units) => units::label(name, units),
+        None => tr(name).to_owned(),
+    };
+    let range = field.range.clone();
+    match &mut field.value {
+        Value::F32(value) => {
+            let text = label(field.label);
+            match range {
+                Some(range) => ui.add(Slider::new(*value, range).text(text)),
+                None => ui.add(egui::DragValue::new(*value).prefix(format!("{}: ", text))),
+            };
+        }
+        Value::U32(value) => {
+            let text = label(field.label);
+            match range {
+                Some(range) => ui.add(
+                    Slider::new(*value, *range.start() as u32..=*range.end() as u32).text(text),
+                ),
+                None => ui.add(egui::DragValue::new(*value).prefix(format!("{}: ", text))),
+            };
+        }
+        Value::Bool(value) => {
+            ui.checkbox(value, label(field.label));
+        }
+        Value::Vector3(value) => {
+            let components = [
+                ("X", &mut value.x),
+                ("Y", &mut value.y),
+                ("Z", &mut value.z),
+            ];
+            for (axis, component) in components {
+                let text = label(&format!("{} {}", field.label, axis));
+                match &range {
+                    Some(range) => ui.add(Slider::new(component, range.clone()).text(text)),
+                    None => ui.add(egui::DragValue::new(component).prefix(format!("{}: ", text))),
+                };
+            }
+        }
+    }
+}
+
+/// Shows a row of buttons for each gravity preset, highlighting the preset matching the current gravity.
+/// Returns the preset the user selected this frame, if any.
+pub fn gravity_presets(ui: &mut egui::Ui, gravity: Vector3<f32>) -> Option<GravityPreset> {
+    let current = GravityPreset::from_vector(gravity);
+    let mut selected = None;
+    ui.horizontal(|ui| {
+        ui.label(tr("Gravity Preset"));
+        for preset in GravityPreset::ALL {
+            if ui
+                .selectable_label(current == Some(preset), tr(preset.name()))
+                .clicked()
+            {
+                selected = Some(preset);
+            }
+        }
+    });
+    selected
+}
+
+/// Shows a checkbox for each collision group, toggling whether the group is in the mask.
+pub fn collision_mask(ui: &mut egui::Ui, label: &str, mask: &mut u32) {
+    ui.horizontal(|ui| {
+        ui.label(tr(label));
+        for (group, name) in CollisionFilter::GROUPS {
+            let mut enabled = *mask & group != 0;
+            if ui.checkbox(&mut enabled, tr(name)).changed() {
+                *mask ^= group;
+            }
+        }
+    });
+}
+
+const ADAPTIVE_TOLERANCE_MIN: f32 = 1e-8;
+const ADAPTIVE_TOLERANCE_MAX: f32 = 1e-2;
+
+/// Shows a checkbox adapting dt to the integration's error estimates, starting from the default bounds, and
+/// sliders for the bounds within the range and the tolerance while it's checked, with the dt it's adapted to.
+/// Shows nothing for integrations which don't estimate their error.
+pub fn adaptive_dt_controls(
+    ui: &mut egui::Ui,
+    integration: Integration,
+    adaptive: &mut Option<AdaptiveTimestep>,
+    adapted_dt: f32,
+    dt_range: std::ops::RangeInclusive<f32>,
+) {
+    if !integration.estimates_error() {
+        return;
+    }
+    let mut enabled = adaptive.is_some();
+    if ui.checkbox(&mut enabled, tr("Adaptive dt")).changed() {
+        *adaptive = enabled.then(AdaptiveTimestep::default);
+    }
+    let Some(adaptive) = adaptive else {
+        return;
+    };
+    ui.add(
+        Slider::new(&mut adaptive.min_dt, dt_range.clone())
+            .logarithmic(true)
+            .text(units::label("Min dt", units::TIME)),
+    );
+    ui.add(
+        Slider::new(&mut adaptive.max_dt, dt_range)
+            .logarithmic(true)
+            .text(units::label("Max dt", units::TIME)),
+    );
+    adaptive.max_dt = adaptive.max_dt.max(adaptive.min_dt);
+    ui.add(
+        Slider::new(
+            &mut adaptive.tolerance,
+            ADAPTIVE_TOLERANCE_MIN..=ADAPTIVE_TOLERANCE_MAX,
+        )
+        .logarithmic(true)
+        .text(tr("Error Tolerance")),
+    );
+    ui.label(tr_format(
+        "Adapted dt: {} ms",
+        &[&format!("{:.3}", adapted_dt * 1e3)],
+    ));
+}
+
+/// Shows the container's shape, and sliders for its dimensions up to twice the half extent, which its walls
+/// move towards at the simulation's wall speed.
+pub fn container_controls(ui: &mut egui::Ui, container: &mut Container, max_half_extent: f32) {
+    let mut shape = container.get_shape();
+    egui::ComboBox::from_label(tr("Container"))
+        .selected_text(tr(shape.name()))
+        .show_ui(ui, |ui| {
+            for option in ContainerShape::ALL {
+                ui.selectable_value(&mut shape, option, tr(option.name()));
+            }
+        });
+    let mut half_extents = container.get_half_extents();
+    let dimensions: &[(usize, &str, f32)] = match shape {
+        ContainerShape::Box => &[(0, "Width", 2.0), (1, "Height", 2.0), (2, "Depth", 2.0)],
+        ContainerShape::Cylinder => &[(0, "Radius", 1.0), (1, "Height", 2.0)],
+        ContainerShape::Sphere => &[(0, "Radius", 1.0)],
+    };
+    for &(axis, name, scale) in dimensions {
+        let mut dimension = half_extents[axis] * scale;
+        ui.add(
+            Slider::new(
+                &mut dimension,
+                Container::MIN_HALF_EXTENT * scale..=max_half_extent * scale,
+            )
+            .text(units::label(name, units::LENGTH)),
+        );
+        half_extents[axis] = dimension / scale;
+    }
+    *container = Container::new(shape, half_extents);
+}
+
+const FAN_POSITION_MIN: f32 = -5.0;
+const FAN_POSITION_MAX: f32 = 5.0;
+const FAN_BLADE_SPEED_MAX: f32 = 30.0;
+const FAN_RADIUS_MIN: f32 = 0.1;
+const FAN_RADIUS_MAX: f32 = 2.0;
+
+/// Shows a checkbox adding the fan, starting from the default, and sliders placing and aiming it while it's added.
+pub fn fan_controls(ui: &mut egui::Ui, fan: &mut Option<FanConfig>, default: FanConfig) {
+    let mut enabled = fan.is_some();
+    if ui.checkbox(&mut enabled, tr("Fan")).changed() {
+        *fan = enabled.then_some(default);
+    }
+    if let Some(fan) = fan {
+        for (value, name) in [
+            (&mut fan.position.x, "Fan X"),
+            (&mut fan.position.y, "Fan Y"),
+            (&mut fan.position.z, "Fan Z"),
+        ] {
+            ui.add(
+                Slider::new(value, FAN_POSITION_MIN..=FAN_POSITION_MAX)
+                    .text(units::label(name, units::LENGTH)),
+            );
+        }
+        ui.add(
+            Slider::new(&mut fan.yaw, -std::f32::consts::PI..=std::f32::consts::PI)
+                .text(units::label("Fan Yaw", units::ANGLE)),
+        );
+        ui.add(
+            Slider::new(
+                &mut fan.pitch,
+                -std::f32::consts::FRAC_PI_2..=std::f32::consts::FRAC_PI_2,
+            )
+            .text(units::label("Fan Pitch", units::ANGLE)),
+        );
+        ui.add(
+            Slider::new(&mut fan.blade_speed, 0.0..=FAN_BLADE_SPEED_MAX)
+                .text(units::label("Blade Speed", units::ANGULAR_VELOCITY)),
+        );
+        ui.add(
+            Slider::new(&mut fan.radius, FAN_RADIUS_MIN..=FAN_RADIUS_MAX)
+                .text(units::label("Fan Radius", units::LENGTH)),
+        );
+    }
+}
+
+const PLANAR_OFFSET_MIN: f32 = -5.0;
+const PLANAR_OFFSET_MAX: f32 = 5.0;
+
+/// Shows a checkbox locking an axis so the simulation plays out in 2D, starting from the default,
+/// and the axis and the offset of the plane along it while it's locked.
+pub fn planar_controls(
+    ui: &mut egui::Ui,
+    planar: &mut Option<PlanarConstraint>,
+    default: PlanarConstraint,
+) {
+    let mut enabled = planar.is_some();
+    if ui.checkbox(&mut enabled, tr("2D Mode")).changed() {
+        *planar = enabled.then_some(default);
+    }
+    if let Some(planar) = planar {
+        egui::ComboBox::from_label(tr("Locked Axis"))
+            .selected_text(tr(planar.axis.name()))
+            .show_ui(ui, |ui| {
+                for axis in PlanarAxis::ALL {
+                    ui.selectable_value(&mut planar.axis, axis, tr(axis.name()));
+                }
+            });
+        ui.add(
+            Slider::new(&mut planar.offset, PLANAR_OFFSET_MIN..=PLANAR_OFFSET_MAX)
+                .text(units::label("Plane Offset", units::LENGTH)),
+        );
+    }
+}
+
 pub struct Gui {
     platform: Platform,
     render_pass: RenderPass,
+    graphics_settings: GraphicsSettings,
+    /// The resolution being edited, which is only applied when the user confirms it,
+    /// so that the window isn't resized while dragging the values.
+    resolution: [u32; 2],
+    graphics_settings_changed: bool,
+    /// The colors the demo's meshes should be drawn with.
+    theme: Theme,
+    theme_changed: bool,
+    help: Option<Help>,
+    show_help: bool,
+    camera_path_status: CameraPathStatus,
+    camera_path_commands: Vec<CameraPathCommand>,
+    capture_frames: bool,
+    top_down: bool,
+    top_down_changed: bool,
+    /// An exponential moving average of the frame time, in seconds.
+    smoothed_frame_time: f32,
+    /// An exponential moving average of the CPU time spent uploading and recording each frame's draws,
+    /// in seconds, if the demo measures it.
+    smoothed_render_cpu_time: Option<f32>,
 }
 
 impl Gui {
-    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, window: &Window) -> Gui {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        window: &Window,
+        graphics_settings: GraphicsSettings,
+    ) -> Gui {
         let size = window.inner_size();
         let platform = Platform::new(PlatformDescriptor {
-            physical_width: (size.width as u32) / 2,
-            physical_height: size.height as u32,
+            physical_width: size.width,
+            physical_height: size.height,
             scale_factor: window.scale_factor(),
             font_definitions: FontDefinitions::default(),
             style: Default::default(),
@@ -36,11 +358,105 @@ impl Gui {
         Gui {
             platform,
             render_pass: egui_rpass,
+            graphics_settings,
+            resolution: [graphics_settings.width, graphics_settings.height],
+            graphics_settings_changed: false,
+            theme: Theme::default(),
+            theme_changed: false,
+            help: None,
+            show_help: false,
+            camera_path_status: CameraPathStatus::default(),
+            camera_path_commands: Vec::new(),
+            capture_frames: false,
+            top_down: false,
+            top_down_changed: false,
+            smoothed_frame_time: 0.0,
+            smoothed_render_cpu_time: None,
+        }
+    }
+
+    /// Records the CPU time the demo spent uploading and recording the frame's draws, shown with the frame time.
+    pub fn record_render_cpu_time(&mut self, time: std::time::Duration) {
+        let smoothed = self
+            .smoothed_render_cpu_time
+            .get_or_insert(time.as_secs_f32());
+        *smoothed += 0.05 * (time.as_secs_f32() - *smoothed);
+    }
+
+    /// Sets the help overlay, which is toggled with Help::TOGGLE_KEY.
+    pub fn set_help(&mut self, help: Help) {
+        self.help = Some(help);
+    }
+
+    /// The graphics settings, if the user changed them since this was last called.
+    pub fn take_graphics_settings(&mut self) -> Option<GraphicsSettings> {
+        if self.graphics_settings_changed {
+            self.graphics_settings_changed = false;
+            Some(self.graphics_settings)
+        } else {
+            None
         }
     }
 
+    /// The theme, if the user changed it since this was last called.
+    pub fn take_theme(&mut self) -> Option<Theme> {
+        if self.theme_changed {
+            self.theme_changed = false;
+            Some(self.theme)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the state of the camera path tool to show in its window.
+    pub fn set_camera_path_status(&mut self, status: CameraPathStatus) {
+        self.camera_path_status = status;
+    }
+
+    /// The camera path commands the user gave since this was last called, in order.
+    pub fn take_camera_path_commands(&mut self) -> Vec<CameraPathCommand> {
+        std::mem::take(&mut self.camera_path_commands)
+    }
+
+    /// Whether to view the scene from the top down, if the user toggled it since this was last called.
+    pub fn take_top_down(&mut self) -> Option<bool> {
+        if self.top_down_changed {
+            self.top_down_changed = false;
+            Some(self.top_down)
+        } else {
+            None
+        }
+    }
+
+    /// True if the event is typing into the GUI, e.g. into a text field, so the demo should ignore it.
+    pub fn wants_keyboard_event(&self, event: &WindowEvent) -> bool {
+        matches!(
+            event,
+            WindowEvent::KeyboardInput { .. } | WindowEvent::ReceivedCharacter(_)
+        ) && self.platform.context().wants_keyboard_input()
+    }
+
     pub fn handle_events(&mut self, event: &Event<()>) {
         self.platform.handle_event(event);
+        if self.platform.context().wants_keyboard_input() {
+            return;
+        }
+        if let Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(Help::TOGGLE_KEY),
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.show_help = !self.show_help;
+        }
     }
 
     pub fn render<T: Ui>(
@@ -54,6 +470,7 @@ impl Gui {
         output: &wgpu::SurfaceTexture,
     ) -> wgpu::CommandBuffer {
         self.platform.update_time(dt.as_secs_f64());
+        self.smoothed_frame_time += 0.05 * (dt.as_secs_f32() - self.smoothed_frame_time);
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Gui Render Encoder"),
         });
@@ -66,6 +483,11 @@ impl Gui {
 
         // Draw the UI.
         ui.ui(&self.platform.context());
+        self.graphics_settings_ui(&self.platform.context());
+        self.camera_path_ui(&self.platform.context());
+        if let Some(help) = &self.help {
+            help.ui(&self.platform.context(), &mut self.show_help);
+        }
 
         // End the UI frame. We could now handle the output and draw the UI with the backend.
         let full_output = self.platform.end_frame(Some(window));
@@ -96,4 +518,195 @@ impl Gui {
             .unwrap();
         encoder.finish()
     }
+
+    /// A window for the graphics settings, which is shared by all demos.
+    fn graphics_settings_ui(&mut self, ctx: &egui::Context) {
+        let settings = &mut self.graphics_settings;
+        let previous = *settings;
+        let theme = &mut self.theme;
+        let previous_theme = *theme;
+        let frame_time = self.smoothed_frame_time;
+        let render_cpu_time = self.smoothed_render_cpu_time;
+        egui::Window::new(tr("Graphics")).show(ctx, |ui| {
+            ui.label(tr_format(
+                "Frame Time: {} ms ({} FPS)",
+                &[
+                    &format!("{:.2}", frame_time * 1000.0),
+                    &format!("{:.0}", 1.0 / frame_time.max(f32::EPSILON)),
+                ],
+            ));
+            if let Some(render_cpu_time) = render_cpu_time {
+                ui.label(tr_format(
+                    "Upload and Render CPU Time: {} ms",
+                    &[&format!("{:.3}", render_cpu_time * 1000.0)],
+                ));
+            }
+            ui.checkbox(&mut settings.vsync, tr("VSync"));
+            egui::ComboBox::from_label(tr("Window Mode"))
+                .selected_text(tr(settings.window_mode.name()))
+                .show_ui(ui, |ui| {
+                    for mode in WindowMode::ALL {
+                        ui.selectable_value(&mut settings.window_mode, mode, tr(mode.name()));
+                    }
+                });
+            egui::ComboBox::from_label(tr("When Minimized"))
+                .selected_text(tr(settings.background_stepping.name()))
+                .show_ui(ui, |ui| {
+                    for stepping in BackgroundStepping::ALL {
+                        ui.selectable_value(
+                            &mut settings.background_stepping,
+                            stepping,
+                            tr(stepping.name()),
+                        );
+                    }
+                });
+            egui::ComboBox::from_label(tr("MSAA"))
+                .selected_text(format!("{}x", settings.msaa_samples))
+                .show_ui(ui, |ui| {
+                    for samples in GraphicsSettings::MSAA_SAMPLES {
+                        ui.selectable_value(
+                            &mut settings.msaa_samples,
+                            samples,
+                            format!("{}x", samples),
+                        );
+                    }
+                });
+            ui.add(
+                Slider::new(
+                    &mut settings.fov,
+                    GraphicsSettings::FOV_MIN..=GraphicsSettings::FOV_MAX,
+                )
+                .text(tr("FOV (degrees)")),
+            );
+            ui.checkbox(&mut settings.stereo, tr("Stereo (Side by Side)"));
+            ui.add(
+                Slider::new(
+                    &mut settings.render_scale,
+                    GraphicsSettings::RENDER_SCALE_MIN..=GraphicsSettings::RENDER_SCALE_MAX,
+                )
+                .text(tr("Render Scale")),
+            );
+            ui.checkbox(&mut settings.depth_prepass, tr("Depth Pre-Pass"));
+            egui::ComboBox::from_label(tr("Theme"))
+                .selected_text(tr(theme.name))
+                .show_ui(ui, |ui| {
+                    for option in Theme::ALL {
+                        ui.selectable_value(theme, option, tr(option.name));
+                    }
+                });
+            egui::ComboBox::from_label(tr("Transparency"))
+                .selected_text(tr(settings.transparency_mode.name()))
+                .show_ui(ui, |ui| {
+                    for mode in TransparencyMode::ALL {
+                        ui.selectable_value(&mut settings.transparency_mode, mode, tr(mode.name()));
+                    }
+                });
+            egui::ComboBox::from_label(tr("Ambient Occlusion"))
+                .selected_text(tr(settings.ambient_occlusion.name()))
+                .show_ui(ui, |ui| {
+                    for quality in AmbientOcclusionQuality::ALL {
+                        ui.selectable_value(
+                            &mut settings.ambient_occlusion,
+                            quality,
+                            tr(quality.name()),
+                        );
+                    }
+                });
+            if settings.ambient_occlusion != AmbientOcclusionQuality::Off {
+                ui.add(
+                    Slider::new(
+                        &mut settings.ambient_occlusion_radius,
+                        GraphicsSettings::AMBIENT_OCCLUSION_RADIUS_MIN
+                            ..=GraphicsSettings::AMBIENT_OCCLUSION_RADIUS_MAX,
+                    )
+                    .text(tr("Occlusion Radius (m)")),
+                );
+            }
+            ui.checkbox(&mut settings.dynamic_resolution, tr("Dynamic Resolution"));
+            if settings.dynamic_resolution {
+                ui.add(
+                    Slider::new(
+                        &mut settings.target_frame_rate,
+                        GraphicsSettings::TARGET_FRAME_RATE_MIN
+                            ..=GraphicsSettings::TARGET_FRAME_RATE_MAX,
+                    )
+                    .text(tr("Target Frame Rate (FPS)")),
+                );
+            }
+            let resolution_range =
+                GraphicsSettings::RESOLUTION_MIN..=GraphicsSettings::RESOLUTION_MAX;
+            ui.horizontal(|ui| {
+                ui.label(tr("Resolution"));
+                ui.add(
+                    egui::DragValue::new(&mut self.resolution[0])
+                        .clamp_range(resolution_range.clone()),
+                );
+                ui.label(tr("x"));
+                ui.add(egui::DragValue::new(&mut self.resolution[1]).clamp_range(resolution_range));
+                if ui.button(tr("Apply")).clicked() {
+                    settings.width = self.resolution[0];
+                    settings.height = self.resolution[1];
+                }
+            });
+        });
+        if *settings != previous {
+            self.graphics_settings_changed = true;
+        }
+        if self.theme != previous_theme {
+            self.theme_changed = true;
+        }
+    }
+
+    /// A window for recording and playing back camera paths, which is shared by all demos.
+    fn camera_path_ui(&mut self, ctx: &egui::Context) {
+        let status = self.camera_path_status;
+        let commands = &mut self.camera_path_commands;
+        egui::Window::new(tr("Camera Path")).show(ctx, |ui| {
+            ui.label(tr_format(
+                "{}: {} keyframes, {} s",
+                &[
+                    &tr(status.mode.name()),
+                    &status.keyframes,
+                    &format!("{:.2}", status.duration),
+                ],
+            ));
+            ui.horizontal(|ui| {
+                if ui.button(tr("Add Keyframe")).clicked() {
+                    commands.push(CameraPathCommand::AddKeyframe);
+                }
+                if ui.button(tr("Record")).clicked() {
+                    commands.push(CameraPathCommand::Record);
+                }
+                if ui.button(tr("Play")).clicked() {
+                    commands.push(CameraPathCommand::Play {
+                        capture_frames: self.capture_frames,
+                    });
+                }
+                if status.mode != CameraPathMode::Idle && ui.button(tr("Stop")).clicked() {
+                    commands.push(CameraPathCommand::Stop);
+                }
+            });
+            ui.add_enabled(
+                status.frame_capture_supported,
+                egui::Checkbox::new(&mut self.capture_frames, tr("Capture Frames")),
+            );
+            if ui
+                .checkbox(&mut self.top_down, tr("Top-Down View"))
+                .changed()
+            {
+                self.top_down_changed = true;
+            }
+            ui.horizontal(|ui| {
+                if ui.button(tr("Clear")).clicked() {
+                    commands.push(CameraPathCommand::Clear);
+                }
+                if ui.button(tr("Save")).clicked() {
+                    commands.push(CameraPathCommand::Save);
+                }
+                if ui.button(tr("Load")).clicked() {
+                    commands.push(CameraPathCommand::Load);
+                }
+            });
+        });
+    }
 }
@@ -1,22 +1,41 @@
-use crate::gui::Ui;
-use crate::simulation::particles_cpu::particles;
+use crate::gui::{self, ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::collidable_mesh::Sidedness;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::fan::FanConfig;
+use crate::simulation::particles_cpu::{
+    budget::StepBudgetConfig,
+    curl_noise::CurlNoiseConfig,
+    fire::FireConfig,
+    generator::EmitterShape,
+    particles,
+    sub_emitter::{BurstTrigger, SubEmitterConfig},
+    swirl::SwirlConfig,
+};
+use crate::simulation::planar::{PlanarAxis, PlanarConstraint};
+use crate::simulation::units;
 
 use egui::Slider;
 
 pub struct ParticlesUi {
     sim_config: particles::Config,
+    trigger_enter_counts: Vec<usize>,
+    particles_simulated: usize,
+    particles_represented: u32,
+    /// The average step time in milliseconds, and the fraction of particles being generated, under the step budget.
+    step_budget_stats: Option<(f32, f32)>,
 }
 
 impl Ui for ParticlesUi {
     fn ui(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Config").show(&ctx, |ui| {
+        egui::Window::new(tr("Config")).show(&ctx, |ui| {
             ui.add(
                 Slider::new(
                     &mut self.sim_config.dt,
                     ParticlesUi::SIMULATION_DT_MIN.as_secs_f32()
                         ..=ParticlesUi::SIMULATION_DT_MAX.as_secs_f32(),
                 )
-                .text("Simualtion dt (secs)"),
+                .text(tr("Simualtion dt (secs)")),
             );
             ui.add(
                 Slider::new(
@@ -24,65 +43,66 @@ impl Ui for ParticlesUi {
                     ParticlesUi::MIN_PARTICLES_GENERATED_PER_STEP
                         ..=ParticlesUi::MAX_PARTICLES_GENRATED_PER_STEP,
                 )
-                .text("Particles Generated Per Step"),
+                .text(tr("Particles Generated Per Step")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.acceleration_gravity.x,
                     ParticlesUi::ACCELERATION_GRAVITY_MIN..=ParticlesUi::ACCELERATION_GRAVITY_MAX,
                 )
-                .text("Gravity X"),
+                .text(units::label("Gravity X", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.acceleration_gravity.y,
                     ParticlesUi::ACCELERATION_GRAVITY_MIN..=ParticlesUi::ACCELERATION_GRAVITY_MAX,
                 )
-                .text("Gravity Y"),
+                .text(units::label("Gravity Y", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.acceleration_gravity.z,
                     ParticlesUi::ACCELERATION_GRAVITY_MIN..=ParticlesUi::ACCELERATION_GRAVITY_MAX,
                 )
-                .text("Gravity Z"),
-            );
-            ui.add(
-                Slider::new(
-                    &mut self.sim_config.y_axis_attractor_gravity,
-                    ParticlesUi::MIN_Y_AXIS_ATTRACTOR_GRAVITY
-                        ..=ParticlesUi::MAX_Y_AXIS_ATTRCTOR_GRAVITY,
-                )
-                .text("Y Axis Attractor Gravity"),
+                .text(units::label("Gravity Z", units::ACCELERATION)),
             );
+            if let Some(preset) = gui::gravity_presets(ui, self.sim_config.acceleration_gravity) {
+                self.sim_config.acceleration_gravity = preset.vector();
+            }
+            self.swirl_controls(ui);
             ui.add(
                 Slider::new(
                     &mut self.sim_config.wind.x,
                     ParticlesUi::MIN_WIND..=ParticlesUi::MAX_WIND,
                 )
-                .text("Wind X"),
+                .text(units::label("Wind X", units::VELOCITY)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.wind.y,
                     ParticlesUi::MIN_WIND..=ParticlesUi::MAX_WIND,
                 )
-                .text("Wind Y"),
+                .text(units::label("Wind Y", units::VELOCITY)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.wind.z,
                     ParticlesUi::MIN_WIND..=ParticlesUi::MAX_WIND,
                 )
-                .text("Wind Z"),
+                .text(units::label("Wind Z", units::VELOCITY)),
             );
+            self.curl_noise_controls(ui);
+            gui::fan_controls(ui, &mut self.sim_config.fan, ParticlesUi::FAN_DEFAULT);
+            gui::planar_controls(ui, &mut self.sim_config.planar, ParticlesUi::PLANAR_DEFAULT);
+            self.fire_controls(ui);
+            self.sub_emitter_controls(ui);
             ui.add(
                 Slider::new(
                     &mut self.sim_config.coefficient_of_restitution,
                     ParticlesUi::MIN_COEFFICIENT_OF_RESTITUTION
                         ..=ParticlesUi::MAX_COEFFICIENT_OF_RESTITUTION,
                 )
-                .text("Coefficient of Restitution"),
+                .text(tr("Coefficient of Restitution")),
             );
             ui.add(
                 Slider::new(
@@ -90,7 +110,39 @@ impl Ui for ParticlesUi {
                     ParticlesUi::MIN_COEFFICIENT_OF_FRICTION
                         ..=ParticlesUi::MAX_COEFFICIENT_OF_FRICTION,
                 )
-                .text("Coefficient of Friction"),
+                .text(tr("Coefficient of Friction")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.obstacle_surface_velocity.x,
+                    ParticlesUi::MIN_OBSTACLE_SURFACE_VELOCITY
+                        ..=ParticlesUi::MAX_OBSTACLE_SURFACE_VELOCITY,
+                )
+                .text(units::label("Obstacle Conveyor X", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.obstacle_surface_velocity.z,
+                    ParticlesUi::MIN_OBSTACLE_SURFACE_VELOCITY
+                        ..=ParticlesUi::MAX_OBSTACLE_SURFACE_VELOCITY,
+                )
+                .text(units::label("Obstacle Conveyor Z", units::VELOCITY)),
+            );
+            egui::ComboBox::from_label(tr("Obstacle Sides"))
+                .selected_text(tr(self.sim_config.obstacle_sidedness.name()))
+                .show_ui(ui, |ui| {
+                    for option in Sidedness::ALL {
+                        ui.selectable_value(
+                            &mut self.sim_config.obstacle_sidedness,
+                            option,
+                            tr(option.name()),
+                        );
+                    }
+                });
+            gui::collision_mask(
+                ui,
+                "Collides With",
+                &mut self.sim_config.particles_collision_mask,
             );
             ui.add(
                 Slider::new(
@@ -98,7 +150,7 @@ impl Ui for ParticlesUi {
                     ParticlesUi::MIN_LIFETIME.as_secs_f32()
                         ..=ParticlesUi::MAX_LIFETIME.as_secs_f32(),
                 )
-                .text("Lifetime Mean"),
+                .text(units::label("Lifetime Mean", units::TIME)),
             );
             ui.add(
                 Slider::new(
@@ -106,78 +158,147 @@ impl Ui for ParticlesUi {
                     ParticlesUi::MIN_LIFETIME_RANGE.as_secs_f32()
                         ..=ParticlesUi::MAX_LIFETIME_RANGE.as_secs_f32(),
                 )
-                .text("Lifetime Range"),
+                .text(units::label("Lifetime Range", units::TIME)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.particles_initial_speed_mean,
                     ParticlesUi::MIN_SPEED..=ParticlesUi::MAX_SPEED,
                 )
-                .text("Initial Speed Mean"),
+                .text(units::label("Initial Speed Mean", units::VELOCITY)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.particles_initial_speed_range,
                     ParticlesUi::MIN_SPEED_RANGE..=ParticlesUi::MAX_SPEED_RANGE,
                 )
-                .text("Initial Speed Range"),
+                .text(units::label("Initial Speed Range", units::VELOCITY)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.particles_mass_mean,
                     ParticlesUi::MIN_MASS..=ParticlesUi::MAX_MASS,
                 )
-                .text("Mass Mean"),
+                .text(units::label("Mass Mean", units::MASS)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.particles_mass_range,
                     ParticlesUi::MIN_MASS_RANGE..=ParticlesUi::MAX_MASS_RANGE,
                 )
-                .text("Mass Range"),
+                .text(units::label("Mass Range", units::MASS)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.particles_drag_mean,
                     ParticlesUi::MIN_DRAG..=ParticlesUi::MAX_DRAG,
                 )
-                .text("Drag Mean"),
+                .text(tr("Drag Mean")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.particles_drag_range,
                     ParticlesUi::MIN_DRAG_RANGE..=ParticlesUi::MAX_DRAG_RANGE,
                 )
-                .text("Drag Range"),
-            );
-            ui.add(
-                Slider::new(
-                    &mut self.sim_config.generator_radius,
-                    ParticlesUi::MIN_GENERATOR_RADIUS..=ParticlesUi::MAX_GENERATOR_RADIUS,
-                )
-                .text("Generator Radius"),
-            );
-            ui.add(
-                Slider::new(
-                    &mut self.sim_config.generator_position.x,
-                    ParticlesUi::MIN_GENERATOR_POSITION..=ParticlesUi::MAX_GENERATOR_POSITION,
-                )
-                .text("Generator X"),
-            );
-            ui.add(
-                Slider::new(
-                    &mut self.sim_config.generator_position.y,
-                    ParticlesUi::MIN_GENERATOR_POSITION..=ParticlesUi::MAX_GENERATOR_POSITION,
-                )
-                .text("Generator Y"),
-            );
-            ui.add(
-                Slider::new(
-                    &mut self.sim_config.generator_position.z,
-                    ParticlesUi::MIN_GENERATOR_POSITION..=ParticlesUi::MAX_GENERATOR_POSITION,
-                )
-                .text("Generator Z"),
+                .text(tr("Drag Range")),
             );
+            egui::ComboBox::from_label(tr("Emitter"))
+                .selected_text(tr(self.sim_config.emitter.name()))
+                .show_ui(ui, |ui| {
+                    for emitter in EmitterShape::ALL {
+                        ui.selectable_value(
+                            &mut self.sim_config.emitter,
+                            emitter,
+                            tr(emitter.name()),
+                        );
+                    }
+                });
+            if self.sim_config.emitter == EmitterShape::Disk {
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.generator_radius,
+                        ParticlesUi::MIN_GENERATOR_RADIUS..=ParticlesUi::MAX_GENERATOR_RADIUS,
+                    )
+                    .text(units::label("Generator Radius", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.generator_position.x,
+                        ParticlesUi::MIN_GENERATOR_POSITION..=ParticlesUi::MAX_GENERATOR_POSITION,
+                    )
+                    .text(units::label("Generator X", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.generator_position.y,
+                        ParticlesUi::MIN_GENERATOR_POSITION..=ParticlesUi::MAX_GENERATOR_POSITION,
+                    )
+                    .text(units::label("Generator Y", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.generator_position.z,
+                        ParticlesUi::MIN_GENERATOR_POSITION..=ParticlesUi::MAX_GENERATOR_POSITION,
+                    )
+                    .text(units::label("Generator Z", units::LENGTH)),
+                );
+            }
+            ui.separator();
+            ui.checkbox(&mut self.sim_config.lod_enabled, tr("Level of Detail"));
+            if self.sim_config.lod_enabled {
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.lod_distance,
+                        ParticlesUi::MIN_LOD_DISTANCE..=ParticlesUi::MAX_LOD_DISTANCE,
+                    )
+                    .text(units::label("LOD Distance", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.lod_error,
+                        ParticlesUi::MIN_LOD_ERROR..=ParticlesUi::MAX_LOD_ERROR,
+                    )
+                    .text(tr("LOD Error (rad)")),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.lod_max_count,
+                        ParticlesUi::MIN_LOD_MAX_COUNT..=ParticlesUi::MAX_LOD_MAX_COUNT,
+                    )
+                    .text(tr("Max Particles Merged")),
+                );
+            }
+            ui.separator();
+            let mut budgeted = self.sim_config.step_budget.is_some();
+            if ui.checkbox(&mut budgeted, tr("Step Budget")).changed() {
+                self.sim_config.step_budget = budgeted.then(StepBudgetConfig::default);
+            }
+            if let Some(step_budget) = &mut self.sim_config.step_budget {
+                ui.add(
+                    Slider::new(
+                        &mut step_budget.milliseconds,
+                        ParticlesUi::MIN_STEP_BUDGET..=ParticlesUi::MAX_STEP_BUDGET,
+                    )
+                    .text(tr("Budget (ms)")),
+                );
+                ui.checkbox(&mut step_budget.kill_oldest, tr("Kill Oldest Particles"));
+            }
+            ui.label(tr_format(
+                "Particles: {} simulated, representing {}",
+                &[&self.particles_simulated, &self.particles_represented],
+            ));
+            if let Some((milliseconds, emission_scale)) = self.step_budget_stats {
+                ui.label(tr_format(
+                    "Step Time: {} ms, generating {}%",
+                    &[
+                        &format!("{:.3}", milliseconds),
+                        &format!("{:.0}", emission_scale * 100.0),
+                    ],
+                ));
+            }
+            for (index, count) in self.trigger_enter_counts.iter().enumerate() {
+                ui.label(tr_format("Trigger {} Entered: {}", &[&index, &count]));
+            }
         });
     }
 }
@@ -198,8 +319,32 @@ impl ParticlesUi {
     const MIN_COEFFICIENT_OF_FRICTION: f32 = 0.0;
     const MAX_COEFFICIENT_OF_FRICTION: f32 = 1.0;
 
-    const MIN_Y_AXIS_ATTRACTOR_GRAVITY: f32 = -10.0;
-    const MAX_Y_AXIS_ATTRCTOR_GRAVITY: f32 = 10.0;
+    const MIN_OBSTACLE_SURFACE_VELOCITY: f32 = -5.0;
+    const MAX_OBSTACLE_SURFACE_VELOCITY: f32 = 5.0;
+
+    const MIN_SWIRL_AXIS: f32 = -1.0;
+    const MAX_SWIRL_AXIS: f32 = 1.0;
+
+    const MIN_SWIRL_STRENGTH: f32 = -20.0;
+    const MAX_SWIRL_STRENGTH: f32 = 20.0;
+
+    const MIN_SWIRL_ATTRACTION: f32 = -10.0;
+    const MAX_SWIRL_ATTRACTION: f32 = 10.0;
+
+    const MIN_SWIRL_FALLOFF: f32 = 0.0;
+    const MAX_SWIRL_FALLOFF: f32 = 3.0;
+
+    const MIN_SWIRL_MAX_SPEED: f32 = 0.0;
+    const MAX_SWIRL_MAX_SPEED: f32 = 20.0;
+
+    const MIN_TURBULENCE_STRENGTH: f32 = 0.0;
+    const MAX_TURBULENCE_STRENGTH: f32 = 5.0;
+
+    const MIN_EDDY_SIZE: f32 = 0.1;
+    const MAX_EDDY_SIZE: f32 = 5.0;
+
+    const MIN_EVOLUTION_RATE: f32 = 0.0;
+    const MAX_EVOLUTION_RATE: f32 = 2.0;
 
     const MIN_LIFETIME: std::time::Duration = std::time::Duration::from_secs(1);
     const MAX_LIFETIME: std::time::Duration = std::time::Duration::from_secs(10);
@@ -224,19 +369,352 @@ impl ParticlesUi {
     const MIN_WIND: f32 = -5.0;
     const MAX_WIND: f32 = 5.0;
 
+    /// Beside the obstacle, blowing the falling particles across it.
+    const FAN_DEFAULT: FanConfig = FanConfig {
+        position: cgmath::Vector3::new(-3.0, 1.0, 0.0),
+        yaw: std::f32::consts::FRAC_PI_2,
+        pitch: 0.0,
+        blade_speed: 12.0,
+        radius: 0.5,
+    };
+
+    /// Facing the default camera, through the generator.
+    const PLANAR_DEFAULT: PlanarConstraint = PlanarConstraint {
+        axis: PlanarAxis::Z,
+        offset: 0.0,
+    };
+
+    const MIN_FIRE_TEMPERATURE: f32 = 500.0;
+    const MAX_FIRE_TEMPERATURE: f32 = 3000.0;
+
+    const MIN_COOLING_RATE: f32 = 0.0;
+    const MAX_COOLING_RATE: f32 = 5.0;
+
+    const MIN_BUOYANCY: f32 = 0.0;
+    const MAX_BUOYANCY: f32 = 2.0;
+
+    const MIN_SMOKE_PER_EMBER: u32 = 0;
+    const MAX_SMOKE_PER_EMBER: u32 = 4;
+
+    const MIN_BURST_AGE: f32 = 0.0;
+    const MAX_BURST_AGE: f32 = 5.0;
+
+    const MIN_BURST_COUNT: u32 = 1;
+    const MAX_BURST_COUNT: u32 = 100;
+
+    const MIN_BURST_SPEED: f32 = 0.0;
+    const MAX_BURST_SPEED: f32 = 10.0;
+
+    const MIN_INHERITED_VELOCITY: f32 = 0.0;
+    const MAX_INHERITED_VELOCITY: f32 = 1.0;
+
+    const MIN_BURST_BUDGET: u32 = 0;
+    const MAX_BURST_BUDGET: u32 = particles::MAX_INSTANCES as u32;
+
     const MIN_GENERATOR_RADIUS: f32 = 0.1;
     const MAX_GENERATOR_RADIUS: f32 = 10.0;
 
     const MIN_GENERATOR_POSITION: f32 = -5.0;
     const MAX_GENERATOR_POSITION: f32 = 5.0;
 
+    const MIN_LOD_DISTANCE: f32 = 1.0;
+    const MAX_LOD_DISTANCE: f32 = 50.0;
+
+    const MIN_LOD_ERROR: f32 = 0.01;
+    const MAX_LOD_ERROR: f32 = 0.5;
+
+    const MIN_STEP_BUDGET: f32 = 0.05;
+    const MAX_STEP_BUDGET: f32 = 5.0;
+
+    const MIN_LOD_MAX_COUNT: u32 = 2;
+    const MAX_LOD_MAX_COUNT: u32 = 500;
+
     pub fn new() -> ParticlesUi {
         ParticlesUi {
             sim_config: particles::Config::default(),
+            trigger_enter_counts: Vec::new(),
+            particles_simulated: 0,
+            particles_represented: 0,
+            step_budget_stats: None,
+        }
+    }
+
+    /// Shows a checkbox swirling the particles around an axis, and the swirl's controls while it's checked.
+    fn swirl_controls(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.sim_config.swirl.is_some();
+        if ui.checkbox(&mut enabled, tr("Swirl")).changed() {
+            self.sim_config.swirl = enabled.then(SwirlConfig::default);
+        }
+        let Some(swirl) = &mut self.sim_config.swirl else {
+            return;
+        };
+        ui.add(
+            Slider::new(
+                &mut swirl.axis.x,
+                ParticlesUi::MIN_SWIRL_AXIS..=ParticlesUi::MAX_SWIRL_AXIS,
+            )
+            .text(tr("Swirl Axis X")),
+        );
+        ui.add(
+            Slider::new(
+                &mut swirl.axis.y,
+                ParticlesUi::MIN_SWIRL_AXIS..=ParticlesUi::MAX_SWIRL_AXIS,
+            )
+            .text(tr("Swirl Axis Y")),
+        );
+        ui.add(
+            Slider::new(
+                &mut swirl.axis.z,
+                ParticlesUi::MIN_SWIRL_AXIS..=ParticlesUi::MAX_SWIRL_AXIS,
+            )
+            .text(tr("Swirl Axis Z")),
+        );
+        ui.add(
+            Slider::new(
+                &mut swirl.strength,
+                ParticlesUi::MIN_SWIRL_STRENGTH..=ParticlesUi::MAX_SWIRL_STRENGTH,
+            )
+            .text(units::label("Swirl Strength", units::ACCELERATION)),
+        );
+        ui.add(
+            Slider::new(
+                &mut swirl.attraction,
+                ParticlesUi::MIN_SWIRL_ATTRACTION..=ParticlesUi::MAX_SWIRL_ATTRACTION,
+            )
+            .text(units::label("Attraction", units::ACCELERATION)),
+        );
+        ui.add(
+            Slider::new(
+                &mut swirl.falloff,
+                ParticlesUi::MIN_SWIRL_FALLOFF..=ParticlesUi::MAX_SWIRL_FALLOFF,
+            )
+            .text(tr("Falloff")),
+        );
+        ui.add(
+            Slider::new(
+                &mut swirl.max_speed,
+                ParticlesUi::MIN_SWIRL_MAX_SPEED..=ParticlesUi::MAX_SWIRL_MAX_SPEED,
+            )
+            .text(units::label("Max Swirl Speed", units::VELOCITY)),
+        );
+    }
+
+    /// Shows a checkbox adding curl noise turbulence to the wind, and the turbulence's controls while it's checked.
+    fn curl_noise_controls(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.sim_config.curl_noise.is_some();
+        if ui.checkbox(&mut enabled, tr("Turbulence")).changed() {
+            self.sim_config.curl_noise = enabled.then(CurlNoiseConfig::default);
+        }
+        let Some(curl_noise) = &mut self.sim_config.curl_noise else {
+            return;
+        };
+        ui.add(
+            Slider::new(
+                &mut curl_noise.strength,
+                ParticlesUi::MIN_TURBULENCE_STRENGTH..=ParticlesUi::MAX_TURBULENCE_STRENGTH,
+            )
+            .text(units::label("Turbulence Strength", units::VELOCITY)),
+        );
+        ui.add(
+            Slider::new(
+                &mut curl_noise.scale,
+                ParticlesUi::MIN_EDDY_SIZE..=ParticlesUi::MAX_EDDY_SIZE,
+            )
+            .text(units::label("Eddy Size", units::LENGTH)),
+        );
+        ui.add(
+            Slider::new(
+                &mut curl_noise.evolution_rate,
+                ParticlesUi::MIN_EVOLUTION_RATE..=ParticlesUi::MAX_EVOLUTION_RATE,
+            )
+            .text(tr("Evolution Rate (1/s)")),
+        );
+    }
+
+    /// Shows a checkbox making the particles burn, which also sets the generator for a campfire,
+    /// and sliders for how they burn while it's checked.
+    fn fire_controls(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.sim_config.fire.is_some();
+        if ui.checkbox(&mut enabled, tr("Fire")).changed() {
+            self.sim_config.fire = enabled.then(FireConfig::default);
+            if enabled {
+                self.apply_fire_preset();
+            }
+        }
+        let Some(fire) = &mut self.sim_config.fire else {
+            return;
+        };
+        ui.add(
+            Slider::new(
+                &mut fire.initial_temperature,
+                ParticlesUi::MIN_FIRE_TEMPERATURE..=ParticlesUi::MAX_FIRE_TEMPERATURE,
+            )
+            .text(units::label("Initial Temperature", units::TEMPERATURE)),
+        );
+        ui.add(
+            Slider::new(
+                &mut fire.cooling_rate,
+                ParticlesUi::MIN_COOLING_RATE..=ParticlesUi::MAX_COOLING_RATE,
+            )
+            .text(tr("Cooling Rate (1/s)")),
+        );
+        ui.add(
+            Slider::new(
+                &mut fire.buoyancy,
+                ParticlesUi::MIN_BUOYANCY..=ParticlesUi::MAX_BUOYANCY,
+            )
+            .text(tr("Buoyancy")),
+        );
+        ui.add(
+            Slider::new(
+                &mut fire.smoke_per_ember,
+                ParticlesUi::MIN_SMOKE_PER_EMBER..=ParticlesUi::MAX_SMOKE_PER_EMBER,
+            )
+            .text(tr("Smoke Per Ember")),
+        );
+        ui.add(
+            Slider::new(
+                &mut fire.smoke_lifetime,
+                ParticlesUi::MIN_LIFETIME.as_secs_f32()..=ParticlesUi::MAX_LIFETIME.as_secs_f32(),
+            )
+            .text(units::label("Smoke Lifetime", units::TIME)),
+        );
+    }
+
+    /// A small campfire beside the obstacle, of short-lived, slow particles which the heat carries up.
+    fn apply_fire_preset(&mut self) {
+        self.sim_config.particles_generated_per_step = 3;
+        self.sim_config.particles_lifetime_mean = 1.2;
+        self.sim_config.particles_lifetime_range = 0.4;
+        self.sim_config.particles_initial_speed_mean = 0.5;
+        self.sim_config.particles_initial_speed_range = 0.3;
+        self.sim_config.particles_mass_mean = 1.0;
+        self.sim_config.particles_mass_range = 0.0;
+        self.sim_config.particles_drag_mean = 2.0;
+        self.sim_config.particles_drag_range = 0.5;
+        self.sim_config.generator_radius = 0.3;
+        self.sim_config.generator_position = cgmath::Vector3::new(2.5, -1.0, 0.0);
+    }
+
+    /// Shows a checkbox bursting the particles into sparks, which also sets the generator to launch rockets,
+    /// and the sub-emitter's controls while it's checked.
+    fn sub_emitter_controls(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.sim_config.sub_emitter.is_some();
+        if ui.checkbox(&mut enabled, tr("Fireworks")).changed() {
+            self.sim_config.sub_emitter = enabled.then(SubEmitterConfig::default);
+            if enabled {
+                self.apply_fireworks_preset();
+            }
         }
+        let Some(sub_emitter) = &mut self.sim_config.sub_emitter else {
+            return;
+        };
+        egui::ComboBox::from_label(tr("Burst"))
+            .selected_text(tr(sub_emitter.trigger.name()))
+            .show_ui(ui, |ui| {
+                for trigger in BurstTrigger::ALL {
+                    ui.selectable_value(&mut sub_emitter.trigger, trigger, tr(trigger.name()));
+                }
+            });
+        if sub_emitter.trigger == BurstTrigger::Age {
+            ui.add(
+                Slider::new(
+                    &mut sub_emitter.burst_age,
+                    ParticlesUi::MIN_BURST_AGE..=ParticlesUi::MAX_BURST_AGE,
+                )
+                .text(units::label("Burst Age", units::TIME)),
+            );
+        }
+        ui.add(
+            Slider::new(
+                &mut sub_emitter.burst_count,
+                ParticlesUi::MIN_BURST_COUNT..=ParticlesUi::MAX_BURST_COUNT,
+            )
+            .text(tr("Sparks Per Burst")),
+        );
+        ui.add(
+            Slider::new(
+                &mut sub_emitter.burst_speed,
+                ParticlesUi::MIN_BURST_SPEED..=ParticlesUi::MAX_BURST_SPEED,
+            )
+            .text(units::label("Burst Speed", units::VELOCITY)),
+        );
+        ui.add(
+            Slider::new(
+                &mut sub_emitter.inherited_velocity,
+                ParticlesUi::MIN_INHERITED_VELOCITY..=ParticlesUi::MAX_INHERITED_VELOCITY,
+            )
+            .text(tr("Inherited Velocity")),
+        );
+        ui.add(
+            Slider::new(
+                &mut sub_emitter.lifetime,
+                ParticlesUi::MIN_LIFETIME.as_secs_f32()..=ParticlesUi::MAX_LIFETIME.as_secs_f32(),
+            )
+            .text(units::label("Spark Lifetime", units::TIME)),
+        );
+        ui.add(
+            Slider::new(
+                &mut sub_emitter.budget,
+                ParticlesUi::MIN_BURST_BUDGET..=ParticlesUi::MAX_BURST_BUDGET,
+            )
+            .text(tr("Spark Budget")),
+        );
     }
 
-    pub fn get_gui_state_mut(&mut self) -> &particles::Config {
+    /// Rockets launched fast and straight up from beside the obstacle, which burst as they slow.
+    fn apply_fireworks_preset(&mut self) {
+        self.sim_config.emitter = EmitterShape::Disk;
+        self.sim_config.particles_generated_per_step = 1;
+        self.sim_config.particles_lifetime_mean = 0.9;
+        self.sim_config.particles_lifetime_range = 0.3;
+        self.sim_config.particles_initial_speed_mean = 9.0;
+        self.sim_config.particles_initial_speed_range = 1.0;
+        self.sim_config.particles_mass_mean = 1.0;
+        self.sim_config.particles_mass_range = 0.0;
+        self.sim_config.particles_drag_mean = 0.05;
+        self.sim_config.particles_drag_range = 0.0;
+        self.sim_config.generator_radius = 0.2;
+        self.sim_config.generator_position = cgmath::Vector3::new(-2.5, -1.0, 0.0);
+        self.sim_config.generator_normal = cgmath::Vector3::unit_y();
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut particles::Simulation) {
+        simulation.sync_config(self.get_config());
+        self.set_trigger_enter_counts(
+            simulation
+                .get_triggers()
+                .iter()
+                .map(|trigger| trigger.get_enter_count())
+                .collect(),
+        );
+        let (simulated, represented) = simulation.get_particle_counts();
+        self.set_particle_counts(simulated, represented);
+        self.step_budget_stats = simulation.get_step_budget_stats();
+    }
+
+    /// Sets the number of particles which have entered each of the simulation's triggers, for display.
+    pub fn set_trigger_enter_counts(&mut self, counts: Vec<usize>) {
+        self.trigger_enter_counts = counts;
+    }
+
+    /// Sets the number of particles simulated, and the number they represent, for display.
+    pub fn set_particle_counts(&mut self, simulated: usize, represented: u32) {
+        self.particles_simulated = simulated;
+        self.particles_represented = represented;
+    }
+}
+
+impl ConfigUi for ParticlesUi {
+    type Config = particles::Config;
+
+    fn get_config(&self) -> &particles::Config {
         &self.sim_config
     }
 }
+
+impl Default for ParticlesUi {
+    fn default() -> Self {
+        ParticlesUi::new()
+    }
+}
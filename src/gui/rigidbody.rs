@@ -1,87 +1,308 @@
-use crate::gui::Ui;
+use crate::gui::{
+    self,
+    console::Console,
+    spectrum::ImpulseAnalysis,
+    undo::{self, UndoAction},
+    ConfigUi, Ui,
+};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::planar::{PlanarAxis, PlanarConstraint};
 use crate::simulation::rigidbody::config::Config;
+use crate::simulation::rigidbody::friction::AnisotropicFriction;
+use crate::simulation::rigidbody::joint::{JointKind, Motor, MotorMode};
+use crate::simulation::rigidbody::schedule::{Axis, LoadKind, Profile, ScheduledLoad};
+use crate::simulation::rigidbody::simulation::{JointHandle, Simulation};
+use crate::simulation::spectrum::ImpulseRecorder;
 use crate::simulation::state::Integration;
+use crate::simulation::units;
 
 use cgmath::{Vector3, Zero};
 use egui::Slider;
 
+/// A joint as listed in the joint editor.
+pub struct JointSummary {
+    pub handle: JointHandle,
+    pub kind: JointKind,
+    /// The indices of the joint's bodies among the live rigidbodies, as in their gizmo toggles.
+    pub bodies: (usize, usize),
+    /// The hinge angle, in radians. See Joint::get_angle().
+    pub angle: f32,
+    pub motor: Motor,
+}
+
+/// An edit to a joint made in the joint editor.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JointEdit {
+    Break(JointHandle),
+    Reanchor(JointHandle),
+    SetMotor(JointHandle, Motor),
+}
+
+/// The kinematic obstacles the demo animates through the room.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnimatedObstacle {
+    Off,
+    /// A slab rising from the floor and sinking back.
+    Platform,
+    /// A bar spinning about the vertical, sweeping across the floor.
+    Paddle,
+    /// A wall sliding back and forth across the room.
+    Wall,
+}
+
+impl AnimatedObstacle {
+    pub const ALL: [AnimatedObstacle; 4] = [
+        AnimatedObstacle::Off,
+        AnimatedObstacle::Platform,
+        AnimatedObstacle::Paddle,
+        AnimatedObstacle::Wall,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimatedObstacle::Off => "Off",
+            AnimatedObstacle::Platform => "Platform",
+            AnimatedObstacle::Paddle => "Paddle",
+            AnimatedObstacle::Wall => "Wall",
+        }
+    }
+}
+
+/// The structures of jointed rigidbodies the demo builds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Structure {
+    /// A wall of blocks, each joined to its neighbors.
+    Wall,
+    /// Blades hinged to a tower, spun by a motor.
+    Windmill,
+    /// Two arm segments hinged to a base, each driven to an angle by a motor.
+    RobotArm,
+}
+
+impl Structure {
+    pub const ALL: [Structure; 3] = [Structure::Wall, Structure::Windmill, Structure::RobotArm];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Structure::Wall => "Wall",
+            Structure::Windmill => "Windmill",
+            Structure::RobotArm => "Robot Arm",
+        }
+    }
+}
+
 pub struct RigidBodyUi {
     sim_config: Config,
     impulse: Vector3<f32>,
     impulse_position: Vector3<f32>,
     free_impulse: bool,
+    torque_axis: Axis,
+    torque_profile: Profile,
+    torque_magnitude: f32,
+    torque_duration: f32,
+    one_shot_torque: bool,
+    show_gizmos: Vec<bool>,
+    drop_rigidbodies: bool,
+    drop_interval: f32,
+    animated_obstacle: AnimatedObstacle,
+    structure: Structure,
+    structure_joint_kind: JointKind,
+    build_structure: bool,
+    inverse_kinematics: bool,
+    ik_target: Vector3<f32>,
+    joints: Vec<JointSummary>,
+    joint_edit: Option<JointEdit>,
+    impulse_analysis: ImpulseAnalysis,
+    console: Console,
+    undo_action: Option<UndoAction>,
+    can_undo: bool,
+    can_redo: bool,
+    /// Whether the user's dragging a slider, so that its changes to the config are coalesced.
+    editing: bool,
+    /// The simulation's dt, while it's adapting it.
+    adapted_dt: f32,
 }
 
 impl Ui for RigidBodyUi {
     fn ui(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Config").show(&ctx, |ui| {
-            egui::ComboBox::from_label("Integration")
+        egui::Window::new(tr("Config")).show(&ctx, |ui| {
+            self.undo_action = undo::controls(ui, self.can_undo, self.can_redo);
+            egui::ComboBox::from_label(tr("Integration"))
                 .selected_text(format!("{:?}", self.sim_config.integration))
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut self.sim_config.integration, Integration::Rk4, "RK4");
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Rk4,
+                        tr("RK4"),
+                    );
                     ui.selectable_value(
                         &mut self.sim_config.integration,
                         Integration::Euler,
-                        "Euler",
+                        tr("Euler"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::VelocityVerlet,
+                        tr("Velocity Verlet"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Leapfrog,
+                        tr("Leapfrog"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Rk45,
+                        tr("RK45"),
                     );
                 });
-            ui.add(
-                Slider::new(
-                    &mut self.sim_config.dt,
-                    RigidBodyUi::SIMULATION_DT_MIN.as_secs_f32()
-                        ..=RigidBodyUi::SIMULATION_DT_MAX.as_secs_f32(),
-                )
-                .text("Simualtion dt (secs)"),
+            ui.checkbox(
+                &mut self.sim_config.implicit_gyroscopic,
+                tr("Implicit Gyroscopic Torque"),
+            );
+            let dt_range = RigidBodyUi::SIMULATION_DT_MIN.as_secs_f32()
+                ..=RigidBodyUi::SIMULATION_DT_MAX.as_secs_f32();
+            ui.add_enabled(
+                self.sim_config.get_adaptive_dt().is_none(),
+                Slider::new(&mut self.sim_config.dt, dt_range.clone())
+                    .text(tr("Simualtion dt (secs)")),
+            );
+            gui::adaptive_dt_controls(
+                ui,
+                self.sim_config.integration,
+                &mut self.sim_config.adaptive_dt,
+                self.adapted_dt,
+                dt_range,
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.x,
                     RigidBodyUi::GRAVITY_MIN..=RigidBodyUi::GRAVITY_MAX,
                 )
-                .text("Gravity X"),
+                .text(units::label("Gravity X", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.y,
                     RigidBodyUi::GRAVITY_MIN..=RigidBodyUi::GRAVITY_MAX,
                 )
-                .text("Gravity Y"),
+                .text(units::label("Gravity Y", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.z,
                     RigidBodyUi::GRAVITY_MIN..=RigidBodyUi::GRAVITY_MAX,
                 )
-                .text("Gravity Z"),
+                .text(units::label("Gravity Z", units::ACCELERATION)),
             );
+            if let Some(preset) = gui::gravity_presets(ui, self.sim_config.gravity) {
+                self.sim_config.gravity = preset.vector();
+            }
             ui.add(
                 Slider::new(
                     &mut self.sim_config.coefficient_of_restitution,
                     RigidBodyUi::COEFFICIENT_OF_RESTITUTION_MIN
                         ..=RigidBodyUi::COEFFICIENT_OF_RESTITUTION_MAX,
                 )
-                .text("Coefficient of Restitution"),
+                .text(tr("Coefficient of Restitution")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.coefficient_of_friction,
+                    RigidBodyUi::COEFFICIENT_OF_FRICTION_MIN
+                        ..=RigidBodyUi::COEFFICIENT_OF_FRICTION_MAX,
+                )
+                .text(tr("Coefficient of Friction")),
+            );
+            self.anisotropic_friction_controls(ui);
+            ui.add(
+                Slider::new(&mut self.sim_config.position_correction, 0.0..=1.0)
+                    .text(tr("Position Correction")),
+            );
+            gui::collision_mask(ui, "Collides With", &mut self.sim_config.collision_mask);
+            gui::planar_controls(ui, &mut self.sim_config.planar, RigidBodyUi::PLANAR_DEFAULT);
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.mouse_spring_stiffness,
+                    RigidBodyUi::MOUSE_SPRING_STIFFNESS_MIN
+                        ..=RigidBodyUi::MOUSE_SPRING_STIFFNESS_MAX,
+                )
+                .text(units::label(
+                    "Mouse Spring Stiffness (Right Click)",
+                    units::STIFFNESS,
+                )),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.torque.x,
                     RigidBodyUi::TORQUE_MIN..=RigidBodyUi::TORQUE_MAX,
                 )
-                .text("Torque X"),
+                .text(units::label("Torque X", units::TORQUE)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.torque.y,
                     RigidBodyUi::TORQUE_MIN..=RigidBodyUi::TORQUE_MAX,
                 )
-                .text("Torque Y"),
+                .text(units::label("Torque Y", units::TORQUE)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.torque.z,
                     RigidBodyUi::TORQUE_MIN..=RigidBodyUi::TORQUE_MAX,
                 )
-                .text("Torque Z"),
+                .text(units::label("Torque Z", units::TORQUE)),
+            );
+            ui.separator();
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.wind.x,
+                    RigidBodyUi::WIND_MIN..=RigidBodyUi::WIND_MAX,
+                )
+                .text(units::label("Wind X", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.wind.y,
+                    RigidBodyUi::WIND_MIN..=RigidBodyUi::WIND_MAX,
+                )
+                .text(units::label("Wind Y", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.wind.z,
+                    RigidBodyUi::WIND_MIN..=RigidBodyUi::WIND_MAX,
+                )
+                .text(units::label("Wind Z", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.linear_drag_viscous,
+                    RigidBodyUi::DRAG_MIN..=RigidBodyUi::DRAG_MAX,
+                )
+                .text(tr("Linear Drag (Viscous)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.linear_drag_quadratic,
+                    RigidBodyUi::DRAG_MIN..=RigidBodyUi::DRAG_MAX,
+                )
+                .text(tr("Linear Drag (Quadratic)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.angular_drag_viscous,
+                    RigidBodyUi::DRAG_MIN..=RigidBodyUi::DRAG_MAX,
+                )
+                .text(tr("Angular Drag (Viscous)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.angular_drag_quadratic,
+                    RigidBodyUi::DRAG_MIN..=RigidBodyUi::DRAG_MAX,
+                )
+                .text(tr("Angular Drag (Quadratic)")),
             );
             ui.separator();
             ui.add(
@@ -89,79 +310,488 @@ impl Ui for RigidBodyUi {
                     &mut self.impulse.x,
                     RigidBodyUi::IMPULSE_MIN..=RigidBodyUi::IMPULSE_MAX,
                 )
-                .text("Impulse X"),
+                .text(units::label("Impulse X", units::IMPULSE)),
             );
             ui.add(
                 Slider::new(
                     &mut self.impulse.y,
                     RigidBodyUi::IMPULSE_MIN..=RigidBodyUi::IMPULSE_MAX,
                 )
-                .text("Impulse Y"),
+                .text(units::label("Impulse Y", units::IMPULSE)),
             );
             ui.add(
                 Slider::new(
                     &mut self.impulse.z,
                     RigidBodyUi::IMPULSE_MIN..=RigidBodyUi::IMPULSE_MAX,
                 )
-                .text("Impulse Z"),
+                .text(units::label("Impulse Z", units::IMPULSE)),
             );
             ui.add(
                 Slider::new(
                     &mut self.impulse_position.x,
                     RigidBodyUi::IMPULSE_POSITION_MIN..=RigidBodyUi::IMPULSE_POSITION_MAX,
                 )
-                .text("Impulse Position X"),
+                .text(units::label("Impulse Position X", units::LENGTH)),
             );
             ui.add(
                 Slider::new(
                     &mut self.impulse_position.y,
                     RigidBodyUi::IMPULSE_POSITION_MIN..=RigidBodyUi::IMPULSE_POSITION_MAX,
                 )
-                .text("Impulse Position Y"),
+                .text(units::label("Impulse Position Y", units::LENGTH)),
             );
             ui.add(
                 Slider::new(
                     &mut self.impulse_position.z,
                     RigidBodyUi::IMPULSE_POSITION_MIN..=RigidBodyUi::IMPULSE_POSITION_MAX,
                 )
-                .text("Impulse Position Z"),
+                .text(units::label("Impulse Position Z", units::LENGTH)),
+            );
+            self.free_impulse = ui.button(tr("Free Impulse")).clicked();
+            ui.separator();
+            egui::ComboBox::from_label(tr("Torque Axis"))
+                .selected_text(tr(self.torque_axis.name()))
+                .show_ui(ui, |ui| {
+                    for axis in Axis::ALL {
+                        ui.selectable_value(&mut self.torque_axis, axis, tr(axis.name()));
+                    }
+                });
+            egui::ComboBox::from_label(tr("Torque Profile"))
+                .selected_text(tr(self.torque_profile.name()))
+                .show_ui(ui, |ui| {
+                    for profile in Profile::ALL {
+                        ui.selectable_value(&mut self.torque_profile, profile, tr(profile.name()));
+                    }
+                });
+            ui.add(
+                Slider::new(
+                    &mut self.torque_magnitude,
+                    RigidBodyUi::TORQUE_MIN..=RigidBodyUi::TORQUE_MAX,
+                )
+                .text(units::label("Torque Magnitude", units::TORQUE)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.torque_duration,
+                    RigidBodyUi::TORQUE_DURATION_MIN..=RigidBodyUi::TORQUE_DURATION_MAX,
+                )
+                .text(units::label("Torque Duration", units::TIME)),
+            );
+            self.one_shot_torque = ui.button(tr("Apply Torque")).clicked();
+            ui.separator();
+            ui.checkbox(&mut self.drop_rigidbodies, tr("Drop Rigidbodies"));
+            ui.add(
+                Slider::new(
+                    &mut self.drop_interval,
+                    RigidBodyUi::DROP_INTERVAL_MIN..=RigidBodyUi::DROP_INTERVAL_MAX,
+                )
+                .text(units::label("Drop Interval", units::TIME)),
+            );
+            ui.separator();
+            egui::ComboBox::from_label(tr("Animated Obstacle"))
+                .selected_text(tr(self.animated_obstacle.name()))
+                .show_ui(ui, |ui| {
+                    for obstacle in AnimatedObstacle::ALL {
+                        ui.selectable_value(
+                            &mut self.animated_obstacle,
+                            obstacle,
+                            tr(obstacle.name()),
+                        );
+                    }
+                });
+            ui.separator();
+            for (index, show_gizmos) in self.show_gizmos.iter_mut().enumerate() {
+                ui.checkbox(show_gizmos, tr_format("Show Gizmos (Body {})", &[&index]));
+            }
+            ui.separator();
+            self.impulse_analysis.checkbox(ui);
+            self.console.checkbox(ui);
+        });
+        self.impulse_analysis.window(ctx);
+        self.console.window(ctx);
+
+        self.joint_edit = None;
+        egui::Window::new(tr("Joints")).show(ctx, |ui| {
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.joint_stiffness,
+                    RigidBodyUi::JOINT_STIFFNESS_MIN..=RigidBodyUi::JOINT_STIFFNESS_MAX,
+                )
+                .text(units::label("Joint Stiffness", units::STIFFNESS)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.joint_angular_stiffness,
+                    RigidBodyUi::JOINT_ANGULAR_STIFFNESS_MIN
+                        ..=RigidBodyUi::JOINT_ANGULAR_STIFFNESS_MAX,
+                )
+                .text(units::label(
+                    "Joint Angular Stiffness",
+                    units::ANGULAR_STIFFNESS,
+                )),
+            );
+            ui.checkbox(
+                &mut self.sim_config.breakable_joints,
+                tr("Breakable Joints"),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.joint_break_force,
+                    RigidBodyUi::JOINT_BREAK_FORCE_MIN..=RigidBodyUi::JOINT_BREAK_FORCE_MAX,
+                )
+                .text(units::label("Break Force", units::FORCE)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.joint_break_torque,
+                    RigidBodyUi::JOINT_BREAK_TORQUE_MIN..=RigidBodyUi::JOINT_BREAK_TORQUE_MAX,
+                )
+                .text(units::label("Break Torque", units::TORQUE)),
             );
-            self.free_impulse = ui.button("Free Impulse").clicked();
             ui.separator();
+            egui::ComboBox::from_label(tr("Structure"))
+                .selected_text(tr(self.structure.name()))
+                .show_ui(ui, |ui| {
+                    for structure in Structure::ALL {
+                        ui.selectable_value(&mut self.structure, structure, tr(structure.name()));
+                    }
+                });
+            if self.structure == Structure::Wall {
+                egui::ComboBox::from_label(tr("Structure Joints"))
+                    .selected_text(tr(self.structure_joint_kind.name()))
+                    .show_ui(ui, |ui| {
+                        for kind in JointKind::ALL {
+                            ui.selectable_value(
+                                &mut self.structure_joint_kind,
+                                kind,
+                                tr(kind.name()),
+                            );
+                        }
+                    });
+            }
+            self.build_structure = ui.button(tr("Build Structure")).clicked();
+            ui.checkbox(
+                &mut self.inverse_kinematics,
+                tr("Inverse Kinematics (Robot Arm)"),
+            );
+            if self.inverse_kinematics {
+                ui.add(
+                    Slider::new(
+                        &mut self.ik_target.x,
+                        RigidBodyUi::IK_TARGET_MIN..=RigidBodyUi::IK_TARGET_MAX,
+                    )
+                    .text(units::label("Target X", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.ik_target.y,
+                        RigidBodyUi::IK_TARGET_MIN..=RigidBodyUi::IK_TARGET_MAX,
+                    )
+                    .text(units::label("Target Y", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.ik_target.z,
+                        RigidBodyUi::IK_TARGET_MIN..=RigidBodyUi::IK_TARGET_MAX,
+                    )
+                    .text(units::label("Target Z", units::LENGTH)),
+                );
+            }
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(RigidBodyUi::JOINT_LIST_HEIGHT)
+                .show(ui, |ui| {
+                    for joint in self.joints.iter_mut() {
+                        ui.push_id(joint.handle, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(tr_format(
+                                    "{} (Body {} - Body {})",
+                                    &[&tr(joint.kind.name()), &joint.bodies.0, &joint.bodies.1],
+                                ));
+                                if ui.button(tr("Break")).clicked() {
+                                    self.joint_edit = Some(JointEdit::Break(joint.handle));
+                                }
+                                if ui.button(tr("Re-anchor")).clicked() {
+                                    self.joint_edit = Some(JointEdit::Reanchor(joint.handle));
+                                }
+                            });
+                            if joint.kind == JointKind::Hinge
+                                && RigidBodyUi::motor(ui, joint.angle, &mut joint.motor)
+                            {
+                                self.joint_edit =
+                                    Some(JointEdit::SetMotor(joint.handle, joint.motor));
+                            }
+                        });
+                    }
+                });
         });
+        self.editing = ctx.is_using_pointer();
     }
 }
 
 impl RigidBodyUi {
+    /// Shows a checkbox making friction anisotropic, and the grain's controls while it's checked.
+    fn anisotropic_friction_controls(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.sim_config.anisotropic_friction.is_some();
+        if ui
+            .checkbox(&mut enabled, tr("Anisotropic Friction"))
+            .changed()
+        {
+            self.sim_config.anisotropic_friction = enabled.then(AnisotropicFriction::default);
+        }
+        let Some(anisotropy) = &mut self.sim_config.anisotropic_friction else {
+            return;
+        };
+        ui.add(
+            Slider::new(
+                &mut anisotropy.friction_along_grain,
+                RigidBodyUi::COEFFICIENT_OF_FRICTION_MIN..=RigidBodyUi::COEFFICIENT_OF_FRICTION_MAX,
+            )
+            .text(tr("Friction Along Grain")),
+        );
+        ui.add(Slider::new(&mut anisotropy.grain.x, -1.0..=1.0).text(tr("Grain X")));
+        ui.add(Slider::new(&mut anisotropy.grain.y, -1.0..=1.0).text(tr("Grain Y")));
+        ui.add(Slider::new(&mut anisotropy.grain.z, -1.0..=1.0).text(tr("Grain Z")));
+    }
+
+    /// Shows the hinge's angle and controls for its motor, returning whether the motor was changed.
+    fn motor(ui: &mut egui::Ui, angle: f32, motor: &mut Motor) -> bool {
+        let mut changed = false;
+        ui.label(format!(
+            "{}: {:.2}",
+            units::label("Angle", units::ANGLE),
+            angle
+        ));
+        egui::ComboBox::from_label(tr("Motor"))
+            .selected_text(tr(motor.mode.name()))
+            .show_ui(ui, |ui| {
+                for mode in MotorMode::ALL {
+                    changed |= ui
+                        .selectable_value(&mut motor.mode, mode, tr(mode.name()))
+                        .changed();
+                }
+            });
+        match motor.mode {
+            MotorMode::Off => {}
+            MotorMode::Angle => {
+                changed |= ui
+                    .add(
+                        Slider::new(
+                            &mut motor.target,
+                            -std::f32::consts::PI..=std::f32::consts::PI,
+                        )
+                        .text(units::label("Target Angle", units::ANGLE)),
+                    )
+                    .changed();
+            }
+            MotorMode::Velocity => {
+                changed |= ui
+                    .add(
+                        Slider::new(
+                            &mut motor.target,
+                            -RigidBodyUi::MOTOR_SPEED_MAX..=RigidBodyUi::MOTOR_SPEED_MAX,
+                        )
+                        .text(units::label("Target Speed", units::ANGULAR_VELOCITY)),
+                    )
+                    .changed();
+            }
+        }
+        if motor.mode != MotorMode::Off {
+            changed |= ui
+                .add(
+                    Slider::new(
+                        &mut motor.max_torque,
+                        RigidBodyUi::MOTOR_TORQUE_MIN..=RigidBodyUi::MOTOR_TORQUE_MAX,
+                    )
+                    .text(units::label("Max Torque", units::TORQUE)),
+                )
+                .changed();
+        }
+        changed
+    }
+
     const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(10);
     const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(100);
 
-    const GRAVITY_MIN: f32 = -2.0;
-    const GRAVITY_MAX: f32 = 2.0;
+    const GRAVITY_MIN: f32 = -20.0;
+    const GRAVITY_MAX: f32 = 20.0;
 
     const COEFFICIENT_OF_RESTITUTION_MIN: f32 = 0.0;
     const COEFFICIENT_OF_RESTITUTION_MAX: f32 = 1.0;
 
+    const COEFFICIENT_OF_FRICTION_MIN: f32 = 0.0;
+    const COEFFICIENT_OF_FRICTION_MAX: f32 = 1.0;
+
     const TORQUE_MIN: f32 = -1.0;
     const TORQUE_MAX: f32 = 1.0;
 
+    const MOUSE_SPRING_STIFFNESS_MIN: f32 = 1.0;
+    const MOUSE_SPRING_STIFFNESS_MAX: f32 = 200.0;
+
+    const WIND_MIN: f32 = -20.0;
+    const WIND_MAX: f32 = 20.0;
+
+    const DRAG_MIN: f32 = 0.0;
+    const DRAG_MAX: f32 = 2.0;
+
+    const TORQUE_DURATION_MIN: f32 = 0.01;
+    const TORQUE_DURATION_MAX: f32 = 2.0;
+
+    const DROP_INTERVAL_MIN: f32 = 0.1;
+    const DROP_INTERVAL_MAX: f32 = 5.0;
+
     const IMPULSE_MIN: f32 = -1.0;
     const IMPULSE_MAX: f32 = 1.0;
 
     const IMPULSE_POSITION_MIN: f32 = -0.5;
     const IMPULSE_POSITION_MAX: f32 = 0.5;
 
+    const JOINT_STIFFNESS_MIN: f32 = 100.0;
+    const JOINT_STIFFNESS_MAX: f32 = 5000.0;
+
+    const JOINT_ANGULAR_STIFFNESS_MIN: f32 = 0.1;
+    const JOINT_ANGULAR_STIFFNESS_MAX: f32 = 20.0;
+
+    const JOINT_BREAK_FORCE_MIN: f32 = 1.0;
+    const JOINT_BREAK_FORCE_MAX: f32 = 200.0;
+
+    const JOINT_BREAK_TORQUE_MIN: f32 = 0.1;
+    const JOINT_BREAK_TORQUE_MAX: f32 = 10.0;
+
+    const JOINT_LIST_HEIGHT: f32 = 300.0;
+
+    const MOTOR_SPEED_MAX: f32 = 10.0;
+
+    // The inside of the room.
+    const IK_TARGET_MIN: f32 = -1.0;
+    const IK_TARGET_MAX: f32 = 1.0;
+
+    const MOTOR_TORQUE_MIN: f32 = 0.0;
+    const MOTOR_TORQUE_MAX: f32 = 5.0;
+
+    /// Through the room's center, facing the default camera.
+    const PLANAR_DEFAULT: PlanarConstraint = PlanarConstraint {
+        axis: PlanarAxis::Z,
+        offset: 0.0,
+    };
+
     pub fn new() -> RigidBodyUi {
         RigidBodyUi {
             sim_config: Config::default(),
             impulse: Vector3::zero(),
             impulse_position: Vector3::zero(),
             free_impulse: false,
+            torque_axis: Axis::WorldY,
+            torque_profile: Profile::Constant,
+            torque_magnitude: 1.0,
+            torque_duration: 0.1,
+            one_shot_torque: false,
+            show_gizmos: Vec::new(),
+            drop_rigidbodies: false,
+            drop_interval: 1.0,
+            animated_obstacle: AnimatedObstacle::Off,
+            structure: Structure::Wall,
+            structure_joint_kind: JointKind::Weld,
+            build_structure: false,
+            inverse_kinematics: false,
+            // In reach of the robot arm, in front of its base.
+            ik_target: Vector3::new(0.4, -0.4, -0.7),
+            joints: Vec::new(),
+            joint_edit: None,
+            impulse_analysis: ImpulseAnalysis::new(),
+            console: Console::new(),
+            undo_action: None,
+            can_undo: false,
+            can_redo: false,
+            editing: false,
+            adapted_dt: 0.0,
         }
     }
 
-    pub fn get_gui_state_mut(&mut self) -> &Config {
-        &self.sim_config
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        self.adapted_dt = simulation.get_config().dt;
+
+        // The impulse and torque controls act on the oldest live rigidbody.
+        let first_rigidbody = simulation
+            .get_rigidbodies()
+            .iter()
+            .next()
+            .map(|(handle, _)| handle);
+        if let Some(first_rigidbody) = first_rigidbody {
+            if let Some((impulse, impulse_position)) = self.get_free_impulse() {
+                simulation.get_rigidbodies_mut()[first_rigidbody]
+                    .apply_impulse(impulse, impulse_position);
+            }
+
+            if let Some((axis, profile, magnitude, duration)) = self.get_one_shot_torque() {
+                let rotation = simulation.get_rigidbodies()[first_rigidbody].get_rotation_matrix();
+                let start = simulation.get_time();
+                simulation.schedule_load(
+                    first_rigidbody,
+                    ScheduledLoad {
+                        kind: LoadKind::Torque,
+                        curve: profile.curve(axis.direction(&rotation) * magnitude, duration),
+                        start,
+                        duration: Some(duration),
+                    },
+                );
+            }
+        }
+
+        match self.get_joint_edit() {
+            Some(JointEdit::Break(handle)) => {
+                simulation.break_joint(handle);
+            }
+            Some(JointEdit::Reanchor(handle)) => simulation.reanchor_joint(handle),
+            Some(JointEdit::SetMotor(handle, motor)) => simulation.set_joint_motor(handle, motor),
+            None => {}
+        }
+
+        let rigidbodies = simulation.get_rigidbodies();
+        self.set_rigidbody_count(rigidbodies.len());
+        self.set_impulses(simulation.get_impulses());
+        // Bodies are listed by their position among the live rigidbodies, as with their gizmos.
+        let rigidbody_handles = rigidbodies
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        let rigidbody_index = |handle| {
+            rigidbody_handles
+                .iter()
+                .position(|rigidbody| *rigidbody == handle)
+                .unwrap_or_default()
+        };
+        self.set_joints(
+            simulation
+                .get_joints()
+                .iter()
+                .map(|(handle, joint)| {
+                    let (a, b) = joint.get_bodies();
+                    JointSummary {
+                        handle,
+                        kind: joint.get_kind(),
+                        bodies: (rigidbody_index(a), rigidbody_index(b)),
+                        angle: joint.get_angle((&rigidbodies[a], &rigidbodies[b])),
+                        motor: joint.get_motor(),
+                    }
+                })
+                .collect(),
+        );
+    }
+
+    /// The config the simulation is synced from, for the console to tweak.
+    pub fn get_config_mut(&mut self) -> &mut Config {
+        &mut self.sim_config
+    }
+
+    /// The lines the user has entered into the console since this was last called.
+    pub fn take_console_lines(&mut self) -> Vec<String> {
+        self.console.take_submitted()
+    }
+
+    pub fn print_to_console(&mut self, text: &str) {
+        self.console.print(text);
     }
 
     /// Returns None if we should not impart a free impulse this frame.
@@ -174,4 +804,108 @@ impl RigidBodyUi {
             None
         }
     }
+
+    /// Returns Some axis, profile, peak magnitude, and duration of a torque to apply to the rigidbody
+    /// if the user has clicked to apply one this frame.
+    pub fn get_one_shot_torque(&self) -> Option<(Axis, Profile, f32, f32)> {
+        if self.one_shot_torque {
+            Some((
+                self.torque_axis,
+                self.torque_profile,
+                self.torque_magnitude,
+                self.torque_duration,
+            ))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_animated_obstacle(&self) -> AnimatedObstacle {
+        self.animated_obstacle
+    }
+
+    /// How often to drop a new rigidbody, or None if the user hasn't enabled dropping.
+    pub fn get_drop_interval(&self) -> Option<std::time::Duration> {
+        if self.drop_rigidbodies {
+            Some(std::time::Duration::from_secs_f32(self.drop_interval))
+        } else {
+            None
+        }
+    }
+
+    /// Resizes the per-body options to match the number of rigidbodies in the simulation.
+    pub fn set_rigidbody_count(&mut self, count: usize) {
+        self.show_gizmos.resize(count, false);
+    }
+
+    /// Returns Some structure, and the kind of joint to build a wall from, if the user has clicked to build one this frame.
+    pub fn get_build_structure(&self) -> Option<(Structure, JointKind)> {
+        if self.build_structure {
+            Some((self.structure, self.structure_joint_kind))
+        } else {
+            None
+        }
+    }
+
+    /// Where the robot arm should reach, or None if the user hasn't enabled inverse kinematics.
+    pub fn get_ik_target(&self) -> Option<Vector3<f32>> {
+        if self.inverse_kinematics {
+            Some(self.ik_target)
+        } else {
+            None
+        }
+    }
+
+    /// The joint the user has chosen to break, re-anchor, or change the motor of this frame, if any.
+    pub fn get_joint_edit(&self) -> Option<JointEdit> {
+        self.joint_edit
+    }
+
+    /// Whether the user's asked to undo or redo an edit this frame.
+    pub fn get_undo_action(&self) -> Option<UndoAction> {
+        self.undo_action
+    }
+
+    /// Whether there are edits to undo and undone edits to redo, enabling their buttons.
+    pub fn set_undo_available(&mut self, can_undo: bool, can_redo: bool) {
+        self.can_undo = can_undo;
+        self.can_redo = can_redo;
+    }
+
+    /// Whether the user's in the middle of editing the config, e.g. dragging a slider.
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    /// Sets the contact impulses of the recorded rigidbody, for analysis.
+    pub fn set_impulses(&mut self, impulses: &ImpulseRecorder) {
+        self.impulse_analysis.update(Some(impulses));
+    }
+
+    /// Sets the joints listed in the joint editor.
+    pub fn set_joints(&mut self, joints: Vec<JointSummary>) {
+        self.joints = joints;
+    }
+
+    /// Whether to draw the center of mass, principal axes, and angular velocity of the rigidbody.
+    pub fn get_show_gizmos(&self, rigidbody_index: usize) -> bool {
+        self.show_gizmos
+            .get(rigidbody_index)
+            .copied()
+            .unwrap_or(false)
+    }
+}
+
+impl ConfigUi for RigidBodyUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for RigidBodyUi {
+    fn default() -> Self {
+        RigidBodyUi::new()
+    }
 }
@@ -0,0 +1,143 @@
+use crate::gui::{ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::slosh::{Config, Simulation};
+use crate::simulation::units;
+
+use egui::plot::{Line, Plot, Value, Values};
+use egui::Slider;
+
+pub struct SloshUi {
+    sim_config: Config,
+    reset: bool,
+    /// The simulation's latest measurements, for display.
+    natural_frequency: f32,
+    wall_height: f32,
+    height_history: Vec<(f32, f32)>,
+}
+
+impl Ui for SloshUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.dt,
+                    SloshUi::SIMULATION_DT_MIN.as_secs_f32()
+                        ..=SloshUi::SIMULATION_DT_MAX.as_secs_f32(),
+                )
+                .text(tr("Simualtion dt (secs)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.amplitude,
+                    SloshUi::AMPLITUDE_MIN..=SloshUi::AMPLITUDE_MAX,
+                )
+                .text(units::label("Amplitude", units::LENGTH)),
+            );
+            ui.horizontal(|ui| {
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.frequency,
+                        SloshUi::FREQUENCY_MIN..=SloshUi::FREQUENCY_MAX,
+                    )
+                    .text(units::label("Frequency", units::FREQUENCY)),
+                );
+                if ui.button(tr("Resonate")).clicked() {
+                    self.sim_config.frequency = self.natural_frequency;
+                }
+            });
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.depth,
+                    SloshUi::DEPTH_MIN..=SloshUi::DEPTH_MAX,
+                )
+                .text(units::label("Depth", units::LENGTH)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.damping,
+                    SloshUi::DAMPING_MIN..=SloshUi::DAMPING_MAX,
+                )
+                .text(tr("Damping (per second)")),
+            );
+            self.reset = ui.button(tr("Reset")).clicked();
+
+            ui.separator();
+            ui.label(tr_format(
+                "Natural Frequency: {} {}",
+                &[&format!("{:.3}", self.natural_frequency), &units::FREQUENCY],
+            ));
+            ui.label(tr_format(
+                "Height at Wall: {} {}",
+                &[&format!("{:.3}", self.wall_height), &units::LENGTH],
+            ));
+            let heights = Values::from_values_iter(
+                self.height_history
+                    .iter()
+                    .map(|(time, height)| Value::new(*time, *height)),
+            );
+            Plot::new("height at wall")
+                .height(SloshUi::PLOT_HEIGHT)
+                .include_y(0.0)
+                .allow_drag(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(heights).name(tr("Height at Wall")))
+                });
+        });
+    }
+}
+
+impl SloshUi {
+    const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(10);
+    const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(500);
+
+    const AMPLITUDE_MIN: f32 = 0.0;
+    const AMPLITUDE_MAX: f32 = 0.1;
+
+    const FREQUENCY_MIN: f32 = 0.1;
+    const FREQUENCY_MAX: f32 = 2.0;
+
+    const DEPTH_MIN: f32 = 0.05;
+    const DEPTH_MAX: f32 = 0.4;
+
+    const DAMPING_MIN: f32 = 0.0;
+    const DAMPING_MAX: f32 = 2.0;
+
+    const PLOT_HEIGHT: f32 = 150.0;
+
+    pub fn new() -> SloshUi {
+        SloshUi {
+            sim_config: Config::default(),
+            reset: false,
+            natural_frequency: 0.0,
+            wall_height: 0.0,
+            height_history: Vec::new(),
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        if self.reset {
+            simulation.reset();
+        }
+        self.natural_frequency = simulation.natural_frequency();
+        self.wall_height = simulation.get_wall_height();
+        self.height_history.clear();
+        self.height_history
+            .extend(simulation.get_height_history().iter().copied());
+    }
+}
+
+impl ConfigUi for SloshUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for SloshUi {
+    fn default() -> Self {
+        SloshUi::new()
+    }
+}
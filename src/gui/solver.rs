@@ -0,0 +1,116 @@
+use crate::localization::{tr, tr_format};
+use crate::simulation::solver::{SolverBudget, SolverStats};
+use crate::simulation::units;
+
+use egui::plot::{Line, Plot, Points, Value, Values};
+use egui::Slider;
+use std::time::Duration;
+
+/// A window plotting how an iterative solver converged over the last step, its residual after each of its
+/// iterations, shown while its checkbox is checked.
+pub struct SolverConvergence {
+    name: &'static str,
+    show: bool,
+    stats: SolverStats,
+}
+
+impl SolverConvergence {
+    const ITERATIONS_MAX: usize = 64;
+    const TOLERANCE_MAX: f32 = 0.01;
+    const TIME_BUDGET_DEFAULT: Duration = Duration::from_micros(100);
+    const TIME_BUDGET_MAX_MICROS: f32 = 2000.0;
+    const PLOT_HEIGHT: f32 = 120.0;
+    /// Residuals are plotted by their logarithm, with those at or below this drawn at it.
+    const RESIDUAL_FLOOR: f32 = 1e-9;
+
+    /// The name titles the window, e.g. "Contact Solver".
+    pub fn new(name: &'static str) -> SolverConvergence {
+        SolverConvergence {
+            name,
+            show: false,
+            stats: SolverStats::default(),
+        }
+    }
+
+    /// Shows the controls for the solver's iterations, tolerance, and time budget.
+    pub fn budget_controls(ui: &mut egui::Ui, budget: &mut SolverBudget) {
+        ui.add(
+            Slider::new(
+                &mut budget.max_iterations,
+                1..=SolverConvergence::ITERATIONS_MAX,
+            )
+            .text(tr("Solver Iterations")),
+        );
+        ui.add(
+            Slider::new(
+                &mut budget.tolerance,
+                0.0..=SolverConvergence::TOLERANCE_MAX,
+            )
+            .logarithmic(true)
+            .text(units::label("Solver Tolerance", units::IMPULSE)),
+        );
+        let mut enabled = budget.time_budget.is_some();
+        if ui.checkbox(&mut enabled, tr("Time Budget")).changed() {
+            budget.time_budget = enabled.then_some(SolverConvergence::TIME_BUDGET_DEFAULT);
+        }
+        if let Some(time_budget) = &mut budget.time_budget {
+            let mut micros = time_budget.as_secs_f32() * 1e6;
+            ui.add(
+                Slider::new(&mut micros, 1.0..=SolverConvergence::TIME_BUDGET_MAX_MICROS)
+                    .logarithmic(true)
+                    .text(units::label("Time Budget", "µs")),
+            );
+            *time_budget = Duration::from_secs_f32(micros / 1e6);
+        }
+    }
+
+    pub fn checkbox(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show, tr("Solver Convergence"));
+    }
+
+    /// Copies the solver's stats for display while the window is shown.
+    pub fn update(&mut self, stats: &SolverStats) {
+        if self.show {
+            self.stats.clone_from(stats);
+        }
+    }
+
+    pub fn window(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr(self.name))
+            .open(&mut self.show)
+            .show(ctx, |ui| {
+                ui.label(tr_format(
+                    "{} iterations in {} µs, stopped by: {}",
+                    &[
+                        &self.stats.get_iterations(),
+                        &format!("{:.0}", self.stats.elapsed.as_secs_f32() * 1e6),
+                        &tr(self.stats.stop.name()),
+                    ],
+                ));
+                if let Some(residual) = self.stats.get_final_residual() {
+                    ui.label(tr_format(
+                        "Final residual: {} N·s",
+                        &[&format!("{:.2e}", residual)],
+                    ));
+                }
+                ui.label(tr("Residual per iteration, log₁₀ N·s"));
+                let log_residuals = || {
+                    Values::from_values_iter(self.stats.residuals.iter().enumerate().map(
+                        |(i, residual)| {
+                            Value::new(
+                                (i + 1) as f32,
+                                residual.max(SolverConvergence::RESIDUAL_FLOOR).log10(),
+                            )
+                        },
+                    ))
+                };
+                Plot::new(self.name)
+                    .height(SolverConvergence::PLOT_HEIGHT)
+                    .allow_drag(false)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(log_residuals()));
+                        plot_ui.points(Points::new(log_residuals()).radius(2.0));
+                    });
+            });
+    }
+}
@@ -0,0 +1,135 @@
+use crate::graphics::palette::Colormap;
+use crate::localization::{tr, tr_format};
+use crate::simulation::spectrum::{ImpulseRecorder, Spectrogram};
+use crate::simulation::units;
+
+use egui::plot::{Line, Plot, Value, Values};
+
+/// A window plotting the contact impulses recorded on an object, and their spectrogram,
+/// shown while its checkbox is checked.
+pub struct ImpulseAnalysis {
+    show: bool,
+    samples: Vec<f32>,
+    sample_rate: f32,
+    spectrogram: Spectrogram,
+}
+
+impl ImpulseAnalysis {
+    /// 256 samples, so that a few bounces fall within each window at the recorders' sample rates.
+    const WINDOW_SIZE: usize = 256;
+    const HOP: usize = 32;
+
+    const PLOT_HEIGHT: f32 = 100.0;
+    const SPECTROGRAM_HEIGHT: f32 = 160.0;
+    /// Magnitudes this far below the loudest are drawn at the bottom of the colormap.
+    const DYNAMIC_RANGE_DB: f32 = 40.0;
+
+    pub fn new() -> ImpulseAnalysis {
+        ImpulseAnalysis {
+            show: false,
+            samples: Vec::new(),
+            sample_rate: 1.0,
+            spectrogram: Spectrogram::default(),
+        }
+    }
+
+    pub fn checkbox(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show, tr("Impulse Spectrogram"));
+    }
+
+    /// Copies the recording for display, transforming it only while the window is shown.
+    pub fn update(&mut self, recorder: Option<&ImpulseRecorder>) {
+        match recorder {
+            Some(recorder) if self.show => {
+                self.samples.clear();
+                self.samples.extend_from_slice(recorder.get_samples());
+                self.sample_rate = recorder.get_sample_rate();
+                self.spectrogram =
+                    recorder.spectrogram(ImpulseAnalysis::WINDOW_SIZE, ImpulseAnalysis::HOP);
+            }
+            Some(_) => {}
+            None => {
+                self.samples.clear();
+                self.spectrogram = Spectrogram::default();
+            }
+        }
+    }
+
+    pub fn window(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Impulse Spectrogram"))
+            .open(&mut self.show)
+            .show(ctx, |ui| {
+                ui.label(units::label("Contact Impulses", units::IMPULSE));
+                let sample_rate = self.sample_rate;
+                let impulses = Values::from_values_iter(
+                    self.samples
+                        .iter()
+                        .enumerate()
+                        .map(|(i, impulse)| Value::new(i as f32 / sample_rate, *impulse)),
+                );
+                Plot::new("contact impulses")
+                    .height(ImpulseAnalysis::PLOT_HEIGHT)
+                    .include_y(0.0)
+                    .allow_drag(false)
+                    .show(ui, |plot_ui| plot_ui.line(Line::new(impulses)));
+
+                ui.label(tr_format(
+                    "Spectrum over time, from 0 to {} Hz",
+                    &[&format!("{:.0}", self.sample_rate / 2.0)],
+                ));
+                draw_spectrogram(ui, &self.spectrogram);
+            });
+    }
+}
+
+impl Default for ImpulseAnalysis {
+    fn default() -> Self {
+        ImpulseAnalysis::new()
+    }
+}
+
+/// Paints each bin of each frame as a cell, from the oldest frame on the left, with low frequencies at the bottom.
+/// Magnitudes are colored in decibels below the loudest. Hovering shows the time and frequency of the cell.
+fn draw_spectrogram(ui: &mut egui::Ui, spectrogram: &Spectrogram) {
+    let size = egui::vec2(ui.available_width(), ImpulseAnalysis::SPECTROGRAM_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+    let frames = &spectrogram.frames;
+    let max = spectrogram.max_magnitude();
+    if frames.is_empty() || max <= 0.0 {
+        return;
+    }
+    let bins = frames[0].len();
+    let cell = egui::vec2(
+        rect.width() / frames.len() as f32,
+        rect.height() / bins as f32,
+    );
+    for (x, frame) in frames.iter().enumerate() {
+        for (y, magnitude) in frame.iter().enumerate() {
+            let decibels = 20.0 * (magnitude / max).max(f32::EPSILON).log10();
+            let level = 1.0 + decibels / ImpulseAnalysis::DYNAMIC_RANGE_DB;
+            let [r, g, b] = Colormap::Viridis.sample(level);
+            let min = egui::pos2(
+                rect.left() + x as f32 * cell.x,
+                rect.bottom() - (y + 1) as f32 * cell.y,
+            );
+            painter.rect_filled(
+                egui::Rect::from_min_size(min, cell),
+                0.0,
+                egui::Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8),
+            );
+        }
+    }
+    if let Some(pointer) = response.hover_pos() {
+        let frame = ((pointer.x - rect.left()) / cell.x).floor();
+        let bin = ((rect.bottom() - pointer.y) / cell.y).floor();
+        response.on_hover_text_at_pointer(tr_format(
+            "{} s, {} Hz",
+            &[
+                &format!("{:.2}", frame * spectrogram.frame_interval),
+                &format!("{:.1}", bin * spectrogram.frequency_resolution),
+            ],
+        ));
+    }
+}
@@ -1,103 +1,247 @@
-use crate::gui::Ui;
-use crate::simulation::sph::config::Config;
+use crate::gui::{self, ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::sph::{
+    config::{Config, Scenario},
+    Simulation, Timestep,
+};
 use crate::simulation::state::Integration;
+use crate::simulation::units::{self, GravityPreset};
 
 use egui::Slider;
 
 pub struct SphUi {
     sim_config: Config,
+    liquid_surface: bool,
+    color_by_phase: bool,
+    /// The simulation's current timestep, for display.
+    timestep: Option<Timestep>,
 }
 
 impl Ui for SphUi {
     fn ui(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Config").show(&ctx, |ui| {
-            egui::ComboBox::from_label("Integration")
+        egui::Window::new(tr("Config")).show(&ctx, |ui| {
+            let previous_scenario = self.sim_config.scenario;
+            egui::ComboBox::from_label(tr("Scenario"))
+                .selected_text(tr(self.sim_config.scenario.name()))
+                .show_ui(ui, |ui| {
+                    for scenario in Scenario::ALL {
+                        ui.selectable_value(
+                            &mut self.sim_config.scenario,
+                            scenario,
+                            tr(scenario.name()),
+                        );
+                    }
+                });
+            if self.sim_config.scenario != previous_scenario {
+                match self.sim_config.scenario {
+                    Scenario::SinglePhase => {}
+                    Scenario::OilAndWater => {
+                        // The phases only separate under gravity.
+                        self.sim_config.gravity = GravityPreset::Earth.vector();
+                        self.color_by_phase = true;
+                    }
+                    Scenario::Sponge | Scenario::Ball => {
+                        self.sim_config.gravity = GravityPreset::Earth.vector();
+                    }
+                }
+            }
+            egui::ComboBox::from_label(tr("Integration"))
                 .selected_text(format!("{:?}", self.sim_config.integration))
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut self.sim_config.integration, Integration::Rk4, "RK4");
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Rk4,
+                        tr("RK4"),
+                    );
                     ui.selectable_value(
                         &mut self.sim_config.integration,
                         Integration::Euler,
-                        "Euler",
+                        tr("Euler"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::VelocityVerlet,
+                        tr("Velocity Verlet"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Leapfrog,
+                        tr("Leapfrog"),
                     );
                 });
+            ui.checkbox(
+                &mut self.sim_config.adaptive_timestep,
+                tr("Adaptive Timestep (CFL)"),
+            );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.dt,
                     SphUi::SIMULATION_DT_MIN.as_secs_f32()..=SphUi::SIMULATION_DT_MAX.as_secs_f32(),
                 )
-                .text("Simualtion dt (secs)"),
+                .text(if self.sim_config.adaptive_timestep {
+                    tr("Maximum dt (secs)")
+                } else {
+                    tr("Simualtion dt (secs)")
+                }),
             );
+            if self.sim_config.adaptive_timestep {
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.cfl_number,
+                        SphUi::CFL_NUMBER_MIN..=SphUi::CFL_NUMBER_MAX,
+                    )
+                    .text(tr("CFL Number")),
+                );
+            }
+            if let Some(timestep) = self.timestep {
+                ui.label(tr_format(
+                    "Timestep: {} ms (limited by {})",
+                    &[
+                        &format!("{:.3}", timestep.dt * 1000.0),
+                        &tr(timestep.limit.name()),
+                    ],
+                ));
+            }
             ui.add(
                 Slider::new(
                     &mut self.sim_config.particle_mass,
                     SphUi::PARTICLE_MASS_MIN..=SphUi::PARTICLE_MASS_MAX,
                 )
-                .text("Particle Mass"),
+                .text(units::label("Particle Mass", units::MASS)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.kernal_max_distance,
                     SphUi::KERNAL_MAX_DIST_MIN..=SphUi::KERNAL_MAX_DIST_MAX,
                 )
-                .text("Kernal Max Dist"),
+                .text(units::label("Kernal Max Dist", units::LENGTH)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.pressure_siffness,
                     SphUi::PRESSURE_STIFFNESS_MIN..=SphUi::PRESSURE_STIFFNESS_MAX,
                 )
-                .text("Pressure Stiffness"),
+                .text(tr("Pressure Stiffness")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.reference_density,
                     SphUi::REFERENCE_DENSITY_MIN..=SphUi::REFERENCE_DENSITY_MAX,
                 )
-                .text("Reference Density"),
+                .text(units::label("Reference Density", units::DENSITY)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.kinematic_viscosity,
                     SphUi::KINEMATIC_VISCOSITY_MIN..=SphUi::KINEMATIC_VISCOSITY_MAX,
                 )
-                .text("Kinematic Viscosity"),
+                .text(units::label(
+                    "Kinematic Viscosity",
+                    units::KINEMATIC_VISCOSITY,
+                )),
             );
+            if self.sim_config.scenario == Scenario::OilAndWater {
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.oil_reference_density,
+                        SphUi::REFERENCE_DENSITY_MIN..=SphUi::REFERENCE_DENSITY_MAX,
+                    )
+                    .text(units::label("Oil Reference Density", units::DENSITY)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.oil_kinematic_viscosity,
+                        SphUi::KINEMATIC_VISCOSITY_MIN..=SphUi::KINEMATIC_VISCOSITY_MAX,
+                    )
+                    .text(units::label(
+                        "Oil Kinematic Viscosity",
+                        units::KINEMATIC_VISCOSITY,
+                    )),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.interface_tension,
+                        SphUi::INTERFACE_TENSION_MIN..=SphUi::INTERFACE_TENSION_MAX,
+                    )
+                    .text(tr("Interface Tension")),
+                );
+            }
+            if self.sim_config.scenario == Scenario::Sponge {
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.sponge_capacity,
+                        SphUi::SPONGE_CAPACITY_MIN..=SphUi::SPONGE_CAPACITY_MAX,
+                    )
+                    .text(tr("Sponge Capacity")),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.sponge_absorption_rate,
+                        SphUi::SPONGE_RATE_MIN..=SphUi::SPONGE_RATE_MAX,
+                    )
+                    .text(tr("Absorption Rate (particles/s)")),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.sponge_drip_rate,
+                        SphUi::SPONGE_RATE_MIN..=SphUi::SPONGE_RATE_MAX,
+                    )
+                    .text(tr("Drip Rate (particles/s)")),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.sponge_resistance,
+                        SphUi::SPONGE_RESISTANCE_MIN..=SphUi::SPONGE_RESISTANCE_MAX,
+                    )
+                    .text(tr("Sponge Resistance (1/s)")),
+                );
+            }
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.x,
                     SphUi::GRAVITY_MIN..=SphUi::GRAVITY_MAX,
                 )
-                .text("Gravity X"),
+                .text(units::label("Gravity X", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.y,
                     SphUi::GRAVITY_MIN..=SphUi::GRAVITY_MAX,
                 )
-                .text("Gravity Y"),
+                .text(units::label("Gravity Y", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.z,
                     SphUi::GRAVITY_MIN..=SphUi::GRAVITY_MAX,
                 )
-                .text("Gravity Z"),
+                .text(units::label("Gravity Z", units::ACCELERATION)),
+            );
+            if let Some(preset) = gui::gravity_presets(ui, self.sim_config.gravity) {
+                self.sim_config.gravity = preset.vector();
+            }
+            gui::container_controls(
+                ui,
+                &mut self.sim_config.container,
+                SphUi::CONTAINER_HALF_EXTENT_MAX,
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.coefficient_of_restitution,
                     SphUi::MIN_COEFFICIENT_OF_RESTITUTION..=SphUi::MAX_COEFFICIENT_OF_RESTITUTION,
                 )
-                .text("Restitution"),
+                .text(tr("Restitution")),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.coefficient_of_friction,
                     SphUi::MIN_COEFFICIENT_OF_FRICTION..=SphUi::MAX_COEFFICIENT_OF_FRICTION,
                 )
-                .text("Friction"),
+                .text(tr("Friction")),
             );
+            ui.checkbox(&mut self.liquid_surface, tr("Liquid Surface"));
+            ui.checkbox(&mut self.color_by_phase, tr("Color by Phase"));
         });
     }
 }
@@ -106,6 +250,9 @@ impl SphUi {
     const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(10);
     const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(100);
 
+    const CFL_NUMBER_MIN: f32 = 0.05;
+    const CFL_NUMBER_MAX: f32 = 1.0;
+
     const PARTICLE_MASS_MIN: f32 = 0.001;
     const PARTICLE_MASS_MAX: f32 = 0.1;
 
@@ -121,8 +268,22 @@ impl SphUi {
     const KINEMATIC_VISCOSITY_MIN: f32 = 0.1;
     const KINEMATIC_VISCOSITY_MAX: f32 = 3.0;
 
-    const GRAVITY_MIN: f32 = -2.0;
-    const GRAVITY_MAX: f32 = 2.0;
+    const INTERFACE_TENSION_MIN: f32 = 0.0;
+    const INTERFACE_TENSION_MAX: f32 = 1.0;
+
+    const SPONGE_CAPACITY_MIN: usize = 0;
+    const SPONGE_CAPACITY_MAX: usize = 512;
+
+    const SPONGE_RATE_MIN: f32 = 0.0;
+    const SPONGE_RATE_MAX: f32 = 1000.0;
+
+    const SPONGE_RESISTANCE_MIN: f32 = 0.0;
+    const SPONGE_RESISTANCE_MAX: f32 = 100.0;
+
+    const GRAVITY_MIN: f32 = -20.0;
+    const GRAVITY_MAX: f32 = 20.0;
+
+    const CONTAINER_HALF_EXTENT_MAX: f32 = 1.5;
 
     const MIN_COEFFICIENT_OF_RESTITUTION: f32 = 0.0;
     const MAX_COEFFICIENT_OF_RESTITUTION: f32 = 1.0;
@@ -133,10 +294,42 @@ impl SphUi {
     pub fn new() -> SphUi {
         SphUi {
             sim_config: Config::default(),
+            liquid_surface: false,
+            color_by_phase: false,
+            timestep: None,
         }
     }
 
-    pub fn get_gui_state_mut(&mut self) -> &Config {
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+    }
+
+    /// Sets the simulation's current timestep, for display.
+    pub fn set_timestep(&mut self, timestep: Timestep) {
+        self.timestep = Some(timestep);
+    }
+
+    /// Whether to render the particles as a continuous liquid surface rather than as spheres.
+    pub fn get_liquid_surface(&self) -> bool {
+        self.liquid_surface
+    }
+
+    /// Whether to color the particles by their phase, rather than with the theme's body color.
+    pub fn get_color_by_phase(&self) -> bool {
+        self.color_by_phase
+    }
+}
+
+impl ConfigUi for SphUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
         &self.sim_config
     }
 }
+
+impl Default for SphUi {
+    fn default() -> Self {
+        SphUi::new()
+    }
+}
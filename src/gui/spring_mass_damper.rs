@@ -1,75 +1,129 @@
-use crate::gui::Ui;
+use crate::gui::{self, ConfigUi, Ui};
+use crate::localization::tr;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::fan::FanConfig;
 use crate::simulation::springy::config::Config;
+use crate::simulation::springy::simulation::Simulation;
 use crate::simulation::state::Integration;
+use crate::simulation::units;
 
 use egui::Slider;
 
 pub struct SpringMassDamperUi {
     sim_config: Config,
+    /// The velocity the floor slides at, as a conveyor belt.
+    floor_surface_velocity: cgmath::Vector3<f32>,
+    /// The simulation's dt, while it's adapting it.
+    adapted_dt: f32,
 }
 
 impl Ui for SpringMassDamperUi {
     fn ui(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Config").show(&ctx, |ui| {
-            egui::ComboBox::from_label("Integration")
+        egui::Window::new(tr("Config")).show(&ctx, |ui| {
+            egui::ComboBox::from_label(tr("Integration"))
                 .selected_text(format!("{:?}", self.sim_config.integration))
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut self.sim_config.integration, Integration::Rk4, "RK4");
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Rk4,
+                        tr("RK4"),
+                    );
                     ui.selectable_value(
                         &mut self.sim_config.integration,
                         Integration::Euler,
-                        "Euler",
+                        tr("Euler"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::VelocityVerlet,
+                        tr("Velocity Verlet"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Leapfrog,
+                        tr("Leapfrog"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::ImplicitEuler,
+                        tr("Implicit Euler"),
+                    );
+                    ui.selectable_value(
+                        &mut self.sim_config.integration,
+                        Integration::Rk45,
+                        tr("RK45"),
                     );
                 });
-            ui.add(
-                Slider::new(
-                    &mut self.sim_config.dt,
-                    SpringMassDamperUi::SIMULATION_DT_MIN.as_secs_f32()
-                        ..=SpringMassDamperUi::SIMULATION_DT_MAX.as_secs_f32(),
-                )
-                .text("Simualtion dt (secs)"),
+            // Implicit integration stays stable at much larger timesteps than the explicit methods.
+            let dt_max = if self.sim_config.integration == Integration::ImplicitEuler {
+                SpringMassDamperUi::IMPLICIT_DT_MAX
+            } else {
+                SpringMassDamperUi::SIMULATION_DT_MAX
+            };
+            self.sim_config.dt = self.sim_config.dt.min(dt_max.as_secs_f32());
+            let dt_range =
+                SpringMassDamperUi::SIMULATION_DT_MIN.as_secs_f32()..=dt_max.as_secs_f32();
+            ui.add_enabled(
+                self.sim_config.get_adaptive_dt().is_none(),
+                Slider::new(&mut self.sim_config.dt, dt_range.clone())
+                    .text(tr("Simualtion dt (secs)")),
+            );
+            gui::adaptive_dt_controls(
+                ui,
+                self.sim_config.integration,
+                &mut self.sim_config.adaptive_dt,
+                self.adapted_dt,
+                dt_range,
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.x,
                     SpringMassDamperUi::GRAVITY_MIN..=SpringMassDamperUi::GRAVITY_MAX,
                 )
-                .text("Gravity X"),
+                .text(units::label("Gravity X", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.y,
                     SpringMassDamperUi::GRAVITY_MIN..=SpringMassDamperUi::GRAVITY_MAX,
                 )
-                .text("Gravity Y"),
+                .text(units::label("Gravity Y", units::ACCELERATION)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.gravity.z,
                     SpringMassDamperUi::GRAVITY_MIN..=SpringMassDamperUi::GRAVITY_MAX,
                 )
-                .text("Gravity Z"),
+                .text(units::label("Gravity Z", units::ACCELERATION)),
             );
+            if let Some(preset) = gui::gravity_presets(ui, self.sim_config.gravity) {
+                self.sim_config.gravity = preset.vector();
+            }
             ui.add(
                 Slider::new(
                     &mut self.sim_config.wind.x,
                     SpringMassDamperUi::WIND_MIN..=SpringMassDamperUi::WIND_MAX,
                 )
-                .text("Wind X"),
+                .text(units::label("Wind X", units::VELOCITY)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.wind.y,
                     SpringMassDamperUi::WIND_MIN..=SpringMassDamperUi::WIND_MAX,
                 )
-                .text("Wind Y"),
+                .text(units::label("Wind Y", units::VELOCITY)),
             );
             ui.add(
                 Slider::new(
                     &mut self.sim_config.wind.z,
                     SpringMassDamperUi::WIND_MIN..=SpringMassDamperUi::WIND_MAX,
                 )
-                .text("Wind Z"),
+                .text(units::label("Wind Z", units::VELOCITY)),
+            );
+            gui::fan_controls(
+                ui,
+                &mut self.sim_config.fan,
+                SpringMassDamperUi::FAN_DEFAULT,
             );
             ui.add(
                 Slider::new(
@@ -77,7 +131,7 @@ impl Ui for SpringMassDamperUi {
                     SpringMassDamperUi::MIN_COEFFICIENT_DRAG
                         ..=SpringMassDamperUi::MAX_COEFFICIENT_DRAG,
                 )
-                .text("Drag"),
+                .text(tr("Drag")),
             );
             ui.add(
                 Slider::new(
@@ -85,7 +139,7 @@ impl Ui for SpringMassDamperUi {
                     SpringMassDamperUi::MIN_COEFFICIENT_LIFT
                         ..=SpringMassDamperUi::MAX_COEFFICIENT_LIFT,
                 )
-                .text("Lift"),
+                .text(tr("Lift")),
             );
             ui.add(
                 Slider::new(
@@ -93,7 +147,7 @@ impl Ui for SpringMassDamperUi {
                     SpringMassDamperUi::MIN_COEFFICIENT_OF_RESTITUTION
                         ..=SpringMassDamperUi::MAX_COEFFICIENT_OF_RESTITUTION,
                 )
-                .text("Restitution"),
+                .text(tr("Restitution")),
             );
             ui.add(
                 Slider::new(
@@ -101,14 +155,77 @@ impl Ui for SpringMassDamperUi {
                     SpringMassDamperUi::MIN_COEFFICIENT_OF_FRICTION
                         ..=SpringMassDamperUi::MAX_COEFFICIENT_OF_FRICTION,
                 )
-                .text("Friction"),
+                .text(tr("Friction")),
+            );
+            gui::collision_mask(ui, "Collides With", &mut self.sim_config.collision_mask);
+            ui.add(
+                Slider::new(
+                    &mut self.floor_surface_velocity.x,
+                    SpringMassDamperUi::FLOOR_SURFACE_VELOCITY_MIN
+                        ..=SpringMassDamperUi::FLOOR_SURFACE_VELOCITY_MAX,
+                )
+                .text(units::label("Floor Conveyor X", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.floor_surface_velocity.z,
+                    SpringMassDamperUi::FLOOR_SURFACE_VELOCITY_MIN
+                        ..=SpringMassDamperUi::FLOOR_SURFACE_VELOCITY_MAX,
+                )
+                .text(units::label("Floor Conveyor Z", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.rain_rate,
+                    SpringMassDamperUi::RAIN_RATE_MIN..=SpringMassDamperUi::RAIN_RATE_MAX,
+                )
+                .text(tr("Rain (drops/s)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.drop_mass,
+                    SpringMassDamperUi::DROP_MASS_MIN..=SpringMassDamperUi::DROP_MASS_MAX,
+                )
+                .text(units::label("Drop Mass", units::MASS)),
             );
+            let mut submerged = self.sim_config.fluid_surface_height.is_some();
+            if ui.checkbox(&mut submerged, tr("Fluid")).changed() {
+                self.sim_config.fluid_surface_height =
+                    submerged.then_some(SpringMassDamperUi::FLUID_SURFACE_HEIGHT_DEFAULT);
+            }
+            if let Some(height) = &mut self.sim_config.fluid_surface_height {
+                ui.add(
+                    Slider::new(
+                        height,
+                        SpringMassDamperUi::FLUID_SURFACE_HEIGHT_MIN
+                            ..=SpringMassDamperUi::FLUID_SURFACE_HEIGHT_MAX,
+                    )
+                    .text(units::label("Fluid Surface Height", units::LENGTH)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.fluid_density,
+                        SpringMassDamperUi::FLUID_DENSITY_MIN
+                            ..=SpringMassDamperUi::FLUID_DENSITY_MAX,
+                    )
+                    .text(units::label("Fluid Density", units::DENSITY)),
+                );
+                ui.add(
+                    Slider::new(
+                        &mut self.sim_config.face_thickness,
+                        SpringMassDamperUi::FACE_THICKNESS_MIN
+                            ..=SpringMassDamperUi::FACE_THICKNESS_MAX,
+                    )
+                    .text(units::label("Face Thickness", units::LENGTH)),
+                );
+            }
         });
     }
 }
 
 impl SpringMassDamperUi {
     const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(10);
+    const IMPLICIT_DT_MAX: std::time::Duration = std::time::Duration::from_millis(50);
     const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(100);
 
     const GRAVITY_MIN: f32 = -20.0;
@@ -117,6 +234,15 @@ impl SpringMassDamperUi {
     const WIND_MIN: f32 = -20.0;
     const WIND_MAX: f32 = 20.0;
 
+    /// In front of the meshes, blowing back at them.
+    const FAN_DEFAULT: FanConfig = FanConfig {
+        position: cgmath::Vector3::new(0.0, -0.3, 1.5),
+        yaw: std::f32::consts::PI,
+        pitch: 0.0,
+        blade_speed: 20.0,
+        radius: 0.4,
+    };
+
     const MIN_COEFFICIENT_DRAG: f32 = 0.0;
     const MAX_COEFFICIENT_DRAG: f32 = 1000.0;
 
@@ -129,13 +255,68 @@ impl SpringMassDamperUi {
     const MIN_COEFFICIENT_OF_FRICTION: f32 = 0.0;
     const MAX_COEFFICIENT_OF_FRICTION: f32 = 1.0;
 
+    const FLOOR_SURFACE_VELOCITY_MIN: f32 = -2.0;
+    const FLOOR_SURFACE_VELOCITY_MAX: f32 = 2.0;
+
+    const RAIN_RATE_MIN: f32 = 0.0;
+    const RAIN_RATE_MAX: f32 = 2000.0;
+
+    const DROP_MASS_MIN: f32 = 0.01;
+    const DROP_MASS_MAX: f32 = 5.0;
+
+    const FLUID_SURFACE_HEIGHT_DEFAULT: f32 = -0.5;
+    const FLUID_SURFACE_HEIGHT_MIN: f32 = -2.0;
+    const FLUID_SURFACE_HEIGHT_MAX: f32 = 2.0;
+
+    const FLUID_DENSITY_MIN: f32 = 1.0;
+    const FLUID_DENSITY_MAX: f32 = 2000.0;
+
+    const FACE_THICKNESS_MIN: f32 = 0.0;
+    const FACE_THICKNESS_MAX: f32 = 1.0;
+
     pub fn new() -> SpringMassDamperUi {
         SpringMassDamperUi {
             sim_config: Config::default(),
+            floor_surface_velocity: cgmath::Vector3::new(0.0, 0.0, 0.0),
+            adapted_dt: 0.0,
         }
     }
 
-    pub fn get_gui_state_mut(&mut self) -> &Config {
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        self.set_adapted_dt(simulation.get_config().dt);
+    }
+
+    /// Sets the readout of the simulation's dt, while it's adapting it.
+    pub fn set_adapted_dt(&mut self, dt: f32) {
+        self.adapted_dt = dt;
+    }
+
+    pub fn get_floor_surface_velocity(&self) -> cgmath::Vector3<f32> {
+        self.floor_surface_velocity
+    }
+
+    /// Sets the config, e.g. back to how it was before an edit was undone.
+    pub fn set_config(&mut self, config: &Config) {
+        self.sim_config.clone_from(config);
+    }
+
+    /// Sets the wind, e.g. to a cloth scenario's.
+    pub fn set_wind(&mut self, wind: cgmath::Vector3<f32>) {
+        self.sim_config.wind = wind;
+    }
+}
+
+impl ConfigUi for SpringMassDamperUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
         &self.sim_config
     }
 }
+
+impl Default for SpringMassDamperUi {
+    fn default() -> Self {
+        SpringMassDamperUi::new()
+    }
+}
@@ -0,0 +1,235 @@
+use crate::gui::{measure::MeasureTools, solver::SolverConvergence, ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::fitting::Trajectory;
+use crate::simulation::target_range::config::{Config, Layout, SleepConfig};
+use crate::simulation::target_range::{self, Simulation};
+use crate::simulation::units;
+
+use egui::{Button, Slider};
+
+pub struct TargetRangeUi {
+    sim_config: Config,
+    reset: bool,
+    score: usize,
+    block_count: usize,
+    shots: usize,
+    contact_solver: SolverConvergence,
+    measure: MeasureTools,
+    relaunch: bool,
+    can_relaunch: bool,
+    freeze_ghost: bool,
+    has_flight: bool,
+    /// A frozen flight, to compare the flights since against.
+    ghost: Option<Trajectory>,
+}
+
+impl Ui for TargetRangeUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            egui::ComboBox::from_label(tr("Layout"))
+                .selected_text(tr(self.sim_config.layout.name()))
+                .show_ui(ui, |ui| {
+                    for option in Layout::ALL {
+                        ui.selectable_value(&mut self.sim_config.layout, option, tr(option.name()));
+                    }
+                });
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.dt,
+                    TargetRangeUi::SIMULATION_DT_MIN.as_secs_f32()
+                        ..=TargetRangeUi::SIMULATION_DT_MAX.as_secs_f32(),
+                )
+                .text(tr("Simualtion dt (secs)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.gravity,
+                    0.0..=TargetRangeUi::GRAVITY_MAX,
+                )
+                .text(units::label("Gravity", units::ACCELERATION)),
+            );
+
+            ui.separator();
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.launch_strength,
+                    TargetRangeUi::LAUNCH_STRENGTH_MIN..=TargetRangeUi::LAUNCH_STRENGTH_MAX,
+                )
+                .text(tr("Launch Strength (1/s)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.projectile_mass,
+                    TargetRangeUi::MASS_MIN..=TargetRangeUi::MASS_MAX,
+                )
+                .text(units::label("Projectile Mass", units::MASS)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.block_mass,
+                    TargetRangeUi::MASS_MIN..=TargetRangeUi::MASS_MAX,
+                )
+                .text(units::label("Block Mass", units::MASS)),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.drag, 0.0..=TargetRangeUi::DRAG_MAX)
+                    .text(tr("Air Drag")),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.contact_restitution, 0.0..=1.0)
+                    .text(tr("Contact Restitution")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.contact_friction,
+                    0.0..=TargetRangeUi::CONTACT_FRICTION_MAX,
+                )
+                .text(tr("Contact Friction")),
+            );
+            ui.add(
+                Slider::new(&mut self.sim_config.contact_position_correction, 0.0..=1.0)
+                    .text(tr("Position Correction")),
+            );
+            SolverConvergence::budget_controls(ui, &mut self.sim_config.contact_solver);
+            self.contact_solver.checkbox(ui);
+            self.measure.checkbox(ui);
+            self.sleep_controls(ui);
+            self.reset = ui.button(tr("Reset")).clicked();
+
+            ui.separator();
+            ui.label(tr_format(
+                "Score: {} of {} blocks, {} shots",
+                &[&self.score, &self.block_count, &self.shots],
+            ));
+            ui.horizontal(|ui| {
+                self.relaunch = ui
+                    .add_enabled(self.can_relaunch, Button::new(tr("Relaunch")))
+                    .clicked();
+                self.freeze_ghost = ui
+                    .add_enabled(self.has_flight, Button::new(tr("Freeze Ghost")))
+                    .clicked();
+                if ui
+                    .add_enabled(self.ghost.is_some(), Button::new(tr("Clear Ghost")))
+                    .clicked()
+                {
+                    self.ghost = None;
+                }
+            });
+        });
+        self.contact_solver.window(ctx);
+        self.measure.window(ctx);
+    }
+}
+
+impl TargetRangeUi {
+    const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(2);
+    const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(250);
+
+    const GRAVITY_MAX: f32 = 20.0;
+
+    const LAUNCH_STRENGTH_MIN: f32 = 2.0;
+    const LAUNCH_STRENGTH_MAX: f32 = 20.0;
+    const MASS_MIN: f32 = 0.1;
+    const MASS_MAX: f32 = 20.0;
+    const CONTACT_FRICTION_MAX: f32 = 1.0;
+    const DRAG_MAX: f32 = 5.0;
+
+    const SLEEP_SPEED_MAX: f32 = 0.5;
+    const SLEEP_TIME_MAX: f32 = 2.0;
+
+    pub fn new() -> TargetRangeUi {
+        TargetRangeUi {
+            sim_config: Config::default(),
+            reset: false,
+            score: 0,
+            block_count: 0,
+            shots: 0,
+            contact_solver: SolverConvergence::new("Contact Solver"),
+            measure: MeasureTools::new(
+                target_range::START_GATE_X,
+                target_range::STOP_GATE_X,
+                -target_range::GROUND_HALF_EXTENTS.x..=target_range::GROUND_HALF_EXTENTS.x,
+            ),
+            relaunch: false,
+            can_relaunch: false,
+            freeze_ghost: false,
+            has_flight: false,
+            ghost: None,
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        if self.reset {
+            simulation.reset();
+        }
+        if std::mem::take(&mut self.relaunch) {
+            simulation.relaunch().ok();
+        }
+        if std::mem::take(&mut self.freeze_ghost) {
+            self.ghost = simulation
+                .get_flight()
+                .map(|flight| flight.get_trajectory().clone());
+        }
+        self.can_relaunch = simulation.can_relaunch();
+        self.has_flight = simulation.get_flight().is_some();
+        self.score = simulation.get_score();
+        self.block_count = simulation.get_block_count();
+        self.shots = simulation.get_shots();
+        self.contact_solver.update(simulation.get_contact_stats());
+        let (start_gate, stop_gate) = self.measure.get_gates();
+        simulation.set_stopwatch_gates(start_gate, stop_gate);
+        if self.measure.take_clear_laps() {
+            simulation.clear_stopwatch();
+        }
+        self.measure.update(simulation.get_stopwatch());
+    }
+
+    /// The frozen flight, if any, for the demo to draw.
+    pub fn get_ghost(&self) -> Option<&Trajectory> {
+        self.ghost.as_ref()
+    }
+
+    /// The measuring tools, for the demo to place their points and draw them.
+    pub fn get_measure_tools(&self) -> &MeasureTools {
+        &self.measure
+    }
+
+    pub fn get_measure_tools_mut(&mut self) -> &mut MeasureTools {
+        &mut self.measure
+    }
+
+    /// Shows a checkbox letting still bodies fall asleep, and the sleep's controls while it's checked.
+    fn sleep_controls(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.sim_config.sleep.is_some();
+        if ui.checkbox(&mut enabled, tr("Sleeping")).changed() {
+            self.sim_config.sleep = enabled.then(SleepConfig::default);
+        }
+        let Some(sleep) = &mut self.sim_config.sleep else {
+            return;
+        };
+        ui.add(
+            Slider::new(&mut sleep.speed, 0.0..=TargetRangeUi::SLEEP_SPEED_MAX)
+                .text(units::label("Sleep Speed", units::VELOCITY)),
+        );
+        ui.add(
+            Slider::new(&mut sleep.time, 0.0..=TargetRangeUi::SLEEP_TIME_MAX)
+                .text(units::label("Sleep Time", units::TIME)),
+        );
+    }
+}
+
+impl ConfigUi for TargetRangeUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for TargetRangeUi {
+    fn default() -> Self {
+        TargetRangeUi::new()
+    }
+}
@@ -0,0 +1,170 @@
+use crate::localization::tr;
+
+use std::collections::VecDeque;
+
+/// Whether the user asked to undo their last edit or redo their last undone one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UndoAction {
+    Undo,
+    Redo,
+}
+
+/// The edits made through a demo's GUI, so that experimenting with it isn't destructive. Only the most recent
+/// edits are kept, and making a new edit discards those undone since. The stack only orders the edits; the
+/// demo reverts and reapplies them.
+pub struct UndoStack<E> {
+    /// Oldest first.
+    done: VecDeque<E>,
+    /// Most recently undone last.
+    undone: Vec<E>,
+}
+
+impl<E> UndoStack<E> {
+    pub const MAX_EDITS: usize = 100;
+
+    pub fn new() -> UndoStack<E> {
+        UndoStack {
+            done: VecDeque::new(),
+            undone: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, edit: E) {
+        self.undone.clear();
+        if self.done.len() >= UndoStack::<E>::MAX_EDITS {
+            self.done.pop_front();
+        }
+        self.done.push_back(edit);
+    }
+
+    /// The most recent edit, for the demo to revert, or None if there's nothing to undo.
+    pub fn undo(&mut self) -> Option<&mut E> {
+        let edit = self.done.pop_back()?;
+        self.undone.push(edit);
+        self.undone.last_mut()
+    }
+
+    /// The most recently undone edit, for the demo to reapply, or None if there's nothing to redo.
+    pub fn redo(&mut self) -> Option<&mut E> {
+        let edit = self.undone.pop()?;
+        self.done.push_back(edit);
+        self.done.back_mut()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Every edit, done or undone, e.g. to update what they refer to once reapplying one respawns it elsewhere.
+    pub fn edits_mut(&mut self) -> impl Iterator<Item = &mut E> {
+        self.done.iter_mut().chain(self.undone.iter_mut())
+    }
+}
+
+impl<E> Default for UndoStack<E> {
+    fn default() -> Self {
+        UndoStack::new()
+    }
+}
+
+/// Coalesces the changes to a value into edits. While the user's still editing it, e.g. dragging a slider or a
+/// gizmo, its changes are held back, so that the whole drag is undone at once.
+pub struct EditTracker<T> {
+    committed: T,
+}
+
+impl<T: Clone + PartialEq> EditTracker<T> {
+    pub fn new(value: &T) -> EditTracker<T> {
+        EditTracker {
+            committed: value.clone(),
+        }
+    }
+
+    /// Returns the value as it was before the edit once the user's finished an edit of it, or None if they haven't.
+    pub fn update(&mut self, value: &T, editing: bool) -> Option<T> {
+        if editing || *value == self.committed {
+            return None;
+        }
+        Some(std::mem::replace(&mut self.committed, value.clone()))
+    }
+
+    /// Sets the value without making an edit of it, e.g. once the demo's undone an edit back to it.
+    pub fn reset(&mut self, value: &T) {
+        self.committed.clone_from(value);
+    }
+}
+
+/// Shows the Undo and Redo buttons, enabled while there's something to undo or redo, and returns the action the
+/// user asked for this frame by them or by their shortcuts: Ctrl+Z to undo, and Ctrl+Shift+Z or Ctrl+Y to redo.
+/// The shortcuts are ignored while a text field has focus, so that it can undo its own typing.
+pub fn controls(ui: &mut egui::Ui, can_undo: bool, can_redo: bool) -> Option<UndoAction> {
+    let mut action = None;
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(can_undo, egui::Button::new(tr("Undo")))
+            .clicked()
+        {
+            action = Some(UndoAction::Undo);
+        }
+        if ui
+            .add_enabled(can_redo, egui::Button::new(tr("Redo")))
+            .clicked()
+        {
+            action = Some(UndoAction::Redo);
+        }
+    });
+    if ui.ctx().wants_keyboard_input() {
+        return action;
+    }
+    let input = ui.input();
+    if input.modifiers.command {
+        if input.key_pressed(egui::Key::Z) {
+            action = Some(if input.modifiers.shift {
+                UndoAction::Redo
+            } else {
+                UndoAction::Undo
+            });
+        } else if input.key_pressed(egui::Key::Y) {
+            action = Some(UndoAction::Redo);
+        }
+    }
+    action.filter(|action| match action {
+        UndoAction::Undo => can_undo,
+        UndoAction::Redo => can_redo,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_edits_discard_undone_ones() {
+        let mut stack = UndoStack::new();
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.undo().copied(), Some(2));
+        assert_eq!(stack.redo().copied(), Some(2));
+        assert_eq!(stack.undo().copied(), Some(2));
+        stack.push(3);
+        assert!(!stack.can_redo());
+        assert_eq!(stack.undo().copied(), Some(3));
+        assert_eq!(stack.undo().copied(), Some(1));
+        assert_eq!(stack.undo(), None);
+    }
+
+    #[test]
+    fn edits_coalesce_until_finished() {
+        let mut tracker = EditTracker::new(&0.0);
+        assert_eq!(tracker.update(&0.5, true), None);
+        assert_eq!(tracker.update(&1.0, true), None);
+        assert_eq!(tracker.update(&1.0, false), Some(0.0));
+        assert_eq!(tracker.update(&1.0, false), None);
+        tracker.reset(&0.0);
+        assert_eq!(tracker.update(&0.0, false), None);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::gui::{ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::units;
+use crate::simulation::vortex::config::Config;
+use crate::simulation::vortex::Simulation;
+
+use egui::Slider;
+
+pub struct VortexUi {
+    sim_config: Config,
+    /// Whether the vortex particles are drawn as well as the smoke.
+    pub show_vortices: bool,
+    vortex_count: usize,
+    tracer_count: usize,
+}
+
+impl Ui for VortexUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.dt,
+                    VortexUi::SIMULATION_DT_MIN.as_secs_f32()
+                        ..=VortexUi::SIMULATION_DT_MAX.as_secs_f32(),
+                )
+                .text(tr("Simualtion dt (secs)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.ring_interval,
+                    VortexUi::RING_INTERVAL_MIN..=VortexUi::RING_INTERVAL_MAX,
+                )
+                .text(units::label("Ring Interval", units::TIME)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.ring_radius,
+                    VortexUi::RING_RADIUS_MIN..=VortexUi::RING_RADIUS_MAX,
+                )
+                .text(units::label("Ring Radius", units::LENGTH)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.ring_circulation,
+                    VortexUi::RING_CIRCULATION_MIN..=VortexUi::RING_CIRCULATION_MAX,
+                )
+                .text(units::label("Ring Circulation", units::CIRCULATION)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.vortices_per_ring,
+                    VortexUi::VORTICES_PER_RING_MIN..=VortexUi::VORTICES_PER_RING_MAX,
+                )
+                .text(tr("Vortices Per Ring")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.tracers_per_ring,
+                    VortexUi::TRACERS_PER_RING_MIN..=VortexUi::TRACERS_PER_RING_MAX,
+                )
+                .text(tr("Tracers Per Ring")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.core_radius,
+                    VortexUi::CORE_RADIUS_MIN..=VortexUi::CORE_RADIUS_MAX,
+                )
+                .text(units::label("Core Radius", units::LENGTH)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.barnes_hut_theta,
+                    VortexUi::BARNES_HUT_THETA_MIN..=VortexUi::BARNES_HUT_THETA_MAX,
+                )
+                .text(tr("Barnes-Hut Theta")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.vorticity_decay,
+                    VortexUi::VORTICITY_DECAY_MIN..=VortexUi::VORTICITY_DECAY_MAX,
+                )
+                .text(tr("Vorticity Decay (1/s)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.lifetime,
+                    VortexUi::LIFETIME_MIN..=VortexUi::LIFETIME_MAX,
+                )
+                .text(units::label("Lifetime", units::TIME)),
+            );
+            ui.checkbox(&mut self.show_vortices, tr("Show Vortices"));
+
+            ui.separator();
+            ui.label(tr_format(
+                "{} vortices, {} tracers",
+                &[&self.vortex_count, &self.tracer_count],
+            ));
+        });
+    }
+}
+
+impl VortexUi {
+    const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(50);
+    const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_millis(1);
+
+    const RING_INTERVAL_MIN: f32 = 0.1;
+    const RING_INTERVAL_MAX: f32 = 5.0;
+
+    const RING_RADIUS_MIN: f32 = 0.05;
+    const RING_RADIUS_MAX: f32 = 1.0;
+
+    const RING_CIRCULATION_MIN: f32 = 0.0;
+    const RING_CIRCULATION_MAX: f32 = 5.0;
+
+    const VORTICES_PER_RING_MIN: usize = 4;
+    const VORTICES_PER_RING_MAX: usize = 128;
+
+    const TRACERS_PER_RING_MIN: usize = 0;
+    const TRACERS_PER_RING_MAX: usize = 2000;
+
+    const CORE_RADIUS_MIN: f32 = 0.01;
+    const CORE_RADIUS_MAX: f32 = 0.5;
+
+    const BARNES_HUT_THETA_MIN: f32 = 0.0;
+    const BARNES_HUT_THETA_MAX: f32 = 1.5;
+
+    const VORTICITY_DECAY_MIN: f32 = 0.0;
+    const VORTICITY_DECAY_MAX: f32 = 2.0;
+
+    const LIFETIME_MIN: f32 = 1.0;
+    const LIFETIME_MAX: f32 = 20.0;
+
+    pub fn new() -> VortexUi {
+        VortexUi {
+            sim_config: Config::default(),
+            show_vortices: false,
+            vortex_count: 0,
+            tracer_count: 0,
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+        self.set_counts(
+            simulation.get_vortices().len(),
+            simulation.get_tracers().len(),
+        );
+    }
+
+    /// Sets the number of vortex and tracer particles in the simulation, for display.
+    pub fn set_counts(&mut self, vortex_count: usize, tracer_count: usize) {
+        self.vortex_count = vortex_count;
+        self.tracer_count = tracer_count;
+    }
+}
+
+impl ConfigUi for VortexUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for VortexUi {
+    fn default() -> Self {
+        VortexUi::new()
+    }
+}
@@ -0,0 +1,175 @@
+use crate::gui::{ConfigUi, Ui};
+use crate::localization::{tr, tr_format};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::units;
+use crate::simulation::wind_tunnel::config::{Config, Shape};
+use crate::simulation::wind_tunnel::Simulation;
+
+use cgmath::{Vector3, Zero};
+use egui::plot::{Line, Plot, Value, Values};
+use egui::Slider;
+
+pub struct WindTunnelUi {
+    sim_config: Config,
+    /// The simulation's latest measurements, for display.
+    force: Vector3<f32>,
+    drag_coefficient: f32,
+    drag_history: Vec<(f32, f32)>,
+}
+
+impl Ui for WindTunnelUi {
+    fn ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Config")).show(ctx, |ui| {
+            egui::ComboBox::from_label(tr("Object"))
+                .selected_text(tr(self.sim_config.shape.name()))
+                .show_ui(ui, |ui| {
+                    for shape in Shape::ALL {
+                        ui.selectable_value(&mut self.sim_config.shape, shape, tr(shape.name()));
+                    }
+                });
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.dt,
+                    WindTunnelUi::SIMULATION_DT_MIN.as_secs_f32()
+                        ..=WindTunnelUi::SIMULATION_DT_MAX.as_secs_f32(),
+                )
+                .text(tr("Simualtion dt (secs)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.wind_speed,
+                    WindTunnelUi::WIND_SPEED_MIN..=WindTunnelUi::WIND_SPEED_MAX,
+                )
+                .text(units::label("Wind Speed", units::VELOCITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.air_density,
+                    WindTunnelUi::AIR_DENSITY_MIN..=WindTunnelUi::AIR_DENSITY_MAX,
+                )
+                .text(units::label("Air Density", units::DENSITY)),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.tracer_rate,
+                    WindTunnelUi::TRACER_RATE_MIN..=WindTunnelUi::TRACER_RATE_MAX,
+                )
+                .text(tr("Tracers (per second)")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.coefficient_of_restitution,
+                    WindTunnelUi::MIN_COEFFICIENT_OF_RESTITUTION
+                        ..=WindTunnelUi::MAX_COEFFICIENT_OF_RESTITUTION,
+                )
+                .text(tr("Restitution")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.coefficient_of_friction,
+                    WindTunnelUi::MIN_COEFFICIENT_OF_FRICTION
+                        ..=WindTunnelUi::MAX_COEFFICIENT_OF_FRICTION,
+                )
+                .text(tr("Friction")),
+            );
+            ui.add(
+                Slider::new(
+                    &mut self.sim_config.relaxation_time,
+                    WindTunnelUi::RELAXATION_TIME_MIN..=WindTunnelUi::RELAXATION_TIME_MAX,
+                )
+                .text(units::label("Relaxation Time", units::TIME)),
+            );
+
+            ui.separator();
+            ui.label(tr_format(
+                "Drag: {} {}",
+                &[&format!("{:.3}", self.force.x), &units::FORCE],
+            ));
+            ui.label(tr_format(
+                "Lift: {} {}",
+                &[&format!("{:.3}", self.force.y), &units::FORCE],
+            ));
+            ui.label(tr_format(
+                "Drag Coefficient: {}",
+                &[&format!("{:.3}", self.drag_coefficient)],
+            ));
+            let drag_coefficients = Values::from_values_iter(
+                self.drag_history
+                    .iter()
+                    .map(|(time, drag_coefficient)| Value::new(*time, *drag_coefficient)),
+            );
+            Plot::new("drag coefficient")
+                .height(WindTunnelUi::PLOT_HEIGHT)
+                .include_y(0.0)
+                .allow_drag(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(drag_coefficients).name(tr("Drag Coefficient")))
+                });
+        });
+    }
+}
+
+impl WindTunnelUi {
+    const SIMULATION_DT_MAX: std::time::Duration = std::time::Duration::from_millis(10);
+    const SIMULATION_DT_MIN: std::time::Duration = std::time::Duration::from_micros(500);
+
+    const WIND_SPEED_MIN: f32 = 0.5;
+    const WIND_SPEED_MAX: f32 = 20.0;
+
+    const AIR_DENSITY_MIN: f32 = 0.1;
+    const AIR_DENSITY_MAX: f32 = 10.0;
+
+    const TRACER_RATE_MIN: f32 = 0.0;
+    const TRACER_RATE_MAX: f32 = 8000.0;
+
+    const MIN_COEFFICIENT_OF_RESTITUTION: f32 = 0.0;
+    const MAX_COEFFICIENT_OF_RESTITUTION: f32 = 1.0;
+
+    const MIN_COEFFICIENT_OF_FRICTION: f32 = 0.0;
+    const MAX_COEFFICIENT_OF_FRICTION: f32 = 1.0;
+
+    const RELAXATION_TIME_MIN: f32 = 0.01;
+    const RELAXATION_TIME_MAX: f32 = 1.0;
+
+    const PLOT_HEIGHT: f32 = 150.0;
+
+    pub fn new() -> WindTunnelUi {
+        WindTunnelUi {
+            sim_config: Config::default(),
+            force: Vector3::zero(),
+            drag_coefficient: 0.0,
+            drag_history: Vec::new(),
+        }
+    }
+
+    pub fn sync_simulation(&mut self, simulation: &mut Simulation) {
+        simulation.sync_config(self.get_config());
+    }
+
+    /// Sets the simulation's latest measurements, for display.
+    pub fn set_measurements(
+        &mut self,
+        force: Vector3<f32>,
+        drag_coefficient: f32,
+        drag_history: impl Iterator<Item = (f32, f32)>,
+    ) {
+        self.force = force;
+        self.drag_coefficient = drag_coefficient;
+        self.drag_history.clear();
+        self.drag_history.extend(drag_history);
+    }
+}
+
+impl ConfigUi for WindTunnelUi {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.sim_config
+    }
+}
+
+impl Default for WindTunnelUi {
+    fn default() -> Self {
+        WindTunnelUi::new()
+    }
+}
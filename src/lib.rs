@@ -0,0 +1,68 @@
+//! Physically based simulations, and the wgpu renderer and egui GUIs which visualize them.
+//! The feriphys binary runs the demos, but each simulation can be embedded on its own, e.g.
+//! stepping a rigidbody simulation from another application's loop. The prelude re-exports
+//! the types most embedders need. Building without the default render feature leaves out the
+//! renderer, GUIs, and demos, and the wgpu, winit, and egui dependencies they bring in.
+#[cfg(feature = "render")]
+mod args;
+#[cfg(feature = "render")]
+mod demos;
+#[cfg(feature = "render")]
+pub mod graphics;
+#[cfg(feature = "render")]
+pub mod gui;
+pub mod localization;
+pub mod math;
+#[cfg(feature = "network")]
+pub mod network;
+pub mod pool;
+pub mod prelude;
+pub mod script;
+pub mod simulation;
+mod utils;
+
+#[cfg(feature = "render")]
+use args::{Demos, FeriphysArgs};
+#[cfg(feature = "render")]
+use clap::{CommandFactory, FromArgMatches};
+
+/// Runs the demo chosen on the command line, as the feriphys binary does.
+#[cfg(feature = "render")]
+pub fn run() {
+    localization::init(None);
+    let matches = localization::localize_command(FeriphysArgs::command()).get_matches();
+    let args = FeriphysArgs::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Some(reference) = &args.fit {
+        demos::fit::run(args.demo, reference);
+        return;
+    }
+    #[cfg(feature = "network")]
+    if args.network.host.is_some() || args.network.view.is_some() {
+        demos::twin::run(args.demo, &args.window, &args.network);
+        return;
+    }
+    if args.scenario.is_some() && args.demo != Demos::Cloth {
+        eprintln!(
+            "{}",
+            localization::tr("Only the cloth demo opens scenarios!")
+        );
+        std::process::exit(1);
+    }
+    match args.demo {
+        Demos::BouncingBall => demos::bouncing_ball::run(&args.window),
+        Demos::ParticlesCpu => demos::particles_cpu::run(&args.window),
+        Demos::Flocking => demos::flocking::run(&args.window),
+        Demos::SpringMassDamper => demos::spring_mass_damper::run(&args.window),
+        Demos::Cloth => demos::cloth::run(&args.window, args.scenario.as_deref()),
+        Demos::RigidBody => demos::rigidbody::run(&args.window),
+        Demos::Sph => demos::sph::run(&args.window),
+        Demos::WindTunnel => demos::wind_tunnel::run(&args.window),
+        Demos::Vortex => demos::vortex::run(&args.window),
+        Demos::Lbm => demos::lbm::run(&args.window),
+        Demos::Charged => demos::charged::run(&args.window),
+        Demos::Billiards => demos::billiards::run(&args.window),
+        Demos::Bridge => demos::bridge::run(&args.window),
+        Demos::TargetRange => demos::target_range::run(&args.window),
+        Demos::Slosh => demos::slosh::run(&args.window),
+    }
+}
@@ -0,0 +1,170 @@
+/// A lightweight string table localizing the GUI, the command line help, and the help overlays.
+/// Text is looked up by its English source text, so untranslated text is simply shown in English.
+/// Tables are loaded from res/locales/<language>.txt, with lines of the form
+/// `English text = Translated text`. Blank lines and lines starting with # are ignored.
+use std::sync::OnceLock;
+
+use rustc_hash::FxHashMap;
+
+/// Overrides the language from the system locale, e.g. FERIPHYS_LANG=es.
+pub const LANGUAGE_ENV_VAR: &str = "FERIPHYS_LANG";
+
+static STRING_TABLE: OnceLock<StringTable> = OnceLock::new();
+
+#[derive(Debug, Default)]
+pub struct StringTable {
+    strings: FxHashMap<String, String>,
+}
+
+impl StringTable {
+    pub fn parse(contents: &str) -> StringTable {
+        let strings = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(english, translated)| (english.trim().to_owned(), translated.trim().to_owned()))
+            .collect();
+        StringTable { strings }
+    }
+
+    /// The translation of the English text, or the text itself if it has no translation.
+    pub fn get<'a>(&'a self, text: &'a str) -> &'a str {
+        self.strings.get(text).map_or(text, String::as_str)
+    }
+}
+
+/// Loads the string table for the language, or for the system's language if None.
+/// English, and languages without a table, are shown in English.
+/// Only the first call has any effect, and it should precede any calls to tr().
+pub fn init(language: Option<&str>) {
+    let language = language.map(str::to_owned).or_else(system_language);
+    let table = match language {
+        Some(language) if language != "en" => {
+            // Read directly, as graphics::resources is only built with the render feature.
+            let path = std::path::Path::new(env!("OUT_DIR"))
+                .join("res")
+                .join("locales")
+                .join(format!("{}.txt", language));
+            match std::fs::read_to_string(path) {
+                Ok(contents) => StringTable::parse(&contents),
+                Err(_) => {
+                    log::warn!("No string table for language {}", language);
+                    StringTable::default()
+                }
+            }
+        }
+        _ => StringTable::default(),
+    };
+    // Ignore repeated initialization, keeping the table in use.
+    let _ = STRING_TABLE.set(table);
+}
+
+/// Translates the English text into the current language.
+pub fn tr(text: &str) -> &str {
+    match STRING_TABLE.get() {
+        Some(table) => table.get(text),
+        None => text,
+    }
+}
+
+/// Translates the English text, then replaces each {} in the translation with the next argument,
+/// so that translations may reorder the text around the arguments.
+pub fn tr_format(text: &str, args: &[&dyn std::fmt::Display]) -> String {
+    let mut pieces = tr(text).split("{}");
+    let mut formatted = pieces.next().unwrap_or_default().to_owned();
+    for (index, piece) in pieces.enumerate() {
+        if let Some(arg) = args.get(index) {
+            formatted.push_str(&arg.to_string());
+        }
+        formatted.push_str(piece);
+    }
+    formatted
+}
+
+/// Localizes the about text and argument help of the command line.
+pub fn localize_command(command: clap::Command) -> clap::Command {
+    let about = command
+        .get_about()
+        .map(|about| tr(&about.to_string()).to_owned());
+    let helps = command
+        .get_arguments()
+        .filter_map(|arg| {
+            arg.get_help()
+                .map(|help| (arg.get_id().to_string(), tr(&help.to_string()).to_owned()))
+        })
+        .collect::<Vec<_>>();
+    let command = match about {
+        Some(about) => command.about(about),
+        None => command,
+    };
+    helps.into_iter().fold(command, |command, (id, help)| {
+        command.mut_arg(id, |arg| arg.help(help))
+    })
+}
+
+/// The language from LANGUAGE_ENV_VAR, or else from the LANG locale, e.g. "es" for es_MX.UTF-8.
+fn system_language() -> Option<String> {
+    std::env::var(LANGUAGE_ENV_VAR)
+        .ok()
+        .or_else(|| std::env::var("LANG").ok())
+        .and_then(|locale| parse_language(&locale))
+}
+
+fn parse_language(locale: &str) -> Option<String> {
+    let language = locale.split(['_', '.', '-', '@']).next()?.to_lowercase();
+    if language.is_empty() || language == "c" || language == "posix" {
+        None
+    } else {
+        Some(language)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_language, tr_format, StringTable};
+
+    #[test]
+    fn string_table() {
+        let table = StringTable::parse(
+            "# Spanish\n\
+             \n\
+             Drag = Arrastre\n\
+             Gravity Preset=Gravedad predefinida\n\
+             not a translation",
+        );
+        assert_eq!("Arrastre", table.get("Drag"));
+        assert_eq!("Gravedad predefinida", table.get("Gravity Preset"));
+        assert_eq!("Lift", table.get("Lift"));
+    }
+
+    #[test]
+    fn shipped_tables_parse() {
+        let contents = include_str!("../res/locales/es.txt");
+        let entries = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        assert!(entries.clone().all(|line| line.contains('=')));
+        assert_eq!(entries.count(), StringTable::parse(contents).strings.len());
+    }
+
+    #[test]
+    fn format() {
+        // Without a string table, the English text is used.
+        assert_eq!(
+            "Trigger 1 Entered: 20",
+            tr_format("Trigger {} Entered: {}", &[&1, &20])
+        );
+        assert_eq!("Missing ", tr_format("Missing {}", &[]));
+    }
+
+    #[test]
+    fn languages() {
+        assert_eq!(Some("es".to_owned()), parse_language("es_MX.UTF-8"));
+        assert_eq!(Some("pt".to_owned()), parse_language("pt-BR"));
+        assert_eq!(Some("de".to_owned()), parse_language("de"));
+        assert_eq!(None, parse_language("C.UTF-8"));
+        assert_eq!(None, parse_language(""));
+    }
+}
@@ -0,0 +1,247 @@
+/// Math types for hot simulation loops, converting to and from cgmath at their boundaries
+/// with the rest of the crate (e.g. rendering, the GUI, and configs).
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use cgmath::Vector3;
+
+/// The scalar the state vectors are integrated in, and the rigidbodies' integrated state is kept in: f32, or f64
+/// with the f64 feature, for long runs which would otherwise drift as f32 rounds away each step's small changes.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+/// A 3D vector padded to 4 lanes and aligned, so that arithmetic on it compiles to packed
+/// 4 lane instructions, as with glam's Vec3A. The padding lane is ignored. Its lanes are Real,
+/// so that the SPH simulation written with it works in f64 with the f64 feature.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+#[cfg_attr(not(feature = "f64"), repr(align(16)))]
+#[cfg_attr(feature = "f64", repr(align(32)))]
+pub struct Vec3A([Real; 4]);
+
+impl Vec3A {
+    pub const ZERO: Vec3A = Vec3A([0.0; 4]);
+    pub const X: Vec3A = Vec3A([1.0, 0.0, 0.0, 0.0]);
+    pub const Y: Vec3A = Vec3A([0.0, 1.0, 0.0, 0.0]);
+    pub const Z: Vec3A = Vec3A([0.0, 0.0, 1.0, 0.0]);
+
+    pub fn new(x: Real, y: Real, z: Real) -> Vec3A {
+        Vec3A([x, y, z, 0.0])
+    }
+
+    pub fn x(&self) -> Real {
+        self.0[0]
+    }
+
+    pub fn y(&self) -> Real {
+        self.0[1]
+    }
+
+    pub fn z(&self) -> Real {
+        self.0[2]
+    }
+
+    pub fn to_array(self) -> [Real; 3] {
+        [self.0[0], self.0[1], self.0[2]]
+    }
+
+    pub fn dot(&self, other: Vec3A) -> Real {
+        let product = self.map2(other, |a, b| a * b);
+        product.0[0] + product.0[1] + product.0[2]
+    }
+
+    pub fn cross(&self, other: Vec3A) -> Vec3A {
+        Vec3A::new(
+            self.y() * other.z() - self.z() * other.y(),
+            self.z() * other.x() - self.x() * other.z(),
+            self.x() * other.y() - self.y() * other.x(),
+        )
+    }
+
+    pub fn length_squared(&self) -> Real {
+        self.dot(*self)
+    }
+
+    pub fn length(&self) -> Real {
+        self.length_squared().sqrt()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.x() == 0.0 && self.y() == 0.0 && self.z() == 0.0
+    }
+
+    /// Returns the unit vector in the same direction, or zero if the vector is zero
+    /// (rather than NaN, as cgmath's normalize() would).
+    pub fn normalize_or_zero(&self) -> Vec3A {
+        if self.is_zero() {
+            Vec3A::ZERO
+        } else {
+            *self / self.length()
+        }
+    }
+
+    /// Clamps each component between the components of min and max.
+    pub fn clamp(&self, min: Vec3A, max: Vec3A) -> Vec3A {
+        Vec3A::new(
+            self.x().clamp(min.x(), max.x()),
+            self.y().clamp(min.y(), max.y()),
+            self.z().clamp(min.z(), max.z()),
+        )
+    }
+
+    // Written over all 4 lanes so the compiler keeps the operation packed.
+    fn map(self, f: impl Fn(Real) -> Real) -> Vec3A {
+        Vec3A([f(self.0[0]), f(self.0[1]), f(self.0[2]), f(self.0[3])])
+    }
+
+    fn map2(self, other: Vec3A, f: impl Fn(Real, Real) -> Real) -> Vec3A {
+        Vec3A([
+            f(self.0[0], other.0[0]),
+            f(self.0[1], other.0[1]),
+            f(self.0[2], other.0[2]),
+            f(self.0[3], other.0[3]),
+        ])
+    }
+}
+
+impl PartialEq for Vec3A {
+    fn eq(&self, other: &Vec3A) -> bool {
+        self.x() == other.x() && self.y() == other.y() && self.z() == other.z()
+    }
+}
+
+impl From<Vector3<f32>> for Vec3A {
+    fn from(v: Vector3<f32>) -> Self {
+        Vec3A::new(v.x as Real, v.y as Real, v.z as Real)
+    }
+}
+
+impl From<Vec3A> for Vector3<f32> {
+    fn from(v: Vec3A) -> Self {
+        Vector3::new(v.x(), v.y(), v.z()).cast().unwrap()
+    }
+}
+
+impl Add for Vec3A {
+    type Output = Vec3A;
+
+    fn add(self, other: Vec3A) -> Vec3A {
+        self.map2(other, |a, b| a + b)
+    }
+}
+
+impl Sub for Vec3A {
+    type Output = Vec3A;
+
+    fn sub(self, other: Vec3A) -> Vec3A {
+        self.map2(other, |a, b| a - b)
+    }
+}
+
+impl Mul<Real> for Vec3A {
+    type Output = Vec3A;
+
+    fn mul(self, scalar: Real) -> Vec3A {
+        self.map(|a| a * scalar)
+    }
+}
+
+impl Mul<Vec3A> for Real {
+    type Output = Vec3A;
+
+    fn mul(self, v: Vec3A) -> Vec3A {
+        v * self
+    }
+}
+
+impl Div<Real> for Vec3A {
+    type Output = Vec3A;
+
+    fn div(self, scalar: Real) -> Vec3A {
+        self.map(|a| a / scalar)
+    }
+}
+
+impl Neg for Vec3A {
+    type Output = Vec3A;
+
+    fn neg(self) -> Vec3A {
+        self.map(|a| -a)
+    }
+}
+
+impl AddAssign for Vec3A {
+    fn add_assign(&mut self, other: Vec3A) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec3A {
+    fn sub_assign(&mut self, other: Vec3A) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<Real> for Vec3A {
+    fn mul_assign(&mut self, scalar: Real) {
+        *self = *self * scalar;
+    }
+}
+
+impl Sum for Vec3A {
+    fn sum<I: Iterator<Item = Vec3A>>(iter: I) -> Vec3A {
+        iter.fold(Vec3A::ZERO, |sum, v| sum + v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{assert_relative_eq, InnerSpace, Vector3};
+
+    use super::{Real, Vec3A};
+
+    #[test]
+    fn matches_cgmath() {
+        let a = Vector3::new(1.0, -2.0, 3.0);
+        let b = Vector3::new(0.5, 4.0, -1.5);
+        let (a_3a, b_3a) = (Vec3A::from(a), Vec3A::from(b));
+        assert_eq!(a + b, (a_3a + b_3a).into());
+        assert_eq!(a - 2.0 * b, (a_3a - 2.0 * b_3a).into());
+        assert_eq!(-a / 4.0, (-a_3a / 4.0).into());
+        assert_eq!(a.dot(b) as Real, a_3a.dot(b_3a));
+        assert_eq!(a.cross(b), a_3a.cross(b_3a).into());
+        assert_relative_eq!(a.magnitude() as Real, a_3a.length(), epsilon = 1e-6);
+        assert_relative_eq!(
+            a.normalize(),
+            Vector3::from(a_3a.normalize_or_zero()),
+            epsilon = 1e-6
+        );
+        assert_eq!(a + b, vec![a_3a, b_3a].into_iter().sum::<Vec3A>().into());
+    }
+
+    #[test]
+    fn normalize_zero() {
+        assert_eq!(Vec3A::ZERO, Vec3A::ZERO.normalize_or_zero());
+    }
+
+    #[test]
+    fn ignores_padding() {
+        let v = (Vec3A::X + Vec3A::Y) * 2.0 - Vec3A::Z / 0.5;
+        assert_eq!(Vec3A::new(2.0, 2.0, -2.0), v);
+        // 0 / 0 makes the padding lane NaN.
+        let v = Vec3A::new(1.0, 1.0, 1.0) / 0.0;
+        assert!(v.0[3].is_nan());
+        assert_eq!(
+            Vec3A::new(Real::INFINITY, Real::INFINITY, Real::INFINITY),
+            v
+        );
+        assert_eq!(
+            4 * std::mem::size_of::<Real>(),
+            std::mem::align_of::<Vec3A>()
+        );
+    }
+}
@@ -0,0 +1,206 @@
+/// Streams simulation snapshots over TCP, so that a second instance can render the same simulation remotely.
+/// The host steps the simulation and sends a snapshot of it to each connected viewer every frame. Viewers
+/// render the latest snapshot they've received rather than simulating. Each message is the snapshot's length,
+/// as a little-endian u32, followed by the snapshot. Sockets are nonblocking, so neither end waits on the other.
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use crate::simulation::snapshot::Snapshot;
+
+/// A viewer which falls this far behind skips snapshots until it catches up, so a slow viewer can't stall the host.
+const MAX_PENDING_BYTES: usize = 1 << 20;
+/// Messages claiming to be longer than this are treated as corrupt, disconnecting the viewer.
+const MAX_SNAPSHOT_BYTES: usize = 1 << 26;
+
+/// A simulation networked with a twin instance.
+pub enum Twin {
+    Host(Host),
+    Viewer(Viewer),
+}
+
+/// A viewer connected to the host, and the bytes yet to be sent to it.
+struct Connection {
+    stream: TcpStream,
+    pending: Vec<u8>,
+}
+
+/// Sends snapshots to the viewers connected to it.
+pub struct Host {
+    listener: TcpListener,
+    connections: Vec<Connection>,
+}
+
+impl Host {
+    /// Listens for viewers at the address, e.g. 0.0.0.0:7878.
+    pub fn bind(address: &str) -> Result<Host, &'static str> {
+        let listener = TcpListener::bind(address).map_err(|_| "Couldn't listen on the address!")?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|_| "Couldn't listen on the address!")?;
+        Ok(Host {
+            listener,
+            connections: Vec::new(),
+        })
+    }
+
+    pub fn get_local_address(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    pub fn get_viewer_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Accepts any new viewers, then sends each the simulation's snapshot.
+    /// Viewers which have disconnected are dropped.
+    pub fn send<S: Snapshot>(&mut self, simulation: &S) {
+        while let Ok((stream, address)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_err() {
+                continue;
+            }
+            let _ = stream.set_nodelay(true);
+            log::info!("Viewer connected from {}", address);
+            self.connections.push(Connection {
+                stream,
+                pending: Vec::new(),
+            });
+        }
+
+        let snapshot = simulation.to_snapshot();
+        self.connections.retain_mut(|connection| {
+            if connection.pending.len() < MAX_PENDING_BYTES {
+                connection
+                    .pending
+                    .extend_from_slice(&(snapshot.len() as u32).to_le_bytes());
+                connection.pending.extend_from_slice(&snapshot);
+            }
+            while !connection.pending.is_empty() {
+                match connection.stream.write(&connection.pending) {
+                    Ok(0) => return false,
+                    Ok(written) => {
+                        connection.pending.drain(..written);
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => return false,
+                }
+            }
+            true
+        });
+    }
+}
+
+/// Receives snapshots from a host.
+pub struct Viewer {
+    stream: TcpStream,
+    received: Vec<u8>,
+    connected: bool,
+}
+
+impl Viewer {
+    /// Connects to the host at the address, e.g. 192.168.0.2:7878.
+    pub fn connect(address: &str) -> Result<Viewer, &'static str> {
+        let stream = TcpStream::connect(address).map_err(|_| "Couldn't connect to the host!")?;
+        stream
+            .set_nonblocking(true)
+            .map_err(|_| "Couldn't connect to the host!")?;
+        let _ = stream.set_nodelay(true);
+        Ok(Viewer {
+            stream,
+            received: Vec::new(),
+            connected: true,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Restores the simulation from the latest snapshot received since the last call, if any.
+    /// Returns whether the simulation was restored. Once the host disconnects, the simulation is left as it was.
+    pub fn receive<S: Snapshot>(&mut self, simulation: &mut S) -> bool {
+        let mut buffer = [0; 4096];
+        while self.connected {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.disconnect(),
+                Ok(read) => self.received.extend_from_slice(&buffer[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.disconnect(),
+            }
+        }
+
+        // Only the latest complete snapshot matters; older ones are skipped.
+        let mut latest = None;
+        let mut start = 0;
+        while self.received.len() - start >= 4 {
+            let length =
+                u32::from_le_bytes(self.received[start..start + 4].try_into().unwrap()) as usize;
+            if length > MAX_SNAPSHOT_BYTES {
+                self.disconnect();
+                self.received.clear();
+                return false;
+            }
+            if self.received.len() - start - 4 < length {
+                break;
+            }
+            latest = Some(start + 4..start + 4 + length);
+            start += 4 + length;
+        }
+        let restored = match latest {
+            Some(range) => match simulation.restore_snapshot(&self.received[range]) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::warn!("Failed to restore snapshot: {}", err);
+                    false
+                }
+            },
+            None => false,
+        };
+        self.received.drain(..start);
+        restored
+    }
+
+    fn disconnect(&mut self) {
+        if self.connected {
+            log::info!("Host disconnected");
+        }
+        self.connected = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::bounce;
+    use crate::simulation::checksum::Checksummed;
+    use cgmath::Vector3;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn viewer_follows_host() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let address = host.get_local_address().unwrap().to_string();
+        let mut viewer = Viewer::connect(&address).unwrap();
+
+        let mut hosted = bounce::State::new();
+        hosted.launch(Vector3::new(0.1, 0.2, 0.3), Vector3::new(1.0, -1.0, 0.5));
+        let mut viewed = bounce::State::new();
+        let start = Instant::now();
+        // Steps and sends until the viewer has caught up to the host's latest snapshot.
+        loop {
+            hosted.step();
+            host.send(&hosted);
+            std::thread::sleep(Duration::from_millis(5));
+            if viewer.receive(&mut viewed) && viewed.checksum() == hosted.checksum() {
+                break;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10));
+        }
+        assert_eq!(host.get_viewer_count(), 1);
+
+        drop(host);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!viewer.receive(&mut viewed));
+        assert!(!viewer.is_connected());
+    }
+}
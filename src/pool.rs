@@ -0,0 +1,288 @@
+/// A pool of objects addressed by generational handles.
+/// Removing an object frees its slot for reuse without moving any other objects, and
+/// bumps the slot's generation so that stale handles to the removed object don't alias
+/// whatever is inserted into the slot next.
+use std::{hash::Hash, marker::PhantomData};
+
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    // fn() -> T keeps the handle Send, Sync, and Copy regardless of T.
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// The index of the handle's slot in its pool. Unique among the pool's live objects,
+    /// but reused by objects inserted after this one is removed.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+// These are implemented by hand because deriving them would require T to implement them.
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+// Handles order by slot, then generation, so that sets of them can be iterated in a consistent order.
+impl<T> PartialOrd for Handle<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Handle<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.index, self.generation).cmp(&(other.index, other.generation))
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+pub struct Pool<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<u32>,
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Pool<T> {
+        Pool {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    /// Creates a pool which can hold the given number of objects before reallocating.
+    pub fn with_capacity(capacity: usize) -> Pool<T> {
+        Pool {
+            slots: Vec::with_capacity(capacity),
+            free_slots: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        let index = match self.free_slots.pop() {
+            Some(index) => {
+                self.slots[index as usize].value = Some(value);
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        Handle {
+            index,
+            generation: self.slots[index as usize].generation,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Removes the object, returning it, or None if the handle is stale.
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index())?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+        Some(value)
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index())
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index())
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// The number of live objects.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the handles and live objects, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value.as_ref().map(|value| {
+                (
+                    Handle {
+                        index: index as u32,
+                        generation: slot.generation,
+                        _marker: PhantomData,
+                    },
+                    value,
+                )
+            })
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let generation = slot.generation;
+                slot.value.as_mut().map(|value| {
+                    (
+                        Handle {
+                            index: index as u32,
+                            generation,
+                            _marker: PhantomData,
+                        },
+                        value,
+                    )
+                })
+            })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Pool::new()
+    }
+}
+
+impl<T> FromIterator<T> for Pool<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut pool = Pool::with_capacity(iter.size_hint().0);
+        for value in iter {
+            pool.insert(value);
+        }
+        pool
+    }
+}
+
+/// Panics if the handle is stale.
+impl<T> std::ops::Index<Handle<T>> for Pool<T> {
+    type Output = T;
+
+    fn index(&self, handle: Handle<T>) -> &Self::Output {
+        self.get(handle).expect("Stale pool handle!")
+    }
+}
+
+/// Panics if the handle is stale.
+impl<T> std::ops::IndexMut<Handle<T>> for Pool<T> {
+    fn index_mut(&mut self, handle: Handle<T>) -> &mut Self::Output {
+        self.get_mut(handle).expect("Stale pool handle!")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::Pool;
+
+    #[test]
+    fn insert_get() {
+        let mut pool = Pool::new();
+        let a = pool.insert("a");
+        let b = pool.insert("b");
+        assert_eq!(Some(&"a"), pool.get(a));
+        assert_eq!("b", pool[b]);
+        assert_eq!(2, pool.len());
+    }
+
+    #[test]
+    fn remove_invalidates_handle() {
+        let mut pool = Pool::new();
+        let a = pool.insert("a");
+        assert_eq!(Some("a"), pool.remove(a));
+        assert_eq!(None, pool.get(a));
+        assert_eq!(None, pool.remove(a));
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn reuses_slots() {
+        let mut pool = Pool::with_capacity(2);
+        let a = pool.insert("a");
+        let b = pool.insert("b");
+        pool.remove(a);
+        let c = pool.insert("c");
+        // The freed slot is reused, but the stale handle doesn't alias the new object.
+        assert_eq!(a.index(), c.index());
+        assert_ne!(a, c);
+        assert_eq!(None, pool.get(a));
+        assert_eq!("c", pool[c]);
+        assert_eq!("b", pool[b]);
+        assert_eq!(2, pool.slots.len());
+    }
+
+    #[test]
+    fn iter_skips_free_slots() {
+        let mut pool: Pool<i32> = (0..4).collect();
+        let handles = pool.iter().map(|(handle, _)| handle).collect_vec();
+        pool.remove(handles[1]);
+        pool.remove(handles[2]);
+        assert_eq!(vec![0, 3], pool.values().copied().collect_vec());
+        for (_, value) in pool.iter_mut() {
+            *value *= 2;
+        }
+        assert_eq!(
+            vec![(handles[0], &0), (handles[3], &6)],
+            pool.iter().collect_vec()
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! The types most applications embedding a simulation need, e.g. `use feriphys::prelude::*;`.
+//! Simulations and their configs share names across modules, so they're re-exported under the
+//! simulation's name, e.g. RigidBodySimulation and RigidBodyConfig.
+pub use cgmath::{InnerSpace, Quaternion, Rotation3, Vector3, Zero};
+
+pub use crate::pool::{Handle, Pool};
+pub use crate::simulation::billiards::{
+    config::Config as BilliardsConfig, Simulation as BilliardsSimulation,
+};
+pub use crate::simulation::bounce::{Config as BounceConfig, State as BouncingBall};
+pub use crate::simulation::bridge::{
+    config::Config as BridgeConfig, Simulation as BridgeSimulation,
+};
+pub use crate::simulation::charged::{
+    config::Config as ChargedConfig, Simulation as ChargedSimulation,
+};
+pub use crate::simulation::checksum::{Checksum, Checksummed};
+pub use crate::simulation::collidable_mesh::CollidableMesh;
+pub use crate::simulation::configurable::Configurable;
+pub use crate::simulation::energy::EnergyBreakdown;
+pub use crate::simulation::flocking::flocking::{
+    Config as FlockingConfig, Simulation as FlockingSimulation,
+};
+pub use crate::simulation::force::{Body, Drag, ForceGenerator, Gravity, Wind};
+pub use crate::simulation::lbm::{config::Config as LbmConfig, Simulation as LbmSimulation};
+pub use crate::simulation::particles_cpu::particles::{
+    Config as ParticlesConfig, Simulation as ParticlesSimulation,
+};
+pub use crate::simulation::plugin::{BehaviorPlugin, ForcePlugin, Load, Plugins};
+pub use crate::simulation::quantity;
+pub use crate::simulation::reflect::{Field, Reflect};
+pub use crate::simulation::rigidbody::{
+    config::Config as RigidBodyConfig, rigidbody::RigidBody,
+    simulation::Simulation as RigidBodySimulation,
+};
+pub use crate::simulation::snapshot::Snapshot;
+pub use crate::simulation::sph::{config::Config as SphConfig, Simulation as SphSimulation};
+pub use crate::simulation::springy::{
+    config::Config as SpringyConfig, simulation::Simulation as SpringySimulation,
+    springy_mesh::SpringyMesh,
+};
+pub use crate::simulation::state::Integration;
+pub use crate::simulation::target_range::{
+    config::Config as TargetRangeConfig, Simulation as TargetRangeSimulation,
+};
+pub use crate::simulation::vortex::{
+    config::Config as VortexConfig, Simulation as VortexSimulation,
+};
+pub use crate::simulation::wind_tunnel::{
+    config::Config as WindTunnelConfig, Simulation as WindTunnelSimulation,
+};
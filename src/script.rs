@@ -0,0 +1,344 @@
+/// A small command language for driving the demos from their consoles at runtime, e.g. to spawn objects,
+/// tweak configs, and apply impulses, without recompiling. Each line is one or more statements separated
+/// by semicolons, and each statement is a command's name followed by its arguments, separated by whitespace.
+/// The commands are the demo's own, from its Sandbox, along with the built-in commands below, which
+/// register callbacks running a statement every so many simulation steps. It's a command console rather than a
+/// scripting language: there are no variables, expressions, or control flow.
+use crate::localization::{tr, tr_format};
+
+/// A command a sandbox runs, for the help text.
+pub struct Command {
+    pub name: &'static str,
+    pub arguments: &'static str,
+    pub description: &'static str,
+}
+
+/// The commands every script runs, besides its sandbox's.
+pub const BUILT_IN_COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        arguments: "",
+        description: "Lists the commands.",
+    },
+    Command {
+        name: "every",
+        arguments: "<steps> <statement>",
+        description: "Runs the statement after every so many simulation steps.",
+    },
+    Command {
+        name: "callbacks",
+        arguments: "",
+        description: "Lists the callbacks registered with every.",
+    },
+    Command {
+        name: "stop",
+        arguments: "[callback]",
+        description: "Stops the callback, or all callbacks.",
+    },
+];
+
+/// A simulation, and anything else a demo lets its console drive.
+pub trait Sandbox {
+    /// The commands the sandbox runs.
+    fn commands(&self) -> &'static [Command];
+
+    /// Runs one of the sandbox's commands with its arguments, returning a description of what it did.
+    fn run(&mut self, command: &str, arguments: &[&str]) -> Result<String, &'static str>;
+}
+
+/// Parses the arguments as numbers, e.g. the components of a vector.
+pub fn numbers(arguments: &[&str]) -> Result<Vec<f32>, &'static str> {
+    arguments
+        .iter()
+        .map(|argument| {
+            argument
+                .parse::<f32>()
+                .map_err(|_| "Arguments must be numbers!")
+        })
+        .collect()
+}
+
+/// A statement run after every interval simulation steps.
+struct Callback {
+    interval: u64,
+    statement: String,
+}
+
+/// The callbacks registered by a console's commands, which persist between lines.
+pub struct Script {
+    callbacks: Vec<Callback>,
+    steps: u64,
+}
+
+impl Script {
+    pub fn new() -> Script {
+        Script {
+            callbacks: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    /// Runs each of the line's statements in turn, returning each's output or error.
+    pub fn run<S: Sandbox>(
+        &mut self,
+        line: &str,
+        sandbox: &mut S,
+    ) -> Vec<Result<String, &'static str>> {
+        line.split(';')
+            .map(str::trim)
+            .filter(|statement| !statement.is_empty())
+            .map(|statement| self.run_statement(statement, sandbox))
+            .collect()
+    }
+
+    /// Runs the callbacks due after a simulation step, returning the errors of any which failed.
+    /// Failing callbacks are stopped, so that their error isn't repeated every step.
+    pub fn step<S: Sandbox>(&mut self, sandbox: &mut S) -> Vec<&'static str> {
+        self.steps += 1;
+        let steps = self.steps;
+        let mut errors = Vec::new();
+        self.callbacks.retain(|callback| {
+            if !steps.is_multiple_of(callback.interval) {
+                return true;
+            }
+            match run_command(&callback.statement, sandbox) {
+                Ok(_) => true,
+                Err(err) => {
+                    errors.push(err);
+                    false
+                }
+            }
+        });
+        errors
+    }
+
+    fn run_statement<S: Sandbox>(
+        &mut self,
+        statement: &str,
+        sandbox: &mut S,
+    ) -> Result<String, &'static str> {
+        let (name, rest) = statement
+            .split_once(char::is_whitespace)
+            .map_or((statement, ""), |(name, rest)| (name, rest.trim()));
+        match name {
+            "help" => Ok(BUILT_IN_COMMANDS
+                .iter()
+                .chain(sandbox.commands())
+                .map(|command| {
+                    format!(
+                        "{} {} - {}",
+                        command.name,
+                        command.arguments,
+                        tr(command.description)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "every" => {
+                let (interval, statement) = rest
+                    .split_once(char::is_whitespace)
+                    .ok_or("Usage: every <steps> <statement>")?;
+                let interval = interval
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|interval| *interval > 0)
+                    .ok_or("The interval must be a positive number of steps!")?;
+                let statement = statement.trim();
+                if statement.split_whitespace().next() == Some("every") {
+                    return Err("Callbacks can't register callbacks!");
+                }
+                check_command(statement, sandbox)?;
+                self.callbacks.push(Callback {
+                    interval,
+                    statement: statement.to_owned(),
+                });
+                Ok(tr_format(
+                    "Registered callback {}",
+                    &[&(self.callbacks.len() - 1)],
+                ))
+            }
+            "callbacks" => Ok(self
+                .callbacks
+                .iter()
+                .enumerate()
+                .map(|(index, callback)| {
+                    tr_format(
+                        "{}: every {} steps, {}",
+                        &[&index, &callback.interval, &callback.statement],
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "stop" => {
+                if rest.is_empty() {
+                    self.callbacks.clear();
+                    return Ok(tr("Stopped all callbacks").to_owned());
+                }
+                let index = rest
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index < self.callbacks.len())
+                    .ok_or("No such callback!")?;
+                self.callbacks.remove(index);
+                Ok(tr_format("Stopped callback {}", &[&index]))
+            }
+            _ => run_command(statement, sandbox),
+        }
+    }
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Script::new()
+    }
+}
+
+/// Errors if the statement isn't one of the sandbox's commands.
+fn check_command<S: Sandbox>(statement: &str, sandbox: &S) -> Result<(), &'static str> {
+    let name = statement.split_whitespace().next().unwrap_or("");
+    if sandbox
+        .commands()
+        .iter()
+        .any(|command| command.name == name)
+    {
+        Ok(())
+    } else {
+        Err("No such command! Try help.")
+    }
+}
+
+/// Runs the statement as one of the sandbox's commands.
+fn run_command<S: Sandbox>(statement: &str, sandbox: &mut S) -> Result<String, &'static str> {
+    check_command(statement, sandbox)?;
+    let mut words = statement.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let arguments: Vec<&str> = words.collect();
+    sandbox.run(name, &arguments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A counter, which the commands add to.
+    struct Counter {
+        count: f32,
+    }
+
+    impl Sandbox for Counter {
+        fn commands(&self) -> &'static [Command] {
+            &[Command {
+                name: "add",
+                arguments: "<amount>",
+                description: "Adds to the count.",
+            }]
+        }
+
+        fn run(&mut self, _command: &str, arguments: &[&str]) -> Result<String, &'static str> {
+            match numbers(arguments)?[..] {
+                [amount] => {
+                    self.count += amount;
+                    Ok(self.count.to_string())
+                }
+                _ => Err("Expected one value!"),
+            }
+        }
+    }
+
+    #[test]
+    fn runs_statements() {
+        let mut script = Script::new();
+        let mut counter = Counter { count: 0.0 };
+        assert_eq!(
+            script.run("add 1; add 2.5", &mut counter),
+            vec![Ok("1".to_owned()), Ok("3.5".to_owned())]
+        );
+        assert_eq!(
+            script.run("add x; subtract 1; add 1 2", &mut counter),
+            vec![
+                Err("Arguments must be numbers!"),
+                Err("No such command! Try help."),
+                Err("Expected one value!")
+            ]
+        );
+        assert_eq!(counter.count, 3.5);
+    }
+
+    #[test]
+    fn reports_malformed_statements() {
+        let mut script = Script::new();
+        let mut counter = Counter { count: 0.0 };
+        // Empty statements are skipped, and whitespace around statements and arguments ignored.
+        assert!(script.run("", &mut counter).is_empty());
+        assert!(script.run(" ; ;", &mut counter).is_empty());
+        assert_eq!(
+            script.run("  add   2 ;", &mut counter),
+            vec![Ok("2".to_owned())]
+        );
+        assert_eq!(
+            script.run(
+                "every; every 2; every x add 1; every -1 add 1; every 2 subtract 1",
+                &mut counter
+            ),
+            vec![
+                Err("Usage: every <steps> <statement>"),
+                Err("Usage: every <steps> <statement>"),
+                Err("The interval must be a positive number of steps!"),
+                Err("The interval must be a positive number of steps!"),
+                Err("No such command! Try help."),
+            ]
+        );
+        assert_eq!(
+            script.run("stop x; stop 0; stop -1", &mut counter),
+            vec![
+                Err("No such callback!"),
+                Err("No such callback!"),
+                Err("No such callback!"),
+            ]
+        );
+        assert_eq!(
+            script.run("add; add 1e; Add 1", &mut counter),
+            vec![
+                Err("Expected one value!"),
+                Err("Arguments must be numbers!"),
+                Err("No such command! Try help."),
+            ]
+        );
+        // None of the failed statements ran, nor registered callbacks.
+        assert_eq!(counter.count, 2.0);
+        assert_eq!(
+            script.run("callbacks", &mut counter),
+            vec![Ok(String::new())]
+        );
+    }
+
+    #[test]
+    fn runs_callbacks_every_interval() {
+        let mut script = Script::new();
+        let mut counter = Counter { count: 0.0 };
+        assert!(script.run("every 3 add 1", &mut counter)[0].is_ok());
+        assert!(script.run("every 2 add 10", &mut counter)[0].is_ok());
+        assert!(script.run("every 0 add 1", &mut counter)[0].is_err());
+        assert!(script.run("every 2 every 2 add 1", &mut counter)[0].is_err());
+        for _ in 0..6 {
+            assert!(script.step(&mut counter).is_empty());
+        }
+        // Twice every third step, and three times every other step.
+        assert_eq!(counter.count, 32.0);
+
+        assert!(script.run("stop 0", &mut counter)[0].is_ok());
+        script.step(&mut counter);
+        script.step(&mut counter);
+        assert_eq!(counter.count, 42.0);
+        assert!(script.run("stop 1", &mut counter)[0].is_err());
+
+        // Callbacks whose arguments are wrong fail when they run, and are stopped.
+        assert!(script.run("every 1 add x", &mut counter)[0].is_ok());
+        assert_eq!(
+            script.step(&mut counter),
+            vec!["Arguments must be numbers!"]
+        );
+        assert!(script.step(&mut counter).is_empty());
+        assert_eq!(counter.count, 52.0);
+    }
+}
@@ -27,6 +27,8 @@ struct InstanceInput {
     @location(9) normal_matrix_0: vec3<f32>,
     @location(10) normal_matrix_1: vec3<f32>,
     @location(11) normal_matrix_2: vec3<f32>,
+    // Replaces the vertex color by its alpha.
+    @location(12) color: vec4<f32>,
 }
 
 struct VertexOutput {
@@ -53,8 +55,8 @@ fn vs_main(
         instance.normal_matrix_2,
     );
     var out: VertexOutput;
-    out.color = model.color;
-    out.world_normal = normal_matrix * model.normal;
+    out.color = mix(model.color, instance.color.rgb, instance.color.a);
+    out.world_normal = normalize(normal_matrix * model.normal);
     var world_position: vec4<f32> = model_matrix * vec4<f32>(model.position, 1.0);
     out.world_position = world_position.xyz;
     out.clip_position = camera.view_proj * world_position;
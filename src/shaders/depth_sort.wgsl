@@ -0,0 +1,87 @@
+// Sorts instances back to front by their distance from the camera, so that translucent instances
+// drawn in order blend correctly. The sort is a bitonic sort of (depth, index) keys, padded to a power
+// of two, which is dispatched once per stage of the sorting network. The sorted instances are then
+// gathered into a second instance buffer to draw from.
+
+// The instance buffers are indexed as floats, since InstanceRaw's matrix members would be padded.
+// Must match the size of InstanceRaw, in floats: its model and normal matrices and its color.
+let INSTANCE_STRIDE: u32 = 29u;
+// The offset of the model matrix's translation within an instance, in floats.
+let TRANSLATION_OFFSET: u32 = 12u;
+
+struct Params {
+    camera_position: vec3<f32>,
+    // The number of instances to sort; the remaining keys are padding.
+    count: u32,
+};
+
+// One stage of the bitonic sorting network: its keys are compared with the key stride away,
+// and sorted descending or ascending by whether they're in an even or odd block.
+struct Stage {
+    block: u32,
+    stride: u32,
+    // Uniforms must be 16 byte aligned.
+    _padding: vec2<u32>,
+};
+
+struct Key {
+    depth: f32,
+    index: u32,
+};
+
+@group(0) @binding(0)
+var<storage, read> instances: array<f32>;
+@group(0) @binding(1)
+var<storage, read_write> sorted_instances: array<f32>;
+@group(0) @binding(2)
+var<storage, read_write> keys: array<Key>;
+@group(0) @binding(3)
+var<uniform> params: Params;
+@group(1) @binding(0)
+var<uniform> stage: Stage;
+
+@compute @workgroup_size(64)
+fn cs_keys(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x;
+    if (i >= arrayLength(&keys)) {
+        return;
+    }
+    if (i >= params.count) {
+        // Padding sorts after every instance.
+        keys[i] = Key(-1.0, i);
+        return;
+    }
+    let base = i * INSTANCE_STRIDE + TRANSLATION_OFFSET;
+    let position = vec3<f32>(instances[base], instances[base + 1u], instances[base + 2u]);
+    let offset = position - params.camera_position;
+    keys[i] = Key(dot(offset, offset), i);
+}
+
+@compute @workgroup_size(64)
+fn cs_bitonic(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x;
+    let partner = i ^ stage.stride;
+    if (i >= arrayLength(&keys) || partner <= i) {
+        return;
+    }
+    let a = keys[i];
+    let b = keys[partner];
+    let descending = (i & stage.block) == 0u;
+    if ((descending && a.depth < b.depth) || (!descending && a.depth > b.depth)) {
+        keys[i] = b;
+        keys[partner] = a;
+    }
+}
+
+@compute @workgroup_size(64)
+fn cs_gather(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x;
+    if (i >= params.count) {
+        return;
+    }
+    let source = keys[i].index * INSTANCE_STRIDE;
+    let destination = i * INSTANCE_STRIDE;
+    for (var offset = 0u; offset < INSTANCE_STRIDE; offset = offset + 1u) {
+        sorted_instances[destination + offset] = instances[source + offset];
+    }
+}
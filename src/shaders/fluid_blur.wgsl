@@ -0,0 +1,49 @@
+// A separable bilateral blur of the fluid's depth, smoothing the particles into a surface without
+// blurring across its silhouette. Empty pixels have a depth of 0.
+
+@group(0) @binding(0)
+var t_depth: texture_2d<f32>;
+
+struct Blur {
+    // The step between samples; (1, 0) to blur horizontally, or (0, 1) vertically.
+    direction: vec2<i32>,
+    padding: vec2<i32>,
+}
+@group(0) @binding(1)
+var<uniform> blur: Blur;
+
+let BLUR_RADIUS: i32 = 8;
+let SPATIAL_SIGMA: f32 = 4.0;
+// The difference in depth, in meters, over which samples stop contributing.
+let DEPTH_FALLOFF: f32 = 0.05;
+
+// A single triangle covering the screen, generated from the vertex index alone.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) f32 {
+    let center = vec2<i32>(position.xy);
+    let depth = textureLoad(t_depth, center, 0).x;
+    if (depth <= 0.0) {
+        return 0.0;
+    }
+    let max_coords = textureDimensions(t_depth) - vec2<i32>(1, 1);
+    var sum = 0.0;
+    var weight_sum = 0.0;
+    for (var i: i32 = -BLUR_RADIUS; i <= BLUR_RADIUS; i = i + 1) {
+        let coords = clamp(center + blur.direction * i, vec2<i32>(0, 0), max_coords);
+        let sample_depth = textureLoad(t_depth, coords, 0).x;
+        if (sample_depth > 0.0) {
+            let offset = f32(i);
+            let range = (sample_depth - depth) / DEPTH_FALLOFF;
+            let weight = exp(-offset * offset / (2.0 * SPATIAL_SIGMA * SPATIAL_SIGMA) - range * range);
+            sum = sum + sample_depth * weight;
+            weight_sum = weight_sum + weight;
+        }
+    }
+    return sum / weight_sum;
+}
@@ -0,0 +1,28 @@
+// Shared by the screen-space fluid shaders, which are each appended to this.
+
+struct Fluid {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    eye_position: vec4<f32>,
+    // The camera view's viewport of the render target in pixels: x, y, width, height.
+    viewport: vec4<f32>,
+    particle_radius: f32,
+}
+@group(0) @binding(0)
+var<uniform> fluid: Fluid;
+
+// The view space position at a pixel position, whose depth is the distance along -z.
+fn view_position(pixel: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec2<f32>(
+        (pixel.x - fluid.viewport.x) / fluid.viewport.z * 2.0 - 1.0,
+        1.0 - (pixel.y - fluid.viewport.y) / fluid.viewport.w * 2.0
+    );
+    return vec3<f32>(ndc.x * depth / fluid.proj[0][0], ndc.y * depth / fluid.proj[1][1], -depth);
+}
+
+// The depth buffer value of a view space position.
+fn clip_depth(position: vec3<f32>) -> f32 {
+    let clip = fluid.proj * vec4<f32>(position, 1.0);
+    return clip.z / clip.w;
+}
@@ -0,0 +1,50 @@
+
+// Draws each particle as a sphere impostor on a camera-facing quad, writing the distance to its
+// surface. Empty pixels are left at 0.
+
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // The position on the quad, from -1 to 1.
+    @location(0) corner: vec2<f32>,
+    @location(1) view_center: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    instance: InstanceInput,
+) -> VertexOutput {
+    // The corners of a triangle strip quad.
+    let corner = vec2<f32>(f32(index & 1u), f32((index >> 1u) & 1u)) * 2.0 - 1.0;
+    let view_center = (fluid.view * instance.model_matrix_3).xyz;
+    var out: VertexOutput;
+    out.corner = corner;
+    out.view_center = view_center;
+    out.clip_position = fluid.proj * vec4<f32>(view_center + vec3<f32>(corner * fluid.particle_radius, 0.0), 1.0);
+    return out;
+}
+
+struct DepthOutput {
+    @location(0) depth: f32,
+    @builtin(frag_depth) frag_depth: f32,
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> DepthOutput {
+    let radius_squared = dot(in.corner, in.corner);
+    if (radius_squared > 1.0) {
+        discard;
+    }
+    let position = in.view_center + vec3<f32>(in.corner, sqrt(1.0 - radius_squared)) * fluid.particle_radius;
+    var out: DepthOutput;
+    out.depth = -position.z;
+    out.frag_depth = clip_depth(position);
+    return out;
+}
@@ -0,0 +1,102 @@
+
+// Shades the fluid's smoothed depth as a water surface refracting and reflecting the sky, and
+// draws the sky behind the scene.
+
+@group(1) @binding(0)
+var t_depth: texture_2d<f32>;
+
+struct Light {
+    position: vec3<f32>,
+    color: vec3<f32>,
+}
+@group(2) @binding(0)
+var<uniform> light: Light;
+
+let REFRACTIVE_INDEX: f32 = 1.33;
+// The fraction of light water reflects when viewed head on.
+let BASE_REFLECTANCE: f32 = 0.02;
+
+// A gradient from the ground through the horizon to the zenith, standing in for a skybox.
+fn sky(direction: vec3<f32>) -> vec3<f32> {
+    let horizon = vec3<f32>(0.85, 0.88, 0.92);
+    if (direction.y < 0.0) {
+        return mix(horizon, vec3<f32>(0.3, 0.27, 0.24), min(-direction.y * 4.0, 1.0));
+    }
+    return mix(horizon, vec3<f32>(0.25, 0.45, 0.8), sqrt(direction.y));
+}
+
+// The GLSL refract(), for a unit incident direction and normal, which this version of WGSL lacks.
+// Light entering the water from the air is never totally internally reflected.
+fn refract_direction(incident: vec3<f32>, normal: vec3<f32>, eta: f32) -> vec3<f32> {
+    let cos_incident = dot(normal, incident);
+    let k = 1.0 - eta * eta * (1.0 - cos_incident * cos_incident);
+    return eta * incident - (eta * cos_incident + sqrt(max(k, 0.0))) * normal;
+}
+
+// A single triangle covering the screen at the far plane, generated from the vertex index alone.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+
+fn load_depth(coords: vec2<i32>) -> f32 {
+    return textureLoad(t_depth, coords, 0).x;
+}
+
+// The difference in view space position to the neighbouring pixel at the offset, taken on
+// whichever side is closer in depth so that normals don't bend around the silhouette.
+fn position_difference(coords: vec2<i32>, offset: vec2<i32>, center: vec3<f32>) -> vec3<f32> {
+    let forward_depth = load_depth(coords + offset);
+    let backward_depth = load_depth(coords - offset);
+    let forward = view_position(vec2<f32>(coords + offset) + 0.5, forward_depth) - center;
+    let backward = center - view_position(vec2<f32>(coords - offset) + 0.5, backward_depth);
+    if (backward_depth <= 0.0 || (forward_depth > 0.0 && abs(forward.z) < abs(backward.z))) {
+        return forward;
+    }
+    return backward;
+}
+
+struct SurfaceOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) frag_depth: f32,
+}
+
+@fragment
+fn fs_surface(@builtin(position) position: vec4<f32>) -> SurfaceOutput {
+    let coords = vec2<i32>(position.xy);
+    let depth = load_depth(coords);
+    if (depth <= 0.0) {
+        discard;
+    }
+    let center = view_position(position.xy, depth);
+    // Pixel rows go down, so this cross product faces the camera.
+    let view_normal = cross(
+        position_difference(coords, vec2<i32>(0, 1), center),
+        position_difference(coords, vec2<i32>(1, 0), center)
+    );
+    let normal = normalize((fluid.inv_view * vec4<f32>(view_normal, 0.0)).xyz);
+    let world_position = (fluid.inv_view * vec4<f32>(center, 1.0)).xyz;
+    let view_dir = normalize(fluid.eye_position.xyz - world_position);
+
+    let cos_theta = max(dot(normal, view_dir), 0.0);
+    let fresnel = BASE_REFLECTANCE + (1.0 - BASE_REFLECTANCE) * pow(1.0 - cos_theta, 5.0);
+    let reflected = sky(reflect(-view_dir, normal));
+    let water_color = vec3<f32>(0.35, 0.65, 0.85);
+    let refracted = sky(refract_direction(-view_dir, normal, 1.0 / REFRACTIVE_INDEX)) * water_color;
+
+    let light_dir = normalize(light.position - world_position);
+    let half_dir = normalize(view_dir + light_dir);
+    let specular = pow(max(dot(normal, half_dir), 0.0), 128.0) * light.color;
+
+    var out: SurfaceOutput;
+    out.color = vec4<f32>(mix(refracted, reflected, fresnel) + specular, 1.0);
+    out.frag_depth = clip_depth(center);
+    return out;
+}
+
+@fragment
+fn fs_sky(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let direction = (fluid.inv_view * vec4<f32>(view_position(position.xy, 1.0), 0.0)).xyz;
+    return vec4<f32>(sky(normalize(direction)), 1.0);
+}
@@ -0,0 +1,55 @@
+// Recomputes smooth vertex normals for a mesh from its positions and indices.
+// Each invocation handles one vertex, summing the (area-weighted) normals of its adjacent faces.
+// The faces adjacent to vertex v are vertex_faces[vertex_face_offsets[v]..vertex_face_offsets[v + 1]].
+
+// The vertex buffer is indexed as floats since vec3 members would be padded to 16 bytes.
+// The layout gives the number of floats per vertex, and the offset of the normal within a vertex,
+// so that both ColoredVertex and ModelVertex buffers can be used. Positions are always at offset 0.
+struct VertexLayout {
+    stride: u32,
+    normal_offset: u32,
+    // Uniforms must be 16 byte aligned.
+    _padding: vec2<u32>,
+};
+
+@group(0) @binding(0)
+var<storage, read_write> vertices: array<f32>;
+@group(0) @binding(1)
+var<storage, read> indices: array<u32>;
+@group(0) @binding(2)
+var<storage, read> vertex_face_offsets: array<u32>;
+@group(0) @binding(3)
+var<storage, read> vertex_faces: array<u32>;
+@group(0) @binding(4)
+var<uniform> vertex_layout: VertexLayout;
+
+fn position(vertex: u32) -> vec3<f32> {
+    let base = vertex * vertex_layout.stride;
+    return vec3<f32>(vertices[base], vertices[base + 1u], vertices[base + 2u]);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let vertex = global_id.x;
+    if (vertex + 1u >= arrayLength(&vertex_face_offsets)) {
+        return;
+    }
+
+    var normal = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = vertex_face_offsets[vertex]; i < vertex_face_offsets[vertex + 1u]; i = i + 1u) {
+        let face = vertex_faces[i];
+        let a = position(indices[face * 3u]);
+        let b = position(indices[face * 3u + 1u]);
+        let c = position(indices[face * 3u + 2u]);
+        // Matches the winding used by graphics::util::get_normals().
+        normal = normal + cross(a - b, a - c);
+    }
+    if (length(normal) > 0.0) {
+        normal = normalize(normal);
+    }
+
+    let base = vertex * vertex_layout.stride + vertex_layout.normal_offset;
+    vertices[base] = normal.x;
+    vertices[base + 1u] = normal.y;
+    vertices[base + 2u] = normal.z;
+}
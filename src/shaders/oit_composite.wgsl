@@ -0,0 +1,26 @@
+// Composites the transparent fragments accumulated by oit_particles.wgsl over the opaque scene.
+
+@group(0) @binding(0)
+var t_accum: texture_2d<f32>;
+@group(0) @binding(1)
+var t_reveal: texture_2d<f32>;
+
+// A single triangle covering the screen, generated from the vertex index alone.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+    let reveal = textureLoad(t_reveal, coords, 0).r;
+    // Skip pixels without any transparent fragments.
+    if (reveal >= 0.9999) {
+        discard;
+    }
+    let accum = textureLoad(t_accum, coords, 0);
+    let average_color = accum.rgb / max(accum.a, 1e-5);
+    return vec4<f32>(average_color, 1.0 - reveal);
+}
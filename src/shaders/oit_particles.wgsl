@@ -0,0 +1,120 @@
+// Lit, colored particles drawn with weighted, blended order-independent transparency,
+// or alpha blended in order if they've been sorted back to front.
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Light {
+    position: vec3<f32>,
+    color: vec3<f32>,
+}
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+// TODO we could fix up the InstanceRaw getter to use locations starting at 3.
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
+    // Replaces the vertex color by its alpha.
+    @location(12) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let normal_matrix = mat3x3<f32>(
+        instance.normal_matrix_0,
+        instance.normal_matrix_1,
+        instance.normal_matrix_2,
+    );
+    var out: VertexOutput;
+    out.color = mix(model.color, instance.color.rgb, instance.color.a);
+    out.world_normal = normalize(normal_matrix * model.normal);
+    var world_position: vec4<f32> = model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_proj * world_position;
+    return out;
+}
+
+// The opacity of each particle.
+let PARTICLE_ALPHA: f32 = 0.35;
+
+// Weighted, blended order-independent transparency, following McGuire and Bavoil 2013.
+// Rather than sorting, each fragment adds its premultiplied color to the accumulation target,
+// weighted to favor nearer and more opaque fragments, and multiplies the revealage target
+// by its transparency. The composite pass then averages the accumulated color.
+struct OitOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) reveal: f32,
+};
+
+fn shade(in: VertexOutput) -> vec3<f32> {
+    let ambient_strength = 0.1;
+    let ambient_color = light.color * ambient_strength;
+
+    let light_dir = normalize(light.position - in.world_position);
+
+    let diffuse_strength = max(dot(in.world_normal, light_dir), 0.0);
+    let diffuse_color = light.color * diffuse_strength;
+
+    let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+    let half_dir = normalize(view_dir + light_dir);
+
+    let specular_strength = pow(max(dot(in.world_normal, half_dir), 0.0), 32.0);
+    let specular_color = specular_strength * light.color;
+
+    return (ambient_color + diffuse_color + specular_color) * in.color;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> OitOutput {
+    let color = shade(in);
+    let alpha = PARTICLE_ALPHA;
+    let depth = in.clip_position.z;
+    let weight = clamp(
+        pow(min(1.0, alpha * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - depth * 0.9, 3.0),
+        1e-2,
+        3e3,
+    );
+
+    var out: OitOutput;
+    out.accum = vec4<f32>(color * alpha, alpha) * weight;
+    out.reveal = alpha;
+    return out;
+}
+
+// Particles sorted back to front are simply alpha blended over what's behind them.
+@fragment
+fn fs_sorted(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade(in), PARTICLE_ALPHA);
+}
@@ -54,7 +54,7 @@ fn vs_main(
 
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    out.world_normal = normal_matrix * model.normal;
+    out.world_normal = normalize(normal_matrix * model.normal);
     var world_position: vec4<f32> = model_matrix * vec4<f32>(model.position, 1.0);
     out.world_position = world_position.xyz;
     out.clip_position = camera.view_proj * world_position;
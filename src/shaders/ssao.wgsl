@@ -0,0 +1,106 @@
+// Screen-space ambient occlusion (Crytek 2007, with a normal-oriented hemisphere), estimating how
+// much of the sky each pixel's surface can see from the scene's depth alone.
+
+struct Ssao {
+    proj: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    // The camera view's viewport of the render target in pixels: x, y, width, height.
+    viewport: vec4<f32>,
+    // The radius of the hemisphere around each pixel which is searched for occluders, in meters.
+    radius: f32,
+    sample_count: u32,
+    // Offsets within the unit hemisphere around +z, more of them close to its center.
+    kernel: array<vec4<f32>, 64>,
+}
+@group(0) @binding(0)
+var<uniform> ssao: Ssao;
+
+// Replaced with texture_depth_multisampled_2d when the depth texture is multisampled.
+@group(1) @binding(0)
+var t_depth: texture_depth_2d;
+
+// Samples this much closer than the surface are still counted as occluding, to avoid acne.
+let BIAS: f32 = 0.025;
+
+// A single triangle covering the screen, generated from the vertex index alone.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn load_depth(coords: vec2<i32>) -> f32 {
+    let max_coords = textureDimensions(t_depth) - vec2<i32>(1, 1);
+    return textureLoad(t_depth, clamp(coords, vec2<i32>(0, 0), max_coords), 0);
+}
+
+// The view space position at a pixel position with the depth buffer value.
+fn view_position(pixel: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec4<f32>(
+        (pixel.x - ssao.viewport.x) / ssao.viewport.z * 2.0 - 1.0,
+        1.0 - (pixel.y - ssao.viewport.y) / ssao.viewport.w * 2.0,
+        depth,
+        1.0
+    );
+    let position = ssao.inv_proj * ndc;
+    return position.xyz / position.w;
+}
+
+fn pixel_position(coords: vec2<i32>) -> vec3<f32> {
+    return view_position(vec2<f32>(coords) + 0.5, load_depth(coords));
+}
+
+// The difference in view space position to the neighbouring pixel at the offset, taken on
+// whichever side is closer in depth so that normals don't bend around silhouettes.
+fn position_difference(coords: vec2<i32>, offset: vec2<i32>, center: vec3<f32>) -> vec3<f32> {
+    let forward = pixel_position(coords + offset) - center;
+    let backward = center - pixel_position(coords - offset);
+    if (abs(forward.z) < abs(backward.z)) {
+        return forward;
+    }
+    return backward;
+}
+
+// A different rotation of the kernel for each pixel of a 4 x 4 tile, which the blur averages over.
+// Multiplying by 7 scatters the rotations of neighbouring pixels.
+fn kernel_rotation(coords: vec2<i32>) -> f32 {
+    let cell = vec2<u32>(coords) & vec2<u32>(3u, 3u);
+    return 6.2831853 * f32((cell.x * 4u + cell.y) * 7u % 16u) / 16.0;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) f32 {
+    let coords = vec2<i32>(position.xy);
+    let depth = load_depth(coords);
+    // Nothing was drawn here.
+    if (depth >= 1.0) {
+        return 1.0;
+    }
+    let origin = view_position(position.xy, depth);
+    // Pixel rows go down, so this cross product faces the camera.
+    let normal = normalize(cross(
+        position_difference(coords, vec2<i32>(0, 1), origin),
+        position_difference(coords, vec2<i32>(1, 0), origin)
+    ));
+
+    let angle = kernel_rotation(coords);
+    let random = vec3<f32>(cos(angle), sin(angle), 0.0);
+    let tangent = normalize(random - normal * dot(random, normal));
+    let bitangent = cross(normal, tangent);
+    let tbn = mat3x3<f32>(tangent, bitangent, normal);
+
+    var occlusion = 0.0;
+    for (var i: u32 = 0u; i < ssao.sample_count; i = i + 1u) {
+        let sample_position = origin + tbn * ssao.kernel[i].xyz * ssao.radius;
+        let clip = ssao.proj * vec4<f32>(sample_position, 1.0);
+        let ndc = clip.xy / clip.w;
+        let pixel = ssao.viewport.xy + (ndc * vec2<f32>(0.5, -0.5) + 0.5) * ssao.viewport.zw;
+        let scene_z = pixel_position(vec2<i32>(pixel)).z;
+        // Occluders much further away than the radius are unrelated surfaces in front.
+        let range = smoothstep(0.0, 1.0, ssao.radius / abs(origin.z - scene_z));
+        if (scene_z >= sample_position.z + BIAS) {
+            occlusion = occlusion + range;
+        }
+    }
+    return 1.0 - occlusion / f32(ssao.sample_count);
+}
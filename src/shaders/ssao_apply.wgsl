@@ -0,0 +1,30 @@
+// Blurs the ambient occlusion over the rotated kernel's noise, and darkens the scene by it.
+
+@group(0) @binding(0)
+var t_occlusion: texture_2d<f32>;
+
+// The blur covers 4 x 4 pixels, the period of the kernel's rotations.
+let BLUR_SIZE: i32 = 4;
+
+// A single triangle covering the screen, generated from the vertex index alone.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Multiplied with the scene's color by the pipeline's blending.
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy) - vec2<i32>(BLUR_SIZE / 2, BLUR_SIZE / 2);
+    let max_coords = textureDimensions(t_occlusion) - vec2<i32>(1, 1);
+    var sum = 0.0;
+    for (var x: i32 = 0; x < BLUR_SIZE; x = x + 1) {
+        for (var y: i32 = 0; y < BLUR_SIZE; y = y + 1) {
+            let sample_coords = clamp(coords + vec2<i32>(x, y), vec2<i32>(0, 0), max_coords);
+            sum = sum + textureLoad(t_occlusion, sample_coords, 0).x;
+        }
+    }
+    let occlusion = sum / f32(BLUR_SIZE * BLUR_SIZE);
+    return vec4<f32>(occlusion, occlusion, occlusion, 1.0);
+}
@@ -0,0 +1,297 @@
+/// Keyframe animation of kinematic objects, which move along a prescribed path rather than being simulated,
+/// e.g. moving platforms, spinning paddles, and oscillating walls. Simulations collide against them,
+/// so they push simulated objects around without being pushed back.
+use cgmath::{InnerSpace, Quaternion, Rotation, Rotation3, Vector3, VectorSpace};
+
+use super::collidable_mesh::{CollidableMesh, Face};
+
+/// A rigid transform, applying the rotation and then the translation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl Transform {
+    pub fn transform_point(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.rotation.rotate_vector(point) + self.position
+    }
+
+    pub fn inverse_transform_point(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.rotation.invert().rotate_vector(point - self.position)
+    }
+
+    pub fn transform_vector(&self, vector: Vector3<f32>) -> Vector3<f32> {
+        self.rotation.rotate_vector(vector)
+    }
+}
+
+/// The transform of an animated object at a time, in seconds, from the start of its animation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+}
+
+/// What an animation does after its last keyframe.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Looping {
+    /// Holds the last keyframe.
+    Once,
+    /// Starts again from the first keyframe.
+    Loop,
+    /// Plays backwards to the first keyframe, then forwards again.
+    PingPong,
+}
+
+/// Interpolates between keyframes, linearly in position and spherically in rotation.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    keyframes: Vec<Keyframe>,
+    looping: Looping,
+}
+
+impl Animation {
+    /// The keyframes must be sorted by time, and there must be at least one.
+    pub fn new(keyframes: Vec<Keyframe>, looping: Looping) -> Result<Animation, &'static str> {
+        if keyframes.is_empty() {
+            return Err("Animations need at least one keyframe!");
+        }
+        if keyframes
+            .windows(2)
+            .any(|keyframes| keyframes[1].time <= keyframes[0].time)
+        {
+            return Err("Keyframes must be sorted by time!");
+        }
+        Ok(Animation { keyframes, looping })
+    }
+
+    /// Moves back and forth between the transform and its translation by the offset, taking the period,
+    /// in seconds, for the round trip.
+    pub fn oscillate(transform: Transform, offset: Vector3<f32>, period: f32) -> Animation {
+        let keyframe = |time, position| Keyframe {
+            time,
+            position,
+            rotation: transform.rotation,
+        };
+        Animation::new(
+            vec![
+                keyframe(0.0, transform.position),
+                keyframe(period / 2.0, transform.position + offset),
+            ],
+            Looping::PingPong,
+        )
+        .expect("Oscillations have sorted keyframes.")
+    }
+
+    /// Turns about the axis through the transform's position, taking the period, in seconds, for each turn.
+    pub fn spin(transform: Transform, axis: Vector3<f32>, period: f32) -> Animation {
+        // Thirds of a turn, so that each interpolation takes the intended way around.
+        const KEYFRAMES: usize = 3;
+        let axis = axis.normalize();
+        let keyframes = (0..=KEYFRAMES)
+            .map(|index| {
+                let fraction = index as f32 / KEYFRAMES as f32;
+                Keyframe {
+                    time: fraction * period,
+                    position: transform.position,
+                    rotation: Quaternion::from_axis_angle(
+                        axis,
+                        cgmath::Rad(fraction * 2.0 * std::f32::consts::PI),
+                    ) * transform.rotation,
+                }
+            })
+            .collect();
+        Animation::new(keyframes, Looping::Loop).expect("Spins have sorted keyframes.")
+    }
+
+    /// The length of one play through the keyframes, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.keyframes[self.keyframes.len() - 1].time - self.keyframes[0].time
+    }
+
+    pub fn sample(&self, time: f32) -> Transform {
+        let first = &self.keyframes[0];
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return Transform {
+                position: first.position,
+                rotation: first.rotation,
+            };
+        }
+        let elapsed = time - first.time;
+        let elapsed = match self.looping {
+            Looping::Once => elapsed.clamp(0.0, duration),
+            Looping::Loop => elapsed.rem_euclid(duration),
+            Looping::PingPong => {
+                let elapsed = elapsed.rem_euclid(2.0 * duration);
+                if elapsed > duration {
+                    2.0 * duration - elapsed
+                } else {
+                    elapsed
+                }
+            }
+        };
+        let time = first.time + elapsed;
+
+        let next_index = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time)
+            .unwrap_or(self.keyframes.len() - 1)
+            .max(1);
+        let from = &self.keyframes[next_index - 1];
+        let to = &self.keyframes[next_index];
+        let amount = ((time - from.time) / (to.time - from.time)).clamp(0.0, 1.0);
+        // q and -q are the same rotation; interpolate the shorter way around.
+        let to_rotation = if from.rotation.dot(to.rotation) < 0.0 {
+            -to.rotation
+        } else {
+            to.rotation
+        };
+        Transform {
+            position: from.position.lerp(to.position, amount),
+            rotation: from.rotation.slerp(to_rotation, amount).normalize(),
+        }
+    }
+}
+
+/// An obstacle moved by an animation. Its mesh is in its local space.
+pub struct KinematicObstacle {
+    mesh: CollidableMesh,
+    animation: Animation,
+    time: f32,
+    transform: Transform,
+    previous_transform: Transform,
+}
+
+impl KinematicObstacle {
+    pub fn new(mesh: CollidableMesh, animation: Animation) -> KinematicObstacle {
+        let transform = animation.sample(0.0);
+        KinematicObstacle {
+            mesh,
+            animation,
+            time: 0.0,
+            transform,
+            previous_transform: transform,
+        }
+    }
+
+    /// Moves the obstacle along its animation by dt seconds.
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt;
+        self.previous_transform = self.transform;
+        self.transform = self.animation.sample(self.time);
+    }
+
+    pub fn get_mesh(&self) -> &CollidableMesh {
+        &self.mesh
+    }
+
+    pub fn get_transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// The transform before the last advance(), so that collisions can follow the obstacle's motion over the step.
+    pub fn get_previous_transform(&self) -> &Transform {
+        &self.previous_transform
+    }
+
+    /// The velocity over the last step of the point, given in the obstacle's local space.
+    pub fn get_point_velocity(&self, point: Vector3<f32>, dt: f32) -> Vector3<f32> {
+        (self.transform.transform_point(point) - self.previous_transform.transform_point(point))
+            / dt
+    }
+
+    pub fn get_world_faces(&self) -> Vec<Face> {
+        self.mesh
+            .get_faces()
+            .iter()
+            .map(|face| Face {
+                v0: self.transform.transform_point(face.v0),
+                v1: self.transform.transform_point(face.v1),
+                v2: self.transform.transform_point(face.v2),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::quantity;
+    use cgmath::Deg;
+
+    fn assert_near(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).magnitude() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn interpolates_and_loops() {
+        let keyframe = |time, x| Keyframe {
+            time,
+            position: Vector3::new(x, 0.0, 0.0),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        };
+        let keyframes = vec![keyframe(0.0, 0.0), keyframe(1.0, 2.0), keyframe(2.0, 0.0)];
+        let once = Animation::new(keyframes.clone(), Looping::Once).unwrap();
+        assert_near(once.sample(0.25).position, Vector3::new(0.5, 0.0, 0.0));
+        assert_near(once.sample(1.5).position, Vector3::new(1.0, 0.0, 0.0));
+        assert_near(once.sample(5.0).position, Vector3::new(0.0, 0.0, 0.0));
+        let looped = Animation::new(keyframes.clone(), Looping::Loop).unwrap();
+        assert_near(looped.sample(2.5).position, Vector3::new(1.0, 0.0, 0.0));
+        let ping_pong = Animation::oscillate(Transform::default(), Vector3::unit_y(), 2.0);
+        assert_near(ping_pong.sample(0.5).position, Vector3::new(0.0, 0.5, 0.0));
+        assert_near(ping_pong.sample(1.5).position, Vector3::new(0.0, 0.5, 0.0));
+        assert!(
+            Animation::new(vec![keyframe(1.0, 0.0), keyframe(0.0, 0.0)], Looping::Once).is_err()
+        );
+    }
+
+    #[test]
+    fn rising_platform_lifts_rigidbody() {
+        use crate::simulation::geometry;
+        use crate::simulation::rigidbody::{rigidbody::RigidBody, simulation::Simulation};
+
+        let rigidbody =
+            RigidBody::new(Vector3::new(0.0, 0.6, 0.0), quantity::kilograms(1.0)).unwrap();
+        let mut simulation = Simulation::new(vec![rigidbody], Vec::new());
+        let (vertex_positions, indices) = geometry::get_cube_vertices();
+        let vertex_positions = vertex_positions
+            .iter()
+            .map(|v| Vector3::new(v.x * 4.0, v.y * 0.2, v.z * 4.0))
+            .collect();
+        // The platform's top starts just below the rigidbody's bottom, and rises by a meter over two seconds.
+        simulation.spawn_kinematic_obstacle(KinematicObstacle::new(
+            CollidableMesh::new(vertex_positions, indices),
+            Animation::oscillate(Transform::default(), Vector3::unit_y(), 4.0),
+        ));
+        for _ in 0..1000 {
+            simulation.step();
+        }
+        let rigidbody = simulation.get_rigidbodies().values().next().unwrap();
+        // The platform has risen half a meter, so the rigidbody must have been pushed up ahead of it.
+        assert!(rigidbody.get_position().y > 0.5 + 0.1 + 0.5 - 0.01);
+        assert!(rigidbody.get_state().velocity().y > 0.0);
+    }
+
+    #[test]
+    fn spins_the_whole_way_around() {
+        let spin = Animation::spin(Transform::default(), Vector3::unit_y(), 4.0);
+        let quarter = spin.sample(1.0);
+        let expected = Quaternion::from_axis_angle(Vector3::unit_y(), Deg(90.0));
+        assert!(quarter.rotation.dot(expected).abs() > 0.9999);
+        let three_quarters = spin.sample(3.0).transform_point(Vector3::unit_x());
+        assert_near(three_quarters, Vector3::new(0.0, 0.0, 1.0));
+    }
+}
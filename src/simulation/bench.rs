@@ -0,0 +1,227 @@
+/// Headless workloads for benchmarking the simulations, and a timer to run them with. The workloads are built
+/// without the renderer, so `cargo bench` measures them with or without the render feature, e.g. the integrators
+/// at several state sizes, a springy cube, a block of SPH fluid, and a flock.
+use super::collidable_mesh::CollidableMesh;
+use super::configurable::Configurable;
+use super::flocking::flocking;
+use super::geometry;
+use super::quantity;
+use super::sph;
+use super::springy::{simulation as springy, springy_mesh::SpringyMesh};
+use super::state::{stateful_fields, State, StateWriter, Stateful};
+use super::units::GravityPreset;
+use crate::math::Real;
+
+use cgmath::{Vector3, Zero};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The sizes the integrators are benchmarked at, either side of the sizes states are stepped in parallel from.
+pub const STATE_SIZES: [usize; 3] = [64, 1024, 16384];
+const FLOCK_SIZE: u32 = 110;
+const STATE_DT: f32 = 0.001;
+
+/// A simulation stepped by each iteration of a benchmark.
+pub struct Workload {
+    name: String,
+    step: Box<dyn FnMut()>,
+}
+
+impl Workload {
+    pub fn new(name: impl Into<String>, step: impl FnMut() + 'static) -> Workload {
+        Workload {
+            name: name.into(),
+            step: Box::new(step),
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn step(&mut self) {
+        (self.step)()
+    }
+}
+
+/// An element of the integrator benchmarks' states: a point on a damped spring to the origin.
+#[derive(Clone, Copy)]
+struct Oscillator {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+}
+
+impl Stateful for Oscillator {
+    type Context = ();
+
+    stateful_fields!(
+        6;
+        position: Vector3<f32>,
+        velocity: Vector3<f32>,
+    );
+
+    fn write_derivative(&self, _: &(), derivative: &mut [Real]) {
+        StateWriter::new(derivative)
+            .push(&self.velocity)
+            .push(&(-self.position - 0.1 * self.velocity))
+            .finish()
+    }
+
+    fn num_position_elements() -> usize {
+        3
+    }
+}
+
+fn oscillators(size: usize) -> State<Oscillator> {
+    State::new(
+        (0..size)
+            .map(|i| Oscillator {
+                position: Vector3::new(i as f32, 1.0, 0.0),
+                velocity: Vector3::zero(),
+            })
+            .collect(),
+    )
+}
+
+/// Euler steps of a state of the size.
+pub fn euler_step(size: usize) -> Workload {
+    let mut state = oscillators(size);
+    Workload::new(format!("State::euler_step/{}", size), move || {
+        state.euler_step(STATE_DT, &())
+    })
+}
+
+/// RK4 steps of a state of the size.
+pub fn rk4_step(size: usize) -> Workload {
+    let mut state = oscillators(size);
+    Workload::new(format!("State::rk4_step/{}", size), move || {
+        state.rk4_step(STATE_DT, &())
+    })
+}
+
+/// Steps of the spring-mass-damper demo's cube, falling onto its floor.
+pub fn springy_cube() -> Workload {
+    let (vertex_positions, indices) = geometry::get_cube_vertices();
+    let cube = SpringyMesh::new(
+        vertex_positions,
+        indices,
+        quantity::kilograms(80.0),
+        quantity::newtons_per_meter(10000.0),
+        quantity::newton_seconds_per_meter(50.0),
+        None,
+        &None,
+    );
+    let floor = CollidableMesh::new(
+        vec![
+            Vector3::new(-1.0, -2.0, 1.0),
+            Vector3::new(1.0, -2.0, 1.0),
+            Vector3::new(1.0, -2.0, -1.0),
+            Vector3::new(-1.0, -2.0, -1.0),
+        ],
+        vec![0, 1, 2, 0, 2, 3],
+    );
+    let mut simulation = springy::Simulation::new(vec![cube], vec![floor]);
+    Workload::new("springy cube", move || {
+        simulation.step();
+    })
+}
+
+/// Steps of the SPH demo's block of water, falling in its container.
+pub fn sph() -> Workload {
+    let mut simulation = sph::Simulation::new();
+    let mut config = simulation.get_config().clone();
+    config.gravity = GravityPreset::Earth.vector();
+    simulation.sync_config(&config);
+    Workload::new("sph", move || {
+        simulation.step();
+    })
+}
+
+/// Steps of a flock of the flocking demo's size, without obstacles or lead boids.
+pub fn flocking() -> Workload {
+    let mut simulation =
+        flocking::Simulation::new(vec![Vector3::zero()], FLOCK_SIZE, None, None, None, None);
+    Workload::new(format!("flocking/{}", FLOCK_SIZE), move || {
+        simulation.step();
+    })
+}
+
+/// Every workload, in the order they're benchmarked.
+pub fn suite() -> Vec<Workload> {
+    let mut workloads = Vec::new();
+    workloads.extend(STATE_SIZES.map(euler_step));
+    workloads.extend(STATE_SIZES.map(rk4_step));
+    workloads.extend([springy_cube(), sph(), flocking()]);
+    workloads
+}
+
+/// The time a workload's steps took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub name: String,
+    pub iterations: u32,
+    pub mean: Duration,
+    /// The fastest step, which is least disturbed by the rest of the machine.
+    pub min: Duration,
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<28} mean {:>10.3} µs   min {:>10.3} µs   ({} iterations)",
+            self.name,
+            self.mean.as_secs_f64() * 1e6,
+            self.min.as_secs_f64() * 1e6,
+            self.iterations
+        )
+    }
+}
+
+/// Steps the workload for the warm up, and then times each of its steps until the budget is spent, and at least once.
+pub fn measure(workload: &mut Workload, warm_up: Duration, budget: Duration) -> Timing {
+    let start = Instant::now();
+    while start.elapsed() < warm_up {
+        workload.step();
+    }
+    let mut iterations = 0;
+    let mut total = Duration::ZERO;
+    let mut min = Duration::MAX;
+    while iterations == 0 || total < budget {
+        let start = Instant::now();
+        workload.step();
+        let elapsed = start.elapsed();
+        iterations += 1;
+        total += elapsed;
+        min = min.min(elapsed);
+    }
+    Timing {
+        name: workload.name.clone(),
+        iterations,
+        mean: total / iterations,
+        min,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn measures_every_step() {
+        let steps = Rc::new(Cell::new(0));
+        let counter = steps.clone();
+        let mut workload = Workload::new("sleep", move || {
+            counter.set(counter.get() + 1);
+            std::thread::sleep(Duration::from_millis(1));
+        });
+        let timing = measure(&mut workload, Duration::ZERO, Duration::from_millis(5));
+        assert_eq!("sleep", timing.name);
+        assert_eq!(steps.get(), timing.iterations);
+        assert!(timing.iterations >= 1 && timing.iterations <= 5);
+        assert!(timing.min >= Duration::from_millis(1) && timing.min <= timing.mean);
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Scenario {
+    /// A racked triangle of object balls and a cue ball on a pocketed pool table.
+    Billiards,
+    /// A puck and two mallets on a nearly frictionless table, with goals in its ends.
+    AirHockey,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 2] = [Scenario::Billiards, Scenario::AirHockey];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scenario::Billiards => "Billiards",
+            Scenario::AirHockey => "Air Hockey",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub scenario: Scenario,
+    pub dt: f32, // Seconds as f32
+    /// The deceleration of moving discs by the table, in m/s².
+    pub rolling_friction: f32,
+    /// The angular deceleration of spinning discs by the table, in rad/s².
+    pub spin_friction: f32,
+    pub disc_restitution: f32,
+    pub cushion_restitution: f32,
+    /// The coefficient of friction between discs, which throws struck discs and transfers spin between them.
+    pub disc_friction: f32,
+    /// The coefficient of friction between discs and the cushions, which turns spin into a sideways bounce.
+    pub cushion_friction: f32,
+    /// The impulse of the cue per meter it's drawn back, in N·s/m.
+    pub cue_strength: f32,
+    /// How far right of the disc's center the cue strikes it, as a fraction of its radius, spinning it
+    /// counterclockwise seen from above. Negative english strikes left of center.
+    pub english: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut config = Self {
+            scenario: Scenario::Billiards,
+            dt: Duration::from_millis(2).as_secs_f32(),
+            rolling_friction: 0.0,
+            spin_friction: 0.0,
+            disc_restitution: 0.0,
+            cushion_restitution: 0.0,
+            disc_friction: 0.0,
+            cushion_friction: 0.0,
+            cue_strength: 0.0,
+            english: 0.0,
+        };
+        config.set_scenario(Scenario::Billiards);
+        config
+    }
+}
+
+impl Config {
+    /// Sets the friction, restitution, and cue strength to suit the scenario's table.
+    pub fn set_scenario(&mut self, scenario: Scenario) {
+        self.scenario = scenario;
+        match scenario {
+            Scenario::Billiards => {
+                self.rolling_friction = 0.2;
+                self.spin_friction = 2.0;
+                self.disc_restitution = 0.95;
+                self.cushion_restitution = 0.75;
+                self.disc_friction = 0.06;
+                self.cushion_friction = 0.2;
+                self.cue_strength = 1.0;
+            }
+            Scenario::AirHockey => {
+                self.rolling_friction = 0.02;
+                self.spin_friction = 0.5;
+                self.disc_restitution = 0.9;
+                self.cushion_restitution = 0.9;
+                self.disc_friction = 0.1;
+                self.cushion_friction = 0.1;
+                self.cue_strength = 0.3;
+            }
+        }
+    }
+}
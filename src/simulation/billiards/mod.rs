@@ -0,0 +1,581 @@
+/// Discs sliding on a table in 2D, e.g. billiard balls or air hockey pucks, bouncing off each other and the
+/// table's cushions. Contacts have Coulomb friction, so spinning discs bounce sideways off the cushions, and
+/// glancing collisions throw discs and transfer spin between them, like english in billiards.
+/// The table is the plane of x and y; the demo lays it down in the world's xz plane, with y towards -z,
+/// so that spin is counterclockwise seen from above.
+pub mod config;
+
+use self::config::{Config, Scenario};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::conservation::MomentumAudit;
+
+use cgmath::{InnerSpace, Vector2, Zero};
+
+/// The radius and mass of a pool ball.
+const BALL_RADIUS: f32 = 0.028575;
+const BALL_MASS: f32 = 0.17;
+/// Half the length and width of a 9 ft pool table's playing surface.
+const POOL_TABLE: Vector2<f32> = Vector2::new(1.27, 0.635);
+const POCKET_RADIUS: f32 = 0.06;
+
+const PUCK_RADIUS: f32 = 0.04;
+const PUCK_MASS: f32 = 0.02;
+const MALLET_RADIUS: f32 = 0.05;
+const MALLET_MASS: f32 = 0.15;
+const AIR_HOCKEY_TABLE: Vector2<f32> = Vector2::new(1.0, 0.5);
+const GOAL_RADIUS: f32 = 0.12;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum DiscKind {
+    Cue,
+    Object,
+    Puck,
+    Mallet,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Disc {
+    pub position: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    /// The angular velocity about the table's normal, in rad/s, counterclockwise seen from above.
+    pub spin: f32,
+    /// The angle the disc has turned through, for drawing its spin.
+    pub angle: f32,
+    pub radius: f32,
+    pub mass: f32,
+    pub kind: DiscKind,
+    /// Where the disc is put back, at rest, if it's potted; discs without a spawn are removed.
+    pub spawn: Option<Vector2<f32>>,
+}
+
+impl Disc {
+    fn new(kind: DiscKind, position: Vector2<f32>, radius: f32, mass: f32) -> Disc {
+        Disc {
+            position,
+            velocity: Vector2::zero(),
+            spin: 0.0,
+            angle: 0.0,
+            radius,
+            mass,
+            kind,
+            spawn: None,
+        }
+    }
+
+    /// The moment of inertia of a uniform disc about its axis.
+    fn inertia(&self) -> f32 {
+        0.5 * self.mass * self.radius * self.radius
+    }
+
+    /// The velocity of the point on the disc's rim in the direction.
+    fn rim_velocity(&self, direction: Vector2<f32>) -> Vector2<f32> {
+        self.velocity + self.spin * self.radius * perp(direction)
+    }
+}
+
+/// A straight cushion, which discs bounce off of.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Segment {
+    pub start: Vector2<f32>,
+    pub end: Vector2<f32>,
+}
+
+impl Segment {
+    /// The nearest point on the segment to the point.
+    pub fn closest_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        let along = self.end - self.start;
+        let length_squared = along.magnitude2();
+        if length_squared == 0.0 {
+            return self.start;
+        }
+        let t = ((point - self.start).dot(along) / length_squared).clamp(0.0, 1.0);
+        self.start + along * t
+    }
+}
+
+/// The vector turned a quarter turn counterclockwise.
+fn perp(vector: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(-vector.y, vector.x)
+}
+
+/// The 2D cross product, i.e. the torque of the force applied at the offset from the center.
+fn cross(offset: Vector2<f32>, force: Vector2<f32>) -> f32 {
+    offset.x * force.y - offset.y * force.x
+}
+
+/// The normal and tangential impulses of a contact between bodies moving apart along the normal at the
+/// relative velocity, which is the second's contact point's relative to the first's. The inverse masses
+/// are the bodies' summed inverse masses, and that plus their inverse moments of inertia times their squared
+/// radii, which resists sliding. The tangential impulse stops the sliding, if friction allows it to.
+fn contact_impulse(
+    relative_velocity: Vector2<f32>,
+    normal: Vector2<f32>,
+    inverse_mass: f32,
+    inverse_tangent_mass: f32,
+    restitution: f32,
+    friction: f32,
+) -> (f32, f32) {
+    let normal_speed = relative_velocity.dot(normal);
+    if normal_speed >= 0.0 {
+        return (0.0, 0.0);
+    }
+    let normal_impulse = -(1.0 + restitution) * normal_speed / inverse_mass;
+    let sliding_speed = relative_velocity.dot(perp(normal));
+    let max_friction = friction * normal_impulse;
+    let tangent_impulse =
+        (-sliding_speed / inverse_tangent_mass).clamp(-max_friction, max_friction);
+    (normal_impulse, tangent_impulse)
+}
+
+/// Bounces the discs off each other, if they're touching and closing, and pushes them apart.
+fn collide_discs(a: &mut Disc, b: &mut Disc, config: &Config, audit: &mut MomentumAudit) {
+    let offset = b.position - a.position;
+    let distance = offset.magnitude();
+    let penetration = a.radius + b.radius - distance;
+    if penetration <= 0.0 || distance == 0.0 {
+        return;
+    }
+    let normal = offset / distance;
+    let (inverse_a, inverse_b) = (1.0 / a.mass, 1.0 / b.mass);
+    let relative_velocity = b.rim_velocity(-normal) - a.rim_velocity(normal);
+    let (normal_impulse, tangent_impulse) = contact_impulse(
+        relative_velocity,
+        normal,
+        inverse_a + inverse_b,
+        inverse_a
+            + inverse_b
+            + a.radius * a.radius / a.inertia()
+            + b.radius * b.radius / b.inertia(),
+        config.disc_restitution,
+        config.disc_friction,
+    );
+    let impulse = normal * normal_impulse + perp(normal) * tangent_impulse;
+    a.velocity -= impulse * inverse_a;
+    b.velocity += impulse * inverse_b;
+    audit.exchange(impulse.extend(0.0));
+    a.spin -= cross(normal * a.radius, impulse) / a.inertia();
+    b.spin += cross(-normal * b.radius, impulse) / b.inertia();
+
+    let correction = normal * penetration / (inverse_a + inverse_b);
+    a.position -= correction * inverse_a;
+    b.position += correction * inverse_b;
+}
+
+/// Bounces the disc off the cushion, if it's touching and closing, and pushes it out.
+fn collide_cushion(disc: &mut Disc, cushion: &Segment, config: &Config, audit: &mut MomentumAudit) {
+    let offset = cushion.closest_point(disc.position) - disc.position;
+    let distance = offset.magnitude();
+    let penetration = disc.radius - distance;
+    if penetration <= 0.0 || distance == 0.0 {
+        return;
+    }
+    let normal = offset / distance;
+    let inverse_mass = 1.0 / disc.mass;
+    let (normal_impulse, tangent_impulse) = contact_impulse(
+        -disc.rim_velocity(normal),
+        normal,
+        inverse_mass,
+        inverse_mass + disc.radius * disc.radius / disc.inertia(),
+        config.cushion_restitution,
+        config.cushion_friction,
+    );
+    let impulse = normal * normal_impulse + perp(normal) * tangent_impulse;
+    disc.velocity -= impulse * inverse_mass;
+    audit.absorb(impulse.extend(0.0));
+    disc.spin -= cross(normal * disc.radius, impulse) / disc.inertia();
+    disc.position -= normal * penetration;
+}
+
+/// The cushions around the rectangular table of the half extents, leaving gaps of the radius around
+/// the pockets on its edges, which discs fall through.
+fn get_cushions(
+    half_extents: Vector2<f32>,
+    pockets: &[Vector2<f32>],
+    pocket_radius: f32,
+) -> Vec<Segment> {
+    let corners = [
+        Vector2::new(-half_extents.x, -half_extents.y),
+        Vector2::new(half_extents.x, -half_extents.y),
+        Vector2::new(half_extents.x, half_extents.y),
+        Vector2::new(-half_extents.x, half_extents.y),
+    ];
+    let mut cushions = Vec::new();
+    for side in 0..corners.len() {
+        let (start, end) = (corners[side], corners[(side + 1) % corners.len()]);
+        let length = (end - start).magnitude();
+        let direction = (end - start) / length;
+        // The stretches of the side between pockets, by distance along it.
+        let mut gaps: Vec<(f32, f32)> = pockets
+            .iter()
+            .filter(|pocket| cross(direction, *pocket - start).abs() < 1e-4)
+            .map(|pocket| {
+                let along = (pocket - start).dot(direction);
+                (along - pocket_radius, along + pocket_radius)
+            })
+            .collect();
+        gaps.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut from = 0.0;
+        for (gap_start, gap_end) in gaps.into_iter().chain([(length, length)]) {
+            if gap_start > from {
+                cushions.push(Segment {
+                    start: start + direction * from,
+                    end: start + direction * gap_start.min(length),
+                });
+            }
+            from = f32::max(from, gap_end);
+        }
+    }
+    cushions
+}
+
+/// Discs on a table, struck by a cue and slowed by the table's friction.
+pub struct Simulation {
+    config: Config,
+    discs: Vec<Disc>,
+    half_extents: Vector2<f32>,
+    pockets: Vec<Vector2<f32>>,
+    pocket_radius: f32,
+    cushions: Vec<Segment>,
+    /// The discs potted since the table was set up, including respawned ones.
+    potted: usize,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        let mut simulation = Simulation {
+            config: Config::default(),
+            discs: Vec::new(),
+            half_extents: Vector2::zero(),
+            pockets: Vec::new(),
+            pocket_radius: 0.0,
+            cushions: Vec::new(),
+            potted: 0,
+        };
+        simulation.reset();
+        simulation
+    }
+
+    /// Sets the scenario's table up again, with its discs at rest.
+    pub fn reset(&mut self) {
+        self.potted = 0;
+        match self.config.scenario {
+            Scenario::Billiards => {
+                let table = POOL_TABLE;
+                self.half_extents = table;
+                self.pocket_radius = POCKET_RADIUS;
+                self.pockets = [-1.0, 0.0, 1.0]
+                    .into_iter()
+                    .flat_map(|x| [-1.0, 1.0].map(|y| Vector2::new(x * table.x, y * table.y)))
+                    .collect();
+                let head_spot = Vector2::new(-table.x / 2.0, 0.0);
+                let mut cue = Disc::new(DiscKind::Cue, head_spot, BALL_RADIUS, BALL_MASS);
+                cue.spawn = Some(head_spot);
+                self.discs = vec![cue];
+                // The rack, a triangle pointing at the cue ball from the foot spot, with a hair between balls.
+                let spacing = 2.0 * BALL_RADIUS * 1.001;
+                for row in 0..5 {
+                    for ball in 0..=row {
+                        let position = Vector2::new(
+                            table.x / 2.0 + row as f32 * spacing * 3.0f32.sqrt() / 2.0,
+                            (ball as f32 - row as f32 / 2.0) * spacing,
+                        );
+                        self.discs.push(Disc::new(
+                            DiscKind::Object,
+                            position,
+                            BALL_RADIUS,
+                            BALL_MASS,
+                        ));
+                    }
+                }
+            }
+            Scenario::AirHockey => {
+                let table = AIR_HOCKEY_TABLE;
+                self.half_extents = table;
+                self.pocket_radius = GOAL_RADIUS;
+                self.pockets = vec![Vector2::new(-table.x, 0.0), Vector2::new(table.x, 0.0)];
+                self.discs = [
+                    (DiscKind::Puck, Vector2::zero(), PUCK_RADIUS, PUCK_MASS),
+                    (
+                        DiscKind::Mallet,
+                        Vector2::new(-table.x * 0.75, 0.0),
+                        MALLET_RADIUS,
+                        MALLET_MASS,
+                    ),
+                    (
+                        DiscKind::Mallet,
+                        Vector2::new(table.x * 0.75, 0.0),
+                        MALLET_RADIUS,
+                        MALLET_MASS,
+                    ),
+                ]
+                .into_iter()
+                .map(|(kind, position, radius, mass)| {
+                    let mut disc = Disc::new(kind, position, radius, mass);
+                    disc.spawn = Some(position);
+                    disc
+                })
+                .collect();
+            }
+        }
+        self.cushions = get_cushions(self.half_extents, &self.pockets, self.pocket_radius);
+    }
+
+    pub fn step(&mut self) -> std::time::Duration {
+        let dt = self.config.dt;
+        let config = &self.config;
+        for disc in self.discs.iter_mut() {
+            let speed = disc.velocity.magnitude();
+            disc.velocity = if speed <= config.rolling_friction * dt {
+                Vector2::zero()
+            } else {
+                disc.velocity * (1.0 - config.rolling_friction * dt / speed)
+            };
+            let spin_loss = config.spin_friction * dt;
+            disc.spin = disc.spin.signum() * f32::max(disc.spin.abs() - spin_loss, 0.0);
+            disc.position += disc.velocity * dt;
+            disc.angle += disc.spin * dt;
+        }
+
+        let momentum = |discs: &[Disc]| {
+            discs
+                .iter()
+                .map(|disc| (disc.mass * disc.velocity).extend(0.0))
+                .sum()
+        };
+        let mut audit = MomentumAudit::begin(module_path!(), || momentum(&self.discs));
+        for i in 0..self.discs.len() {
+            let (before, after) = self.discs.split_at_mut(i + 1);
+            let a = &mut before[i];
+            for b in after.iter_mut() {
+                collide_discs(a, b, config, &mut audit);
+            }
+            for cushion in self.cushions.iter() {
+                collide_cushion(a, cushion, config, &mut audit);
+            }
+        }
+        audit.finish(|| momentum(&self.discs));
+
+        self.pot();
+        std::time::Duration::from_secs_f32(dt)
+    }
+
+    /// Pots the discs which fell off the table through a pocket, respawning those with a spawn.
+    fn pot(&mut self) {
+        let half_extents = self.half_extents;
+        let off_table = |disc: &Disc| {
+            disc.position.x.abs() > half_extents.x || disc.position.y.abs() > half_extents.y
+        };
+        self.potted += self.discs.iter().filter(|disc| off_table(disc)).count();
+        self.discs
+            .retain(|disc| !off_table(disc) || disc.spawn.is_some());
+        for disc in self.discs.iter_mut().filter(|disc| off_table(disc)) {
+            disc.position = disc.spawn.unwrap();
+            disc.velocity = Vector2::zero();
+            disc.spin = 0.0;
+        }
+    }
+
+    /// Strikes the disc with the cue's impulse, off its center by the config's english.
+    pub fn strike(&mut self, index: usize, impulse: Vector2<f32>) {
+        let english = self.config.english;
+        let disc = &mut self.discs[index];
+        disc.velocity += impulse / disc.mass;
+        if !impulse.is_zero() {
+            let offset = -perp(impulse.normalize()) * english * disc.radius;
+            disc.spin += cross(offset, impulse) / disc.inertia();
+        }
+    }
+
+    /// The index of the nearest disc to the point, if any is within the reach of its rim.
+    pub fn get_nearest_disc(&self, point: Vector2<f32>, reach: f32) -> Option<usize> {
+        self.discs
+            .iter()
+            .enumerate()
+            .map(|(index, disc)| (index, (disc.position - point).magnitude() - disc.radius))
+            .filter(|(_, distance)| *distance <= reach)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    /// Whether every disc has stopped, e.g. so the next shot can be taken.
+    pub fn is_at_rest(&self) -> bool {
+        self.discs
+            .iter()
+            .all(|disc| disc.velocity.is_zero() && disc.spin == 0.0)
+    }
+
+    pub fn get_timestep(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn get_discs(&self) -> &[Disc] {
+        &self.discs
+    }
+
+    pub fn get_cushions(&self) -> &[Segment] {
+        &self.cushions
+    }
+
+    pub fn get_pockets(&self) -> &[Vector2<f32>] {
+        &self.pockets
+    }
+
+    pub fn get_pocket_radius(&self) -> f32 {
+        self.pocket_radius
+    }
+
+    /// Half the length and width of the table, inside its cushions.
+    pub fn get_half_extents(&self) -> Vector2<f32> {
+        self.half_extents
+    }
+
+    pub fn get_potted(&self) -> usize {
+        self.potted
+    }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.dt = config.dt;
+        self.config.rolling_friction = config.rolling_friction;
+        self.config.spin_friction = config.spin_friction;
+        self.config.disc_restitution = config.disc_restitution;
+        self.config.cushion_restitution = config.cushion_restitution;
+        self.config.disc_friction = config.disc_friction;
+        self.config.cushion_friction = config.cushion_friction;
+        self.config.cue_strength = config.cue_strength;
+        self.config.english = config.english;
+        if config.scenario != self.config.scenario {
+            self.config.scenario = config.scenario;
+            self.reset();
+        }
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frictionless() -> Config {
+        Config {
+            rolling_friction: 0.0,
+            spin_friction: 0.0,
+            disc_restitution: 1.0,
+            disc_friction: 0.0,
+            cushion_friction: 0.0,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn head_on_collisions_pass_on_the_cue_balls_velocity() {
+        let config = frictionless();
+        let mut cue = Disc::new(DiscKind::Cue, Vector2::zero(), BALL_RADIUS, BALL_MASS);
+        cue.velocity = Vector2::new(2.0, 0.0);
+        let mut object = Disc::new(
+            DiscKind::Object,
+            Vector2::new(2.0 * BALL_RADIUS - 1e-4, 0.0),
+            BALL_RADIUS,
+            BALL_MASS,
+        );
+        let momentum = |discs: [&Disc; 2]| {
+            (discs[0].mass * discs[0].velocity + discs[1].mass * discs[1].velocity).extend(0.0)
+        };
+        let mut audit = MomentumAudit::begin(module_path!(), || momentum([&cue, &object]));
+        collide_discs(&mut cue, &mut object, &config, &mut audit);
+        assert_eq!(None, audit.finish(|| momentum([&cue, &object])));
+        assert!(cue.velocity.magnitude() < 1e-5);
+        assert!((object.velocity - Vector2::new(2.0, 0.0)).magnitude() < 1e-5);
+        // Without friction, neither spins.
+        assert_eq!((cue.spin, object.spin), (0.0, 0.0));
+    }
+
+    #[test]
+    fn english_bounces_sideways_off_cushions() {
+        let cushion = Segment {
+            start: Vector2::new(1.0, -1.0),
+            end: Vector2::new(1.0, 1.0),
+        };
+        let bounce = |spin: f32| {
+            let mut disc = Disc::new(
+                DiscKind::Cue,
+                Vector2::new(1.0 - BALL_RADIUS + 1e-4, 0.0),
+                BALL_RADIUS,
+                BALL_MASS,
+            );
+            disc.velocity = Vector2::new(1.0, 0.0);
+            disc.spin = spin;
+            let momentum = |disc: &Disc| (disc.mass * disc.velocity).extend(0.0);
+            let mut audit = MomentumAudit::begin(module_path!(), || momentum(&disc));
+            collide_cushion(&mut disc, &cushion, &Config::default(), &mut audit);
+            assert_eq!(None, audit.finish(|| momentum(&disc)));
+            disc
+        };
+        let plain = bounce(0.0);
+        let restitution = Config::default().cushion_restitution;
+        assert!((plain.velocity - Vector2::new(-restitution, 0.0)).magnitude() < 1e-5);
+        // Spinning counterclockwise, the disc's rim rolls along the cushion towards +y, so friction pushes it -y.
+        let spun = bounce(50.0);
+        assert!(spun.velocity.x < 0.0 && spun.velocity.y < -0.1);
+        assert!(spun.spin < 50.0);
+        assert!(bounce(-50.0).velocity.y > 0.1);
+    }
+
+    #[test]
+    fn strikes_break_the_rack_and_come_to_rest() {
+        let mut simulation = Simulation::new();
+        assert_eq!(simulation.get_discs().len(), 16);
+        assert!(simulation.is_at_rest());
+        // Six pockets split the four sides into six cushions.
+        assert_eq!(simulation.get_cushions().len(), 6);
+        let cue = simulation
+            .get_nearest_disc(Vector2::new(-POOL_TABLE.x / 2.0 - 0.1, 0.0), 0.1)
+            .unwrap();
+        assert_eq!(simulation.get_discs()[cue].kind, DiscKind::Cue);
+        simulation.strike(cue, Vector2::new(1.0, 0.0));
+        for _ in 0..20000 {
+            simulation.step();
+        }
+        assert!(simulation.is_at_rest());
+        let half_extents = simulation.get_half_extents();
+        for disc in simulation.get_discs() {
+            assert!(disc.position.x.abs() <= half_extents.x);
+            assert!(disc.position.y.abs() <= half_extents.y);
+        }
+        // Potted object balls are removed, but the cue ball's respawned.
+        let discs = simulation.get_discs();
+        assert!(discs.len() <= 16);
+        assert_eq!(
+            discs
+                .iter()
+                .filter(|disc| disc.kind == DiscKind::Cue)
+                .count(),
+            1
+        );
+        // The break scatters the rack.
+        let racked = Simulation::new();
+        let moved = discs
+            .iter()
+            .filter(|disc| disc.kind == DiscKind::Object)
+            .filter(|disc| {
+                racked
+                    .get_discs()
+                    .iter()
+                    .all(|spot| (spot.position - disc.position).magnitude() > BALL_RADIUS)
+            })
+            .count();
+        assert!(moved + simulation.get_potted() >= 10, "{}", moved);
+    }
+}
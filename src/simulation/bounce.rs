@@ -1,10 +1,43 @@
+use crate::simulation::checksum::{Checksum, Checksummed};
 /// The bounce module contains the logic for a bouncing ball simulation.
-use crate::gui::bounce;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::container::{Container, ContainerShape};
+use crate::simulation::energy::EnergyBreakdown;
+use crate::simulation::fitting::{self, Fit, FitParameter, Trajectory};
+use crate::simulation::reflect::{Field, Reflect};
+use crate::simulation::snapshot::{Snapshot, SnapshotReader, SnapshotWriter};
+use crate::simulation::spectrum::ImpulseRecorder;
+use crate::simulation::units::{self, GravityPreset};
 
 use cgmath::{InnerSpace, Vector3, Zero};
 
 const EPSILON: f32 = 0.001;
 
+/// The rate the ball's contact impulses are recorded at, in samples per second.
+const IMPULSE_SAMPLE_RATE: f32 = 200.0;
+/// The seconds of contact impulses kept for analysis.
+const IMPULSE_RECORDING_DURATION: f32 = 10.0;
+
+/// The parameters fit to reference trajectories of the ball, in the order of their fitted values.
+pub const FIT_PARAMETERS: [FitParameter; 3] = [
+    FitParameter {
+        name: "Drag",
+        min: 0.0,
+        max: 2.0,
+    },
+    FitParameter {
+        name: "Coefficient of Restitution",
+        min: 0.0,
+        max: 1.0,
+    },
+    FitParameter {
+        name: "Coefficient of Friction",
+        min: 0.0,
+        max: 1.0,
+    },
+];
+
+#[derive(Clone, PartialEq)]
 pub struct Config {
     pub dt: f32, // secs as f32
     pub sphere_mass: f32,
@@ -14,142 +47,90 @@ pub struct Config {
     pub coefficient_of_restitution: f32,
     pub coefficient_of_friction: f32,
     pub static_coefficient_of_friction: f32,
+    /// The container the walls move towards, which isn't reflected as its fields depend on its shape.
+    pub container: Container,
 }
 
-impl Config {
-    pub fn default() -> Self {
+impl Default for Config {
+    fn default() -> Self {
         Self {
             dt: std::time::Duration::from_millis(1).as_secs_f32(),
             sphere_mass: 1.0,
             drag: 0.5,
             wind: Vector3::<f32>::zero(),
-            acceleration_gravity: -10.0,
+            acceleration_gravity: GravityPreset::Earth.vector().y,
             coefficient_of_restitution: 0.95,
             coefficient_of_friction: 0.25,
             static_coefficient_of_friction: 0.5,
+            container: Container::new(ContainerShape::Box, Vector3::new(1.0, 1.0, 1.0)),
         }
     }
 }
 
-#[derive(Debug)]
-struct Plane {
-    point: cgmath::Vector3<f32>,
-    normal: cgmath::Vector3<f32>,
-}
-
-impl Plane {
-    pub fn new(point: cgmath::Vector3<f32>, normal: cgmath::Vector3<f32>) -> Plane {
-        let normal = normal.normalize();
-        Plane {
-            point: point,
-            normal: normal,
-        }
-    }
-
-    pub fn distance_to(&self, point: cgmath::Vector3<f32>) -> f32 {
-        (point - self.point).dot(self.normal)
+impl Reflect for Config {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![
+            Field::new("dt", "Simualtion dt (secs)", &mut self.dt).range(0.0001..=0.01),
+            Field::new(
+                "acceleration_gravity",
+                "Gravity",
+                &mut self.acceleration_gravity,
+            )
+            .range(-20.0..=20.0)
+            .units(units::ACCELERATION),
+            Field::new("sphere_mass", "Sphere Mass", &mut self.sphere_mass)
+                .range(0.05..=10.0)
+                .units(units::MASS),
+            Field::new("drag", "Drag", &mut self.drag).range(0.05..=2.0),
+            Field::new("wind", "Wind", &mut self.wind)
+                .range(-5.0..=5.0)
+                .units(units::VELOCITY),
+            Field::new(
+                "coefficient_of_restitution",
+                "Coefficient of Restitution",
+                &mut self.coefficient_of_restitution,
+            )
+            .range(0.0..=1.0)
+            .group("Contact"),
+            Field::new(
+                "coefficient_of_friction",
+                "Coefficient of Friction",
+                &mut self.coefficient_of_friction,
+            )
+            .range(0.05..=1.0)
+            .group("Contact"),
+            Field::new(
+                "static_coefficient_of_friction",
+                "Static Coefficient of Friction",
+                &mut self.static_coefficient_of_friction,
+            )
+            .range(0.05..=1.0)
+            .group("Contact"),
+        ]
     }
 }
 
 pub struct State {
-    planes: Vec<Plane>,
+    /// Where the walls are now, moving towards the configured container's.
+    container: Container,
     pub config: Config,
     position: cgmath::Vector3<f32>,
     velocity: cgmath::Vector3<f32>,
+    impulses: ImpulseRecorder,
 }
 
 impl State {
     pub fn new() -> State {
-        let planes = vec![
-            // Top
-            Plane::new(
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: 1.0,
-                    z: 0.0,
-                },
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: -1.0,
-                    z: 0.0,
-                },
-            ),
-            // Bottom
-            Plane::new(
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: -1.0,
-                    z: 0.0,
-                },
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: 1.0,
-                    z: 0.0,
-                },
-            ),
-            // Left
-            Plane::new(
-                cgmath::Vector3 {
-                    x: -1.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-                cgmath::Vector3 {
-                    x: 1.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-            ),
-            // Right
-            Plane::new(
-                cgmath::Vector3 {
-                    x: 1.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-                cgmath::Vector3 {
-                    x: -1.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-            ),
-            // Front
-            Plane::new(
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: -1.0,
-                },
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 1.0,
-                },
-            ),
-            // Back
-            Plane::new(
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 1.0,
-                },
-                cgmath::Vector3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: -1.0,
-                },
-            ),
-        ];
-
         let config = Config::default();
 
         let position = Vector3::<f32>::zero();
         let velocity = Vector3::<f32>::zero();
         State {
-            planes,
+            container: config.container,
             config,
             position,
             velocity,
+            impulses: ImpulseRecorder::new(IMPULSE_SAMPLE_RATE, IMPULSE_RECORDING_DURATION),
         }
     }
 
@@ -157,16 +138,57 @@ impl State {
         self.position
     }
 
+    /// The walls the ball bounces off of, which may be moving towards the configured container's.
+    pub fn get_container(&self) -> &Container {
+        &self.container
+    }
+
+    /// The ball's kinetic energy, and its potential energy in gravity. Drag and wind don't conserve energy, so
+    /// only an undamped ball keeps it between bounces.
+    pub fn energy(&self) -> EnergyBreakdown {
+        EnergyBreakdown {
+            kinetic: 0.5 * self.config.sphere_mass * self.velocity.magnitude2(),
+            gravitational: -self.config.sphere_mass
+                * self.config.acceleration_gravity
+                * self.position.y,
+            elastic: 0.0,
+        }
+    }
+
+    pub fn get_impulses(&self) -> &ImpulseRecorder {
+        &self.impulses
+    }
+
     pub fn get_timestep(&self) -> std::time::Duration {
         std::time::Duration::from_secs_f32(self.config.dt)
     }
 
+    /// Moves the ball to the position, inside the container, moving at the velocity.
+    pub fn launch(&mut self, position: Vector3<f32>, velocity: Vector3<f32>) {
+        self.position = position;
+        self.velocity = velocity;
+    }
+
     /// Advance the simulation by config.dt. Uses first order Euler integration.
     /// If the full timestep wouuld result in a collision before dt,
     /// advances only until the moment after the collision.
     /// Returns the time the simulation has advanced.
     /// That is, dt if no collision has occured, or some duration <= dt if a collision did occur.
     pub fn step(&mut self) -> std::time::Duration {
+        // Move the walls, pushing the ball along if they pass it.
+        let moved = self.container.approach(
+            &self.config.container,
+            Container::WALL_SPEED * self.config.dt,
+        );
+        if moved > 0.0 {
+            self.container.push_inside(
+                &mut self.position,
+                &mut self.velocity,
+                moved / self.config.dt,
+                EPSILON,
+            );
+        }
+
         // Determine the acceleration due to the forces acting on the sphere.
         let acceleration_gravity = cgmath::Vector3 {
             x: 0.0,
@@ -187,6 +209,7 @@ impl State {
         let acceleration = acceleration_air_resistance + acceleration_gravity + acceleration_wind;
 
         if self.is_resting(acceleration) {
+            self.impulses.advance(self.config.dt);
             return self.get_timestep();
         }
 
@@ -199,12 +222,8 @@ impl State {
 
         // TODO note that technically, you can collide with two planes at the same time.
         //      That case really *should* be handled.
-        let collided_plane_maybe = self.planes.iter().find(|plane| -> bool {
-            let old_distance_to_plane = plane.distance_to(old_position);
-            let new_distance_to_plane = plane.distance_to(new_position);
-            // If the signs are different, the point has crossed the plane
-            old_distance_to_plane.is_sign_positive() != new_distance_to_plane.is_sign_positive()
-        });
+        // The ball is inside the walls, so it's crossed any wall its new position is outside of.
+        let collided_plane_maybe = self.container.walls_within(new_position, 0.0).next();
 
         let time_elapsed;
         (self.position, self.velocity, time_elapsed) = match collided_plane_maybe {
@@ -244,6 +263,9 @@ impl State {
                 };
 
                 let velocity_response = velocity_response_normal + velocity_response_tangent;
+                self.impulses.add_impulse(
+                    self.config.sphere_mass * (velocity_response - velocity_collision).magnitude(),
+                );
 
                 (
                     new_position,
@@ -254,13 +276,12 @@ impl State {
             None => (new_position, new_velocity, self.get_timestep()),
         };
 
-        // Cheat a little bit to ensure we stay in the bounds of the box.
+        // Cheat a little bit to ensure we stay in the bounds of the container.
         // Floating point precision could otherwise cause us to clip through the bounds
         // in some edge cases - fixing that would be a great improvement.
-        self.position.x = self.position.x.clamp(-0.9999, 0.9999);
-        self.position.y = self.position.y.clamp(-0.9999, 0.9999);
-        self.position.z = self.position.z.clamp(-0.9999, 0.9999);
+        self.position = self.container.clamp_inside(self.position, 0.0001);
 
+        self.impulses.advance(time_elapsed.as_secs_f32());
         time_elapsed
     }
 
@@ -273,9 +294,8 @@ impl State {
 
         let distance_epsilon = 0.02;
         let contact_walls = self
-            .planes
-            .iter()
-            .filter(|&plane| -> bool { plane.distance_to(self.position) < distance_epsilon })
+            .container
+            .walls_within(self.position, distance_epsilon)
             .collect::<Vec<_>>();
 
         // If we're not touching a wall, we aren't at rest (we assume we're not in a zero-G environment)
@@ -316,15 +336,123 @@ impl State {
         any_wall_friction_overcomes_acceleration
     }
 
-    pub fn sync_state_from_ui(&mut self, ui: &mut bounce::BouncingBallUi) {
-        let ui_config_state = ui.get_gui_state_mut();
-        self.config.dt = ui_config_state.dt;
-        self.config.acceleration_gravity = ui_config_state.acceleration_gravity;
-        self.config.sphere_mass = ui_config_state.sphere_mass;
-        self.config.drag = ui_config_state.drag;
-        self.config.wind = ui_config_state.wind;
-        self.config.coefficient_of_restitution = ui_config_state.coefficient_of_restitution;
-        self.config.coefficient_of_friction = ui_config_state.coefficient_of_friction;
-        self.config.static_coefficient_of_friction = ui_config_state.static_coefficient_of_friction;
+    /// Fits the drag, coefficient of restitution, and coefficient of friction to the reference
+    /// trajectory of the ball, launched from its first position and velocity. Positions are in the
+    /// box's space, whose walls are a unit from its center.
+    pub fn fit(reference: &Trajectory) -> Result<Fit, &'static str> {
+        fitting::fit(&FIT_PARAMETERS, reference, |values| {
+            let mut state = State::new();
+            state.config.drag = values[0];
+            state.config.coefficient_of_restitution = values[1];
+            state.config.coefficient_of_friction = values[2];
+            state.launch(reference.positions[0], reference.initial_velocity());
+            reference.sample(|| (state.step().as_secs_f32(), state.position))
+        })
+    }
+}
+
+impl Configurable for State {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.dt = config.dt;
+        self.config.acceleration_gravity = config.acceleration_gravity;
+        self.config.sphere_mass = config.sphere_mass;
+        self.config.drag = config.drag;
+        self.config.wind = config.wind;
+        self.config.coefficient_of_restitution = config.coefficient_of_restitution;
+        self.config.coefficient_of_friction = config.coefficient_of_friction;
+        self.config.static_coefficient_of_friction = config.static_coefficient_of_friction;
+        self.config.container = config.container;
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::new()
+    }
+}
+
+impl Checksummed for State {
+    fn add_to_checksum(&self, checksum: &mut Checksum) {
+        checksum.add_vector3(self.position);
+        checksum.add_vector3(self.velocity);
+    }
+}
+
+impl Snapshot for State {
+    fn write_snapshot(&self, writer: &mut SnapshotWriter) {
+        writer.write_vector3(self.position);
+        writer.write_vector3(self.velocity);
+    }
+
+    fn read_snapshot(&mut self, reader: &mut SnapshotReader) -> Result<(), &'static str> {
+        self.position = reader.read_vector3()?;
+        self.velocity = reader.read_vector3()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_restitution() {
+        // A ball thrown sideways with a restitution of 0.7, tracked at 30 frames per second.
+        let mut state = State::new();
+        state.config.coefficient_of_restitution = 0.7;
+        let velocity = Vector3::new(1.5, 0.0, 0.0);
+        state.launch(Vector3::zero(), velocity);
+        let mut reference = Trajectory {
+            times: (0..60).map(|frame| frame as f32 / 30.0).collect(),
+            positions: vec![Vector3::zero(), velocity / 30.0],
+        };
+        reference.positions = reference
+            .sample(|| (state.step().as_secs_f32(), state.position))
+            .unwrap();
+
+        let fit = State::fit(&reference).unwrap();
+        assert!((fit.values[1] - 0.7).abs() < 0.05, "{:?}", fit);
+        assert!(fit.error < 0.01, "{:?}", fit);
+    }
+
+    #[test]
+    fn undamped_ball_keeps_its_energy() {
+        let mut state = State::new();
+        state.config.drag = 0.0;
+        state.config.coefficient_of_restitution = 1.0;
+        state.config.coefficient_of_friction = 0.0;
+        state.launch(Vector3::new(0.0, 0.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let initial = state.energy();
+        assert_eq!(0.5, initial.kinetic);
+        let mut elapsed = 0.0;
+        while elapsed < 1.0 {
+            elapsed += state.step().as_secs_f32();
+        }
+        // The ball falls and bounces, trading its potential energy for kinetic and back, with only Euler's drift.
+        assert!(
+            state.energy().drift(&initial).abs() < 0.02,
+            "{:?}",
+            state.energy()
+        );
+        assert!(state.energy().gravitational != initial.gravitational);
+    }
+
+    #[test]
+    fn rising_floor_lifts_resting_ball() {
+        let mut state = State::new();
+        state.launch(Vector3::new(0.0, -0.99, 0.0), Vector3::zero());
+        state.config.container = Container::new(ContainerShape::Box, Vector3::new(1.0, 0.5, 1.0));
+        for _ in 0..2000 {
+            state.step();
+            assert!(state.get_container().contains(state.position));
+        }
+        assert_eq!(state.config.container, *state.get_container());
+        assert!(state.position.y > -0.51, "{:?}", state.position);
     }
 }
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use crate::simulation::rigidbody::contact::CONTACT_ITERATIONS;
+use crate::simulation::solver::SolverBudget;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub dt: f32, // Seconds as f32
+    /// The acceleration of gravity, in m/s².
+    pub gravity: f32,
+    /// The tension which would stretch a strand to twice its length, in N.
+    pub strand_stiffness: f32,
+    /// The damping force of a strand stretching by its own length each second, in N·s.
+    pub strand_damping: f32,
+    /// How much longer the strands are than the gaps they span, sagging the bridge.
+    pub strand_slack: f32,
+    /// Whether strands snap once their tension is over the break tension.
+    pub breakable_strands: bool,
+    /// The tension strands snap at, in N, which also sets the top of the stress coloring.
+    pub break_tension: f32,
+    /// The mass of each dropped load, in kg.
+    pub load_mass: f32,
+    pub contact_restitution: f32,
+    pub contact_friction: f32,
+    /// The fraction of each contact's overlap that's corrected each step.
+    pub contact_position_correction: f32,
+    /// The contact solver's iterations, tolerance, and time budget each step.
+    pub contact_solver: SolverBudget,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dt: Duration::from_micros(500).as_secs_f32(),
+            gravity: 9.8,
+            strand_stiffness: 4000.0,
+            strand_damping: 20.0,
+            strand_slack: 1.05,
+            breakable_strands: true,
+            break_tension: 1000.0,
+            load_mass: 20.0,
+            contact_restitution: 0.2,
+            contact_friction: 0.5,
+            contact_position_correction: 0.8,
+            contact_solver: SolverBudget::new(CONTACT_ITERATIONS),
+        }
+    }
+}
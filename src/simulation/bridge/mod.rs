@@ -0,0 +1,399 @@
+/// A rope bridge over a gap between two ledges: plank rigidbodies tied together, and to posts on the ledges,
+/// by strands of rope. Loads dropped onto the bridge press on the planks through contacts, which pass their
+/// weight into the strands, so that the tension spreads along the bridge to the posts.
+/// The rigidbody simulation doesn't collide rigidbodies with each other, so the bridge collides its boxes
+/// with box contacts, against each other and the fixed ledges and ground.
+pub mod config;
+pub mod strand;
+
+use self::config::Config;
+use self::strand::{Strand, StrandMaterial, Tie};
+use crate::pool::Pool;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::quantity;
+use crate::simulation::rigidbody::{
+    contact::{self, ContactBody, ContactMaterial, Cuboid},
+    rigidbody::RigidBody,
+    simulation::{RigidBodyHandle, Simulation as RigidBodySimulation},
+};
+use crate::simulation::solver::SolverStats;
+
+use cgmath::{Quaternion, Rad, Rotation3, Vector3, Zero};
+use std::time::Duration;
+
+/// Half the width of the gap the bridge spans, along x, and the height of the ledges' tops.
+pub const GAP_HALF_WIDTH: f32 = 3.0;
+pub const LEDGE_HEIGHT: f32 = 0.0;
+/// The size of each ledge, and of the ground at the bottom of the gap, which is this deep below the ledges.
+pub const LEDGE_DIMENSIONS: Vector3<f32> = Vector3::new(2.0, 4.0, 3.0);
+pub const GROUND_THICKNESS: f32 = 1.0;
+
+pub const PLANK_COUNT: usize = 10;
+pub const PLANK_DIMENSIONS: Vector3<f32> = Vector3::new(0.4, 0.06, 1.0);
+const PLANK_MASS: f32 = 3.0;
+/// The strands are tied this far from the planks' centers along z, on either side.
+const TIE_OFFSET: f32 = 0.45;
+const STRAND_SEGMENTS: usize = 2;
+const STRAND_MASS: f32 = 0.5;
+/// The viscous drag on the strands' knots, per kg.
+const STRAND_DRAG: f32 = 1.0;
+/// The viscous drag on the planks and loads, per kg, which damps the bridge's swinging.
+const BODY_DRAG: f32 = 1.0;
+const ANGULAR_DRAG: f32 = 0.02;
+
+pub const LOAD_DIMENSIONS: Vector3<f32> = Vector3::new(0.3, 0.3, 0.3);
+const DROP_HEIGHT: f32 = 1.0;
+/// The oldest load is removed to drop another once there are this many.
+pub const MAX_LOADS: usize = 8;
+
+pub struct Simulation {
+    config: Config,
+    rigidbodies: RigidBodySimulation,
+    obstacles: Vec<Cuboid>,
+    planks: Vec<RigidBodyHandle>,
+    loads: Vec<RigidBodyHandle>,
+    strands: Vec<Strand>,
+    broken_strands: usize,
+    // How the contact solver converged over the last step.
+    contact_stats: SolverStats,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        let mut simulation = Simulation {
+            config: Config::default(),
+            rigidbodies: RigidBodySimulation::new(Vec::new(), Vec::new()),
+            obstacles: get_obstacle_boxes()
+                .into_iter()
+                .map(|(center, dimensions)| Cuboid::fixed(center, dimensions))
+                .collect(),
+            planks: Vec::new(),
+            loads: Vec::new(),
+            strands: Vec::new(),
+            broken_strands: 0,
+            contact_stats: SolverStats::default(),
+        };
+        simulation.reset();
+        simulation
+    }
+
+    /// Rebuilds the bridge hanging between the ledges, without any loads.
+    pub fn reset(&mut self) {
+        self.rigidbodies = RigidBodySimulation::new(Vec::new(), Vec::new());
+        self.sync_rigidbody_config();
+        self.loads.clear();
+        self.strands.clear();
+        self.broken_strands = 0;
+
+        // The planks are spaced evenly, with gaps of the same width between each other and the ledges.
+        let span = 2.0 * GAP_HALF_WIDTH;
+        let gap = (span - PLANK_COUNT as f32 * PLANK_DIMENSIONS.x) / (PLANK_COUNT + 1) as f32;
+        let strand_length = gap * self.config.strand_slack;
+        // They start hung along the parabola as long as the bridge, so that it doesn't swing for long as it settles.
+        let length =
+            PLANK_COUNT as f32 * PLANK_DIMENSIONS.x + (PLANK_COUNT + 1) as f32 * strand_length;
+        let sag = (3.0 * span * (length - span).max(0.0) / 8.0).sqrt();
+        self.planks = (0..PLANK_COUNT)
+            .map(|i| {
+                let x = -GAP_HALF_WIDTH
+                    + gap
+                    + PLANK_DIMENSIONS.x / 2.0
+                    + i as f32 * (PLANK_DIMENSIONS.x + gap);
+                let y = LEDGE_HEIGHT - sag * (1.0 - (x / GAP_HALF_WIDTH).powi(2));
+                let slope = 2.0 * sag * x / GAP_HALF_WIDTH.powi(2);
+                let plank = RigidBody::cuboid(
+                    Vector3::new(x, y, 0.0),
+                    quantity::kilograms(PLANK_MASS),
+                    PLANK_DIMENSIONS,
+                )
+                .expect("Planks have volume and mass")
+                .with_rotation(Quaternion::from_angle_z(Rad(slope.atan())));
+                self.rigidbodies.spawn_rigidbody(plank)
+            })
+            .collect();
+
+        // Each side of each plank is tied to the next plank, or to a post on the ledge at either end.
+        let half_width = PLANK_DIMENSIONS.x / 2.0;
+        for side in [-TIE_OFFSET, TIE_OFFSET] {
+            let ties = std::iter::once(Tie::Fixed(Vector3::new(
+                -GAP_HALF_WIDTH,
+                LEDGE_HEIGHT,
+                side,
+            )))
+            .chain(self.planks.iter().flat_map(|plank| {
+                [
+                    Tie::Body(*plank, Vector3::new(-half_width, 0.0, side)),
+                    Tie::Body(*plank, Vector3::new(half_width, 0.0, side)),
+                ]
+            }))
+            .chain(std::iter::once(Tie::Fixed(Vector3::new(
+                GAP_HALF_WIDTH,
+                LEDGE_HEIGHT,
+                side,
+            ))))
+            .collect::<Vec<_>>();
+            for pair in ties.chunks(2) {
+                self.strands.push(Strand::new(
+                    pair[0],
+                    pair[1],
+                    self.rigidbodies.get_rigidbodies(),
+                    STRAND_SEGMENTS,
+                    strand_length,
+                    STRAND_MASS,
+                ));
+            }
+        }
+    }
+
+    pub fn step(&mut self) -> Duration {
+        let material = StrandMaterial {
+            axial: self.config.strand_stiffness,
+            damping: self.config.strand_damping,
+            drag: STRAND_DRAG,
+        };
+        let rigidbodies = self.rigidbodies.get_rigidbodies_mut();
+        for strand in self.strands.iter_mut() {
+            strand.accumulate_forces(rigidbodies, material, self.config.gravity);
+        }
+        // The rigidbody simulation's gravity and drag are forces, but the loads' should grow with their mass.
+        for rigidbody in rigidbodies.values_mut() {
+            let force = rigidbody.get_mass()
+                * (Vector3::new(0.0, -self.config.gravity, 0.0)
+                    - BODY_DRAG * rigidbody.get_state().velocity());
+            rigidbody.add_force(force);
+        }
+
+        self.rigidbodies.step();
+        for strand in self.strands.iter_mut() {
+            strand.integrate(self.config.dt);
+        }
+        self.collide_boxes();
+
+        if self.config.breakable_strands {
+            let break_tension = self.config.break_tension;
+            let count = self.strands.len();
+            self.strands
+                .retain(|strand| strand.get_max_tension() <= break_tension);
+            self.broken_strands += count - self.strands.len();
+        }
+
+        Duration::from_secs_f32(self.config.dt)
+    }
+
+    /// Pushes apart each pair of overlapping boxes, and the boxes and the fixed obstacles.
+    fn collide_boxes(&mut self) {
+        let material = ContactMaterial {
+            restitution: self.config.contact_restitution,
+            friction: self.config.contact_friction,
+            anisotropy: None,
+            position_correction: self.config.contact_position_correction,
+        };
+        let rigidbodies = self.rigidbodies.get_rigidbodies_mut();
+        let handles = rigidbodies
+            .iter()
+            .map(|(handle, _)| ContactBody::Rigid(handle))
+            .collect::<Vec<_>>();
+        let mut pairs = Vec::new();
+        for (i, first) in handles.iter().enumerate() {
+            pairs.extend(handles[i + 1..].iter().map(|second| (*first, *second)));
+            pairs.extend(
+                self.obstacles
+                    .iter()
+                    .map(|obstacle| (*first, ContactBody::Fixed(*obstacle))),
+            );
+        }
+        self.contact_stats =
+            contact::collide(rigidbodies, &pairs, material, &self.config.contact_solver);
+    }
+
+    /// Drops a load from above the point on the bridge, removing the oldest load if there are too many.
+    pub fn drop_load(&mut self, x: f32, z: f32) -> Result<RigidBodyHandle, &'static str> {
+        let load = RigidBody::cuboid(
+            Vector3::new(x, LEDGE_HEIGHT + DROP_HEIGHT, z),
+            quantity::kilograms(self.config.load_mass),
+            LOAD_DIMENSIONS,
+        )?;
+        if self.loads.len() >= MAX_LOADS {
+            let oldest = self.loads.remove(0);
+            self.rigidbodies.despawn_rigidbody(oldest);
+        }
+        let handle = self.rigidbodies.spawn_rigidbody(load);
+        self.loads.push(handle);
+        Ok(handle)
+    }
+
+    fn sync_rigidbody_config(&mut self) {
+        let config = self.rigidbodies.get_config_mut();
+        config.dt = self.config.dt;
+        config.gravity = Vector3::zero();
+        config.angular_drag_viscous = ANGULAR_DRAG;
+    }
+
+    /// The stress on the plank, as the greatest tension of the strands tied to it over the break tension.
+    pub fn get_stress(&self, plank: RigidBodyHandle) -> f32 {
+        self.strands
+            .iter()
+            .filter_map(|strand| {
+                let (start, end) = strand.get_ties();
+                let tensions = strand.get_tensions();
+                if start.get_body() == Some(plank) {
+                    tensions.first().copied()
+                } else if end.get_body() == Some(plank) {
+                    tensions.last().copied()
+                } else {
+                    None
+                }
+            })
+            .fold(0.0, f32::max)
+            / self.config.break_tension
+    }
+
+    /// The greatest tension of any strand, in N.
+    pub fn get_max_tension(&self) -> f32 {
+        self.strands
+            .iter()
+            .map(|strand| strand.get_max_tension())
+            .fold(0.0, f32::max)
+    }
+
+    pub fn get_timestep(&self) -> Duration {
+        Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn get_rigidbodies(&self) -> &Pool<RigidBody> {
+        self.rigidbodies.get_rigidbodies()
+    }
+
+    pub fn get_planks(&self) -> &[RigidBodyHandle] {
+        &self.planks
+    }
+
+    pub fn get_loads(&self) -> &[RigidBodyHandle] {
+        &self.loads
+    }
+
+    pub fn get_strands(&self) -> &[Strand] {
+        &self.strands
+    }
+
+    pub fn get_broken_strands(&self) -> usize {
+        self.broken_strands
+    }
+
+    pub fn get_contact_stats(&self) -> &SolverStats {
+        &self.contact_stats
+    }
+}
+
+/// The ledges on either side of the gap, and the ground at its bottom.
+pub fn get_obstacle_boxes() -> Vec<(Vector3<f32>, Vector3<f32>)> {
+    let ledge_center = |side: f32| {
+        Vector3::new(
+            side * (GAP_HALF_WIDTH + LEDGE_DIMENSIONS.x / 2.0),
+            LEDGE_HEIGHT - LEDGE_DIMENSIONS.y / 2.0,
+            0.0,
+        )
+    };
+    let ground = Vector3::new(
+        2.0 * (GAP_HALF_WIDTH + LEDGE_DIMENSIONS.x),
+        GROUND_THICKNESS,
+        LEDGE_DIMENSIONS.z,
+    );
+    vec![
+        (ledge_center(-1.0), LEDGE_DIMENSIONS),
+        (ledge_center(1.0), LEDGE_DIMENSIONS),
+        (
+            Vector3::new(
+                0.0,
+                LEDGE_HEIGHT - LEDGE_DIMENSIONS.y - GROUND_THICKNESS / 2.0,
+                0.0,
+            ),
+            ground,
+        ),
+    ]
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        let rebuild = config.strand_slack != self.config.strand_slack;
+        self.config = config.clone();
+        self.sync_rigidbody_config();
+        // The slack sets the strands' lengths, so the bridge is rebuilt with them.
+        if rebuild {
+            self.reset();
+        }
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settle(simulation: &mut Simulation, seconds: f32) {
+        for _ in 0..(seconds / simulation.get_config().dt) as usize {
+            simulation.step();
+        }
+    }
+
+    /// The tension of the strands tied to the posts, and to the middle planks, in N.
+    fn get_end_and_middle_tensions(simulation: &Simulation) -> (f32, f32) {
+        let planks = simulation.get_planks();
+        let middle = planks[PLANK_COUNT / 2];
+        let end = simulation
+            .get_strands()
+            .iter()
+            .filter(|strand| {
+                let (start, end) = strand.get_ties();
+                start.get_body().is_none() || end.get_body().is_none()
+            })
+            .map(|strand| strand.get_max_tension())
+            .fold(0.0, f32::max);
+        let middle = simulation.get_stress(middle) * simulation.get_config().break_tension;
+        (end, middle)
+    }
+
+    #[test]
+    fn bridge_carries_a_load_to_its_posts() {
+        let mut simulation = Simulation::new();
+        settle(&mut simulation, 2.0);
+        let (unloaded_end, unloaded_middle) = get_end_and_middle_tensions(&simulation);
+        // The planks' weight builds up along the bridge, so the posts bear the most tension.
+        assert!(unloaded_end > unloaded_middle);
+
+        let load = simulation.drop_load(0.0, 0.0).unwrap();
+        settle(&mut simulation, 2.0);
+        assert_eq!(simulation.get_broken_strands(), 0);
+        // The load rests on the bridge, whose strands carry its weight to the posts.
+        let position = simulation.get_rigidbodies()[load].get_position();
+        assert!(position.y > LEDGE_HEIGHT - 2.0, "{:?}", position);
+        let (loaded_end, _) = get_end_and_middle_tensions(&simulation);
+        assert!(loaded_end > 1.2 * unloaded_end);
+    }
+
+    #[test]
+    fn heavy_loads_snap_strands() {
+        let mut simulation = Simulation::new();
+        simulation.sync_config(&Config {
+            load_mass: 200.0,
+            ..Config::default()
+        });
+        let load = simulation.drop_load(0.0, 0.0).unwrap();
+        settle(&mut simulation, 2.0);
+        assert!(simulation.get_broken_strands() > 0);
+        // It falls through the bridge, onto the ground.
+        let position = simulation.get_rigidbodies()[load].get_position();
+        assert!(position.y < LEDGE_HEIGHT - LEDGE_DIMENSIONS.y + 0.5);
+    }
+}
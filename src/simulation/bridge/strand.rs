@@ -0,0 +1,251 @@
+/// Strands of rope, chains of point masses joined by springs which pull when stretched but go slack when
+/// compressed. Each end is tied to a fixed point or to a point on a rigidbody, which the strand's tension pulls on.
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use crate::pool::Pool;
+use crate::simulation::rigidbody::{rigidbody::RigidBody, simulation::RigidBodyHandle};
+
+/// Where an end of a strand is tied.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tie {
+    /// A fixed point in world space, e.g. a post on a ledge.
+    Fixed(Vector3<f32>),
+    /// A point on a rigidbody, in the rigidbody's local space.
+    Body(RigidBodyHandle, Vector3<f32>),
+}
+
+impl Tie {
+    /// The world space position and velocity of the tied point.
+    fn get_motion(&self, rigidbodies: &Pool<RigidBody>) -> (Vector3<f32>, Vector3<f32>) {
+        match self {
+            Tie::Fixed(position) => (*position, Vector3::zero()),
+            Tie::Body(handle, anchor) => {
+                let rigidbody = &rigidbodies[*handle];
+                (
+                    rigidbody.local_to_world(*anchor),
+                    rigidbody.get_point_velocity(*anchor),
+                )
+            }
+        }
+    }
+
+    /// Pulls the tied rigidbody, if any, by the force at the tied point.
+    fn pull(&self, rigidbodies: &mut Pool<RigidBody>, force: Vector3<f32>) {
+        if let Tie::Body(handle, anchor) = self {
+            let rigidbody = &mut rigidbodies[*handle];
+            let offset = rigidbody.local_to_world(*anchor) - rigidbody.get_position();
+            rigidbody.add_force_at(force, offset);
+        }
+    }
+
+    pub fn get_body(&self) -> Option<RigidBodyHandle> {
+        match self {
+            Tie::Fixed(_) => None,
+            Tie::Body(handle, _) => Some(*handle),
+        }
+    }
+}
+
+/// The stiffness and damping of the strands' springs, and the air's drag on them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrandMaterial {
+    /// The tension which would stretch a segment by its own rest length, in N.
+    /// The segments' stiffness is this over their rest length, so that longer ropes are as stiff for their length.
+    pub axial: f32,
+    /// The damping force of a segment stretching by its own rest length each second, in N·s.
+    pub damping: f32,
+    /// The viscous drag on the knots per kg, in 1/s, which damps the rope's swinging.
+    pub drag: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Knot {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    force: Vector3<f32>,
+}
+
+pub struct Strand {
+    start: Tie,
+    end: Tie,
+    // The point masses between the ends.
+    knots: Vec<Knot>,
+    knot_mass: f32,
+    rest_length: f32,
+    // Each segment's tension in the last step, from the start to the end.
+    tensions: Vec<f32>,
+}
+
+impl Strand {
+    /// A strand of the given length and number of segments between the ties, laid straight between them.
+    /// Strands longer than the distance between their ties are slack until they're pulled taut.
+    pub fn new(
+        start: Tie,
+        end: Tie,
+        rigidbodies: &Pool<RigidBody>,
+        segments: usize,
+        length: f32,
+        mass: f32,
+    ) -> Strand {
+        let segments = segments.max(1);
+        let (start_position, _) = start.get_motion(rigidbodies);
+        let (end_position, _) = end.get_motion(rigidbodies);
+        let knots = (1..segments)
+            .map(|i| Knot {
+                position: start_position
+                    + (end_position - start_position) * (i as f32 / segments as f32),
+                velocity: Vector3::zero(),
+                force: Vector3::zero(),
+            })
+            .collect();
+        Strand {
+            start,
+            end,
+            knots,
+            knot_mass: mass / segments as f32,
+            rest_length: length / segments as f32,
+            tensions: vec![0.0; segments],
+        }
+    }
+
+    /// Adds the segments' tensions to the knots, and to the rigidbodies the ends are tied to.
+    pub fn accumulate_forces(
+        &mut self,
+        rigidbodies: &mut Pool<RigidBody>,
+        material: StrandMaterial,
+        gravity: f32,
+    ) {
+        let rest_length = self.rest_length.max(f32::EPSILON);
+        let stiffness = material.axial / rest_length;
+        let damping = material.damping / rest_length;
+        for knot in self.knots.iter_mut() {
+            knot.force =
+                self.knot_mass * (Vector3::new(0.0, -gravity, 0.0) - material.drag * knot.velocity);
+        }
+
+        let start = self.start.get_motion(rigidbodies);
+        let end = self.end.get_motion(rigidbodies);
+        let motion = |knots: &[Knot], i: usize| match i {
+            0 => start,
+            i if i == knots.len() + 1 => end,
+            i => (knots[i - 1].position, knots[i - 1].velocity),
+        };
+        let mut start_force = Vector3::zero();
+        let mut end_force = Vector3::zero();
+        for segment in 0..self.tensions.len() {
+            let (from, from_velocity) = motion(&self.knots, segment);
+            let (to, to_velocity) = motion(&self.knots, segment + 1);
+            let length = (to - from).magnitude();
+            let stretch = length - self.rest_length;
+            // Ropes only pull; compressed segments are slack.
+            let tension = if stretch > 0.0 && length > f32::EPSILON {
+                let direction = (to - from) / length;
+                let stretch_rate = (to_velocity - from_velocity).dot(direction);
+                (stiffness * stretch + damping * stretch_rate).max(0.0)
+            } else {
+                0.0
+            };
+            self.tensions[segment] = tension;
+            if tension == 0.0 {
+                continue;
+            }
+            let force = tension * (to - from) / length;
+            if segment == 0 {
+                start_force += force;
+            } else {
+                self.knots[segment - 1].force += force;
+            }
+            if segment + 1 == self.tensions.len() {
+                end_force -= force;
+            } else {
+                self.knots[segment].force -= force;
+            }
+        }
+        self.start.pull(rigidbodies, start_force);
+        self.end.pull(rigidbodies, end_force);
+    }
+
+    /// Moves the knots by their accumulated forces, with semi-implicit Euler.
+    pub fn integrate(&mut self, dt: f32) {
+        for knot in self.knots.iter_mut() {
+            knot.velocity += knot.force / self.knot_mass * dt;
+            knot.position += knot.velocity * dt;
+        }
+    }
+
+    /// The points along the strand, from its start to its end.
+    pub fn get_points(&self, rigidbodies: &Pool<RigidBody>) -> Vec<Vector3<f32>> {
+        std::iter::once(self.start.get_motion(rigidbodies).0)
+            .chain(self.knots.iter().map(|knot| knot.position))
+            .chain(std::iter::once(self.end.get_motion(rigidbodies).0))
+            .collect()
+    }
+
+    /// Each segment's tension in the last step, in N, from the start to the end.
+    pub fn get_tensions(&self) -> &[f32] {
+        &self.tensions
+    }
+
+    pub fn get_max_tension(&self) -> f32 {
+        self.tensions.iter().copied().fold(0.0, f32::max)
+    }
+
+    pub fn get_ties(&self) -> (Tie, Tie) {
+        (self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATERIAL: StrandMaterial = StrandMaterial {
+        axial: 1000.0,
+        damping: 5.0,
+        drag: 2.0,
+    };
+
+    #[test]
+    fn slack_strands_carry_no_tension() {
+        let mut rigidbodies = Pool::new();
+        let mut strand = Strand::new(
+            Tie::Fixed(Vector3::zero()),
+            Tie::Fixed(Vector3::unit_x()),
+            &rigidbodies,
+            4,
+            1.5,
+            0.1,
+        );
+        strand.accumulate_forces(&mut rigidbodies, MATERIAL, 0.0);
+        assert_eq!(strand.get_max_tension(), 0.0);
+        assert_eq!(strand.get_points(&rigidbodies).len(), 5);
+    }
+
+    #[test]
+    fn hanging_strand_holds_its_weight() {
+        let mut rigidbodies = Pool::new();
+        let mut strand = Strand::new(
+            Tie::Fixed(Vector3::zero()),
+            Tie::Fixed(Vector3::unit_x()),
+            &rigidbodies,
+            8,
+            1.2,
+            1.0,
+        );
+        for _ in 0..20000 {
+            strand.accumulate_forces(&mut rigidbodies, MATERIAL, 9.8);
+            strand.integrate(0.0005);
+        }
+        // The middle sags, and the ends bear the most tension, each holding up half the weight of the knots
+        // between them, which carry the rope's mass.
+        let points = strand.get_points(&rigidbodies);
+        assert!(points[4].y < -0.2);
+        let tensions = strand.get_tensions();
+        assert!(tensions[0] > tensions[3] && tensions[7] > tensions[4]);
+        let vertical = |segment: usize| {
+            let direction = (points[segment + 1] - points[segment]).normalize();
+            tensions[segment] * direction.y.abs()
+        };
+        assert!((vertical(0) - 0.5 * 7.0 / 8.0 * 9.8).abs() < 0.1);
+    }
+}
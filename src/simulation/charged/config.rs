@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use crate::simulation::field::Field;
+use crate::simulation::state::Integration;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Scenario {
+    /// A uniform magnetic field, which particles spiral along in helices.
+    Uniform,
+    /// The field of a bar magnet, which traps particles bouncing between its poles, like the Van Allen belts.
+    Dipole,
+    /// Perpendicular electric and magnetic fields, which particles drift across rather than accelerating along.
+    CrossedFields,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 3] = [Scenario::Uniform, Scenario::Dipole, Scenario::CrossedFields];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scenario::Uniform => "Uniform",
+            Scenario::Dipole => "Dipole",
+            Scenario::CrossedFields => "Crossed Fields",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Config {
+    pub scenario: Scenario,
+    /// RK4 is needed for particles to keep to their helices; Euler spirals them outward.
+    pub integration: Integration,
+    pub dt: f32, // Seconds as f32
+    pub electric: Field,
+    pub magnetic: Field,
+    pub charge: f32,
+    pub mass: f32,
+    pub emitter_position: Vector3<f32>,
+    /// The unit vector particles are emitted along.
+    pub emitter_direction: Vector3<f32>,
+    pub emitter_speed: f32,
+    /// The greatest random velocity added to each particle as it's emitted.
+    pub emitter_spread: f32,
+    /// Particles emitted per second.
+    pub emission_rate: f32,
+    /// The time particles live for after they're emitted.
+    pub lifetime: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut config = Self {
+            scenario: Scenario::Uniform,
+            integration: Integration::Rk4,
+            dt: Duration::from_millis(5).as_secs_f32(),
+            electric: Field::uniform(Vector3::zero()),
+            magnetic: Field::uniform(Vector3::zero()),
+            charge: 1.0,
+            mass: 1.0,
+            emitter_position: Vector3::zero(),
+            emitter_direction: Vector3::unit_x(),
+            emitter_speed: 1.0,
+            emitter_spread: 0.05,
+            emission_rate: 50.0,
+            lifetime: 10.0,
+        };
+        config.set_scenario(Scenario::Uniform);
+        config
+    }
+}
+
+impl Config {
+    /// Sets up the fields and emitter for the scenario.
+    pub fn set_scenario(&mut self, scenario: Scenario) {
+        self.scenario = scenario;
+        match scenario {
+            Scenario::Uniform => {
+                self.electric = Field::uniform(Vector3::zero());
+                self.magnetic = Field::uniform(Vector3::new(0.0, 2.0, 0.0));
+                self.emitter_position = Vector3::new(0.0, -2.0, 0.0);
+                self.emitter_direction = Vector3::new(1.0, 0.3, 0.0).normalize();
+            }
+            Scenario::Dipole => {
+                self.electric = Field::uniform(Vector3::zero());
+                self.magnetic = Field::dipole(Vector3::zero(), Vector3::new(0.0, 50.0, 0.0));
+                self.emitter_position = Vector3::new(2.0, 0.0, 0.0);
+                self.emitter_direction = Vector3::new(0.0, 1.0, 1.0).normalize();
+            }
+            Scenario::CrossedFields => {
+                self.electric = Field::uniform(Vector3::new(1.0, 0.0, 0.0));
+                self.magnetic = Field::uniform(Vector3::new(0.0, 2.0, 0.0));
+                self.emitter_position = Vector3::new(0.0, 0.0, -2.0);
+                self.emitter_direction = Vector3::unit_x();
+            }
+        }
+    }
+}
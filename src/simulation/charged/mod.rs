@@ -0,0 +1,240 @@
+pub mod config;
+
+use self::config::Config;
+use super::field;
+use super::state::{stateful_fields, State, StateWriter, Stateful};
+use crate::math::Real;
+use crate::simulation::configurable::Configurable;
+
+use cgmath::Vector3;
+use rand::Rng;
+
+/// Particles aren't emitted past this many.
+pub const MAX_PARTICLES: usize = 5000;
+
+/// A charged particle. Its charge and mass aren't integrated, so they're left out of its state vector, and the
+/// fields acting on it are read from the config as its derivative's evaluated at the intermediate states of RK4.
+#[derive(Clone, Copy)]
+pub struct ChargedParticle {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub charge: f32,
+    pub mass: f32,
+    age: f32,
+}
+
+impl ChargedParticle {
+    /// The fraction of its lifetime the particle has lived.
+    pub fn get_age_fraction(&self, config: &Config) -> f32 {
+        self.age / config.lifetime
+    }
+}
+
+impl Stateful for ChargedParticle {
+    type Context = Config;
+
+    stateful_fields!(
+        7;
+        position: Vector3<f32>,
+        velocity: Vector3<f32>,
+        age: f32,
+    );
+
+    fn write_derivative(&self, config: &Config, derivative: &mut [Real]) {
+        let force = field::lorentz_force(
+            self.charge,
+            self.velocity,
+            config.electric.at(self.position),
+            config.magnetic.at(self.position),
+        );
+        StateWriter::new(derivative)
+            .push(&self.velocity)
+            .push(&(force / self.mass))
+            .push(&1.0)
+            .finish()
+    }
+
+    // The position. The age steps with the velocity, though it doesn't depend on anything.
+    fn num_position_elements() -> usize {
+        3
+    }
+}
+
+/// Charged particles moving through static electric and magnetic fields by the Lorentz force.
+/// An emitter streams particles with the same velocity, give or take a little spread,
+/// so the stream traces the path each particle takes, e.g. a helix along a magnetic field line.
+/// Gravity and the particles' fields on each other are neglected.
+pub struct Simulation {
+    config: Config,
+    state: State<ChargedParticle>,
+    /// The particles owed by the emitter, which are emitted once whole.
+    emission_accumulator: f32,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        Simulation {
+            config: Config::default(),
+            state: State::new(Vec::new()),
+            emission_accumulator: 0.0,
+        }
+    }
+
+    pub fn step(&mut self) -> std::time::Duration {
+        let dt = self.config.dt;
+        self.emit(dt);
+        self.state.step(self.config.integration, dt, &self.config);
+        let lifetime = self.config.lifetime;
+        self.state.retain(|particle| particle.age < lifetime);
+        std::time::Duration::from_secs_f32(dt)
+    }
+
+    fn emit(&mut self, dt: f32) {
+        self.emission_accumulator += self.config.emission_rate * dt;
+        let count = self.emission_accumulator.floor();
+        self.emission_accumulator -= count;
+        let count = (count as usize).min(MAX_PARTICLES.saturating_sub(self.state.elements().len()));
+
+        let mut rng = rand::thread_rng();
+        let spread = self.config.emitter_spread;
+        for _ in 0..count {
+            let jitter = Vector3::new(
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(-1.0..=1.0),
+            ) * spread;
+            let particle = ChargedParticle {
+                position: self.config.emitter_position,
+                velocity: self.config.emitter_direction * self.config.emitter_speed + jitter,
+                charge: self.config.charge,
+                mass: self.config.mass,
+                age: 0.0,
+            };
+            self.state.insert(particle);
+        }
+    }
+
+    pub fn get_timestep(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn get_particles(&self) -> &[ChargedParticle] {
+        self.state.elements()
+    }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.scenario = config.scenario;
+        self.config.integration = config.integration;
+        self.config.dt = config.dt;
+        self.config.electric = config.electric;
+        self.config.magnetic = config.magnetic;
+        self.config.charge = config.charge;
+        self.config.mass = config.mass;
+        self.config.emitter_position = config.emitter_position;
+        self.config.emitter_direction = config.emitter_direction;
+        self.config.emitter_speed = config.emitter_speed;
+        self.config.emitter_spread = config.emitter_spread;
+        self.config.emission_rate = config.emission_rate;
+        self.config.lifetime = config.lifetime;
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::field::Field;
+    use crate::simulation::state::Integration;
+    use cgmath::{InnerSpace, Zero};
+
+    /// Steps a single particle, of unit charge and mass, through the fields for the duration.
+    fn trace(
+        integration: Integration,
+        electric: Vector3<f32>,
+        magnetic: Vector3<f32>,
+        velocity: Vector3<f32>,
+        duration: f32,
+    ) -> ChargedParticle {
+        let mut state = State::new(vec![ChargedParticle {
+            position: Vector3::zero(),
+            velocity,
+            charge: 1.0,
+            mass: 1.0,
+            age: 0.0,
+        }]);
+        let config = Config {
+            electric: Field::uniform(electric),
+            magnetic: Field::uniform(magnetic),
+            ..Default::default()
+        };
+        let dt = 0.01;
+        for _ in 0..(duration / dt).round() as usize {
+            state.step(integration, dt, &config);
+        }
+        state.elements()[0]
+    }
+
+    #[test]
+    fn rk4_keeps_helix_radius() {
+        let magnetic = Vector3::unit_y();
+        // Unit speed across a unit field gyrates with unit radius, about a center a unit along v × B.
+        let velocity = Vector3::new(1.0, 0.5, 0.0);
+        let center = Vector3::unit_z();
+        let radius = |particle: ChargedParticle| {
+            let offset = particle.position - center;
+            (offset - magnetic * offset.dot(magnetic)).magnitude()
+        };
+        let rk4 = trace(Integration::Rk4, Vector3::zero(), magnetic, velocity, 10.0);
+        assert!((radius(rk4) - 1.0).abs() < 1e-3, "{}", radius(rk4));
+        // The particle keeps its speed along the field line.
+        assert!((rk4.position.y - 5.0).abs() < 1e-3);
+        // Euler gains energy every step, so its particles spiral outward.
+        let euler = trace(
+            Integration::Euler,
+            Vector3::zero(),
+            magnetic,
+            velocity,
+            10.0,
+        );
+        assert!(radius(euler) > 1.02, "{}", radius(euler));
+    }
+
+    #[test]
+    fn crossed_fields_drift() {
+        let period = 2.0 * std::f32::consts::PI;
+        let particle = trace(
+            Integration::Rk4,
+            Vector3::unit_x(),
+            Vector3::unit_y(),
+            Vector3::zero(),
+            period,
+        );
+        // After each gyration, a particle starting at rest has drifted by E × B / B² over it.
+        assert!((particle.position - Vector3::unit_z() * period).magnitude() < 0.05);
+    }
+
+    #[test]
+    fn emits_and_expires_particles() {
+        let mut simulation = Simulation::new();
+        simulation.config.lifetime = 0.5;
+        for _ in 0..200 {
+            simulation.step();
+        }
+        let count = simulation.get_particles().len();
+        // About half a second's worth of particles live at once.
+        assert!((20..=30).contains(&count), "{}", count);
+    }
+}
@@ -0,0 +1,186 @@
+/// Checksums of simulation state, for checking that simulations are deterministic, i.e. that the same
+/// simulation stepped the same way ends in bit-identical state, which replays and networking depend on.
+/// The checksum is a 64-bit FNV-1a hash of the bits of each value, in order, so that any difference
+/// in any value, even the sign of a zero, changes it.
+use cgmath::{Quaternion, Vector3};
+
+pub struct Checksum {
+    hash: u64,
+}
+
+impl Checksum {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    pub fn new() -> Checksum {
+        Checksum {
+            hash: Checksum::OFFSET_BASIS,
+        }
+    }
+
+    pub fn add_u32(&mut self, value: u32) {
+        for byte in value.to_le_bytes() {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(Checksum::PRIME);
+        }
+    }
+
+    pub fn add_f32(&mut self, value: f32) {
+        self.add_u32(value.to_bits());
+    }
+
+    pub fn add_vector3(&mut self, value: Vector3<f32>) {
+        self.add_f32(value.x);
+        self.add_f32(value.y);
+        self.add_f32(value.z);
+    }
+
+    pub fn add_quaternion(&mut self, value: Quaternion<f32>) {
+        self.add_f32(value.s);
+        self.add_vector3(value.v);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Checksum::new()
+    }
+}
+
+/// Simulation state which can be checksummed.
+pub trait Checksummed {
+    /// Adds the state which evolves as the simulation steps to the checksum, in a fixed order.
+    fn add_to_checksum(&self, checksum: &mut Checksum);
+
+    fn checksum(&self) -> u64 {
+        let mut checksum = Checksum::new();
+        self.add_to_checksum(&mut checksum);
+        checksum.finish()
+    }
+}
+
+impl<T: Checksummed> Checksummed for [T] {
+    fn add_to_checksum(&self, checksum: &mut Checksum) {
+        checksum.add_u32(self.len() as u32);
+        self.iter()
+            .for_each(|element| element.add_to_checksum(checksum));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::collidable_mesh::CollidableMesh;
+    use crate::simulation::geometry;
+    use crate::simulation::quantity;
+    use crate::simulation::rigidbody::{rigidbody::RigidBody, simulation::Simulation as Rigid};
+    use crate::simulation::springy::{simulation::Simulation as Springy, springy_mesh};
+    use crate::simulation::{bounce, springy::springy_mesh::SpringyMesh};
+    use cgmath::{ElementWise, Zero};
+
+    /// Steps the simulation the number of times, returning its checksum after each step.
+    fn checksums<S, F: FnMut(&mut S)>(simulation: &mut S, steps: usize, mut step: F) -> Vec<u64>
+    where
+        S: Checksummed,
+    {
+        (0..steps)
+            .map(|_| {
+                step(simulation);
+                simulation.checksum()
+            })
+            .collect()
+    }
+
+    fn floor() -> CollidableMesh {
+        let (vertices, indices) = geometry::get_cube_vertices();
+        let vertices = vertices
+            .into_iter()
+            .map(|v| v.mul_element_wise(Vector3::new(20.0, 1.0, 20.0)) - Vector3::unit_y() * 3.0)
+            .collect();
+        CollidableMesh::new(vertices, indices)
+    }
+
+    fn springy() -> Springy {
+        let (vertices, indices) = geometry::get_cube_vertices();
+        let mut cube = SpringyMesh::new(
+            vertices,
+            indices,
+            quantity::kilograms(80.0),
+            quantity::newtons_per_meter(10000.0),
+            quantity::newton_seconds_per_meter(10.0),
+            Some(springy_mesh::TorsionalSpringConfig::default()),
+            &None,
+        );
+        cube.add_strut(
+            (0, 7),
+            quantity::newtons_per_meter(10000.0),
+            quantity::newton_seconds_per_meter(10.0),
+        );
+        Springy::new(vec![cube], vec![floor()])
+    }
+
+    fn rigid() -> Rigid {
+        let dimensions = Vector3::new(1.0, 0.5, 2.0);
+        let a = RigidBody::cuboid(Vector3::zero(), quantity::kilograms(1.0), dimensions).unwrap();
+        let b = RigidBody::cuboid(
+            Vector3::new(0.3, 2.0, 0.2),
+            quantity::kilograms(2.0),
+            dimensions,
+        )
+        .unwrap();
+        Rigid::new(vec![a, b], vec![floor()])
+    }
+
+    #[test]
+    fn distinguishes_values_and_order() {
+        let hash = |values: &[f32]| {
+            let mut checksum = Checksum::new();
+            values.iter().for_each(|value| checksum.add_f32(*value));
+            checksum.finish()
+        };
+        assert_eq!(hash(&[1.0, 2.0]), hash(&[1.0, 2.0]));
+        assert_ne!(hash(&[1.0, 2.0]), hash(&[2.0, 1.0]));
+        assert_ne!(hash(&[0.0]), hash(&[-0.0]));
+    }
+
+    #[test]
+    fn springy_is_deterministic() {
+        let first = checksums(&mut springy(), 300, |s| {
+            s.step();
+        });
+        let second = checksums(&mut springy(), 300, |s| {
+            s.step();
+        });
+        assert_eq!(first, second);
+        assert_ne!(first[0], first[299]);
+    }
+
+    #[test]
+    fn rigidbodies_are_deterministic() {
+        let first = checksums(&mut rigid(), 300, |s| {
+            s.step();
+        });
+        let second = checksums(&mut rigid(), 300, |s| {
+            s.step();
+        });
+        assert_eq!(first, second);
+        assert_ne!(first[0], first[299]);
+    }
+
+    #[test]
+    fn bounce_matches_across_platforms() {
+        // The ball's physics are only arithmetic and square roots, which IEEE 754 rounds the same way
+        // everywhere, so its state after a few bounces has the same checksum on every platform.
+        // If the bouncing ball's physics change on purpose, this checksum needs updating.
+        let mut state = bounce::State::new();
+        state.launch(Vector3::zero(), Vector3::new(1.5, 2.0, -0.5));
+        let checksums = checksums(&mut state, 500, |s| {
+            s.step();
+        });
+        assert_eq!(checksums[499], 0x3f4d4d8c51935620);
+    }
+}
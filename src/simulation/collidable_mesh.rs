@@ -1,7 +1,11 @@
 use std::{collections::BTreeSet, time::Duration};
 
-use cgmath::{InnerSpace, Vector3};
+use cgmath::{InnerSpace, Vector3, Zero};
 use itertools::Itertools;
+
+use super::collision_filter::CollisionFilter;
+use super::consts;
+
 pub struct Vertex {
     position: Vector3<f32>,
 }
@@ -30,7 +34,7 @@ impl Edge {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Face {
     pub v0: Vector3<f32>,
     pub v1: Vector3<f32>,
@@ -45,12 +49,122 @@ impl Face {
     pub fn distance_from_plane(&self, point: &cgmath::Vector3<f32>) -> f32 {
         (point - self.v0).dot(self.normal())
     }
+
+    /// Gets the distance along the ray at which it hits the face, if it does.
+    /// The direction should be normalized. Hits from either side of the face are reported.
+    pub fn ray_intersection(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        // Möller–Trumbore
+        let edge0 = self.v1 - self.v0;
+        let edge1 = self.v2 - self.v0;
+        let p = direction.cross(edge1);
+        let determinant = edge0.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+        let s = origin - self.v0;
+        let u = s.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge0);
+        let v = direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge1.dot(q) * inverse_determinant;
+        if distance < 0.0 {
+            None
+        } else {
+            Some(distance)
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.v1 - self.v0).cross(self.v2 - self.v0).magnitude() / 2.0
+    }
+
+    /// True if the point, projected along the normal onto the face's plane, lands inside the face.
+    pub fn contains_projection(&self, point: Vector3<f32>) -> bool {
+        let normal = self.normal();
+        let inside = |a: Vector3<f32>, b: Vector3<f32>| (b - a).cross(point - a).dot(normal) >= 0.0;
+        inside(self.v0, self.v1) && inside(self.v1, self.v2) && inside(self.v2, self.v0)
+    }
+}
+
+/// Which sides of an obstacle's faces collide.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Sidedness {
+    /// Only the fronts of the faces, which their normals point out of, as on the outside of a closed mesh.
+    /// Points behind the faces are inside the obstacle, and are pushed back out of it.
+    OneSided,
+    /// Both sides of the faces, as of a thin sheet. Points bounce back to whichever side they came from.
+    TwoSided,
+}
+
+impl Sidedness {
+    pub const ALL: [Sidedness; 2] = [Sidedness::OneSided, Sidedness::TwoSided];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sidedness::OneSided => "One-Sided",
+            Sidedness::TwoSided => "Two-Sided",
+        }
+    }
+
+    /// True if a point at the old signed distance from a face's plane, moving to the new one, hits it.
+    pub fn crosses(&self, old_distance: f32, new_distance: f32) -> bool {
+        match self {
+            Sidedness::OneSided => {
+                old_distance.is_sign_positive() && new_distance.is_sign_negative()
+            }
+            Sidedness::TwoSided => {
+                old_distance.is_sign_positive() != new_distance.is_sign_positive()
+            }
+        }
+    }
+}
+
+/// Where a point must move to be out of a face it penetrates.
+pub struct Penetration<'a> {
+    pub face: &'a Face,
+    /// How far the point is behind the face.
+    pub depth: f32,
+}
+
+impl<'a> Penetration<'a> {
+    /// The point moved out along the face's normal to just in front of it.
+    pub fn resolve(&self, point: Vector3<f32>) -> Vector3<f32> {
+        point + self.face.normal() * (self.depth + consts::EPSILON)
+    }
+}
+
+/// Finds the face nearest the point among those whose planes it projects inside of, and if the point's
+/// behind that face, how far. Points above a face of a closed mesh are nearer to it than to the faces on
+/// the far side, so only points inside the mesh, or under an open mesh's faces, penetrate it.
+pub fn find_penetration<'a>(
+    faces: impl Iterator<Item = &'a Face>,
+    point: Vector3<f32>,
+) -> Option<Penetration<'a>> {
+    faces
+        .filter(|face| face.contains_projection(point))
+        .map(|face| (face, face.distance_from_plane(&point)))
+        .min_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        .filter(|(_, distance)| *distance < 0.0)
+        .map(|(face, distance)| Penetration {
+            face,
+            depth: -distance,
+        })
 }
 
 pub struct CollidableMesh {
     vertices: Vec<Vertex>,
     edges: Vec<Edge>,
     faces: Vec<Face>,
+    collision_filter: CollisionFilter,
+    /// The velocity the mesh's surface slides at, e.g. as a conveyor belt's does, though the mesh stays in place.
+    surface_velocity: Vector3<f32>,
+    sidedness: Sidedness,
 }
 
 impl CollidableMesh {
@@ -101,11 +215,65 @@ impl CollidableMesh {
             vertices,
             edges,
             faces,
+            collision_filter: CollisionFilter::default(),
+            surface_velocity: Vector3::zero(),
+            sidedness: Sidedness::TwoSided,
         }
     }
 
+    pub fn with_collision_filter(mut self, collision_filter: CollisionFilter) -> CollidableMesh {
+        self.collision_filter = collision_filter;
+        self
+    }
+
+    pub fn get_collision_filter(&self) -> &CollisionFilter {
+        &self.collision_filter
+    }
+
+    pub fn with_surface_velocity(mut self, surface_velocity: Vector3<f32>) -> CollidableMesh {
+        self.surface_velocity = surface_velocity;
+        self
+    }
+
+    pub fn set_surface_velocity(&mut self, surface_velocity: Vector3<f32>) {
+        self.surface_velocity = surface_velocity;
+    }
+
+    /// Makes the mesh one-sided or two-sided. Meshes are two-sided unless they're made one-sided, which suits
+    /// closed meshes whose normals point outwards.
+    pub fn with_sidedness(mut self, sidedness: Sidedness) -> CollidableMesh {
+        self.sidedness = sidedness;
+        self
+    }
+
+    pub fn set_sidedness(&mut self, sidedness: Sidedness) {
+        self.sidedness = sidedness;
+    }
+
+    pub fn get_sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    /// Where the point penetrates the mesh, if it's one-sided and the point is inside it.
+    pub fn get_penetration(&self, point: Vector3<f32>) -> Option<Penetration<'_>> {
+        match self.sidedness {
+            Sidedness::OneSided => find_penetration(self.faces.iter(), point),
+            Sidedness::TwoSided => None,
+        }
+    }
+
+    /// The velocity the surface slides at along the face, which friction drags whatever touches it towards.
+    /// Only the part of the mesh's surface velocity in the face's plane slides, so a flat mesh makes a conveyor belt.
+    pub fn get_surface_velocity(&self, face: &Face) -> Vector3<f32> {
+        let normal = face.normal();
+        self.surface_velocity - self.surface_velocity.dot(normal) * normal
+    }
+
+    /// The first of the faces the point hit moving from its old to its new position, hitting them on the
+    /// sides the sidedness collides.
     pub fn get_collided_face_from_list<'a>(
         faces: &'a Vec<&Face>,
+        sidedness: Sidedness,
         old_position: Vector3<f32>,
         new_position: Vector3<f32>,
         dt: Duration,
@@ -116,10 +284,7 @@ impl CollidableMesh {
             let old_distance_to_plane = face.distance_from_plane(&old_position);
             let new_distance_to_plane = face.distance_from_plane(&new_position);
 
-            let crossed_plane = old_distance_to_plane.is_sign_positive()
-                != new_distance_to_plane.is_sign_positive();
-
-            if !crossed_plane {
+            if !sidedness.crosses(old_distance_to_plane, new_distance_to_plane) {
                 return false;
             }
             // Get the point in the plane of the tri
@@ -194,16 +359,27 @@ impl CollidableMesh {
     pub fn get_faces(&self) -> &Vec<Face> {
         &self.faces
     }
+
+    /// The area of the mesh's silhouette when viewed along the direction, i.e. the area presented
+    /// to a flow moving in that direction. Assumes the mesh is closed, with outward facing normals.
+    pub fn projected_area(&self, direction: Vector3<f32>) -> f32 {
+        let direction = direction.normalize();
+        self.faces
+            .iter()
+            .map(|face| face.area() * face.normal().dot(-direction).max(0.0))
+            .sum()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use cgmath::{Vector3, Zero};
+    use cgmath::{InnerSpace, Vector3, Zero};
     use itertools::Itertools;
 
     use super::CollidableMesh;
     use super::Edge;
     use super::Face;
+    use super::Sidedness;
 
     fn get_strip() -> CollidableMesh {
         let vertex_positions = vec![
@@ -267,4 +443,97 @@ mod tests {
         ];
         assert_eq!(expected_faces, obstacle.faces);
     }
+
+    #[test]
+    fn surface_velocity_slides_along_faces() {
+        let obstacle = get_strip().with_surface_velocity(Vector3::new(1.0, 2.0, 3.0));
+        // The strip lies in the XY plane, so it can't slide along Z.
+        for face in obstacle.get_faces() {
+            assert_eq!(
+                Vector3::new(1.0, 2.0, 0.0),
+                obstacle.get_surface_velocity(face)
+            );
+        }
+    }
+
+    #[test]
+    fn ray_intersection() {
+        let face = Face {
+            v0: Vector3::<f32>::zero(),
+            v1: Vector3::<f32>::unit_x(),
+            v2: Vector3::<f32>::unit_y(),
+        };
+        let hit = face.ray_intersection(Vector3::new(0.25, 0.25, 2.0), -Vector3::<f32>::unit_z());
+        assert_eq!(Some(2.0), hit);
+        // Pointing away from the face.
+        assert_eq!(
+            None,
+            face.ray_intersection(Vector3::new(0.25, 0.25, 2.0), Vector3::<f32>::unit_z())
+        );
+        // Outside the triangle.
+        assert_eq!(
+            None,
+            face.ray_intersection(Vector3::new(0.75, 0.75, 2.0), -Vector3::<f32>::unit_z())
+        );
+    }
+
+    #[test]
+    fn one_sided_faces_are_hit_from_the_front() {
+        let strip = get_strip();
+        let faces = strip.get_faces().iter().collect::<Vec<_>>();
+        let dt = std::time::Duration::from_secs_f32(0.1);
+        let front = Vector3::new(0.1, 0.5, 0.1);
+        let back = Vector3::new(0.1, 0.5, -0.1);
+        for sidedness in Sidedness::ALL {
+            assert!(CollidableMesh::get_collided_face_from_list(
+                &faces, sidedness, front, back, dt
+            )
+            .is_some());
+        }
+        // Only two-sided faces are hit from behind.
+        assert!(CollidableMesh::get_collided_face_from_list(
+            &faces,
+            Sidedness::OneSided,
+            back,
+            front,
+            dt
+        )
+        .is_none());
+        assert!(CollidableMesh::get_collided_face_from_list(
+            &faces,
+            Sidedness::TwoSided,
+            back,
+            front,
+            dt
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn penetration_through_the_nearest_face() {
+        let (vertex_positions, vertex_indices) = crate::simulation::geometry::get_cube_vertices();
+        let cube = CollidableMesh::new(vertex_positions, vertex_indices);
+        let inside = Vector3::new(0.1, 0.4, -0.2);
+        assert!(cube.get_penetration(inside).is_none());
+        let cube = cube.with_sidedness(Sidedness::OneSided);
+        let penetration = cube.get_penetration(inside).unwrap();
+        assert!((penetration.face.normal() - Vector3::unit_y()).magnitude() < 1e-5);
+        assert!((penetration.depth - 0.1).abs() < 1e-5);
+        assert!(cube.get_penetration(penetration.resolve(inside)).is_none());
+        // Points outside the cube don't penetrate it, though they're behind its far faces.
+        assert!(cube.get_penetration(Vector3::new(0.1, 0.7, -0.2)).is_none());
+        assert!(cube.get_penetration(Vector3::new(0.7, 0.7, 0.0)).is_none());
+    }
+
+    #[test]
+    fn projected_area() {
+        let (vertex_positions, vertex_indices) = crate::simulation::geometry::get_cube_vertices();
+        let cube = CollidableMesh::new(vertex_positions, vertex_indices);
+        assert!((cube.projected_area(Vector3::<f32>::unit_x()) - 1.0).abs() < 0.0001);
+        // Viewed along the diagonal, the silhouette of a unit cube is a hexagon of area sqrt(3).
+        assert!(
+            (cube.projected_area(Vector3::<f32>::new(1.0, 1.0, 1.0)) - 3.0_f32.sqrt()).abs()
+                < 0.0001
+        );
+    }
 }
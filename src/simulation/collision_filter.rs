@@ -0,0 +1,63 @@
+/// Filters which objects can collide with each other, by group and mask bitfields.
+/// Two objects collide only if each one's group is in the other's mask.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CollisionFilter {
+    /// The groups this object belongs to.
+    pub group: u32,
+    /// The groups this object collides with.
+    pub mask: u32,
+}
+
+impl CollisionFilter {
+    pub const STATIC: u32 = 1 << 0;
+    pub const PARTICLE: u32 = 1 << 1;
+    pub const CLOTH: u32 = 1 << 2;
+    pub const RIGIDBODY: u32 = 1 << 3;
+    pub const ALL: u32 = u32::MAX;
+
+    /// The built in groups, with names for display.
+    pub const GROUPS: [(u32, &'static str); 4] = [
+        (CollisionFilter::STATIC, "Static"),
+        (CollisionFilter::PARTICLE, "Particles"),
+        (CollisionFilter::CLOTH, "Cloth"),
+        (CollisionFilter::RIGIDBODY, "Rigidbodies"),
+    ];
+
+    pub fn new(group: u32, mask: u32) -> CollisionFilter {
+        CollisionFilter { group, mask }
+    }
+
+    pub fn collides_with(&self, other: &CollisionFilter) -> bool {
+        self.group & other.mask != 0 && other.group & self.mask != 0
+    }
+}
+
+impl Default for CollisionFilter {
+    /// A static object which collides with everything.
+    fn default() -> Self {
+        CollisionFilter::new(CollisionFilter::STATIC, CollisionFilter::ALL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CollisionFilter;
+
+    #[test]
+    fn collides_with() {
+        let floor = CollisionFilter::default();
+        // Debris hits the floor, but not other debris.
+        let debris = CollisionFilter::new(CollisionFilter::PARTICLE, CollisionFilter::STATIC);
+        assert!(debris.collides_with(&floor));
+        assert!(floor.collides_with(&debris));
+        assert!(!debris.collides_with(&debris));
+    }
+
+    #[test]
+    fn collides_with_requires_both_masks() {
+        let ghost = CollisionFilter::new(CollisionFilter::STATIC, 0);
+        let floor = CollisionFilter::default();
+        assert!(!floor.collides_with(&ghost));
+        assert!(!ghost.collides_with(&floor));
+    }
+}
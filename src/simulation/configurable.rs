@@ -0,0 +1,36 @@
+/// Simulations configured by a plain config, which a GUI, a script, or another application edits
+/// and the simulation syncs from, so that simulations don't depend on whatever edits their configs.
+pub trait Configurable {
+    type Config: Clone + PartialEq;
+
+    fn get_config(&self) -> &Self::Config;
+
+    /// Copies the settings of the config, e.g. a GUI's, into the simulation's config.
+    fn sync_config(&mut self, config: &Self::Config);
+
+    /// Syncs from the config only if it differs from the simulation's, returning whether it did.
+    fn sync_config_if_changed(&mut self, config: &Self::Config) -> bool {
+        if self.get_config() == config {
+            return false;
+        }
+        self.sync_config(config);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::bounce;
+
+    #[test]
+    fn syncs_only_changed_configs() {
+        let mut ball = bounce::State::new();
+        let mut config = bounce::Config::default();
+        assert!(!ball.sync_config_if_changed(&config));
+        config.drag = 2.0;
+        assert!(ball.sync_config_if_changed(&config));
+        assert_eq!(ball.get_config().drag, 2.0);
+        assert!(!ball.sync_config_if_changed(&config));
+    }
+}
@@ -0,0 +1,94 @@
+/// Audits, in debug builds, that collision responses conserve linear momentum: whatever momentum the moving bodies
+/// gain or lose over a response must be what the fixed bodies they hit, e.g. obstacles and cushions, absorb. A
+/// response which applies an impulse to one body of a pair without its opposite to the other, or misses what a fixed
+/// body absorbs, injects momentum, which is logged as a warning under the responsible module's target with the
+/// momentum injected, so that the bug shows as it happens rather than as a simulation slowly gaining energy.
+/// Release builds skip the audits.
+use cgmath::{InnerSpace, Vector3, Zero};
+
+/// Momentum injected past this fraction of the impulses exchanged is a violation, so that their rounding isn't.
+const RELATIVE_TOLERANCE: f32 = 1e-3;
+/// Momentum injected past this fraction of the moving bodies' total momentum is also a violation, since the
+/// impulses are rounded to it as they're added to a body's momentum.
+const MOMENTUM_TOLERANCE: f32 = 1e-5;
+/// Momentum injected past this many kg m/s is a violation, however small the impulses and momentum.
+const ABSOLUTE_TOLERANCE: f32 = 1e-5;
+
+pub struct MomentumAudit {
+    module: &'static str,
+    // The moving bodies' total momentum before the response, left unsummed in release builds.
+    before: Option<Vector3<f32>>,
+    absorbed: Vector3<f32>,
+    exchanged: f32,
+}
+
+impl MomentumAudit {
+    /// Begins auditing the module's response from the moving bodies' total momentum, which is only summed in
+    /// debug builds.
+    pub fn begin(module: &'static str, momentum: impl FnOnce() -> Vector3<f32>) -> MomentumAudit {
+        MomentumAudit {
+            module,
+            before: cfg!(debug_assertions).then(momentum),
+            absorbed: Vector3::zero(),
+            exchanged: 0.0,
+        }
+    }
+
+    /// Records an impulse the response applied between the bodies.
+    pub fn exchange(&mut self, impulse: Vector3<f32>) {
+        self.exchanged += impulse.magnitude();
+    }
+
+    /// Records an impulse the response applied to a fixed body, which absorbs it.
+    pub fn absorb(&mut self, impulse: Vector3<f32>) {
+        self.absorbed += impulse;
+        self.exchange(impulse);
+    }
+
+    /// Ends the audit with the moving bodies' total momentum after the response, returning the magnitude of the
+    /// momentum it injected if that's a violation, which is logged. Release builds never find a violation.
+    pub fn finish(self, momentum: impl FnOnce() -> Vector3<f32>) -> Option<f32> {
+        let before = self.before?;
+        let after = momentum();
+        let injected = (after - before + self.absorbed).magnitude();
+        let tolerance = RELATIVE_TOLERANCE * self.exchanged
+            + MOMENTUM_TOLERANCE * before.magnitude().max(after.magnitude());
+        if injected <= tolerance.max(ABSOLUTE_TOLERANCE) {
+            return None;
+        }
+        log::warn!(
+            target: self.module,
+            "Collision response injected {} kg m/s of momentum, over {} kg m/s of impulses",
+            injected,
+            self.exchanged
+        );
+        Some(injected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::assert_relative_eq;
+
+    #[test]
+    fn balanced_responses_pass() {
+        // A body bounces off a wall, and then passes half its momentum on to another.
+        let mut momentum = Vector3::new(2.0, 0.0, 0.0);
+        let mut audit = MomentumAudit::begin(module_path!(), || momentum);
+        audit.absorb(Vector3::new(4.0, 0.0, 0.0));
+        audit.exchange(Vector3::new(1.0, 0.0, 0.0));
+        momentum = Vector3::new(-2.0, 0.0, 0.0);
+        assert_eq!(None, audit.finish(|| momentum));
+    }
+
+    #[test]
+    #[cfg_attr(not(debug_assertions), ignore)]
+    fn unbalanced_responses_are_violations() {
+        // The impulse on the second body was never applied.
+        let audit = MomentumAudit::begin(module_path!(), || Vector3::new(1.0, 0.0, 0.0));
+        let injected = audit.finish(|| Vector3::new(0.5, 0.0, 0.0));
+        assert_relative_eq!(0.5, injected.unwrap());
+    }
+}
@@ -0,0 +1,306 @@
+/// Containers for the particle simulations, centered on the origin, whose walls can be moved and reshaped as
+/// they run. A container's walls move towards the size and shape they're set to at a limited speed, pushing
+/// the particles they pass inward, so that the simulations can compress their contents like a piston.
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use super::geometry;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContainerShape {
+    Box,
+    /// A cylinder with a vertical axis, capped at the top and bottom.
+    Cylinder,
+    Sphere,
+}
+
+impl ContainerShape {
+    pub const ALL: [ContainerShape; 3] = [
+        ContainerShape::Box,
+        ContainerShape::Cylinder,
+        ContainerShape::Sphere,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContainerShape::Box => "Box",
+            ContainerShape::Cylinder => "Cylinder",
+            ContainerShape::Sphere => "Sphere",
+        }
+    }
+}
+
+/// A wall of a container: the plane tangent to it at a point on its surface, facing into it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Wall {
+    pub point: Vector3<f32>,
+    pub normal: Vector3<f32>,
+}
+
+impl Wall {
+    /// Positive inside the container.
+    pub fn distance_to(&self, point: Vector3<f32>) -> f32 {
+        (point - self.point).dot(self.normal)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Container {
+    shape: ContainerShape,
+    // A cylinder's radius is x, and its half height y. A sphere's radius is x.
+    half_extents: Vector3<f32>,
+}
+
+impl Container {
+    /// The walls move at up to this many meters per second towards the container they're set to.
+    pub const WALL_SPEED: f32 = 0.5;
+    /// Walls come no closer together than this, so that the container always has room inside.
+    pub const MIN_HALF_EXTENT: f32 = 0.1;
+    const SECTORS: u32 = 32;
+
+    /// The half extents of the dimensions the shape doesn't have are ignored, e.g. a sphere's y and z.
+    pub fn new(shape: ContainerShape, half_extents: Vector3<f32>) -> Container {
+        let half_extents = half_extents.map(|extent| extent.max(Container::MIN_HALF_EXTENT));
+        let half_extents = match shape {
+            ContainerShape::Box => half_extents,
+            ContainerShape::Cylinder => {
+                Vector3::new(half_extents.x, half_extents.y, half_extents.x)
+            }
+            ContainerShape::Sphere => Vector3::new(half_extents.x, half_extents.x, half_extents.x),
+        };
+        Container {
+            shape,
+            half_extents,
+        }
+    }
+
+    pub fn get_shape(&self) -> ContainerShape {
+        self.shape
+    }
+
+    /// How far the container extends from its center along each axis.
+    pub fn get_half_extents(&self) -> Vector3<f32> {
+        self.half_extents
+    }
+
+    /// The wall nearest the point, inside or outside the container.
+    pub fn nearest_wall(&self, point: Vector3<f32>) -> Wall {
+        let (walls, count) = self.walls(point);
+        walls[..count]
+            .iter()
+            .copied()
+            .min_by(|a, b| a.distance_to(point).total_cmp(&b.distance_to(point)))
+            .unwrap()
+    }
+
+    /// The walls the point is within the distance of, or outside of.
+    pub fn walls_within(&self, point: Vector3<f32>, distance: f32) -> impl Iterator<Item = Wall> {
+        let (walls, count) = self.walls(point);
+        walls
+            .into_iter()
+            .take(count)
+            .filter(move |wall| wall.distance_to(point) < distance)
+    }
+
+    pub fn contains(&self, point: Vector3<f32>) -> bool {
+        self.nearest_wall(point).distance_to(point) >= 0.0
+    }
+
+    /// The point moved inside the container, at least the margin from each of its walls.
+    pub fn clamp_inside(&self, mut point: Vector3<f32>, margin: f32) -> Vector3<f32> {
+        for wall in self.walls_within(point, margin) {
+            point += (margin - wall.distance_to(point)) * wall.normal;
+        }
+        point
+    }
+
+    /// Moves a particle the container's walls have passed back inside the margin from them, at least as fast
+    /// as the walls are moving inward, so that it's pushed along by them rather than through them.
+    pub fn push_inside(
+        &self,
+        position: &mut Vector3<f32>,
+        velocity: &mut Vector3<f32>,
+        wall_speed: f32,
+        margin: f32,
+    ) {
+        for wall in self.walls_within(*position, margin) {
+            *position += (margin - wall.distance_to(*position)) * wall.normal;
+            let normal_speed = velocity.dot(wall.normal);
+            if normal_speed < wall_speed {
+                *velocity += (wall_speed - normal_speed) * wall.normal;
+            }
+        }
+    }
+
+    /// The smallest container of the shape which this one fits inside.
+    pub fn enclosing(&self, shape: ContainerShape) -> Container {
+        let Vector3 { x, y, z } = self.half_extents;
+        let half_extents = match (self.shape, shape) {
+            (from, to) if from == to => self.half_extents,
+            (ContainerShape::Box, ContainerShape::Cylinder) => {
+                Vector3::new((x * x + z * z).sqrt(), y, 0.0)
+            }
+            (ContainerShape::Box, ContainerShape::Sphere) => {
+                Vector3::new(self.half_extents.magnitude(), 0.0, 0.0)
+            }
+            (ContainerShape::Cylinder, ContainerShape::Box) => Vector3::new(x, y, x),
+            (ContainerShape::Cylinder, ContainerShape::Sphere) => {
+                Vector3::new((x * x + y * y).sqrt(), 0.0, 0.0)
+            }
+            (ContainerShape::Sphere, _) => Vector3::new(x, x, x),
+            _ => unreachable!(),
+        };
+        Container::new(shape, half_extents)
+    }
+
+    /// Moves the container's walls towards the target's by up to the distance, first growing it into the
+    /// target's shape if it differs. Returns the furthest any wall moved inward.
+    pub fn approach(&mut self, target: &Container, max_distance: f32) -> f32 {
+        if self.shape != target.shape {
+            *self = self.enclosing(target.shape);
+        }
+        let mut inward = 0.0_f32;
+        let mut half_extents = self.half_extents;
+        for axis in 0..3 {
+            let step =
+                (target.half_extents[axis] - half_extents[axis]).clamp(-max_distance, max_distance);
+            if step.abs() < max_distance {
+                half_extents[axis] = target.half_extents[axis];
+            } else {
+                half_extents[axis] += step;
+            }
+            inward = inward.max(-step);
+        }
+        *self = Container::new(self.shape, half_extents);
+        inward
+    }
+
+    /// The vertices and indices of the container's surface, with its triangles facing inward.
+    pub fn get_mesh(&self) -> (Vec<Vector3<f32>>, Vec<usize>) {
+        let (vertices, mut indices) = match self.shape {
+            ContainerShape::Box => {
+                let (vertices, indices) = geometry::get_cube_vertices();
+                let scale = 2.0 * self.half_extents;
+                let vertices = vertices
+                    .into_iter()
+                    .map(|vertex| vertex.zip(scale, |vertex, scale| vertex * scale))
+                    .collect();
+                (vertices, indices)
+            }
+            ContainerShape::Cylinder => geometry::get_cylinder_vertices(
+                self.half_extents.x,
+                self.half_extents.y,
+                Container::SECTORS,
+            ),
+            ContainerShape::Sphere => geometry::get_sphere_vertices(
+                self.half_extents.x,
+                Container::SECTORS,
+                Container::SECTORS,
+            ),
+        };
+        for triangle in indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+        (vertices, indices)
+    }
+
+    // The walls the point could be nearest: for a box, the face on the point's side along each axis, for a
+    // cylinder, its side and the cap on the point's side, and for a sphere, its surface.
+    fn walls(&self, point: Vector3<f32>) -> ([Wall; 3], usize) {
+        let side = |value: f32| if value < 0.0 { -1.0 } else { 1.0 };
+        let face = |axis: usize| {
+            let mut normal = Vector3::zero();
+            normal[axis] = -side(point[axis]);
+            Wall {
+                point: -normal * self.half_extents[axis],
+                normal,
+            }
+        };
+        // The direction from the axis or center to the point, or any direction if the point's on it.
+        let outward = |direction: Vector3<f32>| {
+            if direction.magnitude2() > f32::EPSILON {
+                direction.normalize()
+            } else {
+                Vector3::unit_x()
+            }
+        };
+        match self.shape {
+            ContainerShape::Box => ([face(0), face(1), face(2)], 3),
+            ContainerShape::Cylinder => {
+                let outward = outward(Vector3::new(point.x, 0.0, point.z));
+                let wall = Wall {
+                    point: outward * self.half_extents.x,
+                    normal: -outward,
+                };
+                ([wall, face(1), wall], 2)
+            }
+            ContainerShape::Sphere => {
+                let outward = outward(point);
+                let wall = Wall {
+                    point: outward * self.half_extents.x,
+                    normal: -outward,
+                };
+                ([wall; 3], 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::assert_relative_eq;
+
+    #[test]
+    fn walls_of_each_shape() {
+        let half_extents = Vector3::new(1.0, 2.0, 3.0);
+        let point = Vector3::new(0.5, 1.8, 0.0);
+        let r#box = Container::new(ContainerShape::Box, half_extents);
+        assert!(r#box.contains(point));
+        assert_eq!(-Vector3::unit_y(), r#box.nearest_wall(point).normal);
+        assert_eq!(2, r#box.walls_within(point, 0.6).count());
+
+        let cylinder = Container::new(ContainerShape::Cylinder, half_extents);
+        assert_eq!(1.0, cylinder.get_half_extents().z);
+        assert!(!cylinder.contains(Vector3::new(0.8, 0.0, 0.8)));
+        let outside = Vector3::new(0.0, 0.0, 1.5);
+        assert_relative_eq!(-0.5, cylinder.nearest_wall(outside).distance_to(outside));
+
+        let sphere = Container::new(ContainerShape::Sphere, half_extents);
+        let clamped = sphere.clamp_inside(Vector3::new(3.0, 0.0, 4.0), 0.1);
+        assert_relative_eq!(0.9, clamped.magnitude(), epsilon = 1e-6);
+        assert_relative_eq!(Vector3::new(0.54, 0.0, 0.72), clamped, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn approach_grows_into_new_shapes_before_shrinking() {
+        let mut container = Container::new(ContainerShape::Box, Vector3::new(1.0, 1.0, 1.0));
+        let corner = Vector3::new(0.999, 0.999, 0.999);
+        assert!(container.enclosing(ContainerShape::Sphere).contains(corner));
+        assert!(container
+            .enclosing(ContainerShape::Cylinder)
+            .contains(corner));
+        let target = Container::new(ContainerShape::Sphere, Vector3::new(0.5, 0.0, 0.0));
+        let inward = container.approach(&target, 0.1);
+        assert_eq!(ContainerShape::Sphere, container.get_shape());
+        // The sphere starts around the box, so that nothing's suddenly outside it, and then shrinks.
+        assert_relative_eq!(3.0_f32.sqrt() - 0.1, container.get_half_extents().x);
+        assert_relative_eq!(0.1, inward);
+        for _ in 0..20 {
+            container.approach(&target, 0.1);
+        }
+        assert_eq!(target, container);
+        assert_eq!(0.0, container.approach(&target, 0.1));
+    }
+
+    #[test]
+    fn pushes_particles_inward() {
+        let container = Container::new(ContainerShape::Box, Vector3::new(1.0, 1.0, 1.0));
+        let mut position = Vector3::new(1.05, 0.0, 0.0);
+        let mut velocity = Vector3::new(2.0, 1.0, 0.0);
+        container.push_inside(&mut position, &mut velocity, 0.5, 0.01);
+        assert_relative_eq!(Vector3::new(0.99, 0.0, 0.0), position);
+        // The particle moves inward with the wall, keeping its tangential velocity.
+        assert_relative_eq!(Vector3::new(-0.5, 1.0, 0.0), velocity);
+    }
+}
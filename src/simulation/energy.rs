@@ -0,0 +1,91 @@
+/// The energy of a simulation, broken down by kind, so that how well an integrator conserves it can be checked, and
+/// its drift plotted over a run. Potential energies are relative to the origin, so only their changes are meaningful.
+use std::iter::Sum;
+use std::ops::Add;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct EnergyBreakdown {
+    /// The energy of the bodies' translation and rotation.
+    pub kinetic: f32,
+    /// The potential energy of the bodies' heights in gravity.
+    pub gravitational: f32,
+    /// The potential energy stored in stretched and compressed springs.
+    pub elastic: f32,
+}
+
+impl EnergyBreakdown {
+    pub fn potential(&self) -> f32 {
+        self.gravitational + self.elastic
+    }
+
+    pub fn total(&self) -> f32 {
+        self.kinetic + self.potential()
+    }
+
+    /// The change in the total energy since the initial energy, as a fraction of the initial energy's magnitude,
+    /// or the change itself if there was no initial energy.
+    pub fn drift(&self, initial: &EnergyBreakdown) -> f32 {
+        let change = self.total() - initial.total();
+        if initial.total() == 0.0 {
+            change
+        } else {
+            change / initial.total().abs()
+        }
+    }
+}
+
+impl Add for EnergyBreakdown {
+    type Output = EnergyBreakdown;
+
+    fn add(self, other: EnergyBreakdown) -> EnergyBreakdown {
+        EnergyBreakdown {
+            kinetic: self.kinetic + other.kinetic,
+            gravitational: self.gravitational + other.gravitational,
+            elastic: self.elastic + other.elastic,
+        }
+    }
+}
+
+impl Sum for EnergyBreakdown {
+    fn sum<I: Iterator<Item = EnergyBreakdown>>(iter: I) -> EnergyBreakdown {
+        iter.fold(EnergyBreakdown::default(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_and_drifts() {
+        let energy: EnergyBreakdown = [
+            EnergyBreakdown {
+                kinetic: 1.0,
+                gravitational: -4.0,
+                elastic: 0.5,
+            },
+            EnergyBreakdown {
+                kinetic: 2.0,
+                gravitational: 0.0,
+                elastic: 0.5,
+            },
+        ]
+        .into_iter()
+        .sum();
+        assert_eq!(-3.0, energy.potential());
+        assert_eq!(0.0, energy.total());
+        let later = EnergyBreakdown {
+            kinetic: 0.5,
+            ..energy
+        };
+        // There's no initial energy to drift as a fraction of.
+        assert_eq!(-2.5, later.drift(&energy));
+        assert_eq!(
+            -1.0,
+            energy.drift(&EnergyBreakdown {
+                kinetic: 6.0,
+                ..energy
+            })
+        );
+    }
+}
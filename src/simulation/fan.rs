@@ -0,0 +1,211 @@
+/// A fan whose spinning blades blow a jet of wind along its axis. The blades are a kinematic obstacle
+/// spun by an animation, and the wind is driven by how fast they turn, spreading and weakening with
+/// distance from the fan like a free jet.
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation, Rotation3, Vector3, Zero};
+
+use super::animation::{Animation, Keyframe, KinematicObstacle, Looping, Transform};
+use super::collidable_mesh::CollidableMesh;
+use crate::simulation::geometry;
+
+/// The fraction of the blade tips' speed that the air leaves the fan at.
+const PITCH_RATIO: f32 = 0.5;
+/// How much wider the jet gets for each meter it travels from the fan.
+const SPREAD: f32 = 0.2;
+
+/// Where a fan is and how it is aimed, by its yaw about the Y axis and pitch up from the horizontal.
+/// With no yaw or pitch, it blows along Z.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FanConfig {
+    pub position: Vector3<f32>,
+    /// Radians.
+    pub yaw: f32,
+    /// Radians.
+    pub pitch: f32,
+    /// The angular speed of the blades, in radians per second.
+    pub blade_speed: f32,
+    /// The length of each blade.
+    pub radius: f32,
+}
+
+impl FanConfig {
+    fn get_rotation(&self) -> Quaternion<f32> {
+        Quaternion::from_angle_y(Rad(self.yaw)) * Quaternion::from_angle_x(Rad(-self.pitch))
+    }
+
+    /// The unit vector the fan blows along.
+    pub fn get_direction(&self) -> Vector3<f32> {
+        self.get_rotation().rotate_vector(Vector3::unit_z())
+    }
+}
+
+pub struct Fan {
+    config: FanConfig,
+    blades: KinematicObstacle,
+    /// The angular speed of the blades over the last step, in radians per second.
+    angular_speed: f32,
+}
+
+impl Fan {
+    pub fn new(config: FanConfig) -> Fan {
+        let rotation = config.get_rotation();
+        Fan::with_blade_rotation(config, rotation)
+    }
+
+    fn with_blade_rotation(config: FanConfig, rotation: Quaternion<f32>) -> Fan {
+        let transform = Transform {
+            position: config.position,
+            rotation,
+        };
+        let animation = if config.blade_speed > 0.0 {
+            Animation::spin(
+                transform,
+                config.get_direction(),
+                2.0 * std::f32::consts::PI / config.blade_speed,
+            )
+        } else {
+            let keyframe = Keyframe {
+                time: 0.0,
+                position: transform.position,
+                rotation: transform.rotation,
+            };
+            Animation::new(vec![keyframe], Looping::Once)
+                .expect("A keyframe is enough to hold still.")
+        };
+        Fan {
+            config,
+            blades: KinematicObstacle::new(Fan::blade_mesh(config.radius), animation),
+            angular_speed: 0.0,
+        }
+    }
+
+    /// Moves the fan to the config. The blades keep turning from where they are, unless the fan is re-aimed.
+    pub fn set_config(&mut self, config: FanConfig) {
+        if config == self.config {
+            return;
+        }
+        let rotation = if config.get_rotation() == self.config.get_rotation() {
+            self.blades.get_transform().rotation
+        } else {
+            config.get_rotation()
+        };
+        *self = Fan::with_blade_rotation(config, rotation);
+    }
+
+    pub fn get_config(&self) -> &FanConfig {
+        &self.config
+    }
+
+    pub fn get_blades(&self) -> &KinematicObstacle {
+        &self.blades
+    }
+
+    /// Turns the blades by dt seconds.
+    pub fn advance(&mut self, dt: f32) {
+        self.blades.advance(dt);
+        let turn = self.blades.get_transform().rotation
+            * self.blades.get_previous_transform().rotation.invert();
+        // q and -q are the same rotation; take the shorter way around.
+        self.angular_speed = 2.0 * turn.v.magnitude().atan2(turn.s.abs()) / dt;
+    }
+
+    /// The velocity of the wind the fan blows at the point, which is zero behind the fan.
+    pub fn get_wind(&self, point: Vector3<f32>) -> Vector3<f32> {
+        let direction = self.config.get_direction();
+        let offset = point - self.config.position;
+        let distance = offset.dot(direction);
+        if distance < 0.0 {
+            return Vector3::zero();
+        }
+        let radial_distance = (offset - direction * distance).magnitude();
+        // The jet widens as it travels, keeping its momentum, so it slows with the square of its width.
+        let width = self.config.radius + SPREAD * distance;
+        let exit_speed = PITCH_RATIO * self.angular_speed * self.config.radius;
+        let speed = exit_speed
+            * (self.config.radius / width).powi(2)
+            * (-(radial_distance / width).powi(2)).exp();
+        direction * speed
+    }
+
+    /// Two crossed blades of the radius, in the fan's local XY plane, which they turn in.
+    pub fn blade_mesh(radius: f32) -> CollidableMesh {
+        let (cube_positions, cube_indices) = geometry::get_cube_vertices();
+        let mut vertex_positions = Vec::new();
+        let mut indices = Vec::new();
+        for dimensions in [
+            Vector3::new(2.0, 0.2, 0.04) * radius,
+            Vector3::new(0.2, 2.0, 0.04) * radius,
+        ] {
+            let offset = vertex_positions.len();
+            vertex_positions.extend(
+                cube_positions.iter().map(|v| {
+                    Vector3::new(v.x * dimensions.x, v.y * dimensions.y, v.z * dimensions.z)
+                }),
+            );
+            indices.extend(cube_indices.iter().map(|i| i + offset));
+        }
+        CollidableMesh::new(vertex_positions, indices)
+    }
+}
+
+/// Keeps the fan matching its config, making or removing it as the config is set or cleared.
+pub fn sync(fan: &mut Option<Fan>, config: Option<FanConfig>) {
+    match (fan.as_mut(), config) {
+        (Some(fan), Some(config)) => fan.set_config(config),
+        (None, Some(config)) => *fan = Some(Fan::new(config)),
+        (_, None) => *fan = None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spun_fan(blade_speed: f32) -> Fan {
+        let mut fan = Fan::new(FanConfig {
+            position: Vector3::zero(),
+            yaw: std::f32::consts::FRAC_PI_2,
+            pitch: 0.0,
+            blade_speed,
+            radius: 0.5,
+        });
+        for _ in 0..100 {
+            fan.advance(0.001);
+        }
+        fan
+    }
+
+    #[test]
+    fn wind_weakens_away_from_fan() {
+        let fan = spun_fan(10.0);
+        // Yawed a quarter turn, the fan blows along X.
+        let near = fan.get_wind(Vector3::new(1.0, 0.0, 0.0));
+        let far = fan.get_wind(Vector3::new(3.0, 0.0, 0.0));
+        let aside = fan.get_wind(Vector3::new(1.0, 1.0, 0.0));
+        assert!(near.x > far.x && far.x > 0.0);
+        assert!(near.x > aside.x);
+        assert!(near.y.abs() < 1e-5 && near.z.abs() < 1e-5);
+        assert_eq!(fan.get_wind(Vector3::new(-1.0, 0.0, 0.0)), Vector3::zero());
+    }
+
+    #[test]
+    fn wind_follows_blade_speed() {
+        let point = Vector3::new(1.0, 0.0, 0.0);
+        assert_eq!(spun_fan(0.0).get_wind(point), Vector3::zero());
+        let slow = spun_fan(5.0).get_wind(point).x;
+        let fast = spun_fan(10.0).get_wind(point).x;
+        assert!((fast / slow - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn speeding_up_keeps_blades_turned() {
+        let mut fan = spun_fan(10.0);
+        let rotation = fan.get_blades().get_transform().rotation;
+        fan.set_config(FanConfig {
+            blade_speed: 20.0,
+            ..*fan.get_config()
+        });
+        assert!(fan.get_blades().get_transform().rotation.dot(rotation) > 0.9999);
+        fan.advance(0.001);
+        assert!(fan.get_wind(Vector3::new(1.0, 0.0, 0.0)).x > 0.0);
+    }
+}
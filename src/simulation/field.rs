@@ -0,0 +1,83 @@
+/// Static electric and magnetic fields, and the Lorentz force they exert on charged particles.
+/// Fields are a uniform part plus the field of a point dipole, e.g. a bar magnet or a pair of opposite charges.
+/// Both are in simulation units, with the constant of the dipole field (μ0/4π or 1/4πε0) taken to be 1.
+use cgmath::{InnerSpace, Vector3, Zero};
+
+/// Dipole fields are evaluated no nearer to the dipole than this, so that they stay finite at it.
+const DIPOLE_CORE_RADIUS: f32 = 0.05;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Field {
+    pub uniform: Vector3<f32>,
+    pub dipole_position: Vector3<f32>,
+    /// The dipole's moment; zero for no dipole.
+    pub dipole_moment: Vector3<f32>,
+}
+
+impl Field {
+    pub fn uniform(uniform: Vector3<f32>) -> Field {
+        Field {
+            uniform,
+            dipole_position: Vector3::zero(),
+            dipole_moment: Vector3::zero(),
+        }
+    }
+
+    pub fn dipole(position: Vector3<f32>, moment: Vector3<f32>) -> Field {
+        Field {
+            uniform: Vector3::zero(),
+            dipole_position: position,
+            dipole_moment: moment,
+        }
+    }
+
+    /// The field at the point; the dipole's part is (3(m·r̂)r̂ - m)/r³.
+    pub fn at(&self, point: Vector3<f32>) -> Vector3<f32> {
+        if self.dipole_moment == Vector3::zero() {
+            return self.uniform;
+        }
+        let offset = point - self.dipole_position;
+        let distance = offset.magnitude().max(DIPOLE_CORE_RADIUS);
+        let direction = if offset.magnitude2() > 0.0 {
+            offset.normalize()
+        } else {
+            self.dipole_moment.normalize()
+        };
+        let moment = self.dipole_moment;
+        self.uniform + (direction * 3.0 * moment.dot(direction) - moment) / distance.powi(3)
+    }
+}
+
+/// The force q(E + v × B) on a charge moving with the velocity through the electric and magnetic fields.
+pub fn lorentz_force(
+    charge: f32,
+    velocity: Vector3<f32>,
+    electric: Vector3<f32>,
+    magnetic: Vector3<f32>,
+) -> Vector3<f32> {
+    (electric + velocity.cross(magnetic)) * charge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dipole_field_follows_field_lines() {
+        let field = Field::dipole(Vector3::zero(), Vector3::unit_y());
+        // Along the axis the field points with the moment, and around the equator against it.
+        let axis = field.at(Vector3::new(0.0, 2.0, 0.0));
+        let equator = field.at(Vector3::new(2.0, 0.0, 0.0));
+        assert!((axis - Vector3::new(0.0, 0.25, 0.0)).magnitude() < 1e-6);
+        assert!((equator - Vector3::new(0.0, -0.125, 0.0)).magnitude() < 1e-6);
+        assert!(field.at(Vector3::zero()).magnitude().is_finite());
+    }
+
+    #[test]
+    fn magnetic_force_is_perpendicular_to_velocity() {
+        let velocity = Vector3::new(1.0, 2.0, 0.0);
+        let force = lorentz_force(-2.0, velocity, Vector3::zero(), Vector3::unit_z());
+        assert!(force.dot(velocity).abs() < 1e-6);
+        assert_eq!(force, Vector3::new(-4.0, 2.0, 0.0));
+    }
+}
@@ -0,0 +1,459 @@
+/// Fitting simulation parameters to reference trajectories, e.g. the path of a real ball tracked in footage,
+/// so that the simulations can be calibrated against the real world. A trajectory is simulated for each
+/// guess of the parameters, and the Nelder-Mead method searches for the guess whose trajectory has the
+/// least error against the reference. Nelder-Mead needs no derivatives, which suits simulations whose
+/// collisions make their trajectories discontinuous in their parameters.
+use std::cell::Cell;
+
+use cgmath::{InnerSpace, Vector3};
+
+/// Fitting stops once the errors across the simplex are within this, in meters.
+const FIT_TOLERANCE: f32 = 1e-5;
+const FIT_MAX_ITERATIONS: usize = 200;
+const FIT_MAX_RESTARTS: usize = 10;
+/// The grid the search starts from has this many points along each parameter, including its bounds.
+const FIT_GRID_SIZE: usize = 5;
+
+/// Positions sampled over time, in seconds, at increasing times.
+#[derive(Debug, Clone, Default)]
+pub struct Trajectory {
+    pub times: Vec<f32>,
+    pub positions: Vec<Vector3<f32>>,
+}
+
+impl Trajectory {
+    /// Parses a CSV of a time and x, y, and z positions on each line. Blank lines, lines starting with #,
+    /// and a header on the first line are skipped.
+    pub fn from_csv(text: &str) -> Result<Trajectory, &'static str> {
+        let mut times = Vec::new();
+        let mut positions = Vec::new();
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for (index, line) in lines.enumerate() {
+            let values = line
+                .split(',')
+                .map(|value| value.trim().parse::<f32>())
+                .collect::<Result<Vec<_>, _>>();
+            let values = match values {
+                Ok(values) => values,
+                Err(_) if index == 0 => continue,
+                Err(_) => return Err("Trajectory values must be numbers!"),
+            };
+            if values.iter().any(|value| !value.is_finite()) {
+                return Err("Trajectory values must be finite!");
+            }
+            if values.len() != 4 {
+                return Err(
+                    "Each line of a trajectory must have a time and x, y, and z positions!",
+                );
+            }
+            if times.last().is_some_and(|last| values[0] <= *last) {
+                return Err("A trajectory's times must increase!");
+            }
+            times.push(values[0]);
+            positions.push(Vector3::new(values[1], values[2], values[3]));
+        }
+        if times.len() < 2 {
+            return Err("A trajectory needs at least two samples!");
+        }
+        Ok(Trajectory { times, positions })
+    }
+
+    /// The velocity at the first sample, to start simulations moving as the reference does.
+    /// Differentiates the parabola through the first three samples, which is exact under a constant
+    /// acceleration such as gravity, or else the line through the first two.
+    pub fn initial_velocity(&self) -> Vector3<f32> {
+        let p = &self.positions;
+        let h1 = self.times[1] - self.times[0];
+        if p.len() < 3 {
+            return (p[1] - p[0]) / h1;
+        }
+        let h2 = self.times[2] - self.times[0];
+        -p[0] * (h1 + h2) / (h1 * h2) + p[1] * h2 / (h1 * (h2 - h1)) - p[2] * h1 / (h2 * (h2 - h1))
+    }
+
+    /// Samples a simulation at the trajectory's times, starting from its first. The step advances
+    /// the simulation, returning the time it advanced by and the position after it. Each sample is the
+    /// position after the first step that reaches the sample's time.
+    /// Returns an error if a step doesn't advance by a positive, finite time, which would never reach the next sample.
+    pub fn sample(
+        &self,
+        mut step: impl FnMut() -> (f32, Vector3<f32>),
+    ) -> Result<Vec<Vector3<f32>>, &'static str> {
+        let mut positions = vec![self.positions[0]];
+        let mut time = self.times[0];
+        let mut position = self.positions[0];
+        for sample_time in self.times.iter().skip(1) {
+            while time < *sample_time {
+                let (elapsed, new_position) = step();
+                if elapsed <= 0.0 || !elapsed.is_finite() {
+                    return Err("Simulation steps must advance time by a positive, finite amount!");
+                }
+                time += elapsed;
+                position = new_position;
+            }
+            positions.push(position);
+        }
+        Ok(positions)
+    }
+
+    /// The position at the time, interpolated linearly between the samples either side of it, or the first or
+    /// last sample's if it's outside them. None if there are no samples.
+    pub fn position_at(&self, time: f32) -> Option<Vector3<f32>> {
+        let after = self
+            .times
+            .partition_point(|sample_time| *sample_time <= time);
+        if after == 0 || after == self.times.len() {
+            return self.positions.get(after.saturating_sub(1)).copied();
+        }
+        let (t0, t1) = (self.times[after - 1], self.times[after]);
+        let (p0, p1) = (self.positions[after - 1], self.positions[after]);
+        Some(p0 + (p1 - p0) * ((time - t0) / (t1 - t0)))
+    }
+
+    /// The root mean square distance between the simulated positions and the trajectory's.
+    pub fn rms_error(&self, simulated: &[Vector3<f32>]) -> f32 {
+        let squared_error: f32 = self
+            .positions
+            .iter()
+            .zip(simulated.iter())
+            .map(|(reference, simulated)| (reference - simulated).magnitude2())
+            .sum();
+        (squared_error / self.positions.len() as f32).sqrt()
+    }
+}
+
+/// A parameter to fit, which is kept within its range.
+#[derive(Debug, Copy, Clone)]
+pub struct FitParameter {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// The fitted parameters, in the order they were given, and the RMS error of their trajectory.
+#[derive(Debug, Clone)]
+pub struct Fit {
+    pub values: Vec<f32>,
+    pub error: f32,
+}
+
+/// Fits the parameters to the reference, simulating the trajectory for each guess at them.
+/// Returns the first error simulating a guess, if any.
+pub fn fit(
+    parameters: &[FitParameter],
+    reference: &Trajectory,
+    mut simulate: impl FnMut(&[f32]) -> Result<Vec<Vector3<f32>>, &'static str>,
+) -> Result<Fit, &'static str> {
+    let clamp = |values: &[f32]| {
+        values
+            .iter()
+            .zip(parameters.iter())
+            .map(|(value, parameter)| value.clamp(parameter.min, parameter.max))
+            .collect::<Vec<_>>()
+    };
+    // The first simplex spans a tenth of each parameter's range.
+    let steps = parameters
+        .iter()
+        .map(|parameter| (parameter.max - parameter.min) * 0.1)
+        .collect::<Vec<_>>();
+    // Guesses out of range are simulated at the nearest values in range, and penalized by how far out they are,
+    // so that the search isn't stalled by the error being flat out of range.
+    let penalty = |values: &[f32], clamped: &[f32]| {
+        values
+            .iter()
+            .zip(clamped.iter())
+            .zip(parameters.iter())
+            .map(|((value, clamped), parameter)| {
+                (value - clamped).abs() / (parameter.max - parameter.min)
+            })
+            .sum::<f32>()
+    };
+    // The search can't stop partway, so once a guess fails to simulate, the rest are skipped as infinitely bad.
+    let failure = Cell::new(None);
+    let mut objective = |values: &[f32]| {
+        if failure.get().is_some() {
+            return f32::INFINITY;
+        }
+        let clamped = clamp(values);
+        match simulate(&clamped) {
+            Ok(simulated) => reference.rms_error(&simulated) + penalty(values, &clamped),
+            Err(message) => {
+                failure.set(Some(message));
+                f32::INFINITY
+            }
+        }
+    };
+    // Collisions give the error plateaus, e.g. wherever friction stops a sliding ball at once, which stall
+    // the search, so it starts from the best of a coarse grid over the ranges.
+    let (mut values, mut error) = (0..FIT_GRID_SIZE.pow(parameters.len() as u32))
+        .map(|cell| {
+            let values = parameters
+                .iter()
+                .enumerate()
+                .map(|(axis, parameter)| {
+                    let step = cell / FIT_GRID_SIZE.pow(axis as u32) % FIT_GRID_SIZE;
+                    parameter.min
+                        + (parameter.max - parameter.min) * step as f32 / (FIT_GRID_SIZE - 1) as f32
+                })
+                .collect::<Vec<_>>();
+            let error = objective(&values);
+            (values, error)
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+    if let Some(message) = failure.get() {
+        return Err(message);
+    }
+    // The simplex can collapse before reaching the minimum, so the search is restarted from its best point
+    // until restarting stops improving it.
+    for _ in 0..FIT_MAX_RESTARTS {
+        let (restarted_values, restarted_error) = nelder_mead(
+            &mut objective,
+            &values,
+            &steps,
+            FIT_TOLERANCE,
+            FIT_MAX_ITERATIONS,
+        );
+        let improvement = error - restarted_error;
+        if restarted_error < error {
+            (values, error) = (restarted_values, restarted_error);
+        }
+        if improvement < FIT_TOLERANCE {
+            break;
+        }
+    }
+    if let Some(message) = failure.get() {
+        return Err(message);
+    }
+    let values = clamp(&values);
+    let error = reference.rms_error(&simulate(&values)?);
+    Ok(Fit { values, error })
+}
+
+/// Minimizes the function with the Nelder-Mead method, starting from a simplex of the initial point and
+/// the initial point moved by the step along each axis. Stops once the values at the simplex's points
+/// are within the tolerance of each other, or after the maximum iterations.
+/// Returns the best point found and the function's value there.
+pub fn nelder_mead(
+    mut f: impl FnMut(&[f32]) -> f32,
+    initial: &[f32],
+    steps: &[f32],
+    tolerance: f32,
+    max_iterations: usize,
+) -> (Vec<f32>, f32) {
+    // The standard coefficients of reflection, expansion, contraction, and shrinking.
+    const REFLECTION: f32 = 1.0;
+    const EXPANSION: f32 = 2.0;
+    const CONTRACTION: f32 = 0.5;
+    const SHRINK: f32 = 0.5;
+
+    let dimensions = initial.len();
+    let mut simplex = vec![initial.to_vec()];
+    for (axis, step) in steps.iter().enumerate() {
+        let mut point = initial.to_vec();
+        point[axis] += step;
+        simplex.push(point);
+    }
+    let mut values = simplex.iter().map(|point| f(point)).collect::<Vec<_>>();
+    // The point along the line from the centroid through the point, as a multiple of the distance between them.
+    let along = |centroid: &[f32], point: &[f32], coefficient: f32| {
+        centroid
+            .iter()
+            .zip(point.iter())
+            .map(|(c, p)| c + coefficient * (p - c))
+            .collect::<Vec<_>>()
+    };
+
+    for _ in 0..max_iterations {
+        let mut order = (0..=dimensions).collect::<Vec<_>>();
+        order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+        simplex = order.iter().map(|i| simplex[*i].clone()).collect();
+        values = order.iter().map(|i| values[*i]).collect();
+        if values[dimensions] - values[0] < tolerance {
+            break;
+        }
+
+        let centroid = (0..dimensions)
+            .map(|axis| {
+                simplex[..dimensions]
+                    .iter()
+                    .map(|point| point[axis])
+                    .sum::<f32>()
+                    / dimensions as f32
+            })
+            .collect::<Vec<_>>();
+        let worst = simplex[dimensions].clone();
+
+        let reflected = along(&centroid, &worst, -REFLECTION);
+        let reflected_value = f(&reflected);
+        if reflected_value < values[0] {
+            let expanded = along(&centroid, &worst, -EXPANSION);
+            let expanded_value = f(&expanded);
+            if expanded_value < reflected_value {
+                simplex[dimensions] = expanded;
+                values[dimensions] = expanded_value;
+            } else {
+                simplex[dimensions] = reflected;
+                values[dimensions] = reflected_value;
+            }
+        } else if reflected_value < values[dimensions - 1] {
+            simplex[dimensions] = reflected;
+            values[dimensions] = reflected_value;
+        } else {
+            let contracted = along(&centroid, &worst, CONTRACTION);
+            let contracted_value = f(&contracted);
+            if contracted_value < values[dimensions] {
+                simplex[dimensions] = contracted;
+                values[dimensions] = contracted_value;
+            } else {
+                let best = simplex[0].clone();
+                for (point, value) in simplex.iter_mut().zip(values.iter_mut()).skip(1) {
+                    *point = along(&best, point, SHRINK);
+                    *value = f(point);
+                }
+            }
+        }
+    }
+
+    let best = (0..=dimensions)
+        .min_by(|a, b| values[*a].total_cmp(&values[*b]))
+        .unwrap();
+    (simplex[best].clone(), values[best])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nelder_mead_finds_minimum() {
+        // Rosenbrock's banana function, with its minimum at (1, 1) at the end of a long curved valley.
+        let rosenbrock = |p: &[f32]| (1.0 - p[0]).powi(2) + 100.0 * (p[1] - p[0] * p[0]).powi(2);
+        let (point, value) = nelder_mead(rosenbrock, &[-1.0, 1.0], &[0.5, 0.5], 1e-10, 2000);
+        assert!(value < 1e-4, "{}", value);
+        assert!((point[0] - 1.0).abs() < 0.05 && (point[1] - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn parses_csv() {
+        let trajectory =
+            Trajectory::from_csv("t,x,y,z\n0, 0, 1, 0\n\n# Tracked\n0.5, 1, 0, 0\n").unwrap();
+        assert_eq!(trajectory.times, vec![0.0, 0.5]);
+        assert_eq!(trajectory.initial_velocity(), Vector3::new(2.0, -2.0, 0.0));
+        assert!(Trajectory::from_csv("0,0,0,0\n0,1,1,1").is_err());
+        assert!(Trajectory::from_csv("0,0,0,0\n1,1,1").is_err());
+        assert!(Trajectory::from_csv("0,0,0,0\n1,a,1,1").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        assert_eq!(
+            Trajectory::from_csv("0,0,0,0\n1,1,1,1\n0.5,2,2,2").unwrap_err(),
+            "A trajectory's times must increase!"
+        );
+        // NaN and infinity parse as numbers, but NaN compares as neither before nor after the times either side.
+        for text in [
+            "0,0,0,0\nNaN,1,1,1\n1,2,2,2",
+            "0,0,0,0\ninf,1,1,1",
+            "-inf,0,0,0\n1,1,1,1",
+            "0,0,0,0\n1,1,NaN,1",
+        ] {
+            assert_eq!(
+                Trajectory::from_csv(text).unwrap_err(),
+                "Trajectory values must be finite!",
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_steps_which_dont_advance() {
+        let trajectory = Trajectory::from_csv("0,0,0,0\n1,1,1,1").unwrap();
+        for elapsed in [0.0, -0.1, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                trajectory
+                    .sample(|| (elapsed, Vector3::new(0.0, 0.0, 0.0)))
+                    .unwrap_err(),
+                "Simulation steps must advance time by a positive, finite amount!",
+                "{}",
+                elapsed
+            );
+        }
+        let parameters = [FitParameter {
+            name: "Drag",
+            min: 0.0,
+            max: 1.0,
+        }];
+        let fitted = fit(&parameters, &trajectory, |_| {
+            trajectory.sample(|| (0.0, Vector3::new(0.0, 0.0, 0.0)))
+        });
+        assert!(fitted.is_err());
+    }
+
+    #[test]
+    fn interpolates_positions() {
+        let trajectory = Trajectory {
+            times: vec![0.0, 1.0, 3.0],
+            positions: vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(2.0, 0.0, 0.0),
+                Vector3::new(2.0, 4.0, 0.0),
+            ],
+        };
+        assert_eq!(
+            trajectory.position_at(0.5),
+            Some(Vector3::new(1.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            trajectory.position_at(2.0),
+            Some(Vector3::new(2.0, 2.0, 0.0))
+        );
+        assert_eq!(
+            trajectory.position_at(-1.0),
+            Some(Vector3::new(0.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            trajectory.position_at(5.0),
+            Some(Vector3::new(2.0, 4.0, 0.0))
+        );
+        assert_eq!(Trajectory::default().position_at(0.0), None);
+    }
+
+    #[test]
+    fn fits_drag() {
+        // A body falling against linear drag, sampled every tenth of a second.
+        let simulate = |drag: f32, times: &[f32]| {
+            let trajectory = Trajectory {
+                times: times.to_vec(),
+                positions: vec![Vector3::new(0.0, 0.0, 0.0); times.len()],
+            };
+            let (mut position, mut velocity) = (Vector3::new(0.0, 0.0, 0.0), Vector3::unit_x());
+            trajectory.sample(|| {
+                let dt = 0.001;
+                velocity += (Vector3::new(0.0, -9.8, 0.0) - drag * velocity) * dt;
+                position += velocity * dt;
+                (dt, position)
+            })
+        };
+        let times = (0..20).map(|i| i as f32 * 0.1).collect::<Vec<_>>();
+        let reference = Trajectory {
+            positions: simulate(0.7, &times).unwrap(),
+            times,
+        };
+        let parameters = [FitParameter {
+            name: "Drag",
+            min: 0.0,
+            max: 2.0,
+        }];
+        let fit = fit(&parameters, &reference, |values| {
+            simulate(values[0], &reference.times)
+        })
+        .unwrap();
+        assert!((fit.values[0] - 0.7).abs() < 0.01, "{:?}", fit);
+        assert!(fit.error < 1e-3);
+    }
+}
@@ -61,6 +61,8 @@ pub struct FlockingBoid {
     weight: f32,
     /// Mass for gravitational attraction to e.g. a PointAttractor
     mass: f32,
+    /// From 1 when rested to 0 when exhausted, if the flock's stamina is modelled.
+    stamina: f32,
 }
 
 impl Boid for FlockingBoid {
@@ -84,6 +86,7 @@ impl FlockingBoid {
             velocity,
             weight: 1.0,
             mass: 1.0,
+            stamina: 1.0,
         }
     }
 
@@ -91,6 +94,14 @@ impl FlockingBoid {
         self.mass
     }
 
+    pub fn stamina(&self) -> f32 {
+        self.stamina
+    }
+
+    pub fn set_stamina(&mut self, stamina: f32) {
+        self.stamina = stamina;
+    }
+
     pub fn distance(&self, other: &impl Boid) -> f32 {
         (other.position() - self.position).magnitude()
     }
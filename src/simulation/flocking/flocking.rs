@@ -1,17 +1,24 @@
 use super::{
     boid::{Boid, FlockingBoid, LeadBoid},
+    goal::{self, Goals},
+    limits::{self, StaminaConfig},
     obstacle::Obstacle,
 };
-use crate::{
-    graphics::instance::Instance,
-    gui,
-    simulation::{bounding_box::BoundingBox, point_attractor::PointAttractor},
+#[cfg(feature = "render")]
+use crate::graphics::instance::Instance;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::{
+    bounding_box::BoundingBox,
+    planar::PlanarConstraint,
+    point_attractor::PointAttractor,
+    trigger::{Trigger, TriggerAction},
 };
 
 use cgmath::{InnerSpace, Vector3, Zero};
 
 use std::time::Duration;
 
+#[derive(Clone, PartialEq)]
 pub struct Config {
     pub dt: f32, // secs as f32
     pub avoidance_factor: f32,
@@ -31,6 +38,22 @@ pub struct Config {
     /// other sources of acceleration. This can help prevent cases where
     /// a boid will clip through obstacles, but can cause unnatural motion.
     pub steering_overrides: bool,
+    /// How strongly boids steer towards the velocity that carries them to the current goal.
+    pub goal_factor: f32,
+    /// The speed boids seek goals at, in m/s.
+    pub goal_speed: f32,
+    /// The distance from the goal within which boids slow down to arrive at it.
+    pub goal_slowing_radius: f32,
+    /// The distance from the goal the flock's center must come within to reach it.
+    pub goal_reached_radius: f32,
+    /// The fastest boids may fly, in m/s.
+    pub max_speed: f32,
+    /// The hardest boids may steer, i.e. the greatest magnitude of their acceleration, in m/s².
+    pub max_acceleration: f32,
+    /// Tires boids flying faster than they cruise, limiting their speed and acceleration until they rest, if set.
+    pub stamina: Option<StaminaConfig>,
+    /// Holds the boids in a plane, if set.
+    pub planar: Option<PlanarConstraint>,
 }
 
 impl Default for Config {
@@ -46,6 +69,14 @@ impl Default for Config {
             max_sight_angle_to_lead_boid: std::f32::consts::PI,
             time_to_start_steering: Duration::from_secs(4),
             steering_overrides: false,
+            goal_factor: 2.0,
+            goal_speed: 4.0,
+            goal_slowing_radius: 8.0,
+            goal_reached_radius: 2.0,
+            max_speed: 10.0,
+            max_acceleration: 20.0,
+            stamina: None,
+            planar: None,
         }
     }
 }
@@ -57,6 +88,8 @@ pub struct Simulation {
     bounding_box: Option<BoundingBox>,
     obstacles: Option<Vec<Obstacle>>,
     attractors: Option<Vec<PointAttractor>>,
+    triggers: Vec<Trigger>,
+    goals: Goals,
 }
 
 impl Simulation {
@@ -91,36 +124,111 @@ impl Simulation {
             bounding_box,
             obstacles,
             attractors,
+            triggers: Vec::new(),
+            goals: Goals::new(),
         }
     }
 
+    /// Adds a trigger volume, returning its index.
+    /// Boids can't be removed, so despawning triggers only count the boids which enter them.
+    pub fn add_trigger(&mut self, trigger: Trigger) -> usize {
+        self.triggers.push(trigger);
+        self.triggers.len() - 1
+    }
+
+    /// Queues a goal for the flock to seek once it's reached the goals queued before it.
+    pub fn push_goal(&mut self, goal: Vector3<f32>) {
+        self.goals.push(goal);
+    }
+
+    pub fn clear_goals(&mut self) {
+        self.goals.clear();
+    }
+
+    pub fn get_boids(&self) -> &[FlockingBoid] {
+        &self.boids
+    }
+
+    pub fn get_goals(&self) -> &Goals {
+        &self.goals
+    }
+
+    /// The mean position of the flock's boids, if it has any.
+    pub fn get_flock_center(&self) -> Option<Vector3<f32>> {
+        if self.boids.is_empty() {
+            return None;
+        }
+        let sum = self
+            .boids
+            .iter()
+            .fold(Vector3::zero(), |sum, boid| sum + boid.position());
+        Some(sum / self.boids.len() as f32)
+    }
+
     pub fn step(&mut self) -> Duration {
         // TODO we could use a double buffer here instead of allocating a new vector here every step.
         let mut new_state = Vec::with_capacity(self.boids.len());
 
-        for boid in self.boids.iter() {
+        for (index, boid) in self.boids.iter().enumerate() {
             let boid_acceleration = if self.config.steering_overrides {
                 self.get_acceleration_from_steering(boid)
             } else {
                 self.get_acceleration_from_boids(boid)
                     + self.get_acceleration_from_lead_boids(boid)
                     + self.get_acceleration_from_attractors(boid)
+                    + self.get_acceleration_from_goal(boid)
                     + if let Some(bounding_box) = &self.bounding_box {
                         bounding_box.get_repelling_acceleration(boid.position())
                     } else {
                         Vector3::<f32>::zero()
                     }
+                    + self.get_acceleration_from_triggers(index)
                     + self.get_acceleration_from_steering(boid)
             };
 
-            let new_boid_position = boid.position() + self.config.dt * boid.velocity();
-            let new_boid_velocity = boid.velocity() + self.config.dt * boid_acceleration;
+            let capacity = match &self.config.stamina {
+                Some(stamina) => stamina.capacity(boid.stamina()),
+                None => 1.0,
+            };
+            let boid_acceleration =
+                limits::clamp_magnitude(boid_acceleration, self.config.max_acceleration * capacity);
 
-            new_state.push(FlockingBoid::new(new_boid_position, new_boid_velocity));
+            let mut new_boid_position = boid.position() + self.config.dt * boid.velocity();
+            let mut new_boid_velocity = limits::clamp_magnitude(
+                boid.velocity() + self.config.dt * boid_acceleration,
+                self.config.max_speed * capacity,
+            );
+            if let Some(planar) = &self.config.planar {
+                new_boid_position = planar.constrain_position(new_boid_position);
+                new_boid_velocity = planar.constrain_velocity(new_boid_velocity);
+            }
+
+            let mut new_boid = FlockingBoid::new(new_boid_position, new_boid_velocity);
+            if let Some(stamina) = &self.config.stamina {
+                new_boid.set_stamina(stamina.update(
+                    boid.stamina(),
+                    new_boid_velocity.magnitude() / self.config.max_speed.max(f32::EPSILON),
+                    self.config.dt,
+                ));
+            }
+            new_state.push(new_boid);
         }
 
         self.boids = new_state;
 
+        if let Some(center) = self.get_flock_center() {
+            self.goals.update(center, self.config.goal_reached_radius);
+        }
+
+        for trigger in self.triggers.iter_mut() {
+            trigger.update(
+                self.boids
+                    .iter()
+                    .enumerate()
+                    .map(|(index, boid)| (index, boid.position())),
+            );
+        }
+
         if let Some(lead_boids) = &mut self.lead_boids {
             for lead_boid in lead_boids.iter_mut() {
                 lead_boid.step(Duration::from_secs_f32(self.config.dt));
@@ -130,6 +238,20 @@ impl Simulation {
         self.get_timestep()
     }
 
+    /// Wind triggers push the boids inside them directly, since boids have no drag.
+    fn get_acceleration_from_triggers(&self, boid_index: usize) -> Vector3<f32> {
+        self.triggers
+            .iter()
+            .filter(|trigger| trigger.contains(boid_index))
+            .fold(
+                Vector3::<f32>::zero(),
+                |acceleration, trigger| match trigger.get_action() {
+                    TriggerAction::Wind(wind) => acceleration + wind,
+                    _ => acceleration,
+                },
+            )
+    }
+
     fn get_acceleration_from_boids(&self, boid: &FlockingBoid) -> Vector3<f32> {
         // TODO use a functional approach
         let mut total_acceleration = Vector3::<f32>::zero();
@@ -179,6 +301,20 @@ impl Simulation {
         total_accel
     }
 
+    fn get_acceleration_from_goal(&self, boid: &FlockingBoid) -> Vector3<f32> {
+        match self.goals.current() {
+            Some(current_goal) => goal::get_arrival_acceleration(
+                boid.position(),
+                boid.velocity(),
+                current_goal,
+                self.config.goal_factor,
+                self.config.goal_speed,
+                self.config.goal_slowing_radius,
+            ),
+            None => Vector3::<f32>::zero(),
+        }
+    }
+
     fn get_acceleration_from_steering(&self, boid: &FlockingBoid) -> Vector3<f32> {
         if let Some(obstacles) = &self.obstacles {
             // Find the first obstacle we might hit, which is the one we'll steer to avoid.
@@ -212,21 +348,7 @@ impl Simulation {
         Duration::from_secs_f32(self.config.dt)
     }
 
-    pub fn sync_sim_config_from_ui(&mut self, ui: &mut gui::flocking::FlockingUi) {
-        let ui_config_state = ui.get_gui_state_mut();
-        self.config.dt = ui_config_state.dt;
-        self.config.avoidance_factor = ui_config_state.avoidance_factor;
-        self.config.centering_factor = ui_config_state.centering_factor;
-        self.config.velocity_matching_factor = ui_config_state.velocity_matching_factor;
-        self.config.distance_weight_threshold = ui_config_state.distance_weight_threshold;
-        self.config.distance_weight_threshold_falloff =
-            ui_config_state.distance_weight_threshold_falloff;
-        self.config.max_sight_angle = ui_config_state.max_sight_angle;
-        self.config.max_sight_angle_to_lead_boid = ui_config_state.max_sight_angle_to_lead_boid;
-        self.config.time_to_start_steering = ui_config_state.time_to_start_steering;
-        self.config.steering_overrides = ui_config_state.steering_overrides;
-    }
-
+    #[cfg(feature = "render")]
     pub fn get_boid_instances(&self) -> Vec<Instance> {
         let mut instances = Vec::<Instance>::with_capacity(self.boids.len());
 
@@ -238,9 +360,39 @@ impl Simulation {
                     boid.velocity().normalize(),
                     None,
                 ),
-                scale: 0.1,
+                scale: Vector3::new(0.1, 0.1, 0.1),
+                color: None,
             });
         }
         instances
     }
 }
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.dt = config.dt;
+        self.config.avoidance_factor = config.avoidance_factor;
+        self.config.centering_factor = config.centering_factor;
+        self.config.velocity_matching_factor = config.velocity_matching_factor;
+        self.config.distance_weight_threshold = config.distance_weight_threshold;
+        self.config.distance_weight_threshold_falloff = config.distance_weight_threshold_falloff;
+        self.config.max_sight_angle = config.max_sight_angle;
+        self.config.max_sight_angle_to_lead_boid = config.max_sight_angle_to_lead_boid;
+        self.config.time_to_start_steering = config.time_to_start_steering;
+        self.config.steering_overrides = config.steering_overrides;
+        self.config.goal_factor = config.goal_factor;
+        self.config.goal_speed = config.goal_speed;
+        self.config.goal_slowing_radius = config.goal_slowing_radius;
+        self.config.goal_reached_radius = config.goal_reached_radius;
+        self.config.max_speed = config.max_speed;
+        self.config.max_acceleration = config.max_acceleration;
+        self.config.stamina = config.stamina;
+        self.config.planar = config.planar;
+    }
+}
@@ -0,0 +1,140 @@
+/// Goals the flock seeks, queued so that it heads for each in turn, e.g. as placed by clicking.
+/// Boids steer towards the current goal with Reynolds' arrival behavior, slowing as they near it
+/// rather than overshooting, and the goal is reached once the center of the flock is close to it.
+use std::collections::VecDeque;
+
+use cgmath::{InnerSpace, Vector3, Zero};
+
+pub struct Goals {
+    queue: VecDeque<Vector3<f32>>,
+}
+
+impl Goals {
+    pub fn new() -> Goals {
+        Goals {
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Queues the goal after any the flock hasn't reached yet.
+    pub fn push(&mut self, goal: Vector3<f32>) {
+        self.queue.push_back(goal);
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// The goal the flock is seeking, if any.
+    pub fn current(&self) -> Option<Vector3<f32>> {
+        self.queue.front().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// The goals the flock hasn't reached, in the order it'll seek them.
+    pub fn iter(&self) -> impl Iterator<Item = &Vector3<f32>> {
+        self.queue.iter()
+    }
+
+    /// Moves on to the next goal if the flock's center is within the radius of the current one.
+    pub fn update(&mut self, flock_center: Vector3<f32>, reached_radius: f32) {
+        if let Some(goal) = self.current() {
+            if (goal - flock_center).magnitude() <= reached_radius {
+                self.queue.pop_front();
+            }
+        }
+    }
+}
+
+impl Default for Goals {
+    fn default() -> Self {
+        Goals::new()
+    }
+}
+
+/// The acceleration steering a boid at the position with the velocity towards the goal. It steers towards
+/// the speed, slowing in proportion to the distance once it's within the slowing radius of the goal.
+pub fn get_arrival_acceleration(
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    goal: Vector3<f32>,
+    factor: f32,
+    speed: f32,
+    slowing_radius: f32,
+) -> Vector3<f32> {
+    let to_goal = goal - position;
+    let distance = to_goal.magnitude();
+    let desired_velocity = if distance == 0.0 {
+        Vector3::zero()
+    } else {
+        let slowing = if slowing_radius > 0.0 {
+            f32::min(distance / slowing_radius, 1.0)
+        } else {
+            1.0
+        };
+        to_goal / distance * speed * slowing
+    };
+    factor * (desired_velocity - velocity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::configurable::Configurable;
+    use crate::simulation::flocking::flocking::{Config, Simulation};
+
+    #[test]
+    fn arrival_slows_near_the_goal() {
+        let goal = Vector3::new(10.0, 0.0, 0.0);
+        let far = get_arrival_acceleration(Vector3::zero(), Vector3::zero(), goal, 1.0, 4.0, 5.0);
+        assert!((far - Vector3::new(4.0, 0.0, 0.0)).magnitude() < 1e-5);
+        // Halfway into the slowing radius, the boid should be at half speed, and brakes if it's faster.
+        let near = get_arrival_acceleration(
+            Vector3::new(7.5, 0.0, 0.0),
+            Vector3::new(4.0, 0.0, 0.0),
+            goal,
+            1.0,
+            4.0,
+            5.0,
+        );
+        assert!((near - Vector3::new(-2.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert_eq!(
+            get_arrival_acceleration(goal, Vector3::zero(), goal, 1.0, 4.0, 5.0),
+            Vector3::zero()
+        );
+    }
+
+    #[test]
+    fn flock_seeks_queued_goals_in_turn() {
+        let mut simulation = Simulation::new(vec![Vector3::zero()], 10, None, None, None, None);
+        simulation.sync_config(&Config {
+            dt: 0.01,
+            ..Config::default()
+        });
+        let first = Vector3::new(12.0, 0.0, 0.0);
+        let second = Vector3::new(12.0, 0.0, 12.0);
+        simulation.push_goal(first);
+        simulation.push_goal(second);
+        let mut reached_first = false;
+        for _ in 0..6000 {
+            simulation.step();
+            if simulation.get_goals().current() == Some(second) {
+                reached_first = true;
+            }
+            if simulation.get_goals().current().is_none() {
+                break;
+            }
+        }
+        assert!(reached_first);
+        assert!(simulation.get_goals().current().is_none());
+        let center = simulation.get_flock_center().unwrap();
+        assert!((center - second).magnitude() < 5.0, "{:?}", center);
+    }
+}
@@ -0,0 +1,121 @@
+/// Limits on how fast boids fly and how hard they steer, so strong factors can't accelerate them without bound,
+/// and the stamina boids spend flying fast. Tired boids can't fly or steer as hard, so they slow and fall
+/// behind the flock until they've rested.
+use cgmath::{InnerSpace, Vector3};
+
+/// Scales the vector down to the magnitude, if it's longer.
+pub fn clamp_magnitude(vector: Vector3<f32>, max: f32) -> Vector3<f32> {
+    let magnitude = vector.magnitude();
+    if magnitude > max {
+        vector * (max / magnitude)
+    } else {
+        vector
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StaminaConfig {
+    /// The fraction of the max speed boids cruise at, which neither tires nor rests them.
+    pub cruise_fraction: f32,
+    /// The stamina spent per second flying at the max speed, from the full stamina of 1.
+    pub drain_rate: f32,
+    /// The stamina regained per second at rest.
+    pub recovery_rate: f32,
+    /// The fraction of the max speed and acceleration boids are limited to with no stamina left.
+    pub exhausted_fraction: f32,
+}
+
+impl Default for StaminaConfig {
+    fn default() -> Self {
+        Self {
+            cruise_fraction: 0.5,
+            drain_rate: 0.2,
+            recovery_rate: 0.1,
+            exhausted_fraction: 0.3,
+        }
+    }
+}
+
+impl StaminaConfig {
+    /// The stamina after flying for dt seconds at the fraction of the max speed. Flying faster than
+    /// the cruising speed drains stamina, up to the drain rate at the max speed, and flying slower
+    /// restores it, up to the recovery rate at rest.
+    pub fn update(&self, stamina: f32, speed_fraction: f32, dt: f32) -> f32 {
+        let rate = if speed_fraction > self.cruise_fraction {
+            -self.drain_rate * (speed_fraction - self.cruise_fraction)
+                / (1.0 - self.cruise_fraction).max(f32::EPSILON)
+        } else {
+            self.recovery_rate * (self.cruise_fraction - speed_fraction)
+                / self.cruise_fraction.max(f32::EPSILON)
+        };
+        (stamina + rate * dt).clamp(0.0, 1.0)
+    }
+
+    /// The fraction of the max speed and acceleration a boid with the stamina may fly and steer at.
+    pub fn capacity(&self, stamina: f32) -> f32 {
+        self.exhausted_fraction + (1.0 - self.exhausted_fraction) * stamina
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::configurable::Configurable;
+    use crate::simulation::flocking::boid::Boid;
+    use crate::simulation::flocking::flocking::{Config, Simulation};
+    use cgmath::Zero;
+
+    #[test]
+    fn stamina_drains_fast_and_recovers_slow() {
+        let stamina = StaminaConfig::default();
+        assert!((stamina.update(1.0, 1.0, 1.0) - (1.0 - stamina.drain_rate)).abs() < 1e-6);
+        assert!((stamina.update(0.5, 0.0, 1.0) - (0.5 + stamina.recovery_rate)).abs() < 1e-6);
+        assert_eq!(stamina.update(0.5, stamina.cruise_fraction, 1.0), 0.5);
+        assert_eq!(stamina.update(0.05, 1.0, 1.0), 0.0);
+        assert_eq!(stamina.capacity(0.0), stamina.exhausted_fraction);
+        assert_eq!(stamina.capacity(1.0), 1.0);
+    }
+
+    #[test]
+    fn limits_bound_strong_factors() {
+        let config = Config {
+            dt: 0.01,
+            centering_factor: 2.0,
+            velocity_matching_factor: 2.0,
+            goal_factor: 10.0,
+            goal_speed: 100.0,
+            max_speed: 6.0,
+            ..Config::default()
+        };
+        let mut simulation = Simulation::new(vec![Vector3::zero()], 10, None, None, None, None);
+        simulation.sync_config(&config);
+        simulation.push_goal(Vector3::new(1000.0, 0.0, 0.0));
+        for _ in 0..800 {
+            simulation.step();
+        }
+        assert!(simulation
+            .get_boids()
+            .iter()
+            .all(|boid| boid.velocity().magnitude() <= config.max_speed + 1e-3));
+
+        // Flying flat out for as long tires the boids, slowing them.
+        let mut tiring = Simulation::new(vec![Vector3::zero()], 10, None, None, None, None);
+        tiring.sync_config(&Config {
+            stamina: Some(StaminaConfig::default()),
+            ..config
+        });
+        tiring.push_goal(Vector3::new(1000.0, 0.0, 0.0));
+        for _ in 0..800 {
+            tiring.step();
+        }
+        assert!(tiring.get_boids().iter().all(|boid| boid.stamina() < 0.5));
+        let speed = |simulation: &Simulation| {
+            simulation
+                .get_boids()
+                .iter()
+                .map(|boid| boid.velocity().magnitude())
+                .sum::<f32>()
+        };
+        assert!(speed(&tiring) < 0.9 * speed(&simulation));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod boid;
 pub mod flocking;
+pub mod goal;
+pub mod limits;
 pub mod obstacle;
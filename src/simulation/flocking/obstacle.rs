@@ -1,16 +1,27 @@
+use std::rc::Rc;
 use std::time::Duration;
 
 use cgmath::{num_traits::Signed, InnerSpace, Vector3, Zero};
 
+#[cfg(feature = "render")]
 use crate::graphics::entity::Entity;
+use crate::simulation::raycast::Bvh;
 
 use super::boid::{Boid, FlockingBoid};
 
+/// Obstacles created from entities have collision proxies simplified to this many triangles.
+#[cfg(feature = "render")]
+const PROXY_TRIANGLES: usize = 500;
+
 /// An obstacle which FlockingBoids may avoid by steering, handled as a bounding sphere for some mesh.
+/// If the obstacle has a collision proxy of the mesh, boids only steer around the sphere if
+/// they're heading into the proxy, so that they may pass through the sphere where the mesh isn't.
 #[derive(Clone)]
 pub struct Obstacle {
     pub position: Vector3<f32>,
     pub radius: f32,
+    /// Accelerated for the whisker rays boids cast along their velocity.
+    pub proxy: Option<Rc<Bvh>>,
 }
 
 impl Obstacle {
@@ -18,7 +29,7 @@ impl Obstacle {
     /// difference in positions, which includes the obstacle.
     /// If it will never collide with that plane, returns None.
     pub fn get_time_to_plane_collision(&self, boid: &FlockingBoid) -> Option<Duration> {
-        if !self.will_collide_with_plane(boid) {
+        if !self.will_collide_with_plane(boid) || !self.will_hit_proxy(boid) {
             return None;
         }
         let (velocity_i, _) = self.get_velocity_components(boid);
@@ -45,19 +56,37 @@ impl Obstacle {
         }
     }
 
+    /// Creates an obstacle for each of the entity's instances, with a bounding sphere of the radius
+    /// (scaled by the instance) and a collision proxy simplified from the entity's model.
+    #[cfg(feature = "render")]
     pub fn from_entity(entity: &Entity, radius: f32) -> Vec<Obstacle> {
         entity
             .instances()
             .iter()
-            .map(|instance| -> Obstacle {
+            .zip(entity.get_collision_proxies(PROXY_TRIANGLES))
+            .map(|(instance, proxy)| -> Obstacle {
                 Obstacle {
                     position: instance.position,
-                    radius: instance.scale * radius,
+                    radius: instance.max_scale() * radius,
+                    proxy: Some(Rc::new(Bvh::new(proxy.get_faces().clone()))),
                 }
             })
             .collect()
     }
 
+    /// If the boid continues at its current velocity, will it hit the collision proxy?
+    /// True if the obstacle has no proxy.
+    fn will_hit_proxy(&self, boid: &FlockingBoid) -> bool {
+        let Some(proxy) = &self.proxy else {
+            return true;
+        };
+        if boid.velocity().magnitude2() == 0.0 {
+            return false;
+        }
+        let direction = boid.velocity().normalize();
+        proxy.raycast(boid.position(), direction).is_some()
+    }
+
     /// If the boid continues at its current velocity, will it collide with the plane perpendicular
     /// to the vector that is the difference between this obstacle's center and the boid's position?
     fn will_collide_with_plane(&self, boid: &FlockingBoid) -> bool {
@@ -80,3 +109,38 @@ impl Obstacle {
         (velocity_i, velocity_t)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::collidable_mesh::CollidableMesh;
+    use crate::simulation::geometry;
+
+    #[test]
+    fn proxy_narrows_avoidance() {
+        let (positions, indices) = geometry::get_cube_vertices();
+        let obstacle = Obstacle {
+            position: Vector3::zero(),
+            radius: 4.0,
+            proxy: Some(Rc::new(Bvh::new(
+                CollidableMesh::new(positions, indices).get_faces().clone(),
+            ))),
+        };
+        let heading_into_cube = FlockingBoid::new(Vector3::new(-10.0, 0.0, 0.0), Vector3::unit_x());
+        assert!(obstacle
+            .get_time_to_plane_collision(&heading_into_cube)
+            .is_some());
+        // Within the bounding sphere, but clear of the mesh.
+        let passing_cube = FlockingBoid::new(Vector3::new(-10.0, 2.0, 0.0), Vector3::unit_x());
+        assert!(obstacle
+            .get_time_to_plane_collision(&passing_cube)
+            .is_none());
+        let without_proxy = Obstacle {
+            proxy: None,
+            ..obstacle
+        };
+        assert!(without_proxy
+            .get_time_to_plane_collision(&passing_cube)
+            .is_some());
+    }
+}
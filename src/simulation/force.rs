@@ -0,0 +1,216 @@
+/// Force fields which act on any body by its position, velocity, and mass, so that one field can push particles,
+/// the points of springy meshes, and rigidbodies alike. The particle, springy mesh, and rigidbody simulations add
+/// the forces of their generators to the forces they accumulate themselves, in the order they were added, so a
+/// scene's fields can be composed without changing the simulations. Their forces act through the body's center of
+/// mass, so they don't spin rigidbodies.
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use super::particles_cpu::swirl::SwirlConfig;
+use super::point_attractor::PointAttractor;
+
+/// The state of a body which force generators act on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Body {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub mass: f32,
+}
+
+pub trait ForceGenerator {
+    /// The force on the body, given the seconds simulated so far.
+    fn force(&self, body: &Body, time: f32) -> Vector3<f32>;
+}
+
+/// Closures of the body and time are force generators.
+impl<F: Fn(&Body, f32) -> Vector3<f32>> ForceGenerator for F {
+    fn force(&self, body: &Body, time: f32) -> Vector3<f32> {
+        self(body, time)
+    }
+}
+
+/// The sum of the generators' forces on the body.
+pub fn sum(generators: &[Box<dyn ForceGenerator>], body: &Body, time: f32) -> Vector3<f32> {
+    generators.iter().fold(Vector3::zero(), |sum, generator| {
+        sum + generator.force(body, time)
+    })
+}
+
+/// A uniform acceleration, e.g. the gravity of a planet other than the simulation's own.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Gravity {
+    pub acceleration: Vector3<f32>,
+}
+
+impl ForceGenerator for Gravity {
+    fn force(&self, body: &Body, _: f32) -> Vector3<f32> {
+        self.acceleration * body.mass
+    }
+}
+
+/// Air moving at a uniform velocity, which drags bodies toward its velocity by the square of their speed through it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Wind {
+    pub velocity: Vector3<f32>,
+    /// The force per square of the relative speed, in kg/m.
+    pub drag: f32,
+}
+
+impl ForceGenerator for Wind {
+    fn force(&self, body: &Body, _: f32) -> Vector3<f32> {
+        let relative = self.velocity - body.velocity;
+        self.drag * relative * relative.magnitude()
+    }
+}
+
+/// Still air, which drags against bodies' velocities by the square of their speed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Drag {
+    /// The force per square of the speed, in kg/m.
+    pub coefficient: f32,
+}
+
+impl ForceGenerator for Drag {
+    fn force(&self, body: &Body, time: f32) -> Vector3<f32> {
+        Wind {
+            velocity: Vector3::zero(),
+            drag: self.coefficient,
+        }
+        .force(body, time)
+    }
+}
+
+/// Point attractors pull bodies toward them, or push them away with a negative mass. Bodies at the attractor
+/// are left be, as the direction to it is undefined.
+impl ForceGenerator for PointAttractor {
+    fn force(&self, body: &Body, _: f32) -> Vector3<f32> {
+        if body.position == self.position {
+            return Vector3::zero();
+        }
+        self.get_acceleration(body.position, body.mass) * body.mass
+    }
+}
+
+/// Swirls are vortices, circling bodies about their axis.
+impl ForceGenerator for SwirlConfig {
+    fn force(&self, body: &Body, _: f32) -> Vector3<f32> {
+        self.acceleration(body.position, body.velocity) * body.mass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::configurable::Configurable;
+    use crate::simulation::geometry;
+    use crate::simulation::quantity;
+    use crate::simulation::rigidbody::{rigidbody::RigidBody, simulation::Simulation as Rigid};
+    use crate::simulation::springy::{
+        config::Config as SpringyConfig, simulation::Simulation as Springy,
+        springy_mesh::SpringyMesh,
+    };
+
+    use cgmath::assert_relative_eq;
+
+    #[test]
+    fn generators_compose() {
+        let body = Body {
+            position: Vector3::new(1.0, 0.0, 0.0),
+            velocity: Vector3::new(0.0, 0.0, 2.0),
+            mass: 2.0,
+        };
+        let generators: Vec<Box<dyn ForceGenerator>> = vec![
+            Box::new(Gravity {
+                acceleration: Vector3::new(0.0, -3.0, 0.0),
+            }),
+            Box::new(Drag { coefficient: 0.5 }),
+            // A wind blowing with the body has no effect.
+            Box::new(Wind {
+                velocity: body.velocity,
+                drag: 10.0,
+            }),
+            Box::new(|_: &Body, time: f32| Vector3::unit_x() * time),
+        ];
+        assert_relative_eq!(Vector3::new(4.0, -6.0, -2.0), sum(&generators, &body, 4.0));
+    }
+
+    #[test]
+    fn attractors_and_vortices_act_by_mass() {
+        let body = |mass: f32| Body {
+            position: Vector3::new(2.0, 0.0, 0.0),
+            velocity: Vector3::zero(),
+            mass,
+        };
+        let attractor = PointAttractor {
+            position: Vector3::zero(),
+            mass: 1.0,
+        };
+        assert!(attractor.force(&body(1.0), 0.0).x < 0.0);
+        assert_eq!(
+            Vector3::zero(),
+            attractor.force(
+                &Body {
+                    position: Vector3::zero(),
+                    ..body(1.0)
+                },
+                0.0
+            )
+        );
+
+        let vortex = SwirlConfig::default();
+        assert_relative_eq!(
+            2.0 * vortex.force(&body(1.0), 0.0),
+            vortex.force(&body(2.0), 0.0)
+        );
+        // It circles the body about the Y axis, and pulls it in toward it.
+        let force = vortex.force(&body(1.0), 0.0);
+        assert!(force.z < 0.0 && force.x < 0.0);
+    }
+
+    #[test]
+    fn one_generator_pushes_rigidbodies_and_meshes() {
+        let push = || -> Box<dyn ForceGenerator> {
+            Box::new(Gravity {
+                acceleration: Vector3::unit_x() * 2.0,
+            })
+        };
+        let rigidbody = RigidBody::new(Vector3::zero(), quantity::kilograms(3.0)).unwrap();
+        let mut rigid = Rigid::new(vec![rigidbody], Vec::new());
+        rigid.get_force_generators_mut().push(push());
+        let (vertices, indices) = geometry::get_cube_vertices();
+        let mesh = SpringyMesh::new(
+            vertices,
+            indices,
+            quantity::kilograms(8.0),
+            quantity::newtons_per_meter(1000.0),
+            quantity::newton_seconds_per_meter(10.0),
+            None,
+            &None,
+        );
+        let mut springy = Springy::new(vec![mesh], Vec::new());
+        // Without the air, which drags the mesh's faces.
+        springy.sync_config(&SpringyConfig {
+            lift_coefficient: 0.0,
+            drag_coefficient: 0.0,
+            ..SpringyConfig::default()
+        });
+        springy.get_force_generators_mut().push(push());
+
+        let steps = |timestep: std::time::Duration| (1.0 / timestep.as_secs_f32()).round() as usize;
+        for _ in 0..steps(rigid.get_timestep()) {
+            rigid.step();
+        }
+        for _ in 0..steps(springy.get_timestep()) {
+            springy.step();
+        }
+        // Accelerating at 2 m/s² for a second.
+        let (_, rigidbody) = rigid.get_rigidbodies().iter().next().unwrap();
+        assert!((rigidbody.get_state().velocity().x - 2.0).abs() < 0.05);
+        let points = springy.get_meshes()[0].get_points();
+        let velocity = points
+            .iter()
+            .map(|point| point.get_velocity().x)
+            .sum::<f32>()
+            / points.len() as f32;
+        assert!((velocity - 2.0).abs() < 0.05);
+    }
+}
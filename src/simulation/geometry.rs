@@ -0,0 +1,152 @@
+/// Vertices and triangle indices of basic shapes, for building the simulations' meshes,
+/// e.g. collidable meshes, springy meshes, and rigidbodies, without a renderer.
+use cgmath::Vector3;
+
+/// Returns the vertices and indices for a sphere centered around (0,0,0), with the specified
+/// radius and number of sectors and stacks.
+pub fn get_sphere_vertices(
+    radius: f32,
+    sectors: u32,
+    stacks: u32,
+) -> (Vec<Vector3<f32>>, Vec<usize>) {
+    let sector_step = 2.0 * std::f32::consts::PI / sectors as f32;
+    let stack_step = std::f32::consts::PI / stacks as f32;
+
+    let mut vertex_positions = Vec::new();
+    for i in 0..=stacks {
+        let stack_angle = std::f32::consts::PI / 2.0 - i as f32 * stack_step;
+        let xy = radius * f32::cos(stack_angle);
+        let z = radius * f32::sin(stack_angle);
+
+        for j in 0..=sectors {
+            let sector_angle = j as f32 * sector_step;
+            let x = xy * f32::cos(sector_angle);
+            let y = xy * f32::sin(sector_angle);
+            vertex_positions.push(cgmath::Vector3 { x, y, z });
+        }
+    }
+
+    // generate CCW index list of sphere triangles
+    // k1--k1+1
+    // |  / |
+    // | /  |
+    // k2--k2+1
+    let mut vertex_indices = Vec::new();
+    let sectors = sectors as usize;
+    for i in 0..stacks as usize {
+        let mut k1 = i * (sectors + 1);
+        let mut k2 = k1 + sectors + 1;
+
+        for _j in 0..sectors {
+            // First and last stacks do not need quads, just tris.
+            if i != 0 {
+                vertex_indices.push(k1);
+                vertex_indices.push(k2);
+                vertex_indices.push(k1 + 1);
+            }
+            if i != (stacks as usize - 1) {
+                vertex_indices.push(k1 + 1);
+                vertex_indices.push(k2);
+                vertex_indices.push(k2 + 1);
+            }
+            k1 = k1 + 1;
+            k2 = k2 + 1;
+        }
+    }
+    (vertex_positions, vertex_indices)
+}
+
+/// Returns the vertices and indices for a 1 x 1 x 1 cube centered around (0,0,0).
+pub fn get_cube_vertices() -> (Vec<Vector3<f32>>, Vec<usize>) {
+    let vertex_positions = vec![
+        // front
+        cgmath::Vector3 {
+            x: -0.5,
+            y: -0.5,
+            z: 0.5,
+        },
+        cgmath::Vector3 {
+            x: 0.5,
+            y: -0.5,
+            z: 0.5,
+        },
+        cgmath::Vector3 {
+            x: 0.5,
+            y: 0.5,
+            z: 0.5,
+        },
+        cgmath::Vector3 {
+            x: -0.5,
+            y: 0.5,
+            z: 0.5,
+        },
+        cgmath::Vector3 {
+            x: -0.5,
+            y: -0.5,
+            z: -0.5,
+        },
+        cgmath::Vector3 {
+            x: 0.5,
+            y: -0.5,
+            z: -0.5,
+        },
+        cgmath::Vector3 {
+            x: 0.5,
+            y: 0.5,
+            z: -0.5,
+        },
+        cgmath::Vector3 {
+            x: -0.5,
+            y: 0.5,
+            z: -0.5,
+        },
+    ];
+
+    let indices: Vec<usize> = vec![
+        0, 1, 2, 2, 3, 0, // front
+        1, 5, 6, 6, 2, 1, // right
+        7, 6, 5, 5, 4, 7, // back
+        4, 0, 3, 3, 7, 4, // left
+        4, 5, 1, 1, 0, 4, // bottom
+        3, 2, 6, 6, 7, 3, // top
+    ];
+
+    (vertex_positions, indices)
+}
+
+/// Returns the vertices and indices for a cylinder centered around (0,0,0) with its axis along y, with the
+/// specified radius, half height, and number of sectors, capped at both ends.
+pub fn get_cylinder_vertices(
+    radius: f32,
+    half_height: f32,
+    sectors: u32,
+) -> (Vec<Vector3<f32>>, Vec<usize>) {
+    let sector_step = 2.0 * std::f32::consts::PI / sectors as f32;
+
+    // The bottom ring, then the top ring, then the bottom and top caps' centers.
+    let mut vertex_positions = Vec::new();
+    for y in [-half_height, half_height] {
+        for i in 0..sectors {
+            let sector_angle = i as f32 * sector_step;
+            let x = radius * f32::cos(sector_angle);
+            let z = radius * f32::sin(sector_angle);
+            vertex_positions.push(cgmath::Vector3 { x, y, z });
+        }
+    }
+    vertex_positions.push(cgmath::Vector3::new(0.0, -half_height, 0.0));
+    vertex_positions.push(cgmath::Vector3::new(0.0, half_height, 0.0));
+
+    // generate CCW index list of the side's quads and the caps' tris
+    let sectors = sectors as usize;
+    let (bottom_center, top_center) = (2 * sectors, 2 * sectors + 1);
+    let mut vertex_indices = Vec::new();
+    for i in 0..sectors {
+        let j = (i + 1) % sectors;
+        let (bottom_i, bottom_j, top_i, top_j) = (i, j, i + sectors, j + sectors);
+        vertex_indices.extend_from_slice(&[bottom_i, top_i, bottom_j]);
+        vertex_indices.extend_from_slice(&[bottom_j, top_i, top_j]);
+        vertex_indices.extend_from_slice(&[bottom_center, bottom_i, bottom_j]);
+        vertex_indices.extend_from_slice(&[top_center, top_j, top_i]);
+    }
+    (vertex_positions, vertex_indices)
+}
@@ -0,0 +1,144 @@
+/// Inverse kinematics for chains of joints, solved by FABRIK (Forward And Backward Reaching Inverse Kinematics).
+/// Chains are points, from the root to the end effector, connected by segments of fixed lengths,
+/// so they can come from jointed rigidbodies, e.g. an arm of hinged segments or the limbs of a ragdoll.
+use cgmath::{InnerSpace, Vector3};
+
+/// Moves the points of the chain so that its end effector, the last point, reaches for the target,
+/// keeping the root, the first point, in place and the lengths of the segments between the points.
+/// The chain starts from the given points, so that solving each frame moves it smoothly.
+/// Returns whether the end effector is within the tolerance of the target.
+pub fn fabrik(
+    points: &mut [Vector3<f32>],
+    target: Vector3<f32>,
+    tolerance: f32,
+    max_iterations: usize,
+) -> bool {
+    if points.len() < 2 {
+        return false;
+    }
+    let lengths = points
+        .windows(2)
+        .map(|segment| (segment[1] - segment[0]).magnitude())
+        .collect::<Vec<_>>();
+    let root = points[0];
+
+    // An unreachable target is reached for by straightening the chain towards it.
+    if (target - root).magnitude() >= lengths.iter().sum::<f32>() {
+        let direction = (target - root).normalize();
+        for (index, length) in lengths.iter().enumerate() {
+            points[index + 1] = points[index] + direction * *length;
+        }
+        return (points[points.len() - 1] - target).magnitude() <= tolerance;
+    }
+
+    for _ in 0..max_iterations {
+        if (points[points.len() - 1] - target).magnitude() <= tolerance {
+            return true;
+        }
+        // Backward, placing the end effector on the target and each point in turn towards the root.
+        let last = points.len() - 1;
+        points[last] = target;
+        for index in (0..last).rev() {
+            points[index] = place(points[index], points[index + 1], lengths[index]);
+        }
+        // Forward, placing the root back and each point in turn towards the end effector.
+        points[0] = root;
+        for index in 0..last {
+            points[index + 1] = place(points[index + 1], points[index], lengths[index]);
+        }
+    }
+    (points[points.len() - 1] - target).magnitude() <= tolerance
+}
+
+/// Moves the point to the given distance from the fixed point, along the line between them.
+fn place(point: Vector3<f32>, fixed: Vector3<f32>, length: f32) -> Vector3<f32> {
+    let offset = point - fixed;
+    let distance = offset.magnitude();
+    if distance < f32::EPSILON {
+        return point;
+    }
+    fixed + offset * (length / distance)
+}
+
+/// The angles in radians, about the axis, that each segment of the chain is turned from the one before it,
+/// as a chain of hinges sharing that axis would turn them. The first segment is measured from the rest direction.
+pub fn hinge_angles(
+    points: &[Vector3<f32>],
+    axis: Vector3<f32>,
+    rest_direction: Vector3<f32>,
+) -> Vec<f32> {
+    let axis = axis.normalize();
+    let mut previous = rest_direction;
+    points
+        .windows(2)
+        .map(|segment| {
+            let direction = segment[1] - segment[0];
+            let angle = signed_angle(previous, direction, axis);
+            previous = direction;
+            angle
+        })
+        .collect()
+}
+
+/// The angle turning from one vector to the other about the unit axis, in [-π, π],
+/// ignoring their components along the axis.
+fn signed_angle(from: Vector3<f32>, to: Vector3<f32>, axis: Vector3<f32>) -> f32 {
+    let from = from - axis * from.dot(axis);
+    let to = to - axis * to.dot(axis);
+    from.cross(to).dot(axis).atan2(from.dot(to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arm() -> Vec<Vector3<f32>> {
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.5, 0.0),
+            Vector3::new(0.0, 0.9, 0.0),
+            Vector3::new(0.0, 1.2, 0.0),
+        ]
+    }
+
+    fn lengths(points: &[Vector3<f32>]) -> Vec<f32> {
+        points
+            .windows(2)
+            .map(|segment| (segment[1] - segment[0]).magnitude())
+            .collect()
+    }
+
+    #[test]
+    fn reaches_target_keeping_lengths() {
+        let mut points = arm();
+        let target = Vector3::new(0.6, 0.4, 0.2);
+        assert!(fabrik(&mut points, target, 1e-4, 100));
+        assert!((points[3] - target).magnitude() <= 1e-4);
+        assert_eq!(points[0], Vector3::new(0.0, 0.0, 0.0));
+        for (length, expected) in lengths(&points).iter().zip(lengths(&arm()).iter()) {
+            assert!((length - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn straightens_towards_unreachable_target() {
+        let mut points = arm();
+        let target = Vector3::new(3.0, 0.0, 0.0);
+        assert!(!fabrik(&mut points, target, 1e-4, 100));
+        assert!((points[3] - Vector3::new(1.2, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn hinge_angles_of_bent_chain() {
+        let points = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(-1.0, -1.0, 0.0),
+        ];
+        let angles = hinge_angles(&points, Vector3::unit_z(), Vector3::unit_y());
+        let quarter = std::f32::consts::FRAC_PI_2;
+        // Turning from up to left is a positive turn about z, then from left to down is another.
+        assert!((angles[0] - quarter).abs() < 1e-6);
+        assert!((angles[1] - quarter).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::simulation::reflect::{Field, Reflect};
+
+use std::time::Duration;
+
+/// The lattice Boltzmann solver works in lattice units: lengths in cells, and times in steps.
+#[derive(Clone, PartialEq)]
+pub struct Config {
+    /// The real time each step is shown for, in seconds as f32, which sets how fast the flow plays.
+    pub dt: f32,
+    /// The velocity of the flow into the channel, in cells per step.
+    /// This should stay well below the lattice's speed of sound, 1/√3, for the flow to be accurate.
+    pub inlet_velocity: f32,
+    /// The Reynolds number of the flow past the cylinder, which sets the fluid's viscosity.
+    /// The cylinder sheds a vortex street above about 50.
+    pub reynolds_number: f32,
+    /// The radius of the cylinder, in cells.
+    pub cylinder_radius: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dt: Duration::from_millis(2).as_secs_f32(),
+            inlet_velocity: 0.1,
+            reynolds_number: 150.0,
+            cylinder_radius: 8.0,
+        }
+    }
+}
+
+impl Reflect for Config {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![
+            Field::new("dt", "Simualtion dt (secs)", &mut self.dt).range(0.001..=0.02),
+            Field::new(
+                "inlet_velocity",
+                "Inlet Velocity (cells/step)",
+                &mut self.inlet_velocity,
+            )
+            .range(0.01..=0.2),
+            Field::new(
+                "reynolds_number",
+                "Reynolds Number",
+                &mut self.reynolds_number,
+            )
+            .range(10.0..=400.0),
+            Field::new(
+                "cylinder_radius",
+                "Cylinder Radius (cells)",
+                &mut self.cylinder_radius,
+            )
+            .range(2.0..=24.0),
+        ]
+    }
+}
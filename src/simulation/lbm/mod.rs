@@ -0,0 +1,284 @@
+pub mod config;
+
+use self::config::Config;
+use crate::simulation::configurable::Configurable;
+
+use cgmath::{InnerSpace, Vector2, Zero};
+
+/// The size of the lattice, in cells. The flow enters at x = 0, and leaves at x = WIDTH - 1.
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 96;
+
+/// The D2Q9 lattice's velocities: rest, the four axes, then the four diagonals.
+const VELOCITIES: [(i32, i32); 9] = [
+    (0, 0),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+    (1, -1),
+];
+const WEIGHTS: [f32; 9] = [
+    4.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 36.0,
+    1.0 / 36.0,
+    1.0 / 36.0,
+    1.0 / 36.0,
+];
+/// The index of the velocity opposite each velocity, which bounce-back reflects it into.
+const OPPOSITE: [usize; 9] = [0, 3, 4, 1, 2, 7, 8, 5, 6];
+/// The relaxation time is kept above this, since the BGK collision is unstable as it nears 0.5.
+const MIN_RELAXATION_TIME: f32 = 0.51;
+
+type Distribution = [f32; 9];
+
+/// Gets the equilibrium distribution of a cell with the density and velocity.
+fn equilibrium(density: f32, velocity: Vector2<f32>) -> Distribution {
+    let speed_squared = velocity.magnitude2();
+    let mut distribution = [0.0; 9];
+    for (i, (cx, cy)) in VELOCITIES.iter().enumerate() {
+        let cu = *cx as f32 * velocity.x + *cy as f32 * velocity.y;
+        distribution[i] =
+            WEIGHTS[i] * density * (1.0 + 3.0 * cu + 4.5 * cu * cu - 1.5 * speed_squared);
+    }
+    distribution
+}
+
+/// Gets the density and velocity of a cell from its distribution.
+fn moments(distribution: &Distribution) -> (f32, Vector2<f32>) {
+    let (density, momentum) = distribution.iter().zip(VELOCITIES.iter()).fold(
+        (0.0, Vector2::zero()),
+        |(density, momentum), (f, (cx, cy))| {
+            (
+                density + f,
+                momentum + Vector2::new(*cx as f32, *cy as f32) * *f,
+            )
+        },
+    );
+    if density > 0.0 {
+        (density, momentum / density)
+    } else {
+        (density, Vector2::zero())
+    }
+}
+
+/// Two dimensional flow past a cylinder, simulated with a D2Q9 lattice Boltzmann solver.
+/// Rather than tracking particles, each cell of the lattice holds the distribution of fluid
+/// moving along each of the lattice's nine velocities. Each step, the distributions relax toward
+/// their equilibrium (the BGK collision), then stream to the neighbouring cells. Distributions
+/// streaming into the cylinder bounce back, which gives it a no-slip boundary.
+/// The flow enters at the left with a uniform velocity and leaves at the right; the top and
+/// bottom are periodic.
+pub struct Simulation {
+    config: Config,
+    distributions: Vec<Distribution>,
+    /// The distributions being streamed into, which are swapped with the current ones each step.
+    streamed: Vec<Distribution>,
+    solid: Vec<bool>,
+    /// The radius the cylinder was built with, to rebuild it when the configuration changes.
+    cylinder_radius: f32,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        let config = Config::default();
+        let cylinder_radius = config.cylinder_radius;
+        let mut simulation = Simulation {
+            config,
+            distributions: Vec::new(),
+            streamed: vec![[0.0; 9]; WIDTH * HEIGHT],
+            solid: vec![false; WIDTH * HEIGHT],
+            cylinder_radius,
+        };
+        simulation.reset();
+        simulation
+    }
+
+    /// Fills the channel with fluid moving at the inlet velocity, around the cylinder.
+    pub fn reset(&mut self) {
+        self.build_cylinder();
+        let inlet = equilibrium(1.0, Vector2::new(self.config.inlet_velocity, 0.0));
+        self.distributions = self
+            .solid
+            .iter()
+            .map(|solid| {
+                if *solid {
+                    equilibrium(1.0, Vector2::zero())
+                } else {
+                    inlet
+                }
+            })
+            .collect();
+    }
+
+    /// Marks the cells within the cylinder as solid. The cylinder is slightly off the channel's
+    /// center line, so that the symmetric flow around it becomes unstable and sheds vortices sooner.
+    fn build_cylinder(&mut self) {
+        self.cylinder_radius = self.config.cylinder_radius;
+        let center = Vector2::new(WIDTH as f32 / 4.0, HEIGHT as f32 / 2.0 + 1.0);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let distance = (Vector2::new(x as f32, y as f32) - center).magnitude();
+                self.solid[y * WIDTH + x] = distance < self.cylinder_radius;
+            }
+        }
+    }
+
+    /// The relaxation time which gives the fluid the viscosity for the configured Reynolds number.
+    fn relaxation_time(&self) -> f32 {
+        let viscosity = self.config.inlet_velocity * 2.0 * self.config.cylinder_radius
+            / self.config.reynolds_number;
+        (3.0 * viscosity + 0.5).max(MIN_RELAXATION_TIME)
+    }
+
+    pub fn step(&mut self) -> std::time::Duration {
+        let tau = self.relaxation_time();
+        for (distribution, solid) in self.distributions.iter_mut().zip(self.solid.iter()) {
+            if *solid {
+                continue;
+            }
+            let (density, velocity) = moments(distribution);
+            let target = equilibrium(density, velocity);
+            for (f, f_eq) in distribution.iter_mut().zip(target.iter()) {
+                *f += (f_eq - *f) / tau;
+            }
+        }
+
+        let inlet = equilibrium(1.0, Vector2::new(self.config.inlet_velocity, 0.0));
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let index = y * WIDTH + x;
+                if self.solid[index] {
+                    continue;
+                }
+                if x == 0 {
+                    self.streamed[index] = inlet;
+                    continue;
+                }
+                for (i, (cx, cy)) in VELOCITIES.iter().enumerate() {
+                    // Pull the distribution moving along this velocity from the upstream cell.
+                    let source_x = x as i32 - cx;
+                    let source_y = (y as i32 - cy).rem_euclid(HEIGHT as i32) as usize;
+                    self.streamed[index][i] = if source_x as usize >= WIDTH {
+                        // Nothing flows in from beyond the outlet, so the outflow is extrapolated.
+                        self.distributions[index][i]
+                    } else {
+                        let source = source_y * WIDTH + source_x as usize;
+                        if self.solid[source] {
+                            self.distributions[index][OPPOSITE[i]]
+                        } else {
+                            self.distributions[source][i]
+                        }
+                    };
+                }
+            }
+        }
+        std::mem::swap(&mut self.distributions, &mut self.streamed);
+
+        std::time::Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn get_timestep(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn is_solid(&self, x: usize, y: usize) -> bool {
+        self.solid[y * WIDTH + x]
+    }
+
+    /// Gets the velocity of the fluid in the cell, in cells per step.
+    pub fn get_velocity(&self, x: usize, y: usize) -> Vector2<f32> {
+        let (_, velocity) = moments(&self.distributions[y * WIDTH + x]);
+        velocity
+    }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.dt = config.dt;
+        self.config.inlet_velocity = config.inlet_velocity;
+        self.config.reynolds_number = config.reynolds_number;
+        self.config.cylinder_radius = config.cylinder_radius;
+        if self.config.cylinder_radius != self.cylinder_radius {
+            self.build_cylinder();
+        }
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equilibrium_has_its_moments() {
+        let velocity = Vector2::new(0.05, -0.02);
+        let (density, equilibrium_velocity) = moments(&equilibrium(1.2, velocity));
+        assert!((density - 1.2).abs() < 1e-5);
+        assert!((equilibrium_velocity - velocity).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn conserves_mass_without_obstacles() {
+        let mut simulation = Simulation::new();
+        simulation.config.cylinder_radius = 0.0;
+        simulation.config.inlet_velocity = 0.0;
+        simulation.reset();
+        // A density bump spreads out, but the total mass is unchanged with no through-flow.
+        let center = HEIGHT / 2 * WIDTH + WIDTH / 2;
+        simulation.distributions[center] = equilibrium(2.0, Vector2::zero());
+        let mass = |simulation: &Simulation| {
+            simulation
+                .distributions
+                .iter()
+                .map(|distribution| distribution.iter().sum::<f32>())
+                .sum::<f32>()
+        };
+        let initial_mass = mass(&simulation);
+        for _ in 0..50 {
+            simulation.step();
+        }
+        assert!((mass(&simulation) - initial_mass).abs() / initial_mass < 1e-4);
+    }
+
+    #[test]
+    fn cylinder_slows_flow() {
+        let mut simulation = Simulation::new();
+        for _ in 0..100 {
+            simulation.step();
+        }
+        let radius = simulation.config.cylinder_radius as usize;
+        let (x, y) = (WIDTH / 4, HEIGHT / 2);
+        assert!(simulation.is_solid(x, y));
+        assert_eq!(simulation.get_velocity(x, y), Vector2::zero());
+        // The fluid just upstream of the cylinder stagnates, while the fluid passing it speeds up.
+        let upstream = simulation.get_velocity(x - radius - 2, y).magnitude();
+        let beside = simulation.get_velocity(x, y + radius + 3).magnitude();
+        assert!(
+            upstream < 0.5 * simulation.config.inlet_velocity,
+            "{}",
+            upstream
+        );
+        assert!(beside > simulation.config.inlet_velocity, "{}", beside);
+        let velocity = simulation.get_velocity(WIDTH - 1, HEIGHT / 2);
+        assert!(velocity.x.is_finite() && velocity.x > 0.0);
+    }
+}
@@ -0,0 +1,223 @@
+/// Measurements for lab-style exercises: distances and angles between points placed in the world, and
+/// stopwatches timing objects between two trigger volumes, e.g. a projectile's flight from launch to a target.
+use cgmath::{InnerSpace, Rad, Vector3};
+use rustc_hash::FxHashMap;
+
+use super::trigger::{Trigger, TriggerAction, TriggerEvent, TriggerShape};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MeasureTool {
+    /// The distance between two points.
+    Ruler,
+    /// The angle at the second of three points, between the lines to the first and third.
+    Angle,
+}
+
+impl MeasureTool {
+    pub const ALL: [MeasureTool; 2] = [MeasureTool::Ruler, MeasureTool::Angle];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MeasureTool::Ruler => "Ruler",
+            MeasureTool::Angle => "Angle",
+        }
+    }
+
+    /// How many points the tool measures between.
+    pub fn point_count(&self) -> usize {
+        match self {
+            MeasureTool::Ruler => 2,
+            MeasureTool::Angle => 3,
+        }
+    }
+}
+
+/// The points placed for a measuring tool. Placing a point once all of the tool's are placed starts a new
+/// measurement from it.
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    tool: MeasureTool,
+    points: Vec<Vector3<f32>>,
+}
+
+impl Measurement {
+    pub fn new(tool: MeasureTool) -> Measurement {
+        Measurement {
+            tool,
+            points: Vec::new(),
+        }
+    }
+
+    pub fn get_tool(&self) -> MeasureTool {
+        self.tool
+    }
+
+    /// Switches to the tool, keeping as many of the placed points as it measures between.
+    pub fn set_tool(&mut self, tool: MeasureTool) {
+        self.tool = tool;
+        self.points.truncate(tool.point_count());
+    }
+
+    pub fn place(&mut self, point: Vector3<f32>) {
+        if self.is_complete() {
+            self.points.clear();
+        }
+        self.points.push(point);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn get_points(&self) -> &[Vector3<f32>] {
+        &self.points
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.points.len() == self.tool.point_count()
+    }
+
+    /// The ruler's distance, once both its points are placed.
+    pub fn distance(&self) -> Option<f32> {
+        match (self.tool, self.points.as_slice()) {
+            (MeasureTool::Ruler, [start, end]) => Some((end - start).magnitude()),
+            _ => None,
+        }
+    }
+
+    /// The angle's size, once its three points are placed, or None if either of its ends is at its vertex.
+    pub fn angle(&self) -> Option<Rad<f32>> {
+        match (self.tool, self.points.as_slice()) {
+            (MeasureTool::Angle, [first, vertex, second]) => {
+                let (first, second) = (first - vertex, second - vertex);
+                if first.magnitude2() < f32::EPSILON || second.magnitude2() < f32::EPSILON {
+                    return None;
+                }
+                Some(first.angle(second))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Times objects from when they enter the start gate to when they next enter the stop gate, recording each
+/// time as a lap. Objects are identified by the index the simulation gave them, as for triggers, and those
+/// removed before they reach the stop gate aren't timed.
+pub struct Stopwatch {
+    start_gate: Trigger,
+    stop_gate: Trigger,
+    time: f32,
+    // The time each running object entered the start gate, by its index.
+    started: FxHashMap<usize, f32>,
+    laps: Vec<f32>,
+}
+
+impl Stopwatch {
+    /// Only the most recent laps are kept.
+    pub const MAX_LAPS: usize = 32;
+
+    pub fn new(start_gate: TriggerShape, stop_gate: TriggerShape) -> Stopwatch {
+        Stopwatch {
+            start_gate: Trigger::new(start_gate, TriggerAction::Count),
+            stop_gate: Trigger::new(stop_gate, TriggerAction::Count),
+            time: 0.0,
+            started: FxHashMap::default(),
+            laps: Vec::new(),
+        }
+    }
+
+    /// Moves the gates, keeping the objects being timed.
+    pub fn set_gates(&mut self, start_gate: TriggerShape, stop_gate: TriggerShape) {
+        self.start_gate.set_shape(start_gate);
+        self.stop_gate.set_shape(stop_gate);
+    }
+
+    /// Advances the stopwatch by the seconds, given each object's index and position at the end of them.
+    pub fn update(&mut self, dt: f32, objects: &[(usize, Vector3<f32>)]) {
+        self.time += dt;
+        for event in self.start_gate.update(objects.iter().copied()) {
+            if let TriggerEvent::Enter(index) = event {
+                self.started.insert(index, self.time);
+            }
+        }
+        for event in self.stop_gate.update(objects.iter().copied()) {
+            if let TriggerEvent::Enter(index) = event {
+                if let Some(start) = self.started.remove(&index) {
+                    if self.laps.len() >= Stopwatch::MAX_LAPS {
+                        self.laps.remove(0);
+                    }
+                    self.laps.push(self.time - start);
+                }
+            }
+        }
+        self.started
+            .retain(|index, _| objects.iter().any(|(object, _)| object == index));
+    }
+
+    /// The seconds of each lap, oldest first.
+    pub fn get_laps(&self) -> &[f32] {
+        &self.laps
+    }
+
+    /// How many objects are being timed, having entered the start gate but not yet the stop gate.
+    pub fn get_running_count(&self) -> usize {
+        self.started.len()
+    }
+
+    /// Forgets the laps and the objects being timed.
+    pub fn clear(&mut self) {
+        self.started.clear();
+        self.laps.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{assert_relative_eq, Deg};
+
+    #[test]
+    fn ruler_and_angle() {
+        let mut measurement = Measurement::new(MeasureTool::Ruler);
+        measurement.place(Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(measurement.distance(), None);
+        measurement.place(Vector3::new(1.0, 3.0, 4.0));
+        assert_relative_eq!(measurement.distance().unwrap(), 5.0);
+
+        measurement.set_tool(MeasureTool::Angle);
+        assert_eq!(measurement.angle(), None);
+        measurement.clear();
+        measurement.place(Vector3::new(1.0, 0.0, 0.0));
+        measurement.place(Vector3::new(0.0, 0.0, 0.0));
+        measurement.place(Vector3::new(1.0, 1.0, 0.0));
+        assert_relative_eq!(measurement.angle().unwrap(), Rad::from(Deg(45.0)));
+        // A fourth point starts a new angle.
+        measurement.place(Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(measurement.get_points().len(), 1);
+    }
+
+    #[test]
+    fn stopwatch_times_objects_between_gates() {
+        // Gates on the planes x = 0 and x = 1, passed by objects moving along x.
+        let gate = |x: f32| TriggerShape::Aabb {
+            min: Vector3::new(x, f32::MIN, f32::MIN),
+            max: Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+        };
+        let mut stopwatch = Stopwatch::new(gate(0.0), gate(1.0));
+        let dt = 0.1;
+        let at = |x: f32| Vector3::new(x, 0.0, 0.0);
+        // The first object moves at 2 m/s, the second at 1 m/s and is removed before it reaches the stop gate.
+        for step in 0..10 {
+            let t = (step + 1) as f32 * dt;
+            let mut objects = vec![(0, at(2.0 * t - 0.5))];
+            if step < 8 {
+                objects.push((1, at(t - 0.05)));
+            }
+            stopwatch.update(dt, &objects);
+        }
+        assert_eq!(stopwatch.get_laps().len(), 1);
+        assert_relative_eq!(stopwatch.get_laps()[0], 0.5, epsilon = 1e-5);
+        assert_eq!(stopwatch.get_running_count(), 0);
+    }
+}
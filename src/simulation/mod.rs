@@ -1,12 +1,48 @@
+pub mod animation;
+pub mod bench;
+pub mod billiards;
 pub mod bounce;
 pub mod bounding_box;
+pub mod bridge;
+pub mod charged;
+pub mod checksum;
 pub mod collidable_mesh;
+pub mod collision_filter;
+pub mod configurable;
+pub mod conservation;
 pub mod consts;
+pub mod container;
+pub mod energy;
+pub mod fan;
+pub mod field;
+pub mod fitting;
 pub mod flocking;
+pub mod force;
+pub mod geometry;
+pub mod ik;
+pub mod lbm;
+pub mod measure;
 pub mod parametric;
 pub mod particles_cpu;
+pub mod planar;
+pub mod plugin;
 pub mod point_attractor;
+pub mod quantity;
+pub mod raycast;
+pub mod reflect;
 pub mod rigidbody;
+pub mod simplification;
+pub mod slosh;
+pub mod snapshot;
+pub mod solver;
+pub mod spectrum;
 pub mod sph;
 pub mod springy;
 pub mod state;
+pub mod state_vector;
+pub mod target_range;
+pub mod trigger;
+pub mod units;
+pub mod vortex;
+pub mod voxelize;
+pub mod wind_tunnel;
@@ -0,0 +1,202 @@
+/// Keeping the particle simulation's steps within a budget of milliseconds, by throttling how many particles
+/// are generated, and optionally by killing the oldest particles when throttling alone can't keep up.
+/// The controller follows a moving average of the step time, so single slow steps don't throttle emission.
+use super::particle::ParticlePool;
+
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StepBudgetConfig {
+    /// The time each step should take, in milliseconds.
+    pub milliseconds: f32,
+    /// Whether to kill the oldest particles while steps run far over the budget.
+    pub kill_oldest: bool,
+}
+
+impl Default for StepBudgetConfig {
+    fn default() -> Self {
+        Self {
+            milliseconds: 0.5,
+            kill_oldest: false,
+        }
+    }
+}
+
+/// Scales the generator's emission down while steps take longer than the budget, and back up once there is headroom.
+#[derive(Debug)]
+pub struct StepBudget {
+    /// An exponential moving average of the step time, in milliseconds.
+    smoothed_milliseconds: Option<f32>,
+    /// The fraction of the configured particles per step which are generated.
+    emission_scale: f32,
+    /// The fraction of a particle left over from scaling previous steps' emission, so the average rate is kept.
+    carry: f32,
+}
+
+impl StepBudget {
+    /// The weight of each new step time in the moving average.
+    const SMOOTHING: f32 = 0.05;
+    /// The most the emission scale falls or rises by each step.
+    const MAX_DECREASE: f32 = 0.02;
+    const MAX_INCREASE: f32 = 0.01;
+    /// Emission is raised once steps take less than this fraction of the budget.
+    const HEADROOM: f32 = 0.8;
+    /// The oldest particles are killed once steps take this many times the budget.
+    const OVERLOAD: f32 = 1.5;
+    /// The fraction of the live particles killed each overloaded step.
+    const KILL_FRACTION: f32 = 0.01;
+
+    pub fn new() -> StepBudget {
+        StepBudget {
+            smoothed_milliseconds: None,
+            emission_scale: 1.0,
+            carry: 0.0,
+        }
+    }
+
+    /// Gets the moving average of the step time, in milliseconds.
+    pub fn get_step_milliseconds(&self) -> Option<f32> {
+        self.smoothed_milliseconds
+    }
+
+    pub fn get_emission_scale(&self) -> f32 {
+        self.emission_scale
+    }
+
+    /// Gets the number of particles to generate this step, of the configured number per step.
+    pub fn emission(&mut self, particles_per_step: u32) -> u32 {
+        let scaled = particles_per_step as f32 * self.emission_scale + self.carry;
+        let emitted = scaled.floor();
+        self.carry = scaled - emitted;
+        emitted as u32
+    }
+
+    /// Adjusts the emission scale for the time the step took, killing the pool's oldest particles if the
+    /// step ran far over the budget and the config allows it.
+    pub fn update(
+        &mut self,
+        step_time: Duration,
+        config: &StepBudgetConfig,
+        pool: &mut ParticlePool,
+    ) {
+        let milliseconds = step_time.as_secs_f32() * 1000.0;
+        let smoothed = match self.smoothed_milliseconds {
+            Some(smoothed) => smoothed + StepBudget::SMOOTHING * (milliseconds - smoothed),
+            None => milliseconds,
+        };
+        self.smoothed_milliseconds = Some(smoothed);
+
+        let ratio = config.milliseconds / smoothed.max(f32::EPSILON);
+        self.emission_scale = if ratio < 1.0 {
+            self.emission_scale * ratio.max(1.0 - StepBudget::MAX_DECREASE)
+        } else if ratio > 1.0 / StepBudget::HEADROOM {
+            // Raised additively, so emission recovers even once it's been throttled to nothing.
+            self.emission_scale + StepBudget::MAX_INCREASE
+        } else {
+            self.emission_scale
+        }
+        .clamp(0.0, 1.0);
+
+        if config.kill_oldest && ratio < 1.0 / StepBudget::OVERLOAD {
+            let live = pool
+                .particles
+                .iter()
+                .filter(|particle| particle.in_use())
+                .count();
+            kill_oldest(
+                pool,
+                (live as f32 * StepBudget::KILL_FRACTION).ceil() as usize,
+            );
+        }
+    }
+
+    /// Forgets the step times, and stops throttling emission.
+    pub fn reset(&mut self) {
+        *self = StepBudget::new();
+    }
+}
+
+impl Default for StepBudget {
+    fn default() -> Self {
+        StepBudget::new()
+    }
+}
+
+/// Kills up to count of the pool's particles, oldest first.
+pub fn kill_oldest(pool: &mut ParticlePool, count: usize) {
+    let mut live = pool
+        .particles
+        .iter()
+        .enumerate()
+        .filter(|(_, particle)| particle.in_use())
+        .map(|(index, particle)| (index, particle.age))
+        .collect::<Vec<_>>();
+    if count < live.len() {
+        live.select_nth_unstable_by(count, |(_, a), (_, b)| b.total_cmp(a));
+        live.truncate(count);
+    }
+    for (index, _) in live {
+        pool.particles[index].lifetime = Duration::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+
+    /// Runs the controller for a thousand steps of the given time.
+    fn run(budget: &mut StepBudget, step_time: Duration, config: &StepBudgetConfig) {
+        let mut pool = ParticlePool::new();
+        for _ in 0..1000 {
+            budget.update(step_time, config, &mut pool);
+        }
+    }
+
+    #[test]
+    fn throttles_emission_when_slow_and_restores_it_when_fast() {
+        let config = StepBudgetConfig::default();
+        let mut budget = StepBudget::new();
+        run(&mut budget, Duration::from_millis(2), &config);
+        assert!(budget.get_emission_scale() < 0.01);
+        assert_eq!(budget.emission(10), 0);
+
+        run(&mut budget, Duration::from_micros(100), &config);
+        assert_eq!(budget.get_emission_scale(), 1.0);
+        assert_eq!(budget.emission(10), 10);
+    }
+
+    #[test]
+    fn keeps_the_average_emission_rate() {
+        let mut budget = StepBudget::new();
+        budget.emission_scale = 0.25;
+        let emitted = (0..100).map(|_| budget.emission(3)).sum::<u32>();
+        assert_eq!(emitted, 75);
+    }
+
+    #[test]
+    fn kills_oldest_particles_first() {
+        let mut pool = ParticlePool::new();
+        for age in 0..10 {
+            let particle = pool
+                .create(
+                    Vector3::new(0.0, 0.0, 0.0),
+                    Vector3::new(0.0, 0.0, 0.0),
+                    Duration::from_secs(1),
+                    1.0,
+                    1.0,
+                )
+                .unwrap();
+            particle.age = age as f32;
+        }
+        kill_oldest(&mut pool, 3);
+        let mut ages = pool
+            .particles
+            .iter()
+            .filter(|particle| particle.in_use())
+            .map(|particle| particle.age)
+            .collect::<Vec<_>>();
+        ages.sort_by(f32::total_cmp);
+        assert_eq!(ages, (0..7).map(|age| age as f32).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,186 @@
+/// Curl noise turbulence, a wind of swirling eddies which evolve over time. The wind is the curl of a vector
+/// potential of gradient noise, so it's divergence-free, like an incompressible flow: particles it carries
+/// swirl around each other without bunching up or spreading out. It blows on particles through their drag.
+use cgmath::Vector3;
+
+/// The step of the central differences the potential's curl is taken with, relative to the eddies' size.
+const DIFFERENCE_STEP: f32 = 1e-3;
+
+/// Offsets sampling each component of the potential from elsewhere in the noise, so they're uncorrelated.
+const COMPONENT_OFFSETS: [Vector3<f32>; 3] = [
+    Vector3::new(0.0, 0.0, 0.0),
+    Vector3::new(31.4, -17.3, 53.9),
+    Vector3::new(-47.1, 71.8, -22.6),
+];
+
+/// The directions the noise drifts through over time, one per component of the potential, so that it
+/// evolves rather than just scrolling.
+const DRIFTS: [Vector3<f32>; 3] = [
+    Vector3::new(0.0, 1.0, 0.0),
+    Vector3::new(1.0, 0.0, 0.0),
+    Vector3::new(0.0, 0.0, 1.0),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CurlNoiseConfig {
+    /// The typical speed of the turbulent wind, in m/s.
+    pub strength: f32,
+    /// The typical size of the eddies, in m.
+    pub scale: f32,
+    /// How quickly the eddies evolve, in eddy sizes per second.
+    pub evolution_rate: f32,
+}
+
+impl Default for CurlNoiseConfig {
+    fn default() -> Self {
+        Self {
+            strength: 1.5,
+            scale: 1.0,
+            evolution_rate: 0.3,
+        }
+    }
+}
+
+impl CurlNoiseConfig {
+    /// The turbulent wind at the position, the given seconds into the simulation.
+    pub fn velocity(&self, position: Vector3<f32>, time: f32) -> Vector3<f32> {
+        let point = position / self.scale;
+        let drift = time * self.evolution_rate;
+        let potential = |component: usize, offset: Vector3<f32>| {
+            gradient_noise(
+                point + offset + COMPONENT_OFFSETS[component] + DRIFTS[component] * drift,
+                component as u32,
+            )
+        };
+        // The derivative of the component of the potential along the axis.
+        let derivative = |component: usize, axis: Vector3<f32>| {
+            (potential(component, axis * DIFFERENCE_STEP)
+                - potential(component, -axis * DIFFERENCE_STEP))
+                / (2.0 * DIFFERENCE_STEP)
+        };
+        let (x, y, z) = (Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z());
+        let curl = Vector3::new(
+            derivative(2, y) - derivative(1, z),
+            derivative(0, z) - derivative(2, x),
+            derivative(1, x) - derivative(0, y),
+        );
+        self.strength * curl
+    }
+}
+
+/// The gradients of the noise's lattice points, the midpoints of a cube's edges.
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// Hashes a lattice point and seed, mixing the bits so that neighboring points hash unrelated values.
+fn hash(x: i32, y: i32, z: i32, seed: u32) -> u32 {
+    let mut hash = seed.wrapping_mul(0x9e3779b9)
+        ^ (x as u32).wrapping_mul(0x8da6b343)
+        ^ (y as u32).wrapping_mul(0xd8163841)
+        ^ (z as u32).wrapping_mul(0xcb1ab31f);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846ca68b);
+    hash ^ (hash >> 16)
+}
+
+/// Perlin's gradient noise, smooth noise between about -1 and 1 which is 0 at each integer lattice point.
+fn gradient_noise(point: Vector3<f32>, seed: u32) -> f32 {
+    let cell = [point.x.floor(), point.y.floor(), point.z.floor()];
+    let local = [point.x - cell[0], point.y - cell[1], point.z - cell[2]];
+    // Perlin's quintic fade, whose first and second derivatives are 0 at the lattice points.
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let weights = local.map(fade);
+
+    let mut sum = 0.0;
+    for corner in 0..8 {
+        let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1].map(|bit| bit as f32);
+        let gradient = GRADIENTS[hash(
+            (cell[0] + offset[0]) as i32,
+            (cell[1] + offset[1]) as i32,
+            (cell[2] + offset[2]) as i32,
+            seed,
+        ) as usize
+            % GRADIENTS.len()];
+        let dot = (0..3)
+            .map(|axis| gradient[axis] * (local[axis] - offset[axis]))
+            .sum::<f32>();
+        let weight = (0..3)
+            .map(|axis| {
+                if offset[axis] == 1.0 {
+                    weights[axis]
+                } else {
+                    1.0 - weights[axis]
+                }
+            })
+            .product::<f32>();
+        sum += weight * dot;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn noise_is_smooth_and_zero_on_the_lattice() {
+        assert_eq!(gradient_noise(Vector3::new(2.0, -3.0, 5.0), 0), 0.0);
+        let point = Vector3::new(0.3, 1.7, -2.2);
+        let nearby = point + Vector3::new(1e-3, 0.0, 0.0);
+        assert!((gradient_noise(point, 0) - gradient_noise(nearby, 0)).abs() < 1e-2);
+        assert_ne!(gradient_noise(point, 0), gradient_noise(point, 1));
+    }
+
+    #[test]
+    fn turbulence_is_divergence_free() {
+        let noise = CurlNoiseConfig::default();
+        let step = 1e-2;
+        for point in [
+            Vector3::new(0.3, 1.2, -0.7),
+            Vector3::new(-2.1, 0.4, 3.3),
+            Vector3::new(1.6, -1.9, 0.2),
+        ] {
+            let velocity = noise.velocity(point, 2.0);
+            assert!(velocity.magnitude() > 1e-3);
+            let divergence = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()]
+                .iter()
+                .map(|axis| {
+                    (noise.velocity(point + axis * step, 2.0)
+                        - noise.velocity(point - axis * step, 2.0))
+                    .dot(*axis)
+                        / (2.0 * step)
+                })
+                .sum::<f32>();
+            // Small relative to the velocity's derivatives, which are about its magnitude over the eddies' size.
+            assert!(divergence.abs() < 0.05 * noise.strength, "{}", divergence);
+        }
+    }
+
+    #[test]
+    fn turbulence_evolves() {
+        let noise = CurlNoiseConfig::default();
+        let point = Vector3::new(0.5, 0.5, 0.5);
+        assert_eq!(noise.velocity(point, 1.0), noise.velocity(point, 1.0));
+        assert_ne!(noise.velocity(point, 1.0), noise.velocity(point, 3.0));
+        let still = CurlNoiseConfig {
+            evolution_rate: 0.0,
+            ..noise
+        };
+        assert_eq!(still.velocity(point, 1.0), still.velocity(point, 3.0));
+    }
+}
@@ -0,0 +1,81 @@
+/// Temperature-driven particles, for fire and embers. Burning particles are parcels of hot gas,
+/// which cool towards the ambient temperature and are buoyed up by how much hotter than the air they are.
+/// When a burning particle's lifetime ends it burns out into smoke, which keeps rising as it cools.
+use cgmath::Vector3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FireConfig {
+    /// The temperature the particles are generated at, in kelvin.
+    pub initial_temperature: f32,
+    /// The temperature of the air, in kelvin.
+    pub ambient_temperature: f32,
+    /// The rate the particles' temperature decays towards the ambient temperature, in 1/s.
+    pub cooling_rate: f32,
+    /// Scales the buoyancy of an ideal gas; 1 buoys a particle twice the ambient temperature at 1 g.
+    pub buoyancy: f32,
+    /// The number of smoke particles each burning particle burns out into.
+    pub smoke_per_ember: u32,
+    /// Seconds.
+    pub smoke_lifetime: f32,
+}
+
+impl Default for FireConfig {
+    fn default() -> Self {
+        Self {
+            initial_temperature: 1800.0,
+            ambient_temperature: 300.0,
+            cooling_rate: 1.5,
+            buoyancy: 0.5,
+            smoke_per_ember: 1,
+            smoke_lifetime: 3.0,
+        }
+    }
+}
+
+impl FireConfig {
+    /// The temperature after cooling for dt seconds, exactly integrating Newton's law of cooling.
+    pub fn cool(&self, temperature: f32, dt: f32) -> f32 {
+        self.ambient_temperature
+            + (temperature - self.ambient_temperature) * (-self.cooling_rate * dt).exp()
+    }
+
+    /// The net acceleration of a parcel of gas at the temperature. Its weight is balanced by the air
+    /// it displaces, leaving it buoyed against gravity by its relative density deficit, (T - Tₐ) / Tₐ.
+    /// So a parcel at the ambient temperature floats, rather than falling as other particles do.
+    pub fn buoyancy(&self, temperature: f32, gravity: Vector3<f32>) -> Vector3<f32> {
+        -gravity * self.buoyancy * (temperature - self.ambient_temperature)
+            / self.ambient_temperature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::units::GravityPreset;
+    use cgmath::Zero;
+
+    #[test]
+    fn cools_towards_ambient() {
+        let fire = FireConfig::default();
+        let once = fire.cool(fire.initial_temperature, 1.0);
+        let twice = fire.cool(fire.cool(fire.initial_temperature, 0.5), 0.5);
+        assert!((once - twice).abs() < 1e-2);
+        assert!(once < fire.initial_temperature && once > fire.ambient_temperature);
+        assert!(
+            (fire.cool(fire.initial_temperature, 100.0) - fire.ambient_temperature).abs() < 1e-3
+        );
+    }
+
+    #[test]
+    fn hotter_particles_rise_faster() {
+        let fire = FireConfig::default();
+        let gravity = GravityPreset::Earth.vector();
+        let hot = fire.buoyancy(1500.0, gravity);
+        let warm = fire.buoyancy(600.0, gravity);
+        assert!(hot.y > warm.y && warm.y > 0.0);
+        assert_eq!(
+            fire.buoyancy(fire.ambient_temperature, gravity),
+            Vector3::zero()
+        );
+    }
+}
@@ -1,10 +1,107 @@
 use super::particle::ParticlePool;
+#[cfg(feature = "render")]
+use crate::graphics::model::ColoredMesh;
+use crate::simulation::collidable_mesh::{CollidableMesh, Face};
 
 use cgmath::{InnerSpace, Vector3};
 use rand::Rng;
 
 use std::{ops::Range, time::Duration};
 
+/// Particles emitted from a surface start this far above it, so they don't collide with it as they're emitted.
+const SURFACE_OFFSET: f32 = 0.001;
+
+/// Where the particles are emitted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitterShape {
+    /// A disk, given by the generator's position, normal, and radius.
+    Disk,
+    /// The surface of the obstacle, e.g. to make it sweat or shed snow.
+    ObstacleSurface,
+}
+
+impl EmitterShape {
+    pub const ALL: [EmitterShape; 2] = [EmitterShape::Disk, EmitterShape::ObstacleSurface];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmitterShape::Disk => "Disk",
+            EmitterShape::ObstacleSurface => "Obstacle Surface",
+        }
+    }
+}
+
+/// Emits particles uniformly over the surface of a mesh, choosing each triangle with probability
+/// proportional to its area, and then a uniformly distributed point within it.
+pub struct SurfaceEmitter {
+    faces: Vec<Face>,
+    /// The running total of the faces' areas, for choosing a face by its area.
+    cumulative_areas: Vec<f32>,
+}
+
+impl SurfaceEmitter {
+    /// Faces without any area are skipped, as they have no normal to emit along.
+    pub fn new(faces: impl IntoIterator<Item = Face>) -> SurfaceEmitter {
+        let faces: Vec<Face> = faces.into_iter().filter(|face| face.area() > 0.0).collect();
+        let cumulative_areas = faces
+            .iter()
+            .scan(0.0, |total, face| {
+                *total += face.area();
+                Some(*total)
+            })
+            .collect();
+        SurfaceEmitter {
+            faces,
+            cumulative_areas,
+        }
+    }
+
+    /// The vertices and indices are as a ColoredMesh's, with each three indices forming a triangle.
+    pub fn from_indexed(
+        vertex_positions: &[Vector3<f32>],
+        vertex_indices: &[u32],
+    ) -> SurfaceEmitter {
+        SurfaceEmitter::new(vertex_indices.chunks_exact(3).map(|triangle| Face {
+            v0: vertex_positions[triangle[0] as usize],
+            v1: vertex_positions[triangle[1] as usize],
+            v2: vertex_positions[triangle[2] as usize],
+        }))
+    }
+
+    pub fn from_collidable_mesh(mesh: &CollidableMesh) -> SurfaceEmitter {
+        SurfaceEmitter::new(mesh.get_faces().iter().copied())
+    }
+
+    #[cfg(feature = "render")]
+    pub fn from_colored_mesh(mesh: &ColoredMesh) -> SurfaceEmitter {
+        SurfaceEmitter::from_indexed(&mesh.vertex_positions, &mesh.vertex_indices)
+    }
+
+    pub fn get_area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.0)
+    }
+
+    /// A point uniformly distributed over the surface, and the surface's normal there,
+    /// or None if the surface has no area.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let area = self.get_area();
+        if area <= 0.0 {
+            return None;
+        }
+        let target = rng.gen_range(0.0..area);
+        let index = self
+            .cumulative_areas
+            .partition_point(|total| *total <= target)
+            .min(self.faces.len() - 1);
+        let face = &self.faces[index];
+        // Taking the square root keeps the points from bunching up at v0.
+        let r1 = rng.gen::<f32>().sqrt();
+        let r2 = rng.gen::<f32>();
+        let position = face.v0 * (1.0 - r1) + face.v1 * (r1 * (1.0 - r2)) + face.v2 * (r1 * r2);
+        Some((position, face.normal()))
+    }
+}
+
 /// Generates particles in the plane defined by position, normal in a circular disk,
 /// with a uniform distribution.
 pub fn generate_particles(
@@ -18,6 +115,8 @@ pub fn generate_particles(
     lifetime: Range<Duration>,
     mass: Range<f32>,
     drag: Range<f32>,
+    // The temperature to spawn with, if the particles are temperature-driven.
+    temperature: Option<f32>,
 ) {
     let mut rng = rand::thread_rng();
     let non_parallel_vec = if cgmath::relative_eq!(normal.normalize(), Vector3::<f32>::unit_z()) {
@@ -33,12 +132,99 @@ pub fn generate_particles(
             + normal.cross(vec_in_plane) * f32::sin(angle)
             + normal * normal.dot(vec_in_plane) * (1.0 - f32::cos(angle));
         let gen_position = position + rotated_vec.normalize() * radius;
-        pool.create(
+        if let Some(particle) = pool.create(
             gen_position,
             normal * rng.gen_range(speed.start..=speed.end),
             rng.gen_range(lifetime.start..=lifetime.end),
             rng.gen_range(mass.start..=mass.end),
             rng.gen_range(drag.start..=drag.end),
-        );
+        ) {
+            particle.temperature = temperature;
+        }
+    }
+}
+
+/// Generates particles uniformly over the emitter's surface, moving along the surface's normal.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_particles_on_surface(
+    emitter: &SurfaceEmitter,
+    pool: &mut ParticlePool,
+    num_particles: u32,
+    // Speed in direction of the surface's normal to spawn with.
+    speed: Range<f32>,
+    lifetime: Range<Duration>,
+    mass: Range<f32>,
+    drag: Range<f32>,
+    // The temperature to spawn with, if the particles are temperature-driven.
+    temperature: Option<f32>,
+) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..num_particles {
+        let (position, normal) = match emitter.sample(&mut rng) {
+            Some(sample) => sample,
+            None => return,
+        };
+        if let Some(particle) = pool.create(
+            position + normal * SURFACE_OFFSET,
+            normal * rng.gen_range(speed.start..=speed.end),
+            rng.gen_range(lifetime.start..=lifetime.end),
+            rng.gen_range(mass.start..=mass.end),
+            rng.gen_range(drag.start..=drag.end),
+        ) {
+            particle.temperature = temperature;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::geometry;
+    use rand::SeedableRng;
+
+    #[test]
+    fn samples_cube_surface() {
+        let (positions, indices) = geometry::get_cube_vertices();
+        let indices: Vec<u32> = indices.into_iter().map(|i| i as u32).collect();
+        let emitter = SurfaceEmitter::from_indexed(&positions, &indices);
+        assert!((emitter.get_area() - 6.0).abs() < 1e-4);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let (position, normal) = emitter.sample(&mut rng).unwrap();
+            let extent = position.x.abs().max(position.y.abs()).max(position.z.abs());
+            assert!((extent - 0.5).abs() < 1e-4, "{:?}", position);
+            // The cube's faces wind outwards.
+            assert!(normal.dot(position) > 0.0);
+        }
+    }
+
+    #[test]
+    fn samples_by_area() {
+        let small = Face {
+            v0: Vector3::new(0.0, 0.0, 0.0),
+            v1: Vector3::new(1.0, 0.0, 0.0),
+            v2: Vector3::new(0.0, 1.0, 0.0),
+        };
+        // Three times the area of the small face.
+        let large = Face {
+            v0: Vector3::new(0.0, 0.0, 1.0),
+            v1: Vector3::new(3.0, 0.0, 1.0),
+            v2: Vector3::new(0.0, 1.0, 1.0),
+        };
+        let degenerate = Face {
+            v0: Vector3::new(0.0, 0.0, 2.0),
+            v1: Vector3::new(1.0, 0.0, 2.0),
+            v2: Vector3::new(2.0, 0.0, 2.0),
+        };
+        let emitter = SurfaceEmitter::new([small, large, degenerate]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let samples = 10000;
+        let on_large = (0..samples)
+            .filter(|_| emitter.sample(&mut rng).unwrap().0.z > 0.5)
+            .count();
+        let fraction = on_large as f32 / samples as f32;
+        assert!((fraction - 0.75).abs() < 0.02, "{}", fraction);
+
+        assert!(SurfaceEmitter::new([degenerate]).sample(&mut rng).is_none());
     }
 }
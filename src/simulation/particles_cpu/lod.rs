@@ -0,0 +1,291 @@
+use super::particle::{Particle, ParticlePool};
+use super::particles::Config;
+
+use cgmath::{InnerSpace, Vector3};
+use rand::Rng;
+use rustc_hash::FxHashMap;
+
+use std::time::Duration;
+
+/// Super-particles are split once they're this fraction of the LOD distance from the viewpoint,
+/// so that particles near the LOD distance don't alternate between merging and splitting.
+pub const SPLIT_HYSTERESIS: f32 = 0.9;
+
+/// The cell of the LOD grid a particle is merged within: whether it's smoke, so that smoke isn't merged
+/// with the particles which burn out into it, whether it was sub-emitted, so that bursts aren't merged
+/// with the particles which burst, the distance band from the viewpoint, and the particle's cell in that band's grid.
+type CellKey = (bool, bool, i32, i32, i32, i32);
+
+/// Merges the pool's particles further than the LOD distance from the viewpoint into super-particles.
+/// Particles are merged with the others in their cell of a grid whose cells double in size with each
+/// doubling of distance, so that each cell subtends roughly the LOD error (in radians) from the viewpoint.
+/// Super-particles keep the total mass, drag, and momentum of the particles they represent, so they
+/// move as their particles would have on average.
+pub fn merge(pool: &mut ParticlePool, viewpoint: Vector3<f32>, config: &Config) {
+    let mut representatives: FxHashMap<CellKey, usize> = FxHashMap::default();
+    for index in 0..pool.particles.len() {
+        let particle = pool.particles[index];
+        if !particle.in_use() {
+            continue;
+        }
+        let Some(key) = cell_key(&particle, viewpoint, config) else {
+            continue;
+        };
+        match representatives.get(&key) {
+            Some(&representative)
+                if pool.particles[representative].count + particle.count
+                    <= config.lod_max_count =>
+            {
+                absorb(&mut pool.particles[representative], &particle);
+                pool.particles[index].lifetime = Duration::ZERO;
+            }
+            // The representative is full, so this particle represents the cell from now on.
+            _ => {
+                representatives.insert(key, index);
+            }
+        }
+    }
+}
+
+/// Splits super-particles within the split distance of the viewpoint back into the particles
+/// they represent, scattered within the super-particle's spread.
+/// A super-particle is only split if the pool has enough free particles for its detail.
+pub fn split(pool: &mut ParticlePool, viewpoint: Vector3<f32>, split_distance: f32) {
+    let mut free = pool
+        .particles
+        .iter()
+        .filter(|particle| !particle.in_use())
+        .count();
+    let mut rng = rand::thread_rng();
+    for index in 0..pool.particles.len() {
+        let particle = pool.particles[index];
+        if !particle.in_use()
+            || particle.count <= 1
+            || (particle.position - viewpoint).magnitude() >= split_distance
+        {
+            continue;
+        }
+        let new_particles = (particle.count - 1) as usize;
+        if new_particles > free {
+            continue;
+        }
+        free -= new_particles;
+
+        let mass = particle.mass / particle.count as f32;
+        let drag = particle.drag / particle.count as f32;
+        // Particles uniformly distributed in a ball of this radius have the super-particle's spread.
+        let radius = particle.spread * (5.0_f32 / 3.0).sqrt();
+        for child in 0..particle.count {
+            let position = particle.position + random_in_ball(&mut rng) * radius;
+            let child = if child == 0 {
+                pool.particles[index].init(
+                    position,
+                    particle.velocity,
+                    particle.lifetime,
+                    mass,
+                    drag,
+                );
+                Some(&mut pool.particles[index])
+            } else {
+                pool.create(position, particle.velocity, particle.lifetime, mass, drag)
+            };
+            if let Some(child) = child {
+                child.temperature = particle.temperature;
+                child.smoke = particle.smoke;
+                child.age = particle.age;
+                child.sub_emitted = particle.sub_emitted;
+            }
+        }
+    }
+}
+
+/// Gets the LOD cell the particle is merged within, or None if it's near enough to the viewpoint
+/// to be simulated in full.
+fn cell_key(particle: &Particle, viewpoint: Vector3<f32>, config: &Config) -> Option<CellKey> {
+    let position = particle.position;
+    let distance = (position - viewpoint).magnitude();
+    if distance < config.lod_distance {
+        return None;
+    }
+    let band = (distance / config.lod_distance).log2().floor() as i32;
+    let cell_size = config.lod_error * config.lod_distance * 2.0_f32.powi(band);
+    Some((
+        particle.smoke,
+        particle.sub_emitted,
+        band,
+        (position.x / cell_size).floor() as i32,
+        (position.y / cell_size).floor() as i32,
+        (position.z / cell_size).floor() as i32,
+    ))
+}
+
+/// Merges the particle into the super-particle, conserving mass and momentum, and heat if they're temperature-driven.
+fn absorb(super_particle: &mut Particle, particle: &Particle) {
+    let mass = super_particle.mass + particle.mass;
+    let position =
+        (super_particle.position * super_particle.mass + particle.position * particle.mass) / mass;
+    let velocity =
+        (super_particle.velocity * super_particle.mass + particle.velocity * particle.mass) / mass;
+    // The combined variance of the positions about the new center.
+    let variance = (super_particle.mass
+        * (super_particle.spread.powi(2) + (super_particle.position - position).magnitude2())
+        + particle.mass * (particle.spread.powi(2) + (particle.position - position).magnitude2()))
+        / mass;
+    let count = super_particle.count + particle.count;
+    let lifetime = (super_particle.lifetime * super_particle.count
+        + particle.lifetime * particle.count)
+        / count;
+    let age = (super_particle.age * super_particle.count as f32
+        + particle.age * particle.count as f32)
+        / count as f32;
+    let temperature = match (super_particle.temperature, particle.temperature) {
+        (Some(super_temperature), Some(temperature)) => {
+            Some((super_temperature * super_particle.mass + temperature * particle.mass) / mass)
+        }
+        (temperature, None) | (None, temperature) => temperature,
+    };
+
+    super_particle.position = position;
+    super_particle.velocity = velocity;
+    super_particle.mass = mass;
+    super_particle.drag += particle.drag;
+    super_particle.spread = variance.sqrt();
+    super_particle.lifetime = lifetime;
+    super_particle.count = count;
+    super_particle.age = age;
+    super_particle.temperature = temperature;
+}
+
+/// Gets a point uniformly distributed in the unit ball.
+fn random_in_ball(rng: &mut impl Rng) -> Vector3<f32> {
+    loop {
+        let point = Vector3::new(
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+        );
+        if point.magnitude2() <= 1.0 {
+            return point;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Zero;
+
+    fn lod_config() -> Config {
+        Config {
+            lod_enabled: true,
+            lod_distance: 10.0,
+            lod_error: 0.1,
+            lod_max_count: 100,
+            ..Default::default()
+        }
+    }
+
+    fn pool_with(particles: &[(Vector3<f32>, Vector3<f32>)]) -> ParticlePool {
+        let mut pool = ParticlePool::new();
+        for (position, velocity) in particles {
+            pool.create(*position, *velocity, Duration::from_secs(1), 1.0, 0.5);
+        }
+        pool
+    }
+
+    fn active(pool: &ParticlePool) -> Vec<Particle> {
+        pool.particles
+            .iter()
+            .filter(|particle| particle.in_use())
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn merge_conserves_mass_and_momentum() {
+        let mut pool = pool_with(&[
+            (Vector3::new(20.1, 0.1, 0.1), Vector3::new(1.0, 0.0, 0.0)),
+            (Vector3::new(20.3, 0.1, 0.1), Vector3::new(0.0, 2.0, 0.0)),
+            (Vector3::new(20.2, 0.3, 0.1), Vector3::new(0.0, 0.0, 3.0)),
+        ]);
+        merge(&mut pool, Vector3::zero(), &lod_config());
+
+        let particles = active(&pool);
+        assert_eq!(particles.len(), 1);
+        let super_particle = particles[0];
+        assert_eq!(super_particle.count, 3);
+        assert_eq!(super_particle.mass, 3.0);
+        assert_eq!(super_particle.drag, 1.5);
+        assert!(
+            (super_particle.velocity * super_particle.mass - Vector3::new(1.0, 2.0, 3.0))
+                .magnitude()
+                < 1e-5
+        );
+        assert!(super_particle.spread > 0.0);
+    }
+
+    #[test]
+    fn near_particles_are_not_merged() {
+        let mut pool = pool_with(&[
+            (Vector3::new(1.0, 0.0, 0.0), Vector3::zero()),
+            (Vector3::new(1.0, 0.0, 0.0), Vector3::zero()),
+        ]);
+        merge(&mut pool, Vector3::zero(), &lod_config());
+        assert_eq!(active(&pool).len(), 2);
+    }
+
+    #[test]
+    fn merge_respects_max_count() {
+        let position = Vector3::new(20.1, 0.1, 0.1);
+        let mut pool = pool_with(&[(position, Vector3::zero()); 5]);
+        let config = Config {
+            lod_max_count: 2,
+            ..lod_config()
+        };
+        merge(&mut pool, Vector3::zero(), &config);
+        let counts: Vec<u32> = active(&pool)
+            .iter()
+            .map(|particle| particle.count)
+            .collect();
+        assert_eq!(counts, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn smoke_is_merged_apart() {
+        let position = Vector3::new(20.1, 0.1, 0.1);
+        let mut pool = pool_with(&[(position, Vector3::zero()); 4]);
+        for (index, particle) in pool.particles.iter_mut().take(4).enumerate() {
+            particle.temperature = Some(1000.0 + 200.0 * index as f32);
+            particle.smoke = index >= 2;
+        }
+        merge(&mut pool, Vector3::zero(), &lod_config());
+        let particles = active(&pool);
+        assert_eq!(particles.len(), 2);
+        for particle in particles {
+            let expected = if particle.smoke { 1500.0 } else { 1100.0 };
+            assert_eq!(particle.temperature, Some(expected));
+        }
+    }
+
+    #[test]
+    fn split_restores_particles() {
+        let mut pool = pool_with(&[
+            (Vector3::new(20.1, 0.1, 0.1), Vector3::new(1.0, 0.0, 0.0)),
+            (Vector3::new(20.3, 0.1, 0.1), Vector3::new(1.0, 0.0, 0.0)),
+        ]);
+        merge(&mut pool, Vector3::zero(), &lod_config());
+        let spread = active(&pool)[0].spread;
+
+        // Approaching the super-particle splits it.
+        let viewpoint = Vector3::new(20.0, 0.0, 0.0);
+        split(&mut pool, viewpoint, 9.0);
+        let particles = active(&pool);
+        assert_eq!(particles.len(), 2);
+        for particle in particles {
+            assert_eq!(particle.count, 1);
+            assert_eq!(particle.mass, 1.0);
+            assert_eq!(particle.velocity, Vector3::new(1.0, 0.0, 0.0));
+            assert!((particle.position - Vector3::new(20.2, 0.1, 0.1)).magnitude() <= spread * 1.3);
+        }
+    }
+}
@@ -1,4 +1,10 @@
+pub mod budget;
+pub mod curl_noise;
+pub mod fire;
 pub mod generator;
+pub mod lod;
 pub mod obstacle;
 pub mod particle;
 pub mod particles;
+pub mod sub_emitter;
+pub mod swirl;
@@ -1,44 +1,33 @@
-use crate::graphics::model::ColoredMesh;
+use crate::simulation::collidable_mesh::{self, Face, Penetration, Sidedness};
+use crate::simulation::collision_filter::CollisionFilter;
 
-use cgmath::{InnerSpace, Vector3};
+use cgmath::{InnerSpace, Vector3, Zero};
 use itertools::Itertools;
 
-pub struct Tri {
-    v1: Vector3<f32>,
-    v2: Vector3<f32>,
-    v3: Vector3<f32>,
-}
-
-impl Tri {
-    pub fn normal(&self) -> Vector3<f32> {
-        (self.v2 - self.v1).cross(self.v3 - self.v1).normalize()
-    }
-
-    pub fn distance_from_plane(&self, point: cgmath::Vector3<f32>) -> f32 {
-        (point - self.v1).dot(self.normal())
-    }
-}
-
 pub struct Obstacle {
-    tris: Vec<Tri>,
+    tris: Vec<Face>,
     min_x: f32,
     max_x: f32,
     min_y: f32,
     max_y: f32,
     min_z: f32,
     max_z: f32,
+    collision_filter: CollisionFilter,
+    /// The velocity the obstacle's surface slides at, e.g. as a conveyor belt's does, though the obstacle stays in place.
+    surface_velocity: Vector3<f32>,
+    sidedness: Sidedness,
 }
 
 impl Obstacle {
-    pub fn new(mesh: &ColoredMesh) -> Obstacle {
+    pub fn new(vertex_positions: &[Vector3<f32>], vertex_indices: &[u32]) -> Obstacle {
         let mut min_x = f32::MAX;
         let mut max_x = f32::MIN;
         let mut min_y = f32::MAX;
         let mut max_y = f32::MIN;
         let mut min_z = f32::MAX;
         let mut max_z = f32::MIN;
-        for vertex_index in mesh.vertex_indices.iter() {
-            let v = mesh.vertex_positions[*vertex_index as usize];
+        for vertex_index in vertex_indices.iter() {
+            let v = vertex_positions[*vertex_index as usize];
             min_x = min_x.min(v.x);
             max_x = max_x.max(v.x);
             min_y = min_y.min(v.y);
@@ -48,11 +37,12 @@ impl Obstacle {
         }
 
         let mut tris = vec![];
-        for (i1, i2, i3) in mesh.vertex_indices.iter().tuple_windows() {
-            let v1 = mesh.vertex_positions[*i1 as usize];
-            let v2 = mesh.vertex_positions[*i2 as usize];
-            let v3 = mesh.vertex_positions[*i3 as usize];
-            tris.push(Tri { v1, v2, v3 });
+        for (i0, i1, i2) in vertex_indices.iter().tuples() {
+            tris.push(Face {
+                v0: vertex_positions[*i0 as usize],
+                v1: vertex_positions[*i1 as usize],
+                v2: vertex_positions[*i2 as usize],
+            });
         }
         Obstacle {
             tris,
@@ -62,9 +52,38 @@ impl Obstacle {
             max_y,
             min_z,
             max_z,
+            collision_filter: CollisionFilter::default(),
+            surface_velocity: Vector3::zero(),
+            sidedness: Sidedness::TwoSided,
+        }
+    }
+
+    pub fn get_collision_filter(&self) -> &CollisionFilter {
+        &self.collision_filter
+    }
+
+    pub fn set_surface_velocity(&mut self, surface_velocity: Vector3<f32>) {
+        self.surface_velocity = surface_velocity;
+    }
+
+    pub fn set_sidedness(&mut self, sidedness: Sidedness) {
+        self.sidedness = sidedness;
+    }
+
+    /// Where the particle's position penetrates the obstacle, if it's one-sided and the particle is inside it.
+    pub fn get_penetration(&self, position: Vector3<f32>) -> Option<Penetration<'_>> {
+        match self.sidedness {
+            Sidedness::OneSided => collidable_mesh::find_penetration(self.tris.iter(), position),
+            Sidedness::TwoSided => None,
         }
     }
 
+    /// The velocity the surface slides at along the tri, which friction drags particles touching it towards.
+    pub fn get_surface_velocity(&self, tri: &Face) -> Vector3<f32> {
+        let normal = tri.normal();
+        self.surface_velocity - self.surface_velocity.dot(normal) * normal
+    }
+
     /// True if the position is in the bounds of the box.
     /// Useful for quick preliminary checks.
     /// Should call with the NEW position, not the old position.
@@ -85,15 +104,15 @@ impl Obstacle {
         old_velocity: Vector3<f32>,
         new_position: Vector3<f32>,
         dt: f32,
-    ) -> Option<&Tri> {
+    ) -> Option<&Face> {
         self.tris.iter().find(|tri| -> bool {
             // TODO share this with the springy mesh collision stuff.
-            let old_distance_to_plane = tri.distance_from_plane(old_position);
-            let new_distance_to_plane = tri.distance_from_plane(new_position);
-            // If the signs are different, the point has crossed the plane
-            let crossed_plane = old_distance_to_plane.is_sign_positive()
-                != new_distance_to_plane.is_sign_positive();
-            if !crossed_plane {
+            let old_distance_to_plane = tri.distance_from_plane(&old_position);
+            let new_distance_to_plane = tri.distance_from_plane(&new_position);
+            if !self
+                .sidedness
+                .crosses(old_distance_to_plane, new_distance_to_plane)
+            {
                 false
             } else {
                 // Get the point in the plane of the tri
@@ -107,23 +126,23 @@ impl Obstacle {
                     && tri.normal().x >= tri.normal().z
                 {
                     // Eliminate the x component of all the elements
-                    let v1_flat = Vector3::<f32>::new(0.0, tri.v1.y, tri.v1.z);
-                    let v2_flat = Vector3::<f32>::new(0.0, tri.v2.y, tri.v2.z);
-                    let v3_flat = Vector3::<f32>::new(0.0, tri.v3.y, tri.v3.z);
+                    let v1_flat = Vector3::<f32>::new(0.0, tri.v0.y, tri.v0.z);
+                    let v2_flat = Vector3::<f32>::new(0.0, tri.v1.y, tri.v1.z);
+                    let v3_flat = Vector3::<f32>::new(0.0, tri.v2.y, tri.v2.z);
                     let point_flat = Vector3::<f32>::new(0.0, collision_point.y, collision_point.z);
                     (v1_flat, v2_flat, v3_flat, point_flat)
                 } else if tri.normal().y >= tri.normal().x && tri.normal().y >= tri.normal().z {
                     // Eliminate the y component of all the elements
-                    let v1_flat = Vector3::<f32>::new(tri.v1.x, 0.0, tri.v1.z);
-                    let v2_flat = Vector3::<f32>::new(tri.v2.x, 0.0, tri.v2.z);
-                    let v3_flat = Vector3::<f32>::new(tri.v3.x, 0.0, tri.v3.z);
+                    let v1_flat = Vector3::<f32>::new(tri.v0.x, 0.0, tri.v0.z);
+                    let v2_flat = Vector3::<f32>::new(tri.v1.x, 0.0, tri.v1.z);
+                    let v3_flat = Vector3::<f32>::new(tri.v2.x, 0.0, tri.v2.z);
                     let point_flat = Vector3::<f32>::new(collision_point.x, 0.0, collision_point.z);
                     (v1_flat, v2_flat, v3_flat, point_flat)
                 } else {
                     // Eliminate the z component of all the elements
-                    let v1_flat = Vector3::<f32>::new(tri.v1.x, tri.v1.y, 0.0);
-                    let v2_flat = Vector3::<f32>::new(tri.v2.x, tri.v2.y, 0.0);
-                    let v3_flat = Vector3::<f32>::new(tri.v3.x, tri.v3.y, 0.0);
+                    let v1_flat = Vector3::<f32>::new(tri.v0.x, tri.v0.y, 0.0);
+                    let v2_flat = Vector3::<f32>::new(tri.v1.x, tri.v1.y, 0.0);
+                    let v3_flat = Vector3::<f32>::new(tri.v2.x, tri.v2.y, 0.0);
                     let point_flat = Vector3::<f32>::new(collision_point.x, collision_point.y, 0.0);
                     (v1_flat, v2_flat, v3_flat, point_flat)
                 };
@@ -14,8 +14,8 @@ impl ParticlePool {
         ParticlePool { particles }
     }
 
-    /// Activates a particle in the pool and initializes to values.
-    /// If there are no free particles in the pool, does nothing.
+    /// Activates a particle in the pool and initializes to values, returning it.
+    /// If there are no free particles in the pool, does nothing and returns None.
     /// Note that this means that we shouldn't overflow the instance buffer ever, since
     /// the pool is made with a size that fits within the buffer!
     /// TODO: Use a free list instead of searching for first unused particle.
@@ -26,13 +26,19 @@ impl ParticlePool {
         lifetime: std::time::Duration,
         mass: f32,
         drag: f32,
-    ) {
-        for particle in self.particles.iter_mut() {
-            if !particle.in_use() {
-                particle.init(position, velocity, lifetime, mass, drag);
-                return;
-            }
-        }
+    ) -> Option<&mut Particle> {
+        let particle = self
+            .particles
+            .iter_mut()
+            .find(|particle| !particle.in_use())?;
+        particle.init(position, velocity, lifetime, mass, drag);
+        Some(particle)
+    }
+}
+
+impl Default for ParticlePool {
+    fn default() -> Self {
+        ParticlePool::new()
     }
 }
 
@@ -43,6 +49,19 @@ pub struct Particle {
     pub lifetime: std::time::Duration,
     pub mass: f32,
     pub drag: f32,
+    /// The number of particles this particle represents; more than one if distant particles
+    /// were merged into it for level of detail.
+    pub count: u32,
+    /// The RMS distance of the represented particles from the position.
+    pub spread: f32,
+    /// The particle's temperature in kelvin, if it's temperature-driven, e.g. burning.
+    pub temperature: Option<f32>,
+    /// Whether the particle is smoke, which a burning particle burnt out into.
+    pub smoke: bool,
+    /// Seconds since the particle was created.
+    pub age: f32,
+    /// Whether the particle was spawned by a sub-emitter's burst, rather than the generator.
+    pub sub_emitted: bool,
 }
 
 impl Particle {
@@ -59,6 +78,12 @@ impl Particle {
         self.lifetime = lifetime;
         self.mass = mass;
         self.drag = drag;
+        self.count = 1;
+        self.spread = 0.0;
+        self.temperature = None;
+        self.smoke = false;
+        self.age = 0.0;
+        self.sub_emitted = false;
     }
 
     pub fn in_use(&self) -> bool {
@@ -74,6 +99,12 @@ impl Default for Particle {
             lifetime: Duration::ZERO,
             mass: 0.0,
             drag: 0.0,
+            count: 1,
+            spread: 0.0,
+            temperature: None,
+            smoke: false,
+            age: 0.0,
+            sub_emitted: false,
         }
     }
 }
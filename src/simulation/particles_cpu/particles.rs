@@ -1,29 +1,52 @@
-use crate::{
-    graphics::entity::ColoredMeshEntity, graphics::forms, graphics::gpu_interface::GPUInterface,
-    graphics::instance::Instance, graphics::model::ColoredMesh, gui,
+#[cfg(feature = "render")]
+use crate::graphics::{
+    entity::ColoredMeshEntity, forms, gpu_interface::GPUInterface, instance::Instance, palette,
 };
-
-use super::generator;
+use crate::simulation::collidable_mesh::Sidedness;
+use crate::simulation::configurable::Configurable;
+
+use super::budget::{StepBudget, StepBudgetConfig};
+use super::curl_noise::CurlNoiseConfig;
+use super::fire::FireConfig;
+use super::generator::{self, EmitterShape, SurfaceEmitter};
+use super::lod;
 use super::obstacle::Obstacle;
+use super::sub_emitter::SubEmitterConfig;
+use super::swirl::SwirlConfig;
 
-use cgmath::{InnerSpace, Rotation3, Vector3, Zero};
+#[cfg(feature = "render")]
+use cgmath::Rotation3;
+use cgmath::{InnerSpace, Vector3, Zero};
+use rand::Rng;
 use std::{ops::Range, time::Duration};
 
-use super::particle::ParticlePool;
+use super::particle::{Particle, ParticlePool};
+use crate::simulation::collision_filter::CollisionFilter;
+use crate::simulation::fan::{self, Fan, FanConfig};
+use crate::simulation::force::{self, Body, ForceGenerator};
+use crate::simulation::planar::PlanarConstraint;
+use crate::simulation::trigger::{Trigger, TriggerAction, TriggerEvent};
+use crate::simulation::units::GravityPreset;
 
 pub const MAX_INSTANCES: usize = 5000;
 
 const EPSILON: f32 = 0.001;
 
-/// TODO:
-/// We should add colors to our particles. We can do that by adding color information to IntanceRaw,
-/// and handling that in the shader instead of using our colored mesh's color. The colored mesh color
-/// will only be used to inform the default instance color.
-///
-/// a vortex would be pretty easy to add. Its strength could be from 0 to some large value.
-/// We just apply a circular force around the y axis, proportional to the distance
-/// from the center (stronger when closer up to some cap).
+/// The simulated time between merging and splitting particles for level of detail.
+const LOD_INTERVAL: f32 = 0.05;
+
+#[cfg(feature = "render")]
+/// The scale of a particle's instance; super-particles are scaled up to the volume of
+/// the particles they represent.
+const PARTICLE_SCALE: f32 = 0.05;
+
+/// The color of smoke particles.
+#[cfg(feature = "render")]
+const SMOKE_COLOR: palette::Color = [0.3, 0.3, 0.3];
+/// The greatest random velocity smoke particles are given as they're burnt out into.
+const SMOKE_SPREAD: f32 = 0.2;
 
+#[derive(Clone, PartialEq)]
 pub struct Config {
     pub dt: f32, // secs as f32
     pub particles_generated_per_step: u32,
@@ -37,12 +60,43 @@ pub struct Config {
     pub particles_drag_range: f32,
     pub acceleration_gravity: Vector3<f32>,
     pub wind: cgmath::Vector3<f32>,
+    /// The fan blowing on the particles, if any.
+    pub fan: Option<FanConfig>,
     pub coefficient_of_restitution: f32,
     pub coefficient_of_friction: f32,
-    pub y_axis_attractor_gravity: f32,
+    /// The velocity the obstacle's surface slides at, carrying particles resting on it along like a conveyor belt.
+    pub obstacle_surface_velocity: Vector3<f32>,
+    /// Which sides of the obstacle's faces particles bounce off. Particles inside a one-sided obstacle are
+    /// pushed out of it.
+    pub obstacle_sidedness: Sidedness,
+    /// Makes the generated particles burn, if set, rising as they cool and burning out into smoke.
+    pub fire: Option<FireConfig>,
+    /// Bursts the generated particles into more particles as they die or reach an age, if set, e.g. fireworks.
+    pub sub_emitter: Option<SubEmitterConfig>,
+    /// Swirls the particles around an axis and pulls them towards it, if set.
+    pub swirl: Option<SwirlConfig>,
+    /// Adds a turbulent wind of evolving eddies to the wind, if set.
+    pub curl_noise: Option<CurlNoiseConfig>,
+    /// Where the particles are emitted from. The generator's radius, position, and normal only apply to disks.
+    pub emitter: EmitterShape,
     pub generator_radius: f32,
     pub generator_position: Vector3<f32>,
     pub generator_normal: Vector3<f32>,
+    /// The collision groups the particles collide with.
+    pub particles_collision_mask: u32,
+    /// Whether particles far from the viewpoint are merged into super-particles.
+    pub lod_enabled: bool,
+    /// The distance from the viewpoint beyond which particles are merged.
+    pub lod_distance: f32,
+    /// The angle, in radians, the cells particles are merged within subtend from the viewpoint.
+    /// Larger errors merge more particles.
+    pub lod_error: f32,
+    /// The most particles a super-particle may represent.
+    pub lod_max_count: u32,
+    /// Throttles the generator to keep each step within a budget of milliseconds, if set.
+    pub step_budget: Option<StepBudgetConfig>,
+    /// Holds the particles in a plane, if set.
+    pub planar: Option<PlanarConstraint>,
 }
 
 impl Default for Config {
@@ -58,14 +112,28 @@ impl Default for Config {
             particles_mass_range: 0.0,
             particles_drag_mean: 0.5,
             particles_drag_range: 0.0,
-            acceleration_gravity: Vector3::<f32>::new(0.0, -10.0, 0.0),
+            acceleration_gravity: GravityPreset::Earth.vector(),
             wind: Vector3::<f32>::zero(),
+            fan: None,
             coefficient_of_restitution: 0.95,
             coefficient_of_friction: 0.3,
-            y_axis_attractor_gravity: 0.0,
+            obstacle_surface_velocity: Vector3::<f32>::zero(),
+            obstacle_sidedness: Sidedness::TwoSided,
+            fire: None,
+            sub_emitter: None,
+            swirl: None,
+            curl_noise: None,
+            emitter: EmitterShape::Disk,
             generator_radius: 1.0,
             generator_position: Vector3::<f32>::unit_y() * 2.0,
             generator_normal: Vector3::<f32>::unit_y(),
+            particles_collision_mask: CollisionFilter::ALL,
+            lod_enabled: false,
+            lod_distance: 8.0,
+            lod_error: 0.05,
+            lod_max_count: 50,
+            step_budget: None,
+            planar: None,
         }
     }
 }
@@ -74,24 +142,89 @@ pub struct Simulation {
     config: Config,
     particles: ParticlePool,
     obstacle: Obstacle,
+    /// Emits particles from the obstacle's surface.
+    surface_emitter: SurfaceEmitter,
+    triggers: Vec<Trigger>,
+    fan: Option<Fan>,
+    force_generators: Vec<Box<dyn ForceGenerator>>,
+    /// The position the particles' level of detail is relative to, e.g. the camera's.
+    viewpoint: Vector3<f32>,
+    lod_timer: f32,
+    step_budget: StepBudget,
+    /// The seconds simulated so far, which the turbulence evolves with.
+    time: f32,
 }
 
 impl Simulation {
-    pub fn new(obstacle: &ColoredMesh) -> Simulation {
+    /// Particles collide with the obstacle, given by its vertices and triangle indices.
+    pub fn new(obstacle_positions: &[Vector3<f32>], obstacle_indices: &[u32]) -> Simulation {
         let config = Config::default();
 
         let particles = ParticlePool::new();
 
-        let obstacle = Obstacle::new(&obstacle);
+        let obstacle = Obstacle::new(obstacle_positions, obstacle_indices);
+        let surface_emitter = SurfaceEmitter::from_indexed(obstacle_positions, obstacle_indices);
 
         Simulation {
             config,
             particles,
             obstacle,
+            surface_emitter,
+            triggers: Vec::new(),
+            fan: None,
+            force_generators: Vec::new(),
+            viewpoint: Vector3::zero(),
+            lod_timer: 0.0,
+            step_budget: StepBudget::new(),
+            time: 0.0,
         }
     }
 
+    /// Adds a trigger volume, returning its index.
+    pub fn add_trigger(&mut self, trigger: Trigger) -> usize {
+        self.triggers.push(trigger);
+        self.triggers.len() - 1
+    }
+
+    pub fn get_triggers(&self) -> &[Trigger] {
+        &self.triggers
+    }
+
+    /// The force fields pushing the particles, besides the configured gravity, wind, and swirl.
+    pub fn get_force_generators_mut(&mut self) -> &mut Vec<Box<dyn ForceGenerator>> {
+        &mut self.force_generators
+    }
+
+    /// Sets the position the particles' level of detail is relative to, e.g. the camera's.
+    pub fn set_viewpoint(&mut self, viewpoint: Vector3<f32>) {
+        self.viewpoint = viewpoint;
+    }
+
+    /// Gets the moving average of the step time in milliseconds, and the fraction of the configured
+    /// particles per step being generated, if the step budget is set.
+    pub fn get_step_budget_stats(&self) -> Option<(f32, f32)> {
+        self.config.step_budget?;
+        Some((
+            self.step_budget.get_step_milliseconds()?,
+            self.step_budget.get_emission_scale(),
+        ))
+    }
+
+    /// Gets the number of particles simulated, and the number of particles they represent.
+    /// These differ when distant particles are merged for level of detail.
+    pub fn get_particle_counts(&self) -> (usize, u32) {
+        self.particles
+            .particles
+            .iter()
+            .filter(|particle| particle.in_use())
+            .fold((0, 0), |(simulated, represented), particle| {
+                (simulated + 1, represented + particle.count)
+            })
+    }
+
     pub fn step(&mut self) -> std::time::Duration {
+        let start = std::time::Instant::now();
+
         // TODO we want a way to generate fewer particles, maybe tying it "number generated per second".
         //   Right now we just get to max very quickly, so it generates in waves.
 
@@ -114,61 +247,130 @@ impl Simulation {
             0.0_f32.max(self.config.particles_drag_mean - self.config.particles_drag_range);
         let max_drag = self.config.particles_drag_mean + self.config.particles_drag_range;
 
-        generator::generate_particles(
-            self.config.generator_position,
-            self.config.generator_normal,
-            self.config.generator_radius,
-            &mut self.particles,
-            self.config.particles_generated_per_step,
-            Range {
-                start: (self.config.particles_initial_speed_mean
-                    - self.config.particles_initial_speed_range),
-                end: (self.config.particles_initial_speed_mean
-                    + self.config.particles_initial_speed_range),
-            },
-            Range {
-                start: min_lifetime,
-                end: max_lifetime,
-            },
-            Range {
-                start: min_mass,
-                end: max_mass,
-            },
-            Range {
-                start: min_drag,
-                end: max_drag,
-            },
+        let speed = Range {
+            start: (self.config.particles_initial_speed_mean
+                - self.config.particles_initial_speed_range),
+            end: (self.config.particles_initial_speed_mean
+                + self.config.particles_initial_speed_range),
+        };
+        let lifetime = Range {
+            start: min_lifetime,
+            end: max_lifetime,
+        };
+        let mass = Range {
+            start: min_mass,
+            end: max_mass,
+        };
+        let drag = Range {
+            start: min_drag,
+            end: max_drag,
+        };
+        let temperature = self.config.fire.map(|fire| fire.initial_temperature);
+        let particles_generated = match self.config.step_budget {
+            Some(_) => self
+                .step_budget
+                .emission(self.config.particles_generated_per_step),
+            None => self.config.particles_generated_per_step,
+        };
+        match self.config.emitter {
+            EmitterShape::Disk => generator::generate_particles(
+                self.config.generator_position,
+                self.config.generator_normal,
+                self.config.generator_radius,
+                &mut self.particles,
+                particles_generated,
+                speed,
+                lifetime,
+                mass,
+                drag,
+                temperature,
+            ),
+            EmitterShape::ObstacleSurface => generator::generate_particles_on_surface(
+                &self.surface_emitter,
+                &mut self.particles,
+                particles_generated,
+                speed,
+                lifetime,
+                mass,
+                drag,
+                temperature,
+            ),
+        }
+
+        let particles_collision_filter = CollisionFilter::new(
+            CollisionFilter::PARTICLE,
+            self.config.particles_collision_mask,
         );
+        let collides_with_obstacle =
+            particles_collision_filter.collides_with(self.obstacle.get_collision_filter());
+
+        if let Some(fan) = &mut self.fan {
+            fan.advance(self.config.dt);
+        }
 
-        for particle in self.particles.particles.iter_mut() {
+        let fire = self.config.fire;
+        let mut burnt_out = Vec::new();
+        let sub_emitter = self.config.sub_emitter;
+        let mut bursting = Vec::new();
+
+        for (index, particle) in self.particles.particles.iter_mut().enumerate() {
             // TODO rather than manually checking this here, the pool
             //  should offer an iterator over the active particles.
             if !particle.in_use() {
                 continue;
             }
 
+            let wind = self
+                .triggers
+                .iter()
+                .filter(|trigger| trigger.contains(index))
+                .fold(self.config.wind, |wind, trigger| {
+                    match trigger.get_action() {
+                        TriggerAction::Wind(trigger_wind) => wind + trigger_wind,
+                        _ => wind,
+                    }
+                });
+            let wind = match &self.fan {
+                Some(fan) => wind + fan.get_wind(particle.position),
+                None => wind,
+            };
+            let wind = match self.config.curl_noise {
+                Some(curl_noise) => wind + curl_noise.velocity(particle.position, self.time),
+                None => wind,
+            };
+
             // Calculate acceleration of particle from forces
             let acceleration_air_resistance =
                 -1.0 * particle.drag * particle.velocity * particle.velocity.magnitude()
                     / particle.mass;
 
-            let acceleration_wind =
-                particle.drag * self.config.wind * self.config.wind.magnitude() / particle.mass;
+            let acceleration_wind = particle.drag * wind * wind.magnitude() / particle.mass;
+
+            let acceleration_swirl = match self.config.swirl {
+                Some(swirl) => swirl.acceleration(particle.position, particle.velocity),
+                None => Vector3::zero(),
+            };
 
-            let center_line_unit_vec = Vector3::<f32>::unit_y();
-            let displacement_on_center_line = (particle.position - Vector3::<f32>::zero())
-                .dot(center_line_unit_vec)
-                * center_line_unit_vec;
-            let displacement_from_center_line =
-                (particle.position - Vector3::<f32>::zero()) - displacement_on_center_line;
-            let acceleration_gravity_center_line = self.config.y_axis_attractor_gravity
-                / displacement_from_center_line.magnitude().powi(2)
-                * displacement_from_center_line;
+            let acceleration_gravity = match (fire, particle.temperature) {
+                (Some(fire), Some(temperature)) => {
+                    fire.buoyancy(temperature, self.config.acceleration_gravity)
+                }
+                _ => self.config.acceleration_gravity,
+            };
 
-            let acceleration = self.config.acceleration_gravity
+            let body = Body {
+                position: particle.position,
+                velocity: particle.velocity,
+                mass: particle.mass,
+            };
+            let acceleration_generators =
+                force::sum(&self.force_generators, &body, self.time) / particle.mass;
+
+            let acceleration = acceleration_gravity
                 + acceleration_air_resistance
                 + acceleration_wind
-                + acceleration_gravity_center_line;
+                + acceleration_swirl
+                + acceleration_generators;
 
             let original_position = particle.position;
             let original_velocity = particle.velocity;
@@ -177,22 +379,23 @@ impl Simulation {
             let new_position = original_position + self.config.dt * original_velocity;
             let new_velocity = original_velocity + self.config.dt * acceleration;
 
-            let collided_tri_maybe = if self.obstacle.in_bounds(&new_position) {
-                self.obstacle.get_collided_tri(
-                    original_position,
-                    original_velocity,
-                    new_position,
-                    self.config.dt,
-                )
-            } else {
-                None
-            };
+            let collided_tri_maybe =
+                if collides_with_obstacle && self.obstacle.in_bounds(&new_position) {
+                    self.obstacle.get_collided_tri(
+                        original_position,
+                        original_velocity,
+                        new_position,
+                        self.config.dt,
+                    )
+                } else {
+                    None
+                };
 
             (particle.position, particle.velocity) = match collided_tri_maybe {
                 None => (new_position, new_velocity),
                 Some(tri) => {
-                    let old_distance_to_plane = tri.distance_from_plane(original_position);
-                    let new_distance_to_plane = tri.distance_from_plane(new_position);
+                    let old_distance_to_plane = tri.distance_from_plane(&original_position);
+                    let new_distance_to_plane = tri.distance_from_plane(&new_position);
 
                     // Get the point in the plane of the tri
                     let fraction_timestep =
@@ -203,18 +406,23 @@ impl Simulation {
                     let velocity_collision =
                         original_velocity + self.config.dt * fraction_timestep * acceleration;
 
-                    let new_position = collision_point + tri.normal() * EPSILON;
+                    // The particle's kept on the side of the tri it came from.
+                    let new_position =
+                        collision_point + tri.normal() * EPSILON * old_distance_to_plane.signum();
 
                     let velocity_collision_normal =
                         velocity_collision.dot(tri.normal()) * tri.normal();
-                    let velocity_collision_tangent = velocity_collision - velocity_collision_normal;
+                    // Friction acts on the velocity relative to the obstacle's sliding surface.
+                    let surface_velocity = self.obstacle.get_surface_velocity(tri);
+                    let velocity_collision_tangent =
+                        velocity_collision - velocity_collision_normal - surface_velocity;
 
                     let velocity_response_normal =
                         -1.0 * velocity_collision_normal * self.config.coefficient_of_restitution;
                     let velocity_response_tangent = if velocity_collision_tangent.is_zero() {
-                        velocity_collision_tangent
+                        surface_velocity
                     } else {
-                        velocity_collision_tangent
+                        surface_velocity + velocity_collision_tangent
                             - velocity_collision_tangent.normalize()
                                 * f32::min(
                                     self.config.coefficient_of_friction
@@ -228,6 +436,18 @@ impl Simulation {
                     (new_position, velocity_response)
                 }
             };
+            // Particles inside a one-sided obstacle, e.g. generated inside it, are pushed out through its nearest tri.
+            if collides_with_obstacle && self.obstacle.in_bounds(&particle.position) {
+                if let Some(penetration) = self.obstacle.get_penetration(particle.position) {
+                    let normal = penetration.face.normal();
+                    particle.position = penetration.resolve(particle.position);
+                    particle.velocity -= particle.velocity.dot(normal).min(0.0) * normal;
+                }
+            }
+            if let Some(planar) = &self.config.planar {
+                particle.position = planar.constrain_position(particle.position);
+                particle.velocity = planar.constrain_velocity(particle.velocity);
+            }
 
             particle.lifetime = match particle
                 .lifetime
@@ -236,76 +456,242 @@ impl Simulation {
                 None => Duration::ZERO,
                 Some(duration) => duration,
             };
+
+            if let (Some(fire), Some(temperature)) = (fire, particle.temperature) {
+                particle.temperature = Some(fire.cool(temperature, self.config.dt));
+                if !particle.in_use() && !particle.smoke {
+                    burnt_out.push(*particle);
+                }
+            }
+
+            let age = particle.age;
+            particle.age += self.config.dt;
+            if let Some(sub_emitter) = sub_emitter {
+                if !particle.smoke
+                    && !particle.sub_emitted
+                    && sub_emitter.bursts(age, particle.age, !particle.in_use())
+                {
+                    bursting.push(*particle);
+                }
+            }
+        }
+        if let Some(fire) = fire {
+            self.emit_smoke(&burnt_out, &fire);
+        }
+        if let Some(sub_emitter) = sub_emitter {
+            self.emit_bursts(&bursting, &sub_emitter);
+        }
+
+        self.time += self.config.dt;
+        self.update_triggers();
+        self.update_lod();
+
+        if let Some(step_budget) = &self.config.step_budget {
+            self.step_budget
+                .update(start.elapsed(), step_budget, &mut self.particles);
         }
 
         std::time::Duration::from_secs_f32(self.config.dt)
     }
 
-    pub fn get_particles_entity(&self, gpu: &GPUInterface) -> ColoredMeshEntity {
-        let mesh = forms::get_quad(&gpu.device, [1.0, 1.0, 1.0]);
-
-        let mut instances = Vec::<Instance>::new();
-        for particle in self.particles.particles.iter() {
-            if !particle.in_use() {
-                continue;
+    /// Replaces each burnt out particle with smoke, which keeps its heat and momentum.
+    fn emit_smoke(&mut self, burnt_out: &[Particle], fire: &FireConfig) {
+        let mut rng = rand::thread_rng();
+        for particle in burnt_out {
+            let count = fire.smoke_per_ember * particle.count;
+            for _ in 0..count {
+                let jitter = Vector3::new(
+                    rng.gen_range(-1.0..=1.0),
+                    rng.gen_range(-1.0..=1.0),
+                    rng.gen_range(-1.0..=1.0),
+                ) * SMOKE_SPREAD;
+                if let Some(smoke) = self.particles.create(
+                    particle.position,
+                    particle.velocity + jitter,
+                    Duration::from_secs_f32(fire.smoke_lifetime),
+                    particle.mass / count as f32,
+                    particle.drag / count as f32,
+                ) {
+                    smoke.temperature = particle.temperature;
+                    smoke.smoke = true;
+                }
             }
-            let instance = Instance {
-                position: particle.position,
-                // TODO this should be some Default.
-                rotation: cgmath::Quaternion::from_axis_angle(
-                    cgmath::Vector3::unit_z(),
-                    cgmath::Deg(0.0),
-                ),
-                scale: 0.05,
-            };
-            instances.push(instance);
         }
+    }
 
-        ColoredMeshEntity::new(&gpu, mesh, instances, Some(MAX_INSTANCES))
+    /// Bursts each particle into the sub-emitter's particles, which share its mass and drag and keep its heat,
+    /// until the sub-emitter's budget of live particles is spent. Super-particles burst into the same number
+    /// of particles as any other, each representing as many particles as the super-particle did.
+    fn emit_bursts(&mut self, bursting: &[Particle], sub_emitter: &SubEmitterConfig) {
+        let live = self
+            .particles
+            .particles
+            .iter()
+            .filter(|particle| particle.in_use() && particle.sub_emitted)
+            .map(|particle| particle.count)
+            .sum::<u32>();
+        let mut remaining = sub_emitter.budget.saturating_sub(live);
+        let mut rng = rand::thread_rng();
+        for particle in bursting {
+            for _ in 0..sub_emitter.burst_count {
+                if remaining < particle.count {
+                    return;
+                }
+                let velocity = sub_emitter.burst_velocity(particle.velocity, &mut rng);
+                match self.particles.create(
+                    particle.position,
+                    velocity,
+                    Duration::from_secs_f32(sub_emitter.lifetime),
+                    particle.mass / sub_emitter.burst_count as f32,
+                    particle.drag / sub_emitter.burst_count as f32,
+                ) {
+                    Some(spark) => {
+                        spark.count = particle.count;
+                        spark.temperature = particle.temperature;
+                        spark.sub_emitted = true;
+                        remaining -= particle.count;
+                    }
+                    // The pool is full.
+                    None => return,
+                }
+            }
+        }
     }
 
-    pub fn get_particles_instances(&self) -> Vec<Instance> {
-        let mut instances = Vec::<Instance>::new();
+    /// Splits super-particles which are near the viewpoint, and merges particles which are far from it,
+    /// every LOD interval. If LOD is disabled, splits all super-particles.
+    fn update_lod(&mut self) {
+        if !self.config.lod_enabled {
+            lod::split(&mut self.particles, self.viewpoint, f32::INFINITY);
+            return;
+        }
+        self.lod_timer += self.config.dt;
+        if self.lod_timer < LOD_INTERVAL {
+            return;
+        }
+        self.lod_timer = 0.0;
+        lod::split(
+            &mut self.particles,
+            self.viewpoint,
+            self.config.lod_distance * lod::SPLIT_HYSTERESIS,
+        );
+        lod::merge(&mut self.particles, self.viewpoint, &self.config);
+    }
 
-        for particle in self.particles.particles.iter() {
-            if !particle.in_use() {
-                continue;
+    fn update_triggers(&mut self) {
+        for trigger in self.triggers.iter_mut() {
+            let active_particles = self
+                .particles
+                .particles
+                .iter()
+                .enumerate()
+                .filter(|(_, particle)| particle.in_use())
+                .map(|(index, particle)| (index, particle.position));
+            let events = trigger.update(active_particles);
+            if trigger.get_action() == TriggerAction::Despawn {
+                for event in events {
+                    if let TriggerEvent::Enter(index) = event {
+                        self.particles.particles[index].lifetime = Duration::ZERO;
+                    }
+                }
             }
-            instances.push(Instance {
-                position: particle.position,
-                rotation: cgmath::Quaternion::from_axis_angle(
-                    cgmath::Vector3::unit_z(),
-                    cgmath::Deg(0.0),
-                ),
-                scale: 0.05,
-            });
         }
-        instances
+    }
+
+    #[cfg(feature = "render")]
+    pub fn get_particles_entity(&self, gpu: &GPUInterface) -> ColoredMeshEntity {
+        let mesh = forms::get_quad(&gpu.device, [1.0, 1.0, 1.0]);
+
+        ColoredMeshEntity::new(
+            &gpu,
+            mesh,
+            self.get_particles_instances(),
+            Some(MAX_INSTANCES),
+        )
+    }
+
+    /// Burning particles are colored by their temperature, and smoke is grey.
+    #[cfg(feature = "render")]
+    pub fn get_particles_instances(&self) -> Vec<Instance> {
+        self.particles
+            .particles
+            .iter()
+            .filter(|particle| particle.in_use())
+            .map(|particle| {
+                let scale = PARTICLE_SCALE * (particle.count as f32).cbrt();
+                let color = if particle.smoke {
+                    Some(SMOKE_COLOR)
+                } else {
+                    particle.temperature.map(palette::blackbody)
+                };
+                Instance {
+                    position: particle.position,
+                    rotation: cgmath::Quaternion::from_axis_angle(
+                        cgmath::Vector3::unit_z(),
+                        cgmath::Deg(0.0),
+                    ),
+                    scale: Vector3::new(scale, scale, scale),
+                    color,
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_fan(&self) -> Option<&Fan> {
+        self.fan.as_ref()
     }
 
     pub fn get_timestep(&self) -> std::time::Duration {
         std::time::Duration::from_secs_f32(self.config.dt)
     }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
 
-    pub fn sync_sim_config_from_ui(&mut self, ui: &mut gui::particles::ParticlesUi) {
-        let ui_config_state = ui.get_gui_state_mut();
-        self.config.dt = ui_config_state.dt;
-        self.config.particles_generated_per_step = ui_config_state.particles_generated_per_step;
-        self.config.acceleration_gravity = ui_config_state.acceleration_gravity;
-        self.config.wind = ui_config_state.wind;
-        self.config.coefficient_of_restitution = ui_config_state.coefficient_of_restitution;
-        self.config.coefficient_of_friction = ui_config_state.coefficient_of_friction;
-        self.config.y_axis_attractor_gravity = ui_config_state.y_axis_attractor_gravity;
-        self.config.particles_lifetime_mean = ui_config_state.particles_lifetime_mean;
-        self.config.particles_lifetime_range = ui_config_state.particles_lifetime_range;
-        self.config.particles_initial_speed_mean = ui_config_state.particles_initial_speed_mean;
-        self.config.particles_initial_speed_range = ui_config_state.particles_initial_speed_range;
-        self.config.particles_mass_mean = ui_config_state.particles_mass_mean;
-        self.config.particles_mass_range = ui_config_state.particles_mass_range;
-        self.config.particles_drag_mean = ui_config_state.particles_drag_mean;
-        self.config.particles_drag_range = ui_config_state.particles_drag_range;
-        self.config.generator_radius = ui_config_state.generator_radius;
-        self.config.generator_position = ui_config_state.generator_position;
-        self.config.generator_normal = ui_config_state.generator_normal;
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.dt = config.dt;
+        self.config.particles_generated_per_step = config.particles_generated_per_step;
+        self.config.acceleration_gravity = config.acceleration_gravity;
+        self.config.wind = config.wind;
+        self.config.fan = config.fan;
+        fan::sync(&mut self.fan, self.config.fan);
+        self.config.coefficient_of_restitution = config.coefficient_of_restitution;
+        self.config.coefficient_of_friction = config.coefficient_of_friction;
+        self.config.obstacle_surface_velocity = config.obstacle_surface_velocity;
+        self.obstacle
+            .set_surface_velocity(self.config.obstacle_surface_velocity);
+        self.config.obstacle_sidedness = config.obstacle_sidedness;
+        self.obstacle.set_sidedness(self.config.obstacle_sidedness);
+        self.config.fire = config.fire;
+        self.config.sub_emitter = config.sub_emitter;
+        self.config.swirl = config.swirl;
+        self.config.curl_noise = config.curl_noise;
+        self.config.particles_lifetime_mean = config.particles_lifetime_mean;
+        self.config.particles_lifetime_range = config.particles_lifetime_range;
+        self.config.particles_initial_speed_mean = config.particles_initial_speed_mean;
+        self.config.particles_initial_speed_range = config.particles_initial_speed_range;
+        self.config.particles_mass_mean = config.particles_mass_mean;
+        self.config.particles_mass_range = config.particles_mass_range;
+        self.config.particles_drag_mean = config.particles_drag_mean;
+        self.config.particles_drag_range = config.particles_drag_range;
+        self.config.emitter = config.emitter;
+        self.config.generator_radius = config.generator_radius;
+        self.config.generator_position = config.generator_position;
+        self.config.generator_normal = config.generator_normal;
+        self.config.particles_collision_mask = config.particles_collision_mask;
+        self.config.lod_enabled = config.lod_enabled;
+        self.config.lod_distance = config.lod_distance;
+        self.config.lod_error = config.lod_error;
+        self.config.lod_max_count = config.lod_max_count;
+        if config.step_budget.is_none() {
+            self.step_budget.reset();
+        }
+        self.config.step_budget = config.step_budget;
+        self.config.planar = config.planar;
     }
 }
@@ -0,0 +1,158 @@
+/// Sub-emitters, which spawn a burst of particles from each particle as it dies or reaches an age,
+/// e.g. firework rockets exploding into sparks. Particles spawned by a sub-emitter don't burst themselves,
+/// and the sub-emitter's budget caps how many of its particles are alive at once, so bursts can't grow
+/// without bound (nor crowd the generator's particles out of the pool).
+use cgmath::{InnerSpace, Vector3};
+use rand::Rng;
+
+/// When a particle bursts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurstTrigger {
+    /// As its lifetime ends.
+    Death,
+    /// As it reaches the sub-emitter's burst age, if it lives that long.
+    Age,
+}
+
+impl BurstTrigger {
+    pub const ALL: [BurstTrigger; 2] = [BurstTrigger::Death, BurstTrigger::Age];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BurstTrigger::Death => "On Death",
+            BurstTrigger::Age => "At Age",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SubEmitterConfig {
+    pub trigger: BurstTrigger,
+    /// Seconds. Only used by BurstTrigger::Age.
+    pub burst_age: f32,
+    /// The number of particles each particle bursts into.
+    pub burst_count: u32,
+    /// The speed the burst's particles leave the particle at, in uniformly random directions.
+    pub burst_speed: f32,
+    /// The fraction of the particle's velocity the burst's particles keep.
+    pub inherited_velocity: f32,
+    /// Seconds.
+    pub lifetime: f32,
+    /// The most particles of the sub-emitter's alive at once. Bursts beyond it are cut short.
+    pub budget: u32,
+}
+
+impl Default for SubEmitterConfig {
+    fn default() -> Self {
+        Self {
+            trigger: BurstTrigger::Death,
+            burst_age: 1.0,
+            burst_count: 40,
+            burst_speed: 3.0,
+            inherited_velocity: 0.3,
+            lifetime: 1.0,
+            budget: 2000,
+        }
+    }
+}
+
+impl SubEmitterConfig {
+    /// Whether a particle bursts during a step in which it aged from age_before to age_after,
+    /// and whether its lifetime ended.
+    pub fn bursts(&self, age_before: f32, age_after: f32, died: bool) -> bool {
+        match self.trigger {
+            BurstTrigger::Death => died,
+            BurstTrigger::Age => age_before < self.burst_age && self.burst_age <= age_after,
+        }
+    }
+
+    /// The velocity of one of the burst's particles, from a particle moving at the velocity.
+    pub fn burst_velocity(&self, velocity: Vector3<f32>, rng: &mut impl Rng) -> Vector3<f32> {
+        velocity * self.inherited_velocity + random_direction(rng) * self.burst_speed
+    }
+}
+
+/// Gets a unit vector uniformly distributed over the sphere.
+fn random_direction(rng: &mut impl Rng) -> Vector3<f32> {
+    loop {
+        let point = Vector3::new(
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+        );
+        let magnitude2: f32 = point.magnitude2();
+        if magnitude2 > 1e-6 && magnitude2 <= 1.0 {
+            return point / magnitude2.sqrt();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::configurable::Configurable;
+    use crate::simulation::particles_cpu::particles::{Config, Simulation};
+    use cgmath::Zero;
+    use rand::SeedableRng;
+
+    #[test]
+    fn bursts_on_trigger() {
+        let death = SubEmitterConfig::default();
+        assert!(death.bursts(0.5, 0.6, true));
+        assert!(!death.bursts(0.5, 0.6, false));
+
+        let age = SubEmitterConfig {
+            trigger: BurstTrigger::Age,
+            burst_age: 1.0,
+            ..Default::default()
+        };
+        assert!(!age.bursts(0.9, 0.95, false));
+        assert!(age.bursts(0.95, 1.05, false));
+        // Only once, as the particle passes the age.
+        assert!(!age.bursts(1.05, 1.15, false));
+        // Particles which die before the age never burst.
+        assert!(!age.bursts(0.5, 0.6, true));
+    }
+
+    #[test]
+    fn burst_speed_is_uniform() {
+        let config = SubEmitterConfig {
+            inherited_velocity: 0.0,
+            ..Default::default()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut sum = Vector3::zero();
+        for _ in 0..1000 {
+            let velocity = config.burst_velocity(Vector3::unit_y() * 10.0, &mut rng);
+            assert!((velocity.magnitude() - config.burst_speed).abs() < 1e-4);
+            sum += velocity;
+        }
+        // Directions are spread evenly, so they average out.
+        assert!(sum.magnitude() / 1000.0 < 0.3);
+    }
+
+    #[test]
+    fn bursts_stay_within_budget() {
+        let mut simulation = Simulation::new(&[], &[]);
+        let config = Config {
+            particles_generated_per_step: 2,
+            particles_lifetime_mean: 0.01,
+            sub_emitter: Some(SubEmitterConfig {
+                burst_count: 30,
+                lifetime: 10.0,
+                budget: 100,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        simulation.sync_config(&config);
+        for _ in 0..200 {
+            simulation.step();
+        }
+        // The sparks outlive the test, so they fill the budget, but each step's bursts stop there.
+        // At most a dozen steps' worth of rockets are alive at once.
+        let (_, represented) = simulation.get_particle_counts();
+        assert!(represented >= 100, "{}", represented);
+        assert!(represented <= 100 + 2 * 12, "{}", represented);
+    }
+}
@@ -0,0 +1,121 @@
+/// A vortex about an axis through the origin, swirling particles around it and pulling them towards it.
+/// Both accelerations fall off with the distance from the axis, so particles nearer it swirl faster,
+/// and the swirl stops accelerating particles once they circle the axis at its max speed.
+use cgmath::{InnerSpace, Vector3, Zero};
+
+/// Particles nearer the axis than this are accelerated as though they were this far from it,
+/// so that the vortex's core doesn't fling them out.
+const CORE_RADIUS: f32 = 0.1;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SwirlConfig {
+    /// The direction of the axis the particles swirl about; it needn't be normalized.
+    pub axis: Vector3<f32>,
+    /// The tangential acceleration 1 m from the axis, in m/s². Positive strengths swirl by the right-hand rule,
+    /// i.e. counterclockwise seen from the axis' tip.
+    pub strength: f32,
+    /// The acceleration towards the axis 1 m from it, in m/s². Negative attractions push particles away.
+    pub attraction: f32,
+    /// The power of the distance from the axis the accelerations fall off with; 0 is uniform,
+    /// and 2 is an inverse-square law.
+    pub falloff: f32,
+    /// The speed around the axis, in m/s, beyond which the swirl stops accelerating particles.
+    pub max_speed: f32,
+}
+
+impl Default for SwirlConfig {
+    fn default() -> Self {
+        Self {
+            axis: Vector3::unit_y(),
+            strength: 5.0,
+            attraction: 1.0,
+            falloff: 1.0,
+            max_speed: 5.0,
+        }
+    }
+}
+
+impl SwirlConfig {
+    /// The acceleration of a particle at the position moving with the velocity.
+    pub fn acceleration(&self, position: Vector3<f32>, velocity: Vector3<f32>) -> Vector3<f32> {
+        if self.axis.is_zero() {
+            return Vector3::zero();
+        }
+        let axis = self.axis.normalize();
+        let radial = position - position.dot(axis) * axis;
+        let distance = radial.magnitude();
+        if distance == 0.0 {
+            return Vector3::zero();
+        }
+        let outward = radial / distance;
+        let tangent = axis.cross(outward);
+        let falloff = distance.max(CORE_RADIUS).powf(-self.falloff);
+
+        let swirl_speed = velocity.dot(tangent) * self.strength.signum();
+        let swirl = if swirl_speed < self.max_speed {
+            self.strength * falloff * tangent
+        } else {
+            Vector3::zero()
+        };
+        swirl - self.attraction * falloff * outward
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swirls_counterclockwise_and_stronger_closer() {
+        let swirl = SwirlConfig {
+            attraction: 0.0,
+            ..SwirlConfig::default()
+        };
+        let near = swirl.acceleration(Vector3::new(0.5, 3.0, 0.0), Vector3::zero());
+        let far = swirl.acceleration(Vector3::new(2.0, -1.0, 0.0), Vector3::zero());
+        // Counterclockwise seen from above the y axis carries +x towards -z.
+        assert!(near.z < 0.0 && near.x.abs() < 1e-6 && near.y.abs() < 1e-6);
+        assert!((near.magnitude() - 4.0 * far.magnitude()).abs() < 1e-4);
+
+        let reversed = SwirlConfig {
+            strength: -swirl.strength,
+            ..swirl
+        };
+        assert_eq!(
+            reversed.acceleration(Vector3::new(0.5, 0.0, 0.0), Vector3::zero()),
+            -near
+        );
+    }
+
+    #[test]
+    fn stops_swirling_at_max_speed() {
+        let swirl = SwirlConfig {
+            attraction: 0.0,
+            ..SwirlConfig::default()
+        };
+        let position = Vector3::new(1.0, 0.0, 0.0);
+        let slow = Vector3::new(0.0, 0.0, -0.5 * swirl.max_speed);
+        let fast = Vector3::new(0.0, 0.0, -2.0 * swirl.max_speed);
+        assert!(!swirl.acceleration(position, slow).is_zero());
+        assert!(swirl.acceleration(position, fast).is_zero());
+        // Particles circling against the swirl are always slowed.
+        assert!(!swirl.acceleration(position, -fast).is_zero());
+    }
+
+    #[test]
+    fn attracts_towards_any_axis() {
+        let swirl = SwirlConfig {
+            axis: Vector3::new(0.0, 0.0, 2.0),
+            strength: 0.0,
+            attraction: 3.0,
+            falloff: 2.0,
+            max_speed: 1.0,
+        };
+        let acceleration = swirl.acceleration(Vector3::new(0.0, -2.0, 5.0), Vector3::zero());
+        assert!((acceleration - Vector3::new(0.0, 0.75, 0.0)).magnitude() < 1e-5);
+        // On the axis, there's no direction to swirl or pull in.
+        assert!(swirl
+            .acceleration(Vector3::new(0.0, 0.0, 1.0), Vector3::zero())
+            .is_zero());
+    }
+}
@@ -0,0 +1,128 @@
+/// Planar constraints, locking one axis so that a 3D simulation plays out in 2D, e.g. for teaching.
+/// Bodies are held in the plane perpendicular to the locked axis, with no velocity along it, and rigid bodies
+/// only spin about it. Constraining the solvers' results keeps one solver for both 2D and 3D.
+use cgmath::{BaseFloat, InnerSpace, Vector3};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum PlanarAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl PlanarAxis {
+    pub const ALL: [PlanarAxis; 3] = [PlanarAxis::X, PlanarAxis::Y, PlanarAxis::Z];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlanarAxis::X => "X",
+            PlanarAxis::Y => "Y",
+            PlanarAxis::Z => "Z",
+        }
+    }
+
+    pub fn unit(&self) -> Vector3<f32> {
+        match self {
+            PlanarAxis::X => Vector3::unit_x(),
+            PlanarAxis::Y => Vector3::unit_y(),
+            PlanarAxis::Z => Vector3::unit_z(),
+        }
+    }
+}
+
+/// Locks the axis, holding bodies in the plane perpendicular to it at the offset along it.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct PlanarConstraint {
+    pub axis: PlanarAxis,
+    pub offset: f32,
+}
+
+impl PlanarConstraint {
+    // In either precision, since rigidbodies constrain their integrated state in math::Real.
+    fn axis<S: BaseFloat>(&self) -> Vector3<S> {
+        self.axis.unit().cast().unwrap()
+    }
+
+    /// Moves the position into the plane.
+    pub fn constrain_position<S: BaseFloat>(&self, position: Vector3<S>) -> Vector3<S> {
+        let axis = self.axis();
+        position + axis * (S::from(self.offset).unwrap() - position.dot(axis))
+    }
+
+    /// Removes the component of the velocity, or momentum, along the locked axis.
+    pub fn constrain_velocity<S: BaseFloat>(&self, velocity: Vector3<S>) -> Vector3<S> {
+        let axis = self.axis();
+        velocity - axis * velocity.dot(axis)
+    }
+
+    /// Keeps only the spin about the locked axis, the one rotation which keeps a body in the plane.
+    pub fn constrain_angular_velocity<S: BaseFloat>(
+        &self,
+        angular_velocity: Vector3<S>,
+    ) -> Vector3<S> {
+        let axis = self.axis();
+        axis * angular_velocity.dot(axis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::configurable::Configurable;
+    use crate::simulation::quantity;
+    use crate::simulation::rigidbody::{rigidbody::RigidBody, simulation::Simulation};
+    use cgmath::Zero;
+
+    #[test]
+    fn constrains_to_the_plane() {
+        let planar = PlanarConstraint {
+            axis: PlanarAxis::Y,
+            offset: 0.5,
+        };
+        let vector = Vector3::new(1.0, 2.0, 3.0);
+        assert_eq!(
+            planar.constrain_position(vector),
+            Vector3::new(1.0, 0.5, 3.0)
+        );
+        assert_eq!(
+            planar.constrain_velocity(vector),
+            Vector3::new(1.0, 0.0, 3.0)
+        );
+        assert_eq!(
+            planar.constrain_angular_velocity(vector),
+            Vector3::new(0.0, 2.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn rigidbodies_stay_in_the_plane() {
+        let rigidbody = RigidBody::cuboid(
+            Vector3::new(0.0, 1.0, 0.0),
+            quantity::kilograms(1.0),
+            Vector3::new(1.0, 0.5, 2.0),
+        )
+        .unwrap();
+        let mut simulation = Simulation::new(vec![rigidbody], Vec::new());
+        let mut config = simulation.get_config().clone();
+        // Everything pushes the rigidbody out of the plane, and spins it about every axis.
+        config.gravity = Vector3::new(1.0, -9.8, 2.0);
+        config.torque = Vector3::new(1.0, 1.0, 1.0);
+        config.wind = Vector3::new(0.0, 0.0, 5.0);
+        config.linear_drag_viscous = 1.0;
+        config.planar = Some(PlanarConstraint {
+            axis: PlanarAxis::Z,
+            offset: 0.0,
+        });
+        simulation.sync_config(&config);
+        for _ in 0..2000 {
+            simulation.step();
+        }
+        let (_, rigidbody) = simulation.get_rigidbodies().iter().next().unwrap();
+        let state = rigidbody.get_state();
+        assert!(rigidbody.get_position().z.abs() < 1e-5);
+        assert!(state.velocity().z.abs() < 1e-5);
+        let angular_velocity = rigidbody.get_angular_velocity();
+        assert!(angular_velocity.x.abs() < 1e-4 && angular_velocity.y.abs() < 1e-4);
+        assert!(!angular_velocity.is_zero());
+    }
+}
@@ -0,0 +1,213 @@
+/// Extension points for custom physics, registered with a simulation rather than added to its source.
+/// Force plugins add loads to each body while the simulation accumulates forces, with read access to the body's
+/// state. Behavior plugins run after each step with the whole simulation, e.g. to spawn, despawn, or push bodies.
+/// Plugins run in the order they were registered, so that simulations with plugins stay deterministic.
+use cgmath::{Vector3, Zero};
+
+/// A force through a body's center of mass, and a torque about it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Load {
+    pub force: Vector3<f32>,
+    pub torque: Vector3<f32>,
+}
+
+impl Load {
+    pub fn force(force: Vector3<f32>) -> Load {
+        Load {
+            force,
+            torque: Vector3::zero(),
+        }
+    }
+}
+
+impl Default for Load {
+    fn default() -> Self {
+        Load::force(Vector3::zero())
+    }
+}
+
+/// Adds a load to each body of type B during force accumulation, e.g. each rigidbody or each point of a springy mesh.
+/// Points have no rotation, so torques on them are ignored.
+pub trait ForcePlugin<B> {
+    /// The load on the body, given its state and the seconds simulated so far.
+    fn load(&mut self, body: &B, time: f32) -> Load;
+}
+
+/// Closures of the body and time are force plugins.
+impl<B, F: FnMut(&B, f32) -> Load> ForcePlugin<B> for F {
+    fn load(&mut self, body: &B, time: f32) -> Load {
+        self(body, time)
+    }
+}
+
+/// Acts on a simulation of type S after each of its steps.
+pub trait BehaviorPlugin<S> {
+    fn after_step(&mut self, simulation: &mut S);
+}
+
+/// Closures of the simulation are behavior plugins.
+impl<S, F: FnMut(&mut S)> BehaviorPlugin<S> for F {
+    fn after_step(&mut self, simulation: &mut S) {
+        self(simulation)
+    }
+}
+
+/// The plugins registered with a simulation of type S, whose bodies are of type B.
+pub struct Plugins<S, B> {
+    forces: Vec<Box<dyn ForcePlugin<B>>>,
+    behaviors: Vec<Box<dyn BehaviorPlugin<S>>>,
+}
+
+impl<S, B> Plugins<S, B> {
+    pub fn new() -> Plugins<S, B> {
+        Plugins {
+            forces: Vec::new(),
+            behaviors: Vec::new(),
+        }
+    }
+
+    pub fn add_force(&mut self, plugin: Box<dyn ForcePlugin<B>>) {
+        self.forces.push(plugin);
+    }
+
+    pub fn add_behavior(&mut self, plugin: Box<dyn BehaviorPlugin<S>>) {
+        self.behaviors.push(plugin);
+    }
+
+    /// The sum of the force plugins' loads on the body.
+    pub fn load(&mut self, body: &B, time: f32) -> Load {
+        self.forces.iter_mut().fold(Load::default(), |sum, plugin| {
+            let load = plugin.load(body, time);
+            Load {
+                force: sum.force + load.force,
+                torque: sum.torque + load.torque,
+            }
+        })
+    }
+
+    /// Runs the behavior plugins on the simulation, which owns these plugins, so they're taken from it while they run.
+    /// Behaviors they register are kept, and run from the next step.
+    pub fn after_step(simulation: &mut S, plugins: fn(&mut S) -> &mut Plugins<S, B>) {
+        let mut behaviors = std::mem::take(&mut plugins(simulation).behaviors);
+        for behavior in behaviors.iter_mut() {
+            behavior.after_step(simulation);
+        }
+        let added = std::mem::replace(&mut plugins(simulation).behaviors, behaviors);
+        plugins(simulation).behaviors.extend(added);
+    }
+}
+
+impl<S, B> Default for Plugins<S, B> {
+    fn default() -> Self {
+        Plugins::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::geometry;
+    use crate::simulation::quantity;
+    use crate::simulation::rigidbody::{rigidbody::RigidBody, simulation::Simulation as Rigid};
+    use crate::simulation::springy::{
+        simulation::Simulation as Springy,
+        springy_mesh::{Point, SpringyMesh},
+    };
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn force_plugins_push_rigidbodies() {
+        let rigidbody = RigidBody::new(Vector3::zero(), quantity::kilograms(2.0)).unwrap();
+        let mut simulation = Rigid::new(vec![rigidbody], Vec::new());
+        simulation
+            .get_plugins_mut()
+            .add_force(Box::new(|_: &RigidBody, _| {
+                Load::force(Vector3::unit_x() * 4.0)
+            }));
+        // Spins the body up about y, but only for the first half second.
+        simulation
+            .get_plugins_mut()
+            .add_force(Box::new(|_: &RigidBody, time: f32| Load {
+                force: Vector3::zero(),
+                torque: if time < 0.5 {
+                    Vector3::unit_y()
+                } else {
+                    Vector3::zero()
+                },
+            }));
+        let steps = (1.0 / simulation.get_timestep().as_secs_f32()).round() as usize;
+        for _ in 0..steps {
+            simulation.step();
+        }
+        let (_, rigidbody) = simulation.get_rigidbodies().iter().next().unwrap();
+        // 4 N for a second accelerates 2 kg to 2 m/s.
+        let velocity = rigidbody.get_state().velocity();
+        assert!((velocity.x - 2.0).abs() < 1e-2, "{:?}", velocity);
+        assert!(rigidbody.get_angular_velocity().y > 0.0);
+    }
+
+    #[test]
+    fn behavior_plugins_run_after_each_step() {
+        let rigidbody = RigidBody::new(Vector3::zero(), quantity::kilograms(1.0)).unwrap();
+        let mut simulation = Rigid::new(vec![rigidbody], Vec::new());
+        let mut steps = 0;
+        // Despawns every rigidbody after the tenth step.
+        simulation
+            .get_plugins_mut()
+            .add_behavior(Box::new(move |simulation: &mut Rigid| {
+                steps += 1;
+                if steps == 10 {
+                    let handles = simulation
+                        .get_rigidbodies()
+                        .iter()
+                        .map(|(handle, _)| handle)
+                        .collect::<Vec<_>>();
+                    for handle in handles {
+                        simulation.despawn_rigidbody(handle);
+                    }
+                }
+            }));
+        for _ in 0..9 {
+            simulation.step();
+        }
+        assert_eq!(simulation.get_rigidbodies().len(), 1);
+        simulation.step();
+        assert!(simulation.get_rigidbodies().is_empty());
+    }
+
+    #[test]
+    fn force_plugins_read_point_state() {
+        let mesh = || {
+            let (vertices, indices) = geometry::get_cube_vertices();
+            SpringyMesh::new(
+                vertices,
+                indices,
+                quantity::kilograms(8.0),
+                quantity::newtons_per_meter(1000.0),
+                quantity::newton_seconds_per_meter(10.0),
+                None,
+                &None,
+            )
+        };
+        let mut free = Springy::new(vec![mesh()], Vec::new());
+        let mut dragged = Springy::new(vec![mesh()], Vec::new());
+        // Heavy drag against each point's velocity.
+        dragged
+            .get_plugins_mut()
+            .add_force(Box::new(|point: &Point, _| {
+                Load::force(-5.0 * point.get_velocity())
+            }));
+        for _ in 0..500 {
+            free.step();
+            dragged.step();
+        }
+        let speed = |simulation: &Springy| {
+            simulation.get_meshes()[0]
+                .get_points()
+                .iter()
+                .map(|point| point.get_velocity().magnitude())
+                .sum::<f32>()
+        };
+        assert!(speed(&dragged) < 0.5 * speed(&free));
+    }
+}
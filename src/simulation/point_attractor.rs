@@ -1,5 +1,6 @@
 use cgmath::{InnerSpace, Vector3};
 
+#[cfg(feature = "render")]
 use crate::graphics::entity::ColoredMeshEntity;
 
 use super::consts::GRAVITY;
@@ -24,6 +25,7 @@ impl PointAttractor {
     /// The mass of the objects is scaled by each Instance's scale.
     /// Note that a negative mass would result in repellers.
     #[allow(dead_code)]
+    #[cfg(feature = "render")]
     pub fn from_entity(entity: &ColoredMeshEntity, mass: f32) -> Vec<PointAttractor> {
         entity
             .instances()
@@ -31,7 +33,7 @@ impl PointAttractor {
             .map(|instance| -> PointAttractor {
                 PointAttractor {
                     position: instance.position,
-                    mass: mass * instance.scale,
+                    mass: mass * instance.max_scale(),
                 }
             })
             .collect()
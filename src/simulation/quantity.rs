@@ -0,0 +1,184 @@
+/// Dimensioned quantities for the parameters of user-facing APIs, e.g. a springy mesh's mass and its struts'
+/// stiffness and damping, in the SI units of the units module.
+///
+/// With the typed-units feature, each quantity is its own newtype, so that dimensional mistakes such as passing
+/// a damping where a stiffness is expected fail to compile. Without it, each is an alias of f32, costing nothing.
+/// Either way, quantities are made with the constructors here, e.g. newtons_per_meter(1000.0), and read with
+/// Quantity::value(), so that code compiles with the feature on or off.
+pub trait Quantity: Copy {
+    /// The quantity in its SI units.
+    fn value(self) -> f32;
+}
+
+/// Defines each quantity, with its constructor from a value in its SI units.
+/// Not every quantity is in an API yet, so their constructors may be unused.
+macro_rules! quantities {
+    ($($(#[$meta:meta])* $name:ident, $constructor:ident;)*) => {
+        #[cfg(feature = "typed-units")]
+        mod typed {
+            use std::ops::{Add, Div, Mul, Neg, Sub};
+
+            $(
+                $(#[$meta])*
+                #[derive(Debug, Default, Copy, Clone, PartialEq, PartialOrd)]
+                pub struct $name(pub(super) f32);
+
+                impl super::Quantity for $name {
+                    fn value(self) -> f32 {
+                        self.0
+                    }
+                }
+
+                pub const fn $constructor(value: f32) -> $name {
+                    $name(value)
+                }
+
+                impl Add for $name {
+                    type Output = $name;
+                    fn add(self, other: $name) -> $name {
+                        $name(self.0 + other.0)
+                    }
+                }
+
+                impl Sub for $name {
+                    type Output = $name;
+                    fn sub(self, other: $name) -> $name {
+                        $name(self.0 - other.0)
+                    }
+                }
+
+                impl Neg for $name {
+                    type Output = $name;
+                    fn neg(self) -> $name {
+                        $name(-self.0)
+                    }
+                }
+
+                impl Mul<f32> for $name {
+                    type Output = $name;
+                    fn mul(self, scale: f32) -> $name {
+                        $name(self.0 * scale)
+                    }
+                }
+
+                impl Mul<$name> for f32 {
+                    type Output = $name;
+                    fn mul(self, quantity: $name) -> $name {
+                        $name(self * quantity.0)
+                    }
+                }
+
+                impl Div<f32> for $name {
+                    type Output = $name;
+                    fn div(self, scale: f32) -> $name {
+                        $name(self.0 / scale)
+                    }
+                }
+
+                /// The ratio of two quantities of the same dimension is dimensionless.
+                impl Div for $name {
+                    type Output = f32;
+                    fn div(self, other: $name) -> f32 {
+                        self.0 / other.0
+                    }
+                }
+            )*
+        }
+
+        #[cfg(feature = "typed-units")]
+        pub use typed::*;
+
+        $(
+            #[cfg(not(feature = "typed-units"))]
+            $(#[$meta])*
+            pub type $name = f32;
+
+            #[cfg(not(feature = "typed-units"))]
+            pub const fn $constructor(value: f32) -> $name {
+                value
+            }
+        )*
+    };
+}
+
+quantities! {
+    /// Kilograms.
+    Mass, kilograms;
+    /// Meters.
+    Length, meters;
+    /// Seconds.
+    Time, seconds;
+    /// The force of a spring per meter it's stretched, in N/m.
+    Stiffness, newtons_per_meter;
+    /// The force of a damper per meter per second its ends move apart, in N·s/m.
+    Damping, newton_seconds_per_meter;
+}
+
+#[cfg(not(feature = "typed-units"))]
+impl Quantity for f32 {
+    fn value(self) -> f32 {
+        self
+    }
+}
+
+/// Products and quotients of quantities of different dimensions, e.g. the damping of a spring's stiffness
+/// over a time. Without the typed-units feature, these are the f32 operators.
+#[cfg(feature = "typed-units")]
+mod dimensions {
+    use super::typed::*;
+    use std::ops::{Div, Mul};
+
+    macro_rules! product {
+        ($a:ident * $b:ident = $product:ident) => {
+            impl Mul<$b> for $a {
+                type Output = $product;
+                fn mul(self, other: $b) -> $product {
+                    $product(self.0 * other.0)
+                }
+            }
+
+            impl Mul<$a> for $b {
+                type Output = $product;
+                fn mul(self, other: $a) -> $product {
+                    $product(self.0 * other.0)
+                }
+            }
+
+            impl Div<$b> for $product {
+                type Output = $a;
+                fn div(self, other: $b) -> $a {
+                    $a(self.0 / other.0)
+                }
+            }
+
+            impl Div<$a> for $product {
+                type Output = $b;
+                fn div(self, other: $a) -> $b {
+                    $b(self.0 / other.0)
+                }
+            }
+        };
+    }
+
+    // N/m * s = N·s/m, and N·s/m * s = kg.
+    product!(Stiffness * Time = Damping);
+    product!(Damping * Time = Mass);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn critical_damping() {
+        // A spring-mass-damper is critically damped at c = 2√(km), whose dimensions work out to damping.
+        let mass = kilograms(2.0);
+        let stiffness = newtons_per_meter(8.0);
+        let natural_period = seconds((mass.value() / stiffness.value()).sqrt());
+        let damping: Damping = 2.0 * stiffness * natural_period;
+        assert_eq!(damping.value(), 8.0);
+        let back: Mass = damping * natural_period / 2.0;
+        assert_eq!(back, mass);
+        assert_eq!(meters(3.0) / meters(1.5), 2.0);
+    }
+}
@@ -0,0 +1,296 @@
+/// Ray casting against triangle meshes, accelerated by bounding volume hierarchies.
+use cgmath::{InnerSpace, Vector3};
+
+use super::collidable_mesh::Face;
+
+/// Nodes with at most this many faces aren't split.
+const LEAF_SIZE: usize = 4;
+
+/// Where a ray hit an object.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Hit<T> {
+    /// The distance along the ray's direction to the hit.
+    pub distance: f32,
+    pub point: Vector3<f32>,
+    /// The unit normal of the face hit, on the side the ray hit it from.
+    pub normal: Vector3<f32>,
+    pub object: T,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Aabb {
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+}
+
+impl Aabb {
+    fn empty() -> Aabb {
+        Aabb {
+            min: Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    fn grow(&mut self, point: Vector3<f32>) {
+        self.min = Vector3::new(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = Vector3::new(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    /// The distance along the ray at which it enters the box, if it hits it before max_distance.
+    /// The inverse direction's infinities for axis-parallel rays are handled by the slab test's comparisons.
+    fn ray_entry(
+        &self,
+        origin: Vector3<f32>,
+        inverse_direction: Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<f32> {
+        let mut entry: f32 = 0.0;
+        let mut exit = max_distance;
+        for axis in 0..3 {
+            let t0 = (self.min[axis] - origin[axis]) * inverse_direction[axis];
+            let t1 = (self.max[axis] - origin[axis]) * inverse_direction[axis];
+            // NaNs, from rays along a slab's face, leave the bounds unchanged.
+            entry = entry.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+        }
+        if entry <= exit {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+}
+
+/// A node of the hierarchy. Leaves have no children, and own a range of the face order.
+struct Node {
+    bounds: Aabb,
+    children: Option<(usize, usize)>,
+    first_face: usize,
+    face_count: usize,
+}
+
+/// A bounding volume hierarchy over triangles, so that a ray costs O(log n) triangle tests rather than O(n).
+/// Nodes are split at the median of their faces' centroids along their longest axis.
+pub struct Bvh {
+    faces: Vec<Face>,
+    nodes: Vec<Node>,
+    /// The indices of the faces, ordered so that each node's faces are contiguous.
+    order: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(faces: Vec<Face>) -> Bvh {
+        let mut bvh = Bvh {
+            order: (0..faces.len()).collect(),
+            faces,
+            nodes: Vec::new(),
+        };
+        if !bvh.faces.is_empty() {
+            bvh.build(0, bvh.faces.len());
+        }
+        bvh
+    }
+
+    /// Adds the node over order[first..first + count], reordering them, returning its index.
+    fn build(&mut self, first: usize, count: usize) -> usize {
+        let mut bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for face_index in self.order[first..first + count].iter() {
+            let face = &self.faces[*face_index];
+            bounds.grow(face.v0);
+            bounds.grow(face.v1);
+            bounds.grow(face.v2);
+            centroid_bounds.grow(centroid(face));
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            children: None,
+            first_face: first,
+            face_count: count,
+        });
+        if count <= LEAF_SIZE {
+            return index;
+        }
+
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let half = count / 2;
+        let faces = &self.faces;
+        self.order[first..first + count].select_nth_unstable_by(half, |a, b| {
+            centroid(&faces[*a])[axis].total_cmp(&centroid(&faces[*b])[axis])
+        });
+        let left = self.build(first, half);
+        let right = self.build(first + half, count - half);
+        self.nodes[index].children = Some((left, right));
+        self.nodes[index].face_count = 0;
+        index
+    }
+
+    pub fn get_faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// Gets the distance along the ray at which it first hits a face, and that face's index in get_faces().
+    /// The direction should be normalized.
+    pub fn raycast(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<(f32, usize)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inverse_direction = direction.map(|d| 1.0 / d);
+        let mut nearest: Option<(f32, usize)> = None;
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let max_distance = nearest.map_or(f32::MAX, |(distance, _)| distance);
+            if node
+                .bounds
+                .ray_entry(origin, inverse_direction, max_distance)
+                .is_none()
+            {
+                continue;
+            }
+            match node.children {
+                Some((left, right)) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                None => {
+                    for &face_index in
+                        self.order[node.first_face..node.first_face + node.face_count].iter()
+                    {
+                        if let Some(distance) =
+                            self.faces[face_index].ray_intersection(origin, direction)
+                        {
+                            if nearest.is_none_or(|(nearest, _)| distance < nearest) {
+                                nearest = Some((distance, face_index));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        nearest
+    }
+}
+
+fn centroid(face: &Face) -> Vector3<f32> {
+    (face.v0 + face.v1 + face.v2) / 3.0
+}
+
+/// Casts rays against a set of objects' faces at once, reporting which object each ray hits,
+/// e.g. to pick the object under the cursor. The faces are in whatever space rays are cast in,
+/// usually world space, so the raycaster must be rebuilt if the objects move.
+pub struct Raycaster<T> {
+    bvh: Bvh,
+    objects: Vec<T>,
+    /// The index into objects of each of the BVH's faces.
+    face_objects: Vec<usize>,
+}
+
+impl<T: Copy> Raycaster<T> {
+    pub fn new(objects: impl IntoIterator<Item = (T, Vec<Face>)>) -> Raycaster<T> {
+        let mut all_objects = Vec::new();
+        let mut faces = Vec::new();
+        let mut face_objects = Vec::new();
+        for (object, object_faces) in objects {
+            face_objects.extend(std::iter::repeat_n(all_objects.len(), object_faces.len()));
+            faces.extend(object_faces);
+            all_objects.push(object);
+        }
+        Raycaster {
+            bvh: Bvh::new(faces),
+            objects: all_objects,
+            face_objects,
+        }
+    }
+
+    /// Gets where the ray first hits any of the objects, if it does. The direction should be normalized.
+    pub fn raycast(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<Hit<T>> {
+        let (distance, face_index) = self.bvh.raycast(origin, direction)?;
+        let normal = self.bvh.get_faces()[face_index].normal();
+        Some(Hit {
+            distance,
+            point: origin + direction * distance,
+            normal: if normal.dot(direction) > 0.0 {
+                -normal
+            } else {
+                normal
+            },
+            object: self.objects[self.face_objects[face_index]],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::collidable_mesh::CollidableMesh;
+    use crate::simulation::geometry;
+    use rand::Rng;
+
+    fn sphere_faces(center: Vector3<f32>) -> Vec<Face> {
+        let (positions, indices) = geometry::get_sphere_vertices(1.0, 16, 16);
+        let positions = positions.iter().map(|p| p + center).collect();
+        CollidableMesh::new(positions, indices).get_faces().clone()
+    }
+
+    #[test]
+    fn bvh_matches_brute_force() {
+        let faces = sphere_faces(Vector3::new(0.0, 0.0, 0.0));
+        let bvh = Bvh::new(faces.clone());
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let origin = Vector3::new(
+                rng.gen_range(-3.0..3.0),
+                rng.gen_range(-3.0..3.0),
+                rng.gen_range(-3.0..3.0),
+            );
+            let direction = (Vector3::new(
+                rng.gen_range(-0.5..0.5),
+                rng.gen_range(-0.5..0.5),
+                rng.gen_range(-0.5..0.5),
+            ) - origin)
+                .normalize();
+            let expected = faces
+                .iter()
+                .filter_map(|face| face.ray_intersection(origin, direction))
+                .min_by(|a, b| a.total_cmp(b));
+            assert_eq!(
+                bvh.raycast(origin, direction).map(|(distance, _)| distance),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn raycaster_reports_nearest_object() {
+        let raycaster = Raycaster::new([
+            ("near", sphere_faces(Vector3::new(3.0, 0.0, 0.0))),
+            ("far", sphere_faces(Vector3::new(6.0, 0.0, 0.0))),
+        ]);
+        let hit = raycaster
+            .raycast(Vector3::new(0.0, 0.1, 0.05), Vector3::unit_x())
+            .unwrap();
+        assert_eq!(hit.object, "near");
+        assert!((hit.point.x - 2.0).abs() < 0.05);
+        // The normal faces back along the ray.
+        assert!(hit.normal.x < -0.95);
+        let behind = raycaster.raycast(Vector3::new(10.0, 0.0, 0.0), Vector3::unit_x());
+        assert!(behind.is_none());
+    }
+}
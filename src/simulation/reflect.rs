@@ -0,0 +1,216 @@
+/// Reflection over simulation configs, so that a config's fields are declared once, with their labels,
+/// ranges, units, and groups, and both serialization and editing panels are derived from that declaration
+/// rather than maintained by hand alongside the config. Configs serialize as key = value lines, like the
+/// graphics settings, with vectors written as their space-separated components.
+use cgmath::Vector3;
+use std::{fmt::Write, ops::RangeInclusive};
+
+/// A field's value, borrowed from its config to read or edit.
+pub enum Value<'a> {
+    F32(&'a mut f32),
+    U32(&'a mut u32),
+    Bool(&'a mut bool),
+    Vector3(&'a mut Vector3<f32>),
+}
+
+impl<'a> From<&'a mut f32> for Value<'a> {
+    fn from(value: &'a mut f32) -> Self {
+        Value::F32(value)
+    }
+}
+
+impl<'a> From<&'a mut u32> for Value<'a> {
+    fn from(value: &'a mut u32) -> Self {
+        Value::U32(value)
+    }
+}
+
+impl<'a> From<&'a mut bool> for Value<'a> {
+    fn from(value: &'a mut bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl<'a> From<&'a mut Vector3<f32>> for Value<'a> {
+    fn from(value: &'a mut Vector3<f32>) -> Self {
+        Value::Vector3(value)
+    }
+}
+
+/// A field of a config, and how it's presented.
+pub struct Field<'a> {
+    /// The field's key when serialized.
+    pub key: &'static str,
+    /// The field's label in editing panels, before localization.
+    pub label: &'static str,
+    /// The SI symbol of the field's units, if it has any, e.g. units::MASS.
+    pub units: Option<&'static str>,
+    /// The group the field is shown under in editing panels, if any.
+    pub group: Option<&'static str>,
+    /// The range numbers (and each component of vectors) are edited within. Parsed values are clamped to it.
+    pub range: Option<RangeInclusive<f32>>,
+    pub value: Value<'a>,
+}
+
+impl<'a> Field<'a> {
+    pub fn new(key: &'static str, label: &'static str, value: impl Into<Value<'a>>) -> Field<'a> {
+        Field {
+            key,
+            label,
+            units: None,
+            group: None,
+            range: None,
+            value: value.into(),
+        }
+    }
+
+    pub fn range(mut self, range: RangeInclusive<f32>) -> Field<'a> {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn units(mut self, units: &'static str) -> Field<'a> {
+        self.units = Some(units);
+        self
+    }
+
+    pub fn group(mut self, group: &'static str) -> Field<'a> {
+        self.group = Some(group);
+        self
+    }
+
+    /// Formats the value for serialization.
+    fn format(&self) -> String {
+        match &self.value {
+            Value::F32(value) => value.to_string(),
+            Value::U32(value) => value.to_string(),
+            Value::Bool(value) => value.to_string(),
+            Value::Vector3(value) => format!("{} {} {}", value.x, value.y, value.z),
+        }
+    }
+
+    /// Sets the value from its serialized form, clamped to the range.
+    /// Leaves the value as it was if it doesn't parse.
+    fn parse(&mut self, text: &str) {
+        let clamp = |value: f32, range: &Option<RangeInclusive<f32>>| match range {
+            Some(range) => value.clamp(*range.start(), *range.end()),
+            None => value,
+        };
+        match &mut self.value {
+            Value::F32(value) => {
+                if let Ok(parsed) = text.parse::<f32>() {
+                    if parsed.is_finite() {
+                        **value = clamp(parsed, &self.range);
+                    }
+                }
+            }
+            Value::U32(value) => {
+                if let Ok(parsed) = text.parse::<u32>() {
+                    **value = clamp(parsed as f32, &self.range) as u32;
+                }
+            }
+            Value::Bool(value) => {
+                if let Ok(parsed) = text.parse::<bool>() {
+                    **value = parsed;
+                }
+            }
+            Value::Vector3(value) => {
+                let components = text
+                    .split_whitespace()
+                    .map(str::parse::<f32>)
+                    .collect::<Result<Vec<_>, _>>();
+                if let Ok([x, y, z]) = components.as_deref() {
+                    if x.is_finite() && y.is_finite() && z.is_finite() {
+                        **value = Vector3::new(
+                            clamp(*x, &self.range),
+                            clamp(*y, &self.range),
+                            clamp(*z, &self.range),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A config whose fields can be enumerated, to serialize it and to edit it in a generated panel.
+pub trait Reflect {
+    /// The config's fields, in the order they're presented.
+    fn fields(&mut self) -> Vec<Field<'_>>;
+
+    /// Serializes the config's fields as key = value lines.
+    fn to_key_values(&self) -> String
+    where
+        Self: Clone,
+    {
+        let mut config = self.clone();
+        let mut contents = String::new();
+        for field in config.fields() {
+            writeln!(contents, "{} = {}", field.key, field.format()).unwrap();
+        }
+        contents
+    }
+
+    /// Clamps the config's fields to their ranges, e.g. after they've been edited other than through its panel.
+    fn clamp_to_ranges(&mut self) {
+        for mut field in self.fields() {
+            let text = field.format();
+            field.parse(&text);
+        }
+    }
+
+    /// Sets the config's fields from key = value lines.
+    /// Missing, unknown, and invalid entries are ignored, leaving those fields as they were.
+    fn parse_key_values(&mut self, contents: &str) {
+        let mut fields = self.fields();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if let Some(field) = fields.iter_mut().find(|field| field.key == key.trim()) {
+                field.parse(value.trim());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::bounce;
+
+    #[test]
+    fn round_trips_configs() {
+        let config = bounce::Config {
+            sphere_mass: 2.5,
+            wind: Vector3::new(1.0, -0.5, 0.25),
+            ..Default::default()
+        };
+        let mut parsed = bounce::Config::default();
+        parsed.parse_key_values(&config.to_key_values());
+        assert!(parsed == config);
+    }
+
+    #[test]
+    fn parse_ignores_bad_entries() {
+        let mut config = bounce::Config::default();
+        config.parse_key_values(
+            "sphere_mass = 1000\n\
+             drag = heavy\n\
+             wind = 1 2\n\
+             unknown = 1\n\
+             not a field",
+        );
+        let defaults = bounce::Config::default();
+        // Out of range values are clamped.
+        let max_mass = bounce::Config::default()
+            .fields()
+            .into_iter()
+            .find(|field| field.key == "sphere_mass")
+            .and_then(|field| field.range)
+            .map(|range| *range.end());
+        assert_eq!(Some(config.sphere_mass), max_mass);
+        assert_eq!(config.drag, defaults.drag);
+        assert_eq!(config.wind, defaults.wind);
+    }
+}
@@ -2,24 +2,202 @@ use std::time::Duration;
 
 use cgmath::{Vector3, Zero};
 
-use super::super::state::Integration;
+use super::super::collision_filter::CollisionFilter;
+use super::super::planar::PlanarConstraint;
+use super::super::state::{AdaptiveTimestep, Integration};
+use super::friction::{AnisotropicFriction, FrictionCone};
 
+#[derive(Clone, PartialEq)]
 pub struct Config {
     pub integration: Integration,
+    /// Steps rotations by solving the gyroscopic torque implicitly, rather than with the integration, if set,
+    /// so that fast spinning bodies stay stable.
+    pub implicit_gyroscopic: bool,
     pub dt: f32, // Seconds as f32
+    /// Adapts dt to the error estimates of the integration within the bounds, if set, and if the integration
+    /// estimates its error.
+    pub adaptive_dt: Option<AdaptiveTimestep>,
     pub coefficient_of_restitution: f32,
+    /// The most friction impulse a collision can apply, as a fraction of its normal impulse.
+    pub coefficient_of_friction: f32,
+    /// Friction which grips differently along the grain than across it, if set.
+    pub anisotropic_friction: Option<AnisotropicFriction>,
+    /// The fraction of each penetration into a one-sided obstacle, and of each joint's anchor separation,
+    /// corrected each step by moving the bodies, without changing their velocities. Without it, resting bodies
+    /// slowly sink into the floor and jointed bodies drift apart. It's kept small, as correcting joints
+    /// quickly stiffens them, hiding the strain of sudden loads which should break them.
+    pub position_correction: f32,
     pub gravity: Vector3<f32>,
     pub torque: Vector3<f32>,
+    /// Linear drag proportional to the velocity relative to the wind.
+    pub linear_drag_viscous: f32,
+    /// Linear drag proportional to the square of the velocity relative to the wind,
+    /// and to the area the body presents to the wind.
+    pub linear_drag_quadratic: f32,
+    /// Angular drag proportional to the angular velocity.
+    pub angular_drag_viscous: f32,
+    /// Angular drag proportional to the square of the angular velocity.
+    pub angular_drag_quadratic: f32,
+    pub wind: Vector3<f32>,
+    /// The collision groups the rigidbodies collide with.
+    pub collision_mask: u32,
+    /// The stiffness of the spring dragging rigidbodies with the mouse, in N/m.
+    /// The spring is always critically damped.
+    pub mouse_spring_stiffness: f32,
+    /// The stiffness of the springs holding jointed rigidbodies' anchors together, in N/m.
+    pub joint_stiffness: f32,
+    /// The stiffness of the springs holding welded rigidbodies' relative rotations, in N·m/rad.
+    pub joint_angular_stiffness: f32,
+    /// Whether joints break once they're pulled apart by more than the break force or torque.
+    pub breakable_joints: bool,
+    pub joint_break_force: f32,
+    pub joint_break_torque: f32,
+    /// Holds the rigidbodies in a plane, spinning only about its normal, if set.
+    pub planar: Option<PlanarConstraint>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             integration: Integration::Rk4,
+            implicit_gyroscopic: true,
             dt: Duration::from_millis(1).as_secs_f32(),
+            adaptive_dt: None,
             coefficient_of_restitution: 0.7,
+            coefficient_of_friction: 0.3,
+            anisotropic_friction: None,
+            position_correction: 0.01,
             gravity: Vector3::<f32>::zero(),
             torque: Vector3::<f32>::zero(),
+            linear_drag_viscous: 0.0,
+            linear_drag_quadratic: 0.0,
+            angular_drag_viscous: 0.0,
+            angular_drag_quadratic: 0.0,
+            wind: Vector3::<f32>::zero(),
+            collision_mask: CollisionFilter::ALL,
+            mouse_spring_stiffness: 50.0,
+            joint_stiffness: 2000.0,
+            joint_angular_stiffness: 5.0,
+            breakable_joints: true,
+            joint_break_force: 40.0,
+            joint_break_torque: 2.0,
+            planar: None,
+        }
+    }
+}
+
+impl Config {
+    /// The bounds dt adapts within, if it's adapting.
+    pub fn get_adaptive_dt(&self) -> Option<AdaptiveTimestep> {
+        self.adaptive_dt
+            .filter(|_| self.integration.estimates_error())
+    }
+
+    pub fn get_friction_cone(&self) -> FrictionCone {
+        FrictionCone {
+            friction: self.coefficient_of_friction,
+            anisotropy: self.anisotropic_friction,
+        }
+    }
+
+    /// The names of the values set() sets.
+    pub const SETTABLE: &'static [&'static str] = &[
+        "dt",
+        "implicit_gyroscopic",
+        "coefficient_of_restitution",
+        "coefficient_of_friction",
+        "position_correction",
+        "gravity",
+        "torque",
+        "linear_drag_viscous",
+        "linear_drag_quadratic",
+        "angular_drag_viscous",
+        "angular_drag_quadratic",
+        "wind",
+        "mouse_spring_stiffness",
+        "joint_stiffness",
+        "joint_angular_stiffness",
+        "breakable_joints",
+        "joint_break_force",
+        "joint_break_torque",
+    ];
+
+    /// Sets the value with the field's name, from one number for scalars, three for vectors,
+    /// or 0 or 1 for booleans, e.g. from the console.
+    pub fn set(&mut self, name: &str, values: &[f32]) -> Result<(), &'static str> {
+        let scalar = || match values {
+            [value] => Ok(*value),
+            _ => Err("Expected one value!"),
+        };
+        let non_negative = || {
+            scalar().and_then(|value| {
+                if value >= 0.0 {
+                    Ok(value)
+                } else {
+                    Err("The value can't be negative!")
+                }
+            })
+        };
+        let vector = || match values {
+            [x, y, z] => Ok(Vector3::new(*x, *y, *z)),
+            _ => Err("Expected three values!"),
+        };
+        match name {
+            "dt" => {
+                self.dt = Some(scalar()?)
+                    .filter(|dt| *dt > 0.0)
+                    .ok_or("The timestep must be positive!")?
+            }
+            "implicit_gyroscopic" => self.implicit_gyroscopic = scalar()? != 0.0,
+            "coefficient_of_restitution" => self.coefficient_of_restitution = non_negative()?,
+            "coefficient_of_friction" => self.coefficient_of_friction = non_negative()?,
+            "position_correction" => {
+                self.position_correction = Some(scalar()?)
+                    .filter(|fraction| (0.0..=1.0).contains(fraction))
+                    .ok_or("The position correction must be between 0 and 1!")?
+            }
+            "gravity" => self.gravity = vector()?,
+            "torque" => self.torque = vector()?,
+            "linear_drag_viscous" => self.linear_drag_viscous = non_negative()?,
+            "linear_drag_quadratic" => self.linear_drag_quadratic = non_negative()?,
+            "angular_drag_viscous" => self.angular_drag_viscous = non_negative()?,
+            "angular_drag_quadratic" => self.angular_drag_quadratic = non_negative()?,
+            "wind" => self.wind = vector()?,
+            "mouse_spring_stiffness" => self.mouse_spring_stiffness = non_negative()?,
+            "joint_stiffness" => self.joint_stiffness = non_negative()?,
+            "joint_angular_stiffness" => self.joint_angular_stiffness = non_negative()?,
+            "breakable_joints" => self.breakable_joints = scalar()? != 0.0,
+            "joint_break_force" => self.joint_break_force = non_negative()?,
+            "joint_break_torque" => self.joint_break_torque = non_negative()?,
+            _ => return Err("No such config value!"),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_values_by_name() {
+        let mut config = Config::default();
+        config.set("gravity", &[0.0, -9.8, 0.0]).unwrap();
+        assert_eq!(config.gravity, Vector3::new(0.0, -9.8, 0.0));
+        config.set("breakable_joints", &[0.0]).unwrap();
+        assert!(!config.breakable_joints);
+        assert!(config.set("gravity", &[1.0]).is_err());
+        assert!(config.set("dt", &[0.0]).is_err());
+        assert!(config.set("coefficient_of_friction", &[-1.0]).is_err());
+        assert!(config.set("position_correction", &[2.0]).is_err());
+        assert!(config.set("nothing", &[1.0]).is_err());
+        for name in Config::SETTABLE {
+            // Each settable value takes either one or three values.
+            assert!(
+                config.set(name, &[0.5]).is_ok() || config.set(name, &[0.5, 0.5, 0.5]).is_ok(),
+                "{}",
+                name
+            );
         }
     }
 }
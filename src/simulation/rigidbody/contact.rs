@@ -0,0 +1,527 @@
+/// Contacts between boxes, for simulations which collide rigidbodies with each other, which the rigidbody
+/// simulation doesn't. Overlapping boxes are pushed apart along the axis they overlap least on, by impulses at
+/// each's corners inside the other, with restitution and Coulomb friction, and then moved apart by the deepest.
+use cgmath::{ElementWise, InnerSpace, Matrix, Matrix3, SquareMatrix, Vector3, Zero};
+
+use crate::pool::Pool;
+use crate::simulation::conservation::MomentumAudit;
+use crate::simulation::solver::{self, SolverBudget, SolverStats};
+
+use super::{
+    friction::{AnisotropicFriction, FrictionCone},
+    rigidbody::RigidBody,
+    simulation::RigidBodyHandle,
+};
+
+/// Closing speeds below this don't bounce, so that resting contacts stay put.
+const RESTING_SPEED: f32 = 0.2;
+/// The fraction of the overlap between boxes removed each step, and the overlap left alone.
+const CONTACT_SLOP: f32 = 0.001;
+/// The contact solver's iterations each step, unless it's given another budget.
+pub const CONTACT_ITERATIONS: usize = 8;
+/// Corners this near the other box's sides still touch it, so that boxes stacked flush, e.g. in a tower,
+/// rest on each other.
+const CONTACT_TOLERANCE: f32 = 0.005;
+
+/// A box, either a rigidbody or a fixed obstacle.
+#[derive(Debug, Copy, Clone)]
+pub struct Cuboid {
+    pub center: Vector3<f32>,
+    pub rotation: Matrix3<f32>,
+    pub half_extents: Vector3<f32>,
+}
+
+impl Cuboid {
+    pub fn of(rigidbody: &RigidBody) -> Cuboid {
+        Cuboid {
+            center: rigidbody.get_position(),
+            rotation: rigidbody.get_rotation_matrix(),
+            half_extents: rigidbody.get_dimensions() / 2.0,
+        }
+    }
+
+    /// An axis aligned box, e.g. an obstacle.
+    pub fn fixed(center: Vector3<f32>, dimensions: Vector3<f32>) -> Cuboid {
+        Cuboid {
+            center,
+            rotation: Matrix3::identity(),
+            half_extents: dimensions / 2.0,
+        }
+    }
+
+    /// The same box, grown by the margin on each side, e.g. to find the boxes touching it.
+    pub fn expanded(&self, margin: f32) -> Cuboid {
+        Cuboid {
+            half_extents: self.half_extents.map(|half_extent| half_extent + margin),
+            ..*self
+        }
+    }
+
+    /// The corners of the box, in world space.
+    fn get_corners(&self) -> impl Iterator<Item = Vector3<f32>> + '_ {
+        (0..8).map(move |i| {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            self.center
+                + self.rotation
+                    * Vector3::new(sign(1), sign(2), sign(4)).mul_element_wise(self.half_extents)
+        })
+    }
+
+    /// Whether the point is inside the box grown by the margin.
+    fn contains(&self, point: Vector3<f32>, margin: f32) -> bool {
+        let local = self.rotation.transpose() * (point - self.center);
+        (0..3).all(|axis| local[axis].abs() < self.half_extents[axis] + margin)
+    }
+
+    /// The outward normal of the side the point inside the box grown by the margin is nearest,
+    /// and how deep inside it the point is, if it's inside.
+    pub fn get_penetration(&self, point: Vector3<f32>, margin: f32) -> Option<(Vector3<f32>, f32)> {
+        let local = self.rotation.transpose() * (point - self.center);
+        let (axis, depth) = (0..3)
+            .map(|axis| (axis, self.half_extents[axis] + margin - local[axis].abs()))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        (depth > 0.0).then(|| (self.rotation[axis] * local[axis].signum(), depth))
+    }
+
+    /// How far the box reaches from its center along the axis.
+    fn get_reach(&self, axis: Vector3<f32>) -> f32 {
+        (0..3)
+            .map(|i| self.rotation[i].dot(axis).abs() * self.half_extents[i])
+            .sum()
+    }
+
+    /// The radius of the sphere around the box's center which contains it.
+    fn get_bounding_radius(&self) -> f32 {
+        self.half_extents.magnitude()
+    }
+
+    /// Whether the boxes overlap.
+    pub fn overlaps(&self, other: &Cuboid) -> bool {
+        !get_contacts(self, other).is_empty()
+    }
+}
+
+/// What a box is in a contact: a rigidbody, or a box which doesn't move, e.g. an obstacle.
+#[derive(Debug, Copy, Clone)]
+pub enum ContactBody {
+    Rigid(RigidBodyHandle),
+    Fixed(Cuboid),
+}
+
+impl ContactBody {
+    fn get_cuboid(&self, rigidbodies: &Pool<RigidBody>) -> Cuboid {
+        match self {
+            ContactBody::Rigid(handle) => Cuboid::of(&rigidbodies[*handle]),
+            ContactBody::Fixed(cuboid) => *cuboid,
+        }
+    }
+
+    fn get_handle(&self) -> Option<RigidBodyHandle> {
+        match self {
+            ContactBody::Rigid(handle) => Some(*handle),
+            ContactBody::Fixed(_) => None,
+        }
+    }
+}
+
+/// The bounciness and friction of contacts.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ContactMaterial {
+    pub restitution: f32,
+    /// The most friction impulse a contact can apply, as a fraction of its normal impulse.
+    pub friction: f32,
+    /// Friction which grips differently along the grain than across it, if set.
+    pub anisotropy: Option<AnisotropicFriction>,
+    /// The fraction of each pair's deepest overlap, past the slop, that they're moved apart by each step.
+    pub position_correction: f32,
+}
+
+impl ContactMaterial {
+    pub fn get_friction_cone(&self) -> FrictionCone {
+        FrictionCone {
+            friction: self.friction,
+            anisotropy: self.anisotropy,
+        }
+    }
+}
+
+/// A point where two boxes overlap, with the normal pointing from the second box towards the first.
+struct Contact {
+    point: Vector3<f32>,
+    normal: Vector3<f32>,
+    depth: f32,
+}
+
+/// The corners of each box inside the other, if they overlap. The normal is the axis of either box's faces
+/// the boxes overlap least along, and each corner's depth is how far it's sunk past the other box along it.
+fn get_contacts(a: &Cuboid, b: &Cuboid) -> Vec<Contact> {
+    let offset = a.center - b.center;
+    let Some((normal, _)) = (0..3)
+        .flat_map(|i| [a.rotation[i], b.rotation[i]])
+        .map(|axis| {
+            let overlap = a.get_reach(axis) + b.get_reach(axis) - axis.dot(offset).abs();
+            (axis * axis.dot(offset).signum(), overlap)
+        })
+        .min_by(|(_, first), (_, second)| first.total_cmp(second))
+        .filter(|(_, overlap)| *overlap > 0.0)
+    else {
+        return Vec::new();
+    };
+    // The faces of each box the other's corners are sunk past.
+    let b_face = normal.dot(b.center) + b.get_reach(normal);
+    let a_face = normal.dot(a.center) - a.get_reach(normal);
+    let a_corners = a
+        .get_corners()
+        .filter(|corner| b.contains(*corner, CONTACT_TOLERANCE))
+        .map(|point| (point, b_face - normal.dot(point)));
+    let b_corners = b
+        .get_corners()
+        .filter(|corner| a.contains(*corner, CONTACT_TOLERANCE))
+        .map(|point| (point, normal.dot(point) - a_face));
+    a_corners
+        .chain(b_corners)
+        .filter(|(_, depth)| *depth > 0.0)
+        .map(|(point, depth)| Contact {
+            point,
+            normal,
+            depth,
+        })
+        .collect()
+}
+
+/// The inverse of the rigidbody's effective mass along the direction, when pushed at r from its center of mass.
+pub fn get_inverse_effective_mass(
+    rigidbody: &RigidBody,
+    r: Vector3<f32>,
+    direction: Vector3<f32>,
+) -> f32 {
+    let arm = r.cross(direction);
+    1.0 / rigidbody.get_mass()
+        + arm.dot(rigidbody.get_state().get_moment_of_inertia_inverted() * arm)
+}
+
+/// The contacts between a pair of bodies, which are None if they're fixed, with the speed each bounces back at
+/// and the impulses applied at each so far.
+struct Manifold {
+    a: Option<RigidBodyHandle>,
+    b: Option<RigidBodyHandle>,
+    contacts: Vec<Contact>,
+    bounces: Vec<f32>,
+    impulses: Vec<(f32, Vector3<f32>)>,
+}
+
+impl Manifold {
+    /// The pair's contacts, if their boxes overlap.
+    fn new(
+        rigidbodies: &Pool<RigidBody>,
+        a: ContactBody,
+        b: ContactBody,
+        material: ContactMaterial,
+    ) -> Option<Manifold> {
+        let (cuboid_a, cuboid_b) = (a.get_cuboid(rigidbodies), b.get_cuboid(rigidbodies));
+        // Boxes whose bounding spheres don't touch can't overlap.
+        let reach = cuboid_a.get_bounding_radius() + cuboid_b.get_bounding_radius();
+        if (cuboid_a.center - cuboid_b.center).magnitude2() > reach * reach {
+            return None;
+        }
+        let contacts = get_contacts(&cuboid_a, &cuboid_b);
+        if contacts.is_empty() {
+            return None;
+        }
+        let (a, b) = (a.get_handle(), b.get_handle());
+        // The closing speed each contact bounces back at is taken before any are resolved.
+        let bounces = contacts
+            .iter()
+            .map(|contact| {
+                let closing_speed =
+                    -get_relative_velocity(rigidbodies, a, b, contact.point).dot(contact.normal);
+                if closing_speed > RESTING_SPEED {
+                    material.restitution * closing_speed
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let impulses = vec![(0.0, Vector3::zero()); contacts.len()];
+        Some(Manifold {
+            a,
+            b,
+            contacts,
+            bounces,
+            impulses,
+        })
+    }
+}
+
+/// Pushes apart each pair of boxes which overlap, with impulses at each's corners inside the other,
+/// then moves each pair apart by its deepest contact.
+/// Each contact's impulse changes the velocities at the others, so they're all resolved in turn, several times,
+/// with each contact's total impulse kept pushing the boxes apart and its friction within its cone. So the
+/// weight of a stack of boxes passes down through it.
+/// Each iteration's residual is the largest change it made to any contact's impulses, and the iterations
+/// stop within the budget.
+pub fn collide(
+    rigidbodies: &mut Pool<RigidBody>,
+    pairs: &[(ContactBody, ContactBody)],
+    material: ContactMaterial,
+    budget: &SolverBudget,
+) -> SolverStats {
+    let mut manifolds = pairs
+        .iter()
+        .filter_map(|(a, b)| Manifold::new(rigidbodies, *a, *b, material))
+        .collect::<Vec<_>>();
+    let cone = material.get_friction_cone();
+    let momentum = |rigidbodies: &Pool<RigidBody>| {
+        rigidbodies
+            .values()
+            .map(|rigidbody| rigidbody.get_state().linear_momentum())
+            .sum()
+    };
+    let mut audit = MomentumAudit::begin(module_path!(), || momentum(rigidbodies));
+    let stats = solver::iterate(budget, || {
+        let mut residual: f32 = 0.0;
+        for manifold in manifolds.iter_mut() {
+            for ((contact, bounce), impulses) in manifold
+                .contacts
+                .iter()
+                .zip(manifold.bounces.iter())
+                .zip(manifold.impulses.iter_mut())
+            {
+                let change = resolve_contact(
+                    rigidbodies,
+                    (manifold.a, manifold.b),
+                    contact,
+                    *bounce,
+                    &cone,
+                    impulses,
+                    &mut audit,
+                );
+                residual = residual.max(change);
+            }
+        }
+        residual
+    });
+    audit.finish(|| momentum(rigidbodies));
+    for manifold in manifolds.iter() {
+        if let Some(deepest) = manifold
+            .contacts
+            .iter()
+            .max_by(|c1, c2| c1.depth.total_cmp(&c2.depth))
+        {
+            separate(
+                rigidbodies,
+                manifold.a,
+                manifold.b,
+                deepest,
+                material.position_correction,
+            );
+        }
+    }
+    stats
+}
+
+/// The velocity of the first body relative to the second at the point.
+fn get_relative_velocity(
+    rigidbodies: &Pool<RigidBody>,
+    a: Option<RigidBodyHandle>,
+    b: Option<RigidBodyHandle>,
+    point: Vector3<f32>,
+) -> Vector3<f32> {
+    let velocity = |body: Option<RigidBodyHandle>| {
+        body.map_or(Vector3::zero(), |body| {
+            let body = &rigidbodies[body];
+            body.get_point_velocity(body.world_to_local(point))
+        })
+    };
+    velocity(a) - velocity(b)
+}
+
+/// Applies equal and opposite impulses at the contact, stopping the boxes closing or bouncing them apart
+/// at the bounce speed, with Coulomb friction. The contact's impulses so far are updated to include them,
+/// and the magnitude of the change to them is returned. The impulses are recorded in the audit.
+fn resolve_contact(
+    rigidbodies: &mut Pool<RigidBody>,
+    (a, b): (Option<RigidBodyHandle>, Option<RigidBodyHandle>),
+    contact: &Contact,
+    bounce: f32,
+    cone: &FrictionCone,
+    impulses: &mut (f32, Vector3<f32>),
+    audit: &mut MomentumAudit,
+) -> f32 {
+    let (normal_impulse, friction_impulse) = impulses;
+    let offset = |body: Option<RigidBodyHandle>| {
+        body.map_or(Vector3::zero(), |body| {
+            contact.point - rigidbodies[body].get_position()
+        })
+    };
+    let (r_a, r_b) = (offset(a), offset(b));
+    let inverse_mass = |rigidbodies: &Pool<RigidBody>, direction| {
+        a.map_or(0.0, |a| {
+            get_inverse_effective_mass(&rigidbodies[a], r_a, direction)
+        }) + b.map_or(0.0, |b| {
+            get_inverse_effective_mass(&rigidbodies[b], r_b, direction)
+        })
+    };
+    let mut apply = |rigidbodies: &mut Pool<RigidBody>, impulse: Vector3<f32>| {
+        if let Some(a) = a {
+            rigidbodies[a].apply_impulse(impulse, r_a);
+        }
+        if let Some(b) = b {
+            rigidbodies[b].apply_impulse(-impulse, r_b);
+        }
+        match (a, b) {
+            (Some(_), Some(_)) => audit.exchange(impulse),
+            (None, _) => audit.absorb(impulse),
+            (_, None) => audit.absorb(-impulse),
+        }
+    };
+
+    let normal_velocity =
+        get_relative_velocity(rigidbodies, a, b, contact.point).dot(contact.normal);
+    let total = (*normal_impulse
+        + (bounce - normal_velocity) / inverse_mass(rigidbodies, contact.normal))
+    .max(0.0);
+    let normal_change = total - *normal_impulse;
+    apply(rigidbodies, normal_change * contact.normal);
+    *normal_impulse = total;
+
+    let relative_velocity = get_relative_velocity(rigidbodies, a, b, contact.point);
+    let stopping_impulse = |rigidbodies: &Pool<RigidBody>, tangent: Vector3<f32>| {
+        -relative_velocity.dot(tangent) / inverse_mass(rigidbodies, tangent) * tangent
+    };
+    let (along, across) = cone.get_tangents(contact.normal);
+    let total = cone.clamp(
+        *friction_impulse
+            + stopping_impulse(rigidbodies, along)
+            + stopping_impulse(rigidbodies, across),
+        contact.normal,
+        *normal_impulse,
+    );
+    let friction_change = total - *friction_impulse;
+    apply(rigidbodies, friction_change);
+    *friction_impulse = total;
+    (normal_change * contact.normal + friction_change).magnitude()
+}
+
+/// Moves the boxes apart along the contact's normal by the fraction of its depth past the slop, the lighter further.
+/// Only their positions change, so the correction doesn't push them apart faster than the impulses left them.
+fn separate(
+    rigidbodies: &mut Pool<RigidBody>,
+    a: Option<RigidBodyHandle>,
+    b: Option<RigidBodyHandle>,
+    contact: &Contact,
+    position_correction: f32,
+) {
+    let inverse_mass =
+        |body: Option<RigidBodyHandle>| body.map_or(0.0, |body| 1.0 / rigidbodies[body].get_mass());
+    let (inverse_mass_a, inverse_mass_b) = (inverse_mass(a), inverse_mass(b));
+    let correction = position_correction * (contact.depth - CONTACT_SLOP).max(0.0)
+        / (inverse_mass_a + inverse_mass_b)
+        * contact.normal;
+    if let Some(a) = a {
+        rigidbodies[a].translate(correction * inverse_mass_a);
+    }
+    if let Some(b) = b {
+        rigidbodies[b].translate(-correction * inverse_mass_b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::quantity;
+    use crate::simulation::solver::SolverStop;
+
+    #[test]
+    fn boxes_stacked_flush_are_pushed_apart_vertically() {
+        let lower = Cuboid::fixed(Vector3::new(0.0, 0.5, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let upper = Cuboid::fixed(Vector3::new(0.0, 1.49, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let contacts = get_contacts(&upper, &lower);
+        // The upper box's bottom corners, and the lower box's top corners, all level with its sides.
+        assert_eq!(contacts.len(), 8);
+        for contact in contacts {
+            assert_eq!(contact.normal, Vector3::unit_y());
+            assert!((contact.depth - 0.01).abs() < 1e-4);
+        }
+        let apart = Cuboid::fixed(Vector3::new(0.0, 1.51, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        assert!(!apart.overlaps(&lower));
+    }
+
+    #[test]
+    fn a_falling_box_stops_on_the_floor() {
+        let mut rigidbodies = Pool::new();
+        let handle = rigidbodies.insert(
+            RigidBody::cuboid(
+                Vector3::new(0.0, 0.45, 0.0),
+                quantity::kilograms(1.0),
+                Vector3::new(1.0, 1.0, 1.0),
+            )
+            .unwrap(),
+        );
+        rigidbodies[handle].apply_impulse(Vector3::new(0.0, -1.0, 0.0), Vector3::zero());
+        let floor = Cuboid::fixed(Vector3::new(0.0, -0.5, 0.0), Vector3::new(4.0, 1.0, 4.0));
+        let material = ContactMaterial {
+            restitution: 0.0,
+            friction: 0.5,
+            anisotropy: None,
+            position_correction: 0.8,
+        };
+        let stats = collide(
+            &mut rigidbodies,
+            &[(ContactBody::Rigid(handle), ContactBody::Fixed(floor))],
+            material,
+            &SolverBudget::new(CONTACT_ITERATIONS),
+        );
+        assert_eq!(stats.get_iterations(), CONTACT_ITERATIONS);
+        let rigidbody = &rigidbodies[handle];
+        // Every corner's resolved together, so the box stops flat, without sliding off or tipping.
+        assert!(rigidbody.get_state().velocity().magnitude() < 0.01);
+        assert!(rigidbody.get_angular_velocity().magnitude() < 0.01);
+        assert!(rigidbody.get_position().y > 0.48);
+    }
+
+    #[test]
+    fn contact_solver_converges_within_tolerance() {
+        // A stack of three boxes resting on the floor, the lower passing the upper's weight down.
+        let mut rigidbodies = Pool::new();
+        let handles = (0..3)
+            .map(|i| {
+                let mut rigidbody = RigidBody::cuboid(
+                    Vector3::new(0.0, 0.499 + i as f32 * 0.999, 0.0),
+                    quantity::kilograms(1.0),
+                    Vector3::new(1.0, 1.0, 1.0),
+                )
+                .unwrap();
+                rigidbody.apply_impulse(Vector3::new(0.0, -0.1, 0.0), Vector3::zero());
+                rigidbodies.insert(rigidbody)
+            })
+            .collect::<Vec<_>>();
+        let floor = Cuboid::fixed(Vector3::new(0.0, -0.5, 0.0), Vector3::new(4.0, 1.0, 4.0));
+        let pairs = vec![
+            (ContactBody::Rigid(handles[0]), ContactBody::Fixed(floor)),
+            (
+                ContactBody::Rigid(handles[1]),
+                ContactBody::Rigid(handles[0]),
+            ),
+            (
+                ContactBody::Rigid(handles[2]),
+                ContactBody::Rigid(handles[1]),
+            ),
+        ];
+        let material = ContactMaterial {
+            restitution: 0.0,
+            friction: 0.5,
+            anisotropy: None,
+            position_correction: 0.8,
+        };
+        let budget = SolverBudget {
+            max_iterations: 100,
+            tolerance: 1e-4,
+            time_budget: None,
+        };
+        let stats = collide(&mut rigidbodies, &pairs, material, &budget);
+        assert_eq!(stats.stop, SolverStop::Converged);
+        assert!(stats.get_iterations() > 1 && stats.get_iterations() < 100);
+        assert!(stats.get_final_residual().unwrap() < stats.residuals[0]);
+    }
+}
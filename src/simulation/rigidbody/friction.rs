@@ -0,0 +1,143 @@
+/// Coulomb friction cones for contacts, which may grip differently along a surface's grain than across it.
+use cgmath::{InnerSpace, Vector3};
+
+/// Friction which depends on the direction of sliding, as over brushed metal or the grain of a fabric.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnisotropicFriction {
+    /// The direction of the grain, in world space. Sliding across it uses the isotropic coefficient of friction.
+    pub grain: Vector3<f32>,
+    /// The coefficient of friction for sliding along the grain.
+    pub friction_along_grain: f32,
+}
+
+impl Default for AnisotropicFriction {
+    fn default() -> Self {
+        Self {
+            grain: Vector3::unit_x(),
+            friction_along_grain: 0.05,
+        }
+    }
+}
+
+/// The friction impulses a contact may apply, as a cone about its normal: its friction impulse is at most
+/// the coefficient of friction times its normal impulse. Anisotropic friction flattens the cone into an
+/// elliptical one, with a coefficient for each of the tangent directions.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrictionCone {
+    pub friction: f32,
+    pub anisotropy: Option<AnisotropicFriction>,
+}
+
+impl FrictionCone {
+    /// The two tangent directions at a contact with the normal, along and across the grain if the friction's
+    /// anisotropic and the grain isn't along the normal. Otherwise they're any two perpendicular directions.
+    pub fn get_tangents(&self, normal: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+        let grain = self
+            .anisotropy
+            .map(|anisotropy| anisotropy.grain - anisotropy.grain.dot(normal) * normal)
+            .filter(|grain| grain.magnitude2() > f32::EPSILON);
+        let along = grain.unwrap_or_else(|| {
+            let axis = if normal.x.abs() < 0.9 {
+                Vector3::unit_x()
+            } else {
+                Vector3::unit_y()
+            };
+            axis - axis.dot(normal) * normal
+        });
+        let along = along.normalize();
+        (along, normal.cross(along))
+    }
+
+    /// The coefficients of friction along each of the tangents at a contact with the normal.
+    pub fn get_coefficients(&self, normal: Vector3<f32>) -> (f32, f32) {
+        match self.anisotropy {
+            Some(anisotropy)
+                if (anisotropy.grain - anisotropy.grain.dot(normal) * normal).magnitude2()
+                    > f32::EPSILON =>
+            {
+                (anisotropy.friction_along_grain, self.friction)
+            }
+            _ => (self.friction, self.friction),
+        }
+    }
+
+    /// Scales the friction impulse, in the plane of the contact, back into the cone of the normal impulse,
+    /// keeping its direction.
+    pub fn clamp(
+        &self,
+        impulse: Vector3<f32>,
+        normal: Vector3<f32>,
+        normal_impulse: f32,
+    ) -> Vector3<f32> {
+        let (along, across) = self.get_tangents(normal);
+        let (friction_along, friction_across) = self.get_coefficients(normal);
+        let (impulse_along, impulse_across) = (impulse.dot(along), impulse.dot(across));
+        let normal_impulse = normal_impulse.max(0.0);
+        if friction_along <= 0.0 || friction_across <= 0.0 {
+            // The cone's flat, so the impulse is limited along each tangent on its own.
+            let limit = |impulse: f32, friction: f32| {
+                let limit = friction.max(0.0) * normal_impulse;
+                impulse.clamp(-limit, limit)
+            };
+            return limit(impulse_along, friction_along) * along
+                + limit(impulse_across, friction_across) * across;
+        }
+        // How far out the impulse is, where the cone's edge is 1.
+        let extent = (impulse_along / friction_along).hypot(impulse_across / friction_across);
+        if extent <= normal_impulse {
+            impulse
+        } else {
+            impulse * (normal_impulse / extent)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isotropic_cone_limits_magnitude() {
+        let cone = FrictionCone {
+            friction: 0.5,
+            anisotropy: None,
+        };
+        let normal = Vector3::unit_y();
+        let impulse = Vector3::new(3.0, 0.0, 4.0);
+        let clamped = cone.clamp(impulse, normal, 2.0);
+        assert!((clamped.magnitude() - 1.0).abs() < 1e-5);
+        assert!((clamped.normalize() - impulse.normalize()).magnitude() < 1e-5);
+        assert_eq!(cone.clamp(impulse, normal, 20.0), impulse);
+    }
+
+    #[test]
+    fn grain_slides_more_easily() {
+        let cone = FrictionCone {
+            friction: 0.5,
+            anisotropy: Some(AnisotropicFriction {
+                grain: Vector3::new(1.0, 0.3, 0.0),
+                friction_along_grain: 0.1,
+            }),
+        };
+        let normal = Vector3::unit_y();
+        let (along, across) = cone.get_tangents(normal);
+        assert!((along - Vector3::unit_x()).magnitude() < 1e-5);
+        assert!(along.dot(normal).abs() < 1e-5 && across.dot(normal).abs() < 1e-5);
+        let along_grain = cone.clamp(Vector3::unit_x() * 10.0, normal, 1.0);
+        let across_grain = cone.clamp(Vector3::unit_z() * 10.0, normal, 1.0);
+        assert!((along_grain.magnitude() - 0.1).abs() < 1e-5);
+        assert!((across_grain.magnitude() - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn grain_along_the_normal_is_isotropic() {
+        let cone = FrictionCone {
+            friction: 0.5,
+            anisotropy: Some(AnisotropicFriction {
+                grain: Vector3::unit_y(),
+                friction_along_grain: 0.1,
+            }),
+        };
+        assert_eq!(cone.get_coefficients(Vector3::unit_y()), (0.5, 0.5));
+    }
+}
@@ -0,0 +1,331 @@
+/// Utilities for moment of inertia tensors.
+/// Unless otherwise noted, tensors are about the body's center of mass, in the body's local frame,
+/// and shapes are centered at the origin with their long axis (if any) along Y.
+use std::f32::consts::PI;
+
+use cgmath::{Array, InnerSpace, Matrix, Matrix3, SquareMatrix, Vector3, Zero};
+use itertools::Itertools;
+
+use crate::simulation::voxelize::VoxelGrid;
+
+const JACOBI_MAX_SWEEPS: usize = 50;
+
+/// The mass properties of a closed mesh of uniform density.
+#[derive(Debug, Copy, Clone)]
+pub struct MassProperties {
+    pub volume: f32,
+    pub center_of_mass: Vector3<f32>,
+    /// About the center of mass.
+    pub inertia: Matrix3<f32>,
+}
+
+/// A solid box with the given side lengths along each axis.
+pub fn cuboid(mass: f32, dimensions: Vector3<f32>) -> Matrix3<f32> {
+    let x2 = dimensions.x * dimensions.x;
+    let y2 = dimensions.y * dimensions.y;
+    let z2 = dimensions.z * dimensions.z;
+    Matrix3::from_diagonal(Vector3::new(y2 + z2, x2 + z2, x2 + y2) * mass / 12.0)
+}
+
+/// A solid sphere.
+pub fn sphere(mass: f32, radius: f32) -> Matrix3<f32> {
+    Matrix3::from_value(2.0 / 5.0 * mass * radius * radius)
+}
+
+/// A solid cylinder whose axis is along Y.
+pub fn cylinder(mass: f32, radius: f32, height: f32) -> Matrix3<f32> {
+    let perpendicular = mass * (3.0 * radius * radius + height * height) / 12.0;
+    let axial = mass * radius * radius / 2.0;
+    Matrix3::from_diagonal(Vector3::new(perpendicular, axial, perpendicular))
+}
+
+/// A solid capsule whose axis is along Y. The height is that of the cylindrical section,
+/// not including the hemispherical caps.
+pub fn capsule(mass: f32, radius: f32, height: f32) -> Matrix3<f32> {
+    let cylinder_volume = PI * radius * radius * height;
+    let caps_volume = 4.0 / 3.0 * PI * radius.powi(3);
+    let density = mass / (cylinder_volume + caps_volume);
+    let cylinder_mass = density * cylinder_volume;
+    let hemisphere_mass = density * caps_volume / 2.0;
+
+    let r2 = radius * radius;
+    let axial = cylinder_mass * r2 / 2.0 + 2.0 * hemisphere_mass * 2.0 / 5.0 * r2;
+    // Each hemisphere's inertia is shifted from its own center of mass, 3r/8 from its flat face,
+    // to the capsule's center.
+    let perpendicular = cylinder_mass * (r2 / 4.0 + height * height / 12.0)
+        + 2.0
+            * hemisphere_mass
+            * (2.0 * r2 / 5.0 + height * height / 4.0 + 3.0 * height * radius / 8.0);
+    Matrix3::from_diagonal(Vector3::new(perpendicular, axial, perpendicular))
+}
+
+/// A closed triangle mesh of uniform density, consistently wound.
+/// Uses the divergence theorem by summing signed tetrahedra formed by each face and the origin,
+/// so the mesh needn't be convex, nor contain the origin.
+/// Returns None if the mesh encloses no volume.
+pub fn mesh(
+    vertex_positions: &[Vector3<f32>],
+    vertex_indices: &[usize],
+    mass: f32,
+) -> Option<MassProperties> {
+    // The covariance of the canonical tetrahedron (0, e_x, e_y, e_z), for unit density.
+    let canonical_covariance =
+        Matrix3::new(2.0, 1.0, 1.0, 1.0, 2.0, 1.0, 1.0, 1.0, 2.0) * (1.0 / 120.0);
+
+    let mut volume = 0.0;
+    let mut first_moment = Vector3::<f32>::zero();
+    let mut covariance = Matrix3::<f32>::zero();
+    for (i0, i1, i2) in vertex_indices.iter().tuples() {
+        let a = vertex_positions[*i0];
+        let b = vertex_positions[*i1];
+        let c = vertex_positions[*i2];
+        let transform = Matrix3::from_cols(a, b, c);
+        let determinant = transform.determinant();
+
+        volume += determinant / 6.0;
+        first_moment += (a + b + c) * determinant / 24.0;
+        covariance += transform * canonical_covariance * transform.transpose() * determinant;
+    }
+
+    // Inward winding gives a negative volume, but the signs cancel below.
+    if volume.abs() < f32::EPSILON {
+        return None;
+    }
+    let density = mass / volume;
+    let center_of_mass = first_moment / volume;
+    // Move the covariance to the center of mass, and convert it to the inertia tensor.
+    let covariance = covariance * density
+        - mass
+            * Matrix3::from_cols(
+                center_of_mass * center_of_mass.x,
+                center_of_mass * center_of_mass.y,
+                center_of_mass * center_of_mass.z,
+            );
+    let inertia = Matrix3::from_value(trace(&covariance)) - covariance;
+
+    Some(MassProperties {
+        volume: volume.abs(),
+        center_of_mass,
+        inertia,
+    })
+}
+
+/// Approximates the mass properties of a body of uniform density from its voxelization, as the sum of
+/// its occupied voxels. Unlike mesh(), this handles meshes which aren't closed or consistently wound,
+/// e.g. concave models voxelized by their surface, at the cost of accuracy near the surface.
+/// Returns None if no voxels are occupied.
+pub fn voxels(grid: &VoxelGrid, mass: f32) -> Option<MassProperties> {
+    let centers = grid.get_occupied_centers();
+    if centers.is_empty() {
+        return None;
+    }
+    let voxel_mass = mass / centers.len() as f32;
+    let center_of_mass = centers.iter().sum::<Vector3<f32>>() / centers.len() as f32;
+    let voxel_inertia = cuboid(voxel_mass, Vector3::from_value(grid.get_voxel_size()));
+    let inertia = centers.iter().fold(Matrix3::zero(), |inertia, center| {
+        inertia + parallel_axis(&voxel_inertia, voxel_mass, center - center_of_mass)
+    });
+    Some(MassProperties {
+        volume: centers.len() as f32 * grid.get_voxel_size().powi(3),
+        center_of_mass,
+        inertia,
+    })
+}
+
+/// Moves an inertia tensor about a body's center of mass to a point offset from the center of mass.
+pub fn parallel_axis(inertia: &Matrix3<f32>, mass: f32, offset: Vector3<f32>) -> Matrix3<f32> {
+    let outer_product = Matrix3::from_cols(offset * offset.x, offset * offset.y, offset * offset.z);
+    inertia + (Matrix3::from_value(offset.magnitude2()) - outer_product) * mass
+}
+
+fn trace(matrix: &Matrix3<f32>) -> f32 {
+    matrix.x.x + matrix.y.y + matrix.z.z
+}
+
+/// Decomposes the symmetric inertia tensor into its principal moments and principal axes.
+/// Returns the principal moments, and a rotation matrix whose columns are the corresponding
+/// (unit length) principal axes, so that inertia = axes * diag(moments) * axes^T.
+/// Uses the cyclic Jacobi eigenvalue algorithm, which is robust for small symmetric matrices.
+pub fn principal_axes(inertia: &Matrix3<f32>) -> ([f32; 3], Matrix3<f32>) {
+    let mut a = *inertia;
+    let mut axes = Matrix3::<f32>::identity();
+
+    for _ in 0..JACOBI_MAX_SWEEPS {
+        let off_diagonal = a[1][0].powi(2) + a[2][0].powi(2) + a[2][1].powi(2);
+        if off_diagonal < f32::EPSILON * f32::EPSILON {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[q][p].abs() < f32::EPSILON {
+                continue;
+            }
+            // Find the Givens rotation which zeroes a[p][q].
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[q][p]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            let mut rotation = Matrix3::<f32>::identity();
+            rotation[p][p] = c;
+            rotation[q][q] = c;
+            rotation[q][p] = s;
+            rotation[p][q] = -s;
+
+            a = rotation.transpose() * a * rotation;
+            axes = axes * rotation;
+        }
+    }
+
+    ([a[0][0], a[1][1], a[2][2]], axes)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, InnerSpace, Matrix, Matrix3, SquareMatrix, Vector3, Zero};
+
+    use super::principal_axes;
+
+    const TOLERANCE: f32 = 0.0001;
+
+    fn assert_approx_eq(expected: Matrix3<f32>, actual: Matrix3<f32>) {
+        for col in 0..3 {
+            for row in 0..3 {
+                assert!(
+                    (expected[col][row] - actual[col][row]).abs() < TOLERANCE,
+                    "expected {:?}, got {:?}",
+                    expected,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn principal_axes_diagonal() {
+        let inertia = Matrix3::from_diagonal(Vector3::new(1.0, 2.0, 3.0));
+        let (moments, axes) = principal_axes(&inertia);
+        assert_eq!([1.0, 2.0, 3.0], moments);
+        assert_eq!(Matrix3::identity(), axes);
+    }
+
+    #[test]
+    fn principal_axes_rotated() {
+        let body_inertia = Matrix3::from_diagonal(Vector3::new(1.0, 2.0, 3.0));
+        let rotation = Matrix3::from_axis_angle(Vector3::new(1.0, 1.0, 0.0).normalize(), Deg(30.0));
+        let inertia = rotation * body_inertia * rotation.transpose();
+
+        let (moments, axes) = principal_axes(&inertia);
+        let reconstructed = axes * Matrix3::from_diagonal(moments.into()) * axes.transpose();
+        assert_approx_eq(inertia, reconstructed);
+        // The axes should be orthonormal.
+        assert_approx_eq(Matrix3::identity(), axes.transpose() * axes);
+
+        let mut sorted_moments = moments;
+        sorted_moments.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (expected, actual) in [1.0, 2.0, 3.0].iter().zip(sorted_moments.iter()) {
+            assert!((expected - actual).abs() < TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn cuboid() {
+        let inertia = super::cuboid(12.0, Vector3::new(1.0, 2.0, 3.0));
+        assert_approx_eq(
+            Matrix3::from_diagonal(Vector3::new(13.0, 10.0, 5.0)),
+            inertia,
+        );
+    }
+
+    #[test]
+    fn sphere() {
+        assert_approx_eq(
+            Matrix3::from_value(0.4 * 2.0 * 9.0),
+            super::sphere(2.0, 3.0),
+        );
+    }
+
+    #[test]
+    fn cylinder() {
+        let inertia = super::cylinder(12.0, 1.0, 2.0);
+        assert_approx_eq(Matrix3::from_diagonal(Vector3::new(7.0, 6.0, 7.0)), inertia);
+    }
+
+    #[test]
+    fn capsule_without_height_is_sphere() {
+        assert_approx_eq(super::sphere(3.0, 0.5), super::capsule(3.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn capsule_between_cylinder_and_sphere() {
+        // A capsule's caps add mass away from its center, so it has more perpendicular inertia
+        // than a cylinder of the same mass and height, with its mass distributed the same radially.
+        let capsule = super::capsule(1.0, 0.5, 2.0);
+        let cylinder = super::cylinder(1.0, 0.5, 2.0);
+        assert!(capsule.x.x > cylinder.x.x);
+        assert!(capsule.y.y < cylinder.y.y);
+        assert_eq!(capsule.x.x, capsule.z.z);
+    }
+
+    #[test]
+    fn parallel_axis() {
+        // A point mass at the center of mass has no inertia; shifted by 2 along x,
+        // it has inertia m * d^2 about the y and z axes.
+        let shifted = super::parallel_axis(&Matrix3::zero(), 3.0, Vector3::new(2.0, 0.0, 0.0));
+        assert_approx_eq(
+            Matrix3::from_diagonal(Vector3::new(0.0, 12.0, 12.0)),
+            shifted,
+        );
+    }
+
+    #[test]
+    fn voxels_approximate_mesh() {
+        use crate::simulation::voxelize::{voxelize, Mode};
+        let (vertex_positions, vertex_indices) = crate::simulation::geometry::get_cube_vertices();
+        let grid = voxelize(&vertex_positions, &vertex_indices, 0.1, Mode::Solid);
+        let properties = super::voxels(&grid, 6.0).unwrap();
+        assert!((properties.volume - 1.0).abs() < TOLERANCE);
+        assert!(properties.center_of_mass.magnitude() < TOLERANCE);
+        // Voxels filling the cube exactly sum to its inertia.
+        let expected = super::cuboid(6.0, Vector3::new(1.0, 1.0, 1.0));
+        assert!((properties.inertia.x.x - expected.x.x).abs() < 0.001);
+        assert!(properties.inertia.x.y.abs() < 0.001);
+    }
+
+    #[test]
+    fn mesh_cube() {
+        let (vertex_positions, vertex_indices) = crate::simulation::geometry::get_cube_vertices();
+        let properties = super::mesh(&vertex_positions, &vertex_indices, 6.0).unwrap();
+        assert!((properties.volume - 1.0).abs() < TOLERANCE);
+        assert!(properties.center_of_mass.magnitude() < TOLERANCE);
+        assert_approx_eq(
+            super::cuboid(6.0, Vector3::new(1.0, 1.0, 1.0)),
+            properties.inertia,
+        );
+    }
+
+    #[test]
+    fn mesh_translated_cube() {
+        let (vertex_positions, vertex_indices) = crate::simulation::geometry::get_cube_vertices();
+        let offset = Vector3::new(1.0, -2.0, 3.0);
+        let vertex_positions = vertex_positions
+            .iter()
+            .map(|v| v + offset)
+            .collect::<Vec<_>>();
+        let properties = super::mesh(&vertex_positions, &vertex_indices, 6.0).unwrap();
+        assert!((properties.center_of_mass - offset).magnitude() < TOLERANCE);
+        assert_approx_eq(
+            super::cuboid(6.0, Vector3::new(1.0, 1.0, 1.0)),
+            properties.inertia,
+        );
+    }
+
+    #[test]
+    fn mesh_without_volume() {
+        let vertex_positions = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ];
+        assert!(super::mesh(&vertex_positions, &[0, 1, 2], 1.0).is_none());
+    }
+}
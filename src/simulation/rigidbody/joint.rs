@@ -0,0 +1,505 @@
+/// Joints holding pairs of rigidbodies together, enforced by critically damped springs.
+use cgmath::{InnerSpace, Quaternion, Vector3, Zero};
+
+use super::rigidbody::RigidBody;
+use super::simulation::RigidBodyHandle;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JointKind {
+    /// Holds the anchor points of the bodies together, leaving them free to rotate about it.
+    Ball,
+    /// Also holds the bodies' hinge axes together, leaving them free to rotate about only that axis.
+    /// Hinges may be driven by a motor.
+    Hinge,
+    /// Also holds the bodies' relative rotation, so that they move as if they were one body.
+    Weld,
+}
+
+impl JointKind {
+    pub const ALL: [JointKind; 3] = [JointKind::Ball, JointKind::Hinge, JointKind::Weld];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JointKind::Ball => "Ball",
+            JointKind::Hinge => "Hinge",
+            JointKind::Weld => "Weld",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MotorMode {
+    Off,
+    /// Drives the hinge to the target angle, in radians from its rest angle.
+    Angle,
+    /// Drives the hinge at the target angular velocity, in rad/s.
+    Velocity,
+}
+
+impl MotorMode {
+    pub const ALL: [MotorMode; 3] = [MotorMode::Off, MotorMode::Angle, MotorMode::Velocity];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MotorMode::Off => "Off",
+            MotorMode::Angle => "Angle",
+            MotorMode::Velocity => "Velocity",
+        }
+    }
+}
+
+/// A PD controller driving a hinge about its axis, with its torque limited to the max torque.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Motor {
+    pub mode: MotorMode,
+    /// The target angle or angular velocity, depending on the mode.
+    pub target: f32,
+    /// The torque per radian of angle error, in N·m/rad.
+    pub proportional_gain: f32,
+    /// The torque per rad/s of angular velocity error, in N·m·s/rad.
+    pub derivative_gain: f32,
+    pub max_torque: f32,
+}
+
+impl Default for Motor {
+    fn default() -> Self {
+        Self {
+            mode: MotorMode::Off,
+            target: 0.0,
+            proportional_gain: 5.0,
+            derivative_gain: 0.5,
+            max_torque: 2.0,
+        }
+    }
+}
+
+impl Motor {
+    /// The torque about the hinge axis, given the hinge's angle and angular velocity.
+    /// In velocity mode, only the derivative term acts, as the target angle is always moving.
+    pub fn get_torque(&self, angle: f32, angular_velocity: f32) -> f32 {
+        let torque = match self.mode {
+            MotorMode::Off => return 0.0,
+            MotorMode::Angle => {
+                self.proportional_gain * wrap_angle(self.target - angle)
+                    - self.derivative_gain * angular_velocity
+            }
+            MotorMode::Velocity => self.derivative_gain * (self.target - angular_velocity),
+        };
+        torque.clamp(-self.max_torque, self.max_torque)
+    }
+}
+
+/// Wraps the angle, in radians, into [-π, π].
+fn wrap_angle(angle: f32) -> f32 {
+    let tau = 2.0 * std::f32::consts::PI;
+    angle - tau * (angle / tau).round()
+}
+
+/// The stiffnesses the joint springs pull with. The damping is derived from them.
+#[derive(Debug, Copy, Clone)]
+pub struct JointStiffness {
+    /// In N/m.
+    pub linear: f32,
+    /// In N·m/rad.
+    pub angular: f32,
+}
+
+/// The loads a joint applies to its bodies over the next step.
+/// The first body receives the opposite force and torque.
+#[derive(Debug, Copy, Clone)]
+pub struct JointLoads {
+    /// The force on the second body at its anchor.
+    pub force: Vector3<f32>,
+    /// The torque on the second body, beyond that of the force about its anchor.
+    pub torque: Vector3<f32>,
+    /// The magnitudes of the spring terms of the force and torque, excluding damping,
+    /// which measure how hard the joint is being pulled apart.
+    pub strain_force: f32,
+    pub strain_torque: f32,
+}
+
+pub struct Joint {
+    kind: JointKind,
+    bodies: (RigidBodyHandle, RigidBodyHandle),
+    // The joint's anchor, in each body's local space.
+    local_anchors: (Vector3<f32>, Vector3<f32>),
+    // The rotation of the second body relative to the first which the joint holds.
+    rest_rotation: Quaternion<f32>,
+    // The hinge axis, in each body's local space. Only hinges use it.
+    local_axes: (Vector3<f32>, Vector3<f32>),
+    motor: Motor,
+}
+
+impl Joint {
+    /// Joins the bodies at the world space anchor, holding their current relative rotation.
+    /// The axis is the hinge axis in world space, which only hinges use.
+    pub fn new(
+        kind: JointKind,
+        bodies: (RigidBodyHandle, RigidBodyHandle),
+        rigidbodies: (&RigidBody, &RigidBody),
+        anchor: Vector3<f32>,
+        axis: Vector3<f32>,
+    ) -> Joint {
+        let (a, b) = rigidbodies;
+        let axis = axis.normalize();
+        let mut joint = Joint {
+            kind,
+            bodies,
+            local_anchors: (Vector3::zero(), Vector3::zero()),
+            rest_rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            local_axes: (
+                a.get_rotation().conjugate() * axis,
+                b.get_rotation().conjugate() * axis,
+            ),
+            motor: Motor::default(),
+        };
+        joint.reanchor(rigidbodies, anchor);
+        joint
+    }
+
+    /// Moves the anchor to the world space point, and makes the bodies' current relative rotation the rest rotation,
+    /// so that the joint holds the bodies as they are now. A hinge keeps its axis in the first body,
+    /// and its angle is measured from the new rest rotation.
+    pub fn reanchor(&mut self, rigidbodies: (&RigidBody, &RigidBody), anchor: Vector3<f32>) {
+        let (a, b) = rigidbodies;
+        self.local_anchors = (a.world_to_local(anchor), b.world_to_local(anchor));
+        self.rest_rotation = a.get_rotation().conjugate() * b.get_rotation();
+        let axis = a.get_rotation() * self.local_axes.0;
+        self.local_axes.1 = b.get_rotation().conjugate() * axis;
+    }
+
+    pub fn get_motor(&self) -> Motor {
+        self.motor
+    }
+
+    /// Sets the motor driving the joint. Only hinges are driven by their motor.
+    pub fn set_motor(&mut self, motor: Motor) {
+        self.motor = motor;
+    }
+
+    /// The world space hinge axis, as held by the first body.
+    pub fn get_axis(&self, rigidbodies: (&RigidBody, &RigidBody)) -> Vector3<f32> {
+        rigidbodies.0.get_rotation() * self.local_axes.0
+    }
+
+    /// The angle in radians, in [-π, π], that the second body has turned about the hinge axis from its rest rotation.
+    pub fn get_angle(&self, rigidbodies: (&RigidBody, &RigidBody)) -> f32 {
+        let (a, b) = rigidbodies;
+        // The turn from the rest rotation, in the first body's space, where the axis is fixed.
+        let mut turn =
+            a.get_rotation().conjugate() * b.get_rotation() * self.rest_rotation.conjugate();
+        if turn.s < 0.0 {
+            turn = -turn;
+        }
+        // The twist of the turn about the axis.
+        2.0 * turn.v.dot(self.local_axes.0).atan2(turn.s)
+    }
+
+    pub fn get_kind(&self) -> JointKind {
+        self.kind
+    }
+
+    pub fn get_bodies(&self) -> (RigidBodyHandle, RigidBodyHandle) {
+        self.bodies
+    }
+
+    pub fn involves(&self, handle: RigidBodyHandle) -> bool {
+        self.bodies.0 == handle || self.bodies.1 == handle
+    }
+
+    /// The world space anchor points on each body, which coincide while the joint is at rest.
+    pub fn get_world_anchors(
+        &self,
+        rigidbodies: (&RigidBody, &RigidBody),
+    ) -> (Vector3<f32>, Vector3<f32>) {
+        let (a, b) = rigidbodies;
+        (
+            a.local_to_world(self.local_anchors.0),
+            b.local_to_world(self.local_anchors.1),
+        )
+    }
+
+    /// The spring forces pulling the bodies back to rest. Each spring is critically damped
+    /// for the reduced mass (or moment of inertia) of the pair, so joints settle without oscillating.
+    pub fn get_loads(
+        &self,
+        rigidbodies: (&RigidBody, &RigidBody),
+        stiffness: JointStiffness,
+    ) -> JointLoads {
+        let (a, b) = rigidbodies;
+        let (anchor_a, anchor_b) = self.get_world_anchors(rigidbodies);
+        let reduced_mass = a.get_mass() * b.get_mass() / (a.get_mass() + b.get_mass());
+        let linear_damping = 2.0 * (stiffness.linear * reduced_mass).sqrt();
+        let spring_force = stiffness.linear * (anchor_a - anchor_b);
+        let relative_velocity =
+            b.get_point_velocity(self.local_anchors.1) - a.get_point_velocity(self.local_anchors.0);
+        let force = spring_force - linear_damping * relative_velocity;
+
+        let reduced_inertia = 1.0 / (inverse_inertia(a) + inverse_inertia(b));
+        let angular_damping = 2.0 * (stiffness.angular * reduced_inertia).sqrt();
+        let relative_angular_velocity = b.get_angular_velocity() - a.get_angular_velocity();
+        let (torque, strain_torque) = match self.kind {
+            JointKind::Ball => (Vector3::zero(), 0.0),
+            JointKind::Hinge => {
+                // Turn the second body's axis back onto the first's, damping all but the turning about the axis.
+                let axis = self.get_axis(rigidbodies);
+                let axis_b = b.get_rotation() * self.local_axes.1;
+                let spring_torque = stiffness.angular * axis_b.cross(axis);
+                let hinge_angular_velocity = relative_angular_velocity.dot(axis);
+                let motor_torque = self
+                    .motor
+                    .get_torque(self.get_angle(rigidbodies), hinge_angular_velocity);
+                (
+                    spring_torque
+                        - angular_damping
+                            * (relative_angular_velocity - hinge_angular_velocity * axis)
+                        + motor_torque * axis,
+                    spring_torque.magnitude(),
+                )
+            }
+            JointKind::Weld => {
+                let spring_torque = -stiffness.angular * self.get_rotation_error(a, b);
+                (
+                    spring_torque - angular_damping * relative_angular_velocity,
+                    spring_torque.magnitude(),
+                )
+            }
+        };
+
+        JointLoads {
+            force,
+            torque,
+            strain_force: spring_force.magnitude(),
+            strain_torque,
+        }
+    }
+
+    /// The rotation, as an axis scaled by its angle in radians, from the rest rotation of the second body to its current rotation.
+    fn get_rotation_error(&self, a: &RigidBody, b: &RigidBody) -> Vector3<f32> {
+        let rest = a.get_rotation() * self.rest_rotation;
+        let mut error = b.get_rotation() * rest.conjugate();
+        // q and -q are the same rotation; take the shorter way around.
+        if error.s < 0.0 {
+            error = -error;
+        }
+        let sin_half_angle = error.v.magnitude();
+        if sin_half_angle < f32::EPSILON {
+            return Vector3::zero();
+        }
+        let angle = 2.0 * sin_half_angle.atan2(error.s);
+        error.v * (angle / sin_half_angle)
+    }
+}
+
+/// The rigidbody's inverse moment of inertia, averaged over the axes, to damp rotations with.
+fn inverse_inertia(rigidbody: &RigidBody) -> f32 {
+    let inverse = rigidbody.get_state().get_moment_of_inertia_inverted();
+    (inverse.x.x + inverse.y.y + inverse.z.z) / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::quantity::{self, Mass};
+    use crate::simulation::rigidbody::config::Config;
+    use crate::simulation::rigidbody::simulation::Simulation;
+
+    const SIZE: f32 = 0.25;
+    const MASS: Mass = quantity::kilograms(0.25);
+
+    /// Two blocks side by side along x, jointed at the center of their shared face.
+    /// The second block is set spinning about x by an off-center impulse, which a ball joint doesn't resist.
+    fn jointed_pair(kind: JointKind) -> (Simulation, RigidBodyHandle, RigidBodyHandle) {
+        let dimensions = Vector3::new(SIZE, SIZE, SIZE);
+        let a = RigidBody::cuboid(Vector3::zero(), MASS, dimensions).unwrap();
+        let mut b = RigidBody::cuboid(Vector3::unit_x() * SIZE, MASS, dimensions).unwrap();
+        b.apply_impulse(Vector3::unit_z() * 0.01, Vector3::unit_y() * SIZE / 2.0);
+        b.apply_impulse(-Vector3::unit_z() * 0.01, -Vector3::unit_y() * SIZE / 2.0);
+        let mut simulation = Simulation::new(vec![a, b], Vec::new());
+        let handles = simulation
+            .get_rigidbodies()
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        let (a, b) = (handles[0], handles[1]);
+        simulation
+            .add_joint(
+                kind,
+                a,
+                b,
+                Vector3::unit_x() * SIZE / 2.0,
+                Vector3::unit_x(),
+            )
+            .unwrap();
+        (simulation, a, b)
+    }
+
+    /// The angle, in radians, between the blocks' rotations.
+    fn relative_angle(simulation: &Simulation, a: RigidBodyHandle, b: RigidBodyHandle) -> f32 {
+        let rigidbodies = simulation.get_rigidbodies();
+        let relative = rigidbodies[a].get_rotation().conjugate() * rigidbodies[b].get_rotation();
+        2.0 * relative.v.magnitude().atan2(relative.s.abs())
+    }
+
+    #[test]
+    fn weld_holds_relative_pose() {
+        let (mut simulation, a, b) = jointed_pair(JointKind::Weld);
+        for _ in 0..2000 {
+            simulation.step();
+        }
+        assert_eq!(simulation.get_joints().len(), 1);
+        assert!(relative_angle(&simulation, a, b) < 0.05);
+        let handle = simulation.get_joints().iter().next().unwrap().0;
+        let (anchor_a, anchor_b) = simulation.get_joint_anchors(handle).unwrap();
+        assert!((anchor_a - anchor_b).magnitude() < 0.01);
+    }
+
+    #[test]
+    fn ball_joint_lets_bodies_rotate() {
+        let (mut simulation, a, b) = jointed_pair(JointKind::Ball);
+        for _ in 0..2000 {
+            simulation.step();
+        }
+        assert!(relative_angle(&simulation, a, b) > 0.5);
+        let handle = simulation.get_joints().iter().next().unwrap().0;
+        let (anchor_a, anchor_b) = simulation.get_joint_anchors(handle).unwrap();
+        assert!((anchor_a - anchor_b).magnitude() < 0.01);
+    }
+
+    #[test]
+    fn strained_joints_break() {
+        let dimensions = Vector3::new(SIZE, SIZE, SIZE);
+        let a = RigidBody::cuboid(Vector3::zero(), MASS, dimensions).unwrap();
+        let mut b = RigidBody::cuboid(Vector3::unit_x() * SIZE, MASS, dimensions).unwrap();
+        // Fast enough to pull past the default break force within a few steps.
+        b.apply_impulse(Vector3::unit_x() * 2.0, Vector3::zero());
+        let mut simulation = Simulation::new(vec![a, b], Vec::new());
+        let handles = simulation
+            .get_rigidbodies()
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        simulation
+            .add_joint(
+                JointKind::Weld,
+                handles[0],
+                handles[1],
+                Vector3::unit_x() * SIZE / 2.0,
+                Vector3::unit_x(),
+            )
+            .unwrap();
+        for _ in 0..100 {
+            simulation.step();
+        }
+        assert!(simulation.get_joints().is_empty());
+    }
+
+    /// How far apart the anchors of a ball jointed pair are after orbiting each other for a while,
+    /// held together against their spin only by the joint.
+    fn orbiting_separation(position_correction: f32) -> f32 {
+        let dimensions = Vector3::new(SIZE, SIZE, SIZE);
+        let mut a = RigidBody::cuboid(Vector3::zero(), MASS, dimensions).unwrap();
+        let mut b = RigidBody::cuboid(Vector3::unit_x() * SIZE, MASS, dimensions).unwrap();
+        a.apply_impulse(-Vector3::unit_z() * 0.5, Vector3::zero());
+        b.apply_impulse(Vector3::unit_z() * 0.5, Vector3::zero());
+        let mut simulation = Simulation::new(vec![a, b], Vec::new());
+        simulation.get_config_mut().breakable_joints = false;
+        simulation.get_config_mut().position_correction = position_correction;
+        let handles = simulation
+            .get_rigidbodies()
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        let joint = simulation
+            .add_joint(
+                JointKind::Ball,
+                handles[0],
+                handles[1],
+                Vector3::unit_x() * SIZE / 2.0,
+                Vector3::unit_x(),
+            )
+            .unwrap();
+        for _ in 0..5000 {
+            simulation.step();
+        }
+        let (anchor_a, anchor_b) = simulation.get_joint_anchors(joint).unwrap();
+        (anchor_a - anchor_b).magnitude()
+    }
+
+    #[test]
+    fn position_correction_holds_joints_together() {
+        let uncorrected = orbiting_separation(0.0);
+        let corrected = orbiting_separation(Config::default().position_correction);
+        // The correction is gentle next to the springs, so that it doesn't hide the strain of sudden loads
+        // from breaking joints, but it still takes a good part of the stretch out of steady ones.
+        assert!(corrected < uncorrected * 0.75);
+    }
+
+    #[test]
+    fn motor_torque_is_limited() {
+        let motor = Motor {
+            mode: MotorMode::Angle,
+            target: 3.0,
+            ..Default::default()
+        };
+        assert_eq!(motor.get_torque(0.0, 0.0), motor.max_torque);
+        // The error wraps around, so the motor turns the shorter way to the target.
+        assert!(motor.get_torque(-3.0, 0.0) < 0.0);
+        let motor = Motor {
+            mode: MotorMode::Velocity,
+            target: 1.0,
+            ..Default::default()
+        };
+        assert!(motor.get_torque(0.0, 0.0) > 0.0);
+        assert_eq!(motor.get_torque(0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn motor_drives_hinge() {
+        let (mut simulation, a, b) = jointed_pair(JointKind::Hinge);
+        let handle = simulation.get_joints().iter().next().unwrap().0;
+        simulation.set_joint_motor(
+            handle,
+            Motor {
+                mode: MotorMode::Angle,
+                target: 1.0,
+                ..Default::default()
+            },
+        );
+        for _ in 0..3000 {
+            simulation.step();
+        }
+        let rigidbodies = simulation.get_rigidbodies();
+        let bodies = (&rigidbodies[a], &rigidbodies[b]);
+        let joint = &simulation.get_joints()[handle];
+        assert!((joint.get_angle(bodies) - 1.0).abs() < 0.05);
+        // The bodies still only turn about the hinge axis.
+        let axis_b = bodies.1.get_rotation() * joint.local_axes.1;
+        assert!(axis_b.dot(joint.get_axis(bodies)) > 0.999);
+
+        simulation.set_joint_motor(
+            handle,
+            Motor {
+                mode: MotorMode::Velocity,
+                target: 2.0,
+                ..Default::default()
+            },
+        );
+        for _ in 0..2000 {
+            simulation.step();
+        }
+        let rigidbodies = simulation.get_rigidbodies();
+        let relative_angular_velocity =
+            rigidbodies[b].get_angular_velocity() - rigidbodies[a].get_angular_velocity();
+        assert!((relative_angular_velocity.x - 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn despawning_removes_joints() {
+        let (mut simulation, a, _) = jointed_pair(JointKind::Weld);
+        simulation.despawn_rigidbody(a);
+        assert!(simulation.get_joints().is_empty());
+        // Stepping with the remaining body shouldn't index the despawned one.
+        simulation.step();
+    }
+}
@@ -1,3 +1,8 @@
 pub mod config;
+pub mod contact;
+pub mod friction;
+pub mod inertia;
+pub mod joint;
 pub mod rigidbody;
+pub mod schedule;
 pub mod simulation;
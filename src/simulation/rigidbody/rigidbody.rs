@@ -1,191 +1,225 @@
 use std::time::Duration;
 
-use cgmath::{InnerSpace, Matrix, Matrix3, One, Quaternion, SquareMatrix, Vector3, Zero};
+use cgmath::{
+    ElementWise, InnerSpace, Matrix, Matrix3, One, Quaternion, SquareMatrix, Vector3, Zero,
+};
 use itertools::Itertools;
 
+use crate::math::Real;
+use crate::pool::Pool;
 use crate::simulation::{
-    collidable_mesh::{self, CollidableMesh},
+    animation::KinematicObstacle,
+    checksum::{Checksum, Checksummed},
+    collidable_mesh::{CollidableMesh, Face},
+    collision_filter::CollisionFilter,
     consts,
-    state::Stateful,
+    planar::PlanarConstraint,
+    quantity::{Mass, Quantity},
+    state::{stateful_fields, StateWriter, Stateful},
 };
 
 use super::config::Config;
+use super::friction::FrictionCone;
+use super::inertia;
+
+/// Penetrations this shallow are left alone by the position correction, so that resting bodies stay in contact.
+const PENETRATION_SLOP: f32 = 0.001;
 
 #[derive(Clone, Copy)]
 pub struct State {
+    // The integrated elements are kept in Real, and read in f32 for the rest of the math through the getters below,
+    // so that with the f64 feature each step's small changes to them aren't rounded away.
     // The position of the center of mass of the RididBody, in worldspace
-    position: Vector3<f32>,
-    rotation: Quaternion<f32>,
-    linear_momentum: Vector3<f32>,
-    angular_momentum: Vector3<f32>,
+    position: Vector3<Real>,
+    rotation: Quaternion<Real>,
+    linear_momentum: Vector3<Real>,
+    angular_momentum: Vector3<Real>,
 
-    // These elements won't vary with time, but are necessary for calculating the rest of the state.
+    // These elements aren't integrated, but are necessary for calculating the derivative of the rest of the state.
     mass: f32,
     initial_moment_of_intertia_inverted: Matrix3<f32>,
+    // The loads accumulated on the rigidbody for the step, besides those derived from the config.
     accumulated_force: Vector3<f32>,
     accumulated_torque: Vector3<f32>,
 }
 
+/// The vector in Real, to be added to the integrated state.
+fn to_real(v: Vector3<f32>) -> Vector3<Real> {
+    v.cast().unwrap()
+}
+
 impl State {
+    pub fn position(&self) -> Vector3<f32> {
+        self.position.cast().unwrap()
+    }
+
+    pub fn rotation(&self) -> Quaternion<f32> {
+        self.rotation.cast().unwrap()
+    }
+
+    pub fn linear_momentum(&self) -> Vector3<f32> {
+        self.linear_momentum.cast().unwrap()
+    }
+
+    pub fn angular_momentum(&self) -> Vector3<f32> {
+        self.angular_momentum.cast().unwrap()
+    }
+
     pub fn normalize_rotation(&mut self) {
         self.rotation = self.rotation.normalize();
     }
 
     pub fn get_moment_of_inertia_inverted(&self) -> Matrix3<f32> {
-        let rotation_matrix = Matrix3::<f32>::from(self.rotation);
-        rotation_matrix * self.initial_moment_of_intertia_inverted * rotation_matrix.transpose()
+        self.moment_of_inertia_inverted().cast().unwrap()
+    }
+
+    // In Real, for the math on the integrated state.
+    fn moment_of_inertia_inverted(&self) -> Matrix3<Real> {
+        let rotation_matrix = Matrix3::from(self.rotation);
+        let initial: Matrix3<Real> = self.initial_moment_of_intertia_inverted.cast().unwrap();
+        rotation_matrix * initial * rotation_matrix.transpose()
     }
 
     pub fn apply_impulse(&mut self, impulse: Vector3<f32>, position: Vector3<f32>) {
+        let impulse = to_real(impulse);
         self.linear_momentum += impulse;
-        self.angular_momentum += position.cross(impulse);
+        self.angular_momentum += to_real(position).cross(impulse);
     }
 
     pub fn velocity(&self) -> Vector3<f32> {
-        self.linear_momentum / self.mass
+        self.linear_momentum() / self.mass
+    }
+
+    /// Moves the rigidbody into the plane, removing its momentum along the plane's normal
+    /// and its spin about any other axis.
+    pub fn constrain_to_plane(&mut self, planar: &PlanarConstraint) {
+        self.position = planar.constrain_position(self.position);
+        self.linear_momentum = planar.constrain_velocity(self.linear_momentum);
+        let inverse_inertia = self.moment_of_inertia_inverted();
+        if let Some(inertia) = inverse_inertia.invert() {
+            self.angular_momentum = inertia
+                * planar.constrain_angular_velocity(inverse_inertia * self.angular_momentum);
+        }
     }
 
     pub fn angular_velocity(&self) -> Vector3<f32> {
-        self.get_moment_of_inertia_inverted() * self.angular_momentum
+        self.get_moment_of_inertia_inverted() * self.angular_momentum()
     }
-}
 
-impl Stateful for State {
-    fn num_state_elements() -> usize {
-        3 + // Position
-        4 + // Rotation (vector scalar representation of a Quaternion)
-        3 + // Linear Momentum
-        3 + // Angular Momentum
-        1 + // mass
-        9 + // Moment of inertia
-        3 + // accumulated force
-        3 // accumulated torque
-    }
-
-    fn as_state(&self) -> Vec<f32> {
-        let state_vec = vec![
-            self.position.x,
-            self.position.y,
-            self.position.z,
-            self.rotation.v.x,
-            self.rotation.v.y,
-            self.rotation.v.z,
-            self.rotation.s,
-            self.linear_momentum.x,
-            self.linear_momentum.y,
-            self.linear_momentum.z,
-            self.angular_momentum.x,
-            self.angular_momentum.y,
-            self.angular_momentum.z,
-            self.mass,
-            self.initial_moment_of_intertia_inverted.x.x,
-            self.initial_moment_of_intertia_inverted.x.y,
-            self.initial_moment_of_intertia_inverted.x.z,
-            self.initial_moment_of_intertia_inverted.y.x,
-            self.initial_moment_of_intertia_inverted.y.y,
-            self.initial_moment_of_intertia_inverted.y.z,
-            self.initial_moment_of_intertia_inverted.z.x,
-            self.initial_moment_of_intertia_inverted.z.y,
-            self.initial_moment_of_intertia_inverted.z.z,
-            self.accumulated_force.x,
-            self.accumulated_force.y,
-            self.accumulated_force.z,
-            self.accumulated_torque.x,
-            self.accumulated_torque.y,
-            self.accumulated_torque.z,
-        ];
-        if state_vec.len() != Self::num_state_elements() {
-            panic!("Incorrect size of state vector!");
+    /// The force on the rigidbody, its accumulated force along with gravity.
+    pub fn get_force(&self, config: &Config) -> Vector3<f32> {
+        self.accumulated_force + config.gravity
+    }
+
+    /// The torque on the rigidbody, its accumulated torque along with the config's torque and the angular drag
+    /// against its spin.
+    pub fn get_torque(&self, config: &Config) -> Vector3<f32> {
+        let angular_velocity = self.angular_velocity();
+        self.accumulated_torque + config.torque
+            - (config.angular_drag_viscous
+                + config.angular_drag_quadratic * angular_velocity.magnitude())
+                * angular_velocity
+    }
+
+    /// Steps the rotation and angular momentum on from the old state over the config's dt, under its torque,
+    /// by solving Euler's equations in the body's frame, where the gyroscopic torque ω × Iω appears.
+    /// The gyroscopic term is solved implicitly, with one Newton step, so fast spinners precess without
+    /// gaining energy as they do when the momentum and rotation are stepped explicitly.
+    pub fn step_rotation_implicitly(&mut self, old: &State, config: &Config) {
+        let dt = config.dt as Real;
+        let inertia_inverted: Matrix3<Real> =
+            old.initial_moment_of_intertia_inverted.cast().unwrap();
+        let Some(inertia) = inertia_inverted.invert() else {
+            return;
+        };
+        let old_rotation = Matrix3::from(old.rotation);
+        // The body space angular velocity, after the torque's impulse over the step.
+        let angular_velocity = inertia_inverted
+            * old_rotation.transpose()
+            * (old.angular_momentum + dt * to_real(old.get_torque(config)));
+        let gyroscopic = dt * angular_velocity.cross(inertia * angular_velocity);
+        let jacobian =
+            inertia + dt * (skew(angular_velocity) * inertia - skew(inertia * angular_velocity));
+        let angular_velocity = match jacobian.invert() {
+            Some(jacobian_inverted) => angular_velocity - jacobian_inverted * gyroscopic,
+            None => angular_velocity,
+        };
+        self.rotation = (old.rotation
+            + 0.5 * dt * old.rotation * Quaternion::from_sv(0.0, angular_velocity))
+        .normalize();
+        self.angular_momentum = Matrix3::from(self.rotation) * inertia * angular_velocity;
+    }
+
+    /// The kinetic energy of the rigidbody's translation and rotation.
+    pub fn kinetic_energy(&self) -> f32 {
+        0.5 * (self.linear_momentum().dot(self.velocity())
+            + self.angular_momentum().dot(self.angular_velocity()))
+    }
+
+    /// The impulse of Coulomb friction against the point at r from the center of mass sliding over a surface,
+    /// given the point's velocity relative to the surface. It's just enough to stop the sliding along each of
+    /// the cone's tangents, but kept within the cone of the normal impulse.
+    pub fn get_friction_impulse(
+        &self,
+        r: Vector3<f32>,
+        normal: Vector3<f32>,
+        relative_velocity: Vector3<f32>,
+        normal_impulse: f32,
+        cone: &FrictionCone,
+    ) -> Vector3<f32> {
+        if normal_impulse <= 0.0 {
+            return Vector3::zero();
         }
-        state_vec
+        let stopping_impulse = |tangent: Vector3<f32>| {
+            -relative_velocity.dot(tangent)
+                / (1.0 / self.mass
+                    + tangent
+                        .dot(self.get_moment_of_inertia_inverted() * r.cross(tangent).cross(r)))
+                * tangent
+        };
+        let (along, across) = cone.get_tangents(normal);
+        cone.clamp(
+            stopping_impulse(along) + stopping_impulse(across),
+            normal,
+            normal_impulse,
+        )
     }
+}
+
+/// The matrix which takes the cross product of the vector with whatever it multiplies.
+fn skew(v: Vector3<Real>) -> Matrix3<Real> {
+    Matrix3::new(0.0, v.z, -v.y, -v.z, 0.0, v.x, v.y, -v.x, 0.0)
+}
 
-    fn derivative(&self) -> Vec<f32> {
+impl Stateful for State {
+    type Context = Config;
+
+    stateful_fields!(
+        13;
+        position: Vector3<Real>,
+        rotation: Quaternion<Real>,
+        linear_momentum: Vector3<Real>,
+        angular_momentum: Vector3<Real>,
+    );
+
+    fn write_derivative(&self, config: &Config, derivative: &mut [Real]) {
         let position_derivative = self.velocity();
         let rotation_derivative =
-            0.5 * Quaternion::from_sv(0.0, self.angular_velocity()) * self.rotation;
-
-        let derivative_state = vec![
-            position_derivative.x,
-            position_derivative.y,
-            position_derivative.z,
-            rotation_derivative.v.x,
-            rotation_derivative.v.y,
-            rotation_derivative.v.z,
-            rotation_derivative.s,
+            0.5 * Quaternion::from_sv(0.0, self.angular_velocity()) * self.rotation();
+
+        StateWriter::new(derivative)
+            .push(&position_derivative)
+            .push(&rotation_derivative)
             // Linear momentum derivative is force
-            self.accumulated_force.x,
-            self.accumulated_force.y,
-            self.accumulated_force.z,
-            // Angular momentum derivative is torque
-            self.accumulated_torque.x,
-            self.accumulated_torque.y,
-            self.accumulated_torque.z,
-            // The remaining elements of the state are constant
-            // TODO I'd really like to remove the need for this, but the need to convert back to this Struct representation from
-            //   the state vector (state::State::from_state_vector()), means we need to associate this constant data stored in the state alongside
-            //   with the varying data, even though it's wasteful copies/ops.
-            //   Less wasteful solutions might include either:
-            //    1. In state::State, keep these constants associated with the state vectors as we reconstruct via some additional bookkeeping.
-            //    2. Stop precomputing the forces/torques, and instead derive them from the state vector itself inside the derivation function.
-            //       This is closer to how "Foundations of Physically Based Modeling and Animation" elects to represent State.
-            //       However, this is a big rework, since it requires that the full simulation state is represented in a single State vector
-            //       to allow for interaction between objects in the simulation. If that's not enough, state::State would need to allow for multiple
-            //       kinds of Stateful objects, requiring dyn Traits, but that's not possible with Stateful::from_state_vector() because it would return
-            //       Self, which isn't allowed since we can't know the size of Self at compile time. There are some potential workarounds, but for now we
-            //       just accept this additional memory usage.
-            //       TL;DR This approach needs some re-architecting.
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-        ];
-        if derivative_state.len() != Self::num_state_elements() {
-            panic!("Incorrect size of derivative state!");
-        }
-        derivative_state
+            .push(&self.get_force(config))
+            // Angular momentum derivative is torque, with the drag evaluated at each intermediate state
+            .push(&self.get_torque(config))
+            .finish()
     }
 
-    fn from_state_vector(state_data: Vec<f32>) -> Self {
-        if state_data.len() != Self::num_state_elements() {
-            panic!("Incorrect size of state vector!");
-        }
-        let position = Vector3::<f32>::new(state_data[0], state_data[1], state_data[2]);
-        let rotation_vec = Vector3::<f32>::new(state_data[3], state_data[4], state_data[5]);
-        let rotation_scalar = state_data[6];
-        let rotation = Quaternion::from_sv(rotation_scalar, rotation_vec);
-        let linear_momentum = Vector3::<f32>::new(state_data[7], state_data[8], state_data[9]);
-        let angular_momentum = Vector3::<f32>::new(state_data[10], state_data[11], state_data[12]);
-        let mass = state_data[13];
-        let moi_x = Vector3::<f32>::new(state_data[14], state_data[15], state_data[16]);
-        let moi_y = Vector3::<f32>::new(state_data[17], state_data[18], state_data[19]);
-        let moi_z = Vector3::<f32>::new(state_data[20], state_data[21], state_data[22]);
-        let moi = Matrix3::from_cols(moi_x, moi_y, moi_z);
-        let accumulated_force = Vector3::<f32>::new(state_data[23], state_data[24], state_data[25]);
-        let accumulated_torque =
-            Vector3::<f32>::new(state_data[26], state_data[27], state_data[28]);
-        State {
-            position,
-            rotation,
-            linear_momentum,
-            angular_momentum,
-            mass,
-            initial_moment_of_intertia_inverted: moi,
-            accumulated_force,
-            accumulated_torque,
-        }
+    // The position and rotation.
+    fn num_position_elements() -> usize {
+        7
     }
 }
 
@@ -194,38 +228,75 @@ pub struct RigidBody {
 
     // The collidable mesh in local coordinates, where the center of mass (State.position) is at the origin.
     mesh: CollidableMesh,
+    // The side lengths of the box the mesh was built from, or which bounds it, along its local axes.
+    dimensions: Vector3<f32>,
+
+    collision_filter: CollisionFilter,
 }
 
 impl RigidBody {
-    // TODO we will add vector positions/indices in as params for this, and calculate the moment of intertia, center of mass etc from that.
+    // TODO we will add vector positions/indices in as params for this, and recenter the mesh on its center of mass.
     //      For now, we are working with only a 1x1x1 cube.
-    pub fn new(position: Vector3<f32>, mass: f32) -> Result<RigidBody, &'static str> {
-        let (cube_vertices, cube_indices) = crate::graphics::forms::get_cube_vertices();
+    pub fn new(position: Vector3<f32>, mass: Mass) -> Result<RigidBody, &'static str> {
+        RigidBody::cuboid(position, mass, Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    /// A box with the given side lengths along its local axes.
+    pub fn cuboid(
+        position: Vector3<f32>,
+        mass: Mass,
+        dimensions: Vector3<f32>,
+    ) -> Result<RigidBody, &'static str> {
+        let mass = mass.value();
+        let (cube_vertices, cube_indices) = crate::simulation::geometry::get_cube_vertices();
+        let cube_vertices = cube_vertices
+            .iter()
+            .map(|v| v.mul_element_wise(dimensions))
+            .collect_vec();
+        let moment_of_inertia = inertia::mesh(&cube_vertices, &cube_indices, mass)
+            .ok_or("Rigidbody mesh encloses no volume!")?
+            .inertia;
         let mesh = CollidableMesh::new(cube_vertices, cube_indices);
+        RigidBody::from_mesh(position, mass, moment_of_inertia, mesh, dimensions)
+    }
 
-        let moment_of_inertia = Matrix3::<f32>::new(
-            mass / 6.0,
-            0.0,
-            0.0,
-            0.0,
-            mass / 6.0,
-            0.0,
-            0.0,
-            0.0,
-            mass / 6.0,
-        );
+    /// A solid ball of the given radius. It collides with obstacles by the vertices of a faceted sphere.
+    pub fn sphere(
+        position: Vector3<f32>,
+        mass: Mass,
+        radius: f32,
+    ) -> Result<RigidBody, &'static str> {
+        let mass = mass.value();
+        let (vertices, indices) = crate::simulation::geometry::get_sphere_vertices(radius, 16, 8);
+        let mesh = CollidableMesh::new(vertices, indices);
+        let diameter = 2.0 * radius;
+        RigidBody::from_mesh(
+            position,
+            mass,
+            inertia::sphere(mass, radius),
+            mesh,
+            Vector3::new(diameter, diameter, diameter),
+        )
+    }
 
+    fn from_mesh(
+        position: Vector3<f32>,
+        mass: f32,
+        moment_of_inertia: Matrix3<f32>,
+        mesh: CollidableMesh,
+        dimensions: Vector3<f32>,
+    ) -> Result<RigidBody, &'static str> {
         let initial_moment_of_intertia_inverted = moment_of_inertia
             .invert()
             .ok_or("Uninvertable moment of inertia!")?;
 
         let rotation = Quaternion::one();
 
-        let linear_momentum = mass * Vector3::<f32>::zero();
-        let angular_momentum = moment_of_inertia * Vector3::<f32>::zero();
+        let linear_momentum = to_real(mass * Vector3::<f32>::zero());
+        let angular_momentum = to_real(moment_of_inertia * Vector3::<f32>::zero());
 
         let state = State {
-            position,
+            position: to_real(position),
             rotation,
             linear_momentum,
             angular_momentum,
@@ -235,29 +306,51 @@ impl RigidBody {
             accumulated_torque: Vector3::<f32>::zero(),
         };
 
-        Ok(RigidBody { state, mesh })
+        Ok(RigidBody {
+            state,
+            mesh,
+            dimensions,
+            collision_filter: CollisionFilter::new(
+                CollisionFilter::RIGIDBODY,
+                CollisionFilter::ALL,
+            ),
+        })
+    }
+
+    /// Turns the rigidbody to the rotation, e.g. to lay it out before the simulation begins.
+    pub fn with_rotation(mut self, rotation: Quaternion<f32>) -> RigidBody {
+        self.state.rotation = rotation.normalize().cast().unwrap();
+        self
     }
 
     pub fn get_state(&self) -> &State {
         &self.state
     }
 
+    /// Moves the rigidbody to the new state, responding to its collisions over the step.
+    /// Returns the summed magnitude of the contact impulses the rigidbody received.
     pub fn update_state(
         &mut self,
         mut new_state: State,
-        obstacles: &Vec<collidable_mesh::CollidableMesh>,
+        obstacles: &Pool<CollidableMesh>,
+        kinematic_obstacles: &Pool<KinematicObstacle>,
         config: &Config,
-    ) {
+    ) -> f32 {
         // The new state might need to be modified if there is a collision.
         //   For now, we are just going to pass in static obstacles, so we don't need to get obstacles from a rigidbody or whatever, that's good.
         //   We will need to use the new state's pos and rot to get new positions for verts to test etc.
+        // Each obstacle's faces are kept with it, for its surface velocity.
         let obstacle_faces = obstacles
-            .iter()
-            .map(|o| o.get_faces())
-            .flatten()
+            .values()
+            .filter(|o| {
+                self.collision_filter
+                    .collides_with(o.get_collision_filter())
+            })
+            .map(|o| (o, o.get_faces().iter().collect_vec()))
             .collect_vec();
 
         // Handle collisions between this rigidbody's vertices, and the world's faces.
+        let mut contact_impulse = 0.0;
 
         let vertices_old_world_positions = self
             .mesh
@@ -271,21 +364,27 @@ impl RigidBody {
             .get_vertices()
             .to_owned()
             .iter()
-            .map(|v| Matrix3::<f32>::from(new_state.rotation) * v.position() + new_state.position)
+            .map(|v| {
+                Matrix3::<f32>::from(new_state.rotation()) * v.position() + new_state.position()
+            })
             .collect_vec();
         for (new_point, old_point) in vertices_new_world_positions
             .iter()
             .zip(vertices_old_world_positions.iter())
         {
-            if let Some(face) = CollidableMesh::get_collided_face_from_list(
-                &obstacle_faces,
-                *old_point,
-                *new_point,
-                Duration::from_secs_f32(config.dt),
-            ) {
+            if let Some((obstacle, face)) = obstacle_faces.iter().find_map(|(obstacle, faces)| {
+                CollidableMesh::get_collided_face_from_list(
+                    faces,
+                    obstacle.get_sidedness(),
+                    *old_point,
+                    *new_point,
+                    Duration::from_secs_f32(config.dt),
+                )
+                .map(|face| (obstacle, face))
+            }) {
                 let old_distance_to_plane = face.distance_from_plane(&old_point);
                 let new_distance_to_plane = face.distance_from_plane(&new_point);
-                let r = old_point - self.state.position;
+                let r = old_point - self.state.position();
 
                 let fraction_timestep =
                     old_distance_to_plane / (old_distance_to_plane - new_distance_to_plane);
@@ -304,26 +403,180 @@ impl RigidBody {
                             self.state.get_moment_of_inertia_inverted()
                                 * r.cross(face.normal()).cross(r),
                         ));
-                let impulse = impulse_magnitude * face.normal();
-
-                new_state.position = collision_point - r + consts::EPSILON * 2.0 * face.normal();
+                let friction_impulse = self.state.get_friction_impulse(
+                    r,
+                    face.normal(),
+                    collision_velocity - obstacle.get_surface_velocity(face),
+                    impulse_magnitude,
+                    &config.get_friction_cone(),
+                );
+                let impulse = impulse_magnitude * face.normal() + friction_impulse;
+
+                new_state.position =
+                    to_real(collision_point - r + consts::EPSILON * 2.0 * face.normal());
                 new_state.apply_impulse(impulse, r);
+                contact_impulse += impulse.magnitude();
             }
         }
 
         // TODO this can further be improved by handling edge-edge collision, and
         //  by handling collisions between the world's vertices and this rigidbody's faces.
 
+        for obstacle in kinematic_obstacles.values().filter(|o| {
+            self.collision_filter
+                .collides_with(o.get_mesh().get_collision_filter())
+        }) {
+            contact_impulse +=
+                self.collide_with_kinematic_obstacle(&mut new_state, obstacle, config);
+        }
+
+        self.correct_penetration(&mut new_state, &obstacle_faces, config.position_correction);
+
         self.state = new_state;
+        contact_impulse
     }
 
-    /// Accumulates the body forces on the rigidbody
-    pub fn accumulate_forces(&mut self, config: &Config) {
-        self.state.accumulated_force += config.gravity;
+    /// Moves the new state out of the one-sided obstacles by the fraction of its deepest penetration past the slop.
+    /// Only the position changes, so that the correction doesn't bounce the body up as a biased impulse would.
+    fn correct_penetration(
+        &self,
+        new_state: &mut State,
+        obstacle_faces: &[(&CollidableMesh, Vec<&Face>)],
+        position_correction: f32,
+    ) {
+        let rotation = Matrix3::<f32>::from(new_state.rotation());
+        let deepest = self
+            .mesh
+            .get_vertices()
+            .iter()
+            .map(|vertex| rotation * vertex.position() + new_state.position())
+            .filter_map(|point| {
+                obstacle_faces
+                    .iter()
+                    .filter_map(|(obstacle, _)| obstacle.get_penetration(point))
+                    .max_by(|a, b| a.depth.total_cmp(&b.depth))
+            })
+            .max_by(|a, b| a.depth.total_cmp(&b.depth));
+        if let Some(penetration) = deepest {
+            new_state.position += to_real(
+                position_correction
+                    * (penetration.depth - PENETRATION_SLOP).max(0.0)
+                    * penetration.face.normal(),
+            );
+        }
     }
 
-    pub fn accumulate_torques(&mut self, config: &Config) {
-        self.state.accumulated_torque += config.torque;
+    /// Handles collisions between this rigidbody's vertices and the faces of an obstacle which moved over the step.
+    /// Each vertex is followed in the obstacle's local space, where its faces are still, so that the obstacle
+    /// sweeping into a vertex is detected just as the vertex moving into the obstacle is.
+    /// Returns the summed magnitude of the contact impulses.
+    fn collide_with_kinematic_obstacle(
+        &self,
+        new_state: &mut State,
+        obstacle: &KinematicObstacle,
+        config: &Config,
+    ) -> f32 {
+        let mut contact_impulse = 0.0;
+        let faces = obstacle.get_mesh().get_faces().iter().collect_vec();
+        let old_transform = obstacle.get_previous_transform();
+        let new_transform = obstacle.get_transform();
+        let new_rotation = Matrix3::<f32>::from(new_state.rotation());
+        for vertex in self.mesh.get_vertices().iter() {
+            let old_point = self.get_rotation_matrix() * vertex.position() + self.get_position();
+            let new_point = new_rotation * vertex.position() + new_state.position();
+            let old_local = old_transform.inverse_transform_point(old_point);
+            let new_local = new_transform.inverse_transform_point(new_point);
+            let face = match CollidableMesh::get_collided_face_from_list(
+                &faces,
+                obstacle.get_mesh().get_sidedness(),
+                old_local,
+                new_local,
+                Duration::from_secs_f32(config.dt),
+            ) {
+                Some(face) => face,
+                None => continue,
+            };
+
+            let old_distance_to_plane = face.distance_from_plane(&old_local);
+            let new_distance_to_plane = face.distance_from_plane(&new_local);
+            let fraction_timestep =
+                old_distance_to_plane / (old_distance_to_plane - new_distance_to_plane);
+            let collision_local = old_local + fraction_timestep * (new_local - old_local);
+            // The normal facing the side of the face that the vertex came from.
+            let normal =
+                new_transform.transform_vector(face.normal()) * old_distance_to_plane.signum();
+
+            let r = old_point - self.state.position();
+            let collision_velocity = new_state.velocity() + new_state.angular_velocity().cross(r);
+            let surface_velocity =
+                new_transform.transform_vector(obstacle.get_mesh().get_surface_velocity(face));
+            let relative_velocity = collision_velocity
+                - obstacle.get_point_velocity(collision_local, config.dt)
+                - surface_velocity;
+            let normal_velocity = relative_velocity.dot(normal);
+            if normal_velocity >= 0.0 {
+                continue;
+            }
+            let impulse_magnitude = (-(1.0 + config.coefficient_of_restitution) * normal_velocity)
+                / (1.0 / new_state.mass
+                    + normal.dot(
+                        new_state.get_moment_of_inertia_inverted() * r.cross(normal).cross(r),
+                    ));
+
+            let friction_impulse = new_state.get_friction_impulse(
+                r,
+                normal,
+                relative_velocity,
+                impulse_magnitude,
+                &config.get_friction_cone(),
+            );
+
+            let collision_point = new_transform.transform_point(collision_local);
+            new_state.position = to_real(collision_point - r + consts::EPSILON * 2.0 * normal);
+            let impulse = impulse_magnitude * normal + friction_impulse;
+            new_state.apply_impulse(impulse, r);
+            contact_impulse += impulse.magnitude();
+        }
+        contact_impulse
+    }
+
+    /// Accumulates the air resistance on the rigidbody, given the wind at its position. The other body forces
+    /// and torques are derived from the config as the state is integrated; see State::get_force() and get_torque().
+    pub fn accumulate_forces(&mut self, config: &Config, wind: Vector3<f32>) {
+        self.state.accumulated_force += self.get_air_resistance(config, wind);
+    }
+
+    /// The drag force on the rigidbody due to its motion relative to the wind.
+    /// The quadratic term acts on the area the body presents to the relative flow,
+    /// so a still body in a wind is pushed along with it.
+    fn get_air_resistance(&self, config: &Config, wind: Vector3<f32>) -> Vector3<f32> {
+        let relative_velocity = self.state.velocity() - wind;
+        let speed = relative_velocity.magnitude();
+        if speed < consts::EPSILON {
+            return Vector3::<f32>::zero();
+        }
+        // The mesh is in local coordinates, so view it along the flow in local coordinates.
+        let local_direction = self.get_rotation_matrix().transpose() * relative_velocity;
+        let projected_area = self.mesh.projected_area(local_direction);
+        -(config.linear_drag_viscous + config.linear_drag_quadratic * projected_area * speed)
+            * relative_velocity
+    }
+
+    /// Adds a force through the center of mass, to be applied on the next step.
+    pub fn add_force(&mut self, force: Vector3<f32>) {
+        self.state.accumulated_force += force;
+    }
+
+    /// Adds a force applied at the point, given relative to the center of mass in world space,
+    /// to be applied on the next step.
+    pub fn add_force_at(&mut self, force: Vector3<f32>, position: Vector3<f32>) {
+        self.state.accumulated_force += force;
+        self.state.accumulated_torque += position.cross(force);
+    }
+
+    /// Adds a torque to be applied on the next step.
+    pub fn add_torque(&mut self, torque: Vector3<f32>) {
+        self.state.accumulated_torque += torque;
     }
 
     pub fn clear_forces(&mut self) {
@@ -335,20 +588,315 @@ impl RigidBody {
     }
 
     pub fn get_rotation_matrix(&self) -> Matrix3<f32> {
-        Matrix3::<f32>::from(self.state.rotation)
+        Matrix3::<f32>::from(self.get_rotation())
+    }
+
+    pub fn get_position(&self) -> Vector3<f32> {
+        self.state.position()
+    }
+
+    pub fn get_rotation(&self) -> Quaternion<f32> {
+        self.state.rotation()
+    }
+
+    /// The principal axes of inertia in worldspace, as the columns of the matrix.
+    pub fn get_principal_axes(&self) -> Matrix3<f32> {
+        // The inverse of the inertia tensor shares its eigenvectors.
+        let (_, body_axes) =
+            inertia::principal_axes(&self.state.initial_moment_of_intertia_inverted);
+        self.get_rotation_matrix() * body_axes
+    }
+
+    pub fn get_angular_velocity(&self) -> Vector3<f32> {
+        self.state.angular_velocity()
+    }
+
+    /// Stops the rigidbody moving and spinning, e.g. as it falls asleep.
+    pub fn stop(&mut self) {
+        self.state.linear_momentum = Vector3::zero();
+        self.state.angular_momentum = Vector3::zero();
+    }
+
+    /// Moves the rigidbody by the offset, e.g. to push it out of a body it's sunk into.
+    pub fn translate(&mut self, offset: Vector3<f32>) {
+        self.state.position += to_real(offset);
+    }
+
+    pub fn constrain_to_plane(&mut self, planar: &PlanarConstraint) {
+        self.state.constrain_to_plane(planar);
+    }
+
+    /// Gets the faces of the rigidbody's mesh in world space, e.g. to cast rays against.
+    pub fn get_world_faces(&self) -> Vec<Face> {
+        self.mesh
+            .get_faces()
+            .iter()
+            .map(|face| Face {
+                v0: self.local_to_world(face.v0),
+                v1: self.local_to_world(face.v1),
+                v2: self.local_to_world(face.v2),
+            })
+            .collect()
+    }
+
+    /// Converts a point from world space to the rigidbody's local space.
+    pub fn world_to_local(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.get_rotation_matrix().transpose() * (point - self.get_position())
+    }
+
+    /// Converts a point from the rigidbody's local space to world space.
+    pub fn local_to_world(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.get_rotation_matrix() * point + self.get_position()
+    }
+
+    /// The world space velocity of a point given in the rigidbody's local space.
+    pub fn get_point_velocity(&self, point: Vector3<f32>) -> Vector3<f32> {
+        let r = self.get_rotation_matrix() * point;
+        self.state.velocity() + self.state.angular_velocity().cross(r)
+    }
+
+    pub fn get_mass(&self) -> f32 {
+        self.state.mass
     }
 
-    pub fn get_position(&self) -> &Vector3<f32> {
-        &self.state.position
+    pub fn set_collision_mask(&mut self, mask: u32) {
+        self.collision_filter.mask = mask;
     }
 
     pub fn get_mesh(&self) -> &CollidableMesh {
         &self.mesh
     }
 
+    pub fn get_dimensions(&self) -> Vector3<f32> {
+        self.dimensions
+    }
+
     /// Applies the impulse, updating the linear and angular momentum.
     /// The position describes the vector from the center of mass to the point that the impulse is applied.
     pub fn apply_impulse(&mut self, impulse: Vector3<f32>, position: Vector3<f32>) {
         self.state.apply_impulse(impulse, position);
     }
 }
+
+impl Checksummed for RigidBody {
+    fn add_to_checksum(&self, checksum: &mut Checksum) {
+        checksum.add_vector3(self.state.position());
+        checksum.add_quaternion(self.state.rotation());
+        checksum.add_vector3(self.state.linear_momentum());
+        checksum.add_vector3(self.state.angular_momentum());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::collidable_mesh::Sidedness;
+    use crate::simulation::configurable::Configurable;
+    use crate::simulation::quantity;
+    use crate::simulation::rigidbody::friction::AnisotropicFriction;
+    use crate::simulation::rigidbody::simulation::Simulation;
+    use crate::simulation::state::{AdaptiveTimestep, Integration};
+
+    #[test]
+    fn conveyor_drags_rigidbody() {
+        let floor = CollidableMesh::new(
+            vec![
+                Vector3::new(-2.0, 0.0, -2.0),
+                Vector3::new(2.0, 0.0, -2.0),
+                Vector3::new(2.0, 0.0, 2.0),
+                Vector3::new(-2.0, 0.0, 2.0),
+            ],
+            vec![0, 2, 1, 0, 3, 2],
+        )
+        .with_surface_velocity(Vector3::new(1.0, 0.0, 0.0));
+        let obstacles = vec![floor].into_iter().collect();
+        let mut rigidbody =
+            RigidBody::new(Vector3::new(0.0, 0.505, 0.0), quantity::kilograms(1.0)).unwrap();
+        // The rigidbody falls onto the belt, which slides along X.
+        rigidbody.state.linear_momentum = Vector3::new(0.0, -1.0, 0.0);
+        let mut new_state = rigidbody.state;
+        new_state.position.y -= 0.01;
+        rigidbody.update_state(new_state, &obstacles, &Pool::new(), &Config::default());
+        let velocity = rigidbody.get_state().velocity();
+        assert!(velocity.y > 0.0);
+        // Friction with the belt drags it along.
+        assert!(velocity.x > 0.0 && velocity.z.abs() < 1e-5);
+    }
+
+    #[test]
+    fn anisotropic_friction_grips_across_the_grain() {
+        let floor = CollidableMesh::new(
+            vec![
+                Vector3::new(-2.0, 0.0, -2.0),
+                Vector3::new(2.0, 0.0, -2.0),
+                Vector3::new(2.0, 0.0, 2.0),
+                Vector3::new(-2.0, 0.0, 2.0),
+            ],
+            vec![0, 2, 1, 0, 3, 2],
+        );
+        let obstacles = vec![floor].into_iter().collect();
+        let config = Config {
+            anisotropic_friction: Some(AnisotropicFriction {
+                grain: Vector3::unit_x(),
+                friction_along_grain: 0.05,
+            }),
+            ..Default::default()
+        };
+        // The rigidbody's speed along the floor after landing on it while sliding in the direction.
+        let sliding_speed = |direction: Vector3<f32>| {
+            let mut rigidbody =
+                RigidBody::new(Vector3::new(0.0, 0.505, 0.0), quantity::kilograms(1.0)).unwrap();
+            rigidbody.state.linear_momentum = to_real(Vector3::new(0.0, -1.0, 0.0) + direction);
+            let mut new_state = rigidbody.state;
+            new_state.position.y -= 0.01;
+            rigidbody.update_state(new_state, &obstacles, &Pool::new(), &config);
+            rigidbody.get_state().velocity().dot(direction)
+        };
+        assert!(sliding_speed(Vector3::unit_x()) > sliding_speed(Vector3::unit_z()) + 0.1);
+    }
+
+    /// A flat box spun fast about an axis near its intermediate one, free of any loads, with the integration
+    /// and gyroscopic solve, returning its kinetic energy and angular momentum before and after a second.
+    fn spin_freely(implicit_gyroscopic: bool) -> ((f32, Vector3<f32>), (f32, Vector3<f32>)) {
+        let mut rigidbody = RigidBody::cuboid(
+            Vector3::zero(),
+            quantity::kilograms(1.0),
+            Vector3::new(1.0, 0.5, 0.1),
+        )
+        .unwrap();
+        rigidbody.state.angular_momentum = Vector3::new(0.05, 2.0, 0.05);
+        let mut simulation = Simulation::new(vec![rigidbody], Vec::new());
+        let config = simulation.get_config_mut();
+        config.integration = Integration::Euler;
+        config.implicit_gyroscopic = implicit_gyroscopic;
+        let measure = |simulation: &Simulation| {
+            let state = *simulation
+                .get_rigidbodies()
+                .values()
+                .next()
+                .unwrap()
+                .get_state();
+            (state.kinetic_energy(), state.angular_momentum())
+        };
+        let before = measure(&simulation);
+        for _ in 0..1000 {
+            simulation.step();
+        }
+        (before, measure(&simulation))
+    }
+
+    #[test]
+    fn implicit_gyroscopic_spinner_keeps_its_energy() {
+        let ((energy, momentum), (implicit_energy, implicit_momentum)) = spin_freely(true);
+        let (_, (explicit_energy, _)) = spin_freely(false);
+        // Explicit steps gain energy as the box tumbles; the implicit solve only loses a little.
+        assert!(
+            explicit_energy > energy * 1.01,
+            "{} {}",
+            energy,
+            explicit_energy
+        );
+        assert!(implicit_energy <= energy * 1.001 && implicit_energy > energy * 0.9);
+        // With no torque, the momentum holds still in world space as the box tumbles.
+        assert!((implicit_momentum - momentum).magnitude() < 0.05 * momentum.magnitude());
+    }
+
+    #[cfg(feature = "f64")]
+    #[test]
+    fn f64_keeps_slow_motions_far_from_the_origin() {
+        let start = Vector3::new(1.0e4, 0.0, 0.0);
+        let mut rigidbody =
+            RigidBody::cuboid(start, quantity::kilograms(1.0), Vector3::new(1.0, 1.0, 1.0))
+                .unwrap();
+        // Each step moves it a millionth of a meter, far below f32's precision ten kilometers out.
+        let velocity = Vector3::new(1.0e-3, 0.0, 0.0);
+        rigidbody.state.linear_momentum = velocity;
+        rigidbody.state.angular_momentum = Vector3::new(0.0, 1.0e-3, 2.0e-3);
+        let before = rigidbody.state;
+        let mut simulation = Simulation::new(vec![rigidbody], Vec::new());
+        let steps = 100_000;
+        for _ in 0..steps {
+            simulation.step();
+        }
+        let after = *simulation
+            .get_rigidbodies()
+            .values()
+            .next()
+            .unwrap()
+            .get_state();
+        let seconds = steps as Real * simulation.get_config().dt as Real;
+        // It travels a tenth of a meter, where f32 would leave it still.
+        assert!((after.position - (to_real(start) + velocity * seconds)).magnitude() < 1.0e-6);
+        // Nothing acts on it, so its momenta hold, and it keeps its energy.
+        assert!((after.linear_momentum - before.linear_momentum).magnitude() < 1.0e-15);
+        assert!((after.angular_momentum - before.angular_momentum).magnitude() < 1.0e-12);
+        assert!((after.rotation.magnitude() - 1.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn adaptive_dt_shrinks_for_fast_spins() {
+        let adaptive = AdaptiveTimestep::default();
+        let dt_after_spinning = |angular_momentum| {
+            let mut rigidbody = RigidBody::cuboid(
+                Vector3::zero(),
+                quantity::kilograms(1.0),
+                Vector3::new(1.0, 0.5, 0.1),
+            )
+            .unwrap();
+            rigidbody.state.angular_momentum = angular_momentum;
+            let mut simulation = Simulation::new(vec![rigidbody], Vec::new());
+            let config = simulation.get_config_mut();
+            config.integration = Integration::Rk45;
+            config.implicit_gyroscopic = false;
+            config.adaptive_dt = Some(adaptive);
+            for _ in 0..100 {
+                simulation.step();
+            }
+            simulation.get_config().dt
+        };
+        // A still body's steps have no error, so the timestep grows to its bound.
+        assert_eq!(dt_after_spinning(Vector3::zero()), adaptive.max_dt);
+        let dt = dt_after_spinning(Vector3::new(0.05, 20.0, 0.05));
+        assert!(dt < adaptive.max_dt / 2.0, "{}", dt);
+    }
+
+    #[test]
+    fn implicit_gyroscopic_applies_torque() {
+        let mut rigidbody = RigidBody::new(Vector3::zero(), quantity::kilograms(1.0)).unwrap();
+        rigidbody.add_torque(Vector3::new(0.0, 0.0, 2.0));
+        let mut new_state = rigidbody.state;
+        let config = Config {
+            dt: 0.5,
+            ..Default::default()
+        };
+        new_state.step_rotation_implicitly(&rigidbody.state, &config);
+        // A cube spins up about the torque's axis, with no gyroscopic torque to turn it away.
+        assert!((new_state.angular_momentum() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn position_correction_lifts_sunken_rigidbody() {
+        let floor = CollidableMesh::new(
+            vec![
+                Vector3::new(-2.0, 0.0, -2.0),
+                Vector3::new(2.0, 0.0, -2.0),
+                Vector3::new(2.0, 0.0, 2.0),
+                Vector3::new(-2.0, 0.0, 2.0),
+            ],
+            vec![0, 2, 1, 0, 3, 2],
+        )
+        .with_sidedness(Sidedness::OneSided);
+        let obstacles = vec![floor].into_iter().collect();
+        let config = Config::default();
+        // A unit cube resting 0.1 deep in the floor.
+        let mut rigidbody =
+            RigidBody::new(Vector3::new(0.0, 0.4, 0.0), quantity::kilograms(1.0)).unwrap();
+        for _ in 0..1000 {
+            rigidbody.update_state(rigidbody.state, &obstacles, &Pool::new(), &config);
+        }
+        let y = rigidbody.get_position().y;
+        assert!(y > 0.49 && y < 0.5, "{}", y);
+        // Only the position's corrected, so the body isn't launched out of the floor.
+        assert!(rigidbody.get_state().velocity().magnitude() < 1e-6);
+    }
+}
@@ -0,0 +1,221 @@
+/// Time-varying forces and torques applied to rigidbodies over an interval of simulation time.
+use cgmath::{Matrix3, Vector3, VectorSpace, Zero};
+
+/// A vector valued function of the time, in seconds, since its load began.
+#[derive(Debug, Clone)]
+pub enum Curve {
+    Constant(Vector3<f32>),
+    /// Linearly interpolates between (time, value) keyframes, which must be sorted by time.
+    /// Holds the first and last values outside of the keyframes' range.
+    Keyframes(Vec<(f32, Vector3<f32>)>),
+    /// amplitude * sin(2π * frequency * t + phase), with frequency in Hz and phase in radians.
+    Sine {
+        amplitude: Vector3<f32>,
+        frequency: f32,
+        phase: f32,
+    },
+}
+
+impl Curve {
+    pub fn evaluate(&self, time: f32) -> Vector3<f32> {
+        match self {
+            Curve::Constant(value) => *value,
+            Curve::Keyframes(keyframes) => {
+                let next_index = keyframes.iter().position(|(t, _)| *t > time);
+                match next_index {
+                    None => keyframes.last().map_or(Vector3::zero(), |(_, v)| *v),
+                    Some(0) => keyframes[0].1,
+                    Some(index) => {
+                        let (t0, v0) = keyframes[index - 1];
+                        let (t1, v1) = keyframes[index];
+                        v0.lerp(v1, (time - t0) / (t1 - t0))
+                    }
+                }
+            }
+            Curve::Sine {
+                amplitude,
+                frequency,
+                phase,
+            } => amplitude * (2.0 * std::f32::consts::PI * frequency * time + phase).sin(),
+        }
+    }
+}
+
+/// An axis to apply a load about, either fixed in the world or fixed to the body.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Axis {
+    WorldX,
+    WorldY,
+    WorldZ,
+    BodyX,
+    BodyY,
+    BodyZ,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 6] = [
+        Axis::WorldX,
+        Axis::WorldY,
+        Axis::WorldZ,
+        Axis::BodyX,
+        Axis::BodyY,
+        Axis::BodyZ,
+    ];
+
+    /// The axis in world space, for a body with the given rotation.
+    pub fn direction(&self, rotation: &Matrix3<f32>) -> Vector3<f32> {
+        match self {
+            Axis::WorldX => Vector3::unit_x(),
+            Axis::WorldY => Vector3::unit_y(),
+            Axis::WorldZ => Vector3::unit_z(),
+            Axis::BodyX => rotation.x,
+            Axis::BodyY => rotation.y,
+            Axis::BodyZ => rotation.z,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Axis::WorldX => "World X",
+            Axis::WorldY => "World Y",
+            Axis::WorldZ => "World Z",
+            Axis::BodyX => "Body X",
+            Axis::BodyY => "Body Y",
+            Axis::BodyZ => "Body Z",
+        }
+    }
+}
+
+/// The shape of a torque applied over some duration, for building curves from the GUI.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Profile {
+    Constant,
+    /// Ramps linearly up to the peak at the halfway point, then back down.
+    Ramp,
+    /// A single period of a sine wave.
+    Oscillating,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 3] = [Profile::Constant, Profile::Ramp, Profile::Oscillating];
+
+    /// A curve with this profile, peaking at the value, over the duration in seconds.
+    pub fn curve(&self, value: Vector3<f32>, duration: f32) -> Curve {
+        match self {
+            Profile::Constant => Curve::Constant(value),
+            Profile::Ramp => Curve::Keyframes(vec![
+                (0.0, Vector3::zero()),
+                (duration / 2.0, value),
+                (duration, Vector3::zero()),
+            ]),
+            Profile::Oscillating => Curve::Sine {
+                amplitude: value,
+                frequency: 1.0 / duration,
+                phase: 0.0,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Constant => "Constant",
+            Profile::Ramp => "Ramp",
+            Profile::Oscillating => "Oscillating",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LoadKind {
+    /// A force through the center of mass.
+    Force,
+    Torque,
+}
+
+/// A curve applied as a force or torque, starting at some simulation time.
+#[derive(Debug, Clone)]
+pub struct ScheduledLoad {
+    pub kind: LoadKind,
+    pub curve: Curve,
+    /// Simulation time, in seconds, at which the load begins.
+    pub start: f32,
+    /// Duration in seconds, or None if the load should be applied indefinitely.
+    pub duration: Option<f32>,
+}
+
+impl ScheduledLoad {
+    /// The value of the load at the simulation time, or None if it's not active at that time.
+    pub fn evaluate(&self, time: f32) -> Option<Vector3<f32>> {
+        if time < self.start || self.is_finished(time) {
+            return None;
+        }
+        Some(self.curve.evaluate(time - self.start))
+    }
+
+    pub fn is_finished(&self, time: f32) -> bool {
+        self.duration
+            .is_some_and(|duration| time >= self.start + duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, InnerSpace, Matrix3, Vector3};
+
+    use super::{Axis, Curve, LoadKind, Profile, ScheduledLoad};
+
+    const TOLERANCE: f32 = 0.0001;
+
+    #[test]
+    fn keyframes() {
+        let curve = Curve::Keyframes(vec![
+            (1.0, Vector3::new(0.0, 0.0, 0.0)),
+            (2.0, Vector3::new(2.0, 0.0, 0.0)),
+            (4.0, Vector3::new(2.0, 4.0, 0.0)),
+        ]);
+        assert_eq!(Vector3::new(0.0, 0.0, 0.0), curve.evaluate(0.0));
+        assert!((Vector3::new(1.0, 0.0, 0.0) - curve.evaluate(1.5)).magnitude() < TOLERANCE);
+        assert!((Vector3::new(2.0, 1.0, 0.0) - curve.evaluate(2.5)).magnitude() < TOLERANCE);
+        assert_eq!(Vector3::new(2.0, 4.0, 0.0), curve.evaluate(10.0));
+    }
+
+    #[test]
+    fn sine() {
+        let curve = Curve::Sine {
+            amplitude: Vector3::new(0.0, 2.0, 0.0),
+            frequency: 0.5,
+            phase: 0.0,
+        };
+        assert!((Vector3::new(0.0, 2.0, 0.0) - curve.evaluate(0.5)).magnitude() < TOLERANCE);
+        assert!(curve.evaluate(1.0).magnitude() < TOLERANCE);
+    }
+
+    #[test]
+    fn scheduled_load_interval() {
+        let load = ScheduledLoad {
+            kind: LoadKind::Torque,
+            curve: Curve::Constant(Vector3::unit_x()),
+            start: 1.0,
+            duration: Some(0.5),
+        };
+        assert_eq!(None, load.evaluate(0.5));
+        assert_eq!(Some(Vector3::unit_x()), load.evaluate(1.25));
+        assert_eq!(None, load.evaluate(1.5));
+        assert!(load.is_finished(1.5));
+    }
+
+    #[test]
+    fn axis_direction() {
+        let rotation = Matrix3::from_angle_z(Deg(90.0));
+        assert_eq!(Vector3::unit_x(), Axis::WorldX.direction(&rotation));
+        assert!((Vector3::unit_y() - Axis::BodyX.direction(&rotation)).magnitude() < TOLERANCE);
+    }
+
+    #[test]
+    fn ramp_profile() {
+        let curve = Profile::Ramp.curve(Vector3::unit_z(), 2.0);
+        assert!((Vector3::unit_z() - curve.evaluate(1.0)).magnitude() < TOLERANCE);
+        assert!((Vector3::unit_z() * 0.5 - curve.evaluate(1.5)).magnitude() < TOLERANCE);
+        assert!(curve.evaluate(2.0).magnitude() < TOLERANCE);
+    }
+}
@@ -1,16 +1,66 @@
 use std::time::Duration;
 
+use crate::simulation::configurable::Configurable;
+use cgmath::{InnerSpace, Vector3};
+
+use crate::pool::{Handle, Pool};
 use crate::simulation::{
+    animation::KinematicObstacle,
+    checksum::{Checksum, Checksummed},
     collidable_mesh::CollidableMesh,
-    state::{Integration, State},
+    energy::EnergyBreakdown,
+    force::{self, Body, ForceGenerator},
+    plugin::Plugins,
+    raycast::Raycaster,
+    spectrum::ImpulseRecorder,
+    state::State,
+    trigger::{Trigger, TriggerAction, TriggerEvent},
+};
+
+use super::{
+    config::Config,
+    joint::{Joint, JointKind, JointLoads, JointStiffness, Motor},
+    rigidbody::{self, RigidBody},
+    schedule::{LoadKind, ScheduledLoad},
 };
 
-use super::{config::Config, rigidbody::RigidBody};
+pub type RigidBodyHandle = Handle<RigidBody>;
+pub type ObstacleHandle = Handle<CollidableMesh>;
+pub type KinematicObstacleHandle = Handle<KinematicObstacle>;
+pub type JointHandle = Handle<Joint>;
+
+/// The rate the recorded rigidbody's contact impulses are recorded at, in samples per second.
+const IMPULSE_SAMPLE_RATE: f32 = 200.0;
+/// The seconds of contact impulses kept for analysis.
+const IMPULSE_RECORDING_DURATION: f32 = 10.0;
+
+/// A spring between a point on a rigidbody and a target, e.g. the mouse cursor.
+struct MouseSpring {
+    rigidbody: RigidBodyHandle,
+    // The attached point, in the rigidbody's local space.
+    local_anchor: Vector3<f32>,
+    target: Vector3<f32>,
+}
 
 pub struct Simulation {
     config: Config,
-    rigidbodies: Vec<RigidBody>,
-    obstacles: Vec<CollidableMesh>,
+    rigidbodies: Pool<RigidBody>,
+    obstacles: Pool<CollidableMesh>,
+    kinematic_obstacles: Pool<KinematicObstacle>,
+    joints: Pool<Joint>,
+    scheduled_loads: Vec<(RigidBodyHandle, ScheduledLoad)>,
+    triggers: Vec<Trigger<RigidBodyHandle>>,
+    mouse_spring: Option<MouseSpring>,
+    // The rigidbody whose contact impulses are recorded; the last grabbed, or else the oldest live rigidbody.
+    recorded: Option<RigidBodyHandle>,
+    impulse_recorder: ImpulseRecorder,
+    plugins: Plugins<Simulation, RigidBody>,
+    force_generators: Vec<Box<dyn ForceGenerator>>,
+    // Seconds of simulated time since the simulation began.
+    time: f32,
+    // All of the rigidbodies' states, integrated together in one flat buffer which is kept between steps, so that
+    // stepping doesn't allocate, and large scenes integrate in parallel.
+    integration_state: State<rigidbody::State>,
 }
 
 impl Simulation {
@@ -18,61 +68,563 @@ impl Simulation {
         let config = Config::default();
         Simulation {
             config,
-            rigidbodies,
-            obstacles,
+            rigidbodies: rigidbodies.into_iter().collect(),
+            obstacles: obstacles.into_iter().collect(),
+            kinematic_obstacles: Pool::new(),
+            joints: Pool::new(),
+            scheduled_loads: Vec::new(),
+            triggers: Vec::new(),
+            mouse_spring: None,
+            recorded: None,
+            impulse_recorder: ImpulseRecorder::new(IMPULSE_SAMPLE_RATE, IMPULSE_RECORDING_DURATION),
+            plugins: Plugins::new(),
+            force_generators: Vec::new(),
+            time: 0.0,
+            integration_state: State::new(Vec::new()),
         }
     }
 
     pub fn step(&mut self) -> Duration {
-        self.rigidbodies.iter_mut().for_each(|rigidbody| {
-            rigidbody.accumulate_forces(&self.config);
-            rigidbody.accumulate_torques(&self.config);
-
-            let state = State::new(vec![*rigidbody.get_state()]);
-            let new_state = match self.config.integration {
-                Integration::Rk4 => state.rk4_step(self.config.dt),
-                Integration::Euler => state.euler_step(self.config.dt),
+        if let Some(mouse_spring) = &self.mouse_spring {
+            let rigidbody = &mut self.rigidbodies[mouse_spring.rigidbody];
+            let stiffness = self.config.mouse_spring_stiffness;
+            let damping = 2.0 * (stiffness * rigidbody.get_mass()).sqrt();
+            let anchor = rigidbody.local_to_world(mouse_spring.local_anchor);
+            let force = stiffness * (mouse_spring.target - anchor)
+                - damping * rigidbody.get_point_velocity(mouse_spring.local_anchor);
+            let offset = anchor - rigidbody.get_position();
+            rigidbody.add_force_at(force, offset);
+        }
+
+        self.apply_joint_loads();
+
+        if !self
+            .recorded
+            .is_some_and(|handle| self.rigidbodies.contains(handle))
+        {
+            let oldest = self.rigidbodies.iter().next().map(|(handle, _)| handle);
+            self.record(oldest);
+        }
+
+        for obstacle in self.kinematic_obstacles.values_mut() {
+            obstacle.advance(self.config.dt);
+        }
+
+        for (handle, load) in self.scheduled_loads.iter() {
+            if let (Some(value), Some(rigidbody)) =
+                (load.evaluate(self.time), self.rigidbodies.get_mut(*handle))
+            {
+                match load.kind {
+                    LoadKind::Force => rigidbody.add_force(value),
+                    LoadKind::Torque => rigidbody.add_torque(value),
+                }
+            }
+        }
+
+        // Each body's loads are accumulated, then all of the bodies are stepped at once, and then each of them is
+        // collided in turn from its new state. A body's loads and step don't depend on the others'.
+        let states = self.integration_state.elements_mut();
+        states.clear();
+        self.rigidbodies.iter_mut().for_each(|(handle, rigidbody)| {
+            let wind = self
+                .triggers
+                .iter()
+                .filter(|trigger| trigger.contains(handle))
+                .fold(self.config.wind, |wind, trigger| {
+                    match trigger.get_action() {
+                        TriggerAction::Wind(trigger_wind) => wind + trigger_wind,
+                        _ => wind,
+                    }
+                });
+            rigidbody.accumulate_forces(&self.config, wind);
+            let load = self.plugins.load(rigidbody, self.time);
+            rigidbody.add_force(load.force);
+            rigidbody.add_torque(load.torque);
+            let body = Body {
+                position: rigidbody.get_position(),
+                velocity: rigidbody.get_state().velocity(),
+                mass: rigidbody.get_mass(),
             };
-            let mut new_rigidbody_state = new_state.get_elements()[0];
+            rigidbody.add_force(force::sum(&self.force_generators, &body, self.time));
+            states.push(*rigidbody.get_state());
+        });
+        // The largest error estimate of any body's step, if the integration estimates it.
+        let error =
+            self.integration_state
+                .step(self.config.integration, self.config.dt, &self.config);
+
+        let new_states = self.integration_state.elements();
+        let rigidbodies = self.rigidbodies.iter_mut();
+        for ((handle, rigidbody), new_rigidbody_state) in rigidbodies.zip(new_states) {
+            let mut new_rigidbody_state = *new_rigidbody_state;
+            if self.config.implicit_gyroscopic {
+                new_rigidbody_state.step_rotation_implicitly(rigidbody.get_state(), &self.config);
+            }
             new_rigidbody_state.normalize_rotation();
 
-            rigidbody.update_state(new_rigidbody_state, &self.obstacles, &self.config);
+            let contact_impulse = rigidbody.update_state(
+                new_rigidbody_state,
+                &self.obstacles,
+                &self.kinematic_obstacles,
+                &self.config,
+            );
+            if self.recorded == Some(handle) {
+                self.impulse_recorder.add_impulse(contact_impulse);
+            }
+            if let Some(planar) = &self.config.planar {
+                rigidbody.constrain_to_plane(planar);
+            }
 
             // TODO The collision response should also handle other rigidbodies, which would require examining and updating all rigidbodies at once,
-            //        rather than sequentially as here. Really, we should have all rigidbodies in a single State vector, and handle derivative calculation etc from
-            //        that, rather than statefully determining accumulated forces and torques.
+            //        rather than sequentially as here. Their contact and joint forces and torques would then be better derived from the shared
+            //        integration state during the step, rather than statefully accumulated before it.
             //        Beware that the CollidableMesh in the rigidbodies is stored as local coordinates, so we'd need to transform into world coordinates
             //        for comparison/collisions.
 
             rigidbody.clear_forces();
             rigidbody.clear_torques();
+        }
+
+        self.correct_joints();
+        self.update_triggers();
+
+        self.impulse_recorder.advance(self.config.dt);
+        self.time += self.config.dt;
+        let time = self.time;
+        let rigidbodies = &self.rigidbodies;
+        self.scheduled_loads
+            .retain(|(handle, load)| rigidbodies.contains(*handle) && !load.is_finished(time));
+
+        Plugins::after_step(self, |simulation| &mut simulation.plugins);
+
+        let dt = self.config.dt;
+        // The step isn't retaken if its error's over the tolerance, but the next is shrunk to meet it.
+        if let (Some(adaptive), Some(error)) = (self.config.get_adaptive_dt(), error) {
+            self.config.dt = adaptive.next_timestep(dt, error);
+        }
+        Duration::from_secs_f32(dt)
+    }
+
+    /// Adds each joint's spring loads to its bodies, first breaking any joints strained past the break force or torque.
+    fn apply_joint_loads(&mut self) {
+        let stiffness = self.get_joint_stiffness();
+        let mut broken = Vec::new();
+        for (handle, joint) in self.joints.iter() {
+            let (a, b) = joint.get_bodies();
+            let bodies = (&self.rigidbodies[a], &self.rigidbodies[b]);
+            let loads = joint.get_loads(bodies, stiffness);
+            if self.is_breaking(&loads) {
+                broken.push(handle);
+                continue;
+            }
+            let (anchor_a, anchor_b) = joint.get_world_anchors(bodies);
+
+            let rigidbody = &mut self.rigidbodies[a];
+            let offset = anchor_a - rigidbody.get_position();
+            rigidbody.add_force_at(-loads.force, offset);
+            rigidbody.add_torque(-loads.torque);
+
+            let rigidbody = &mut self.rigidbodies[b];
+            let offset = anchor_b - rigidbody.get_position();
+            rigidbody.add_force_at(loads.force, offset);
+            rigidbody.add_torque(loads.torque);
+        }
+        for handle in broken {
+            self.joints.remove(handle);
+        }
+    }
+
+    fn get_joint_stiffness(&self) -> JointStiffness {
+        JointStiffness {
+            linear: self.config.joint_stiffness,
+            angular: self.config.joint_angular_stiffness,
+        }
+    }
+
+    /// Whether the joint's loads strain it past the break force or torque, if joints are breakable.
+    fn is_breaking(&self, loads: &JointLoads) -> bool {
+        self.config.breakable_joints
+            && (loads.strain_force > self.config.joint_break_force
+                || loads.strain_torque > self.config.joint_break_torque)
+    }
+
+    /// Moves each joint's bodies towards each other by the fraction of their anchors' separation,
+    /// the lighter further, so that the separation the springs leave doesn't build up over long runs.
+    /// Joints are checked for breaking first, as the correction hides some of the strain from the springs.
+    fn correct_joints(&mut self) {
+        let stiffness = self.get_joint_stiffness();
+        let position_correction = self.config.position_correction;
+        let mut broken = Vec::new();
+        for (handle, joint) in self.joints.iter() {
+            let (a, b) = joint.get_bodies();
+            let bodies = (&self.rigidbodies[a], &self.rigidbodies[b]);
+            if self.is_breaking(&joint.get_loads(bodies, stiffness)) {
+                broken.push(handle);
+                continue;
+            }
+            let (anchor_a, anchor_b) = joint.get_world_anchors(bodies);
+            let (inverse_mass_a, inverse_mass_b) =
+                (1.0 / bodies.0.get_mass(), 1.0 / bodies.1.get_mass());
+            let correction =
+                position_correction * (anchor_b - anchor_a) / (inverse_mass_a + inverse_mass_b);
+            self.rigidbodies[a].translate(correction * inverse_mass_a);
+            self.rigidbodies[b].translate(-correction * inverse_mass_b);
+        }
+        for handle in broken {
+            self.joints.remove(handle);
+        }
+    }
+
+    fn update_triggers(&mut self) {
+        let mut despawned = Vec::new();
+        for trigger in self.triggers.iter_mut() {
+            let events = trigger.update(
+                self.rigidbodies
+                    .iter()
+                    .map(|(handle, rigidbody)| (handle, rigidbody.get_position())),
+            );
+            if trigger.get_action() == TriggerAction::Despawn {
+                despawned.extend(events.iter().filter_map(|event| match event {
+                    TriggerEvent::Enter(handle) => Some(*handle),
+                    TriggerEvent::Exit(_) => None,
+                }));
+            }
+        }
+        // A body entering several despawning triggers at once is only despawned once; despawning it again is a no-op.
+        for handle in despawned {
+            self.despawn_rigidbody(handle);
+        }
+    }
+
+    /// Adds the rigidbody to the simulation, reusing the slot of a despawned rigidbody if possible.
+    pub fn spawn_rigidbody(&mut self, rigidbody: RigidBody) -> RigidBodyHandle {
+        let mut rigidbody = rigidbody;
+        rigidbody.set_collision_mask(self.config.collision_mask);
+        self.rigidbodies.insert(rigidbody)
+    }
+
+    /// Removes the rigidbody from the simulation, along with its scheduled loads, its joints, and the mouse spring
+    /// if it's attached to it. Returns None if the rigidbody was already despawned.
+    pub fn despawn_rigidbody(&mut self, handle: RigidBodyHandle) -> Option<RigidBody> {
+        let rigidbody = self.rigidbodies.remove(handle)?;
+        self.scheduled_loads
+            .retain(|(load_handle, _)| *load_handle != handle);
+        let joints = self
+            .joints
+            .iter()
+            .filter(|(_, joint)| joint.involves(handle))
+            .map(|(joint_handle, _)| joint_handle)
+            .collect::<Vec<_>>();
+        for joint in joints {
+            self.joints.remove(joint);
+        }
+        if self
+            .mouse_spring
+            .as_ref()
+            .is_some_and(|mouse_spring| mouse_spring.rigidbody == handle)
+        {
+            self.mouse_spring = None;
+        }
+        Some(rigidbody)
+    }
+
+    pub fn spawn_obstacle(&mut self, obstacle: CollidableMesh) -> ObstacleHandle {
+        self.obstacles.insert(obstacle)
+    }
+
+    pub fn despawn_obstacle(&mut self, handle: ObstacleHandle) -> Option<CollidableMesh> {
+        self.obstacles.remove(handle)
+    }
+
+    /// Adds an obstacle which moves along its animation as the simulation steps, pushing rigidbodies it collides with.
+    pub fn spawn_kinematic_obstacle(
+        &mut self,
+        obstacle: KinematicObstacle,
+    ) -> KinematicObstacleHandle {
+        self.kinematic_obstacles.insert(obstacle)
+    }
+
+    pub fn despawn_kinematic_obstacle(
+        &mut self,
+        handle: KinematicObstacleHandle,
+    ) -> Option<KinematicObstacle> {
+        self.kinematic_obstacles.remove(handle)
+    }
+
+    pub fn get_kinematic_obstacles(&self) -> &Pool<KinematicObstacle> {
+        &self.kinematic_obstacles
+    }
+
+    /// Attaches the mouse spring to the nearest rigidbody hit by the ray, at the point it was hit,
+    /// initially targeting that point. Returns the distance along the ray to the hit, if any.
+    pub fn grab(&mut self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        let raycaster = Raycaster::new(
+            self.rigidbodies
+                .iter()
+                .map(|(handle, rigidbody)| (handle, rigidbody.get_world_faces())),
+        );
+        let hit = raycaster.raycast(origin, direction)?;
+        self.record(Some(hit.object));
+        self.mouse_spring = Some(MouseSpring {
+            rigidbody: hit.object,
+            local_anchor: self.rigidbodies[hit.object].world_to_local(hit.point),
+            target: hit.point,
         });
+        Some(hit.distance)
+    }
 
-        Duration::from_secs_f32(self.config.dt)
+    /// Records the contact impulses of the rigidbody, starting a new recording if it wasn't already recorded.
+    fn record(&mut self, rigidbody: Option<RigidBodyHandle>) {
+        if self.recorded != rigidbody {
+            self.recorded = rigidbody;
+            self.impulse_recorder.clear();
+        }
+    }
+
+    /// Moves the target of the mouse spring, if it's attached.
+    pub fn set_grab_target(&mut self, target: Vector3<f32>) {
+        if let Some(mouse_spring) = &mut self.mouse_spring {
+            mouse_spring.target = target;
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.mouse_spring = None;
+    }
+
+    /// The world space endpoints of the mouse spring, as (anchor, target), if it's attached.
+    pub fn get_mouse_spring(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        self.mouse_spring.as_ref().map(|mouse_spring| {
+            (
+                self.rigidbodies[mouse_spring.rigidbody].local_to_world(mouse_spring.local_anchor),
+                mouse_spring.target,
+            )
+        })
+    }
+
+    /// Joins the rigidbodies at the world space anchor, holding them as they are now.
+    /// The axis is the world space hinge axis, which only hinges use.
+    /// Returns None if either rigidbody has been despawned, or they're the same rigidbody.
+    pub fn add_joint(
+        &mut self,
+        kind: JointKind,
+        a: RigidBodyHandle,
+        b: RigidBodyHandle,
+        anchor: Vector3<f32>,
+        axis: Vector3<f32>,
+    ) -> Option<JointHandle> {
+        if a == b {
+            return None;
+        }
+        let bodies = (self.rigidbodies.get(a)?, self.rigidbodies.get(b)?);
+        let joint = Joint::new(kind, (a, b), bodies, anchor, axis);
+        Some(self.joints.insert(joint))
+    }
+
+    /// Sets the motor driving the joint, if it hasn't been broken. Only hinges are driven by their motor.
+    pub fn set_joint_motor(&mut self, handle: JointHandle, motor: Motor) {
+        if let Some(joint) = self.joints.get_mut(handle) {
+            joint.set_motor(motor);
+        }
+    }
+
+    /// Removes the joint, letting its bodies separate. Returns None if it was already broken.
+    pub fn break_joint(&mut self, handle: JointHandle) -> Option<Joint> {
+        self.joints.remove(handle)
+    }
+
+    /// Moves the joint's anchor to midway between its bodies' anchor points, and makes their current
+    /// relative rotation its rest rotation, so that the joint holds its bodies where they are now.
+    pub fn reanchor_joint(&mut self, handle: JointHandle) {
+        if let Some(joint) = self.joints.get_mut(handle) {
+            let (a, b) = joint.get_bodies();
+            let bodies = (&self.rigidbodies[a], &self.rigidbodies[b]);
+            let (anchor_a, anchor_b) = joint.get_world_anchors(bodies);
+            joint.reanchor(bodies, (anchor_a + anchor_b) / 2.0);
+        }
+    }
+
+    pub fn get_joints(&self) -> &Pool<Joint> {
+        &self.joints
+    }
+
+    /// The world space anchor points on each of the joint's bodies, if it hasn't been broken.
+    pub fn get_joint_anchors(&self, handle: JointHandle) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let joint = self.joints.get(handle)?;
+        let (a, b) = joint.get_bodies();
+        Some(joint.get_world_anchors((&self.rigidbodies[a], &self.rigidbodies[b])))
+    }
+
+    /// Adds a trigger volume, returning its index.
+    pub fn add_trigger(&mut self, trigger: Trigger<RigidBodyHandle>) -> usize {
+        self.triggers.push(trigger);
+        self.triggers.len() - 1
+    }
+
+    pub fn get_triggers(&self) -> &[Trigger<RigidBodyHandle>] {
+        &self.triggers
+    }
+
+    /// Schedules a force or torque on a rigidbody, evaluated at the start of each step.
+    /// The load is dropped if the rigidbody is despawned.
+    pub fn schedule_load(&mut self, rigidbody: RigidBodyHandle, load: ScheduledLoad) {
+        if self.rigidbodies.contains(rigidbody) {
+            self.scheduled_loads.push((rigidbody, load));
+        }
     }
 
     pub fn get_timestep(&self) -> Duration {
         Duration::from_secs_f32(self.config.dt)
     }
 
-    pub fn get_rigidbodies(&self) -> &Vec<RigidBody> {
+    /// The seconds simulated so far.
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn get_impulses(&self) -> &ImpulseRecorder {
+        &self.impulse_recorder
+    }
+
+    pub fn get_rigidbodies(&self) -> &Pool<RigidBody> {
         &self.rigidbodies
     }
 
-    pub fn get_obstacles(&self) -> &Vec<CollidableMesh> {
+    /// The rigidbodies' kinetic energy and potential energy in the gravity force, and the elastic energy of the
+    /// joints' springs holding their anchors together. The joints' angular springs' energy isn't counted.
+    pub fn energy(&self) -> EnergyBreakdown {
+        let bodies: EnergyBreakdown = self
+            .rigidbodies
+            .values()
+            .map(|rigidbody| EnergyBreakdown {
+                kinetic: rigidbody.get_state().kinetic_energy(),
+                gravitational: -self.config.gravity.dot(rigidbody.get_position()),
+                elastic: 0.0,
+            })
+            .sum();
+        let stiffness = self.get_joint_stiffness();
+        let elastic: f32 = self
+            .joints
+            .values()
+            .map(|joint| {
+                let (a, b) = joint.get_bodies();
+                let (anchor_a, anchor_b) =
+                    joint.get_world_anchors((&self.rigidbodies[a], &self.rigidbodies[b]));
+                0.5 * stiffness.linear * (anchor_b - anchor_a).magnitude2()
+            })
+            .sum();
+        EnergyBreakdown { elastic, ..bodies }
+    }
+
+    pub fn get_rigidbodies_mut(&mut self) -> &mut Pool<RigidBody> {
+        &mut self.rigidbodies
+    }
+
+    /// The plugins adding custom forces to the rigidbodies, and custom behaviors after each step.
+    pub fn get_plugins_mut(&mut self) -> &mut Plugins<Simulation, RigidBody> {
+        &mut self.plugins
+    }
+
+    /// The force fields pushing the rigidbodies, besides the configured gravity and wind.
+    pub fn get_force_generators_mut(&mut self) -> &mut Vec<Box<dyn ForceGenerator>> {
+        &mut self.force_generators
+    }
+
+    /// The config, e.g. to tweak from the console between syncs from the UI.
+    pub fn get_config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    pub fn get_obstacles(&self) -> &Pool<CollidableMesh> {
         &self.obstacles
     }
+}
 
-    pub fn sync_sim_from_ui(&mut self, ui: &mut crate::gui::rigidbody::RigidBodyUi) {
-        let ui_config_state = ui.get_gui_state_mut();
-        self.config.integration = ui_config_state.integration;
-        self.config.dt = ui_config_state.dt;
-        self.config.coefficient_of_restitution = ui_config_state.coefficient_of_restitution;
-        self.config.gravity = ui_config_state.gravity;
-        self.config.torque = ui_config_state.torque;
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
 
-        if let Some((impulse, impulse_position)) = ui.get_free_impulse() {
-            self.rigidbodies[0].apply_impulse(impulse, impulse_position);
+    fn sync_config(&mut self, config: &Config) {
+        self.config.integration = config.integration;
+        self.config.implicit_gyroscopic = config.implicit_gyroscopic;
+        self.config.adaptive_dt = config.adaptive_dt;
+        // While dt adapts, it's the simulation's to set.
+        if self.config.get_adaptive_dt().is_none() {
+            self.config.dt = config.dt;
         }
+        self.config.coefficient_of_restitution = config.coefficient_of_restitution;
+        self.config.coefficient_of_friction = config.coefficient_of_friction;
+        self.config.anisotropic_friction = config.anisotropic_friction;
+        self.config.position_correction = config.position_correction;
+        self.config.gravity = config.gravity;
+        self.config.torque = config.torque;
+        self.config.linear_drag_viscous = config.linear_drag_viscous;
+        self.config.linear_drag_quadratic = config.linear_drag_quadratic;
+        self.config.angular_drag_viscous = config.angular_drag_viscous;
+        self.config.angular_drag_quadratic = config.angular_drag_quadratic;
+        self.config.wind = config.wind;
+        self.config.collision_mask = config.collision_mask;
+        self.config.mouse_spring_stiffness = config.mouse_spring_stiffness;
+        self.config.joint_stiffness = config.joint_stiffness;
+        self.config.joint_angular_stiffness = config.joint_angular_stiffness;
+        self.config.breakable_joints = config.breakable_joints;
+        self.config.joint_break_force = config.joint_break_force;
+        self.config.joint_break_torque = config.joint_break_torque;
+        self.config.planar = config.planar;
+        for (_, rigidbody) in self.rigidbodies.iter_mut() {
+            rigidbody.set_collision_mask(self.config.collision_mask);
+        }
+    }
+}
+
+impl Checksummed for Simulation {
+    /// The live rigidbodies, in the order of their slots in the pool, and the simulated time.
+    fn add_to_checksum(&self, checksum: &mut Checksum) {
+        self.rigidbodies
+            .iter()
+            .for_each(|(_, rigidbody)| rigidbody.add_to_checksum(checksum));
+        checksum.add_f32(self.time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::Simulation;
+    use crate::simulation::quantity;
+    use crate::simulation::rigidbody::rigidbody::RigidBody;
+    use crate::simulation::trigger::{Trigger, TriggerAction, TriggerShape};
+
+    #[test]
+    fn respawned_rigidbody_enters_triggers_afresh() {
+        let mut simulation = Simulation::new(Vec::new(), Vec::new());
+        let trigger = simulation.add_trigger(Trigger::new(
+            TriggerShape::Sphere {
+                center: Vector3::new(0.0, 0.0, 0.0),
+                radius: 10.0,
+            },
+            TriggerAction::Count,
+        ));
+        let body =
+            || RigidBody::new(Vector3::new(0.0, 0.0, 0.0), quantity::kilograms(1.0)).unwrap();
+
+        let first = simulation.spawn_rigidbody(body());
+        simulation.step();
+        assert!(simulation.get_triggers()[trigger].contains(first));
+        assert_eq!(1, simulation.get_triggers()[trigger].get_enter_count());
+
+        // The new rigidbody takes the despawned one's slot, but not its place in the trigger.
+        simulation.despawn_rigidbody(first);
+        let second = simulation.spawn_rigidbody(body());
+        assert_eq!(first.index(), second.index());
+        simulation.step();
+        let trigger = &simulation.get_triggers()[trigger];
+        assert!(trigger.contains(second));
+        assert!(!trigger.contains(first));
+        assert_eq!(2, trigger.get_enter_count());
     }
 }
@@ -0,0 +1,437 @@
+/// Mesh simplification by quadric error edge collapse (Garland and Heckbert, 1997), e.g. to make
+/// collision proxies of dense render meshes.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use cgmath::{InnerSpace, Matrix3, SquareMatrix, Vector3};
+use rustc_hash::FxHashMap;
+
+/// The weight of the planes constraining boundary edges, relative to the faces' planes,
+/// so that collapses keep open meshes' outlines.
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+/// The squared distance from a set of planes, as a symmetric 4x4 matrix of which only the upper
+/// triangle is stored: a², ab, ac, ad, b², bc, bd, c², cd, d² for planes ax + by + cz + d = 0.
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: Vector3<f64>, point: Vector3<f64>, weight: f64) -> Quadric {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        let d = -normal.dot(point);
+        Quadric(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * weight),
+        )
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (q, o) in self.0.iter_mut().zip(other.0.iter()) {
+            *q += o;
+        }
+    }
+
+    fn error(&self, v: Vector3<f64>) -> f64 {
+        let q = &self.0;
+        q[0] * v.x * v.x
+            + 2.0 * q[1] * v.x * v.y
+            + 2.0 * q[2] * v.x * v.z
+            + 2.0 * q[3] * v.x
+            + q[4] * v.y * v.y
+            + 2.0 * q[5] * v.y * v.z
+            + 2.0 * q[6] * v.y
+            + q[7] * v.z * v.z
+            + 2.0 * q[8] * v.z
+            + q[9]
+    }
+
+    /// The position minimizing the error, if the quadric isn't singular, e.g. for coplanar faces.
+    fn minimizer(&self) -> Option<Vector3<f64>> {
+        let q = &self.0;
+        let a = Matrix3::new(q[0], q[1], q[2], q[1], q[4], q[5], q[2], q[5], q[7]);
+        a.invert()
+            .map(|inverse| inverse * -Vector3::new(q[3], q[6], q[8]))
+    }
+}
+
+/// A candidate edge collapse. Collapses are ordered by increasing cost, so the heap pops the cheapest.
+struct Collapse {
+    cost: f64,
+    position: Vector3<f64>,
+    vertices: (usize, usize),
+    /// The versions of the vertices when the collapse was computed; it's stale if either has
+    /// since changed.
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+struct Simplifier {
+    positions: Vec<Vector3<f64>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed_vertices: Vec<bool>,
+    triangles: Vec<[usize; 3]>,
+    removed_triangles: Vec<bool>,
+    /// The triangles around each vertex, which may include removed triangles.
+    vertex_triangles: Vec<Vec<usize>>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    fn new(positions: &[Vector3<f32>], indices: &[usize]) -> Simplifier {
+        // Meshes loaded for rendering duplicate vertices along UV seams, which must be welded
+        // for the surface either side of a seam to collapse together.
+        let mut welded: FxHashMap<[u32; 3], usize> = FxHashMap::default();
+        let mut welded_positions = Vec::new();
+        let remap: Vec<usize> = positions
+            .iter()
+            .map(|p| {
+                *welded
+                    .entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+                    .or_insert_with(|| {
+                        welded_positions.push(p.cast::<f64>().unwrap());
+                        welded_positions.len() - 1
+                    })
+            })
+            .collect();
+        let triangles: Vec<[usize; 3]> = indices
+            .chunks_exact(3)
+            .map(|t| [remap[t[0]], remap[t[1]], remap[t[2]]])
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .collect();
+
+        let vertex_count = welded_positions.len();
+        let mut simplifier = Simplifier {
+            positions: welded_positions,
+            quadrics: vec![Quadric::default(); vertex_count],
+            versions: vec![0; vertex_count],
+            removed_vertices: vec![false; vertex_count],
+            removed_triangles: vec![false; triangles.len()],
+            vertex_triangles: vec![Vec::new(); vertex_count],
+            triangles,
+            heap: BinaryHeap::new(),
+        };
+        simplifier.init_quadrics();
+        simplifier
+    }
+
+    /// Sums the planes of each vertex's faces, weighted by area, and the planes perpendicular to
+    /// its boundary edges.
+    fn init_quadrics(&mut self) {
+        let mut edge_faces: FxHashMap<(usize, usize), (usize, u32)> = FxHashMap::default();
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            let [a, b, c] = *triangle;
+            let cross = (self.positions[b] - self.positions[a])
+                .cross(self.positions[c] - self.positions[a]);
+            let area = cross.magnitude() / 2.0;
+            if area > 0.0 {
+                let quadric = Quadric::from_plane(cross.normalize(), self.positions[a], area);
+                for vertex in triangle {
+                    self.quadrics[*vertex].add(&quadric);
+                }
+            }
+            for vertex in triangle {
+                self.vertex_triangles[*vertex].push(index);
+            }
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                edge_faces
+                    .entry((from.min(to), from.max(to)))
+                    .or_insert((index, 0))
+                    .1 += 1;
+            }
+        }
+
+        for ((from, to), (triangle, count)) in edge_faces {
+            if count != 1 {
+                continue;
+            }
+            let normal = self.triangle_normal(triangle);
+            let edge = self.positions[to] - self.positions[from];
+            let boundary_normal = edge.cross(normal);
+            if boundary_normal.magnitude2() == 0.0 {
+                continue;
+            }
+            let quadric = Quadric::from_plane(
+                boundary_normal.normalize(),
+                self.positions[from],
+                BOUNDARY_WEIGHT * edge.magnitude2(),
+            );
+            self.quadrics[from].add(&quadric);
+            self.quadrics[to].add(&quadric);
+        }
+    }
+
+    fn triangle_normal(&self, triangle: usize) -> Vector3<f64> {
+        let [a, b, c] = self.triangles[triangle];
+        let cross =
+            (self.positions[b] - self.positions[a]).cross(self.positions[c] - self.positions[a]);
+        if cross.magnitude2() > 0.0 {
+            cross.normalize()
+        } else {
+            cross
+        }
+    }
+
+    /// Adds the collapse of the edge between the vertices to the heap, at the position which
+    /// minimizes its error.
+    fn push_collapse(&mut self, v0: usize, v1: usize) {
+        let mut quadric = self.quadrics[v0];
+        quadric.add(&self.quadrics[v1]);
+        let (p0, p1) = (self.positions[v0], self.positions[v1]);
+        let midpoint = (p0 + p1) / 2.0;
+        let mut candidates = vec![p0, p1, midpoint];
+        // The minimizer is only trusted near the edge; far from it, the quadric is nearly singular.
+        if let Some(minimizer) = quadric.minimizer() {
+            if (minimizer - midpoint).magnitude() <= (p1 - p0).magnitude() {
+                candidates.push(minimizer);
+            }
+        }
+        let (cost, position) = candidates
+            .into_iter()
+            .map(|p| (quadric.error(p), p))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+        self.heap.push(Collapse {
+            cost,
+            position,
+            vertices: (v0, v1),
+            versions: (self.versions[v0], self.versions[v1]),
+        });
+    }
+
+    /// Whether moving the vertices to the position would flip any of the faces around them which
+    /// aren't removed by the collapse.
+    fn flips(&self, v0: usize, v1: usize, position: Vector3<f64>) -> bool {
+        for &vertex in [v0, v1].iter() {
+            for &triangle in self.vertex_triangles[vertex].iter() {
+                let corners = self.triangles[triangle];
+                if self.removed_triangles[triangle]
+                    || (corners.contains(&v0) && corners.contains(&v1))
+                {
+                    continue;
+                }
+                let before = self.triangle_normal(triangle);
+                let moved = corners.map(|corner| {
+                    if corner == vertex {
+                        position
+                    } else {
+                        self.positions[corner]
+                    }
+                });
+                let after = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+                if after.dot(before) <= 0.0 {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Merges v1 into v0 at the position, returning the number of triangles removed.
+    fn collapse(&mut self, v0: usize, v1: usize, position: Vector3<f64>) -> usize {
+        self.positions[v0] = position;
+        let quadric = self.quadrics[v1];
+        self.quadrics[v0].add(&quadric);
+        self.removed_vertices[v1] = true;
+        self.versions[v0] += 1;
+
+        let mut removed = 0;
+        let moved_triangles = std::mem::take(&mut self.vertex_triangles[v1]);
+        for triangle in moved_triangles {
+            if self.removed_triangles[triangle] {
+                continue;
+            }
+            if self.triangles[triangle].contains(&v0) {
+                self.removed_triangles[triangle] = true;
+                removed += 1;
+            } else {
+                for corner in self.triangles[triangle].iter_mut() {
+                    if *corner == v1 {
+                        *corner = v0;
+                    }
+                }
+                self.vertex_triangles[v0].push(triangle);
+            }
+        }
+        let removed_triangles = &self.removed_triangles;
+        self.vertex_triangles[v0].retain(|triangle| !removed_triangles[*triangle]);
+
+        let mut neighbours: Vec<usize> = self.vertex_triangles[v0]
+            .iter()
+            .flat_map(|triangle| self.triangles[*triangle])
+            .filter(|vertex| *vertex != v0)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for neighbour in neighbours {
+            self.push_collapse(v0, neighbour);
+        }
+        removed
+    }
+
+    fn simplify(&mut self, target_triangles: usize) {
+        for triangle in 0..self.triangles.len() {
+            let [a, b, c] = self.triangles[triangle];
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                // Each interior edge is shared by two triangles, so is pushed by the one it runs forward in.
+                if from < to {
+                    self.push_collapse(from, to);
+                }
+            }
+        }
+
+        let mut triangle_count = self.triangles.len();
+        while triangle_count > target_triangles {
+            let Some(collapse) = self.heap.pop() else {
+                break;
+            };
+            let (v0, v1) = collapse.vertices;
+            if self.removed_vertices[v0]
+                || self.removed_vertices[v1]
+                || collapse.versions != (self.versions[v0], self.versions[v1])
+                || self.flips(v0, v1, collapse.position)
+            {
+                continue;
+            }
+            triangle_count -= self.collapse(v0, v1, collapse.position);
+        }
+    }
+
+    /// Gets the remaining vertices and triangles, without removed vertices.
+    fn into_mesh(self) -> (Vec<Vector3<f32>>, Vec<usize>) {
+        let mut remap = vec![usize::MAX; self.positions.len()];
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for (triangle, corners) in self.triangles.iter().enumerate() {
+            if self.removed_triangles[triangle] {
+                continue;
+            }
+            for corner in corners {
+                if remap[*corner] == usize::MAX {
+                    remap[*corner] = positions.len();
+                    positions.push(self.positions[*corner].cast::<f32>().unwrap());
+                }
+                indices.push(remap[*corner]);
+            }
+        }
+        (positions, indices)
+    }
+}
+
+/// Simplifies the triangle mesh to at most the target number of triangles, collapsing the edges
+/// whose removal least changes the surface first.
+/// Fewer triangles than the target may remain if no more collapses can be made without
+/// flipping faces. Vertices at the same position are welded, and the outlines of open meshes are kept.
+pub fn simplify(
+    positions: &[Vector3<f32>],
+    indices: &[usize],
+    target_triangles: usize,
+) -> (Vec<Vector3<f32>>, Vec<usize>) {
+    let mut simplifier = Simplifier::new(positions, indices);
+    simplifier.simplify(target_triangles);
+    simplifier.into_mesh()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::geometry;
+
+    /// A unit square in the XY plane, divided into cells x cells quads.
+    fn grid(cells: usize) -> (Vec<Vector3<f32>>, Vec<usize>) {
+        let positions = (0..=cells)
+            .flat_map(|y| {
+                (0..=cells).map(move |x| {
+                    Vector3::new(x as f32 / cells as f32, y as f32 / cells as f32, 0.0)
+                })
+            })
+            .collect();
+        let row = cells + 1;
+        let indices = (0..cells)
+            .flat_map(|y| {
+                (0..cells).flat_map(move |x| {
+                    let corner = y * row + x;
+                    [
+                        corner,
+                        corner + 1,
+                        corner + row + 1,
+                        corner,
+                        corner + row + 1,
+                        corner + row,
+                    ]
+                })
+            })
+            .collect();
+        (positions, indices)
+    }
+
+    fn area(positions: &[Vector3<f32>], indices: &[usize]) -> f32 {
+        indices
+            .chunks_exact(3)
+            .map(|t| {
+                (positions[t[1]] - positions[t[0]])
+                    .cross(positions[t[2]] - positions[t[0]])
+                    .magnitude()
+                    / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn flat_grid_keeps_its_outline() {
+        let (positions, indices) = grid(10);
+        let (simplified_positions, simplified_indices) = simplify(&positions, &indices, 20);
+        assert!(simplified_indices.len() / 3 <= 20);
+        assert!(simplified_positions.iter().all(|p| p.z == 0.0));
+        assert!((area(&simplified_positions, &simplified_indices) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn sphere_stays_round() {
+        let (positions, indices) = geometry::get_sphere_vertices(1.0, 32, 32);
+        let (simplified_positions, simplified_indices) = simplify(&positions, &indices, 200);
+        let triangles = simplified_indices.len() / 3;
+        assert!(triangles <= 200 && triangles > 150, "{}", triangles);
+        for position in simplified_positions {
+            assert!((position.magnitude() - 1.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn meshes_under_the_target_are_unchanged() {
+        let (positions, indices) = grid(2);
+        let (simplified_positions, simplified_indices) = simplify(&positions, &indices, 100);
+        assert_eq!(simplified_positions.len(), positions.len());
+        assert_eq!(simplified_indices.len(), indices.len());
+    }
+}
@@ -0,0 +1,269 @@
+/// A tank of water shaken side to side, which sloshes most when shaken near its natural frequency. The water is
+/// a heightfield of columns across the tank, stepped with the shallow water equations in the tank's frame, where
+/// the shaking is a fictitious force opposite the tank's acceleration, so that its walls needn't move.
+use super::configurable::Configurable;
+use super::consts;
+
+use cgmath::Vector3;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+/// The number of columns of water across the tank.
+pub const COLUMNS: usize = 48;
+/// How often the height of the water at the wall is recorded.
+const SAMPLE_INTERVAL: f32 = 0.02;
+/// The most recorded heights kept.
+pub const MAX_SAMPLES: usize = 600;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub dt: f32, // Seconds as f32
+    /// How far the tank moves either side of its rest position along X, in meters.
+    pub amplitude: f32,
+    /// How many times a second the tank is shaken back and forth.
+    pub frequency: f32,
+    /// The depth of the water at rest. Changing it refills the tank.
+    pub depth: f32,
+    /// The fraction of the water's velocity lost per second, to its viscosity and the tank's walls.
+    pub damping: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            dt: Duration::from_millis(5).as_secs_f32(),
+            amplitude: 0.02,
+            frequency: 0.5,
+            depth: 0.2,
+            damping: 0.2,
+        }
+    }
+}
+
+pub struct Simulation {
+    config: Config,
+    /// The depth of the water in each column, from -X to +X.
+    heights: Vec<f32>,
+    /// The velocity of the water across the faces between the columns, and the tank's walls at either end.
+    velocities: Vec<f32>,
+    time: f32,
+    /// The phase of the tank's oscillation, accumulated so that changing the frequency doesn't jerk the tank.
+    phase: f32,
+    /// The time and height of the water at the +X wall of each sample.
+    height_history: VecDeque<(f32, f32)>,
+    sample_budget: f32,
+}
+
+impl Simulation {
+    /// The tank is long along X, which it's shaken along, and narrow along Z.
+    pub const TANK_HALF_EXTENTS: Vector3<f32> = Vector3::new(0.75, 0.5, 0.2);
+
+    pub fn new() -> Simulation {
+        let mut simulation = Simulation {
+            config: Config::default(),
+            heights: Vec::new(),
+            velocities: Vec::new(),
+            time: 0.0,
+            phase: 0.0,
+            height_history: VecDeque::with_capacity(MAX_SAMPLES),
+            sample_budget: 0.0,
+        };
+        simulation.reset();
+        simulation
+    }
+
+    /// Refills the tank with still water, restarting the shaking and the measurements.
+    pub fn reset(&mut self) {
+        self.heights = vec![self.config.depth; COLUMNS];
+        self.velocities = vec![0.0; COLUMNS + 1];
+        self.time = 0.0;
+        self.phase = 0.0;
+        self.height_history.clear();
+        self.sample_budget = 0.0;
+    }
+
+    pub fn step(&mut self) -> Duration {
+        let dt = self.config.dt;
+        let dx = Simulation::column_width();
+        let tank_acceleration = self.get_tank_acceleration().x;
+        let damping = (1.0 - self.config.damping * dt).max(0.0);
+
+        // The water between columns is pushed from the higher toward the lower, and left behind by the tank.
+        // The faces at the walls stay still.
+        for face in 1..COLUMNS {
+            let slope = (self.heights[face] - self.heights[face - 1]) / dx;
+            self.velocities[face] += dt * (-consts::GRAVITY * slope - tank_acceleration);
+            self.velocities[face] *= damping;
+        }
+        // Each column gains the water flowing into it, carried from the column upwind of each face.
+        let fluxes = (0..=COLUMNS)
+            .map(|face| {
+                let velocity = self.velocities[face];
+                let upwind = if velocity > 0.0 {
+                    face.saturating_sub(1)
+                } else {
+                    face.min(COLUMNS - 1)
+                };
+                velocity * self.heights[upwind]
+            })
+            .collect::<Vec<f32>>();
+        for (column, height) in self.heights.iter_mut().enumerate() {
+            *height = (*height - dt * (fluxes[column + 1] - fluxes[column]) / dx).max(0.0);
+        }
+
+        self.time += dt;
+        self.phase = (self.phase + self.angular_frequency() * dt) % (2.0 * PI);
+        self.sample_budget += dt;
+        if self.sample_budget >= SAMPLE_INTERVAL {
+            self.sample_budget -= SAMPLE_INTERVAL;
+            if self.height_history.len() == MAX_SAMPLES {
+                self.height_history.pop_front();
+            }
+            self.height_history
+                .push_back((self.time, self.get_wall_height()));
+        }
+
+        Duration::from_secs_f32(dt)
+    }
+
+    pub fn get_timestep(&self) -> Duration {
+        Duration::from_secs_f32(self.config.dt)
+    }
+
+    /// The depth of the water in each column, from -X to +X.
+    pub fn get_heights(&self) -> &Vec<f32> {
+        &self.heights
+    }
+
+    /// The width of each column of water along X.
+    pub fn column_width() -> f32 {
+        2.0 * Simulation::TANK_HALF_EXTENTS.x / COLUMNS as f32
+    }
+
+    /// How far the tank is from its rest position.
+    pub fn get_tank_offset(&self) -> Vector3<f32> {
+        Vector3::unit_x() * self.config.amplitude * self.phase.sin()
+    }
+
+    pub fn get_tank_acceleration(&self) -> Vector3<f32> {
+        -Vector3::unit_x()
+            * self.config.amplitude
+            * self.angular_frequency().powi(2)
+            * self.phase.sin()
+    }
+
+    /// The depth of the water against the +X wall.
+    pub fn get_wall_height(&self) -> f32 {
+        self.heights[COLUMNS - 1]
+    }
+
+    pub fn get_height_history(&self) -> &VecDeque<(f32, f32)> {
+        &self.height_history
+    }
+
+    /// The frequency the water sloshes at most readily, in its fundamental mode.
+    pub fn natural_frequency(&self) -> f32 {
+        natural_frequency(2.0 * Simulation::TANK_HALF_EXTENTS.x, self.config.depth)
+    }
+
+    fn angular_frequency(&self) -> f32 {
+        2.0 * PI * self.config.frequency
+    }
+}
+
+/// The frequency of the fundamental sloshing mode of shallow water of the depth in a tank of the length: a
+/// standing wave half a wavelength long, of waves travelling at sqrt(g h). Deeper water sloshes a little slower,
+/// since its waves feel the floor less, which the heightfield doesn't model.
+pub fn natural_frequency(length: f32, depth: f32) -> f32 {
+    (consts::GRAVITY * depth).sqrt() / (2.0 * length)
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        let depth_changed = config.depth != self.config.depth;
+        self.config = config.clone();
+        if depth_changed {
+            self.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::assert_relative_eq;
+
+    fn wall_height_range(frequency: f32) -> f32 {
+        let mut simulation = Simulation::new();
+        simulation.sync_config(&Config {
+            frequency,
+            ..Config::default()
+        });
+        while simulation.time < 10.0 {
+            simulation.step();
+        }
+        let heights = simulation
+            .get_height_history()
+            .iter()
+            .map(|(_, height)| *height);
+        heights.clone().fold(f32::MIN, f32::max) - heights.fold(f32::MAX, f32::min)
+    }
+
+    #[test]
+    fn still_tank_stays_flat() {
+        let mut simulation = Simulation::new();
+        simulation.sync_config(&Config {
+            amplitude: 0.0,
+            ..Config::default()
+        });
+        for _ in 0..1000 {
+            simulation.step();
+        }
+        for height in simulation.get_heights() {
+            assert_relative_eq!(Config::default().depth, *height);
+        }
+    }
+
+    #[test]
+    fn shaking_at_resonance_sloshes_most() {
+        let natural_frequency = Simulation::new().natural_frequency();
+        let resonant = wall_height_range(natural_frequency);
+        assert!(resonant > 3.0 * wall_height_range(0.5 * natural_frequency));
+        // Shaking twice as fast takes four times the force, but still sloshes less.
+        assert!(resonant > 2.0 * wall_height_range(2.0 * natural_frequency));
+    }
+
+    #[test]
+    fn shaking_keeps_the_water() {
+        let mut simulation = Simulation::new();
+        simulation.sync_config(&Config {
+            amplitude: 0.05,
+            frequency: simulation.natural_frequency(),
+            ..Config::default()
+        });
+        for _ in 0..2000 {
+            simulation.step();
+        }
+        let volume: f32 = simulation.get_heights().iter().sum();
+        assert_relative_eq!(
+            COLUMNS as f32 * Config::default().depth,
+            volume,
+            epsilon = 1e-3
+        );
+        assert!(simulation.get_heights().iter().all(|height| *height >= 0.0));
+    }
+}
@@ -0,0 +1,127 @@
+/// Snapshots of simulation state, serialized to bytes, e.g. to stream a simulation to another instance
+/// which renders it remotely. Values are written in order as little-endian bytes, and read back in the
+/// same order, so a snapshot is only meaningful to the same type that wrote it.
+use cgmath::Vector3;
+
+/// Serializes values into a snapshot.
+#[derive(Default)]
+pub struct SnapshotWriter {
+    bytes: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    pub fn new() -> SnapshotWriter {
+        SnapshotWriter { bytes: Vec::new() }
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_vector3(&mut self, value: Vector3<f32>) {
+        self.write_f32(value.x);
+        self.write_f32(value.y);
+        self.write_f32(value.z);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Deserializes the values of a snapshot, in the order they were written.
+pub struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> SnapshotReader<'a> {
+        SnapshotReader { bytes }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        if self.bytes.len() < N {
+            return Err("The snapshot ended early!");
+        }
+        let (value, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(value.try_into().unwrap())
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, &'static str> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, &'static str> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_vector3(&mut self) -> Result<Vector3<f32>, &'static str> {
+        Ok(Vector3::new(
+            self.read_f32()?,
+            self.read_f32()?,
+            self.read_f32()?,
+        ))
+    }
+
+    /// Errors if any of the snapshot is left unread, e.g. because it was written by a different type.
+    pub fn finish(self) -> Result<(), &'static str> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err("The snapshot has unexpected data!")
+        }
+    }
+}
+
+/// Simulation state which can be saved to and restored from snapshots.
+pub trait Snapshot {
+    /// Writes the state which evolves as the simulation steps, i.e. what's needed to render it.
+    fn write_snapshot(&self, writer: &mut SnapshotWriter);
+
+    /// Restores the state written by write_snapshot. On error, the state may be partially restored.
+    fn read_snapshot(&mut self, reader: &mut SnapshotReader) -> Result<(), &'static str>;
+
+    fn to_snapshot(&self) -> Vec<u8> {
+        let mut writer = SnapshotWriter::new();
+        self.write_snapshot(&mut writer);
+        writer.finish()
+    }
+
+    fn restore_snapshot(&mut self, bytes: &[u8]) -> Result<(), &'static str> {
+        let mut reader = SnapshotReader::new(bytes);
+        self.read_snapshot(&mut reader)?;
+        reader.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::bounce;
+    use crate::simulation::checksum::Checksummed;
+
+    #[test]
+    fn round_trips_bouncing_ball() {
+        let mut original = bounce::State::new();
+        original.launch(Vector3::new(0.5, 0.2, -0.3), Vector3::new(1.0, 2.0, 3.0));
+        for _ in 0..100 {
+            original.step();
+        }
+        let mut copy = bounce::State::new();
+        copy.restore_snapshot(&original.to_snapshot()).unwrap();
+        assert_eq!(copy.checksum(), original.checksum());
+
+        let snapshot = original.to_snapshot();
+        assert!(copy
+            .restore_snapshot(&snapshot[..snapshot.len() - 1])
+            .is_err());
+        let mut longer = snapshot.clone();
+        longer.push(0);
+        assert!(copy.restore_snapshot(&longer).is_err());
+    }
+}
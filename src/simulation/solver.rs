@@ -0,0 +1,132 @@
+/// Budgets and convergence statistics for iterative solvers, which refine their solution over several
+/// iterations each step, so that accuracy can be traded for speed knowingly.
+use std::time::{Duration, Instant};
+
+/// How much work an iterative solver may do each step. It stops after the most iterations, once an
+/// iteration's residual falls to the tolerance, or once it's run for the time budget, if there is one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SolverBudget {
+    pub max_iterations: usize,
+    pub tolerance: f32,
+    pub time_budget: Option<Duration>,
+}
+
+impl SolverBudget {
+    pub fn new(max_iterations: usize) -> SolverBudget {
+        SolverBudget {
+            max_iterations,
+            tolerance: 0.0,
+            time_budget: None,
+        }
+    }
+}
+
+/// Why an iterative solver stopped.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SolverStop {
+    Converged,
+    MaxIterations,
+    TimeBudget,
+}
+
+impl SolverStop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SolverStop::Converged => "Converged",
+            SolverStop::MaxIterations => "Max Iterations",
+            SolverStop::TimeBudget => "Time Budget",
+        }
+    }
+}
+
+/// How an iterative solver converged over a step: the residual after each iteration it ran, how long it ran,
+/// and why it stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolverStats {
+    pub residuals: Vec<f32>,
+    pub elapsed: Duration,
+    pub stop: SolverStop,
+}
+
+impl Default for SolverStats {
+    fn default() -> Self {
+        Self {
+            residuals: Vec::new(),
+            elapsed: Duration::ZERO,
+            stop: SolverStop::Converged,
+        }
+    }
+}
+
+impl SolverStats {
+    pub fn get_iterations(&self) -> usize {
+        self.residuals.len()
+    }
+
+    pub fn get_final_residual(&self) -> Option<f32> {
+        self.residuals.last().copied()
+    }
+}
+
+/// Runs the iteration, which returns its residual, until the budget says to stop. At least one iteration
+/// runs however small the time budget, unless the most iterations is zero.
+pub fn iterate(budget: &SolverBudget, mut iteration: impl FnMut() -> f32) -> SolverStats {
+    let start = Instant::now();
+    let mut residuals = Vec::with_capacity(budget.max_iterations);
+    let stop = loop {
+        if residuals.len() >= budget.max_iterations {
+            break SolverStop::MaxIterations;
+        }
+        let residual = iteration();
+        residuals.push(residual);
+        if residual <= budget.tolerance {
+            break SolverStop::Converged;
+        }
+        if budget
+            .time_budget
+            .is_some_and(|time_budget| start.elapsed() >= time_budget)
+        {
+            break SolverStop::TimeBudget;
+        }
+    };
+    SolverStats {
+        residuals,
+        elapsed: start.elapsed(),
+        stop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_once_converged() {
+        let budget = SolverBudget {
+            max_iterations: 10,
+            tolerance: 0.1,
+            time_budget: None,
+        };
+        let mut residual = 1.0;
+        let stats = iterate(&budget, || {
+            residual /= 2.0;
+            residual
+        });
+        assert_eq!(stats.residuals, vec![0.5, 0.25, 0.125, 0.0625]);
+        assert_eq!(stats.stop, SolverStop::Converged);
+    }
+
+    #[test]
+    fn stops_at_the_budget() {
+        let stats = iterate(&SolverBudget::new(3), || 1.0);
+        assert_eq!(stats.get_iterations(), 3);
+        assert_eq!(stats.stop, SolverStop::MaxIterations);
+        let budget = SolverBudget {
+            time_budget: Some(Duration::ZERO),
+            ..SolverBudget::new(3)
+        };
+        let stats = iterate(&budget, || 1.0);
+        assert_eq!(stats.get_iterations(), 1);
+        assert_eq!(stats.stop, SolverStop::TimeBudget);
+    }
+}
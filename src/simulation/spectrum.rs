@@ -0,0 +1,268 @@
+/// Spectral analysis of the contact impulses an object receives, e.g. the rattle of a ball coming to rest,
+/// whose bounces come faster and faster. The impulses are recorded at a fixed sample rate, as a microphone
+/// or accelerometer would record the sound or vibration of the collisions, and their spectrum over time is
+/// found with the short-time Fourier transform.
+use std::f32::consts::PI;
+
+/// Records the impulses on an object, summed over each sample period, keeping the most recent samples.
+pub struct ImpulseRecorder {
+    /// Samples per second.
+    sample_rate: f32,
+    max_samples: usize,
+    samples: Vec<f32>,
+    /// The impulse received since the last sample was recorded.
+    pending_impulse: f32,
+    /// The time since the last sample was recorded.
+    pending_time: f32,
+}
+
+impl ImpulseRecorder {
+    /// Records at the sample rate, keeping the samples of the last duration seconds.
+    pub fn new(sample_rate: f32, duration: f32) -> ImpulseRecorder {
+        ImpulseRecorder {
+            sample_rate,
+            max_samples: (sample_rate * duration).ceil() as usize,
+            samples: Vec::new(),
+            pending_impulse: 0.0,
+            pending_time: 0.0,
+        }
+    }
+
+    /// Adds the magnitude of an impulse received during the current sample.
+    pub fn add_impulse(&mut self, impulse: f32) {
+        self.pending_impulse += impulse;
+    }
+
+    /// Advances the recording by dt seconds, recording a sample for each sample period that ends.
+    pub fn advance(&mut self, dt: f32) {
+        let period = 1.0 / self.sample_rate;
+        self.pending_time += dt;
+        while self.pending_time >= period {
+            self.pending_time -= period;
+            self.samples.push(self.pending_impulse);
+            self.pending_impulse = 0.0;
+        }
+        if self.samples.len() > self.max_samples {
+            let excess = self.samples.len() - self.max_samples;
+            self.samples.drain(..excess);
+        }
+    }
+
+    pub fn get_sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// The impulse in each sample period, oldest first.
+    pub fn get_samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.pending_impulse = 0.0;
+        self.pending_time = 0.0;
+    }
+
+    /// The spectrogram of the samples, transforming windows of the window size,
+    /// which must be a power of two, every hop samples. It's empty for empty windows or hops.
+    pub fn spectrogram(&self, window_size: usize, hop: usize) -> Spectrogram {
+        if window_size == 0 || hop == 0 {
+            return Spectrogram::default();
+        }
+        let frames = if self.samples.len() < window_size {
+            Vec::new()
+        } else {
+            (0..=self.samples.len() - window_size)
+                .step_by(hop)
+                .map(|start| spectrum(&self.samples[start..start + window_size]))
+                .collect()
+        };
+        Spectrogram {
+            frames,
+            frame_interval: hop as f32 / self.sample_rate,
+            frequency_resolution: self.sample_rate / window_size as f32,
+        }
+    }
+}
+
+/// The magnitude spectra of successive windows of a recording.
+#[derive(Debug, Clone, Default)]
+pub struct Spectrogram {
+    /// Each window's spectrum, oldest first, from 0 Hz up to half the sample rate.
+    pub frames: Vec<Vec<f32>>,
+    /// The seconds between the starts of frames.
+    pub frame_interval: f32,
+    /// The hertz between the frequencies of each frame's bins.
+    pub frequency_resolution: f32,
+}
+
+impl Spectrogram {
+    pub fn max_magnitude(&self) -> f32 {
+        self.frames
+            .iter()
+            .flatten()
+            .fold(0.0, |max, magnitude| magnitude.max(max))
+    }
+}
+
+/// The magnitude spectrum of the samples, whose length must be a power of two, with a Hann window applied
+/// so that the edges of the window don't smear the spectrum. Has a bin for each multiple of the
+/// frequency resolution up to, but not including, half the sample rate.
+pub fn spectrum(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    let mut real: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| sample * 0.5 * (1.0 - (2.0 * PI * i as f32 / n as f32).cos()))
+        .collect();
+    let mut imaginary = vec![0.0; n];
+    fft(&mut real, &mut imaginary);
+    real.iter()
+        .zip(imaginary.iter())
+        .take(n / 2)
+        .map(|(re, im)| (re * re + im * im).sqrt())
+        .collect()
+}
+
+/// The in-place radix-2 Cooley-Tukey fast Fourier transform. Panics if the length isn't a power of two.
+fn fft(real: &mut [f32], imaginary: &mut [f32]) {
+    let n = real.len();
+    assert!(n.is_power_of_two(), "FFTs need a power of two samples!");
+    // Reorders the samples by bit-reversed index, so that each pass combines adjacent halves.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            real.swap(i, j);
+            imaginary.swap(i, j);
+        }
+    }
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f32;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (even, odd) = (start + k, start + k + length / 2);
+                let odd_real = real[odd] * cos - imaginary[odd] * sin;
+                let odd_imaginary = real[odd] * sin + imaginary[odd] * cos;
+                real[odd] = real[even] - odd_real;
+                imaginary[odd] = imaginary[even] - odd_imaginary;
+                real[even] += odd_real;
+                imaginary[even] += odd_imaginary;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The frequency of the loudest bin, other than 0 Hz.
+    fn peak_frequency(spectrum: &[f32], frequency_resolution: f32) -> f32 {
+        let (bin, _) =
+            spectrum
+                .iter()
+                .enumerate()
+                .skip(1)
+                .fold((0, 0.0), |(peak, max), (bin, magnitude)| {
+                    if *magnitude > max {
+                        (bin, *magnitude)
+                    } else {
+                        (peak, max)
+                    }
+                });
+        bin as f32 * frequency_resolution
+    }
+
+    /// The frequency of the lowest strong peak, above the leakage of 0 Hz into its neighbouring bins.
+    /// An impulse train's spectrum has peaks at its rate and each harmonic of it, so this is its rate.
+    fn fundamental_frequency(spectrum: &[f32], frequency_resolution: f32) -> f32 {
+        let max = spectrum[2..].iter().fold(0.0_f32, |max, m| m.max(max));
+        let bin = (2..spectrum.len() - 1)
+            .find(|&bin| {
+                spectrum[bin] >= 0.5 * max
+                    && spectrum[bin] >= spectrum[bin - 1]
+                    && spectrum[bin] >= spectrum[bin + 1]
+            })
+            .unwrap();
+        bin as f32 * frequency_resolution
+    }
+
+    #[test]
+    fn sine_peaks_at_its_frequency() {
+        let samples: Vec<f32> = (0..256)
+            .map(|i| (2.0 * PI * 10.0 * i as f32 / 256.0).sin())
+            .collect();
+        // 256 samples over a second, so bins are 1 Hz apart.
+        assert_eq!(peak_frequency(&spectrum(&samples), 1.0), 10.0);
+    }
+
+    #[test]
+    fn records_impulses_per_sample() {
+        let mut recorder = ImpulseRecorder::new(100.0, 1.0);
+        for step in 0..2000 {
+            // Struck eight times a second, with a step of a millisecond.
+            if step % 125 == 60 {
+                recorder.add_impulse(1.0);
+            }
+            recorder.advance(0.001);
+        }
+        // Only the last second is kept.
+        let samples = recorder.get_samples();
+        assert_eq!(samples.len(), 100);
+        assert_eq!(samples.iter().sum::<f32>(), 8.0);
+
+        let spectrogram = recorder.spectrogram(64, 16);
+        assert_eq!(spectrogram.frames.len(), 3);
+        assert_eq!(spectrogram.frequency_resolution, 100.0 / 64.0);
+    }
+
+    #[test]
+    fn empty_windows_and_hops_give_empty_spectrograms() {
+        let mut recorder = ImpulseRecorder::new(100.0, 1.0);
+        for _ in 0..1000 {
+            recorder.add_impulse(1.0);
+            recorder.advance(0.001);
+        }
+        assert!(recorder.spectrogram(0, 16).frames.is_empty());
+        assert!(recorder.spectrogram(64, 0).frames.is_empty());
+    }
+
+    #[test]
+    fn spectrogram_follows_chirp() {
+        let mut recorder = ImpulseRecorder::new(200.0, 10.0);
+        // Strikes twice a second for five seconds, then ten times a second.
+        for step in 0..2000 {
+            let interval = if step < 1000 { 100 } else { 20 };
+            if step % interval == 0 {
+                recorder.add_impulse(1.0);
+            }
+            recorder.advance(0.005);
+        }
+        let spectrogram = recorder.spectrogram(256, 64);
+        let first = fundamental_frequency(&spectrogram.frames[0], spectrogram.frequency_resolution);
+        let last = fundamental_frequency(
+            spectrogram.frames.last().unwrap(),
+            spectrogram.frequency_resolution,
+        );
+        // Within a bin of the rates.
+        assert!(
+            (first - 2.0).abs() < spectrogram.frequency_resolution,
+            "{}",
+            first
+        );
+        assert!(
+            (last - 10.0).abs() < spectrogram.frequency_resolution,
+            "{}",
+            last
+        );
+    }
+}
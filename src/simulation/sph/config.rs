@@ -1,18 +1,71 @@
 use cgmath::{Vector3, Zero};
 
+use super::super::container::{Container, ContainerShape};
 use super::super::state::Integration;
 
 use std::time::Duration;
 
+/// The initial particles of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// A block of water.
+    SinglePhase,
+    /// A layer of oil beneath a layer of water, which separate as the oil rises.
+    OilAndWater,
+    /// A block of water falling through a sponge, which absorbs it until saturated.
+    Sponge,
+    /// A ball of water, seeded by voxelizing a sphere mesh.
+    Ball,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 4] = [
+        Scenario::SinglePhase,
+        Scenario::OilAndWater,
+        Scenario::Sponge,
+        Scenario::Ball,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scenario::SinglePhase => "Single Phase",
+            Scenario::OilAndWater => "Oil and Water",
+            Scenario::Sponge => "Sponge",
+            Scenario::Ball => "Ball",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Config {
     pub integration: Integration,
     pub dt: f32, // Seconds as f32
+    /// Whether the timestep is computed each step from the CFL condition, in which case dt is its maximum.
+    pub adaptive_timestep: bool,
+    /// The fraction of the kernel radius a particle may travel in one adaptive timestep.
+    pub cfl_number: f32,
     pub particle_mass: f32,
     pub kernal_max_distance: f32,
     pub pressure_siffness: f32,
     pub reference_density: f32,
     pub kinematic_viscosity: f32,
+    pub scenario: Scenario,
+    /// The reference density of oil particles; that of water particles is reference_density.
+    pub oil_reference_density: f32,
+    pub oil_kinematic_viscosity: f32,
+    /// How strongly particles of different phases repel each other, minimizing their interface.
+    pub interface_tension: f32,
+    /// The number of particles a sponge holds when saturated.
+    pub sponge_capacity: usize,
+    /// Particles per second a sponge absorbs from those inside it.
+    pub sponge_absorption_rate: f32,
+    /// Particles per second a saturated sponge drips.
+    pub sponge_drip_rate: f32,
+    /// The drag on particles inside a sponge, per second.
+    pub sponge_resistance: f32,
     pub gravity: Vector3<f32>,
+    /// The container the walls move towards.
+    pub container: Container,
     pub coefficient_of_restitution: f32,
     pub coefficient_of_friction: f32,
     pub surface_tension_proportionality: f32,
@@ -23,13 +76,24 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             integration: Integration::Euler,
-            particle_mass: 0.001, // grams
+            particle_mass: 0.001, // kg
             kernal_max_distance: 0.1,
             pressure_siffness: 1.0,
-            reference_density: 1.0, // grams per cm
+            reference_density: 1.0, // kg/m^3
             kinematic_viscosity: 0.973,
+            scenario: Scenario::SinglePhase,
+            oil_reference_density: 0.8,
+            oil_kinematic_viscosity: 2.0,
+            interface_tension: 0.05,
+            sponge_capacity: 200,
+            sponge_absorption_rate: 400.0,
+            sponge_drip_rate: 30.0,
+            sponge_resistance: 20.0,
             dt: Duration::from_millis(1).as_secs_f32(),
+            adaptive_timestep: true,
+            cfl_number: 0.4,
             gravity: Vector3::<f32>::zero(),
+            container: Container::new(ContainerShape::Box, Vector3::new(0.75, 0.75, 0.75)),
             coefficient_of_restitution: 0.9,
             coefficient_of_friction: 0.0,
             surface_tension_proportionality: 1.0,
@@ -1,9 +1,9 @@
-use std::f32::consts::PI;
+use crate::math::{Real, Vec3A};
 
-use cgmath::{InnerSpace, Vector3, Zero};
+const PI: Real = std::f64::consts::PI as Real;
 
 /// s is the maximum distance of influence; r larger than s is returns 0.
-pub fn monaghan(r: f32, s: f32) -> f32 {
+pub fn monaghan(r: Real, s: Real) -> Real {
     let variable_numerator = if r / s >= 0.0 && r / s <= 1.0 {
         1.0 - 1.5 * (r / s).powi(2) + 0.75 * (r / s).powi(3)
     } else if r / s >= 1.0 && r / s <= 2.0 {
@@ -14,11 +14,11 @@ pub fn monaghan(r: f32, s: f32) -> f32 {
     variable_numerator / (PI * s.powi(3))
 }
 
-pub fn monaghan_gradient(r_vec: Vector3<f32>, s: f32) -> Vector3<f32> {
+pub fn monaghan_gradient(r_vec: Vec3A, s: Real) -> Vec3A {
     if r_vec.is_zero() {
-        return Vector3::<f32>::zero();
+        return Vec3A::ZERO;
     }
-    let r = r_vec.magnitude();
+    let r = r_vec.length();
     let variable_numerator = if r / s >= 0.0 && r / s <= 1.0 {
         3.0 * r / s * (-1.0 + 0.75 * r / s)
     } else if r / s >= 1.0 && r / s <= 2.0 {
@@ -26,10 +26,10 @@ pub fn monaghan_gradient(r_vec: Vector3<f32>, s: f32) -> Vector3<f32> {
     } else {
         0.0
     };
-    variable_numerator / (PI * s.powi(4)) * r_vec.normalize()
+    variable_numerator / (PI * s.powi(4)) * r_vec.normalize_or_zero()
 }
 
-pub fn monaghan_laplacian(r: f32, s: f32) -> f32 {
+pub fn monaghan_laplacian(r: Real, s: Real) -> Real {
     let variable_numerator = if r / s >= 0.0 && r / s <= 1.0 {
         3.0 * (-1.0 + 1.5 * r / s)
     } else if r / s >= 1.0 && r / s <= 2.0 {
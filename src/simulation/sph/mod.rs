@@ -1,10 +1,19 @@
 pub mod config;
 mod kernals;
+pub mod sponge;
 
-use self::config::Config;
+use self::config::{Config, Scenario};
+use self::sponge::Sponge;
 use super::consts;
-
-use cgmath::{InnerSpace, Vector3, Zero};
+use super::container::Container;
+use super::energy::EnergyBreakdown;
+use super::voxelize::{self, voxelize};
+use crate::math::{Real, Vec3A};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::geometry;
+use crate::simulation::state::Integration;
+
+use cgmath::{Vector3, Zero};
 use itertools::Itertools;
 use kiddo::distance::squared_euclidean;
 use kiddo::KdTree;
@@ -12,63 +21,128 @@ use rustc_hash::FxHashMap;
 
 use std::time::Duration;
 
-#[derive(Clone, Copy)]
-pub struct Plane {
-    point: Vector3<f32>,
-    normal: Vector3<f32>,
+/// The smallest adaptive timestep, so that an unstable simulation's timestep doesn't shrink to nothing.
+/// It still allows thousands of steps per frame, so callers stepping to keep up with real time should cap them.
+const MIN_ADAPTIVE_DT: f32 = 1e-5;
+
+/// The distance between initial particles.
+const INITIAL_SPACING: f32 = 0.1;
+/// The radius of the ball scenario's ball of water, which has about as many particles as the block.
+const BALL_RADIUS: f32 = 0.5;
+
+/// The stable timestep for viscous diffusion is proportional to h^2 / viscosity.
+const VISCOSITY_NUMBER: f32 = 0.125;
+
+/// The term which limits the timestep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestepLimit {
+    /// The configured dt; the fixed timestep, or the maximum adaptive timestep.
+    Configured,
+    /// Particles moving, or pressure waves travelling, too far within a step.
+    Velocity,
+    /// Momentum diffusing past a particle's neighbors within a step.
+    Viscosity,
 }
 
-impl Plane {
-    pub fn normal(&self) -> &Vector3<f32> {
-        &self.normal
+impl TimestepLimit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimestepLimit::Configured => "Configured dt",
+            TimestepLimit::Velocity => "Velocity",
+            TimestepLimit::Viscosity => "Viscosity",
+        }
     }
+}
 
-    pub fn distance_from_plane(&self, position: Vector3<f32>) -> f32 {
-        (position - self.point).dot(*self.normal())
-    }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestep {
+    /// Seconds
+    pub dt: f32,
+    pub limit: TimestepLimit,
+}
+
+/// The fluid a particle belongs to, which determines its reference density and viscosity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Water,
+    Oil,
 }
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct Particle {
     id: u32,
-    position: Vector3<f32>,
-    velocity: Vector3<f32>,
+    phase: Phase,
+    position: Vec3A,
+    velocity: Vec3A,
 }
 
 impl Particle {
-    pub fn new(id: u32, position: Vector3<f32>, velocity: Vector3<f32>) -> Particle {
+    pub fn new(id: u32, phase: Phase, position: Vector3<f32>, velocity: Vector3<f32>) -> Particle {
         Particle {
             id,
-            position,
-            velocity,
+            phase,
+            position: position.into(),
+            velocity: velocity.into(),
         }
     }
 
-    pub fn position(&self) -> &Vector3<f32> {
-        &self.position
+    pub fn position(&self) -> Vector3<f32> {
+        self.position.into()
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
     }
 }
 
+// The simulation is written with Vec3A rather than cgmath for speed in the neighbor loops,
+// converting to and from cgmath in its public API.
 pub struct Simulation {
     config: Config,
     particles: Vec<Particle>,
-    min_bounds: Vector3<f32>,
-    max_bounds: Vector3<f32>,
+    sponges: Vec<Sponge>,
+    /// Where the walls are now, moving towards the configured container's.
+    container: Container,
 }
 
 impl Simulation {
-    pub fn new(min_bounds: Vector3<f32>, max_bounds: Vector3<f32>) -> Self {
+    pub fn new() -> Self {
+        let config = Config::default();
+        let particles = Self::get_initial_particles(config.scenario);
+        let sponges = Self::get_initial_sponges(config.scenario);
+        Simulation {
+            particles,
+            sponges,
+            container: config.container,
+            config,
+        }
+    }
+
+    /// Gets a block of particles at rest, with phases for the scenario.
+    fn get_initial_particles(scenario: Scenario) -> Vec<Particle> {
+        if scenario == Scenario::Ball {
+            return Self::get_particles_in_mesh(geometry::get_sphere_vertices(BALL_RADIUS, 32, 32));
+        }
         let mut particles = Vec::<Particle>::new();
 
         let mut id = 0;
         for x in -4..4 {
             for z in -4..4 {
                 for y in -4..4 {
-                    let x_pos = x as f32 * 0.1;
-                    let z_pos = z as f32 * 0.1;
-                    let y_pos = y as f32 * 0.1;
+                    let x_pos = x as f32 * INITIAL_SPACING;
+                    let z_pos = z as f32 * INITIAL_SPACING;
+                    let y_pos = match scenario {
+                        // The water starts above the sponge.
+                        Scenario::Sponge => (y + 4) as f32 * INITIAL_SPACING,
+                        _ => y as f32 * INITIAL_SPACING,
+                    };
+                    let phase = match scenario {
+                        Scenario::OilAndWater if y < 0 => Phase::Oil,
+                        _ => Phase::Water,
+                    };
                     particles.push(Particle::new(
                         id,
+                        phase,
                         Vector3::<f32>::new(x_pos, y_pos, z_pos),
                         Vector3::<f32>::zero(),
                     ));
@@ -76,98 +150,208 @@ impl Simulation {
                 }
             }
         }
+        particles
+    }
 
-        Simulation {
-            config: Config::default(),
-            particles,
-            min_bounds,
-            max_bounds,
+    /// Gets water particles at rest filling the closed mesh, spaced as the initial block's are.
+    fn get_particles_in_mesh(mesh: (Vec<Vector3<f32>>, Vec<usize>)) -> Vec<Particle> {
+        let (vertex_positions, vertex_indices) = mesh;
+        voxelize(
+            &vertex_positions,
+            &vertex_indices,
+            INITIAL_SPACING,
+            voxelize::Mode::Solid,
+        )
+        .get_occupied_centers()
+        .into_iter()
+        .enumerate()
+        .map(|(id, position)| Particle::new(id as u32, Phase::Water, position, Vector3::zero()))
+        .collect()
+    }
+
+    fn get_initial_sponges(scenario: Scenario) -> Vec<Sponge> {
+        match scenario {
+            Scenario::Sponge => vec![Sponge::new(
+                Vector3::new(-0.5, -0.35, -0.5),
+                Vector3::new(0.5, -0.1, 0.5),
+            )],
+            _ => Vec::new(),
         }
     }
 
     pub fn step(&mut self) -> Duration {
+        let dt = self.timestep().dt;
+
+        self.move_walls(dt);
+
+        let accelerations = self.accelerations(&self.particles);
+        let timestep = dt as Real;
+        let new_particles = match self.config.integration {
+            // The velocities are kicked a whole step, and then the positions drift a whole step at them.
+            Integration::Leapfrog => self
+                .particles
+                .iter()
+                .zip(&accelerations)
+                .map(|(particle, acceleration)| {
+                    let velocity = particle.velocity + timestep * *acceleration;
+                    Particle {
+                        position: particle.position + timestep * velocity,
+                        velocity,
+                        ..*particle
+                    }
+                })
+                .collect_vec(),
+            // The velocities are kicked half a step, the positions drift a whole step at them, and then the
+            // velocities are kicked another half step by the accelerations at the new positions, for which the
+            // neighbors are found again.
+            Integration::VelocityVerlet => {
+                let drifted = self
+                    .particles
+                    .iter()
+                    .zip(&accelerations)
+                    .map(|(particle, acceleration)| {
+                        let velocity = particle.velocity + 0.5 * timestep * *acceleration;
+                        Particle {
+                            position: particle.position + timestep * velocity,
+                            velocity,
+                            ..*particle
+                        }
+                    })
+                    .collect_vec();
+                let accelerations = self.accelerations(&drifted);
+                drifted
+                    .into_iter()
+                    .zip(accelerations)
+                    .map(|(particle, acceleration)| Particle {
+                        velocity: particle.velocity + 0.5 * timestep * acceleration,
+                        ..particle
+                    })
+                    .collect_vec()
+            }
+            // The rest step by Euler, as the neighbors are only found once a step.
+            Integration::Euler
+            | Integration::Rk4
+            | Integration::ImplicitEuler
+            | Integration::Rk45 => self
+                .particles
+                .iter()
+                .zip(&accelerations)
+                .map(|(particle, acceleration)| Particle {
+                    position: particle.position + timestep * particle.velocity,
+                    velocity: particle.velocity + timestep * *acceleration,
+                    ..*particle
+                })
+                .collect_vec(),
+        };
+
+        self.update_particles(new_particles, dt);
+        for sponge in self.sponges.iter_mut() {
+            let particles = std::mem::take(&mut self.particles);
+            self.particles = sponge.update(particles, &self.config, dt);
+        }
+
+        Duration::from_secs_f32(dt)
+    }
+
+    /// The acceleration of each of the particles, from the pressure, viscosity, and interface tension of its
+    /// neighbors among them, and the external forces.
+    fn accelerations(&self, particles: &[Particle]) -> Vec<Vec3A> {
+        let kernal_max_distance = self.config.kernal_max_distance as Real;
         // Build the kdtree
         let mut kdtree = KdTree::new();
-        self.particles
+        particles
             .iter()
-            .for_each(|particle| kdtree.add(particle.position.as_ref(), particle).unwrap());
+            .for_each(|particle| kdtree.add(&particle.position.to_array(), particle).unwrap());
 
-        // Find the neighbors for each particle
+        // Find the neighbors for each particle.
+        // Densities are computed from the number density, the sum of the kernel over the
+        // neighbors regardless of their mass, so that they don't blur across the interfaces
+        // between phases of different densities (Solenthaler and Pajarola 2008).
         let mut neighbor_map: FxHashMap<u32, Vec<Particle>> =
-            FxHashMap::with_capacity_and_hasher(self.particles.len(), Default::default());
-        let mut density_map: FxHashMap<u32, f32> =
-            FxHashMap::with_capacity_and_hasher(self.particles.len(), Default::default());
-        self.particles.iter().for_each(|particle| {
+            FxHashMap::with_capacity_and_hasher(particles.len(), Default::default());
+        let mut number_density_map: FxHashMap<u32, Real> =
+            FxHashMap::with_capacity_and_hasher(particles.len(), Default::default());
+        particles.iter().for_each(|particle| {
             let neighbors = kdtree
-                .nearest(particle.position.as_ref(), 8, &squared_euclidean)
+                .nearest(&particle.position.to_array(), 8, &squared_euclidean)
                 .unwrap();
             let neighbors = neighbors
                 .iter()
-                .filter(|neighbor| neighbor.0 < self.config.kernal_max_distance.powi(2))
+                .filter(|neighbor| neighbor.0 < kernal_max_distance.powi(2))
                 .collect_vec();
             let neighbors = neighbors
                 .iter()
                 .map(|(_, &&particle)| particle)
                 .collect_vec();
 
-            let density: f32 = neighbors
+            let number_density: Real = neighbors
                 .iter()
                 .map(|neighbor| {
-                    let r_ij = particle.position - neighbor.position;
-                    let r = if r_ij.is_zero() {
-                        0.0
-                    } else {
-                        r_ij.magnitude()
-                    };
-                    self.config.particle_mass
-                        * kernals::monaghan(r, self.config.kernal_max_distance)
+                    let r = (particle.position - neighbor.position).length();
+                    kernals::monaghan(r, kernal_max_distance)
                 })
                 .sum();
 
-            density_map.insert(particle.id, density);
+            number_density_map.insert(particle.id, number_density);
             neighbor_map.insert(particle.id, neighbors);
         });
 
-        // Do navier-stokes to find new particle positions, velocities.
-        let mut new_particles = Vec::with_capacity(self.particles.len());
-        self.particles.iter().for_each(|particle| {
+        // Do navier-stokes to find the particles' accelerations.
+        let mut accelerations = Vec::with_capacity(particles.len());
+        particles.iter().for_each(|particle| {
             let neighbors = neighbor_map.get(&particle.id).unwrap();
 
-            let density = *density_map.get(&particle.id).unwrap();
-            let pressure = self.pressure(density);
+            let mass = self.mass(particle.phase);
+            let number_density = *number_density_map.get(&particle.id).unwrap();
+            let pressure = self.pressure(mass * number_density, particle.phase);
 
-            let pressure_gradient: Vector3<f32> = neighbors
+            let pressure_gradient: Vec3A = neighbors
                 .iter()
                 .map(|neighbor| {
                     if neighbor.id == particle.id {
-                        return Vector3::<f32>::zero();
+                        return Vec3A::ZERO;
                     }
-                    let neighbor_density = *density_map.get(&neighbor.id).unwrap();
-                    let neighbor_pressure = self.pressure(neighbor_density);
-                    self.config.particle_mass
-                        * ((pressure / density.powi(2))
-                            + (neighbor_pressure / neighbor_density.powi(2)))
+                    let neighbor_number_density = *number_density_map.get(&neighbor.id).unwrap();
+                    let neighbor_pressure = self.pressure(
+                        self.mass(neighbor.phase) * neighbor_number_density,
+                        neighbor.phase,
+                    );
+                    ((pressure / number_density.powi(2))
+                        + (neighbor_pressure / neighbor_number_density.powi(2)))
                         * kernals::monaghan_gradient(
                             neighbor.position - particle.position,
-                            self.config.kernal_max_distance,
+                            kernal_max_distance,
                         )
                 })
+                .sum::<Vec3A>()
+                / mass;
+
+            let diffusion: Vec3A = neighbors
+                .iter()
+                .map(|neighbor| {
+                    let r = (neighbor.position - particle.position).length();
+                    let kinematic_viscosity = 0.5
+                        * (self.kinematic_viscosity(particle.phase)
+                            + self.kinematic_viscosity(neighbor.phase));
+                    kinematic_viscosity * (neighbor.velocity - particle.velocity) / number_density
+                        * kernals::monaghan_laplacian(r, kernal_max_distance)
+                })
                 .sum();
 
-            let diffusion: Vector3<f32> = neighbors
+            // Particles are repelled by neighbors of other phases, which minimizes the area of
+            // the interface between the phases.
+            let interface_tension: Vec3A = neighbors
                 .iter()
+                .filter(|neighbor| neighbor.phase != particle.phase)
                 .map(|neighbor| {
-                    let r_ij = neighbor.position - particle.position;
-                    let r = if r_ij.is_zero() {
-                        0.0
-                    } else {
-                        r_ij.magnitude()
-                    };
-                    self.config.particle_mass * (neighbor.velocity - particle.velocity) / density
-                        * kernals::monaghan_laplacian(r, self.config.kernal_max_distance)
+                    let neighbor_number_density = *number_density_map.get(&neighbor.id).unwrap();
+                    -kernals::monaghan_gradient(
+                        particle.position - neighbor.position,
+                        kernal_max_distance,
+                    ) / neighbor_number_density
                 })
-                .sum::<Vector3<f32>>()
-                * self.config.kinematic_viscosity;
+                .sum::<Vec3A>()
+                * self.config.interface_tension as Real;
 
             // TODO this surface tension stuff doesn't work, it causes stuff to blow up. Fix it.
             //let surface_value: Vector3<f32> = neighbors
@@ -209,97 +393,183 @@ impl Simulation {
             //    Vector3::<f32>::zero()
             //};
 
-            let external_acceleration = self.config.gravity; // + surface_tension_force / self.config.particle_mass;
-
-            let du_dt = -pressure_gradient + diffusion + external_acceleration;
+            let external_acceleration = Vec3A::from(self.config.gravity); // + surface_tension_force / self.config.particle_mass;
 
-            let new_position = particle.position + self.config.dt * particle.velocity;
-            let new_velocity = particle.velocity + self.config.dt * du_dt;
-            let new_particle = Particle::new(particle.id, new_position, new_velocity);
-            new_particles.push(new_particle);
-        });
+            // Darcy drag, from the fluid flowing through the pores of a sponge.
+            let porous_drag = if self.sponges.iter().any(|sponge| sponge.contains(particle)) {
+                -(self.config.sponge_resistance as Real) * particle.velocity
+            } else {
+                Vec3A::ZERO
+            };
 
-        self.update_particles(new_particles);
+            let du_dt = -pressure_gradient
+                + diffusion
+                + interface_tension
+                + porous_drag
+                + external_acceleration;
 
-        Duration::from_secs_f32(self.config.dt)
+            accelerations.push(du_dt);
+        });
+        accelerations
     }
 
+    /// The duration of the next step.
     pub fn get_timestep(&self) -> Duration {
-        Duration::from_secs_f32(self.config.dt)
+        Duration::from_secs_f32(self.timestep().dt)
+    }
+
+    /// Computes the next step's timestep and the term limiting it.
+    /// If the timestep is adaptive, it is the largest satisfying the CFL condition, where
+    /// no particle may travel more than a fraction of the kernel radius, nor pressure waves
+    /// outrun it, and the viscous diffusion limit, up to the configured dt.
+    pub fn timestep(&self) -> Timestep {
+        let configured = Timestep {
+            dt: self.config.dt,
+            limit: TimestepLimit::Configured,
+        };
+        if !self.config.adaptive_timestep {
+            return configured;
+        }
+        let h = self.config.kernal_max_distance;
+
+        let fastest: Real = self
+            .particles
+            .iter()
+            .map(|particle| particle.velocity.length())
+            .fold(0.0, Real::max);
+        let max_speed = fastest as f32;
+        // The speed of sound of the equation of state, p = k (density - reference density).
+        let sound_speed = self.config.pressure_siffness.max(0.0).sqrt();
+        let velocity = Timestep {
+            dt: self.config.cfl_number * h / (max_speed + sound_speed),
+            limit: TimestepLimit::Velocity,
+        };
+        let max_kinematic_viscosity = match self.config.scenario {
+            Scenario::OilAndWater => self
+                .config
+                .kinematic_viscosity
+                .max(self.config.oil_kinematic_viscosity),
+            _ => self.config.kinematic_viscosity,
+        };
+        let viscosity = Timestep {
+            dt: VISCOSITY_NUMBER * h.powi(2) / max_kinematic_viscosity,
+            limit: TimestepLimit::Viscosity,
+        };
+
+        let timestep = [velocity, viscosity]
+            .into_iter()
+            // Terms which are infinite or NaN, e.g. with no viscosity, don't limit the timestep.
+            .filter(|timestep| timestep.dt.is_finite())
+            .fold(configured, |limiting, timestep| {
+                if timestep.dt < limiting.dt {
+                    timestep
+                } else {
+                    limiting
+                }
+            });
+        Timestep {
+            dt: timestep.dt.max(MIN_ADAPTIVE_DT),
+            ..timestep
+        }
     }
 
+    /// The particles in the fluid, excluding those held by sponges.
     pub fn get_particles(&self) -> &Vec<Particle> {
         &self.particles
     }
 
-    pub fn sync_sim_from_ui(&mut self, ui: &mut crate::gui::sph::SphUi) {
-        let ui_config_state = ui.get_gui_state_mut();
-        self.config.integration = ui_config_state.integration;
-        self.config.dt = ui_config_state.dt;
-        self.config.particle_mass = ui_config_state.particle_mass;
-        self.config.kernal_max_distance = ui_config_state.kernal_max_distance;
-        self.config.gravity = ui_config_state.gravity;
-        self.config.coefficient_of_restitution = ui_config_state.coefficient_of_restitution;
-        self.config.coefficient_of_friction = ui_config_state.coefficient_of_friction;
+    pub fn get_sponges(&self) -> &Vec<Sponge> {
+        &self.sponges
     }
 
-    /// Updates the particles with the new particles, handling collisions with bounding box
+    /// The particles' kinetic energy, and their potential energy in gravity. The energy stored in the fluid's
+    /// compression isn't counted, and viscosity and collisions with the walls dissipate energy.
+    pub fn energy(&self) -> EnergyBreakdown {
+        let gravity: Vec3A = self.config.gravity.into();
+        self.particles
+            .iter()
+            .map(|particle| {
+                let mass = self.mass(particle.phase);
+                let kinetic: Real = 0.5 * mass * particle.velocity.length_squared();
+                let gravitational: Real = -mass * gravity.dot(particle.position);
+                EnergyBreakdown {
+                    kinetic: kinetic as f32,
+                    gravitational: gravitational as f32,
+                    elastic: 0.0,
+                }
+            })
+            .sum()
+    }
+
+    /// The walls the particles are contained by, which may be moving towards the configured container's.
+    pub fn get_container(&self) -> &Container {
+        &self.container
+    }
+
+    // Moves the walls towards the configured container's, pushing the particles they pass along with them.
+    fn move_walls(&mut self, dt: f32) {
+        let moved = self
+            .container
+            .approach(&self.config.container, Container::WALL_SPEED * dt);
+        if moved <= 0.0 {
+            return;
+        }
+        for particle in self.particles.iter_mut() {
+            let mut position = particle.position.into();
+            let mut velocity = particle.velocity.into();
+            self.container
+                .push_inside(&mut position, &mut velocity, moved / dt, consts::EPSILON);
+            particle.position = position.into();
+            particle.velocity = velocity.into();
+        }
+    }
+
+    /// Updates the particles with the new particles, handling collisions with the container's walls
     /// and zeroing accumulated forces, readying the simulation for the next step.
-    fn update_particles(&mut self, mut new_particles: Vec<Particle>) {
+    fn update_particles(&mut self, mut new_particles: Vec<Particle>, dt: f32) {
         for (new_particle, old_particle) in new_particles.iter_mut().zip(&self.particles) {
-            if let Some(plane) =
-                self.get_collided_plane(old_particle.position, new_particle.position)
-            {
-                let old_distance_to_plane = plane.distance_from_plane(old_particle.position);
-                let new_distance_to_plane = plane.distance_from_plane(new_particle.position);
+            let new_position = new_particle.position.into();
+            // The old particle is inside the walls, so it's crossed any wall the new one is outside of.
+            if let Some(wall) = self.container.walls_within(new_position, 0.0).next() {
+                let old_distance_to_plane = wall.distance_to(old_particle.position.into());
+                let new_distance_to_plane = wall.distance_to(new_position);
+                let normal: Vec3A = wall.normal.into();
 
                 let fraction_timestep =
                     old_distance_to_plane / (old_distance_to_plane - new_distance_to_plane);
 
                 let collision_point = old_particle.position
-                    + self.config.dt * fraction_timestep * old_particle.velocity;
-                let collision_point = collision_point + plane.normal() * consts::EPSILON;
-                let new_position = Vector3::new(
-                    collision_point.x.clamp(
-                        self.min_bounds.x + consts::EPSILON,
-                        self.max_bounds.x - consts::EPSILON,
-                    ),
-                    collision_point.y.clamp(
-                        self.min_bounds.y + consts::EPSILON,
-                        self.max_bounds.y - consts::EPSILON,
-                    ),
-                    collision_point.z.clamp(
-                        self.min_bounds.z + consts::EPSILON,
-                        self.max_bounds.z - consts::EPSILON,
-                    ),
-                );
+                    + (dt * fraction_timestep) as Real * old_particle.velocity;
+                let collision_point = collision_point + normal * consts::EPSILON as Real;
+                let new_position = self
+                    .container
+                    .clamp_inside(collision_point.into(), consts::EPSILON);
 
                 let velocity_collision = old_particle.velocity;
 
-                let velocity_collision_normal =
-                    velocity_collision.dot(*plane.normal()) * plane.normal();
+                let velocity_collision_normal = velocity_collision.dot(normal) * normal;
                 let velocity_collision_tangent = velocity_collision - velocity_collision_normal;
 
                 let velocity_response_normal =
-                    -1.0 * velocity_collision_normal * self.config.coefficient_of_restitution;
+                    -velocity_collision_normal * self.config.coefficient_of_restitution as Real;
                 let velocity_response_tangent = if velocity_collision_tangent.is_zero()
-                    || velocity_collision_tangent.magnitude().is_nan()
+                    || velocity_collision_tangent.length().is_nan()
                     || velocity_collision_normal.is_zero()
                 {
-                    Vector3::<f32>::zero()
+                    Vec3A::ZERO
                 } else {
                     velocity_collision_tangent
-                        - velocity_collision_tangent.normalize()
-                            * f32::min(
-                                self.config.coefficient_of_friction
-                                    * velocity_collision_normal.magnitude(),
-                                velocity_collision_tangent.magnitude(),
+                        - velocity_collision_tangent.normalize_or_zero()
+                            * Real::min(
+                                self.config.coefficient_of_friction as Real
+                                    * velocity_collision_normal.length(),
+                                velocity_collision_tangent.length(),
                             )
                 };
 
                 let velocity_response = velocity_response_normal + velocity_response_tangent;
 
-                new_particle.position = new_position;
+                new_particle.position = new_position.into();
                 new_particle.velocity = velocity_response;
             }
         }
@@ -307,54 +577,253 @@ impl Simulation {
         self.particles = new_particles;
     }
 
-    fn get_bounding_planes(&self) -> Vec<Plane> {
-        let bottom = Plane {
-            point: self.min_bounds,
-            normal: Vector3::unit_y(),
-        };
-        let top = Plane {
-            point: self.max_bounds,
-            normal: -Vector3::unit_y(),
-        };
-        let left = Plane {
-            point: self.min_bounds,
-            normal: Vector3::unit_x(),
-        };
-        let right = Plane {
-            point: self.max_bounds,
-            normal: -Vector3::unit_x(),
+    fn pressure(&self, density: Real, phase: Phase) -> Real {
+        self.config.pressure_siffness as Real * (density - self.reference_density(phase))
+    }
+
+    fn reference_density(&self, phase: Phase) -> Real {
+        let density = match phase {
+            Phase::Water => self.config.reference_density,
+            Phase::Oil => self.config.oil_reference_density,
         };
-        let back = Plane {
-            point: self.min_bounds,
-            normal: Vector3::unit_z(),
+        density as Real
+    }
+
+    fn kinematic_viscosity(&self, phase: Phase) -> Real {
+        let viscosity = match phase {
+            Phase::Water => self.config.kinematic_viscosity,
+            Phase::Oil => self.config.oil_kinematic_viscosity,
         };
-        let front = Plane {
-            point: self.max_bounds,
-            normal: -Vector3::unit_z(),
+        viscosity as Real
+    }
+
+    /// All particles have the same rest volume, so their masses are in proportion to
+    /// their phases' reference densities. Water particles have the configured particle mass.
+    fn mass(&self, phase: Phase) -> Real {
+        self.config.particle_mass as Real * self.reference_density(phase)
+            / self.config.reference_density as Real
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.integration = config.integration;
+        self.config.dt = config.dt;
+        self.config.adaptive_timestep = config.adaptive_timestep;
+        self.config.cfl_number = config.cfl_number;
+        self.config.particle_mass = config.particle_mass;
+        self.config.kernal_max_distance = config.kernal_max_distance;
+        self.config.gravity = config.gravity;
+        self.config.coefficient_of_restitution = config.coefficient_of_restitution;
+        self.config.coefficient_of_friction = config.coefficient_of_friction;
+        self.config.pressure_siffness = config.pressure_siffness;
+        self.config.reference_density = config.reference_density;
+        self.config.kinematic_viscosity = config.kinematic_viscosity;
+        self.config.oil_reference_density = config.oil_reference_density;
+        self.config.oil_kinematic_viscosity = config.oil_kinematic_viscosity;
+        self.config.interface_tension = config.interface_tension;
+        self.config.sponge_capacity = config.sponge_capacity;
+        self.config.sponge_absorption_rate = config.sponge_absorption_rate;
+        self.config.sponge_drip_rate = config.sponge_drip_rate;
+        self.config.sponge_resistance = config.sponge_resistance;
+        self.config.container = config.container;
+        if config.scenario != self.config.scenario {
+            self.config.scenario = config.scenario;
+            self.particles = Self::get_initial_particles(self.config.scenario);
+            self.sponges = Self::get_initial_sponges(self.config.scenario);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::container::ContainerShape;
+    use cgmath::{assert_relative_eq, InnerSpace};
+
+    fn simulation() -> Simulation {
+        let mut simulation = Simulation::new();
+        simulation.config.container =
+            Container::new(ContainerShape::Box, Vector3::new(1.0, 1.0, 1.0));
+        simulation.container = simulation.config.container;
+        simulation
+    }
+
+    #[test]
+    fn fixed_timestep_is_configured() {
+        let mut simulation = simulation();
+        simulation.config.adaptive_timestep = false;
+        simulation.config.kinematic_viscosity = 1000.0;
+        assert_eq!(simulation.timestep().dt, simulation.config.dt);
+        assert_eq!(simulation.timestep().limit, TimestepLimit::Configured);
+    }
+
+    #[test]
+    fn fast_particles_limit_timestep() {
+        let mut simulation = simulation();
+        simulation.config.kinematic_viscosity = 0.0;
+        simulation.particles[0].velocity = Vec3A::new(1000.0, 0.0, 0.0);
+        let timestep = simulation.timestep();
+        assert_eq!(timestep.limit, TimestepLimit::Velocity);
+        // No particle travels further than the CFL number's fraction of the kernel radius.
+        let distance = 1000.0 * timestep.dt;
+        assert!(distance <= simulation.config.cfl_number * simulation.config.kernal_max_distance);
+    }
+
+    #[test]
+    fn oil_starts_beneath_water() {
+        let particles = Simulation::get_initial_particles(Scenario::OilAndWater);
+        for particle in particles {
+            let expected = if particle.position().y < 0.0 {
+                Phase::Oil
+            } else {
+                Phase::Water
+            };
+            assert_eq!(particle.phase(), expected);
+        }
+    }
+
+    #[test]
+    fn ball_fills_sphere() {
+        let particles = Simulation::get_initial_particles(Scenario::Ball);
+        let block = Simulation::get_initial_particles(Scenario::SinglePhase);
+        assert!(particles.len() > block.len() * 9 / 10 && particles.len() < block.len() * 11 / 10);
+        for particle in particles {
+            assert!(particle.position().magnitude() < BALL_RADIUS);
+        }
+    }
+
+    #[test]
+    fn velocity_verlet_falls_exactly() {
+        let fall = |integration| {
+            let mut simulation = simulation();
+            simulation.config.integration = integration;
+            simulation.config.adaptive_timestep = false;
+            simulation.config.gravity = Vector3::new(0.0, -9.8, 0.0);
+            simulation.particles = vec![Particle::new(
+                0,
+                Phase::Water,
+                Vector3::zero(),
+                Vector3::zero(),
+            )];
+            for _ in 0..100 {
+                simulation.step();
+            }
+            let t = 100.0 * simulation.config.dt;
+            let expected = 0.5 * simulation.config.gravity * t * t;
+            (simulation.particles[0].position() - expected).magnitude()
         };
-        vec![bottom, top, left, right, back, front]
+        // A lone particle only falls, which velocity Verlet steps exactly, and Euler lags behind.
+        assert!(fall(Integration::VelocityVerlet) < 1e-5);
+        assert!(fall(Integration::Euler) > 1e-4);
     }
 
-    fn get_collided_plane(
-        &self,
-        old_position: Vector3<f32>,
-        new_position: Vector3<f32>,
-    ) -> Option<Plane> {
-        let planes = self.get_bounding_planes();
+    #[test]
+    fn falling_particle_keeps_its_energy() {
+        let mut simulation = simulation();
+        simulation.config.integration = Integration::VelocityVerlet;
+        simulation.config.adaptive_timestep = false;
+        simulation.config.gravity = Vector3::new(0.0, -9.8, 0.0);
+        simulation.particles = vec![Particle::new(
+            0,
+            Phase::Water,
+            Vector3::zero(),
+            Vector3::zero(),
+        )];
+        let initial = simulation.energy();
+        for _ in 0..100 {
+            simulation.step();
+        }
+        let energy = simulation.energy();
+        assert!(energy.kinetic > 0.0 && energy.gravitational < 0.0);
+        assert_relative_eq!(initial.total(), energy.total(), epsilon = 1e-7);
+    }
 
-        planes
+    #[test]
+    fn shrinking_walls_push_particles() {
+        let mut simulation = simulation();
+        simulation.config.adaptive_timestep = false;
+        simulation.particles = (-4..4)
+            .map(|x| {
+                let position = Vector3::new(x as f32 * INITIAL_SPACING, 0.0, 0.0);
+                Particle::new(x as u32, Phase::Water, position, Vector3::zero())
+            })
+            .collect();
+        simulation.config.container =
+            Container::new(ContainerShape::Box, Vector3::new(0.2, 0.2, 0.2));
+        let dt = simulation.config.dt;
+        while simulation.container != simulation.config.container {
+            let previous = simulation.particles.clone();
+            simulation.move_walls(dt);
+            for (particle, previous) in simulation.particles.iter().zip(previous) {
+                assert!(simulation.container.contains(particle.position()));
+                // The walls push the particles along rather than teleporting them inside.
+                let pushed = (particle.position - previous.position).length();
+                assert!(pushed <= (Container::WALL_SPEED * dt + consts::EPSILON) as Real);
+            }
+        }
+        assert_relative_eq!(-0.2, simulation.particles[0].position().x, epsilon = 1e-3);
+        for _ in 0..100 {
+            simulation.step();
+        }
+        let container = simulation.container;
+        assert!(simulation
+            .particles
             .iter()
-            .find(|plane| {
-                let old_distance_to_plane = plane.distance_from_plane(old_position);
-                let new_distance_to_plane = plane.distance_from_plane(new_position);
+            .all(|particle| container.contains(particle.position())));
+    }
 
-                // If the signs don't match, it crossed the plane
-                old_distance_to_plane.is_sign_positive() != new_distance_to_plane.is_sign_positive()
-            })
-            .cloned()
+    #[test]
+    fn oil_is_lighter_than_water() {
+        let simulation = simulation();
+        assert!(simulation.mass(Phase::Oil) < simulation.mass(Phase::Water));
+        assert_eq!(
+            simulation.mass(Phase::Water),
+            simulation.config.particle_mass as Real
+        );
+    }
+
+    #[test]
+    fn viscosity_limits_timestep() {
+        let mut simulation = simulation();
+        simulation.config.kinematic_viscosity = 100.0;
+        assert_eq!(simulation.timestep().limit, TimestepLimit::Viscosity);
+        assert!(simulation.timestep().dt < simulation.config.dt);
     }
 
-    fn pressure(&self, density: f32) -> f32 {
-        self.config.pressure_siffness * (density - self.config.reference_density)
+    #[test]
+    fn synced_stiffness_and_viscosity_limit_timestep() {
+        let mut simulation = simulation();
+        let mut config = simulation.get_config().clone();
+        let dt = simulation.timestep().dt;
+
+        // Stiff enough that sound crosses a kernel faster than the configured dt.
+        config.pressure_siffness = 1e4;
+        simulation.sync_config(&config);
+        let stiff_dt = simulation.timestep().dt;
+        assert!(stiff_dt < dt, "{} {}", stiff_dt, dt);
+        assert_eq!(simulation.timestep().limit, TimestepLimit::Velocity);
+
+        config.kinematic_viscosity = 10.0;
+        config.reference_density *= 2.0;
+        simulation.sync_config(&config);
+        assert!(simulation.timestep().dt < stiff_dt);
+        assert_eq!(simulation.timestep().limit, TimestepLimit::Viscosity);
+        assert_eq!(
+            config.reference_density as Real,
+            simulation.reference_density(Phase::Water)
+        );
     }
 }
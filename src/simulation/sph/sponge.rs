@@ -0,0 +1,161 @@
+use super::config::Config;
+use super::Particle;
+use crate::math::Vec3A;
+use crate::simulation::consts;
+use crate::simulation::trigger::{Trigger, TriggerAction, TriggerShape};
+
+use cgmath::Vector3;
+use rand::Rng;
+
+/// A porous region which slows the fluid passing through it, and absorbs particles up to its
+/// capacity. Once saturated, it drips the particles it holds from its underside.
+/// Particles enter the sponge through a trigger volume, since the sponge doesn't collide with them.
+pub struct Sponge {
+    trigger: Trigger,
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+    /// The absorbed particles, which are removed from the simulation until they drip.
+    held: Vec<Particle>,
+    /// The fractional number of particles which may be absorbed or dripped, accumulated
+    /// until it reaches a whole particle.
+    absorb_budget: f32,
+    drip_budget: f32,
+}
+
+impl Sponge {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Sponge {
+        Sponge {
+            trigger: Trigger::new(TriggerShape::Aabb { min, max }, TriggerAction::Count),
+            min,
+            max,
+            held: Vec::new(),
+            absorb_budget: 0.0,
+            drip_budget: 0.0,
+        }
+    }
+
+    pub fn min(&self) -> Vector3<f32> {
+        self.min
+    }
+
+    pub fn max(&self) -> Vector3<f32> {
+        self.max
+    }
+
+    /// True if the particle was inside the sponge as of the last update.
+    pub fn contains(&self, particle: &Particle) -> bool {
+        self.trigger.contains(particle.id as usize)
+    }
+
+    /// The fraction of the sponge's capacity it holds, in [0, 1].
+    pub fn saturation(&self, config: &Config) -> f32 {
+        if config.sponge_capacity == 0 {
+            return 1.0;
+        }
+        (self.held.len() as f32 / config.sponge_capacity as f32).min(1.0)
+    }
+
+    /// Absorbs particles inside the sponge at the configured rate, and drips held particles
+    /// if the sponge is saturated, for a step of dt seconds.
+    /// Returns the particles which remain in, or dripped back into, the simulation.
+    pub fn update(&mut self, particles: Vec<Particle>, config: &Config, dt: f32) -> Vec<Particle> {
+        self.trigger.update(
+            particles
+                .iter()
+                .map(|particle| (particle.id as usize, particle.position())),
+        );
+
+        // The budget is capped so that the sponge can't bank absorption while it's dry.
+        let absorb_step = config.sponge_absorption_rate * dt;
+        self.absorb_budget = (self.absorb_budget + absorb_step).min(absorb_step.max(1.0));
+        let mut remaining = Vec::with_capacity(particles.len());
+        for particle in particles {
+            if self.absorb_budget >= 1.0
+                && self.held.len() < config.sponge_capacity
+                && self.contains(&particle)
+            {
+                self.absorb_budget -= 1.0;
+                self.held.push(particle);
+            } else {
+                remaining.push(particle);
+            }
+        }
+
+        if self.held.len() < config.sponge_capacity {
+            self.drip_budget = 0.0;
+            return remaining;
+        }
+        self.drip_budget += config.sponge_drip_rate * dt;
+        let mut rng = rand::thread_rng();
+        while self.drip_budget >= 1.0 {
+            let Some(mut particle) = self.held.pop() else {
+                break;
+            };
+            self.drip_budget -= 1.0;
+            particle.position = Vector3::new(
+                rng.gen_range(self.min.x..=self.max.x),
+                self.min.y - consts::EPSILON,
+                rng.gen_range(self.min.z..=self.max.z),
+            )
+            .into();
+            particle.velocity = Vec3A::ZERO;
+            remaining.push(particle);
+        }
+        remaining
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::sph::Phase;
+
+    fn particles_inside(count: u32) -> Vec<Particle> {
+        (0..count)
+            .map(|id| {
+                Particle::new(
+                    id,
+                    Phase::Water,
+                    Vector3::new(0.0, 0.0, 0.0),
+                    Vector3::new(0.0, 0.0, 0.0),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn absorbs_at_rate_up_to_capacity() {
+        let config = Config {
+            sponge_capacity: 3,
+            sponge_absorption_rate: 2.0,
+            sponge_drip_rate: 0.0,
+            ..Default::default()
+        };
+        let mut sponge = Sponge::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let particles = particles_inside(5);
+        let particles = sponge.update(particles, &config, 0.5);
+        assert_eq!(particles.len(), 4);
+        let particles = sponge.update(particles, &config, 0.5);
+        assert_eq!(particles.len(), 3);
+        let particles = sponge.update(particles, &config, 1.0);
+        assert_eq!(particles.len(), 2);
+        assert_eq!(sponge.saturation(&config), 1.0);
+    }
+
+    #[test]
+    fn drips_when_saturated() {
+        let config = Config {
+            sponge_capacity: 2,
+            sponge_absorption_rate: 10.0,
+            sponge_drip_rate: 1.0,
+            ..Default::default()
+        };
+        let mut sponge = Sponge::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let particles = sponge.update(particles_inside(2), &config, 0.2);
+        assert!(particles.is_empty());
+        let particles = sponge.update(particles, &config, 1.0);
+        assert_eq!(particles.len(), 1);
+        // Dripped particles leave from beneath the sponge, so they aren't reabsorbed.
+        assert!(particles[0].position().y < -1.0);
+    }
+}
@@ -1,8 +1,9 @@
 /// Cloth is simulated as a spring-mass-damper mesh.
 // TODO we should have a boolean to say, dont' make any torsional springs for struts for this mesh
 use super::springy_mesh::{SpringConfig, SpringyMesh, StrutKey};
+use crate::simulation::quantity::{self, Damping, Length, Mass, Quantity, Stiffness};
 
-use cgmath::Vector3;
+use cgmath::{Vector2, Vector3};
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
 
@@ -20,17 +21,18 @@ impl Cloth {
     pub fn new(
         rows: usize,
         cols: usize,
-        spacing: f32,
+        spacing: Length,
         position: Vector3<f32>,
-        point_mass: f32,
-        tensile_stiffness: f32,
-        tensile_damping: f32,
-        shear_stiffness: f32,
-        shear_damping: f32,
-        binding_spring_stiffness: f32,
-        binding_spring_damping: f32,
+        point_mass: Mass,
+        tensile_stiffness: Stiffness,
+        tensile_damping: Damping,
+        shear_stiffness: Stiffness,
+        shear_damping: Damping,
+        binding_spring_stiffness: Stiffness,
+        binding_spring_damping: Damping,
         pinned_vertices: Vec<usize>,
     ) -> Cloth {
+        let spacing = spacing.value();
         let mut vertex_positions = Vec::new();
         for row in 0..rows {
             for col in 0..cols {
@@ -86,7 +88,7 @@ impl Cloth {
         let mut mesh = SpringyMesh::new(
             vertex_positions,
             indices,
-            point_mass * rows as f32 * cols as f32,
+            quantity::kilograms(point_mass.value() * (rows * cols) as f32),
             tensile_stiffness,
             tensile_damping,
             None,
@@ -96,6 +98,18 @@ impl Cloth {
             mesh.add_pin(*pin_index)
         }
 
+        // UVs span the cloth from (0, 0) at the top left to (1, 1) at the bottom right.
+        let mut uvs = Vec::with_capacity(rows * cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                uvs.push(Vector2::<f32>::new(
+                    col as f32 / (cols - 1) as f32,
+                    1.0 - row as f32 / (rows - 1) as f32,
+                ));
+            }
+        }
+        mesh.set_uvs(uvs);
+
         // Binding springs resist bending of cloth as a whole.
         let mut binding_spring_index_pairs = Vec::new();
         for row in 0..(rows - 2) {
@@ -1,20 +1,52 @@
-use super::super::state::Integration;
+use super::super::collision_filter::CollisionFilter;
+use super::super::fan::FanConfig;
+use super::super::state::{AdaptiveTimestep, Integration};
+use super::super::units::GravityPreset;
 use std::time::Duration;
 
-use cgmath::{Vector3, Zero};
+use cgmath::{Vector2, Vector3, Zero};
 
 const LIFT_COEFFICIENT_DEFAULT: f32 = 1.0;
 const DRAG_COEFFICIENT_DEFAULT: f32 = 1.0;
+/// The density of the air the lift and drag coefficients are for, in kg/m³.
+pub const AIR_DENSITY: f32 = 1.225;
 
+#[derive(Clone, PartialEq)]
 pub struct Config {
     pub integration: Integration,
     pub dt: f32, // Seconds as f32
+    /// Adapts dt to the error estimates of the integration within the bounds, if set, and if the integration
+    /// estimates its error.
+    pub adaptive_dt: Option<AdaptiveTimestep>,
     pub gravity: Vector3<f32>,
     pub wind: Vector3<f32>,
+    /// The fan blowing on the meshes, if any.
+    pub fan: Option<FanConfig>,
     pub lift_coefficient: f32,
     pub drag_coefficient: f32,
     pub coefficient_of_restitution: f32,
     pub coefficient_of_friction: f32,
+    /// The collision groups the meshes collide with.
+    pub collision_mask: u32,
+    /// Drops of rain spawned per second; no rain falls if zero.
+    pub rain_rate: f32,
+    pub drop_mass: f32,
+    /// The center of the horizontal rectangle drops are spawned in.
+    pub rain_center: Vector3<f32>,
+    /// The half-widths of the rectangle drops are spawned in, along X and Z.
+    pub rain_extent: Vector2<f32>,
+    /// The initial velocity of each drop. Slanted so that the rain hits hanging cloth.
+    pub rain_velocity: Vector3<f32>,
+    /// The height of the surface of the still fluid the meshes are submerged in beneath it,
+    /// if any.
+    pub fluid_surface_height: Option<f32>,
+    /// The density of the fluid, in kg/m³. Lift and drag in the fluid are scaled from the
+    /// air's by the ratio of their densities.
+    pub fluid_density: f32,
+    /// The thickness of the meshes' faces, which determines how much fluid they displace.
+    pub face_thickness: f32,
+    /// The strain, as a fraction of their rest lengths, past which struts tear between steps, if set.
+    pub tear_strain: Option<f32>,
 }
 
 impl Default for Config {
@@ -22,12 +54,32 @@ impl Default for Config {
         Self {
             integration: Integration::Rk4,
             dt: Duration::from_millis(1).as_secs_f32(),
-            gravity: Vector3::<f32>::unit_y() * -10.0,
+            adaptive_dt: None,
+            gravity: GravityPreset::Earth.vector(),
             wind: Vector3::<f32>::zero(),
+            fan: None,
             lift_coefficient: LIFT_COEFFICIENT_DEFAULT,
             drag_coefficient: DRAG_COEFFICIENT_DEFAULT,
             coefficient_of_restitution: 0.95,
             coefficient_of_friction: 0.3,
+            collision_mask: CollisionFilter::ALL,
+            rain_rate: 0.0,
+            drop_mass: 0.5,
+            rain_center: Vector3::new(0.0, 2.5, 1.5),
+            rain_extent: Vector2::new(1.0, 0.5),
+            rain_velocity: Vector3::new(0.0, -3.0, -3.0),
+            fluid_surface_height: None,
+            fluid_density: 1000.0,
+            face_thickness: 0.05,
+            tear_strain: None,
         }
     }
 }
+
+impl Config {
+    /// The bounds dt adapts within, if it's adapting.
+    pub fn get_adaptive_dt(&self) -> Option<AdaptiveTimestep> {
+        self.adaptive_dt
+            .filter(|_| self.integration.estimates_error())
+    }
+}
@@ -0,0 +1,301 @@
+/// Implicit (backward) Euler for springy meshes, after Baraff and Witkin's "Large Steps in Cloth Simulation".
+/// The springs' forces are linearized about the current state, and the change in the points' velocities over
+/// the step solved for with conjugate gradients, so that stiff springs stay stable over large timesteps.
+use cgmath::{InnerSpace, Matrix3, SquareMatrix, Vector3, Zero};
+
+use crate::simulation::solver::{self, SolverBudget, SolverStats};
+
+/// The conjugate gradient iterations stop once the residual is this fraction of the right hand side.
+const RELATIVE_TOLERANCE: f32 = 1e-5;
+const MAX_ITERATIONS: usize = 200;
+
+/// The derivatives of a spring's force on its second point with respect to the other point's position and
+/// velocity. The force on the first point is opposite, and its derivatives with respect to its own position and
+/// velocity are the negatives of these.
+#[derive(Debug, Copy, Clone)]
+pub struct SpringJacobian {
+    pub vertex_indices: (usize, usize),
+    /// With respect to position.
+    pub stiffness: Matrix3<f32>,
+    /// With respect to velocity.
+    pub damping: Matrix3<f32>,
+}
+
+impl SpringJacobian {
+    /// The Jacobian of a spring of the stiffness and damping, and rest length, between points at the positions.
+    /// The stiffness across the spring is dropped while it's compressed, as it would make the system indefinite,
+    /// so compressed springs are only stiff along their length.
+    pub fn new(
+        vertex_indices: (usize, usize),
+        stiffness: f32,
+        damping: f32,
+        length: f32,
+        position_0: Vector3<f32>,
+        position_1: Vector3<f32>,
+    ) -> SpringJacobian {
+        let offset = position_1 - position_0;
+        let distance = offset.magnitude();
+        if distance < f32::EPSILON {
+            return SpringJacobian {
+                vertex_indices,
+                stiffness: Matrix3::zero(),
+                damping: Matrix3::zero(),
+            };
+        }
+        let u = offset / distance;
+        let along = outer(u, u);
+        let across = Matrix3::identity() - along;
+        SpringJacobian {
+            vertex_indices,
+            stiffness: (along + across * (1.0 - length / distance).max(0.0)) * stiffness,
+            damping: along * damping,
+        }
+    }
+}
+
+fn outer(a: Vector3<f32>, b: Vector3<f32>) -> Matrix3<f32> {
+    Matrix3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+/// A point of the system: its mass, its velocity and the total force on it at the start of the step, and
+/// whether it's pinned in place.
+#[derive(Debug, Copy, Clone)]
+struct ImplicitPoint {
+    mass: f32,
+    velocity: Vector3<f32>,
+    force: Vector3<f32>,
+    pinned: bool,
+}
+
+/// Solves (M - h ∂f/∂v - h² ∂f/∂x) Δv = h (f + h ∂f/∂x v) for the change in velocity Δv of each point over the
+/// step h, where only the springs' forces are differentiated; the rest of the forces are taken explicitly.
+/// The points and springs are added afresh each step, and their buffers are reused so that stepping doesn't
+/// allocate once they've grown.
+pub struct ImplicitEuler {
+    points: Vec<ImplicitPoint>,
+    springs: Vec<SpringJacobian>,
+    delta_velocities: Vec<Vector3<f32>>,
+    residual: Vec<Vector3<f32>>,
+    direction: Vec<Vector3<f32>>,
+    product: Vec<Vector3<f32>>,
+    stats: SolverStats,
+}
+
+impl ImplicitEuler {
+    pub fn new() -> ImplicitEuler {
+        ImplicitEuler {
+            points: Vec::new(),
+            springs: Vec::new(),
+            delta_velocities: Vec::new(),
+            residual: Vec::new(),
+            direction: Vec::new(),
+            product: Vec::new(),
+            stats: SolverStats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.springs.clear();
+    }
+
+    /// Adds a point with the mass, and its velocity and the total force on it, including its springs', at the
+    /// start of the step. Pinned points don't move.
+    pub fn add_point(
+        &mut self,
+        mass: f32,
+        velocity: Vector3<f32>,
+        force: Vector3<f32>,
+        pinned: bool,
+    ) {
+        self.points.push(ImplicitPoint {
+            mass,
+            velocity,
+            force,
+            pinned,
+        });
+    }
+
+    pub fn add_spring(&mut self, spring: SpringJacobian) {
+        self.springs.push(spring);
+    }
+
+    /// Solves for the change in each point's velocity over the step, in the order they were added.
+    /// The residuals are relative to the right hand side.
+    pub fn solve(&mut self, dt: f32) -> &[Vector3<f32>] {
+        let n = self.points.len();
+        for buffer in [
+            &mut self.delta_velocities,
+            &mut self.residual,
+            &mut self.direction,
+            &mut self.product,
+        ] {
+            buffer.clear();
+            buffer.resize(n, Vector3::zero());
+        }
+
+        // The right hand side, h (f + h ∂f/∂x v), is the residual of the initial guess of no change.
+        for (residual, point) in self.residual.iter_mut().zip(&self.points) {
+            *residual = dt * point.force;
+        }
+        for spring in self.springs.iter() {
+            let (i, j) = spring.vertex_indices;
+            let force =
+                dt * dt * (spring.stiffness * (self.points[j].velocity - self.points[i].velocity));
+            self.residual[i] += force;
+            self.residual[j] -= force;
+        }
+        project(&self.points, &mut self.residual);
+        let rhs_norm = norm2(&self.residual).sqrt();
+        if rhs_norm == 0.0 {
+            self.stats = SolverStats::default();
+            return &self.delta_velocities;
+        }
+        self.direction.clone_from(&self.residual);
+        let mut residual_norm2 = norm2(&self.residual);
+
+        let budget = SolverBudget {
+            max_iterations: MAX_ITERATIONS,
+            tolerance: RELATIVE_TOLERANCE,
+            time_budget: None,
+        };
+        let (points, springs) = (&self.points, &self.springs);
+        let (delta_velocities, residual, direction, product) = (
+            &mut self.delta_velocities,
+            &mut self.residual,
+            &mut self.direction,
+            &mut self.product,
+        );
+        self.stats = solver::iterate(&budget, || {
+            multiply(points, springs, dt, direction, product);
+            let curvature = dot(direction, product);
+            if curvature <= 0.0 {
+                return 0.0;
+            }
+            let alpha = residual_norm2 / curvature;
+            for ((delta_velocity, residual), (direction, product)) in delta_velocities
+                .iter_mut()
+                .zip(residual.iter_mut())
+                .zip(direction.iter().zip(product.iter()))
+            {
+                *delta_velocity += alpha * direction;
+                *residual -= alpha * product;
+            }
+            let new_residual_norm2 = norm2(residual);
+            let beta = new_residual_norm2 / residual_norm2;
+            residual_norm2 = new_residual_norm2;
+            for (direction, residual) in direction.iter_mut().zip(residual.iter()) {
+                *direction = residual + beta * *direction;
+            }
+            residual_norm2.sqrt() / rhs_norm
+        });
+        &self.delta_velocities
+    }
+
+    /// How the last solve converged.
+    pub fn get_stats(&self) -> &SolverStats {
+        &self.stats
+    }
+}
+
+impl Default for ImplicitEuler {
+    fn default() -> Self {
+        ImplicitEuler::new()
+    }
+}
+
+/// The product of the system's matrix, M - h ∂f/∂v - h² ∂f/∂x, with the vector, into the product.
+fn multiply(
+    points: &[ImplicitPoint],
+    springs: &[SpringJacobian],
+    dt: f32,
+    vector: &[Vector3<f32>],
+    product: &mut [Vector3<f32>],
+) {
+    for ((product, point), vector) in product.iter_mut().zip(points).zip(vector) {
+        *product = point.mass * vector;
+    }
+    for spring in springs.iter() {
+        let (i, j) = spring.vertex_indices;
+        let difference = vector[j] - vector[i];
+        let term = (spring.damping * dt + spring.stiffness * (dt * dt)) * difference;
+        product[i] -= term;
+        product[j] += term;
+    }
+    project(points, product);
+}
+
+/// Zeroes the pinned points' components, so that they don't move.
+fn project(points: &[ImplicitPoint], vector: &mut [Vector3<f32>]) {
+    for (value, point) in vector.iter_mut().zip(points) {
+        if point.pinned {
+            *value = Vector3::zero();
+        }
+    }
+}
+
+fn dot(a: &[Vector3<f32>], b: &[Vector3<f32>]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a.dot(*b)).sum()
+}
+
+fn norm2(a: &[Vector3<f32>]) -> f32 {
+    dot(a, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::assert_relative_eq;
+
+    #[test]
+    fn free_points_accelerate_explicitly() {
+        let mut implicit = ImplicitEuler::new();
+        implicit.add_point(2.0, Vector3::zero(), Vector3::new(0.0, -4.0, 0.0), false);
+        implicit.add_point(1.0, Vector3::zero(), Vector3::new(0.0, -4.0, 0.0), true);
+        let delta_velocities = implicit.solve(0.5);
+        assert_relative_eq!(delta_velocities[0], Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(delta_velocities[1], Vector3::zero());
+    }
+
+    /// Steps a point hung from a pinned one by a spring of the stiffness, returning how far from its rest position
+    /// it ends up after the steps.
+    fn hang(stiffness: f32, dt: f32, steps: usize, implicit_euler: bool) -> f32 {
+        let (mass, gravity) = (1.0, Vector3::new(0.0, -9.8, 0.0));
+        let anchor = Vector3::zero();
+        let (mut position, mut velocity) = (Vector3::new(0.0, -1.2, 0.0), Vector3::zero());
+        let mut implicit = ImplicitEuler::new();
+        for _ in 0..steps {
+            let offset = position - anchor;
+            let force = -stiffness * (offset.magnitude() - 1.0) * offset.normalize() + gravity;
+            let delta_velocity = if implicit_euler {
+                implicit.clear();
+                implicit.add_point(0.0, Vector3::zero(), Vector3::zero(), true);
+                implicit.add_point(mass, velocity, force, false);
+                implicit.add_spring(SpringJacobian::new(
+                    (0, 1),
+                    stiffness,
+                    0.0,
+                    1.0,
+                    anchor,
+                    position,
+                ));
+                implicit.solve(dt)[1]
+            } else {
+                dt * force / mass
+            };
+            velocity += delta_velocity;
+            position += dt * velocity;
+        }
+        let rest = Vector3::new(0.0, -1.0 - gravity.magnitude() * mass / stiffness, 0.0);
+        (position - rest).magnitude()
+    }
+
+    #[test]
+    fn stiff_springs_are_stable_at_large_steps() {
+        let (stiffness, dt, steps) = (1e6, 0.01, 10);
+        assert!(hang(stiffness, dt, steps, false) > 1e3);
+        // Backward Euler damps the spring's oscillation away, leaving it at rest.
+        assert!(hang(stiffness, dt, steps, true) < 1e-3);
+    }
+}
@@ -1,4 +1,7 @@
 pub mod cloth;
 pub mod config;
+pub mod implicit;
+pub mod rain;
+pub mod scenario;
 pub mod simulation;
 pub mod springy_mesh;
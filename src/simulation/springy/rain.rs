@@ -0,0 +1,189 @@
+use super::config::Config;
+use super::springy_mesh::SpringyMesh;
+
+use cgmath::{InnerSpace, Vector3};
+use rand::Rng;
+
+/// The most drops which may fall at once; drops aren't spawned past this.
+pub const MAX_DROPS: usize = 4000;
+/// Drops which haven't hit a mesh within their lifetime are removed.
+const DROP_LIFETIME: f32 = 3.0;
+
+/// A ballistic drop of rain.
+#[derive(Clone, Copy)]
+pub struct Drop {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    age: f32,
+}
+
+/// Rain falling from a rectangle above the scene onto the springy meshes.
+/// The coupling is one-way: a drop which hits a mesh pushes the face it hits and splashes,
+/// but the meshes don't otherwise affect the drops.
+pub struct Rain {
+    drops: Vec<Drop>,
+    /// The fractional number of drops to spawn, accumulated until it reaches a whole drop.
+    spawn_budget: f32,
+}
+
+impl Rain {
+    pub fn new() -> Rain {
+        Rain {
+            drops: Vec::new(),
+            spawn_budget: 0.0,
+        }
+    }
+
+    pub fn get_drops(&self) -> &Vec<Drop> {
+        &self.drops
+    }
+
+    /// Spawns, moves, and collides the drops with the meshes for a step of dt seconds.
+    /// Each drop which hits a face gives the face the momentum along its normal that the drop
+    /// had relative to it, spread to the face's points by their barycentric weights.
+    pub fn step(&mut self, meshes: &mut [SpringyMesh], config: &Config, dt: f32) {
+        self.spawn(config, dt);
+
+        // Most drops are nowhere near a mesh, so they're culled by its bounds before
+        // testing them against its faces.
+        let bounds = meshes
+            .iter()
+            .map(|mesh| mesh.get_bounds())
+            .collect::<Vec<_>>();
+        self.drops.retain_mut(|drop| {
+            let start = drop.position;
+            drop.velocity += config.gravity * dt;
+            drop.position += drop.velocity * dt;
+            drop.age += dt;
+            for (mesh, (min, max)) in meshes.iter_mut().zip(bounds.iter()) {
+                if !segment_overlaps_bounds(start, drop.position, *min, *max) {
+                    continue;
+                }
+                if let Some(hit) = mesh.get_segment_hit(start, drop.position) {
+                    let normal = mesh.get_hit_normal(&hit);
+                    let relative_velocity = drop.velocity - mesh.get_hit_velocity(&hit);
+                    let impulse = config.drop_mass * relative_velocity.dot(normal) * normal;
+                    mesh.apply_impulse(&hit, impulse);
+                    return false;
+                }
+            }
+            drop.age < DROP_LIFETIME
+        });
+    }
+
+    fn spawn(&mut self, config: &Config, dt: f32) {
+        if config.rain_rate <= 0.0 {
+            self.spawn_budget = 0.0;
+            return;
+        }
+        self.spawn_budget += config.rain_rate * dt;
+        let mut rng = rand::thread_rng();
+        while self.spawn_budget >= 1.0 && self.drops.len() < MAX_DROPS {
+            self.spawn_budget -= 1.0;
+            let offset = Vector3::new(
+                rng.gen_range(-config.rain_extent.x..=config.rain_extent.x),
+                0.0,
+                rng.gen_range(-config.rain_extent.y..=config.rain_extent.y),
+            );
+            self.drops.push(Drop {
+                position: config.rain_center + offset,
+                velocity: config.rain_velocity,
+                age: 0.0,
+            });
+        }
+        // Drops which couldn't be spawned are dropped, rather than all spawning at once later.
+        self.spawn_budget = self.spawn_budget.min(1.0);
+    }
+}
+
+impl Default for Rain {
+    fn default() -> Self {
+        Rain::new()
+    }
+}
+
+/// True if the axis-aligned boxes bounding the segment and the bounds overlap.
+fn segment_overlaps_bounds(
+    start: Vector3<f32>,
+    end: Vector3<f32>,
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+) -> bool {
+    start.x.min(end.x) <= max.x
+        && start.x.max(end.x) >= min.x
+        && start.y.min(end.y) <= max.y
+        && start.y.max(end.y) >= min.y
+        && start.z.min(end.z) <= max.z
+        && start.z.max(end.z) >= min.z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::quantity::{kilograms, newton_seconds_per_meter, newtons_per_meter};
+    use crate::simulation::state::Integration;
+    use cgmath::{assert_relative_eq, Vector2, Zero};
+
+    /// A unit square in the XZ plane, centered at the origin.
+    fn get_tarp() -> SpringyMesh {
+        SpringyMesh::new(
+            vec![
+                Vector3::new(-0.5, 0.0, -0.5),
+                Vector3::new(0.5, 0.0, -0.5),
+                Vector3::new(0.5, 0.0, 0.5),
+                Vector3::new(-0.5, 0.0, 0.5),
+            ],
+            vec![0, 2, 1, 0, 3, 2],
+            kilograms(4.0),
+            newtons_per_meter(1.0),
+            newton_seconds_per_meter(1.0),
+            None,
+            &None,
+        )
+    }
+
+    #[test]
+    fn drops_push_the_mesh_they_hit() {
+        let config = Config {
+            integration: Integration::Euler,
+            gravity: Vector3::zero(),
+            rain_rate: 10.0,
+            drop_mass: 0.5,
+            rain_center: Vector3::new(0.0, 1.0, 0.0),
+            rain_extent: Vector2::new(0.25, 0.25),
+            rain_velocity: Vector3::new(0.0, -2.0, 0.0),
+            ..Default::default()
+        };
+        let mut meshes = vec![get_tarp()];
+        let mut rain = Rain::new();
+        rain.step(&mut meshes, &config, 0.1);
+        assert_eq!(rain.get_drops().len(), 1);
+
+        let stopped = Config {
+            rain_rate: 0.0,
+            ..config
+        };
+        for _ in 0..10 {
+            rain.step(&mut meshes, &stopped, 0.1);
+        }
+        // The drop splashed, giving the tarp all of its downward momentum.
+        assert!(rain.get_drops().is_empty());
+        let momentum = meshes[0]
+            .get_points()
+            .iter()
+            .map(|p| p.get_velocity() * p.get_mass())
+            .fold(Vector3::zero(), |sum, m| sum + m);
+        assert_relative_eq!(momentum, Vector3::new(0.0, -1.0, 0.0), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn spawning_stops_at_max_drops() {
+        let config = Config {
+            rain_rate: (MAX_DROPS * 2) as f32,
+            ..Default::default()
+        };
+        let mut rain = Rain::new();
+        rain.step(&mut [], &config, 1.0);
+        assert_eq!(rain.get_drops().len(), MAX_DROPS);
+    }
+}
@@ -0,0 +1,848 @@
+/// Cloth scenarios: a cloth, the pins it hangs from, the obstacles it drapes over, the wind, and a rigid box
+/// or ball dropped onto it, declared as key = value lines like any reflected config. The presets are scenario files in
+/// res/scenarios/cloth, which can be copied and modified, and opened with the cloth demo's --scenario option.
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Quaternion, Rotation3, Vector3, Zero};
+
+use super::{cloth::Cloth, config::Config, simulation::Simulation, springy_mesh::SpringyMesh};
+use crate::simulation::collidable_mesh::{CollidableMesh, Sidedness};
+use crate::simulation::configurable::Configurable;
+use crate::simulation::energy::EnergyBreakdown;
+use crate::simulation::geometry;
+use crate::simulation::quantity;
+use crate::simulation::reflect::{Field, Reflect};
+use crate::simulation::rigidbody::{
+    contact::{self, Cuboid},
+    rigidbody::RigidBody,
+    simulation::{RigidBodyHandle, Simulation as RigidBodySimulation},
+};
+use crate::simulation::units;
+
+/// The floor extends this far either way along x and z.
+const FLOOR_HALF_WIDTH: f32 = 2.0;
+/// Points are pushed this far out of the dropped body, so the cloth doesn't show through its sides.
+const BODY_MARGIN: f32 = 0.01;
+/// The dropped body's bounciness off the floor and obstacles. It doesn't bounce off the cloth, which
+/// springs back by itself.
+const BODY_RESTITUTION: f32 = 0.3;
+
+/// Scenarios saved from the cloth demo which weren't opened from a file are saved to this file.
+pub const SAVED_SCENARIO_FILE: &str = "cloth_scenario.txt";
+
+/// The scenarios the cloth demo ships with.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Preset {
+    /// Hung from the corners and middle of its top edge.
+    Hanging,
+    /// Laid over a cube, draping down its sides.
+    Tablecloth,
+    /// Pinned along one side to a pole, flapping in the wind.
+    Flag,
+    /// Pinned along its top edge, blown by the wind.
+    Curtain,
+    /// Pinned along every edge, bouncing a dropped ball.
+    Trampoline,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 5] = [
+        Preset::Hanging,
+        Preset::Tablecloth,
+        Preset::Flag,
+        Preset::Curtain,
+        Preset::Trampoline,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Hanging => "Hanging",
+            Preset::Tablecloth => "Tablecloth",
+            Preset::Flag => "Flag",
+            Preset::Curtain => "Curtain",
+            Preset::Trampoline => "Trampoline",
+        }
+    }
+
+    /// The preset's scenario file.
+    pub fn contents(&self) -> &'static str {
+        match self {
+            Preset::Hanging => include_str!("../../../res/scenarios/cloth/hanging.txt"),
+            Preset::Tablecloth => include_str!("../../../res/scenarios/cloth/tablecloth.txt"),
+            Preset::Flag => include_str!("../../../res/scenarios/cloth/flag.txt"),
+            Preset::Curtain => include_str!("../../../res/scenarios/cloth/curtain.txt"),
+            Preset::Trampoline => include_str!("../../../res/scenarios/cloth/trampoline.txt"),
+        }
+    }
+
+    pub fn get_scenario(&self) -> Scenario {
+        Scenario::from_key_values(self.contents())
+    }
+}
+
+/// The objects in a scenario which can be arranged with gizmos.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Placeable {
+    Cloth,
+    Floor,
+    Cube,
+    Body,
+}
+
+impl Placeable {
+    pub const ALL: [Placeable; 4] = [
+        Placeable::Cloth,
+        Placeable::Floor,
+        Placeable::Cube,
+        Placeable::Body,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Placeable::Cloth => "Cloth",
+            Placeable::Floor => "Floor",
+            Placeable::Cube => "Cube",
+            Placeable::Body => "Dropped Body",
+        }
+    }
+}
+
+/// Where a placeable is, and how it may be arranged.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Placement {
+    pub center: Vector3<f32>,
+    /// The radius of a sphere about the center which bounds the placeable.
+    pub radius: f32,
+    /// The world axes it may be moved along, and rotated about.
+    pub translation_axes: [bool; 3],
+    pub rotation_axes: [bool; 3],
+    pub scalable: bool,
+}
+
+/// A cloth scenario. Spring constants are for a strut of the cloth's spacing, as for Cloth::new().
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// The number of points along the cloth's height, and its width.
+    pub rows: u32,
+    pub cols: u32,
+    /// The distance between neighboring points, in m.
+    pub spacing: f32,
+    /// The mass of each point, in kg.
+    pub point_mass: f32,
+    pub tensile_stiffness: f32,
+    pub tensile_damping: f32,
+    pub shear_stiffness: f32,
+    pub shear_damping: f32,
+    /// The springs between every other point, resisting the cloth bending.
+    pub bending_stiffness: f32,
+    pub bending_damping: f32,
+    /// The center of the cloth.
+    pub position: Vector3<f32>,
+    /// Lays the cloth flat, its top edge away from the camera, rather than hanging it upright.
+    pub horizontal: bool,
+    /// Pins the corners and the middle of the top edge.
+    pub pin_top_points: bool,
+    pub pin_top: bool,
+    pub pin_bottom: bool,
+    pub pin_left: bool,
+    pub pin_right: bool,
+    pub floor_height: f32,
+    /// Whether there's a cube for the cloth to drape over.
+    pub cube: bool,
+    pub cube_center: Vector3<f32>,
+    /// The cube's side length, in m.
+    pub cube_size: f32,
+    /// The cube's rotation about the vertical axis, in radians.
+    pub cube_yaw: f32,
+    /// Makes the floor and cube one-sided, pushing points under the floor or inside the cube out of them.
+    pub one_sided_obstacles: bool,
+    pub wind: Vector3<f32>,
+    /// Whether a rigid body is dropped onto the cloth.
+    pub body: bool,
+    /// Drops a ball rather than a box.
+    pub body_sphere: bool,
+    pub body_position: Vector3<f32>,
+    /// The box's side length, or the ball's diameter, in m.
+    pub body_size: f32,
+    pub body_mass: f32,
+}
+
+impl Scenario {
+    /// The scenario in the key = value lines, with the hanging cloth's settings for any that are missing.
+    pub fn from_key_values(contents: &str) -> Scenario {
+        let mut scenario = Scenario::default();
+        scenario.parse_key_values(contents);
+        scenario
+    }
+
+    /// The scenario as the contents of a scenario file.
+    pub fn to_file_contents(&self) -> String {
+        format!(
+            "# A cloth scenario, saved from the cloth demo. Open it with feriphys cloth --scenario <FILE>.\n\n{}",
+            self.to_key_values()
+        )
+    }
+
+    /// The placeables the scenario has, e.g. the cube only if there is one.
+    pub fn get_placeables(&self) -> Vec<Placeable> {
+        Placeable::ALL
+            .into_iter()
+            .filter(|placeable| match placeable {
+                Placeable::Cloth | Placeable::Floor => true,
+                Placeable::Cube => self.cube,
+                Placeable::Body => self.body,
+            })
+            .collect()
+    }
+
+    pub fn get_placement(&self, placeable: Placeable) -> Placement {
+        let all = [true; 3];
+        let none = [false; 3];
+        let cube_radius = |size: f32| size * 3.0_f32.sqrt() / 2.0;
+        match placeable {
+            Placeable::Cloth => Placement {
+                center: self.position,
+                radius: self.spacing * (self.rows.max(2) - 1).max(self.cols.max(2) - 1) as f32
+                    / std::f32::consts::SQRT_2,
+                translation_axes: all,
+                rotation_axes: none,
+                scalable: true,
+            },
+            Placeable::Floor => Placement {
+                center: Vector3::new(0.0, self.floor_height, 0.0),
+                radius: FLOOR_HALF_WIDTH * std::f32::consts::SQRT_2,
+                translation_axes: [false, true, false],
+                rotation_axes: none,
+                scalable: false,
+            },
+            Placeable::Cube => Placement {
+                center: self.cube_center,
+                radius: cube_radius(self.cube_size),
+                translation_axes: all,
+                rotation_axes: [false, true, false],
+                scalable: true,
+            },
+            Placeable::Body => Placement {
+                center: self.body_position,
+                radius: cube_radius(self.body_size),
+                translation_axes: all,
+                rotation_axes: none,
+                scalable: true,
+            },
+        }
+    }
+
+    /// The placeable whose bounding sphere the ray passes through, the smallest if it passes through several,
+    /// so that objects resting on or inside larger ones can still be picked.
+    pub fn pick(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<Placeable> {
+        self.get_placeables()
+            .into_iter()
+            .map(|placeable| (placeable, self.get_placement(placeable)))
+            .filter(|(_, placement)| {
+                let offset = placement.center - origin;
+                let along = offset.dot(direction);
+                offset.magnitude2() <= placement.radius * placement.radius
+                    || (along > 0.0
+                        && offset.magnitude2() - along * along
+                            <= placement.radius * placement.radius)
+            })
+            .min_by(|(_, a), (_, b)| a.radius.total_cmp(&b.radius))
+            .map(|(placeable, _)| placeable)
+    }
+
+    /// Moves the placeable, along only the axes it may be moved along, keeping its fields within their ranges.
+    pub fn translate(&mut self, placeable: Placeable, translation: Vector3<f32>) {
+        match placeable {
+            Placeable::Cloth => self.position += translation,
+            Placeable::Floor => self.floor_height += translation.y,
+            Placeable::Cube => self.cube_center += translation,
+            Placeable::Body => self.body_position += translation,
+        }
+        self.clamp_to_ranges();
+    }
+
+    /// Rotates the placeable by the angle, in radians, about the axis, as far as it can be rotated about it.
+    /// Only the cube's yaw can be rotated.
+    pub fn rotate(&mut self, placeable: Placeable, axis: Vector3<f32>, angle: f32) {
+        if placeable == Placeable::Cube {
+            self.cube_yaw = (self.cube_yaw + angle * axis.y + std::f32::consts::PI)
+                .rem_euclid(std::f32::consts::TAU)
+                - std::f32::consts::PI;
+        }
+    }
+
+    /// Scales the placeable by the factor, scaling the cloth by its spacing.
+    pub fn scale(&mut self, placeable: Placeable, factor: f32) {
+        match placeable {
+            Placeable::Cloth => self.spacing *= factor,
+            Placeable::Floor => {}
+            Placeable::Cube => self.cube_size *= factor,
+            Placeable::Body => self.body_size *= factor,
+        }
+        self.clamp_to_ranges();
+    }
+
+    /// The cloth, pinned, and laid flat if it's horizontal.
+    pub fn get_cloth(&self) -> SpringyMesh {
+        let (rows, cols) = (self.rows.max(2) as usize, self.cols.max(2) as usize);
+        let mut mesh = Cloth::new(
+            rows,
+            cols,
+            quantity::meters(self.spacing),
+            self.position,
+            quantity::kilograms(self.point_mass),
+            quantity::newtons_per_meter(self.tensile_stiffness),
+            quantity::newton_seconds_per_meter(self.tensile_damping),
+            quantity::newtons_per_meter(self.shear_stiffness),
+            quantity::newton_seconds_per_meter(self.shear_damping),
+            quantity::newtons_per_meter(self.bending_stiffness),
+            quantity::newton_seconds_per_meter(self.bending_damping),
+            self.get_pins(rows, cols),
+        )
+        .mesh;
+        if self.horizontal {
+            mesh.rotate(Quaternion::from_angle_x(cgmath::Deg(-90.0)), self.position);
+        }
+        mesh
+    }
+
+    /// The indices of the pinned points of a cloth of the rows and columns, whose first row is its bottom edge.
+    fn get_pins(&self, rows: usize, cols: usize) -> Vec<usize> {
+        let top = (rows - 1) * cols;
+        let mut pins = Vec::new();
+        if self.pin_top_points {
+            pins.extend([top, top + cols / 2, top + cols - 1]);
+        }
+        if self.pin_top {
+            pins.extend(top..top + cols);
+        }
+        if self.pin_bottom {
+            pins.extend(0..cols);
+        }
+        if self.pin_left {
+            pins.extend((0..rows).map(|row| row * cols));
+        }
+        if self.pin_right {
+            pins.extend((0..rows).map(|row| row * cols + cols - 1));
+        }
+        pins.sort_unstable();
+        pins.dedup();
+        pins
+    }
+
+    /// The floor, which is always the first obstacle, and the cube if there is one.
+    pub fn get_obstacles(&self) -> Vec<CollidableMesh> {
+        let floor = [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)]
+            .into_iter()
+            .map(|(x, z)| {
+                Vector3::new(
+                    x * FLOOR_HALF_WIDTH,
+                    self.floor_height,
+                    z * FLOOR_HALF_WIDTH,
+                )
+            })
+            .collect();
+        let mut obstacles = vec![CollidableMesh::new(floor, vec![0, 1, 2, 0, 2, 3])];
+        if self.cube {
+            let (vertices, indices) = geometry::get_cube_vertices();
+            let yaw = Quaternion::from_angle_y(cgmath::Rad(self.cube_yaw));
+            let vertices = vertices
+                .into_iter()
+                .map(|vertex| yaw * (vertex * self.cube_size) + self.cube_center)
+                .collect();
+            obstacles.push(CollidableMesh::new(vertices, indices));
+        }
+        let sidedness = if self.one_sided_obstacles {
+            Sidedness::OneSided
+        } else {
+            Sidedness::TwoSided
+        };
+        obstacles
+            .into_iter()
+            .map(|obstacle| obstacle.with_sidedness(sidedness))
+            .collect()
+    }
+
+    /// The body dropped onto the cloth, if there is one.
+    pub fn get_body(&self) -> Result<Option<RigidBody>, &'static str> {
+        if !self.body {
+            return Ok(None);
+        }
+        let mass = quantity::kilograms(self.body_mass);
+        if self.body_sphere {
+            RigidBody::sphere(self.body_position, mass, self.body_size / 2.0)
+        } else {
+            RigidBody::cuboid(
+                self.body_position,
+                mass,
+                Vector3::new(self.body_size, self.body_size, self.body_size),
+            )
+        }
+        .map(Some)
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            rows: 20,
+            cols: 20,
+            spacing: 0.1,
+            point_mass: 10.0,
+            tensile_stiffness: 2000.0,
+            tensile_damping: 200.0,
+            shear_stiffness: 500.0,
+            shear_damping: 20.0,
+            bending_stiffness: 5.0,
+            bending_damping: 2.0,
+            position: Vector3::zero(),
+            horizontal: false,
+            pin_top_points: true,
+            pin_top: false,
+            pin_bottom: false,
+            pin_left: false,
+            pin_right: false,
+            floor_height: -2.0,
+            cube: false,
+            cube_center: Vector3::new(0.0, -1.5, 0.0),
+            cube_size: 1.0,
+            cube_yaw: 0.0,
+            one_sided_obstacles: true,
+            wind: Vector3::zero(),
+            body: false,
+            body_sphere: false,
+            body_position: Vector3::new(0.0, 1.0, 0.0),
+            body_size: 0.4,
+            body_mass: 100.0,
+        }
+    }
+}
+
+impl Reflect for Scenario {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![
+            Field::new("rows", "Rows", &mut self.rows)
+                .range(2.0..=40.0)
+                .group("Cloth"),
+            Field::new("cols", "Columns", &mut self.cols)
+                .range(2.0..=40.0)
+                .group("Cloth"),
+            Field::new("spacing", "Spacing", &mut self.spacing)
+                .range(0.02..=0.3)
+                .units(units::LENGTH)
+                .group("Cloth"),
+            Field::new("point_mass", "Point Mass", &mut self.point_mass)
+                .range(0.1..=50.0)
+                .units(units::MASS)
+                .group("Cloth"),
+            Field::new(
+                "tensile_stiffness",
+                "Tensile Stiffness",
+                &mut self.tensile_stiffness,
+            )
+            .range(0.0..=5000.0)
+            .units(units::STIFFNESS)
+            .group("Cloth"),
+            Field::new(
+                "tensile_damping",
+                "Tensile Damping",
+                &mut self.tensile_damping,
+            )
+            .range(0.0..=500.0)
+            .units(units::IMPULSE_PER_LENGTH)
+            .group("Cloth"),
+            Field::new(
+                "shear_stiffness",
+                "Shear Stiffness",
+                &mut self.shear_stiffness,
+            )
+            .range(0.0..=5000.0)
+            .units(units::STIFFNESS)
+            .group("Cloth"),
+            Field::new("shear_damping", "Shear Damping", &mut self.shear_damping)
+                .range(0.0..=500.0)
+                .units(units::IMPULSE_PER_LENGTH)
+                .group("Cloth"),
+            Field::new(
+                "bending_stiffness",
+                "Bending Stiffness",
+                &mut self.bending_stiffness,
+            )
+            .range(0.0..=500.0)
+            .units(units::STIFFNESS)
+            .group("Cloth"),
+            Field::new(
+                "bending_damping",
+                "Bending Damping",
+                &mut self.bending_damping,
+            )
+            .range(0.0..=100.0)
+            .units(units::IMPULSE_PER_LENGTH)
+            .group("Cloth"),
+            Field::new("position", "Position", &mut self.position)
+                .range(-3.0..=3.0)
+                .units(units::LENGTH)
+                .group("Cloth"),
+            Field::new("horizontal", "Horizontal", &mut self.horizontal).group("Cloth"),
+            Field::new(
+                "pin_top_points",
+                "Pin Top Corners and Middle",
+                &mut self.pin_top_points,
+            )
+            .group("Pins"),
+            Field::new("pin_top", "Pin Top Edge", &mut self.pin_top).group("Pins"),
+            Field::new("pin_bottom", "Pin Bottom Edge", &mut self.pin_bottom).group("Pins"),
+            Field::new("pin_left", "Pin Left Edge", &mut self.pin_left).group("Pins"),
+            Field::new("pin_right", "Pin Right Edge", &mut self.pin_right).group("Pins"),
+            Field::new("floor_height", "Floor Height", &mut self.floor_height)
+                .range(-4.0..=0.0)
+                .units(units::LENGTH)
+                .group("Obstacles"),
+            Field::new("cube", "Cube", &mut self.cube).group("Obstacles"),
+            Field::new("cube_center", "Cube Center", &mut self.cube_center)
+                .range(-3.0..=3.0)
+                .units(units::LENGTH)
+                .group("Obstacles"),
+            Field::new("cube_size", "Cube Size", &mut self.cube_size)
+                .range(0.1..=2.0)
+                .units(units::LENGTH)
+                .group("Obstacles"),
+            Field::new("cube_yaw", "Cube Yaw", &mut self.cube_yaw)
+                .range(-std::f32::consts::PI..=std::f32::consts::PI)
+                .units(units::ANGLE)
+                .group("Obstacles"),
+            Field::new(
+                "one_sided_obstacles",
+                "One-Sided Obstacles",
+                &mut self.one_sided_obstacles,
+            )
+            .group("Obstacles"),
+            Field::new("wind", "Wind", &mut self.wind)
+                .range(-20.0..=20.0)
+                .units(units::VELOCITY),
+            Field::new("body", "Drop Body", &mut self.body).group("Dropped Body"),
+            Field::new("body_sphere", "Sphere", &mut self.body_sphere).group("Dropped Body"),
+            Field::new("body_position", "Body Position", &mut self.body_position)
+                .range(-3.0..=3.0)
+                .units(units::LENGTH)
+                .group("Dropped Body"),
+            Field::new("body_size", "Body Size", &mut self.body_size)
+                .range(0.05..=1.0)
+                .units(units::LENGTH)
+                .group("Dropped Body"),
+            Field::new("body_mass", "Body Mass", &mut self.body_mass)
+                .range(1.0..=1000.0)
+                .units(units::MASS)
+                .group("Dropped Body"),
+        ]
+    }
+}
+
+/// A scenario's cloth, simulated with the body dropped onto it, which the cloth's points push against.
+pub struct Scene {
+    scenario: Scenario,
+    cloth: Simulation,
+    rigidbodies: RigidBodySimulation,
+    body: Option<RigidBodyHandle>,
+}
+
+impl Scene {
+    pub fn new(scenario: Scenario) -> Result<Scene, &'static str> {
+        let mut cloth = Simulation::new(vec![scenario.get_cloth()], scenario.get_obstacles());
+        cloth.sync_config(&Config {
+            wind: scenario.wind,
+            ..cloth.get_config().clone()
+        });
+        let mut rigidbodies = RigidBodySimulation::new(Vec::new(), scenario.get_obstacles());
+        let body = scenario
+            .get_body()?
+            .map(|body| rigidbodies.spawn_rigidbody(body));
+        let mut scene = Scene {
+            scenario,
+            cloth,
+            rigidbodies,
+            body,
+        };
+        scene.sync_rigidbody_config();
+        Ok(scene)
+    }
+
+    pub fn step(&mut self) -> Duration {
+        let elapsed = self.cloth.step();
+        if let Some(handle) = self.body {
+            self.sync_rigidbody_config();
+            self.rigidbodies.step();
+            let rigidbody = &mut self.rigidbodies.get_rigidbodies_mut()[handle];
+            for mesh in self.cloth.get_meshes_mut().iter_mut() {
+                collide_body(mesh, rigidbody, self.scenario.body_sphere);
+            }
+        }
+        elapsed
+    }
+
+    /// Springy meshes take gravity as the force on each point, so the body's weight is scaled by its mass
+    /// relative to a point's, so that it falls as the cloth does.
+    fn sync_rigidbody_config(&mut self) {
+        let springy = self.cloth.get_config();
+        let weight = springy.gravity * self.scenario.body_mass / self.scenario.point_mass;
+        let dt = springy.dt;
+        let config = self.rigidbodies.get_config_mut();
+        config.dt = dt;
+        config.gravity = weight;
+        config.coefficient_of_restitution = BODY_RESTITUTION;
+    }
+
+    pub fn get_scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn get_cloth(&self) -> &Simulation {
+        &self.cloth
+    }
+
+    pub fn get_cloth_mut(&mut self) -> &mut Simulation {
+        &mut self.cloth
+    }
+
+    pub fn get_body(&self) -> Option<&RigidBody> {
+        self.body
+            .map(|handle| &self.rigidbodies.get_rigidbodies()[handle])
+    }
+
+    pub fn get_timestep(&self) -> Duration {
+        self.cloth.get_timestep()
+    }
+
+    /// The energy of the cloth, as SpringyMesh::energy() counts it, and of the body, kinetic and potential.
+    pub fn energy(&self) -> EnergyBreakdown {
+        let gravity = self.cloth.get_config().gravity;
+        let body = self.get_body().map_or(EnergyBreakdown::default(), |body| {
+            let weight = gravity * self.scenario.body_mass / self.scenario.point_mass;
+            EnergyBreakdown {
+                kinetic: body.get_state().kinetic_energy(),
+                gravitational: -weight.dot(body.get_position()),
+                elastic: 0.0,
+            }
+        });
+        self.cloth.energy() + body
+    }
+
+    /// Syncs the cloth's config, e.g. from its UI.
+    pub fn sync_config(&mut self, config: &Config) {
+        self.cloth.sync_config(config);
+    }
+}
+
+/// Pushes the mesh's points out of the rigidbody, stopping each against it with an impulse which pushes
+/// the rigidbody back, so that the cloth catches the rigidbody and the rigidbody presses into the cloth.
+/// The stretched cloth then springs back, pushing the rigidbody away with the same impulses.
+fn collide_body(mesh: &mut SpringyMesh, rigidbody: &mut RigidBody, sphere: bool) {
+    let cuboid = Cuboid::of(rigidbody);
+    for index in 0..mesh.get_points().len() {
+        let point = mesh.get_points()[index];
+        if mesh.is_pinned(index) {
+            continue;
+        }
+        let penetration = if sphere {
+            get_sphere_penetration(cuboid.center, cuboid.half_extents.x, point.get_position())
+        } else {
+            cuboid.get_penetration(point.get_position(), BODY_MARGIN)
+        };
+        let Some((normal, depth)) = penetration else {
+            continue;
+        };
+        let r = point.get_position() - cuboid.center;
+        let body_velocity =
+            rigidbody.get_state().velocity() + rigidbody.get_angular_velocity().cross(r);
+        let closing = (point.get_velocity() - body_velocity).dot(normal);
+        let mut velocity = point.get_velocity();
+        if closing < 0.0 {
+            let impulse = -closing
+                / (1.0 / point.get_mass()
+                    + contact::get_inverse_effective_mass(rigidbody, r, normal));
+            velocity += normal * impulse / point.get_mass();
+            rigidbody.apply_impulse(-normal * impulse, r);
+        }
+        mesh.set_point(index, point.get_position() + normal * depth, velocity);
+    }
+}
+
+/// The outward normal of the sphere at the point, and how far the point must move along it to be
+/// BODY_MARGIN outside the sphere, if it's closer than that.
+fn get_sphere_penetration(
+    center: Vector3<f32>,
+    radius: f32,
+    point: Vector3<f32>,
+) -> Option<(Vector3<f32>, f32)> {
+    let offset = point - center;
+    let distance = offset.magnitude();
+    let depth = radius + BODY_MARGIN - distance;
+    if depth <= 0.0 {
+        return None;
+    }
+    // A point at the very center is pushed straight up.
+    let normal = if distance > f32::EPSILON {
+        offset / distance
+    } else {
+        Vector3::unit_y()
+    };
+    Some((normal, depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(scene: &mut Scene, seconds: f32) {
+        for _ in 0..(seconds / scene.get_timestep().as_secs_f32()) as usize {
+            scene.step();
+        }
+    }
+
+    #[test]
+    fn presets_declare_every_field() {
+        for preset in Preset::ALL {
+            let contents = preset.contents();
+            let scenario = preset.get_scenario();
+            for line in scenario.to_key_values().lines() {
+                let (key, _) = line.split_once('=').unwrap();
+                assert!(
+                    contents
+                        .lines()
+                        .any(|line| line.split_once('=').map(|(k, _)| k.trim()) == Some(key.trim())),
+                    "{} is missing {}",
+                    preset.name(),
+                    key
+                );
+            }
+            // Values in the file are within the fields' ranges, so they round trip.
+            assert_eq!(
+                Scenario::from_key_values(&scenario.to_key_values()),
+                scenario
+            );
+        }
+    }
+
+    #[test]
+    fn placeables_are_picked_and_arranged() {
+        let mut scenario = Preset::Tablecloth.get_scenario();
+        // Looking straight down at the tablecloth laid over the cube, the cube's picked over the larger cloth and
+        // floor, and the cloth where it drapes past the cube.
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        let above = Vector3::new(0.0, 5.0, 0.0);
+        assert_eq!(scenario.pick(above, down), Some(Placeable::Cube));
+        assert_eq!(
+            scenario.pick(Vector3::new(1.0, 5.0, 0.0), down),
+            Some(Placeable::Cloth)
+        );
+        assert_eq!(
+            scenario.pick(Vector3::new(1.8, 5.0, 1.8), down),
+            Some(Placeable::Floor)
+        );
+        assert_eq!(scenario.pick(above, -down), None);
+
+        scenario.translate(Placeable::Floor, Vector3::new(1.0, 0.5, 1.0));
+        assert_eq!(scenario.floor_height, -1.5);
+        // Moves are kept within the fields' ranges.
+        scenario.translate(Placeable::Cube, Vector3::new(10.0, 0.0, 0.0));
+        assert_eq!(scenario.cube_center, Vector3::new(3.0, -1.5, 0.0));
+        scenario.rotate(
+            Placeable::Cube,
+            Vector3::unit_y(),
+            std::f32::consts::PI * 1.5,
+        );
+        assert!((scenario.cube_yaw + std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+        scenario.scale(Placeable::Cube, 1.5);
+        assert_eq!(scenario.cube_size, 1.5);
+        assert_eq!(
+            Scenario::from_key_values(&scenario.to_file_contents()),
+            scenario
+        );
+    }
+
+    #[test]
+    fn presets_stay_finite() {
+        for preset in Preset::ALL {
+            let mut scene = Scene::new(preset.get_scenario()).unwrap();
+            run(&mut scene, 2.0);
+            let points = scene.get_cloth().get_meshes()[0].get_points();
+            assert!(
+                points.iter().all(|point| point.get_position().x.is_finite()
+                    && point.get_position().y.is_finite()
+                    && point.get_position().z.is_finite()),
+                "{}",
+                preset.name()
+            );
+        }
+    }
+
+    #[test]
+    fn trampoline_bounces_the_ball() {
+        let scenario = Preset::Trampoline.get_scenario();
+        let mut scene = Scene::new(scenario.clone()).unwrap();
+        let drop_height = scenario.body_position.y;
+        let initial_energy = scene.energy().total();
+        let mut max_energy = initial_energy;
+        let mut lowest = f32::MAX;
+        let mut rebound = f32::MIN;
+        for _ in 0..(2.0 / scene.get_timestep().as_secs_f32()) as usize {
+            scene.step();
+            max_energy = max_energy.max(scene.energy().total());
+            let body = scene.get_body().unwrap();
+            let height = body.get_position().y;
+            if rebound == f32::MIN && body.get_state().velocity().y <= 0.0 {
+                lowest = lowest.min(height);
+            } else if lowest < drop_height {
+                rebound = rebound.max(height);
+            }
+        }
+        // The ball stretches the trampoline and is thrown back up, but not as high as it was dropped from.
+        let fraction = (rebound - lowest) / (drop_height - lowest);
+        assert!(fraction > 0.2 && fraction < 1.0, "{}", fraction);
+        // The coupling exchanges impulses without making energy, up to the uncounted torsional springs'.
+        let weight = scenario.body_mass / scenario.point_mass
+            * scene.get_cloth().get_config().gravity.magnitude();
+        assert!(
+            max_energy - initial_energy < 0.01 * weight * (drop_height - lowest),
+            "{} {}",
+            initial_energy,
+            max_energy
+        );
+    }
+
+    #[test]
+    fn damped_trampoline_catches_the_box() {
+        let scenario = Scenario {
+            body_sphere: false,
+            point_mass: 10.0,
+            tensile_damping: 200.0,
+            shear_damping: 20.0,
+            bending_damping: 2.0,
+            ..Preset::Trampoline.get_scenario()
+        };
+        let mut scene = Scene::new(scenario.clone()).unwrap();
+        let cloth_height = scenario.position.y;
+        run(&mut scene, 6.0);
+        let body = scene.get_body().unwrap();
+        // The box sinks into the trampoline, but it's held well above the floor, and comes to rest.
+        let bottom = body.get_position().y - scenario.body_size / 2.0;
+        assert!(bottom < cloth_height, "{}", bottom);
+        assert!(bottom > scenario.floor_height + 0.2, "{}", bottom);
+        assert!(body.get_state().velocity().magnitude() < 0.1);
+    }
+
+    #[test]
+    fn tablecloth_drapes_over_the_cube() {
+        let scenario = Preset::Tablecloth.get_scenario();
+        let mut scene = Scene::new(scenario.clone()).unwrap();
+        run(&mut scene, 6.0);
+        let top = scenario.cube_center.y + scenario.cube_size / 2.0;
+        let points = scene.get_cloth().get_meshes()[0].get_points();
+        let (rows, cols) = (scenario.rows as usize, scenario.cols as usize);
+        let middle = points[rows / 2 * cols + cols / 2].get_position();
+        let corner = points[0].get_position();
+        // The middle rests on the cube's top, while the corners hang down its sides.
+        assert!((middle.y - top).abs() < 0.1, "{:?}", middle);
+        assert!(corner.y < top - 0.3, "{:?}", corner);
+    }
+}
@@ -1,17 +1,37 @@
 use std::time::Duration;
 
-use crate::gui;
-use crate::simulation::state::Integration;
+use crate::simulation::configurable::Configurable;
+use cgmath::Vector3;
 
+use super::super::checksum::{Checksum, Checksummed};
 use super::super::collidable_mesh::CollidableMesh;
-use super::super::state::State;
-use super::{config::Config, springy_mesh::SpringyMesh};
+use super::super::energy::EnergyBreakdown;
+use super::super::fan::{self, Fan};
+use super::super::force::ForceGenerator;
+use super::super::plugin::Plugins;
+use super::super::state::{Integration, State};
+use super::{
+    config::Config,
+    implicit::ImplicitEuler,
+    rain::Rain,
+    springy_mesh::{Point, SpringyMesh},
+};
 
 pub struct Simulation {
     config: Config,
     // Deformable springy meshes
     meshes: Vec<SpringyMesh>,
     obstacles: Vec<CollidableMesh>,
+    rain: Rain,
+    fan: Option<Fan>,
+    // Reused to integrate each mesh's points in turn, so that stepping doesn't allocate.
+    state: State<Point>,
+    // Likewise reused for each mesh when integrating implicitly.
+    implicit: ImplicitEuler,
+    plugins: Plugins<Simulation, Point>,
+    force_generators: Vec<Box<dyn ForceGenerator>>,
+    // Seconds of simulated time since the simulation began.
+    time: f32,
 }
 
 impl Simulation {
@@ -21,27 +41,68 @@ impl Simulation {
             config,
             meshes,
             obstacles,
+            rain: Rain::new(),
+            fan: None,
+            state: State::new(Vec::new()),
+            implicit: ImplicitEuler::new(),
+            plugins: Plugins::new(),
+            force_generators: Vec::new(),
+            time: 0.0,
         }
     }
 
     pub fn step(&mut self) -> Duration {
+        if let Some(fan) = &mut self.fan {
+            fan.advance(self.config.dt);
+        }
+        // The largest error estimate of any mesh's step, if the integration estimates it.
+        let mut error = None;
         self.meshes.iter_mut().for_each(|mesh| {
-            mesh.accumulate_forces(&self.config);
+            mesh.accumulate_forces(
+                &self.config,
+                self.fan.as_ref(),
+                &mut self.plugins,
+                &self.force_generators,
+                self.time,
+            );
 
-            let points = mesh.get_points();
-            let state_vector = State::new(points.to_vec());
-            let new_state_vector = match self.config.integration {
-                Integration::Rk4 => state_vector.rk4_step(self.config.dt),
-                Integration::Euler => state_vector.euler_step(self.config.dt),
-            };
-            let new_points = new_state_vector.get_elements();
+            self.state.elements_mut().clone_from(mesh.get_points());
+            // The generic implicit Euler step treats each point on its own, missing the struts coupling them,
+            // so the springy meshes assemble their struts' Jacobian for it instead.
+            if self.config.integration == Integration::ImplicitEuler {
+                mesh.step_implicitly(
+                    &mut self.implicit,
+                    self.state.elements_mut(),
+                    self.config.dt,
+                );
+            } else if let Some(mesh_error) =
+                self.state
+                    .step(self.config.integration, self.config.dt, &())
+            {
+                error = Some(mesh_error.max(error.unwrap_or(0.0)));
+            }
 
-            mesh.update_points(new_points, &self.obstacles, &self.config);
+            mesh.update_points(self.state.elements_mut(), &self.obstacles, &self.config);
+            // Tearing only drops struts, which aren't in the points' state, so the next step integrates the same
+            // points as this one.
+            if let Some(strain) = self.config.tear_strain {
+                mesh.tear(strain);
+            }
 
             mesh.clear_forces();
         });
+        self.rain
+            .step(&mut self.meshes, &self.config, self.config.dt);
+        self.time += self.config.dt;
 
-        Duration::from_secs_f32(self.config.dt)
+        Plugins::after_step(self, |simulation| &mut simulation.plugins);
+
+        let dt = self.config.dt;
+        // The step isn't retaken if its error's over the tolerance, but the next is shrunk to meet it.
+        if let (Some(adaptive), Some(error)) = (self.config.get_adaptive_dt(), error) {
+            self.config.dt = adaptive.next_timestep(dt, error);
+        }
+        Duration::from_secs_f32(dt)
     }
 
     pub fn get_timestep(&self) -> Duration {
@@ -52,27 +113,90 @@ impl Simulation {
         &self.meshes
     }
 
+    /// The energy of the meshes, as SpringyMesh::energy() counts it.
+    pub fn energy(&self) -> EnergyBreakdown {
+        self.meshes
+            .iter()
+            .map(|mesh| mesh.energy(self.config.gravity))
+            .sum()
+    }
+
+    pub fn get_meshes_mut(&mut self) -> &mut Vec<SpringyMesh> {
+        &mut self.meshes
+    }
+
     pub fn get_obstacles(&self) -> &Vec<CollidableMesh> {
         &self.obstacles
     }
 
+    /// Sets the velocity the obstacle's surface slides at, carrying the meshes resting on it along like a conveyor belt.
+    /// Panics if there is no obstacle at the index.
+    pub fn set_obstacle_surface_velocity(&mut self, index: usize, surface_velocity: Vector3<f32>) {
+        self.obstacles[index].set_surface_velocity(surface_velocity);
+    }
+
+    /// The plugins adding custom forces to the meshes' points, and custom behaviors after each step.
+    pub fn get_plugins_mut(&mut self) -> &mut Plugins<Simulation, Point> {
+        &mut self.plugins
+    }
+
+    /// The force fields pushing the meshes' points, besides the configured gravity and wind.
+    pub fn get_force_generators_mut(&mut self) -> &mut Vec<Box<dyn ForceGenerator>> {
+        &mut self.force_generators
+    }
+
+    pub fn get_rain(&self) -> &Rain {
+        &self.rain
+    }
+
+    pub fn get_fan(&self) -> Option<&Fan> {
+        self.fan.as_ref()
+    }
+
     // TODO consider extending this to allow for updating the springy mesh properties, i.e. changing nominal spring constant and damping, and the total mass of
     //      the springy mesh.
     //      Would need to be careful with updating strut values, since
     //      they aren't uniform (add_strut allows us to have varying values,
     //      which is important for cloth simulation)
-    pub fn sync_sim_config_from_ui(
-        &mut self,
-        ui: &mut gui::spring_mass_damper::SpringMassDamperUi,
-    ) {
-        let ui_config_state = ui.get_gui_state_mut();
-        self.config.integration = ui_config_state.integration;
-        self.config.dt = ui_config_state.dt;
-        self.config.gravity = ui_config_state.gravity;
-        self.config.wind = ui_config_state.wind;
-        self.config.lift_coefficient = ui_config_state.lift_coefficient;
-        self.config.drag_coefficient = ui_config_state.drag_coefficient;
-        self.config.coefficient_of_restitution = ui_config_state.coefficient_of_restitution;
-        self.config.coefficient_of_friction = ui_config_state.coefficient_of_friction;
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.integration = config.integration;
+        self.config.adaptive_dt = config.adaptive_dt;
+        // While dt adapts, it's the simulation's to set.
+        if self.config.get_adaptive_dt().is_none() {
+            self.config.dt = config.dt;
+        }
+        self.config.gravity = config.gravity;
+        self.config.wind = config.wind;
+        self.config.fan = config.fan;
+        fan::sync(&mut self.fan, self.config.fan);
+        self.config.lift_coefficient = config.lift_coefficient;
+        self.config.drag_coefficient = config.drag_coefficient;
+        self.config.coefficient_of_restitution = config.coefficient_of_restitution;
+        self.config.coefficient_of_friction = config.coefficient_of_friction;
+        self.config.collision_mask = config.collision_mask;
+        self.config.rain_rate = config.rain_rate;
+        self.config.drop_mass = config.drop_mass;
+        self.config.fluid_surface_height = config.fluid_surface_height;
+        self.config.fluid_density = config.fluid_density;
+        self.config.face_thickness = config.face_thickness;
+        self.config.tear_strain = config.tear_strain;
+        for mesh in self.meshes.iter_mut() {
+            mesh.set_collision_mask(self.config.collision_mask);
+        }
+    }
+}
+
+impl Checksummed for Simulation {
+    fn add_to_checksum(&self, checksum: &mut Checksum) {
+        self.meshes.add_to_checksum(checksum);
     }
 }
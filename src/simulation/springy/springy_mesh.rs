@@ -1,16 +1,28 @@
 use std::{f32::consts::PI, time::Duration};
 
+use crate::math::Real;
 use crate::simulation::collidable_mesh::CollidableMesh;
-use crate::simulation::{consts, state::Stateful};
-
-use super::config::Config;
-use cgmath::{InnerSpace, Rad, Vector3, Zero};
+use crate::simulation::collision_filter::CollisionFilter;
+use crate::simulation::{
+    checksum::{Checksum, Checksummed},
+    consts,
+    energy::EnergyBreakdown,
+    fan::Fan,
+    force::{self, Body, ForceGenerator},
+    plugin::Plugins,
+    quantity::{self, Damping, Mass, Quantity, Stiffness},
+    state::{stateful_fields, StateWriter, Stateful},
+};
+
+use super::config::{Config, AIR_DENSITY};
+use super::implicit::{ImplicitEuler, SpringJacobian};
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation, Vector2, Vector3, Zero};
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
 
 // TODO adjust these (or if these are "okay", we can use them and make them adjustable in UI)
-pub const STRUT_STIFFNESS_DEFAULT: f32 = 999999.0;
-pub const STRUT_DAMPING_DEFAULT: f32 = 700.0;
+pub const STRUT_STIFFNESS_DEFAULT: Stiffness = quantity::newtons_per_meter(999999.0);
+pub const STRUT_DAMPING_DEFAULT: Damping = quantity::newton_seconds_per_meter(700.0);
 // Generally, torsional spring and damping parameters should be one or two orders of magnitude higher
 // than the corresponding strut parameters.
 pub const TORSIONAL_SPRING_STIFFNESS_DEFAULT: f32 = 7000.0;
@@ -37,8 +49,8 @@ pub struct TorsionalSpringConfig {
 
 #[derive(Copy, Clone)]
 pub struct SpringConfig {
-    pub constant: f32,
-    pub damping: f32,
+    pub constant: Stiffness,
+    pub damping: Damping,
 }
 
 impl Default for TorsionalSpringConfig {
@@ -109,7 +121,6 @@ impl StrutKey {
 /// A face of a SpringyMesh
 struct Face {
     /// The indices of the struts comprising this Face's edges in the SpringyMesh
-    #[allow(dead_code)]
     strut_indices: (usize, usize, usize),
     vertex_indices: (usize, usize, usize),
 }
@@ -130,6 +141,37 @@ impl Face {
         (v1 - v0).cross(v2 - v0).magnitude() / 2.0
     }
 
+    /// The lift and drag on the face moving at the relative velocity through a medium,
+    /// with coefficients for the medium's density.
+    fn lift_and_drag(
+        &self,
+        points: &Vec<Point>,
+        relative_velocity: Vector3<f32>,
+        lift_coefficient: f32,
+        drag_coefficient: f32,
+    ) -> Vector3<f32> {
+        let normal = self.normal(points);
+        let effective_area = self.area(points) * normal.dot(relative_velocity).abs();
+        let drag_force = -drag_coefficient * effective_area * relative_velocity;
+        let lift_force = -lift_coefficient
+            * effective_area
+            * (relative_velocity * normal.cross(relative_velocity).magnitude());
+        drag_force + lift_force
+    }
+
+    /// The fraction of the face's vertices below the fluid's surface, if there is a fluid.
+    fn submersion(&self, points: &[Point], fluid_surface_height: Option<f32>) -> f32 {
+        let Some(height) = fluid_surface_height else {
+            return 0.0;
+        };
+        let (i0, i1, i2) = self.vertex_indices;
+        [i0, i1, i2]
+            .iter()
+            .filter(|i| points[**i].position.y < height)
+            .count() as f32
+            / 3.0
+    }
+
     /// Returns the vertex angle of vertex_indices.0
     fn vertex_angle_0(&self, springy_mesh: &SpringyMesh) -> Rad<f32> {
         self.vertex_angle(
@@ -172,6 +214,16 @@ impl Face {
     }
 }
 
+/// Where a segment crossed a face of a SpringyMesh.
+#[derive(Clone, Copy, Debug)]
+pub struct FaceHit {
+    face_index: usize,
+    /// The barycentric weights of the face's vertices at the crossing.
+    weights: Vector3<f32>,
+    /// How far along the segment the crossing is, in [0, 1].
+    fraction: f32,
+}
+
 /// A point in a SpringyMesh
 #[derive(Clone, Copy)]
 pub struct Point {
@@ -194,64 +246,43 @@ impl Point {
     fn add_external_forces(&mut self, config: &Config) {
         self.accumulated_force += config.gravity;
     }
-}
 
-impl Stateful for Point {
-    fn num_state_elements() -> usize {
-        10
+    pub fn get_mass(&self) -> f32 {
+        self.mass
     }
 
-    fn from_state_vector(state_data: Vec<f32>) -> Self {
-        if state_data.len() != Self::num_state_elements() {
-            panic!("State Vector incorrect size!")
-        }
-        let mass = state_data[0];
-        let position = Vector3::<f32>::new(state_data[1], state_data[2], state_data[3]);
-        let velocity = Vector3::<f32>::new(state_data[4], state_data[5], state_data[6]);
-        // TODO I don't know if we actually need this in the state. Probably not? Try to remove. Same with mass, really.
-        //      Issue is getting a point *from* a StateVector. I suppose that we could have a PointState
-        //      that Point contains, that contains the truly stateful stuff. For now, this is ok, just a little inefficient.
-        let accumulated_force = Vector3::<f32>::new(state_data[7], state_data[8], state_data[9]);
-        Point {
-            mass,
-            position,
-            velocity,
-            accumulated_force,
-        }
+    pub fn get_position(&self) -> Vector3<f32> {
+        self.position
     }
 
-    fn derivative(&self) -> Vec<f32> {
-        vec![
-            // Mass does not change
-            0.0,
+    pub fn get_velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+}
+
+// The mass and accumulated force aren't integrated, so they're left out of the state vector.
+// The forces on a point depend on its neighbours, so they're accumulated over the whole mesh before the step
+// rather than derived from the point alone, and there's nothing else for the derivative to be computed from.
+impl Stateful for Point {
+    type Context = ();
+
+    stateful_fields!(
+        6;
+        position: Vector3<f32>,
+        velocity: Vector3<f32>,
+    );
+
+    fn write_derivative(&self, _: &(), derivative: &mut [Real]) {
+        StateWriter::new(derivative)
             // Position derivative
-            self.velocity.x,
-            self.velocity.y,
-            self.velocity.z,
+            .push(&self.velocity)
             // Velocity derivative (acceleration). F = ma.
-            self.accumulated_force.x / self.mass,
-            self.accumulated_force.y / self.mass,
-            self.accumulated_force.z / self.mass,
-            // Accumulated force does not change in derivative.
-            0.0,
-            0.0,
-            0.0,
-        ]
-    }
-
-    fn as_state(&self) -> Vec<f32> {
-        vec![
-            self.mass,
-            self.position.x,
-            self.position.y,
-            self.position.z,
-            self.velocity.x,
-            self.velocity.y,
-            self.velocity.z,
-            self.accumulated_force.x,
-            self.accumulated_force.y,
-            self.accumulated_force.z,
-        ]
+            .push(&(self.accumulated_force / self.mass))
+            .finish()
+    }
+
+    fn num_position_elements() -> usize {
+        3
     }
 }
 
@@ -261,6 +292,9 @@ pub struct SpringyMesh {
     faces: Vec<Face>,
     points: Vec<Point>,
     pinned_points: Vec<usize>,
+    /// Texture coordinates for each point, if the mesh is to be textured.
+    uvs: Option<Vec<Vector2<f32>>>,
+    collision_filter: CollisionFilter,
 }
 
 impl SpringyMesh {
@@ -268,13 +302,13 @@ impl SpringyMesh {
         vertex_positions: Vec<Vector3<f32>>,
         vertex_indices: Vec<usize>,
         // Total mass of the mesh
-        mass: f32,
-        default_stiffness: f32,
-        default_damping: f32,
+        mass: Mass,
+        default_stiffness: Stiffness,
+        default_damping: Damping,
         torsional_spring_config: Option<TorsionalSpringConfig>,
         strut_overrides: &Option<FxHashMap<StrutKey, SpringConfig>>,
     ) -> Self {
-        let mass_per_vert = mass / vertex_positions.len() as f32;
+        let mass_per_vert = mass.value() / vertex_positions.len() as f32;
 
         let points = vertex_positions
             .iter()
@@ -298,8 +332,8 @@ impl SpringyMesh {
                         (default_stiffness, default_damping)
                     };
                     struts.push(Strut::new(
-                        stiffness,
-                        damping,
+                        stiffness.value(),
+                        damping.value(),
                         (points[strut_key.key.0].position - points[strut_key.key.1].position)
                             .magnitude(),
                         (strut_key.key.0, strut_key.key.1),
@@ -346,7 +380,7 @@ impl SpringyMesh {
 
         // Now that struts are aware of their adjacent faces, add a torsional spring if necessary.
         if let Some(torsional_spring_config) = torsional_spring_config {
-            let mut strut_rest_angles = FxHashMap::<usize, Rad<f32>>::default();
+            let mut strut_rest_angles = Vec::<(usize, Rad<f32>)>::new();
             for (strut_index, strut) in struts.iter().enumerate() {
                 if let (Some(f1_index), Some(f2_index)) =
                     (strut.face_indices.0, strut.face_indices.1)
@@ -380,7 +414,7 @@ impl SpringyMesh {
                         normal_l.dot(normal_r),
                     ));
 
-                    strut_rest_angles.insert(strut_index, theta);
+                    strut_rest_angles.push((strut_index, theta));
                 }
             }
             for (strut_index, angle) in strut_rest_angles.iter() {
@@ -397,13 +431,20 @@ impl SpringyMesh {
             faces,
             points,
             pinned_points: vec![],
+            uvs: None,
+            collision_filter: CollisionFilter::new(CollisionFilter::CLOTH, CollisionFilter::ALL),
         }
     }
 
-    pub fn add_strut(&mut self, vertex_indices: (usize, usize), stiffness: f32, damping: f32) {
+    pub fn add_strut(
+        &mut self,
+        vertex_indices: (usize, usize),
+        stiffness: Stiffness,
+        damping: Damping,
+    ) {
         self.struts.push(Strut::new(
-            stiffness,
-            damping,
+            stiffness.value(),
+            damping.value(),
             (self.points[vertex_indices.0].position - self.points[vertex_indices.1].position)
                 .magnitude(),
             vertex_indices,
@@ -412,35 +453,193 @@ impl SpringyMesh {
         ));
     }
 
+    /// Tears the struts stretched by more than the strain, as a fraction of their rest lengths, along with the
+    /// faces they edge, returning how many struts tore. The points are all kept, so the mesh's points are
+    /// integrated as before, just with fewer struts between them.
+    pub fn tear(&mut self, max_strain: f32) -> usize {
+        let torn = self
+            .struts
+            .iter()
+            .map(|strut| {
+                let p0 = self.points[strut.vertex_indices.0].position;
+                let p1 = self.points[strut.vertex_indices.1].position;
+                (p1 - p0).magnitude() > strut.length * (1.0 + max_strain)
+            })
+            .collect_vec();
+        let torn_count = torn.iter().filter(|torn| **torn).count();
+        if torn_count == 0 {
+            return 0;
+        }
+        let torn_faces = self
+            .faces
+            .iter()
+            .map(|face| {
+                let (s0, s1, s2) = face.strut_indices;
+                torn[s0] || torn[s1] || torn[s2]
+            })
+            .collect_vec();
+        let strut_indices = reindex(&torn);
+        let face_indices = reindex(&torn_faces);
+
+        let mut torn_struts = torn.iter();
+        self.struts.retain(|_| !torn_struts.next().unwrap());
+        for strut in self.struts.iter_mut() {
+            let faces = [strut.face_indices.0, strut.face_indices.1]
+                .map(|face| face.and_then(|face| face_indices[face]));
+            strut.face_indices = (faces[0].or(faces[1]), faces[0].and(faces[1]));
+            // It's no longer a hinge once either face is torn away.
+            if strut.face_indices.1.is_none() {
+                strut.torsional_spring = None;
+            }
+        }
+        let mut torn_faces = torn_faces.iter();
+        self.faces.retain(|_| !torn_faces.next().unwrap());
+        for face in self.faces.iter_mut() {
+            let (s0, s1, s2) = face.strut_indices;
+            // The face's struts all held, since it did.
+            face.strut_indices = (
+                strut_indices[s0].unwrap(),
+                strut_indices[s1].unwrap(),
+                strut_indices[s2].unwrap(),
+            );
+        }
+        torn_count
+    }
+
     pub fn add_pin(&mut self, pin_index: usize) {
         self.pinned_points.push(pin_index);
     }
 
+    pub fn is_pinned(&self, index: usize) -> bool {
+        self.pinned_points.contains(&index)
+    }
+
+    /// Turns the mesh's points about the center, e.g. to lay a cloth flat before the simulation begins.
+    pub fn rotate(&mut self, rotation: Quaternion<f32>, center: Vector3<f32>) {
+        for point in self.points.iter_mut() {
+            point.position = center + rotation.rotate_vector(point.position - center);
+            point.velocity = rotation.rotate_vector(point.velocity);
+        }
+    }
+
+    /// Moves the point, and sets its velocity, unless it's pinned, e.g. to push it out of a body it's pressed into.
+    pub fn set_point(&mut self, index: usize, position: Vector3<f32>, velocity: Vector3<f32>) {
+        if self.is_pinned(index) {
+            return;
+        }
+        let point = &mut self.points[index];
+        point.position = position;
+        point.velocity = velocity;
+    }
+
+    pub fn set_collision_mask(&mut self, mask: u32) {
+        self.collision_filter.mask = mask;
+    }
+
+    /// Sets the texture coordinates of each point, which follow the points as the mesh deforms.
+    /// Panics if there is not exactly one UV per point.
+    pub fn set_uvs(&mut self, uvs: Vec<Vector2<f32>>) {
+        if uvs.len() != self.points.len() {
+            panic!("There must be exactly one UV per point!");
+        }
+        self.uvs = Some(uvs);
+    }
+
+    pub fn get_uvs(&self) -> Option<&Vec<Vector2<f32>>> {
+        self.uvs.as_ref()
+    }
+
     pub fn get_points(&self) -> &Vec<Point> {
         &self.points
     }
 
+    /// The points' kinetic energy, their potential energy in the gravity force, and the elastic energy of
+    /// the struts' springs. The torsional springs' energy isn't counted.
+    pub fn energy(&self, gravity: Vector3<f32>) -> EnergyBreakdown {
+        let kinetic: f32 = self
+            .points
+            .iter()
+            .map(|point| 0.5 * point.mass * point.velocity.magnitude2())
+            .sum();
+        let gravitational: f32 = self
+            .points
+            .iter()
+            .map(|point| -gravity.dot(point.position))
+            .sum();
+        let elastic: f32 = self
+            .struts
+            .iter()
+            .map(|strut| {
+                let p0 = self.points[strut.vertex_indices.0].position;
+                let p1 = self.points[strut.vertex_indices.1].position;
+                let stretch = (p1 - p0).magnitude() - strut.length;
+                0.5 * strut.stiffness * stretch * stretch
+            })
+            .sum();
+        EnergyBreakdown {
+            kinetic,
+            gravitational,
+            elastic,
+        }
+    }
+
+    /// Steps the points, with their forces accumulated, over the timestep by implicit Euler into the new points,
+    /// which should start as copies of the points. Only the struts' forces are taken implicitly.
+    pub fn step_implicitly(&self, implicit: &mut ImplicitEuler, new_points: &mut [Point], dt: f32) {
+        implicit.clear();
+        for (index, point) in self.points.iter().enumerate() {
+            implicit.add_point(
+                point.mass,
+                point.velocity,
+                point.accumulated_force,
+                self.is_pinned(index),
+            );
+        }
+        for strut in self.struts.iter() {
+            implicit.add_spring(SpringJacobian::new(
+                strut.vertex_indices,
+                strut.stiffness,
+                strut.damping,
+                strut.length,
+                self.points[strut.vertex_indices.0].position,
+                self.points[strut.vertex_indices.1].position,
+            ));
+        }
+        for (new_point, delta_velocity) in new_points.iter_mut().zip(implicit.solve(dt)) {
+            new_point.velocity += *delta_velocity;
+            new_point.position += dt * new_point.velocity;
+        }
+    }
+
     pub fn update_points(
         &mut self,
-        mut new_points: Vec<Point>,
+        new_points: &mut [Point],
         obstacles: &Vec<CollidableMesh>,
         config: &Config,
     ) {
+        // Each obstacle's faces are kept with it, for its surface velocity.
         let obstacle_faces = obstacles
             .iter()
-            .map(|o| o.get_faces())
-            .flatten()
+            .filter(|o| {
+                self.collision_filter
+                    .collides_with(o.get_collision_filter())
+            })
+            .map(|o| (o, o.get_faces().iter().collect_vec()))
             .collect_vec();
         // TODO collision detection can be more efficient with bounding box checks.
 
         // Vertex-Face collisions
         for (new_point, old_point) in new_points.iter_mut().zip(&self.points) {
-            if let Some(face) = CollidableMesh::get_collided_face_from_list(
-                &obstacle_faces,
-                old_point.position,
-                new_point.position,
-                Duration::from_secs_f32(config.dt),
-            ) {
+            if let Some((obstacle, face)) = obstacle_faces.iter().find_map(|(obstacle, faces)| {
+                CollidableMesh::get_collided_face_from_list(
+                    faces,
+                    obstacle.get_sidedness(),
+                    old_point.position,
+                    new_point.position,
+                    Duration::from_secs_f32(config.dt),
+                )
+                .map(|face| (obstacle, face))
+            }) {
                 let old_distance_to_plane = face.distance_from_plane(&old_point.position);
                 let new_distance_to_plane = face.distance_from_plane(&new_point.position);
 
@@ -452,20 +651,25 @@ impl SpringyMesh {
                 let velocity_collision = old_point.velocity
                     + config.dt * fraction_timestep * old_point.accumulated_force / old_point.mass;
 
-                let new_position = collision_point + face.normal() * consts::EPSILON;
+                // The point's kept on the side of the face it came from.
+                let new_position = collision_point
+                    + face.normal() * consts::EPSILON * old_distance_to_plane.signum();
 
                 let velocity_collision_normal =
                     velocity_collision.dot(face.normal()) * face.normal();
-                let velocity_collision_tangent = velocity_collision - velocity_collision_normal;
+                // Friction acts on the velocity relative to the obstacle's sliding surface.
+                let surface_velocity = obstacle.get_surface_velocity(face);
+                let velocity_collision_tangent =
+                    velocity_collision - velocity_collision_normal - surface_velocity;
 
                 let velocity_response_normal =
                     -1.0 * velocity_collision_normal * config.coefficient_of_restitution;
                 let velocity_response_tangent = if velocity_collision_tangent.is_zero()
                     || velocity_collision_tangent.magnitude().is_nan()
                 {
-                    Vector3::<f32>::zero()
+                    surface_velocity
                 } else {
-                    velocity_collision_tangent
+                    surface_velocity + velocity_collision_tangent
                         - velocity_collision_tangent.normalize()
                             * f32::min(
                                 config.coefficient_of_friction
@@ -481,6 +685,20 @@ impl SpringyMesh {
             }
         }
 
+        // Points which are inside one-sided obstacles, e.g. having started inside them, are pushed out through
+        // the nearest face, rather than left to pop out wherever they next cross a face.
+        for new_point in new_points.iter_mut() {
+            let Some(penetration) = obstacle_faces
+                .iter()
+                .find_map(|(obstacle, _)| obstacle.get_penetration(new_point.position))
+            else {
+                continue;
+            };
+            let normal = penetration.face.normal();
+            new_point.position = penetration.resolve(new_point.position);
+            new_point.velocity -= new_point.velocity.dot(normal).min(0.0) * normal;
+        }
+
         // TODO then, handle face-vertex collisions (obstacles' vertices against the mesh's faces)
         // For each vertex in obstacles
         //   Check for each face of the springy mesh
@@ -489,11 +707,10 @@ impl SpringyMesh {
 
         // TODO then do edge-edge collisions (mesh's edge against environment edge)
 
-        let original_points = self.points.clone();
-        self.points = new_points;
-        for pin_index in self.pinned_points.iter_mut() {
-            self.points[*pin_index] = original_points[*pin_index];
+        for pin_index in self.pinned_points.iter() {
+            new_points[*pin_index] = self.points[*pin_index];
         }
+        self.points.copy_from_slice(new_points);
     }
 
     /// Returns the vertices and their indices. Useful for making a mesh for rendering
@@ -512,12 +729,125 @@ impl SpringyMesh {
         (vertex_positions, vertex_indices)
     }
 
-    pub fn accumulate_forces(&mut self, config: &Config) {
+    /// Returns the corners of the axis-aligned box bounding the mesh's points.
+    pub fn get_bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        self.points.iter().fold(
+            (
+                Vector3::<f32>::new(f32::MAX, f32::MAX, f32::MAX),
+                Vector3::<f32>::new(f32::MIN, f32::MIN, f32::MIN),
+            ),
+            |(min, max), p| {
+                (
+                    Vector3::new(
+                        min.x.min(p.position.x),
+                        min.y.min(p.position.y),
+                        min.z.min(p.position.z),
+                    ),
+                    Vector3::new(
+                        max.x.max(p.position.x),
+                        max.y.max(p.position.y),
+                        max.z.max(p.position.z),
+                    ),
+                )
+            },
+        )
+    }
+
+    /// Finds the first face crossed by the segment from start to end, e.g. the path of
+    /// a particle over a step, if any.
+    pub fn get_segment_hit(&self, start: Vector3<f32>, end: Vector3<f32>) -> Option<FaceHit> {
+        let direction = end - start;
+        let mut closest: Option<FaceHit> = None;
+        for (face_index, face) in self.faces.iter().enumerate() {
+            let v0 = self.points[face.vertex_indices.0].position;
+            let v1 = self.points[face.vertex_indices.1].position;
+            let v2 = self.points[face.vertex_indices.2].position;
+            // Moller-Trumbore intersection, restricted to the segment.
+            let edge_1 = v1 - v0;
+            let edge_2 = v2 - v0;
+            let p = direction.cross(edge_2);
+            let determinant = edge_1.dot(p);
+            if determinant.abs() < consts::EPSILON * consts::EPSILON {
+                continue;
+            }
+            let s = start - v0;
+            let u = s.dot(p) / determinant;
+            if !(0.0..=1.0).contains(&u) {
+                continue;
+            }
+            let q = s.cross(edge_1);
+            let v = direction.dot(q) / determinant;
+            if v < 0.0 || u + v > 1.0 {
+                continue;
+            }
+            let fraction = edge_2.dot(q) / determinant;
+            if !(0.0..=1.0).contains(&fraction) {
+                continue;
+            }
+            if closest.is_none_or(|hit| fraction < hit.fraction) {
+                closest = Some(FaceHit {
+                    face_index,
+                    weights: Vector3::new(1.0 - u - v, u, v),
+                    fraction,
+                });
+            }
+        }
+        closest
+    }
+
+    /// The unit normal of the face that was hit.
+    pub fn get_hit_normal(&self, hit: &FaceHit) -> Vector3<f32> {
+        self.faces[hit.face_index].normal(&self.points)
+    }
+
+    /// The velocity of the mesh's surface where it was hit, interpolated from the face's points.
+    pub fn get_hit_velocity(&self, hit: &FaceHit) -> Vector3<f32> {
+        let (i0, i1, i2) = self.faces[hit.face_index].vertex_indices;
+        self.points[i0].velocity * hit.weights.x
+            + self.points[i1].velocity * hit.weights.y
+            + self.points[i2].velocity * hit.weights.z
+    }
+
+    /// Applies an impulse where the mesh was hit, spreading it to the face's points by
+    /// their barycentric weights. Pinned points are unaffected.
+    pub fn apply_impulse(&mut self, hit: &FaceHit, impulse: Vector3<f32>) {
+        let (i0, i1, i2) = self.faces[hit.face_index].vertex_indices;
+        for (index, weight) in [
+            (i0, hit.weights.x),
+            (i1, hit.weights.y),
+            (i2, hit.weights.z),
+        ] {
+            if self.pinned_points.contains(&index) {
+                continue;
+            }
+            let point = &mut self.points[index];
+            point.velocity += impulse * weight / point.mass;
+        }
+    }
+
+    /// Accumulates the forces on the points, including those of the force plugins and generators at the simulated time.
+    pub fn accumulate_forces<S>(
+        &mut self,
+        config: &Config,
+        fan: Option<&Fan>,
+        plugins: &mut Plugins<S, Point>,
+        generators: &[Box<dyn ForceGenerator>],
+        time: f32,
+    ) {
         self.apply_external_point_forces(config);
         self.apply_strut_forces();
         // TODO unfortunately, torsional forces are broken, causing the mesh to explode. Try to fix them.
         // self.apply_torsional_forces();
-        self.apply_face_forces(config);
+        self.apply_face_forces(config, fan);
+        for point in self.points.iter_mut() {
+            point.accumulated_force += plugins.load(point, time).force;
+            let body = Body {
+                position: point.position,
+                velocity: point.velocity,
+                mass: point.mass,
+            };
+            point.accumulated_force += force::sum(generators, &body, time);
+        }
 
         for pin_index in self.pinned_points.iter() {
             self.points[*pin_index].accumulated_force = Vector3::<f32>::zero();
@@ -552,7 +882,9 @@ impl SpringyMesh {
 
     #[allow(dead_code)]
     fn apply_torsional_forces(&mut self) {
-        let mut vertex_forces: FxHashMap<usize, Vector3<f32>> = FxHashMap::default();
+        // Accumulated per vertex in strut order, then applied in vertex order, so that the
+        // forces are summed in the same order every step.
+        let mut vertex_forces = vec![Vector3::<f32>::zero(); self.points.len()];
         self.struts.iter().for_each(|strut| {
             // See "Foundations of Physically Based Modeling and Animation" section 8.3.2: Computation of Torque from a torsional spring.
             if let (Some(f1_index), Some(f2_index)) = (strut.face_indices.0, strut.face_indices.1) {
@@ -606,47 +938,61 @@ impl SpringyMesh {
                 let force_1 = (d_02 * force_2 + d_03 * force_3) / l_01;
                 let force_0 = -1.0 * (force_1 + force_2 + force_3);
 
-                *vertex_forces
-                    .entry(x_0_index)
-                    .or_insert(cgmath::Vector3::zero()) += force_0;
-                *vertex_forces
-                    .entry(x_1_index)
-                    .or_insert(cgmath::Vector3::zero()) += force_1;
-                *vertex_forces
-                    .entry(x_2_index)
-                    .or_insert(cgmath::Vector3::zero()) += force_2;
-                *vertex_forces
-                    .entry(x_3_index)
-                    .or_insert(cgmath::Vector3::zero()) += force_3;
+                vertex_forces[x_0_index] += force_0;
+                vertex_forces[x_1_index] += force_1;
+                vertex_forces[x_2_index] += force_2;
+                vertex_forces[x_3_index] += force_3;
             }
         });
 
-        for (vertex_index, force) in vertex_forces.iter() {
-            self.points[*vertex_index].accumulated_force += *force;
+        for (point, force) in self.points.iter_mut().zip(vertex_forces) {
+            point.accumulated_force += force;
         }
     }
 
-    fn apply_face_forces(&mut self, config: &Config) {
+    /// Applies lift and drag from the air, blown by the wind and the fan, and buoyancy, lift, and drag
+    /// from the fluid, to each face in proportion to how much of it is submerged.
+    fn apply_face_forces(&mut self, config: &Config, fan: Option<&Fan>) {
         for face in self.faces.iter() {
             let v0 = self.points[face.vertex_indices.0];
             let v1 = self.points[face.vertex_indices.1];
             let v2 = self.points[face.vertex_indices.2];
             let average_vertex_velocity = (v0.velocity + v1.velocity + v2.velocity) / 3.0;
-            let relative_velocity = average_vertex_velocity - config.wind;
-            let effective_area =
-                face.area(&self.points) * face.normal(&self.points).dot(relative_velocity).abs();
-            let drag_force = -1.0 * config.drag_coefficient * effective_area * relative_velocity;
-            let lift_force = -1.0
-                * config.lift_coefficient
-                * effective_area
-                * (relative_velocity
-                    * face
-                        .normal(&self.points)
-                        .cross(relative_velocity)
-                        .magnitude());
-            let v0_force = face.vertex_angle_0(&self) / Rad(PI) * (drag_force + lift_force);
-            let v1_force = face.vertex_angle_1(&self) / Rad(PI) * (drag_force + lift_force);
-            let v2_force = face.vertex_angle_2(&self) / Rad(PI) * (drag_force + lift_force);
+            let wind = match fan {
+                Some(fan) => {
+                    config.wind + fan.get_wind((v0.position + v1.position + v2.position) / 3.0)
+                }
+                None => config.wind,
+            };
+            let submersion = face.submersion(&self.points, config.fluid_surface_height);
+
+            let mut force = Vector3::<f32>::zero();
+            if submersion < 1.0 {
+                force += (1.0 - submersion)
+                    * face.lift_and_drag(
+                        &self.points,
+                        average_vertex_velocity - wind,
+                        config.lift_coefficient,
+                        config.drag_coefficient,
+                    );
+            }
+            if submersion > 0.0 {
+                let density_ratio = config.fluid_density / AIR_DENSITY;
+                let displaced_volume = face.area(&self.points) * config.face_thickness;
+                let buoyancy = -config.gravity * config.fluid_density * displaced_volume;
+                // The fluid is still.
+                let lift_and_drag = face.lift_and_drag(
+                    &self.points,
+                    average_vertex_velocity,
+                    config.lift_coefficient * density_ratio,
+                    config.drag_coefficient * density_ratio,
+                );
+                force += submersion * (buoyancy + lift_and_drag);
+            }
+
+            let v0_force = face.vertex_angle_0(&self) / Rad(PI) * force;
+            let v1_force = face.vertex_angle_1(&self) / Rad(PI) * force;
+            let v2_force = face.vertex_angle_2(&self) / Rad(PI) * force;
             self.points[face.vertex_indices.0].accumulated_force += v0_force;
             self.points[face.vertex_indices.1].accumulated_force += v1_force;
             self.points[face.vertex_indices.2].accumulated_force += v2_force;
@@ -660,12 +1006,38 @@ impl SpringyMesh {
     }
 }
 
+impl Checksummed for SpringyMesh {
+    fn add_to_checksum(&self, checksum: &mut Checksum) {
+        self.points.iter().for_each(|point| {
+            checksum.add_vector3(point.position);
+            checksum.add_vector3(point.velocity);
+        });
+    }
+}
+
+/// The index of each element among those kept once the removed ones are dropped, keeping their order.
+fn reindex(removed: &[bool]) -> Vec<Option<usize>> {
+    let mut kept = 0;
+    removed
+        .iter()
+        .map(|removed| {
+            if *removed {
+                return None;
+            }
+            kept += 1;
+            Some(kept - 1)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::f32::consts::PI;
 
     use cgmath::{assert_relative_eq, Rad, Vector3, Zero};
 
+    use crate::simulation::quantity::{kilograms, newton_seconds_per_meter, newtons_per_meter};
+    use crate::simulation::springy::config::Config;
     use crate::simulation::springy::springy_mesh::NOMINAL_STRUT_LENGTH;
 
     use super::{SpringyMesh, TorsionalSpringConfig};
@@ -684,9 +1056,9 @@ mod tests {
         super::SpringyMesh::new(
             vertex_positions,
             vertex_indices,
-            1.0,
-            2.0,
-            3.0,
+            kilograms(1.0),
+            newtons_per_meter(2.0),
+            newton_seconds_per_meter(3.0),
             Some(tort_cfg),
             &None,
         )
@@ -710,9 +1082,9 @@ mod tests {
         super::SpringyMesh::new(
             vertex_positions,
             vertex_indices,
-            1.0,
-            2.0,
-            3.0,
+            kilograms(1.0),
+            newtons_per_meter(2.0),
+            newton_seconds_per_meter(3.0),
             Some(tort_cfg),
             &None,
         )
@@ -882,6 +1254,13 @@ mod tests {
         assert_eq!(expected_vertex_indices, vertex_inidices);
     }
 
+    #[test]
+    #[should_panic]
+    fn set_uvs_wrong_length() {
+        let mut springy_mesh = get_strip();
+        springy_mesh.set_uvs(vec![cgmath::Vector2::<f32>::zero(); 2]);
+    }
+
     // Tests torsional force for when angle of faces are acute
     // (the angles between the face normals will be obstuse, however)
     #[test]
@@ -898,7 +1277,15 @@ mod tests {
             spring_constant: 1.0,
             spring_damping: 1.0,
         };
-        let mut mesh = SpringyMesh::new(vertices, indices, 2.0, 1.0, 1.0, Some(tort_cfg), &None);
+        let mut mesh = SpringyMesh::new(
+            vertices,
+            indices,
+            kilograms(2.0),
+            newtons_per_meter(1.0),
+            newton_seconds_per_meter(1.0),
+            Some(tort_cfg),
+            &None,
+        );
 
         assert_relative_eq!(
             -Vector3::<f32>::unit_y(),
@@ -946,13 +1333,236 @@ mod tests {
             Vector3::<f32>::unit_y() + Vector3::<f32>::unit_x(),
         ];
         let vertex_indices = vec![0, 4, 3, 0, 1, 4, 1, 5, 4, 1, 2, 5];
-        let strip =
-            super::SpringyMesh::new(vertex_positions, vertex_indices, 1.0, 2.0, 3.0, None, &None);
+        let strip = super::SpringyMesh::new(
+            vertex_positions,
+            vertex_indices,
+            kilograms(1.0),
+            newtons_per_meter(2.0),
+            newton_seconds_per_meter(3.0),
+            None,
+            &None,
+        );
         for i in 0..9 {
             assert!(strip.struts[i].torsional_spring.is_none());
         }
     }
 
+    #[test]
+    fn segment_hit() {
+        let triangle = get_triangle();
+        let hit = triangle
+            .get_segment_hit(Vector3::new(0.5, 0.25, 1.0), Vector3::new(0.5, 0.25, -1.0))
+            .unwrap();
+        assert_relative_eq!(hit.fraction, 0.5);
+        assert_relative_eq!(hit.weights, Vector3::new(0.5, 0.25, 0.25));
+        assert_relative_eq!(triangle.get_hit_normal(&hit), Vector3::unit_z());
+
+        // Segments which end short of the face, or pass beside it, don't hit.
+        assert!(triangle
+            .get_segment_hit(Vector3::new(0.5, 0.25, 1.0), Vector3::new(0.5, 0.25, 0.5))
+            .is_none());
+        assert!(triangle
+            .get_segment_hit(Vector3::new(2.0, 2.0, 1.0), Vector3::new(2.0, 2.0, -1.0))
+            .is_none());
+    }
+
+    #[test]
+    fn impulse_spread_by_weights() {
+        let mut triangle = get_triangle();
+        triangle.add_pin(2);
+        let hit = triangle
+            .get_segment_hit(Vector3::new(0.5, 0.25, 1.0), Vector3::new(0.5, 0.25, -1.0))
+            .unwrap();
+        let impulse = Vector3::new(0.0, 0.0, -3.0);
+        triangle.apply_impulse(&hit, impulse);
+        // Each point has a third of the triangle's unit mass.
+        assert_relative_eq!(triangle.points[0].velocity, Vector3::new(0.0, 0.0, -4.5));
+        assert_relative_eq!(triangle.points[1].velocity, Vector3::new(0.0, 0.0, -2.25));
+        assert_relative_eq!(triangle.points[2].velocity, Vector3::zero());
+        assert_relative_eq!(
+            triangle.get_hit_velocity(&hit),
+            Vector3::new(0.0, 0.0, -2.8125)
+        );
+    }
+
+    #[test]
+    fn overstretched_struts_tear_with_their_faces() {
+        let mut strip = get_strip();
+        assert_eq!(0, strip.tear(0.5));
+        // Pulling the corner of the last face out tears its two struts to it.
+        strip.set_point(2, Vector3::new(3.0, 0.0, 1.0), Vector3::zero());
+        assert_eq!(2, strip.tear(0.5));
+        assert_eq!(6, strip.points.len());
+        assert_eq!(7, strip.struts.len());
+        assert_eq!(3, strip.faces.len());
+        // The strut it shared with the torn face is no longer a hinge.
+        assert_eq!((1, 5), strip.struts[5].vertex_indices);
+        assert_eq!((Some(2), None), strip.struts[5].face_indices);
+        assert!(strip.struts[5].torsional_spring.is_none());
+        assert!(strip.struts[4].torsional_spring.is_some());
+        // The faces left still refer to their own edges.
+        for face in strip.faces.iter() {
+            let (s0, s1, s2) = face.strut_indices;
+            for strut in [s0, s1, s2] {
+                let (v0, v1) = strip.struts[strut].vertex_indices;
+                let (f0, f1, f2) = face.vertex_indices;
+                assert!([f0, f1, f2].contains(&v0) && [f0, f1, f2].contains(&v1));
+            }
+        }
+    }
+
+    #[test]
+    fn torn_meshes_step_on() {
+        use super::super::simulation::Simulation;
+        use crate::simulation::configurable::Configurable;
+
+        let mut strip = get_strip();
+        strip.set_point(
+            2,
+            Vector3::unit_x() + Vector3::unit_z(),
+            Vector3::unit_x() * 50.0,
+        );
+        let mut simulation = Simulation::new(vec![strip], Vec::new());
+        simulation.sync_config(&Config {
+            tear_strain: Some(0.5),
+            ..Default::default()
+        });
+        for _ in 0..100 {
+            simulation.step();
+        }
+        let strip = &simulation.get_meshes()[0];
+        assert_eq!(7, strip.struts.len());
+        assert_eq!(3, strip.faces.len());
+        // The flung point's free of the strip, flying on.
+        assert!(strip.points[2].position.x > 3.0);
+        assert!(strip.points[4].position.x.abs() < 1.0);
+    }
+
+    #[test]
+    fn buoyancy_by_submersion() {
+        let total_face_force = |fluid_surface_height| {
+            let mut triangle = get_triangle();
+            let config = Config {
+                gravity: Vector3::new(0.0, -10.0, 0.0),
+                fluid_surface_height,
+                fluid_density: 1000.0,
+                face_thickness: 0.05,
+                ..Default::default()
+            };
+            triangle.apply_face_forces(&config, None);
+            triangle
+                .points
+                .iter()
+                .fold(Vector3::zero(), |sum, p| sum + p.accumulated_force)
+        };
+        // The triangle has unit area, so it displaces 0.05 m³ when fully submerged.
+        assert_relative_eq!(total_face_force(Some(2.0)), Vector3::new(0.0, 500.0, 0.0));
+        // Two of the triangle's three vertices are below the surface.
+        assert_relative_eq!(
+            total_face_force(Some(0.5)),
+            Vector3::new(0.0, 1000.0 / 3.0, 0.0),
+            epsilon = 1e-3
+        );
+        assert_relative_eq!(total_face_force(None), Vector3::zero());
+    }
+
+    #[test]
+    fn fan_blows_on_faces() {
+        use crate::simulation::fan::{Fan, FanConfig};
+
+        let mut triangle = get_triangle();
+        // The fan blows along Z, at the triangle lying in the XY plane.
+        let mut fan = Fan::new(FanConfig {
+            position: Vector3::new(0.5, 0.3, -1.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            blade_speed: 20.0,
+            radius: 0.5,
+        });
+        fan.advance(0.001);
+        triangle.apply_face_forces(&Config::default(), Some(&fan));
+        let total_force = triangle
+            .points
+            .iter()
+            .fold(Vector3::zero(), |sum, p| sum + p.accumulated_force);
+        assert!(total_force.z > 0.0);
+        assert!(total_force.x.abs() < 1e-5 && total_force.y.abs() < 1e-5);
+    }
+
+    #[test]
+    fn conveyor_drags_points() {
+        use crate::simulation::collidable_mesh::CollidableMesh;
+
+        let mut triangle = get_triangle();
+        // The triangle lies in the XY plane, falling onto a belt just behind it, which slides along Y.
+        let belt = CollidableMesh::new(
+            vec![
+                Vector3::new(-5.0, -5.0, -0.005),
+                Vector3::new(5.0, -5.0, -0.005),
+                Vector3::new(5.0, 5.0, -0.005),
+                Vector3::new(-5.0, 5.0, -0.005),
+            ],
+            vec![0, 1, 2, 0, 2, 3],
+        )
+        .with_surface_velocity(Vector3::unit_y());
+        for point in triangle.points.iter_mut() {
+            point.velocity = -Vector3::unit_z();
+        }
+        let mut new_points = triangle.points.clone();
+        for point in new_points.iter_mut() {
+            point.position.z -= 0.01;
+        }
+        let config = Config::default();
+        triangle.update_points(&mut new_points, &vec![belt], &config);
+        // Friction takes up to the coefficient of friction times the normal speed of the sliding, against the belt.
+        for point in triangle.points.iter() {
+            assert_relative_eq!(
+                point.velocity,
+                Vector3::new(
+                    0.0,
+                    config.coefficient_of_friction,
+                    config.coefficient_of_restitution
+                ),
+                epsilon = 1e-5
+            );
+        }
+    }
+
+    #[test]
+    fn one_sided_obstacles_push_points_out() {
+        use crate::simulation::collidable_mesh::{CollidableMesh, Sidedness};
+
+        // The triangle starts slightly inside the top of a cube, sinking into it.
+        let run = |sidedness: Sidedness| {
+            let (vertices, indices) = crate::simulation::geometry::get_cube_vertices();
+            let vertices = vertices
+                .into_iter()
+                .map(|vertex| vertex * 10.0 - Vector3::new(0.0, 0.0, 4.99))
+                .collect();
+            let cube = CollidableMesh::new(vertices, indices).with_sidedness(sidedness);
+            let mut triangle = get_triangle();
+            for point in triangle.points.iter_mut() {
+                point.velocity = -Vector3::unit_z();
+            }
+            let mut new_points = triangle.points.clone();
+            triangle.update_points(&mut new_points, &vec![cube], &Config::default());
+            triangle.points
+        };
+        // A one-sided cube pushes the points out through its top, the nearest face, and stops them sinking.
+        for point in run(Sidedness::OneSided).iter() {
+            assert!(
+                point.position.z > 0.01 && point.position.z < 0.011,
+                "{:?}",
+                point.position
+            );
+            assert_relative_eq!(point.velocity.z, 0.0);
+        }
+        // A two-sided one leaves them where they are.
+        for point in run(Sidedness::TwoSided).iter() {
+            assert_relative_eq!(point.position.z, 0.0);
+        }
+    }
+
     // TODO Torsional forces unit test with on obtuse angle between the faces
 
     // TODO possibly a unit test for torsional forces where the faces are co-planar?
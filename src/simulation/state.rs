@@ -1,23 +1,317 @@
-use crate::utils;
-use itertools::{izip, Itertools};
+use super::state_vector::StateVector;
+use crate::math::Real;
+use cgmath::{Matrix3, Quaternion, Vector3};
+use rayon::prelude::*;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Integration {
     Euler,
     Rk4,
+    /// Backward Euler, which stays stable for stiff systems at timesteps explicit integration blows up at,
+    /// at the cost of damping them.
+    ImplicitEuler,
+    /// Velocity Verlet, which is symplectic, so that oscillating systems neither gain nor lose energy over long
+    /// runs as they do by Euler and RK4. It needs the positions to come first in the state; see
+    /// Stateful::num_position_elements().
+    VelocityVerlet,
+    /// Leapfrog, i.e. symplectic Euler, where the velocities are taken to be half a step ahead of the positions.
+    /// It's as stable over long runs as velocity Verlet, for one fewer derivative each step.
+    Leapfrog,
+    /// Runge-Kutta-Fehlberg, which takes a fourth order step and estimates its error from an embedded fifth order
+    /// one, so that the timestep can adapt to the error; see AdaptiveTimestep.
+    Rk45,
 }
 
-pub trait Stateful {
-    /// Number of f32 elements that are used to represent this object in the State vector.
+impl Integration {
+    /// Whether stepping by the integration estimates its error.
+    pub fn estimates_error(&self) -> bool {
+        *self == Integration::Rk45
+    }
+}
+
+/// Bounds on a timestep adapted to RK45's error estimates, and the error each step aims for.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AdaptiveTimestep {
+    pub min_dt: f32,
+    pub max_dt: f32,
+    /// The error estimate each step aims for, relative to the magnitude of the state, or absolute where it's
+    /// below 1.
+    pub tolerance: f32,
+}
+
+impl AdaptiveTimestep {
+    /// The timestep's shrunk and grown by at most these factors at once, so that it settles rather than swinging.
+    const MIN_FACTOR: f32 = 0.2;
+    const MAX_FACTOR: f32 = 2.0;
+    /// The timestep aims a little under the tolerance, so that it isn't exceeded as soon as the error grows.
+    const SAFETY: f32 = 0.9;
+
+    /// The timestep to take after a step of the timestep with the error estimate. A fourth order step's error
+    /// grows with the fifth power of its timestep.
+    pub fn next_timestep(&self, timestep: f32, error: f32) -> f32 {
+        let factor = if error > 0.0 {
+            (AdaptiveTimestep::SAFETY * (self.tolerance / error).powf(0.2))
+                .clamp(AdaptiveTimestep::MIN_FACTOR, AdaptiveTimestep::MAX_FACTOR)
+        } else {
+            AdaptiveTimestep::MAX_FACTOR
+        };
+        (timestep * factor).clamp(self.min_dt, self.max_dt)
+    }
+}
+
+impl Default for AdaptiveTimestep {
+    fn default() -> Self {
+        AdaptiveTimestep {
+            min_dt: 1e-4,
+            max_dt: 1e-2,
+            tolerance: 1e-5,
+        }
+    }
+}
+
+/// The Runge-Kutta-Fehlberg tableau: the weights of each stage's predecessors' derivatives in its state.
+const RK45_STAGES: [&[Real]; 5] = [
+    &[1.0 / 4.0],
+    &[3.0 / 32.0, 9.0 / 32.0],
+    &[1932.0 / 2197.0, -7200.0 / 2197.0, 7296.0 / 2197.0],
+    &[439.0 / 216.0, -8.0, 3680.0 / 513.0, -845.0 / 4104.0],
+    &[
+        -8.0 / 27.0,
+        2.0,
+        -3544.0 / 2565.0,
+        1859.0 / 4104.0,
+        -11.0 / 40.0,
+    ],
+];
+/// The weights of the stages' derivatives in the fourth order step.
+const RK45_FOURTH_ORDER: [Real; 6] = [
+    25.0 / 216.0,
+    0.0,
+    1408.0 / 2565.0,
+    2197.0 / 4104.0,
+    -1.0 / 5.0,
+    0.0,
+];
+/// The differences of the fifth order weights from the fourth order ones, which estimate the step's error.
+const RK45_ERROR: [Real; 6] = [
+    1.0 / 360.0,
+    0.0,
+    -128.0 / 4275.0,
+    -2197.0 / 75240.0,
+    1.0 / 50.0,
+    2.0 / 55.0,
+];
+
+/// The step each element of the state is perturbed by, relative to its magnitude, to estimate the Jacobian
+/// of its derivative by finite differences.
+const FINITE_DIFFERENCE_STEP: Real = 1e-3;
+
+/// States with at least this many elements write and read their state vectors, and compute their derivatives,
+/// in parallel. Smaller ones aren't worth the cost of scheduling the work across threads.
+const PARALLEL_MIN_ELEMENTS: usize = 1024;
+/// Each thread takes at least this many elements at a time.
+const PARALLEL_CHUNK_ELEMENTS: usize = 256;
+
+/// An object which a State integrates. Only the fields which vary with time are packed into its state vector.
+/// The rest, e.g. its mass or the loads accumulated on it for the step, are left in place as the state vector
+/// is read back into it, so they needn't be integrated as zeros.
+/// Large states are integrated in parallel, so objects and their contexts are shared between threads.
+pub trait Stateful: Send + Sync {
+    /// What the derivative is computed from besides the object itself, e.g. the simulation's config.
+    type Context: Sync;
+
+    /// Number of Real elements that are used to represent this object in the State vector.
     fn num_state_elements() -> usize;
-    fn from_state_vector(state_data: Vec<f32>) -> Self;
-    fn derivative(&self) -> Vec<f32>;
-    fn as_state(&self) -> Vec<f32>;
+
+    /// Writes the integrated fields into the slice, which has num_state_elements() elements.
+    fn write_state(&self, state: &mut [Real]);
+
+    /// Writes the derivative of the integrated fields into the slice, which has num_state_elements() elements.
+    fn write_derivative(&self, context: &Self::Context, derivative: &mut [Real]);
+
+    /// Sets the integrated fields from the slice, leaving the rest of the object as it is.
+    fn read_state(&mut self, state: &[Real]);
+
+    /// How many of the leading elements of the state vector are positions, whose derivatives depend on the
+    /// elements after them, the velocities or momenta, whose derivatives are the forces. Velocity Verlet and
+    /// leapfrog step the two parts in turn. By default there are none, so that they step the whole state as
+    /// velocities.
+    fn num_position_elements() -> usize {
+        0
+    }
+}
+
+/// A value which is packed into a state vector as a fixed number of Real elements. Values of f32 and f64 can both be
+/// packed, so that objects can keep fields in f32 which needn't be integrated in double precision.
+pub trait StateElement: Sized {
+    const SIZE: usize;
+    /// Writes the value into exactly SIZE elements.
+    fn write(&self, state: &mut [Real]);
+    /// Reads the value from exactly SIZE elements.
+    fn read(state: &[Real]) -> Self;
+}
+
+macro_rules! state_elements {
+    ($($scalar:ty),+) => {
+        $(
+            impl StateElement for $scalar {
+                const SIZE: usize = 1;
+
+                fn write(&self, state: &mut [Real]) {
+                    state[0] = *self as Real;
+                }
+
+                fn read(state: &[Real]) -> Self {
+                    state[0] as $scalar
+                }
+            }
+
+            impl StateElement for Vector3<$scalar> {
+                const SIZE: usize = 3;
+
+                fn write(&self, state: &mut [Real]) {
+                    state.copy_from_slice(&[self.x as Real, self.y as Real, self.z as Real]);
+                }
+
+                fn read(state: &[Real]) -> Self {
+                    Vector3::new(state[0] as $scalar, state[1] as $scalar, state[2] as $scalar)
+                }
+            }
+
+            /// Packed as the vector part followed by the scalar part.
+            impl StateElement for Quaternion<$scalar> {
+                const SIZE: usize = 4;
+
+                fn write(&self, state: &mut [Real]) {
+                    self.v.write(&mut state[0..3]);
+                    self.s.write(&mut state[3..4]);
+                }
+
+                fn read(state: &[Real]) -> Self {
+                    Quaternion::from_sv(<$scalar>::read(&state[3..4]), Vector3::read(&state[0..3]))
+                }
+            }
+
+            /// Packed column by column.
+            impl StateElement for Matrix3<$scalar> {
+                const SIZE: usize = 9;
+
+                fn write(&self, state: &mut [Real]) {
+                    self.x.write(&mut state[0..3]);
+                    self.y.write(&mut state[3..6]);
+                    self.z.write(&mut state[6..9]);
+                }
+
+                fn read(state: &[Real]) -> Self {
+                    Matrix3::from_cols(
+                        Vector3::read(&state[0..3]),
+                        Vector3::read(&state[3..6]),
+                        Vector3::read(&state[6..9]),
+                    )
+                }
+            }
+        )+
+    };
+}
+state_elements!(f32, f64);
+
+/// Writes the state vector or derivative of a Stateful object one field at a time,
+/// so implementations don't need to index into the vector by hand.
+pub struct StateWriter<'a> {
+    state: &'a mut [Real],
+    len: usize,
 }
 
+impl<'a> StateWriter<'a> {
+    pub fn new(state: &'a mut [Real]) -> StateWriter<'a> {
+        StateWriter { state, len: 0 }
+    }
+
+    pub fn push<E: StateElement>(mut self, element: &E) -> StateWriter<'a> {
+        element.write(&mut self.state[self.len..self.len + E::SIZE]);
+        self.len += E::SIZE;
+        self
+    }
+
+    /// Writes zeros in place of an element, e.g. for the derivative of a constant field.
+    pub fn zeros<E: StateElement>(mut self) -> StateWriter<'a> {
+        self.state[self.len..self.len + E::SIZE].fill(0.0);
+        self.len += E::SIZE;
+        self
+    }
+
+    /// Panics if the elements pushed don't fill the state vector exactly.
+    pub fn finish(self) {
+        if self.len != self.state.len() {
+            panic!("Incorrect size of state vector!");
+        }
+    }
+}
+
+/// Reads the fields of a Stateful object back out of its state vector, in the order they were written.
+pub struct StateReader<'a> {
+    state: &'a [Real],
+}
+
+impl<'a> StateReader<'a> {
+    /// Panics if the state vector isn't the size of the Stateful type's state vector.
+    pub fn new<T: Stateful>(state: &'a [Real]) -> StateReader<'a> {
+        if state.len() != T::num_state_elements() {
+            panic!("State Vector incorrect size!")
+        }
+        StateReader { state }
+    }
+
+    pub fn read<E: StateElement>(&mut self) -> E {
+        let (element, rest) = self.state.split_at(E::SIZE);
+        self.state = rest;
+        E::read(element)
+    }
+}
+
+/// Implements Stateful for a struct from a list of its integrated fields and their types, in the order
+/// they're packed in the state vector, other than the Context and write_derivative(), which are written
+/// by hand, the latter with a StateWriter. Fields which aren't listed are left out of the state vector.
+/// The total size is checked against the given size at compile time.
+///
+/// ```ignore
+/// impl Stateful for Point {
+///     type Context = Config;
+///
+///     stateful_fields!(6; position: Vector3<f32>, velocity: Vector3<f32>);
+///
+///     fn write_derivative(&self, config: &Config, derivative: &mut [Real]) { ... }
+/// }
+/// ```
+macro_rules! stateful_fields {
+    ($size:expr; $($field:ident: $ty:ty),+ $(,)?) => {
+        fn num_state_elements() -> usize {
+            const SIZE: usize =
+                0 $(+ <$ty as $crate::simulation::state::StateElement>::SIZE)+;
+            const _: () = assert!(SIZE == $size, "Incorrect size of state vector!");
+            SIZE
+        }
+
+        fn write_state(&self, state: &mut [$crate::math::Real]) {
+            $crate::simulation::state::StateWriter::new(state)
+                $(.push::<$ty>(&self.$field))+
+                .finish()
+        }
+
+        fn read_state(&mut self, state: &[$crate::math::Real]) {
+            let mut reader = $crate::simulation::state::StateReader::new::<Self>(state);
+            $(self.$field = reader.read::<$ty>();)+
+        }
+    };
+}
+pub(crate) use stateful_fields;
+
+/// Identifies an element of a State for as long as it's in the State. Ids aren't reused.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ElementId(u64);
+
 // TODO We'd like for State to be able to contain some dyn Stateful type, instead of being over just one
-//      Stateful type. However, from_state_vector() makes that very difficult. The current solution would be
-//      to just have multiple State objects, one for each Stateful type.
+//      Stateful type. The current solution would be to just have multiple State objects, one for each Stateful type.
 
 /// For numerical integration, it's useful to easily get a State Vector S which represents a system's
 /// state as a vector of floats, as well as to get S', its derivative. Then, the integration can be
@@ -25,141 +319,517 @@ pub trait Stateful {
 /// It's then useful to be able to convert S_new back into a useful struct representation.
 /// The State struct allows for these operations for any physical system composed of a Vec of a Stateful type.
 ///
-/// A State should live one simulation step.
-/// First, create Vec<T> and perform preliminary calculations.
-/// Then create the State from the Vec<T> (giving State ownership of the Vec<T>)
-/// Then integrate to find S_new using derivative(), as_vector(), and from_state_vector().
-/// Finally, get the updated Vec<T> from S_new, destroying it.
-/// Repeat the next timestep.
+/// Stepping a State updates its elements in place. The State keeps its state vectors between steps,
+/// so a simulation which keeps its State (replacing its elements with elements_mut() as needed)
+/// only allocates on the first step, or when the number of elements grows.
+///
+/// Elements can be inserted and removed between steps, e.g. as an emitter spawns particles and they die, each
+/// identified by an ElementId which stays its own as the others come and go. A step integrates whichever elements
+/// there are as it begins, through all of its stages, so a changing population is safe to step with RK4.
 ///
 /// See "Foundations of Physically Based Modeling and Animation" by John C. Keyser
 /// and Donald H. House, 6.2 "Expanding the Concept of State" (page 87).
 pub struct State<T: Stateful> {
     elements: Vec<T>,
+    // The id of each element, in increasing order, since elements are only ever appended.
+    ids: Vec<ElementId>,
+    next_id: u64,
+    // Scratch buffers reused between steps.
+    state_vector: StateVector,
+    derivative: StateVector,
+    intermediate: StateVector,
+    accumulator: StateVector,
+    // The matrix I - h J of each element in turn when stepping implicitly, row by row.
+    jacobian: Vec<Real>,
+    // The derivative at each of RK45's stages.
+    stages: Vec<StateVector>,
 }
 
 impl<T: Stateful> State<T> {
     pub fn new(elements: Vec<T>) -> State<T> {
-        State { elements }
+        let mut state = State {
+            elements,
+            ids: Vec::new(),
+            next_id: 0,
+            state_vector: StateVector::zeros(0),
+            derivative: StateVector::zeros(0),
+            intermediate: StateVector::zeros(0),
+            accumulator: StateVector::zeros(0),
+            jacobian: Vec::new(),
+            stages: Vec::new(),
+        };
+        state.sync_ids();
+        state
     }
 
-    pub fn from_state_vector(state_vector: Vec<f32>) -> State<T> {
-        let elements = state_vector
-            .into_iter()
-            .chunks(T::num_state_elements())
-            .into_iter()
-            .map(|chunk| T::from_state_vector(chunk.collect_vec()))
-            .collect_vec();
-        State { elements }
+    /// The derivative of the elements' state vector given the context, written into the State's own buffer,
+    /// which is only reallocated if the elements have grown.
+    pub fn derivative(&mut self, context: &T::Context) -> &[Real] {
+        self.resize_buffers();
+        write_derivative(&self.elements, context, &mut self.derivative);
+        self.derivative.as_slice()
     }
 
-    pub fn derivative(&self) -> Vec<f32> {
-        self.elements
-            .iter()
-            .map(|e| -> Vec<f32> { e.derivative() })
-            .flatten()
-            .collect_vec()
+    /// The elements' state vector, written into the State's own buffer, which is only reallocated if the
+    /// elements have grown.
+    pub fn as_vector(&mut self) -> &[Real] {
+        self.resize_buffers();
+        write_state(&self.elements, &mut self.state_vector);
+        self.state_vector.as_slice()
     }
 
-    pub fn as_vector(&self) -> Vec<f32> {
-        self.elements
-            .iter()
-            .map(|e| e.as_state())
-            .flatten()
-            .collect_vec()
+    /// Steps the elements over the timestep, with their derivatives computed given the context.
+    /// Returns the step's error estimate, if the integration estimates it.
+    pub fn step(
+        &mut self,
+        integration: Integration,
+        timestep: f32,
+        context: &T::Context,
+    ) -> Option<f32> {
+        match integration {
+            Integration::Euler => self.euler_step(timestep, context),
+            Integration::Rk4 => self.rk4_step(timestep, context),
+            Integration::ImplicitEuler => self.implicit_euler_step(timestep, context),
+            Integration::VelocityVerlet => self.velocity_verlet_step(timestep, context),
+            Integration::Leapfrog => self.leapfrog_step(timestep, context),
+            Integration::Rk45 => return Some(self.rk45_step(timestep, context)),
+        }
+        None
     }
 
-    /// Performs first-order Euler integration on the State, returning the
-    /// next state.
+    /// Performs first-order Euler integration on the State, updating its elements.
     /// S_new = S + h * S'
-    pub fn euler_step(&self, timestep: f32) -> State<T> {
-        let state_delta = self
-            .derivative()
-            .into_iter()
-            .map(|x| x * timestep)
-            .collect_vec();
-        let new_state_vector = crate::utils::vec_add(&self.as_vector(), &state_delta);
-        State::from_state_vector(new_state_vector)
-    }
-
-    /// Performs one step of runge kutta fourth order integration, returning the next state.
-    pub fn rk4_step(&self, timestep: f32) -> State<T> {
-        let k1 = self.derivative();
-        let half_k1_delta = utils::scale(&k1, timestep * 0.5);
-        let k2 = State::<T>::from_state_vector(utils::vec_add(&self.as_vector(), &half_k1_delta))
-            .derivative();
-        let half_k2_delta = utils::scale(&k2, timestep * 0.5);
-        let k3 = State::<T>::from_state_vector(utils::vec_add(&self.as_vector(), &half_k2_delta))
-            .derivative();
-        let k3_delta = utils::scale(&k3, timestep);
-        let k4 = State::<T>::from_state_vector(utils::vec_add(&self.as_vector(), &k3_delta))
-            .derivative();
-        let delta = izip!(k1, k2, k3, k4)
-            .map(|(k1i, k2i, k3i, k4i)| {
-                timestep / 6.0 * k1i
-                    + timestep / 3.0 * k2i
-                    + timestep / 3.0 * k3i
-                    + timestep / 6.0 * k4i
-            })
-            .collect_vec();
-        State::from_state_vector(utils::vec_add(&self.as_vector(), &delta))
+    pub fn euler_step(&mut self, timestep: f32, context: &T::Context) {
+        self.resize_buffers();
+        write_state(&self.elements, &mut self.state_vector);
+        write_derivative(&self.elements, context, &mut self.derivative);
+        self.state_vector.axpy(timestep as Real, &self.derivative);
+        read_state(&mut self.elements, &self.state_vector);
+    }
+
+    /// Performs one step of runge kutta fourth order integration on the State, updating its elements.
+    pub fn rk4_step(&mut self, timestep: f32, context: &T::Context) {
+        self.resize_buffers();
+        write_state(&self.elements, &mut self.state_vector);
+        self.accumulator.copy_from(&self.state_vector);
+
+        // Each of k1..k4 is evaluated at an intermediate state, read into the elements,
+        // and accumulated into S_new = S + h/6 k1 + h/3 k2 + h/3 k3 + h/6 k4.
+        let timestep = timestep as Real;
+        let stages = [
+            (timestep / 6.0, timestep * 0.5),
+            (timestep / 3.0, timestep * 0.5),
+            (timestep / 3.0, timestep),
+        ];
+        for (weight, intermediate_timestep) in stages {
+            write_derivative(&self.elements, context, &mut self.derivative);
+            self.accumulator.axpy(weight, &self.derivative);
+            self.intermediate.copy_from(&self.state_vector);
+            self.intermediate
+                .axpy(intermediate_timestep, &self.derivative);
+            read_state(&mut self.elements, &self.intermediate);
+        }
+        write_derivative(&self.elements, context, &mut self.derivative);
+        self.accumulator.axpy(timestep / 6.0, &self.derivative);
+
+        read_state(&mut self.elements, &self.accumulator);
+    }
+
+    /// Performs one step of Runge-Kutta-Fehlberg integration on the State, updating its elements by the fourth order
+    /// step. Returns the step's error estimate, the largest difference of any element of the state from the fifth
+    /// order step's, relative to the element's magnitude where it's over 1.
+    pub fn rk45_step(&mut self, timestep: f32, context: &T::Context) -> f32 {
+        self.resize_buffers();
+        let len = self.state_vector.len();
+        self.stages
+            .resize_with(RK45_ERROR.len(), || StateVector::zeros(0));
+        for stage in self.stages.iter_mut() {
+            stage.resize(len);
+        }
+        write_state(&self.elements, &mut self.state_vector);
+        let timestep = timestep as Real;
+
+        write_derivative(&self.elements, context, &mut self.stages[0]);
+        for (index, weights) in RK45_STAGES.iter().enumerate() {
+            self.intermediate.copy_from(&self.state_vector);
+            for (weight, stage) in weights.iter().zip(self.stages.iter()) {
+                self.intermediate.axpy(timestep * weight, stage);
+            }
+            read_state(&mut self.elements, &self.intermediate);
+            write_derivative(&self.elements, context, &mut self.stages[index + 1]);
+        }
+
+        // The error's accumulated in the intermediate vector, now that the stages are done with it.
+        self.accumulator.copy_from(&self.state_vector);
+        self.intermediate.scale(0.0);
+        for ((fourth_order, error), stage) in RK45_FOURTH_ORDER
+            .iter()
+            .zip(RK45_ERROR.iter())
+            .zip(self.stages.iter())
+        {
+            self.accumulator.axpy(timestep * fourth_order, stage);
+            self.intermediate.axpy(timestep * error, stage);
+        }
+        read_state(&mut self.elements, &self.accumulator);
+
+        let error: Real = self
+            .intermediate
+            .as_slice()
+            .iter()
+            .zip(self.accumulator.as_slice())
+            .map(|(error, state)| error.abs() / state.abs().max(1.0))
+            .fold(0.0, Real::max);
+        error as f32
+    }
+
+    /// Performs one step of linearized backward Euler integration on the State, updating its elements. That's one
+    /// Newton step towards S_new = S + h * S'(S_new), solving (I - h J) dS = h * S' where J is the Jacobian of S'.
+    /// J is estimated by finite differences over each element's own state, so each element is implicit in itself,
+    /// but elements are only coupled through what's accumulated on them before the step. Stiff couplings between
+    /// elements, such as springs, need Jacobians of their own; see springy::implicit.
+    pub fn implicit_euler_step(&mut self, timestep: f32, context: &T::Context) {
+        self.resize_buffers();
+        let n = T::num_state_elements();
+        self.jacobian.resize(n * n, 0.0);
+        write_state(&self.elements, &mut self.state_vector);
+        write_derivative(&self.elements, context, &mut self.derivative);
+        let timestep = timestep as Real;
+
+        for (index, element) in self.elements.iter_mut().enumerate() {
+            let range = index * n..(index + 1) * n;
+            let state = &mut self.state_vector.as_mut_slice()[range.clone()];
+            let derivative = &self.derivative.as_slice()[range.clone()];
+            let perturbed_derivative = &mut self.intermediate.as_mut_slice()[range.clone()];
+            for column in 0..n {
+                let original = state[column];
+                let epsilon = FINITE_DIFFERENCE_STEP * original.abs().max(1.0);
+                state[column] = original + epsilon;
+                element.read_state(state);
+                element.write_derivative(context, perturbed_derivative);
+                state[column] = original;
+                for row in 0..n {
+                    let identity = if row == column { 1.0 } else { 0.0 };
+                    self.jacobian[row * n + column] = identity
+                        - timestep * (perturbed_derivative[row] - derivative[row]) / epsilon;
+                }
+            }
+
+            let step = &mut self.accumulator.as_mut_slice()[range];
+            for (step, derivative) in step.iter_mut().zip(derivative) {
+                *step = timestep * derivative;
+            }
+            if !solve_dense(&mut self.jacobian, step) {
+                // The element steps explicitly if its system is singular.
+                for (step, derivative) in step.iter_mut().zip(derivative) {
+                    *step = timestep * derivative;
+                }
+            }
+            for (state, step) in state.iter_mut().zip(step.iter()) {
+                *state += step;
+            }
+            element.read_state(state);
+        }
+    }
+
+    /// Performs one step of velocity Verlet integration on the State, updating its elements. The velocities are
+    /// kicked by half a step, the positions drift a whole step at those velocities, and then the velocities are
+    /// kicked by another half step, by the derivative at the new positions.
+    pub fn velocity_verlet_step(&mut self, timestep: f32, context: &T::Context) {
+        self.resize_buffers();
+        write_state(&self.elements, &mut self.state_vector);
+        let timestep = timestep as Real;
+        self.kick(timestep * 0.5, context);
+        self.drift(timestep, context);
+        self.kick(timestep * 0.5, context);
+    }
+
+    /// Performs one step of leapfrog integration on the State, updating its elements. The velocities are kicked
+    /// a whole step, and then the positions drift a whole step at the new velocities.
+    pub fn leapfrog_step(&mut self, timestep: f32, context: &T::Context) {
+        self.resize_buffers();
+        write_state(&self.elements, &mut self.state_vector);
+        let timestep = timestep as Real;
+        self.kick(timestep, context);
+        self.drift(timestep, context);
+    }
+
+    /// Steps the velocities of the state vector, which the elements are read from, by their derivative.
+    fn kick(&mut self, timestep: Real, context: &T::Context) {
+        write_derivative(&self.elements, context, &mut self.derivative);
+        axpy_elements::<T>(
+            &mut self.state_vector,
+            timestep,
+            &self.derivative,
+            T::num_position_elements()..T::num_state_elements(),
+        );
+        read_state(&mut self.elements, &self.state_vector);
+    }
+
+    /// Steps the positions of the state vector, which the elements are read from, by their derivative.
+    fn drift(&mut self, timestep: Real, context: &T::Context) {
+        write_derivative(&self.elements, context, &mut self.derivative);
+        axpy_elements::<T>(
+            &mut self.state_vector,
+            timestep,
+            &self.derivative,
+            0..T::num_position_elements(),
+        );
+        read_state(&mut self.elements, &self.state_vector);
+    }
+
+    pub fn elements(&self) -> &Vec<T> {
+        &self.elements
+    }
+
+    /// For replacing the elements between steps, e.g. with Vec::clone_from() so as not to reallocate.
+    /// The elements keep the ids of the elements they replace, by position, and any added get new ids as
+    /// the next step begins; use insert() and remove() for elements which should keep their own.
+    pub fn elements_mut(&mut self) -> &mut Vec<T> {
+        &mut self.elements
+    }
+
+    /// The ids of the elements, in the same order. Elements added through elements_mut() have none until the
+    /// next step begins.
+    pub fn ids(&self) -> &[ElementId] {
+        &self.ids[..self.ids.len().min(self.elements.len())]
+    }
+
+    /// Adds the element, to be integrated from the next step on.
+    pub fn insert(&mut self, element: T) -> ElementId {
+        self.sync_ids();
+        self.elements.push(element);
+        self.sync_ids();
+        *self.ids.last().unwrap()
+    }
+
+    /// Removes the element with the id, keeping the others in order.
+    pub fn remove(&mut self, id: ElementId) -> Option<T> {
+        self.sync_ids();
+        let index = self.index_of(id)?;
+        self.ids.remove(index);
+        Some(self.elements.remove(index))
+    }
+
+    /// Keeps only the elements for which the predicate's true, e.g. the particles which haven't died.
+    pub fn retain(&mut self, mut predicate: impl FnMut(&T) -> bool) {
+        self.sync_ids();
+        // Compacts the kept elements and their ids together, in place.
+        let mut kept = 0;
+        for index in 0..self.elements.len() {
+            if predicate(&self.elements[index]) {
+                self.elements.swap(kept, index);
+                self.ids.swap(kept, index);
+                kept += 1;
+            }
+        }
+        self.elements.truncate(kept);
+        self.ids.truncate(kept);
+    }
+
+    pub fn get(&self, id: ElementId) -> Option<&T> {
+        self.elements.get(self.index_of(id)?)
+    }
+
+    pub fn get_mut(&mut self, id: ElementId) -> Option<&mut T> {
+        let index = self.index_of(id)?;
+        self.elements.get_mut(index)
+    }
+
+    // The index of the element with the id, if it's among the elements. The ids past the elements, of elements
+    // removed through elements_mut() since the ids were last synced, are as good as dropped.
+    fn index_of(&self, id: ElementId) -> Option<usize> {
+        let index = self.ids.binary_search(&id).ok()?;
+        (index < self.elements.len()).then_some(index)
     }
 
     /// Drops self, returning the State as a Vec<T>.
-    /// Intended to be called at the end of a simulation step, where a new State will be made the next simulation step.
     pub fn get_elements(self) -> Vec<T> {
         self.elements
     }
+
+    // Gives any elements added through elements_mut() new ids, and drops the ids of any removed.
+    fn sync_ids(&mut self) {
+        self.ids.truncate(self.elements.len());
+        while self.ids.len() < self.elements.len() {
+            self.ids.push(ElementId(self.next_id));
+            self.next_id += 1;
+        }
+    }
+
+    fn resize_buffers(&mut self) {
+        self.sync_ids();
+        let len = self.elements.len() * T::num_state_elements();
+        self.state_vector.resize(len);
+        self.derivative.resize(len);
+        self.intermediate.resize(len);
+        self.accumulator.resize(len);
+    }
+}
+
+fn write_state<T: Stateful>(elements: &[T], state_vector: &mut StateVector) {
+    let n = T::num_state_elements();
+    let states = state_vector.as_mut_slice();
+    if elements.len() >= PARALLEL_MIN_ELEMENTS {
+        elements
+            .par_iter()
+            .zip(states.par_chunks_exact_mut(n))
+            .with_min_len(PARALLEL_CHUNK_ELEMENTS)
+            .for_each(|(element, state)| element.write_state(state));
+    } else {
+        for (element, state) in elements.iter().zip(states.chunks_exact_mut(n)) {
+            element.write_state(state);
+        }
+    }
+}
+
+fn write_derivative<T: Stateful>(
+    elements: &[T],
+    context: &T::Context,
+    derivative: &mut StateVector,
+) {
+    let n = T::num_state_elements();
+    let derivatives = derivative.as_mut_slice();
+    if elements.len() >= PARALLEL_MIN_ELEMENTS {
+        elements
+            .par_iter()
+            .zip(derivatives.par_chunks_exact_mut(n))
+            .with_min_len(PARALLEL_CHUNK_ELEMENTS)
+            .for_each(|(element, derivative)| element.write_derivative(context, derivative));
+    } else {
+        for (element, derivative) in elements.iter().zip(derivatives.chunks_exact_mut(n)) {
+            element.write_derivative(context, derivative);
+        }
+    }
+}
+
+/// Adds the derivative times the timestep to the range of each element's state, e.g. its positions.
+fn axpy_elements<T: Stateful>(
+    state_vector: &mut StateVector,
+    timestep: Real,
+    derivative: &StateVector,
+    range: std::ops::Range<usize>,
+) {
+    let n = T::num_state_elements();
+    for (state, derivative) in state_vector
+        .as_mut_slice()
+        .chunks_exact_mut(n)
+        .zip(derivative.as_slice().chunks_exact(n))
+    {
+        for (state, derivative) in state[range.clone()]
+            .iter_mut()
+            .zip(&derivative[range.clone()])
+        {
+            *state += timestep * derivative;
+        }
+    }
 }
 
+/// Solves the system of the square matrix, row by row, for the right hand side in place, by Gaussian elimination
+/// with partial pivoting, which leaves the matrix eliminated. Returns false if the matrix is singular.
+fn solve_dense(matrix: &mut [Real], rhs: &mut [Real]) -> bool {
+    let n = rhs.len();
+    for pivot in 0..n {
+        let Some(max_row) = (pivot..n).max_by(|a, b| {
+            matrix[a * n + pivot]
+                .abs()
+                .total_cmp(&matrix[b * n + pivot].abs())
+        }) else {
+            return false;
+        };
+        if matrix[max_row * n + pivot].abs() < Real::EPSILON {
+            return false;
+        }
+        if max_row != pivot {
+            for column in 0..n {
+                matrix.swap(pivot * n + column, max_row * n + column);
+            }
+            rhs.swap(pivot, max_row);
+        }
+        for row in pivot + 1..n {
+            let factor = matrix[row * n + pivot] / matrix[pivot * n + pivot];
+            for column in pivot..n {
+                matrix[row * n + column] -= factor * matrix[pivot * n + column];
+            }
+            rhs[row] -= factor * rhs[pivot];
+        }
+    }
+    for row in (0..n).rev() {
+        let sum: Real = (row + 1..n)
+            .map(|column| matrix[row * n + column] * rhs[column])
+            .sum();
+        rhs[row] = (rhs[row] - sum) / matrix[row * n + row];
+    }
+    true
+}
+
+fn read_state<T: Stateful>(elements: &mut [T], state_vector: &StateVector) {
+    let n = T::num_state_elements();
+    let states = state_vector.as_slice();
+    if elements.len() >= PARALLEL_MIN_ELEMENTS {
+        elements
+            .par_iter_mut()
+            .zip(states.par_chunks_exact(n))
+            .with_min_len(PARALLEL_CHUNK_ELEMENTS)
+            .for_each(|(element, state)| element.read_state(state));
+    } else {
+        for (element, state) in elements.iter_mut().zip(states.chunks_exact(n)) {
+            element.read_state(state);
+        }
+    }
+}
+
+#[cfg(test)]
 mod tests {
-    use cgmath::Vector3;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    use cgmath::{assert_relative_eq, Quaternion, Vector3};
 
-    use super::Stateful;
+    use super::{Integration, StateWriter, Stateful, PARALLEL_MIN_ELEMENTS};
+    use crate::math::Real;
 
     struct Point {
-        position: Vector3<f32>,
-        velocity: Vector3<f32>,
+        position: Vector3<Real>,
+        velocity: Vector3<Real>,
     }
 
     impl Stateful for Point {
+        // The acceleration.
+        type Context = Vector3<Real>;
+
         fn num_state_elements() -> usize {
             6
         }
 
-        fn from_state_vector(state_data: Vec<f32>) -> Self {
-            if state_data.len() != Self::num_state_elements() {
-                panic!("State Vector incorrect size!")
-            }
-            let position = Vector3::<f32>::new(state_data[0], state_data[1], state_data[2]);
-            let velocity = Vector3::<f32>::new(state_data[3], state_data[4], state_data[5]);
-            Point { position, velocity }
-        }
-
-        fn derivative(&self) -> Vec<f32> {
-            vec![
-                // The derivative of the positoin is just the velocity
+        fn write_state(&self, state: &mut [Real]) {
+            state.copy_from_slice(&[
+                self.position.x,
+                self.position.y,
+                self.position.z,
                 self.velocity.x,
                 self.velocity.y,
                 self.velocity.z,
-                // The accelerations.
-                // In a not-test implementation, Point would have some accumulated forces, torques, etc
-                // and calculate the acceleration from that data.
-                1.0,
-                -1.0,
-                0.0,
-            ]
+            ]);
         }
 
-        fn as_state(&self) -> Vec<f32> {
-            vec![
-                self.position.x,
-                self.position.y,
-                self.position.z,
+        fn write_derivative(&self, acceleration: &Vector3<Real>, derivative: &mut [Real]) {
+            derivative.copy_from_slice(&[
+                // The derivative of the position is just the velocity
                 self.velocity.x,
                 self.velocity.y,
                 self.velocity.z,
-            ]
+                acceleration.x,
+                acceleration.y,
+                acceleration.z,
+            ]);
+        }
+
+        fn read_state(&mut self, state: &[Real]) {
+            if state.len() != Self::num_state_elements() {
+                panic!("State Vector incorrect size!")
+            }
+            self.position = Vector3::<Real>::new(state[0], state[1], state[2]);
+            self.velocity = Vector3::<Real>::new(state[3], state[4], state[5]);
         }
     }
 
@@ -167,51 +837,48 @@ mod tests {
     fn euler_step() {
         let h = 0.5; // Timestep
         let points = vec![Point {
-            position: Vector3::<f32>::new(0.0, 0.0, 0.0),
-            velocity: Vector3::<f32>::new(0.0, 0.0, 1.0),
+            position: Vector3::<Real>::new(0.0, 0.0, 0.0),
+            velocity: Vector3::<Real>::new(0.0, 0.0, 1.0),
         }];
-        let state = super::State::new(points);
+        let mut state = super::State::new(points);
         let expected_initial_state = vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0];
         assert_eq!(expected_initial_state, state.as_vector());
 
-        let next_state = state.euler_step(h);
-        let new_points = next_state.get_elements();
+        state.euler_step(h, &Vector3::new(1.0, -1.0, 0.0));
+        let new_points = state.get_elements();
         let new_point = &new_points[0];
 
-        let expected_position = Vector3::<f32>::new(0.0, 0.0, 0.5);
-        let expected_velocity = Vector3::<f32>::new(0.5, -0.5, 1.0);
+        let expected_position = Vector3::<Real>::new(0.0, 0.0, 0.5);
+        let expected_velocity = Vector3::<Real>::new(0.5, -0.5, 1.0);
         assert_eq!(expected_position, new_point.position);
         assert_eq!(expected_velocity, new_point.velocity);
     }
 
     struct ExampleFn {
-        y: f32,
-        t: f32,
+        y: Real,
+        t: Real,
         timestep: f32,
     }
 
+    // The timestep isn't integrated, so it's left out of the state vector.
     impl Stateful for ExampleFn {
+        type Context = ();
+
         fn num_state_elements() -> usize {
-            3
+            2
         }
 
-        fn from_state_vector(state_data: Vec<f32>) -> Self {
-            if state_data.len() != Self::num_state_elements() {
-                panic!("State Vector incorrect size!")
-            }
-            ExampleFn {
-                y: state_data[0],
-                t: state_data[1],
-                timestep: state_data[2],
-            }
+        fn write_state(&self, state: &mut [Real]) {
+            state.copy_from_slice(&[self.y, self.t]);
         }
 
-        fn derivative(&self) -> Vec<f32> {
-            vec![self.y - f32::powi(self.t, 2) + 1.0, 1.0, 0.0]
+        fn write_derivative(&self, _: &(), derivative: &mut [Real]) {
+            derivative.copy_from_slice(&[self.y - Real::powi(self.t, 2) + 1.0, 1.0]);
         }
 
-        fn as_state(&self) -> Vec<f32> {
-            vec![self.y, self.t, self.timestep]
+        fn read_state(&mut self, state: &[Real]) {
+            self.y = state[0];
+            self.t = state[1];
         }
     }
 
@@ -223,8 +890,8 @@ mod tests {
             t: 0.0,
             timestep: h,
         }];
-        let state = super::State::new(ex);
-        let expected_initial_state = vec![0.5, 0.0, 0.5];
+        let mut state = super::State::new(ex);
+        let expected_initial_state = vec![0.5, 0.0];
         assert_eq!(expected_initial_state, state.as_vector());
 
         let acceptable_error = 0.005;
@@ -232,50 +899,434 @@ mod tests {
         // The exact solution is y = t^2 + 2t + 1 - .5e^t
 
         // Take the first step, t = 0.5
-        let state = state.rk4_step(h);
+        state.rk4_step(h, &());
         let new_state_vec = state.get_elements();
         let new_state_ex = &new_state_vec[0];
         assert!(
             1.425130208333333 + acceptable_error > new_state_ex.y
                 && 1.425130208333333 - acceptable_error < new_state_ex.y
         );
-        assert_eq!(0.5, new_state_ex.t);
+        assert_relative_eq!(0.5, new_state_ex.t);
         assert_eq!(0.5, new_state_ex.timestep);
 
         // Take the second step
-        let state = super::State::new(new_state_vec);
-        let state = state.rk4_step(h);
+        let mut state = super::State::new(new_state_vec);
+        state.rk4_step(h, &());
         let new_state_vec = state.get_elements();
         let new_state_ex = &new_state_vec[0];
         assert!(
             2.640859085770477 + acceptable_error > new_state_ex.y
                 && 2.640859085770477 - acceptable_error < new_state_ex.y
         );
-        assert_eq!(1.0, new_state_ex.t);
+        assert_relative_eq!(1.0, new_state_ex.t);
         assert_eq!(0.5, new_state_ex.timestep);
 
         // Third step
-        let state = super::State::new(new_state_vec);
-        let state = state.rk4_step(h);
+        let mut state = super::State::new(new_state_vec);
+        state.rk4_step(h, &());
         let new_state_vec = state.get_elements();
         let new_state_ex = &new_state_vec[0];
         assert!(
             4.009155464830968 + acceptable_error > new_state_ex.y
                 && 4.009155464830968 - acceptable_error < new_state_ex.y
         );
-        assert_eq!(1.5, new_state_ex.t);
+        assert_relative_eq!(1.5, new_state_ex.t);
         assert_eq!(0.5, new_state_ex.timestep);
 
         // Fourth step
-        let state = super::State::new(new_state_vec);
-        let state = state.rk4_step(h);
+        let mut state = super::State::new(new_state_vec);
+        state.rk4_step(h, &());
         let new_state_vec = state.get_elements();
         let new_state_ex = &new_state_vec[0];
         assert!(
             5.305471950534675 + acceptable_error > new_state_ex.y
                 && 5.305471950534675 - acceptable_error < new_state_ex.y
         );
-        assert_eq!(2.0, new_state_ex.t);
+        assert_relative_eq!(2.0, new_state_ex.t);
         assert_eq!(0.5, new_state_ex.timestep);
     }
+
+    /// Exponential decay at the rate, y' = -rate * y.
+    struct Decay {
+        y: f32,
+    }
+
+    impl Stateful for Decay {
+        type Context = f32;
+
+        super::stateful_fields!(1; y: f32);
+
+        fn write_derivative(&self, rate: &f32, derivative: &mut [Real]) {
+            StateWriter::new(derivative)
+                .push(&(-rate * self.y))
+                .finish()
+        }
+    }
+
+    #[test]
+    fn implicit_euler_step_is_stable_when_stiff() {
+        let (rate, h) = (1000.0, 0.1);
+        let mut explicit = super::State::new(vec![Decay { y: 1.0 }]);
+        let mut implicit = super::State::new(vec![Decay { y: 1.0 }]);
+        for _ in 0..10 {
+            explicit.step(Integration::Euler, h, &rate);
+            implicit.step(Integration::ImplicitEuler, h, &rate);
+        }
+        // Explicit steps multiply y by 1 - h * rate; implicit ones divide it by 1 + h * rate.
+        assert!(explicit.elements()[0].y.abs() > 1e10);
+        let expected = (1.0 + h * rate).powi(-10);
+        assert_relative_eq!(implicit.elements()[0].y, expected, max_relative = 0.01);
+    }
+
+    #[derive(Clone)]
+    struct Body {
+        mass: f32,
+        rotation: Quaternion<f32>,
+        velocity: Vector3<f32>,
+    }
+
+    impl Stateful for Body {
+        // The force on the body.
+        type Context = Vector3<f32>;
+
+        super::stateful_fields!(7; rotation: Quaternion<f32>, velocity: Vector3<f32>);
+
+        fn write_derivative(&self, force: &Vector3<f32>, derivative: &mut [Real]) {
+            StateWriter::new(derivative)
+                .zeros::<Quaternion<f32>>()
+                .push(&(force / self.mass))
+                .finish()
+        }
+    }
+
+    // Exact in f32, so that the derivatives it's packed into compare equal in f64 state vectors too.
+    const WEIGHT: Vector3<f32> = Vector3::new(0.0, -8.0, 0.0);
+
+    /// A unit mass on a spring, in one dimension.
+    struct Oscillator {
+        x: f32,
+        v: f32,
+    }
+
+    impl Stateful for Oscillator {
+        // The stiffness.
+        type Context = f32;
+
+        super::stateful_fields!(2; x: f32, v: f32);
+
+        fn write_derivative(&self, stiffness: &f32, derivative: &mut [Real]) {
+            StateWriter::new(derivative)
+                .push(&self.v)
+                .push(&(-stiffness * self.x))
+                .finish()
+        }
+
+        fn num_position_elements() -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn symplectic_steps_conserve_energy() {
+        let energy = |oscillator: &Oscillator| 0.5 * (oscillator.x.powi(2) + oscillator.v.powi(2));
+        let energy_after = |integration| {
+            let mut state = super::State::new(vec![Oscillator { x: 1.0, v: 0.0 }]);
+            for _ in 0..1000 {
+                state.step(integration, 0.1, &1.0);
+            }
+            energy(&state.elements()[0])
+        };
+        // Euler gains energy every step, spiralling outward.
+        assert!(energy_after(Integration::Euler) > 1.0);
+        // The symplectic steps' energy oscillates about the initial energy, by more for leapfrog, whose
+        // velocities are half a step off.
+        assert_relative_eq!(
+            energy_after(Integration::VelocityVerlet),
+            0.5,
+            epsilon = 0.005
+        );
+        assert_relative_eq!(energy_after(Integration::Leapfrog), 0.5, epsilon = 0.05);
+    }
+
+    #[test]
+    fn rk45_error_adapts_timestep() {
+        let adaptive = super::AdaptiveTimestep {
+            min_dt: 1e-4,
+            max_dt: 1.0,
+            tolerance: 1e-6,
+        };
+        let mut state = super::State::new(vec![Oscillator { x: 1.0, v: 0.0 }]);
+        let (mut time, mut dt) = (0.0, 0.5);
+        let mut dts = Vec::new();
+        while time < 10.0 {
+            let error = state.step(Integration::Rk45, dt, &1.0).unwrap();
+            time += dt;
+            dt = adaptive.next_timestep(dt, error);
+            dts.push(dt);
+        }
+        // The first step's too large for the tolerance, so the timestep shrinks to meet it, and the oscillator
+        // follows x = cos(t) closely.
+        assert!(dts[0] < 0.5);
+        assert_relative_eq!(state.elements()[0].x, time.cos(), epsilon = 1e-3);
+        // A stiffer oscillator needs smaller steps.
+        let mut stiff = super::State::new(vec![Oscillator { x: 1.0, v: 0.0 }]);
+        let error = stiff.step(Integration::Rk45, 0.1, &100.0).unwrap();
+        assert!(adaptive.next_timestep(0.1, error) < dts[dts.len() - 1]);
+        assert_eq!(adaptive.next_timestep(0.1, 1e3), 0.1 * 0.2);
+        assert_eq!(adaptive.next_timestep(0.9, 0.0), 1.0);
+    }
+
+    #[test]
+    fn large_states_step_in_parallel() {
+        let oscillator = |i: usize| Oscillator {
+            x: (i % 7) as f32,
+            v: (i % 5) as f32,
+        };
+        let count = 4 * PARALLEL_MIN_ELEMENTS + 3;
+        let mut state = super::State::new((0..count).map(oscillator).collect());
+        for _ in 0..10 {
+            state.step(Integration::Rk4, 0.1, &4.0);
+        }
+        for (i, stepped) in state.elements().iter().enumerate() {
+            let mut alone = super::State::new(vec![oscillator(i)]);
+            for _ in 0..10 {
+                alone.step(Integration::Rk4, 0.1, &4.0);
+            }
+            assert_eq!(alone.elements()[0].x, stepped.x);
+            assert_eq!(alone.elements()[0].v, stepped.v);
+        }
+    }
+
+    #[test]
+    fn stateful_fields() {
+        let body = Body {
+            mass: 2.0,
+            rotation: Quaternion::new(4.0, 1.0, 2.0, 3.0),
+            velocity: Vector3::new(5.0, 6.0, 7.0),
+        };
+        assert_eq!(7, Body::num_state_elements());
+        let mut state = [0.0; 7];
+        body.write_state(&mut state);
+        assert_eq!([1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0], state);
+
+        let mut read = Body {
+            mass: 3.0,
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        };
+        read.read_state(&state);
+        // The mass isn't in the state vector, so reading it back leaves it be.
+        assert_eq!(3.0, read.mass);
+        assert_eq!(Quaternion::new(4.0, 1.0, 2.0, 3.0), read.rotation);
+        assert_eq!(Vector3::new(5.0, 6.0, 7.0), read.velocity);
+        let mut derivative = [1.0; 7];
+        body.write_derivative(&WEIGHT, &mut derivative);
+        assert_eq!([0.0, 0.0, 0.0, 0.0, 0.0, -4.0, 0.0], derivative);
+    }
+
+    #[test]
+    #[should_panic]
+    fn state_writer_incorrect_size() {
+        StateWriter::new(&mut [0.0; 8])
+            .zeros::<Vector3<f32>>()
+            .finish();
+    }
+
+    #[test]
+    #[should_panic]
+    fn state_reader_incorrect_size() {
+        bodies()[0].read_state(&[0.0; 6]);
+    }
+
+    // Counts the allocations made on each thread, since tests run in parallel.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn count_allocations<R>(f: impl FnOnce() -> R) -> usize {
+        let before = ALLOCATIONS.with(|allocations| allocations.get());
+        f();
+        ALLOCATIONS.with(|allocations| allocations.get()) - before
+    }
+
+    // Few enough to step serially, since rayon allocates as it schedules work across threads.
+    fn bodies() -> Vec<Body> {
+        (0..1000)
+            .map(|i| Body {
+                mass: 1.0,
+                rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                velocity: Vector3::new(i as f32, 0.0, 0.0),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn step_without_allocating() {
+        for integration in [
+            Integration::Euler,
+            Integration::Rk4,
+            Integration::VelocityVerlet,
+            Integration::Leapfrog,
+            Integration::Rk45,
+        ] {
+            let mut state = super::State::new(bodies());
+            // The first step allocates the state vectors.
+            assert!(count_allocations(|| state.step(integration, 0.5, &WEIGHT)) > 0);
+            assert_eq!(
+                0,
+                count_allocations(|| {
+                    for _ in 0..10 {
+                        state.step(integration, 0.5, &WEIGHT);
+                    }
+                })
+            );
+            let body = &state.elements()[10];
+            assert_relative_eq!(
+                Vector3::new(10.0, -8.0 * 0.5 * 11.0, 0.0),
+                body.velocity,
+                epsilon = 0.001
+            );
+        }
+    }
+
+    #[test]
+    fn views_without_allocating() {
+        let mut state = super::State::new(bodies());
+        state.step(Integration::Euler, 0.5, &WEIGHT);
+        assert_eq!(
+            0,
+            count_allocations(|| {
+                assert_eq!(7000, state.as_vector().len());
+                let derivative = state.derivative(&WEIGHT);
+                assert_eq!([0.0, 0.0, 0.0, 0.0, 0.0, -8.0, 0.0], derivative[7..14]);
+            })
+        );
+    }
+
+    #[test]
+    fn step_with_replaced_elements_without_allocating() {
+        let mut state = super::State::new(bodies());
+        state.step(Integration::Rk4, 0.5, &WEIGHT);
+        let replacement = bodies();
+        assert_eq!(
+            0,
+            count_allocations(|| {
+                state.elements_mut().clone_from(&replacement);
+                state.step(Integration::Rk4, 0.5, &WEIGHT);
+            })
+        );
+    }
+
+    /// A value creeping up at a rate too slow for f32 to resolve against the value.
+    struct Creep {
+        y: Real,
+    }
+
+    impl Stateful for Creep {
+        type Context = ();
+
+        super::stateful_fields!(1; y: Real);
+
+        fn write_derivative(&self, _: &(), derivative: &mut [Real]) {
+            derivative[0] = 1e-8;
+        }
+    }
+
+    #[test]
+    fn integrates_in_real_precision() {
+        let mut state = super::State::new(vec![Creep { y: 1.0 }]);
+        for _ in 0..1000 {
+            state.euler_step(1.0, &());
+        }
+        let y = state.elements()[0].y;
+        if cfg!(feature = "f64") {
+            assert_relative_eq!(1.0 + 1e-5, y, epsilon = 1e-9);
+        } else {
+            // Each step's change rounds away.
+            assert_eq!(1.0, y);
+        }
+    }
+
+    #[test]
+    fn step_while_inserting_and_removing() {
+        let body = |x: f32| Body {
+            mass: 1.0,
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            velocity: Vector3::new(x, 0.0, 0.0),
+        };
+        let mut state = super::State::new(vec![body(0.0), body(1.0)]);
+        let first = state.ids()[0];
+        state.step(Integration::Rk4, 0.5, &WEIGHT);
+        let inserted = state.insert(body(2.0));
+        let removed = state.ids()[1];
+        assert_eq!(Some(1.0), state.remove(removed).map(|b| b.velocity.x));
+        state.step(Integration::Rk4, 0.5, &WEIGHT);
+        state.retain(|body| body.velocity.x != 0.0);
+        state.step(Integration::Rk4, 0.5, &WEIGHT);
+
+        // Each body falls from when it's inserted, whatever else comes and goes.
+        assert!(state.get(first).is_none());
+        assert_eq!(&[inserted], state.ids());
+        let body = state.get(inserted).unwrap().clone();
+        assert_eq!(2.0, body.velocity.x);
+        assert_relative_eq!(-8.0, body.velocity.y);
+
+        // Elements added through elements_mut() get their ids as the next step begins.
+        state.elements_mut().push(body);
+        state.step(Integration::Rk4, 0.5, &WEIGHT);
+        assert_eq!(2, state.ids().len());
+        assert!(state.ids()[1] > inserted);
+    }
+
+    #[test]
+    fn get_agrees_with_get_mut_as_the_count_changes() {
+        let body = |x: f32| Body {
+            mass: 1.0,
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            velocity: Vector3::new(x, 0.0, 0.0),
+        };
+        let mut state = super::State::new(vec![body(0.0), body(1.0), body(2.0)]);
+        let ids = state.ids().to_vec();
+
+        // Elements dropped through elements_mut() are gone to get() at once, as they are to get_mut().
+        state.elements_mut().pop();
+        assert!(state.get(ids[2]).is_none());
+        assert_eq!(&ids[..2], state.ids());
+        state.step(Integration::Rk4, 0.5, &WEIGHT);
+        assert!(state.get(ids[2]).is_none());
+        assert_eq!(Some(1.0), state.get(ids[1]).map(|b| b.velocity.x));
+
+        // Added, they're found from the step which gives them ids.
+        state.elements_mut().push(body(3.0));
+        assert_eq!(2, state.ids().len());
+        state.step(Integration::Rk4, 0.5, &WEIGHT);
+        let added = *state.ids().last().unwrap();
+        assert_eq!(Some(3.0), state.get(added).map(|b| b.velocity.x));
+        assert_eq!(
+            state.get(added).map(|b| b.velocity),
+            state.get_mut(added).map(|b| b.velocity)
+        );
+        assert!(state.get(ids[2]).is_none());
+    }
 }
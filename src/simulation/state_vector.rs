@@ -0,0 +1,249 @@
+use crate::math::Real;
+
+use rayon::prelude::*;
+
+/// The number of elements per block; 8 Reals fill a 256 bit register, and 8 f64s a 512 bit one.
+pub const LANES: usize = 8;
+/// Vectors with at least this many elements are operated on in parallel. Smaller ones aren't worth the cost of
+/// scheduling the work across threads.
+const PARALLEL_MIN_LEN: usize = 8192;
+/// Each thread takes a whole number of blocks at a time, so that its loop stays aligned.
+const PARALLEL_CHUNK_LEN: usize = 512 * LANES;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[repr(C, align(32))]
+struct Block([Real; LANES]);
+
+/// A state vector S, as integrated by state::State, in contiguous aligned storage.
+/// The elements are stored in whole, aligned blocks so that each arithmetic operation is a
+/// simple loop the compiler vectorizes. std::simd would make this explicit, but isn't
+/// available on stable.
+/// The final block is padded with zeros, which are never exposed through as_slice().
+/// The elements are in the order the State's elements wrote them, each element's state contiguous, since
+/// Stateful types pack their states into contiguous slices; they aren't split into a structure of arrays.
+/// Large vectors are operated on in parallel, each thread taking whole blocks at a time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateVector {
+    blocks: Vec<Block>,
+    len: usize,
+}
+
+impl StateVector {
+    pub fn zeros(len: usize) -> StateVector {
+        StateVector {
+            blocks: vec![Block::default(); len.div_ceil(LANES)],
+            len,
+        }
+    }
+
+    /// An empty state vector which can be extended to the given length without reallocating.
+    pub fn with_capacity(capacity: usize) -> StateVector {
+        StateVector {
+            blocks: Vec::with_capacity(capacity.div_ceil(LANES)),
+            len: 0,
+        }
+    }
+
+    pub fn from_slice(elements: &[Real]) -> StateVector {
+        let mut state_vector = StateVector::with_capacity(elements.len());
+        state_vector.extend_from_slice(elements);
+        state_vector
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn extend_from_slice(&mut self, elements: &[Real]) {
+        let len = self.len;
+        let new_len = len + elements.len();
+        self.blocks
+            .resize(new_len.div_ceil(LANES), Block::default());
+        self.as_all_elements_mut()[len..new_len].copy_from_slice(elements);
+        self.len = new_len;
+    }
+
+    /// Sets the length of the vector, filling any new elements with zeros.
+    /// Only reallocates if the vector grows beyond the capacity it's had so far.
+    pub fn resize(&mut self, len: usize) {
+        if len < self.len {
+            // Keep the padding in the final block zeroed.
+            let old_len = self.len;
+            self.as_all_elements_mut()[len..old_len].fill(0.0);
+        }
+        self.blocks.resize(len.div_ceil(LANES), Block::default());
+        self.len = len;
+    }
+
+    /// Copies the elements of the other vector into this one, without reallocating.
+    /// Panics if the vectors are of different lengths.
+    pub fn copy_from(&mut self, other: &StateVector) {
+        if self.len != other.len {
+            panic!("Cannot copy state vectors of different lengths!");
+        }
+        self.blocks.copy_from_slice(&other.blocks);
+    }
+
+    pub fn as_slice(&self) -> &[Real] {
+        &self.as_all_elements()[..self.len]
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [Real] {
+        let len = self.len;
+        &mut self.as_all_elements_mut()[..len]
+    }
+
+    /// self += a * x
+    /// Panics if the vectors are of different lengths.
+    pub fn axpy(&mut self, a: Real, x: &StateVector) {
+        if self.len != x.len {
+            panic!("Cannot add state vectors of different lengths!");
+        }
+        // Iterating over the whole blocks, rather than just the first len elements, lets the
+        // compiler use aligned vector loads without handling a remainder.
+        let axpy = |y: &mut [Real], x: &[Real]| {
+            for (y, x) in y.iter_mut().zip(x.iter()) {
+                *y += a * x;
+            }
+        };
+        if self.len >= PARALLEL_MIN_LEN {
+            self.as_all_elements_mut()
+                .par_chunks_mut(PARALLEL_CHUNK_LEN)
+                .zip(x.as_all_elements().par_chunks(PARALLEL_CHUNK_LEN))
+                .for_each(|(y, x)| axpy(y, x));
+        } else {
+            axpy(self.as_all_elements_mut(), x.as_all_elements());
+        }
+    }
+
+    /// self *= a
+    pub fn scale(&mut self, a: Real) {
+        let scale = |elements: &mut [Real]| {
+            for element in elements.iter_mut() {
+                *element *= a;
+            }
+        };
+        if self.len >= PARALLEL_MIN_LEN {
+            self.as_all_elements_mut()
+                .par_chunks_mut(PARALLEL_CHUNK_LEN)
+                .for_each(scale);
+        } else {
+            scale(self.as_all_elements_mut());
+        }
+    }
+
+    // Includes the padding in the final block.
+    fn as_all_elements(&self) -> &[Real] {
+        // Safe since Block is repr(C) and made up only of Reals, with no padding between them.
+        unsafe {
+            std::slice::from_raw_parts(
+                self.blocks.as_ptr() as *const Real,
+                self.blocks.len() * LANES,
+            )
+        }
+    }
+
+    fn as_all_elements_mut(&mut self) -> &mut [Real] {
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.blocks.as_mut_ptr() as *mut Real,
+                self.blocks.len() * LANES,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::{StateVector, LANES, PARALLEL_MIN_LEN};
+    use crate::math::Real;
+
+    #[test]
+    fn from_slice() {
+        let elements = (0..11).map(|i| i as Real).collect_vec();
+        let state_vector = StateVector::from_slice(&elements);
+        assert_eq!(11, state_vector.len());
+        assert_eq!(elements, state_vector.as_slice());
+        assert_eq!(2, state_vector.blocks.len());
+        assert_eq!(0, state_vector.as_slice().as_ptr() as usize % 32);
+    }
+
+    #[test]
+    fn axpy_matches_scalar() {
+        // Not a multiple of LANES, so the padded final block is exercised.
+        let len = 3 * LANES + 5;
+        let x = (0..len).map(|i| i as Real * 0.5).collect_vec();
+        let y = (0..len).map(|i| 10.0 - i as Real).collect_vec();
+        let expected = x
+            .iter()
+            .zip(y.iter())
+            .map(|(x, y)| y + 2.0 * x)
+            .collect_vec();
+
+        let mut result = StateVector::from_slice(&y);
+        result.axpy(2.0, &StateVector::from_slice(&x));
+        assert_eq!(expected, result.as_slice());
+
+        result.scale(0.5);
+        assert_eq!(
+            expected.iter().map(|e| e * 0.5).collect_vec(),
+            result.as_slice()
+        );
+    }
+
+    #[test]
+    fn parallel_axpy_matches_scalar() {
+        let len = 3 * PARALLEL_MIN_LEN + 5;
+        let x = (0..len).map(|i| (i % 100) as Real).collect_vec();
+        let expected = x.iter().map(|x| 0.5 * (1.0 + 2.0 * x)).collect_vec();
+
+        let mut result = StateVector::from_slice(&vec![1.0; len]);
+        result.axpy(2.0, &StateVector::from_slice(&x));
+        result.scale(0.5);
+        assert_eq!(expected, result.as_slice());
+    }
+
+    #[test]
+    #[should_panic]
+    fn axpy_different_lengths() {
+        StateVector::zeros(4).axpy(1.0, &StateVector::zeros(5));
+    }
+
+    /// Compares axpy against a scalar loop over Vec<Real> on a 100k element state vector.
+    /// Run with `cargo test --release bench_axpy -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_axpy() {
+        const LEN: usize = 100_000;
+        const ITERATIONS: u32 = 1000;
+        let x = (0..LEN).map(|i| i as Real).collect_vec();
+
+        let mut y = vec![0.0; LEN];
+        let start = std::time::Instant::now();
+        for _ in 0..ITERATIONS {
+            y = y
+                .iter()
+                .zip(x.iter())
+                .map(|(y, x)| y + 0.001 * x)
+                .collect_vec();
+        }
+        let scalar = start.elapsed() / ITERATIONS;
+
+        let x = StateVector::from_slice(&x);
+        let mut y_blocks = StateVector::zeros(LEN);
+        let start = std::time::Instant::now();
+        for _ in 0..ITERATIONS {
+            y_blocks.axpy(0.001, std::hint::black_box(&x));
+        }
+        let blocks = start.elapsed() / ITERATIONS;
+
+        assert_eq!(y, y_blocks.as_slice());
+        println!("scalar: {:?}, blocks: {:?}", scalar, blocks);
+    }
+}
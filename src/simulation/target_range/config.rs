@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use crate::simulation::rigidbody::contact::CONTACT_ITERATIONS;
+use crate::simulation::solver::SolverBudget;
+
+/// How the blocks are stacked on the platform.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Layout {
+    /// Three towers of cubes.
+    Towers,
+    /// A pyramid of cubes.
+    Pyramid,
+    /// A wall of bricks, with each course offset from the one below.
+    Wall,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Layout::Towers, Layout::Pyramid, Layout::Wall];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Towers => "Towers",
+            Layout::Pyramid => "Pyramid",
+            Layout::Wall => "Wall",
+        }
+    }
+}
+
+/// Still bodies fall asleep, and stop being simulated until something knocks them, which keeps stacks
+/// from jittering and sliding apart while nothing's touching them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SleepConfig {
+    /// Bodies slower than this, in m/s, and spinning slower than this in rad/s, are still.
+    pub speed: f32,
+    /// The seconds a body must be still to fall asleep.
+    pub time: f32,
+}
+
+impl Default for SleepConfig {
+    fn default() -> Self {
+        Self {
+            speed: 0.05,
+            time: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub layout: Layout,
+    pub dt: f32, // Seconds as f32
+    /// The acceleration of gravity, in m/s².
+    pub gravity: f32,
+    /// The launch speed of the projectile per meter the slingshot's pulled back, in 1/s.
+    pub launch_strength: f32,
+    /// The mass of each projectile, in kg.
+    pub projectile_mass: f32,
+    /// The mass of each block, in kg.
+    pub block_mass: f32,
+    /// The quadratic air drag on the bodies, per m² they present to the air, in N·s²/m⁴.
+    pub drag: f32,
+    pub contact_restitution: f32,
+    pub contact_friction: f32,
+    /// The fraction of each contact's overlap that's corrected each step.
+    pub contact_position_correction: f32,
+    /// The contact solver's iterations, tolerance, and time budget each step.
+    pub contact_solver: SolverBudget,
+    /// Stacks start asleep if set, so they stand still until they're hit.
+    pub sleep: Option<SleepConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            layout: Layout::Towers,
+            dt: Duration::from_millis(1).as_secs_f32(),
+            gravity: 9.8,
+            launch_strength: 10.0,
+            projectile_mass: 4.0,
+            block_mass: 1.0,
+            drag: 0.0,
+            contact_restitution: 0.1,
+            contact_friction: 0.6,
+            contact_position_correction: 0.8,
+            contact_solver: SolverBudget::new(CONTACT_ITERATIONS),
+            sleep: Some(SleepConfig::default()),
+        }
+    }
+}
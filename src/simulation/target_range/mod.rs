@@ -0,0 +1,679 @@
+/// A target range: stacks of blocks on a platform, knocked down by projectiles launched from a slingshot.
+/// Blocks collide with each other, the projectiles, and the fixed ground and platform with box contacts.
+/// Still bodies fall asleep, and each block knocked off the platform onto the ground scores once,
+/// when it enters the trigger volume over the ground. A stopwatch times the projectiles' flights between
+/// two gates across the range, and the most recent projectile's flight is recorded, so that it can be
+/// compared against a rerun of it.
+pub mod config;
+
+use self::config::{Config, Layout};
+use crate::pool::Pool;
+use crate::simulation::configurable::Configurable;
+use crate::simulation::fitting::Trajectory;
+use crate::simulation::measure::Stopwatch;
+use crate::simulation::quantity;
+use crate::simulation::rigidbody::{
+    contact::{self, ContactBody, ContactMaterial, Cuboid},
+    rigidbody::RigidBody,
+    simulation::{RigidBodyHandle, Simulation as RigidBodySimulation},
+};
+use crate::simulation::solver::SolverStats;
+use crate::simulation::trigger::{Trigger, TriggerAction, TriggerShape};
+
+use cgmath::{InnerSpace, Vector3, Zero};
+use std::time::Duration;
+
+/// The ground's top is at y = 0, and it extends this far either way along x and z.
+pub const GROUND_HALF_EXTENTS: Vector3<f32> = Vector3::new(15.0, 0.5, 4.0);
+/// The platform the blocks are stacked on, standing on the ground.
+pub const PLATFORM_CENTER: Vector3<f32> = Vector3::new(5.0, 0.25, 0.0);
+pub const PLATFORM_DIMENSIONS: Vector3<f32> = Vector3::new(4.5, 0.5, 2.0);
+/// Where the slingshot's pouch rests, and how far back it can be pulled.
+pub const SLINGSHOT: Vector3<f32> = Vector3::new(-5.0, 1.5, 0.0);
+pub const MAX_PULL: f32 = 1.5;
+/// Where the stopwatch's gates are at first, along x: at the slingshot, and at the near edge of the platform.
+pub const START_GATE_X: f32 = SLINGSHOT.x;
+pub const STOP_GATE_X: f32 = PLATFORM_CENTER.x - PLATFORM_DIMENSIONS.x / 2.0;
+
+const CUBE_DIMENSIONS: Vector3<f32> = Vector3::new(0.4, 0.4, 0.4);
+const BRICK_DIMENSIONS: Vector3<f32> = Vector3::new(0.6, 0.3, 0.3);
+/// The space left between neighboring blocks, so that they don't start touching.
+const BLOCK_GAP: f32 = 0.01;
+pub const PROJECTILE_DIMENSIONS: Vector3<f32> = Vector3::new(0.25, 0.25, 0.25);
+/// The oldest projectile is removed to launch another once there are this many.
+pub const MAX_PROJECTILES: usize = 3;
+/// A projectile's flight is sampled this often, for this long after its launch.
+const FLIGHT_SAMPLE_INTERVAL: f32 = 1.0 / 30.0;
+pub const MAX_FLIGHT_DURATION: f32 = 4.0;
+const ANGULAR_DRAG: f32 = 0.01;
+
+/// Blocks whose centers are below this have been knocked off the platform onto the ground.
+const SCORE_HEIGHT: f32 = 0.45;
+/// Bodies which fall off the edge of the ground are removed once they're this far below it.
+const FALL_DEPTH: f32 = 5.0;
+/// Sleeping bodies within this of an awake body moving faster than the sleep speed are woken.
+const WAKE_MARGIN: f32 = 0.02;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BodyKind {
+    Block,
+    Projectile,
+}
+
+/// The path of a projectile from its launch, sampled until it's removed or it's flown for the max duration.
+pub struct Flight {
+    handle: RigidBodyHandle,
+    // The seconds since the launch, while it's recording.
+    time: f32,
+    trajectory: Trajectory,
+    recording: bool,
+}
+
+impl Flight {
+    /// The times of the trajectory are the seconds since the launch.
+    pub fn get_trajectory(&self) -> &Trajectory {
+        &self.trajectory
+    }
+
+    /// The seconds since the launch, up to the end of the recording.
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+}
+
+/// A block or projectile, and whether it's asleep.
+#[derive(Debug, Copy, Clone)]
+pub struct Body {
+    pub handle: RigidBodyHandle,
+    pub kind: BodyKind,
+    pub asleep: bool,
+    // The seconds the body's been still for, while it's awake.
+    still_time: f32,
+    scored: bool,
+}
+
+impl Body {
+    fn new(handle: RigidBodyHandle, kind: BodyKind, asleep: bool) -> Body {
+        Body {
+            handle,
+            kind,
+            asleep,
+            still_time: 0.0,
+            scored: false,
+        }
+    }
+}
+
+/// Whether the rigidbody is moving and spinning slower than the speed.
+fn is_still(rigidbody: &RigidBody, speed: f32) -> bool {
+    rigidbody.get_state().velocity().magnitude() < speed
+        && rigidbody.get_angular_velocity().magnitude() < speed
+}
+
+/// The centers and dimensions of the blocks in the layout, stacked on the platform.
+fn get_block_boxes(layout: Layout) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+    let top = PLATFORM_CENTER.y + PLATFORM_DIMENSIONS.y / 2.0;
+    // The center of the block in the row, of the given number of blocks centered on the platform, and the course.
+    let place = |dimensions: Vector3<f32>, count: usize, index: usize, course: usize| {
+        let spacing = dimensions.x + BLOCK_GAP;
+        Vector3::new(
+            PLATFORM_CENTER.x + (index as f32 - (count - 1) as f32 / 2.0) * spacing,
+            top + dimensions.y / 2.0 + course as f32 * (dimensions.y + BLOCK_GAP),
+            0.0,
+        )
+    };
+    match layout {
+        Layout::Towers => [-1.2, 0.0, 1.2]
+            .into_iter()
+            .flat_map(|x| {
+                (0..4).map(move |course| {
+                    let mut center = place(CUBE_DIMENSIONS, 1, 0, course);
+                    center.x += x;
+                    (center, CUBE_DIMENSIONS)
+                })
+            })
+            .collect(),
+        Layout::Pyramid => (0..5)
+            .flat_map(|course| {
+                let count = 5 - course;
+                (0..count).map(move |index| {
+                    (
+                        place(CUBE_DIMENSIONS, count, index, course),
+                        CUBE_DIMENSIONS,
+                    )
+                })
+            })
+            .collect(),
+        // Each course is offset by half a brick from the one below, so every other course has one brick
+        // fewer, and a half brick at either end.
+        Layout::Wall => (0..5)
+            .flat_map(|course| {
+                let count = 4 - course % 2;
+                let bricks = (0..count).map(move |index| {
+                    (
+                        place(BRICK_DIMENSIONS, count, index, course),
+                        BRICK_DIMENSIONS,
+                    )
+                });
+                let half_brick = Vector3::new(
+                    BRICK_DIMENSIONS.x / 2.0,
+                    BRICK_DIMENSIONS.y,
+                    BRICK_DIMENSIONS.z,
+                );
+                let ends = (course % 2 == 1)
+                    .then(|| {
+                        let last = place(BRICK_DIMENSIONS, count, count - 1, course);
+                        let offset = last.x - PLATFORM_CENTER.x
+                            + (BRICK_DIMENSIONS.x + half_brick.x) / 2.0
+                            + BLOCK_GAP;
+                        [-offset, offset].map(|offset| {
+                            (
+                                Vector3::new(PLATFORM_CENTER.x + offset, last.y, last.z),
+                                half_brick,
+                            )
+                        })
+                    })
+                    .into_iter()
+                    .flatten();
+                bricks.chain(ends).collect::<Vec<_>>()
+            })
+            .collect(),
+    }
+}
+
+pub struct Simulation {
+    config: Config,
+    rigidbodies: RigidBodySimulation,
+    obstacles: Vec<Cuboid>,
+    bodies: Vec<Body>,
+    // The index of the rigidbody simulation's trigger over the ground which blocks score in.
+    score_trigger: usize,
+    score: usize,
+    shots: usize,
+    // How the contact solver converged over the last step.
+    contact_stats: SolverStats,
+    // Times the projectiles, by their handles' indices.
+    stopwatch: Stopwatch,
+    // How far the slingshot was pulled back for the last launch, to relaunch it.
+    last_pull: Option<Vector3<f32>>,
+    flight: Option<Flight>,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        let mut simulation = Simulation {
+            config: Config::default(),
+            rigidbodies: RigidBodySimulation::new(Vec::new(), Vec::new()),
+            obstacles: get_obstacle_boxes()
+                .into_iter()
+                .map(|(center, dimensions)| Cuboid::fixed(center, dimensions))
+                .collect(),
+            bodies: Vec::new(),
+            score_trigger: 0,
+            score: 0,
+            shots: 0,
+            contact_stats: SolverStats::default(),
+            stopwatch: Stopwatch::new(get_gate(START_GATE_X), get_gate(STOP_GATE_X)),
+            last_pull: None,
+            flight: None,
+        };
+        simulation.reset();
+        simulation
+    }
+
+    /// Restacks the blocks in the config's layout, without any projectiles, and resets the score.
+    pub fn reset(&mut self) {
+        self.rigidbodies = RigidBodySimulation::new(Vec::new(), Vec::new());
+        self.sync_rigidbody_config();
+        self.score_trigger = self.rigidbodies.add_trigger(Trigger::new(
+            TriggerShape::Aabb {
+                min: -GROUND_HALF_EXTENTS,
+                max: Vector3::new(GROUND_HALF_EXTENTS.x, SCORE_HEIGHT, GROUND_HALF_EXTENTS.z),
+            },
+            TriggerAction::Count,
+        ));
+        self.rigidbodies.add_trigger(Trigger::new(
+            TriggerShape::Aabb {
+                min: Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+                max: Vector3::new(f32::MAX, -FALL_DEPTH, f32::MAX),
+            },
+            TriggerAction::Despawn,
+        ));
+        self.score = 0;
+        self.shots = 0;
+        self.flight = None;
+
+        // Stacks start asleep, so they stand perfectly still until they're hit.
+        let asleep = self.config.sleep.is_some();
+        self.bodies = get_block_boxes(self.config.layout)
+            .into_iter()
+            .map(|(center, dimensions)| {
+                let block = RigidBody::cuboid(
+                    center,
+                    quantity::kilograms(self.config.block_mass),
+                    dimensions,
+                )
+                .expect("Blocks have volume and mass");
+                let handle = self.rigidbodies.spawn_rigidbody(block);
+                Body::new(handle, BodyKind::Block, asleep)
+            })
+            .collect();
+    }
+
+    pub fn step(&mut self) -> Duration {
+        let gravity = Vector3::new(0.0, -self.config.gravity, 0.0);
+        let rigidbodies = self.rigidbodies.get_rigidbodies_mut();
+        // The rigidbody simulation's gravity is a force, but the bodies' weights should grow with their mass.
+        // Sleeping bodies have no weight, so that they stay put.
+        for body in self.bodies.iter().filter(|body| !body.asleep) {
+            let rigidbody = &mut rigidbodies[body.handle];
+            let weight = rigidbody.get_mass() * gravity;
+            rigidbody.add_force(weight);
+        }
+
+        self.rigidbodies.step();
+        // Bodies which fell off the ground were despawned.
+        let rigidbodies = self.rigidbodies.get_rigidbodies();
+        self.bodies.retain(|body| rigidbodies.contains(body.handle));
+
+        self.wake_bodies();
+        self.collide_bodies();
+        self.update_sleep();
+        self.update_score();
+        self.update_stopwatch();
+        self.record_flight();
+
+        Duration::from_secs_f32(self.config.dt)
+    }
+
+    /// Samples the most recent projectile's position, once the sample interval's passed since the last sample.
+    fn record_flight(&mut self) {
+        let Some(flight) = self.flight.as_mut().filter(|flight| flight.recording) else {
+            return;
+        };
+        let Some(projectile) = self.rigidbodies.get_rigidbodies().get(flight.handle) else {
+            flight.recording = false;
+            return;
+        };
+        flight.time += self.config.dt;
+        let last_sample = flight.trajectory.times.last().copied().unwrap_or(0.0);
+        if flight.time - last_sample >= FLIGHT_SAMPLE_INTERVAL {
+            flight.trajectory.times.push(flight.time);
+            flight.trajectory.positions.push(projectile.get_position());
+        }
+        flight.recording = flight.time < MAX_FLIGHT_DURATION;
+    }
+
+    fn update_stopwatch(&mut self) {
+        let rigidbodies = self.rigidbodies.get_rigidbodies();
+        let projectiles = self
+            .bodies
+            .iter()
+            .filter(|body| body.kind == BodyKind::Projectile)
+            .map(|body| (body.handle.index(), rigidbodies[body.handle].get_position()))
+            .collect::<Vec<_>>();
+        self.stopwatch.update(self.config.dt, &projectiles);
+    }
+
+    /// Wakes each sleeping body an awake body's moving into, or all of them if sleeping's disabled.
+    fn wake_bodies(&mut self) {
+        let Some(sleep) = self.config.sleep else {
+            for body in self.bodies.iter_mut() {
+                body.asleep = false;
+            }
+            return;
+        };
+        let rigidbodies = self.rigidbodies.get_rigidbodies();
+        // Waking spreads through a stack over successive steps as each woken body starts moving.
+        let moving = self
+            .bodies
+            .iter()
+            .filter(|body| !body.asleep && !is_still(&rigidbodies[body.handle], sleep.speed))
+            .map(|body| Cuboid::of(&rigidbodies[body.handle]).expanded(WAKE_MARGIN))
+            .collect::<Vec<_>>();
+        for body in self.bodies.iter_mut().filter(|body| body.asleep) {
+            let cuboid = Cuboid::of(&rigidbodies[body.handle]);
+            if moving.iter().any(|mover| mover.overlaps(&cuboid)) {
+                body.asleep = false;
+                body.still_time = 0.0;
+            }
+        }
+    }
+
+    /// Pushes apart each overlapping pair of bodies, and the bodies and the fixed ground and platform.
+    /// Sleeping bodies don't move, so they're pushed against as if they were fixed.
+    fn collide_bodies(&mut self) {
+        let material = ContactMaterial {
+            restitution: self.config.contact_restitution,
+            friction: self.config.contact_friction,
+            anisotropy: None,
+            position_correction: self.config.contact_position_correction,
+        };
+        let rigidbodies = self.rigidbodies.get_rigidbodies_mut();
+        let contact_body = |rigidbodies: &Pool<RigidBody>, body: &Body| {
+            if body.asleep {
+                ContactBody::Fixed(Cuboid::of(&rigidbodies[body.handle]))
+            } else {
+                ContactBody::Rigid(body.handle)
+            }
+        };
+        let mut pairs = Vec::new();
+        for (i, first) in self.bodies.iter().enumerate() {
+            pairs.extend(
+                self.bodies[i + 1..]
+                    .iter()
+                    .filter(|second| !(first.asleep && second.asleep))
+                    .map(|second| {
+                        (
+                            contact_body(rigidbodies, first),
+                            contact_body(rigidbodies, second),
+                        )
+                    }),
+            );
+            if !first.asleep {
+                pairs.extend(self.obstacles.iter().map(|obstacle| {
+                    (
+                        ContactBody::Rigid(first.handle),
+                        ContactBody::Fixed(*obstacle),
+                    )
+                }));
+            }
+        }
+        self.contact_stats =
+            contact::collide(rigidbodies, &pairs, material, &self.config.contact_solver);
+    }
+
+    /// Puts bodies which have been still for the sleep time to sleep.
+    fn update_sleep(&mut self) {
+        let Some(sleep) = self.config.sleep else {
+            return;
+        };
+        let rigidbodies = self.rigidbodies.get_rigidbodies_mut();
+        for body in self.bodies.iter_mut().filter(|body| !body.asleep) {
+            let rigidbody = &mut rigidbodies[body.handle];
+            if !is_still(rigidbody, sleep.speed) {
+                body.still_time = 0.0;
+                continue;
+            }
+            body.still_time += self.config.dt;
+            if body.still_time >= sleep.time {
+                body.asleep = true;
+                rigidbody.stop();
+            }
+        }
+    }
+
+    /// Scores each block the first time it's knocked onto the ground.
+    fn update_score(&mut self) {
+        let trigger = &self.rigidbodies.get_triggers()[self.score_trigger];
+        for body in self.bodies.iter_mut() {
+            if body.kind == BodyKind::Block && !body.scored && trigger.contains(body.handle) {
+                body.scored = true;
+                self.score += 1;
+            }
+        }
+    }
+
+    /// Launches a projectile from the slingshot pulled back by the pull, at most the max pull,
+    /// removing the oldest projectile if there are too many.
+    pub fn launch(&mut self, pull: Vector3<f32>) -> Result<RigidBodyHandle, &'static str> {
+        if pull.magnitude() < f32::EPSILON {
+            return Err("The slingshot must be pulled back to launch.");
+        }
+        let pull = if pull.magnitude() > MAX_PULL {
+            pull.normalize_to(MAX_PULL)
+        } else {
+            pull
+        };
+        let mass = self.config.projectile_mass;
+        let mut projectile = RigidBody::cuboid(
+            SLINGSHOT + pull,
+            quantity::kilograms(mass),
+            PROJECTILE_DIMENSIONS,
+        )?;
+        projectile.apply_impulse(-pull * self.config.launch_strength * mass, Vector3::zero());
+
+        let projectiles = self
+            .bodies
+            .iter()
+            .filter(|body| body.kind == BodyKind::Projectile)
+            .count();
+        if projectiles >= MAX_PROJECTILES {
+            if let Some(oldest) = self
+                .bodies
+                .iter()
+                .position(|body| body.kind == BodyKind::Projectile)
+            {
+                let oldest = self.bodies.remove(oldest);
+                self.rigidbodies.despawn_rigidbody(oldest.handle);
+            }
+        }
+        let position = projectile.get_position();
+        let handle = self.rigidbodies.spawn_rigidbody(projectile);
+        self.bodies
+            .push(Body::new(handle, BodyKind::Projectile, false));
+        self.shots += 1;
+        self.last_pull = Some(pull);
+        self.flight = Some(Flight {
+            handle,
+            time: 0.0,
+            trajectory: Trajectory {
+                times: vec![0.0],
+                positions: vec![position],
+            },
+            recording: true,
+        });
+        Ok(handle)
+    }
+
+    /// Launches another projectile with the slingshot pulled back as far as it was for the last launch, e.g. to
+    /// compare its flight with the last one's under different parameters.
+    pub fn relaunch(&mut self) -> Result<RigidBodyHandle, &'static str> {
+        let pull = self
+            .last_pull
+            .ok_or("Nothing's been launched to relaunch.")?;
+        self.launch(pull)
+    }
+
+    pub fn can_relaunch(&self) -> bool {
+        self.last_pull.is_some()
+    }
+
+    /// The flight of the most recently launched projectile, if it hasn't been reset since.
+    pub fn get_flight(&self) -> Option<&Flight> {
+        self.flight.as_ref()
+    }
+
+    fn sync_rigidbody_config(&mut self) {
+        let config = self.rigidbodies.get_config_mut();
+        config.dt = self.config.dt;
+        config.gravity = Vector3::zero();
+        config.angular_drag_viscous = ANGULAR_DRAG;
+        config.linear_drag_quadratic = self.config.drag;
+    }
+
+    pub fn get_timestep(&self) -> Duration {
+        Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn get_rigidbodies(&self) -> &Pool<RigidBody> {
+        self.rigidbodies.get_rigidbodies()
+    }
+
+    pub fn get_bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    /// The number of blocks knocked onto the ground.
+    pub fn get_score(&self) -> usize {
+        self.score
+    }
+
+    /// The number of blocks in the layout, which is the most that can be scored.
+    pub fn get_block_count(&self) -> usize {
+        get_block_boxes(self.config.layout).len()
+    }
+
+    pub fn get_shots(&self) -> usize {
+        self.shots
+    }
+
+    pub fn get_contact_stats(&self) -> &SolverStats {
+        &self.contact_stats
+    }
+
+    /// Moves the stopwatch's gates to the positions along x.
+    pub fn set_stopwatch_gates(&mut self, start_x: f32, stop_x: f32) {
+        self.stopwatch
+            .set_gates(get_gate(start_x), get_gate(stop_x));
+    }
+
+    pub fn get_stopwatch(&self) -> &Stopwatch {
+        &self.stopwatch
+    }
+
+    pub fn clear_stopwatch(&mut self) {
+        self.stopwatch.clear();
+    }
+}
+
+/// A gate across the range at the position along x, which projectiles enter as they fly past it downrange.
+fn get_gate(x: f32) -> TriggerShape {
+    TriggerShape::Aabb {
+        min: Vector3::new(x, f32::MIN, f32::MIN),
+        max: Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+    }
+}
+
+/// The ground, and the platform the blocks are stacked on.
+pub fn get_obstacle_boxes() -> Vec<(Vector3<f32>, Vector3<f32>)> {
+    vec![
+        (
+            Vector3::new(0.0, -GROUND_HALF_EXTENTS.y, 0.0),
+            2.0 * GROUND_HALF_EXTENTS,
+        ),
+        (PLATFORM_CENTER, PLATFORM_DIMENSIONS),
+    ]
+}
+
+/// The most blocks any layout stacks.
+pub fn get_max_block_count() -> usize {
+    Layout::ALL
+        .iter()
+        .map(|layout| get_block_boxes(*layout).len())
+        .max()
+        .unwrap_or(0)
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        // The layout and block mass set the blocks, so they're restacked with them.
+        let restack =
+            config.layout != self.config.layout || config.block_mass != self.config.block_mass;
+        self.config = config.clone();
+        self.sync_rigidbody_config();
+        if restack {
+            self.reset();
+        }
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::assert_relative_eq;
+
+    fn run(simulation: &mut Simulation, seconds: f32) {
+        for _ in 0..(seconds / simulation.get_config().dt) as usize {
+            simulation.step();
+        }
+    }
+
+    fn get_positions(simulation: &Simulation) -> Vec<Vector3<f32>> {
+        simulation
+            .get_bodies()
+            .iter()
+            .map(|body| simulation.get_rigidbodies()[body.handle].get_position())
+            .collect()
+    }
+
+    #[test]
+    fn stacks_stand_until_hit() {
+        for sleep in [Some(config::SleepConfig::default()), None] {
+            let mut simulation = Simulation::new();
+            simulation.sync_config(&Config {
+                layout: Layout::Wall,
+                sleep,
+                ..Config::default()
+            });
+            let start = get_positions(&simulation);
+            run(&mut simulation, 1.0);
+            // Awake stacks settle into the gaps between blocks, but don't topple.
+            for (start, end) in start.iter().zip(get_positions(&simulation)) {
+                assert!((end - start).magnitude() < 0.05, "{:?} {:?}", start, end);
+            }
+            assert_eq!(simulation.get_score(), 0);
+            assert_eq!(
+                simulation.get_bodies().iter().all(|body| body.asleep),
+                sleep.is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn projectiles_knock_blocks_down() {
+        let mut simulation = Simulation::new();
+        // At the nearest tower.
+        simulation.launch(Vector3::new(-1.3, -0.4, 0.0)).unwrap();
+        run(&mut simulation, 3.0);
+        assert_eq!(simulation.get_shots(), 1);
+        assert!(simulation.get_score() > 0);
+        // Timed from the slingshot to the platform.
+        let laps = simulation.get_stopwatch().get_laps();
+        assert_eq!(laps.len(), 1);
+        assert!(laps[0] > 0.0 && laps[0] < 3.0, "{:?}", laps);
+        assert!(simulation.launch(Vector3::zero()).is_err());
+    }
+
+    #[test]
+    fn relaunches_record_flights() {
+        let mut simulation = Simulation::new();
+        assert!(simulation.relaunch().is_err());
+        // Over the platform, into the gap beyond it.
+        simulation.launch(Vector3::new(-1.0, -1.0, 0.0)).unwrap();
+        run(&mut simulation, 1.0);
+        let first = simulation.get_flight().unwrap().get_trajectory().clone();
+        simulation.sync_config(&Config {
+            drag: 2.0,
+            ..Config::default()
+        });
+        simulation.relaunch().unwrap();
+        run(&mut simulation, 1.0);
+        let flight = simulation.get_flight().unwrap();
+        assert_relative_eq!(flight.get_time(), 1.0, epsilon = 0.01);
+        // The flights start together, but dragged projectiles fall short.
+        assert_eq!(flight.get_trajectory().positions[0], first.positions[0]);
+        let distance = |trajectory: &Trajectory| {
+            (trajectory.position_at(0.5).unwrap() - trajectory.positions[0]).magnitude()
+        };
+        assert!(
+            distance(flight.get_trajectory()) < distance(&first) - 0.1,
+            "{} {}",
+            distance(flight.get_trajectory()),
+            distance(&first)
+        );
+    }
+}
@@ -0,0 +1,215 @@
+/// Trigger volumes, which don't collide with objects but report when objects enter and exit them.
+use std::hash::Hash;
+
+use cgmath::{InnerSpace, Vector3};
+use rustc_hash::FxHashSet;
+
+#[derive(Debug, Copy, Clone)]
+pub enum TriggerShape {
+    Aabb {
+        min: Vector3<f32>,
+        max: Vector3<f32>,
+    },
+    Sphere {
+        center: Vector3<f32>,
+        radius: f32,
+    },
+}
+
+impl TriggerShape {
+    pub fn contains(&self, point: Vector3<f32>) -> bool {
+        match self {
+            TriggerShape::Aabb { min, max } => {
+                point.x >= min.x
+                    && point.x <= max.x
+                    && point.y >= min.y
+                    && point.y <= max.y
+                    && point.z >= min.z
+                    && point.z <= max.z
+            }
+            TriggerShape::Sphere { center, radius } => {
+                (point - center).magnitude2() <= radius * radius
+            }
+        }
+    }
+}
+
+/// What a simulation should do with objects in a trigger volume.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TriggerAction {
+    /// Only count the objects entering the volume.
+    Count,
+    /// Remove objects as they enter the volume.
+    /// Simulations which can't remove objects treat this as Count.
+    Despawn,
+    /// Apply an additional wind to objects while they are in the volume.
+    Wind(Vector3<f32>),
+}
+
+/// An object entering or exiting a trigger volume, identified by the key the simulation gave it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TriggerEvent<K = usize> {
+    Enter(K),
+    Exit(K),
+}
+
+/// A trigger volume, tracking objects by a key such as their index or handle.
+/// Simulations which reuse the keys of removed objects should use keys which tell them apart, e.g. generational
+/// handles, so that an object in a reused slot doesn't inherit whether its predecessor was inside.
+pub struct Trigger<K = usize> {
+    shape: TriggerShape,
+    action: TriggerAction,
+    inside: FxHashSet<K>,
+    enter_count: usize,
+}
+
+impl<K: Copy + Eq + Hash + Ord> Trigger<K> {
+    pub fn new(shape: TriggerShape, action: TriggerAction) -> Trigger<K> {
+        Trigger {
+            shape,
+            action,
+            inside: FxHashSet::default(),
+            enter_count: 0,
+        }
+    }
+
+    /// Updates which objects are inside the volume, given each object's key and position.
+    /// Objects which were inside but aren't given at all, e.g. because they were removed
+    /// from the simulation, are treated as having exited.
+    pub fn update(
+        &mut self,
+        objects: impl IntoIterator<Item = (K, Vector3<f32>)>,
+    ) -> Vec<TriggerEvent<K>> {
+        let mut events = Vec::new();
+        let mut inside = FxHashSet::default();
+        for (key, position) in objects {
+            if self.shape.contains(position) {
+                inside.insert(key);
+                if !self.inside.contains(&key) {
+                    events.push(TriggerEvent::Enter(key));
+                }
+            }
+        }
+        // Exits are reported in key order, rather than the set's, so that their actions apply in the same order every run.
+        let mut exited = self
+            .inside
+            .iter()
+            .filter(|key| !inside.contains(key))
+            .copied()
+            .collect::<Vec<_>>();
+        exited.sort_unstable();
+        events.extend(exited.into_iter().map(TriggerEvent::Exit));
+        self.enter_count += events
+            .iter()
+            .filter(|event| matches!(event, TriggerEvent::Enter(_)))
+            .count();
+        self.inside = inside;
+        events
+    }
+
+    /// True if the object was inside the volume as of the last update.
+    pub fn contains(&self, key: K) -> bool {
+        self.inside.contains(&key)
+    }
+
+    /// Moves or resizes the volume. Objects it no longer contains exit it on the next update.
+    pub fn set_shape(&mut self, shape: TriggerShape) {
+        self.shape = shape;
+    }
+
+    pub fn get_shape(&self) -> &TriggerShape {
+        &self.shape
+    }
+
+    pub fn get_action(&self) -> TriggerAction {
+        self.action
+    }
+
+    /// The total number of times objects have entered the volume.
+    pub fn get_enter_count(&self) -> usize {
+        self.enter_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::{Trigger, TriggerAction, TriggerEvent, TriggerShape};
+    use crate::pool::Pool;
+
+    #[test]
+    fn shape_contains() {
+        let aabb = TriggerShape::Aabb {
+            min: Vector3::new(-1.0, 0.0, -1.0),
+            max: Vector3::new(1.0, 1.0, 1.0),
+        };
+        assert!(aabb.contains(Vector3::new(0.5, 0.5, 0.5)));
+        assert!(!aabb.contains(Vector3::new(0.5, -0.5, 0.5)));
+
+        let sphere = TriggerShape::Sphere {
+            center: Vector3::new(0.0, 2.0, 0.0),
+            radius: 1.0,
+        };
+        assert!(sphere.contains(Vector3::new(0.0, 2.5, 0.5)));
+        assert!(!sphere.contains(Vector3::new(0.8, 2.8, 0.0)));
+    }
+
+    #[test]
+    fn enter_and_exit() {
+        let mut trigger = Trigger::new(
+            TriggerShape::Sphere {
+                center: Vector3::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+            },
+            TriggerAction::Count,
+        );
+        let outside = Vector3::new(2.0, 0.0, 0.0);
+        let inside = Vector3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(
+            vec![TriggerEvent::Enter(1)],
+            trigger.update(vec![(0, outside), (1, inside)])
+        );
+        assert!(trigger.contains(1));
+        // Staying inside fires no events.
+        assert!(trigger.update(vec![(0, outside), (1, inside)]).is_empty());
+        assert_eq!(
+            vec![TriggerEvent::Enter(0), TriggerEvent::Exit(1)],
+            trigger.update(vec![(0, inside), (1, outside)])
+        );
+        // A removed object exits.
+        assert_eq!(vec![TriggerEvent::Exit(0)], trigger.update(vec![]));
+        assert_eq!(2, trigger.get_enter_count());
+    }
+
+    #[test]
+    fn reused_handles_are_new_objects() {
+        let mut trigger = Trigger::new(
+            TriggerShape::Sphere {
+                center: Vector3::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+            },
+            TriggerAction::Count,
+        );
+        let inside = Vector3::new(0.0, 0.0, 0.0);
+        let mut pool = Pool::new();
+
+        let first = pool.insert(());
+        assert_eq!(
+            vec![TriggerEvent::Enter(first)],
+            trigger.update(vec![(first, inside)])
+        );
+        // The second object reuses the first's slot after it's removed, so the first exits as the second enters.
+        pool.remove(first);
+        let second = pool.insert(());
+        assert_eq!(first.index(), second.index());
+        assert_eq!(
+            vec![TriggerEvent::Enter(second), TriggerEvent::Exit(first)],
+            trigger.update(vec![(second, inside)])
+        );
+        assert!(trigger.contains(second));
+        assert!(!trigger.contains(first));
+        assert_eq!(2, trigger.get_enter_count());
+    }
+}
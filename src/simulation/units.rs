@@ -0,0 +1,107 @@
+/// All simulations use SI units: lengths are in meters, masses in kilograms, and time in seconds.
+/// Derived quantities follow, e.g. accelerations are in m/s^2 and forces in newtons.
+/// The unit labels here are shown alongside parameters in the GUI.
+use super::consts;
+use crate::localization::tr;
+
+use cgmath::{InnerSpace, Vector3};
+
+pub const LENGTH: &str = "m";
+pub const MASS: &str = "kg";
+pub const TIME: &str = "s";
+pub const VELOCITY: &str = "m/s";
+pub const ANGLE: &str = "rad";
+pub const ANGULAR_VELOCITY: &str = "rad/s";
+pub const ANGULAR_ACCELERATION: &str = "rad/s²";
+pub const ACCELERATION: &str = "m/s²";
+pub const FORCE: &str = "N";
+pub const TORQUE: &str = "N·m";
+pub const IMPULSE: &str = "N·s";
+pub const IMPULSE_PER_LENGTH: &str = "N·s/m";
+pub const STIFFNESS: &str = "N/m";
+pub const ANGULAR_STIFFNESS: &str = "N·m/rad";
+pub const DENSITY: &str = "kg/m³";
+pub const KINEMATIC_VISCOSITY: &str = "m²/s";
+pub const CIRCULATION: &str = "m²/s";
+pub const CHARGE: &str = "C";
+pub const ELECTRIC_FIELD: &str = "V/m";
+pub const MAGNETIC_FIELD: &str = "T";
+pub const TEMPERATURE: &str = "K";
+pub const FREQUENCY: &str = "Hz";
+
+/// Formats a GUI label for a quantity with the given units, e.g. "Gravity Y (m/s²)".
+/// The name is localized; the units are SI symbols, which aren't.
+pub fn label(name: &str, units: &str) -> String {
+    format!("{} ({})", tr(name), units)
+}
+
+/// Surface gravity of various bodies. Gravity always points down the -Y axis.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GravityPreset {
+    Earth,
+    Moon,
+    Mars,
+    ZeroG,
+}
+
+impl GravityPreset {
+    pub const ALL: [GravityPreset; 4] = [
+        GravityPreset::Earth,
+        GravityPreset::Moon,
+        GravityPreset::Mars,
+        GravityPreset::ZeroG,
+    ];
+
+    /// The magnitude of the acceleration due to gravity, in m/s^2.
+    pub fn acceleration(&self) -> f32 {
+        match self {
+            GravityPreset::Earth => consts::GRAVITY,
+            GravityPreset::Moon => 1.62,
+            GravityPreset::Mars => 3.71,
+            GravityPreset::ZeroG => 0.0,
+        }
+    }
+
+    /// The acceleration due to gravity as a vector, in m/s^2.
+    pub fn vector(&self) -> Vector3<f32> {
+        -Vector3::<f32>::unit_y() * self.acceleration()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GravityPreset::Earth => "Earth",
+            GravityPreset::Moon => "Moon",
+            GravityPreset::Mars => "Mars",
+            GravityPreset::ZeroG => "Zero-G",
+        }
+    }
+
+    /// Gets the preset matching the gravity vector, if any.
+    pub fn from_vector(gravity: Vector3<f32>) -> Option<GravityPreset> {
+        GravityPreset::ALL
+            .into_iter()
+            .find(|preset| (preset.vector() - gravity).magnitude() < consts::EPSILON)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GravityPreset;
+
+    use cgmath::Vector3;
+
+    #[test]
+    fn from_vector() {
+        for preset in GravityPreset::ALL {
+            assert_eq!(Some(preset), GravityPreset::from_vector(preset.vector()));
+        }
+        assert_eq!(
+            Some(GravityPreset::ZeroG),
+            GravityPreset::from_vector(Vector3::new(0.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            None,
+            GravityPreset::from_vector(Vector3::new(1.0, -9.8, 0.0))
+        );
+    }
+}
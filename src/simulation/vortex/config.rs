@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+#[derive(Clone, PartialEq)]
+pub struct Config {
+    pub dt: f32, // Seconds as f32
+    /// The time between vortex rings puffed from the emitter.
+    pub ring_interval: f32,
+    pub ring_radius: f32,
+    /// The circulation of each ring, which sets how fast it travels.
+    pub ring_circulation: f32,
+    /// The number of vortex particles discretizing each ring.
+    pub vortices_per_ring: usize,
+    /// The number of tracer particles puffed with each ring, which show the smoke.
+    pub tracers_per_ring: usize,
+    /// The radius the Biot-Savart kernel is regularized over, so that the velocity induced
+    /// near a vortex particle stays finite.
+    pub core_radius: f32,
+    /// The Barnes-Hut opening angle. Groups of vortices which appear smaller than this from
+    /// a point are approximated as a single vortex; 0 sums every vortex directly.
+    pub barnes_hut_theta: f32,
+    /// The rate the vortices' strengths decay at, in 1/s, approximating viscosity.
+    pub vorticity_decay: f32,
+    /// The time vortices and tracers live for after they're emitted.
+    pub lifetime: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dt: Duration::from_millis(10).as_secs_f32(),
+            ring_interval: 1.0,
+            ring_radius: 0.3,
+            ring_circulation: 1.0,
+            vortices_per_ring: 32,
+            tracers_per_ring: 500,
+            core_radius: 0.1,
+            barnes_hut_theta: 0.5,
+            vorticity_decay: 0.1,
+            lifetime: 8.0,
+        }
+    }
+}
@@ -0,0 +1,244 @@
+pub mod config;
+pub mod octree;
+
+use self::config::Config;
+use self::octree::Octree;
+use crate::simulation::configurable::Configurable;
+
+use cgmath::{InnerSpace, Vector3, Zero};
+use rand::Rng;
+
+/// Vortices aren't emitted past this many.
+pub const MAX_VORTICES: usize = 2000;
+/// Tracers aren't emitted past this many.
+pub const MAX_TRACERS: usize = 10000;
+
+/// A particle carrying vorticity.
+#[derive(Clone, Copy)]
+pub struct Vortex {
+    pub position: Vector3<f32>,
+    /// The vorticity integrated over the particle's volume, in m³/s.
+    pub strength: Vector3<f32>,
+    age: f32,
+}
+
+/// A passive particle carried by the flow, which shows the smoke.
+#[derive(Clone, Copy)]
+pub struct Tracer {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    age: f32,
+}
+
+impl Tracer {
+    /// The fraction of its lifetime the tracer has lived.
+    pub fn get_age_fraction(&self, config: &Config) -> f32 {
+        self.age / config.lifetime
+    }
+}
+
+/// Gets the velocity induced at the point by a vortex particle, by the Biot-Savart law
+/// regularized over the core radius.
+pub fn induced_velocity(
+    point: Vector3<f32>,
+    position: Vector3<f32>,
+    strength: Vector3<f32>,
+    core_radius: f32,
+) -> Vector3<f32> {
+    let displacement = point - position;
+    let denominator = (displacement.magnitude2() + core_radius.powi(2)).powf(1.5);
+    strength.cross(displacement) / (4.0 * std::f32::consts::PI * denominator)
+}
+
+/// Smoke simulated by the vortex particle method, which needs no grid.
+/// An emitter at the origin puffs vortex rings upward; the vortex particles discretizing the
+/// rings carry the flow's vorticity, and the velocity they induce by the Biot-Savart law advects
+/// both them and passive tracer particles. The velocity is summed with Barnes-Hut approximation.
+/// Vortex stretching is neglected, so the model is cheap, at the cost of rings not deforming
+/// realistically as they interact.
+pub struct Simulation {
+    config: Config,
+    vortices: Vec<Vortex>,
+    tracers: Vec<Tracer>,
+    /// The time until the next ring is emitted.
+    ring_timer: f32,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        Simulation {
+            config: Config::default(),
+            vortices: Vec::new(),
+            tracers: Vec::new(),
+            ring_timer: 0.0,
+        }
+    }
+
+    pub fn step(&mut self) -> std::time::Duration {
+        let dt = self.config.dt;
+        self.ring_timer -= dt;
+        if self.ring_timer <= 0.0 {
+            self.ring_timer = self.config.ring_interval;
+            self.emit_ring();
+        }
+
+        let octree = Octree::new(&self.vortices);
+        let velocity = |point| {
+            octree.velocity(
+                &self.vortices,
+                point,
+                self.config.core_radius,
+                self.config.barnes_hut_theta,
+            )
+        };
+        let vortex_velocities: Vec<Vector3<f32>> = self
+            .vortices
+            .iter()
+            .map(|vortex| velocity(vortex.position))
+            .collect();
+        for tracer in self.tracers.iter_mut() {
+            tracer.velocity = velocity(tracer.position);
+        }
+
+        let decay = (-self.config.vorticity_decay * dt).exp();
+        for (vortex, velocity) in self.vortices.iter_mut().zip(vortex_velocities) {
+            vortex.position += velocity * dt;
+            vortex.strength *= decay;
+            vortex.age += dt;
+        }
+        for tracer in self.tracers.iter_mut() {
+            tracer.position += tracer.velocity * dt;
+            tracer.age += dt;
+        }
+        let lifetime = self.config.lifetime;
+        self.vortices.retain(|vortex| vortex.age < lifetime);
+        self.tracers.retain(|tracer| tracer.age < lifetime);
+
+        std::time::Duration::from_secs_f32(dt)
+    }
+
+    /// Emits a ring of vortices at the emitter, which travels upward, and tracers within its core.
+    /// The vortices are slightly perturbed, so that rings' interactions aren't perfectly symmetric.
+    fn emit_ring(&mut self) {
+        let mut rng = rand::thread_rng();
+        let radius = self.config.ring_radius;
+        let count = self
+            .config
+            .vortices_per_ring
+            .min(MAX_VORTICES.saturating_sub(self.vortices.len()));
+        // Each vortex carries the circulation over its share of the ring's circumference.
+        let length = 2.0 * std::f32::consts::PI * radius / self.config.vortices_per_ring as f32;
+        for i in 0..count {
+            let angle =
+                2.0 * std::f32::consts::PI * i as f32 / self.config.vortices_per_ring as f32;
+            let jitter = rng.gen_range(-0.01..0.01) * radius;
+            // Circulating this way around the ring induces an upward velocity within it.
+            let tangent = Vector3::new(angle.sin(), 0.0, -angle.cos());
+            self.vortices.push(Vortex {
+                position: Vector3::new(angle.cos(), 0.0, angle.sin()) * (radius + jitter),
+                strength: tangent * self.config.ring_circulation * length,
+                age: 0.0,
+            });
+        }
+
+        let count = self
+            .config
+            .tracers_per_ring
+            .min(MAX_TRACERS.saturating_sub(self.tracers.len()));
+        for _ in 0..count {
+            let angle = rng.gen_range(0.0..2.0 * std::f32::consts::PI);
+            let radial = Vector3::new(angle.cos(), 0.0, angle.sin());
+            // Uniform within the disk of the core about the ring.
+            let core_angle = rng.gen_range(0.0..2.0 * std::f32::consts::PI);
+            let core_distance = self.config.core_radius * rng.gen::<f32>().sqrt();
+            let position = radial * (radius + core_distance * core_angle.cos())
+                + Vector3::unit_y() * core_distance * core_angle.sin();
+            self.tracers.push(Tracer {
+                position,
+                velocity: Vector3::zero(),
+                age: 0.0,
+            });
+        }
+    }
+
+    pub fn get_timestep(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn get_vortices(&self) -> &[Vortex] {
+        &self.vortices
+    }
+
+    pub fn get_tracers(&self) -> &[Tracer] {
+        &self.tracers
+    }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.dt = config.dt;
+        self.config.ring_interval = config.ring_interval;
+        self.config.ring_radius = config.ring_radius;
+        self.config.ring_circulation = config.ring_circulation;
+        self.config.vortices_per_ring = config.vortices_per_ring;
+        self.config.tracers_per_ring = config.tracers_per_ring;
+        self.config.core_radius = config.core_radius;
+        self.config.barnes_hut_theta = config.barnes_hut_theta;
+        self.config.vorticity_decay = config.vorticity_decay;
+        self.config.lifetime = config.lifetime;
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn induced_velocity_circulates() {
+        let velocity = induced_velocity(Vector3::unit_x(), Vector3::zero(), Vector3::unit_z(), 0.1);
+        // Counterclockwise about the vortex's strength.
+        assert!(velocity.y > 0.0);
+        assert!(velocity.x.abs() < 1e-6 && velocity.z.abs() < 1e-6);
+        // The regularized kernel induces no velocity at the vortex itself.
+        assert_eq!(
+            induced_velocity(Vector3::zero(), Vector3::zero(), Vector3::unit_z(), 0.1),
+            Vector3::zero()
+        );
+    }
+
+    #[test]
+    fn ring_travels_upward() {
+        let mut simulation = Simulation::new();
+        simulation.config.ring_interval = 100.0;
+        for _ in 0..50 {
+            simulation.step();
+        }
+        let height = simulation
+            .vortices
+            .iter()
+            .map(|vortex| vortex.position.y)
+            .sum::<f32>()
+            / simulation.vortices.len() as f32;
+        // A thin ring travels at about Γ/(4πR)(ln(8R/a) - 1/4).
+        assert!(height > 0.1, "{}", height);
+        let tracer_height = simulation
+            .tracers
+            .iter()
+            .map(|tracer| tracer.position.y)
+            .sum::<f32>()
+            / simulation.tracers.len() as f32;
+        assert!(tracer_height > 0.1, "{}", tracer_height);
+    }
+}
@@ -0,0 +1,206 @@
+use super::{induced_velocity, Vortex};
+
+use cgmath::{InnerSpace, Vector3, Zero};
+
+/// Nodes with at most this many vortices aren't subdivided.
+const LEAF_SIZE: usize = 8;
+/// Nodes aren't subdivided past this depth, e.g. if many vortices share a position.
+const MAX_DEPTH: u32 = 16;
+
+struct Node {
+    half_size: f32,
+    /// The center of the node's vortices, weighted by the magnitudes of their strengths.
+    centroid: Vector3<f32>,
+    /// The sum of the node's vortices' strengths.
+    strength: Vector3<f32>,
+    children: Vec<usize>,
+    /// The indices of the node's vortices, if it's a leaf.
+    vortices: Vec<usize>,
+}
+
+/// An octree over vortex particles for Barnes-Hut approximation of the velocity they induce.
+/// Distant groups of vortices are approximated by their summed strength at their centroid,
+/// so the velocity at a point costs O(log n) rather than O(n).
+pub struct Octree {
+    nodes: Vec<Node>,
+}
+
+impl Octree {
+    pub fn new(vortices: &[Vortex]) -> Octree {
+        let mut octree = Octree { nodes: Vec::new() };
+        if vortices.is_empty() {
+            return octree;
+        }
+        let (min, max) = vortices.iter().fold(
+            (
+                Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+                Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+            ),
+            |(min, max), vortex| {
+                let p = vortex.position;
+                (
+                    Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                    Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                )
+            },
+        );
+        let center = (min + max) / 2.0;
+        let half_size = (max - min).x.max((max - min).y).max((max - min).z) / 2.0;
+        octree.build(
+            vortices,
+            (0..vortices.len()).collect(),
+            center,
+            half_size,
+            0,
+        );
+        octree
+    }
+
+    /// Adds the node containing the vortices at the indices, returning its index.
+    fn build(
+        &mut self,
+        vortices: &[Vortex],
+        indices: Vec<usize>,
+        center: Vector3<f32>,
+        half_size: f32,
+        depth: u32,
+    ) -> usize {
+        let (weighted_position, weight, strength) = indices.iter().fold(
+            (Vector3::zero(), 0.0, Vector3::zero()),
+            |(weighted_position, weight, strength), &index| {
+                let vortex = &vortices[index];
+                let magnitude = vortex.strength.magnitude();
+                (
+                    weighted_position + vortex.position * magnitude,
+                    weight + magnitude,
+                    strength + vortex.strength,
+                )
+            },
+        );
+        let centroid = if weight > 0.0 {
+            weighted_position / weight
+        } else {
+            center
+        };
+
+        let node_index = self.nodes.len();
+        self.nodes.push(Node {
+            half_size,
+            centroid,
+            strength,
+            children: Vec::new(),
+            vortices: Vec::new(),
+        });
+        if indices.len() <= LEAF_SIZE || depth >= MAX_DEPTH {
+            self.nodes[node_index].vortices = indices;
+            return node_index;
+        }
+
+        let mut octants: [Vec<usize>; 8] = Default::default();
+        for index in indices {
+            let p = vortices[index].position;
+            let octant = (p.x >= center.x) as usize
+                | ((p.y >= center.y) as usize) << 1
+                | ((p.z >= center.z) as usize) << 2;
+            octants[octant].push(index);
+        }
+        let quarter_size = half_size / 2.0;
+        for (octant, indices) in octants.into_iter().enumerate() {
+            if indices.is_empty() {
+                continue;
+            }
+            let offset = Vector3::new(
+                if octant & 1 != 0 { 1.0 } else { -1.0 },
+                if octant & 2 != 0 { 1.0 } else { -1.0 },
+                if octant & 4 != 0 { 1.0 } else { -1.0 },
+            ) * quarter_size;
+            let child = self.build(vortices, indices, center + offset, quarter_size, depth + 1);
+            self.nodes[node_index].children.push(child);
+        }
+        node_index
+    }
+
+    /// Gets the velocity the vortices induce at the point, approximating nodes which appear
+    /// smaller than theta (in radians) from the point.
+    pub fn velocity(
+        &self,
+        vortices: &[Vortex],
+        point: Vector3<f32>,
+        core_radius: f32,
+        theta: f32,
+    ) -> Vector3<f32> {
+        if self.nodes.is_empty() {
+            return Vector3::zero();
+        }
+        let mut velocity = Vector3::zero();
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let distance = (point - node.centroid).magnitude();
+            if !node.vortices.is_empty() || node.children.is_empty() {
+                for &index in node.vortices.iter() {
+                    let vortex = &vortices[index];
+                    velocity +=
+                        induced_velocity(point, vortex.position, vortex.strength, core_radius);
+                }
+            } else if 2.0 * node.half_size < theta * distance {
+                velocity += induced_velocity(point, node.centroid, node.strength, core_radius);
+            } else {
+                stack.extend(node.children.iter());
+            }
+        }
+        velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn approximates_direct_sum() {
+        let mut rng = rand::thread_rng();
+        let mut random_vector = || {
+            Vector3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            )
+        };
+        let vortices: Vec<Vortex> = (0..500)
+            .map(|_| Vortex {
+                position: random_vector(),
+                // Mostly aligned, as they are in a vortex ring's neighbourhood.
+                strength: (Vector3::unit_z() + random_vector() * 0.3) * 0.01,
+                age: 0.0,
+            })
+            .collect();
+        let octree = Octree::new(&vortices);
+
+        let point = Vector3::new(3.0, 0.5, -0.5);
+        let direct = vortices.iter().fold(Vector3::zero(), |velocity, vortex| {
+            velocity + induced_velocity(point, vortex.position, vortex.strength, 0.1)
+        });
+        let approximate = octree.velocity(&vortices, point, 0.1, 0.5);
+        assert!((approximate - direct).magnitude() < 0.05 * direct.magnitude());
+        // With an opening angle of 0, every vortex is summed directly.
+        let exact = octree.velocity(&vortices, point, 0.1, 0.0);
+        assert!((exact - direct).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn coincident_vortices() {
+        let vortices = vec![
+            Vortex {
+                position: Vector3::zero(),
+                strength: Vector3::unit_z(),
+                age: 0.0,
+            };
+            100
+        ];
+        let octree = Octree::new(&vortices);
+        let velocity = octree.velocity(&vortices, Vector3::unit_x(), 0.1, 0.5);
+        assert!(velocity.y > 0.0);
+    }
+}
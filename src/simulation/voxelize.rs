@@ -0,0 +1,441 @@
+/// Voxelization of triangle meshes into occupancy grids, e.g. to seed particles inside arbitrary
+/// shapes, bake signed distance fields, or approximate the mass properties of bodies.
+use cgmath::{InnerSpace, Vector3};
+
+/// Stands in for infinite squared distances in the distance transform, which needs finite values.
+const FAR: f32 = 1e20;
+
+/// Which voxels of a mesh are occupied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Voxels whose centers are inside the mesh, which must be closed.
+    Solid,
+    /// Voxels which any of the mesh's triangles pass through. The mesh needn't be closed.
+    Surface,
+}
+
+/// A regular grid of voxels, each occupied or not.
+/// Voxel (x, y, z) spans from origin + (x, y, z) * voxel_size to one voxel_size further along each axis.
+pub struct VoxelGrid {
+    origin: Vector3<f32>,
+    voxel_size: f32,
+    dimensions: [usize; 3],
+    occupied: Vec<bool>,
+}
+
+impl VoxelGrid {
+    fn new(origin: Vector3<f32>, voxel_size: f32, dimensions: [usize; 3]) -> VoxelGrid {
+        VoxelGrid {
+            origin,
+            voxel_size,
+            dimensions,
+            occupied: vec![false; dimensions[0] * dimensions[1] * dimensions[2]],
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.dimensions[1] + y) * self.dimensions[0] + x
+    }
+
+    /// The number of voxels along each axis.
+    pub fn get_dimensions(&self) -> [usize; 3] {
+        self.dimensions
+    }
+
+    pub fn get_voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    pub fn is_occupied(&self, x: usize, y: usize, z: usize) -> bool {
+        self.occupied[self.index(x, y, z)]
+    }
+
+    pub fn get_center(&self, x: usize, y: usize, z: usize) -> Vector3<f32> {
+        self.origin + Vector3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5) * self.voxel_size
+    }
+
+    pub fn get_occupied_count(&self) -> usize {
+        self.occupied.iter().filter(|occupied| **occupied).count()
+    }
+
+    /// Gets the centers of the occupied voxels, e.g. to seed particles at.
+    pub fn get_occupied_centers(&self) -> Vec<Vector3<f32>> {
+        let [width, height, depth] = self.dimensions;
+        let mut centers = Vec::new();
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    if self.is_occupied(x, y, z) {
+                        centers.push(self.get_center(x, y, z));
+                    }
+                }
+            }
+        }
+        centers
+    }
+
+    /// Gets the signed distance from each voxel's center to the boundary between occupied and
+    /// empty voxels, negative within occupied voxels, indexed as the voxels are (x fastest, then y, then z).
+    /// The boundary is taken as halfway between voxel centers, so distances are accurate to about half a voxel.
+    pub fn signed_distance_field(&self) -> Vec<f32> {
+        let to_occupied = self.squared_distance_transform(true);
+        let to_empty = self.squared_distance_transform(false);
+        self.occupied
+            .iter()
+            .zip(to_occupied.iter().zip(to_empty.iter()))
+            .map(|(occupied, (to_occupied, to_empty))| {
+                if *occupied {
+                    -(to_empty.sqrt() - 0.5) * self.voxel_size
+                } else {
+                    (to_occupied.sqrt() - 0.5) * self.voxel_size
+                }
+            })
+            .collect()
+    }
+
+    /// Gets the squared distance, in voxels, from each voxel's center to the nearest center of a
+    /// voxel whose occupancy is target. This is exact, by Felzenszwalb and Huttenlocher's separable transform.
+    fn squared_distance_transform(&self, target: bool) -> Vec<f32> {
+        let mut distances: Vec<f32> = self
+            .occupied
+            .iter()
+            .map(|occupied| if *occupied == target { 0.0 } else { FAR })
+            .collect();
+        let [width, height, depth] = self.dimensions;
+        let strides = [1, width, width * height];
+        for (length, stride) in self.dimensions.into_iter().zip(strides) {
+            let mut line = vec![0.0; length];
+            for start in 0..width * height * depth {
+                // Each line along the axis starts at the voxels with a 0 coordinate on that axis.
+                if (start / stride) % length != 0 {
+                    continue;
+                }
+                for (i, value) in line.iter_mut().enumerate() {
+                    *value = distances[start + i * stride];
+                }
+                for (i, value) in distance_transform_1d(&line).into_iter().enumerate() {
+                    distances[start + i * stride] = value;
+                }
+            }
+        }
+        distances
+    }
+}
+
+/// The lower envelope of the parabolas rooted at each sample, giving each sample's squared distance
+/// to the nearest zero when the samples are 0 or FAR.
+fn distance_transform_1d(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    // The samples whose parabolas form the envelope, and the boundaries between them.
+    let mut parabolas = vec![0; n];
+    let mut boundaries = vec![0.0; n + 1];
+    let mut k = 0;
+    boundaries[0] = f32::MIN;
+    boundaries[1] = f32::MAX;
+    let intersection = |q: usize, p: usize| {
+        let (q_f, p_f) = (q as f32, p as f32);
+        ((samples[q] + q_f * q_f) - (samples[p] + p_f * p_f)) / (2.0 * (q_f - p_f))
+    };
+    for q in 1..n {
+        let mut s = intersection(q, parabolas[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = f32::MAX;
+    }
+
+    let mut distances = vec![0.0; n];
+    k = 0;
+    for (q, distance) in distances.iter_mut().enumerate() {
+        while boundaries[k + 1] < q as f32 {
+            k += 1;
+        }
+        let offset = q as f32 - parabolas[k] as f32;
+        *distance = offset * offset + samples[parabolas[k]];
+    }
+    distances
+}
+
+/// Voxelizes the triangle mesh into a grid of cubic voxels of the size, which covers the mesh's
+/// bounds with a border of empty voxels.
+pub fn voxelize(
+    vertex_positions: &[Vector3<f32>],
+    vertex_indices: &[usize],
+    voxel_size: f32,
+    mode: Mode,
+) -> VoxelGrid {
+    if vertex_positions.is_empty() {
+        return VoxelGrid::new(Vector3::new(0.0, 0.0, 0.0), voxel_size, [0, 0, 0]);
+    }
+    let (min, max) = vertex_positions.iter().fold(
+        (
+            Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+            Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+        ),
+        |(min, max), p| {
+            (
+                Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        },
+    );
+    let origin = min - Vector3::new(voxel_size, voxel_size, voxel_size);
+    let extent = (max - min) / voxel_size;
+    let dimensions = [extent.x, extent.y, extent.z].map(|e| e.ceil() as usize + 2);
+    let mut grid = VoxelGrid::new(origin, voxel_size, dimensions);
+
+    let triangles: Vec<[Vector3<f32>; 3]> = vertex_indices
+        .chunks_exact(3)
+        .map(|t| {
+            [
+                vertex_positions[t[0]],
+                vertex_positions[t[1]],
+                vertex_positions[t[2]],
+            ]
+        })
+        .collect();
+    match mode {
+        Mode::Solid => fill_solid(&mut grid, &triangles),
+        Mode::Surface => fill_surface(&mut grid, &triangles),
+    }
+    grid
+}
+
+/// Gets the range of voxels along the axis whose centers are within [min, max].
+fn center_range(grid: &VoxelGrid, axis: usize, min: f32, max: f32) -> std::ops::Range<usize> {
+    let to_voxel = |value: f32| (value - grid.origin[axis]) / grid.voxel_size - 0.5;
+    let start = to_voxel(min).ceil().max(0.0) as usize;
+    let end = (to_voxel(max).floor() + 1.0).max(0.0) as usize;
+    start..end.min(grid.dimensions[axis])
+}
+
+/// Occupies the voxels whose centers are inside the mesh, by casting a ray along x through each
+/// row of voxel centers and filling between the pairs of crossings.
+fn fill_solid(grid: &mut VoxelGrid, triangles: &[[Vector3<f32>; 3]]) {
+    let [_, height, depth] = grid.dimensions;
+    let mut crossings: Vec<Vec<f32>> = vec![Vec::new(); height * depth];
+    for triangle in triangles.iter() {
+        let [a, mut b, mut c] = *triangle;
+        let mut determinant = edge_function(a, b, c);
+        // Triangles edge-on to the rays are crossed by their neighbours instead.
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+        // Winding the projections the same way lets shared edges be assigned consistently below.
+        if determinant < 0.0 {
+            std::mem::swap(&mut b, &mut c);
+            determinant = -determinant;
+        }
+        let ys = center_range(grid, 1, a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y));
+        let zs = center_range(grid, 2, a.z.min(b.z).min(c.z), a.z.max(b.z).max(c.z));
+        for z in zs {
+            for y in ys.clone() {
+                let center = grid.get_center(0, y, z);
+                // Rows exactly on an edge cross only the triangle for which it's a top-left edge,
+                // so that rows through shared edges cross exactly one of their triangles.
+                let weights = [(b, c), (c, a), (a, b)].map(|(from, to)| {
+                    let weight = canonical_edge_function(from, to, center);
+                    if weight > 0.0 || (weight == 0.0 && is_top_left(from, to)) {
+                        Some(weight)
+                    } else {
+                        None
+                    }
+                });
+                let [Some(wa), Some(wb), Some(wc)] = weights else {
+                    continue;
+                };
+                crossings[z * height + y].push((wa * a.x + wb * b.x + wc * c.x) / determinant);
+            }
+        }
+    }
+
+    for z in 0..depth {
+        for y in 0..height {
+            let row = &mut crossings[z * height + y];
+            row.sort_by(|a, b| a.total_cmp(b));
+            // An unpaired crossing means the mesh isn't closed; it's ignored.
+            for pair in row.chunks_exact(2) {
+                for x in center_range(grid, 0, pair[0], pair[1]) {
+                    let index = grid.index(x, y, z);
+                    grid.occupied[index] = true;
+                }
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle (from, to, point) projected onto YZ, positive if it's counterclockwise.
+fn edge_function(from: Vector3<f32>, to: Vector3<f32>, point: Vector3<f32>) -> f32 {
+    (to.y - from.y) * (point.z - from.z) - (to.z - from.z) * (point.y - from.y)
+}
+
+/// The edge function, computed the same way for both directions along the edge, so that
+/// triangles sharing it get exactly opposite weights for points near it.
+fn canonical_edge_function(from: Vector3<f32>, to: Vector3<f32>, point: Vector3<f32>) -> f32 {
+    if (from.y, from.z) < (to.y, to.z) {
+        edge_function(from, to, point)
+    } else {
+        -edge_function(to, from, point)
+    }
+}
+
+/// Whether the edge of a counterclockwise triangle projected onto YZ is on its top or left,
+/// which is true of exactly one of the two directions along an edge.
+fn is_top_left(from: Vector3<f32>, to: Vector3<f32>) -> bool {
+    let (dy, dz) = (to.y - from.y, to.z - from.z);
+    dz < 0.0 || (dz == 0.0 && dy > 0.0)
+}
+
+/// Occupies the voxels which any of the triangles overlap.
+fn fill_surface(grid: &mut VoxelGrid, triangles: &[[Vector3<f32>; 3]]) {
+    let half_size = grid.voxel_size / 2.0;
+    for triangle in triangles.iter() {
+        let [a, b, c] = *triangle;
+        // The voxels whose bounds overlap the triangle's.
+        let range = |axis: usize| {
+            let min = a[axis].min(b[axis]).min(c[axis]) - half_size;
+            let max = a[axis].max(b[axis]).max(c[axis]) + half_size;
+            center_range(grid, axis, min, max)
+        };
+        let (xs, ys, zs) = (range(0), range(1), range(2));
+        for z in zs {
+            for y in ys.clone() {
+                for x in xs.clone() {
+                    if triangle_overlaps_box(triangle, grid.get_center(x, y, z), half_size) {
+                        let index = grid.index(x, y, z);
+                        grid.occupied[index] = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether the triangle overlaps the axis-aligned cube, by the separating axis theorem
+/// (Akenine-Möller, 2001): they overlap unless separated along the cube's axes, the triangle's
+/// normal, or the cross products of their edges.
+fn triangle_overlaps_box(
+    triangle: &[Vector3<f32>; 3],
+    center: Vector3<f32>,
+    half_size: f32,
+) -> bool {
+    let vertices = triangle.map(|v| v - center);
+    let edges = [
+        vertices[1] - vertices[0],
+        vertices[2] - vertices[1],
+        vertices[0] - vertices[2],
+    ];
+    let box_axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+    let mut axes = box_axes.to_vec();
+    axes.push(edges[0].cross(edges[1]));
+    for edge in edges.iter() {
+        for box_axis in box_axes.iter() {
+            axes.push(edge.cross(*box_axis));
+        }
+    }
+    axes.into_iter()
+        .filter(|axis| axis.magnitude2() > 0.0)
+        .all(|axis| {
+            let projections = vertices.map(|v| v.dot(axis));
+            let min = projections[0].min(projections[1]).min(projections[2]);
+            let max = projections[0].max(projections[1]).max(projections[2]);
+            let radius = half_size * (axis.x.abs() + axis.y.abs() + axis.z.abs());
+            min <= radius && max >= -radius
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::geometry;
+
+    #[test]
+    fn solid_sphere_volume() {
+        let (positions, indices) = geometry::get_sphere_vertices(1.0, 32, 32);
+        let grid = voxelize(&positions, &indices, 0.05, Mode::Solid);
+        let volume = grid.get_occupied_count() as f32 * 0.05_f32.powi(3);
+        let expected = 4.0 / 3.0 * std::f32::consts::PI;
+        assert!((volume - expected).abs() / expected < 0.02, "{}", volume);
+        for center in grid.get_occupied_centers() {
+            assert!(center.magnitude() < 1.0);
+        }
+    }
+
+    #[test]
+    fn surface_is_a_shell() {
+        let (positions, indices) = geometry::get_sphere_vertices(1.0, 32, 32);
+        let grid = voxelize(&positions, &indices, 0.1, Mode::Surface);
+        let centers = grid.get_occupied_centers();
+        assert!(!centers.is_empty());
+        // Every voxel the surface passes through is within a voxel's diagonal of the sphere.
+        for center in centers {
+            assert!((center.magnitude() - 1.0).abs() < 0.1 * 3.0_f32.sqrt());
+        }
+        let [width, height, depth] = grid.get_dimensions();
+        assert!(!grid.is_occupied(width / 2, height / 2, depth / 2));
+    }
+
+    #[test]
+    fn signed_distance_field_sign_matches_ray_parity() {
+        use crate::simulation::collidable_mesh::CollidableMesh;
+        use crate::simulation::raycast::Bvh;
+
+        let (positions, indices) = geometry::get_sphere_vertices(1.0, 16, 16);
+        let grid = voxelize(&positions, &indices, 0.2, Mode::Solid);
+        let bvh = Bvh::new(CollidableMesh::new(positions, indices).get_faces().clone());
+        let sdf = grid.signed_distance_field();
+        // Off axis, so that rays don't run along the mesh's edges.
+        let direction = Vector3::new(1.0, 0.3, 0.1).normalize();
+        let [width, height, depth] = grid.get_dimensions();
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    let mut origin = grid.get_center(x, y, z);
+                    let mut crossings = 0;
+                    while let Some((distance, _)) = bvh.raycast(origin, direction) {
+                        crossings += 1;
+                        origin += direction * (distance + 1e-4);
+                    }
+                    let inside = crossings % 2 == 1;
+                    assert_eq!(inside, sdf[grid.index(x, y, z)] < 0.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn signed_distance_field_of_cube() {
+        // A 1 x 1 x 1 cube, with voxels a tenth of its side.
+        let (positions, indices) = geometry::get_cube_vertices();
+        let grid = voxelize(&positions, &indices, 0.1, Mode::Solid);
+        assert_eq!(grid.get_occupied_count(), 1000);
+        let sdf = grid.signed_distance_field();
+        let [width, height, depth] = grid.get_dimensions();
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    let center = grid.get_center(x, y, z);
+                    // The exact signed distance to the cube's surface.
+                    let q = center.map(|c| c.abs() - 0.5);
+                    let outside = q.map(|c| c.max(0.0)).magnitude();
+                    let inside = q.x.max(q.y).max(q.z).min(0.0);
+                    let expected = outside + inside;
+                    assert!(
+                        (sdf[grid.index(x, y, z)] - expected).abs() < 0.06,
+                        "{:?}: {} vs {}",
+                        center,
+                        sdf[grid.index(x, y, z)],
+                        expected
+                    );
+                }
+            }
+        }
+    }
+}
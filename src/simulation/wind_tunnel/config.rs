@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+/// The object placed in the tunnel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Sphere,
+    Cube,
+    /// A model loaded from an OBJ file.
+    Fish,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 3] = [Shape::Sphere, Shape::Cube, Shape::Fish];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Sphere => "Sphere",
+            Shape::Cube => "Cube",
+            Shape::Fish => "Fish (OBJ)",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Config {
+    pub dt: f32, // Seconds as f32
+    pub shape: Shape,
+    /// The speed of the uniform wind, which blows along +X.
+    pub wind_speed: f32,
+    /// The density of the air, in kg/m³.
+    pub air_density: f32,
+    /// Tracers spawned at the inlet per second. Each carries an equal share of the air
+    /// flowing through the inlet, so the measured force doesn't depend on the rate.
+    pub tracer_rate: f32,
+    /// The fraction of a tracer's normal velocity kept when it hits the object.
+    pub coefficient_of_restitution: f32,
+    pub coefficient_of_friction: f32,
+    /// The time for a deflected tracer to return to the wind's velocity, in seconds.
+    pub relaxation_time: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dt: Duration::from_millis(2).as_secs_f32(),
+            shape: Shape::Sphere,
+            wind_speed: 5.0,
+            air_density: 1.225,
+            tracer_rate: 3000.0,
+            coefficient_of_restitution: 0.0,
+            coefficient_of_friction: 0.0,
+            relaxation_time: 0.1,
+        }
+    }
+}
@@ -0,0 +1,341 @@
+pub mod config;
+
+use crate::simulation::configurable::Configurable;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use self::config::Config;
+use super::collidable_mesh::CollidableMesh;
+use super::consts;
+
+use cgmath::{InnerSpace, Vector3, Zero};
+use rand::Rng;
+
+/// The tunnel spans X from the inlet to the outlet, with a square cross-section.
+pub const INLET: f32 = -3.0;
+pub const OUTLET: f32 = 3.0;
+pub const HALF_WIDTH: f32 = 1.5;
+/// Tracers aren't spawned past this many.
+pub const MAX_TRACERS: usize = 10000;
+/// The drag coefficient is averaged over about this long, since the impacts of each step are noisy.
+const AVERAGING_TIME: f32 = 0.5;
+/// How often the averaged drag coefficient is recorded.
+const SAMPLE_INTERVAL: f32 = 0.05;
+/// The most recorded drag coefficients kept.
+pub const MAX_SAMPLES: usize = 600;
+
+#[derive(Clone, Copy)]
+pub struct Tracer {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    /// Whether the tracer has hit the object.
+    deflected: bool,
+}
+
+/// A wind tunnel, measuring the drag on an object in a uniform wind.
+/// The air is modelled as tracer particles which give the object their momentum when they hit it,
+/// as in Newtonian impact theory, and then relax back toward the wind's velocity.
+pub struct Simulation {
+    config: Config,
+    object: CollidableMesh,
+    /// The corners of the box bounding the object.
+    bounds: (Vector3<f32>, Vector3<f32>),
+    /// The area of the object's silhouette facing the wind, which its drag is relative to.
+    reference_area: f32,
+    tracers: Vec<Tracer>,
+    /// The fractional number of tracers to spawn, accumulated until it reaches a whole tracer.
+    spawn_budget: f32,
+    time: f32,
+    /// The net force on the object over the last step.
+    force: Vector3<f32>,
+    drag_coefficient: f32,
+    /// The time and averaged drag coefficient of each sample.
+    drag_history: VecDeque<(f32, f32)>,
+    sample_budget: f32,
+}
+
+impl Simulation {
+    pub fn new(object: CollidableMesh) -> Simulation {
+        let mut simulation = Simulation {
+            config: Config::default(),
+            object: CollidableMesh::new(Vec::new(), Vec::new()),
+            bounds: (Vector3::zero(), Vector3::zero()),
+            reference_area: 0.0,
+            tracers: Vec::new(),
+            spawn_budget: 0.0,
+            time: 0.0,
+            force: Vector3::zero(),
+            drag_coefficient: 0.0,
+            drag_history: VecDeque::with_capacity(MAX_SAMPLES),
+            sample_budget: 0.0,
+        };
+        simulation.set_object(object);
+        simulation
+    }
+
+    /// Replaces the object in the tunnel, restarting the measurements.
+    pub fn set_object(&mut self, object: CollidableMesh) {
+        self.bounds = object.get_vertices().iter().fold(
+            (
+                Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+                Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+            ),
+            |(min, max), v| {
+                let p = v.position();
+                (
+                    Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                    Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                )
+            },
+        );
+        self.reference_area = object.projected_area(Vector3::unit_x());
+        self.object = object;
+        self.time = 0.0;
+        self.force = Vector3::zero();
+        self.drag_coefficient = 0.0;
+        self.drag_history.clear();
+        self.sample_budget = 0.0;
+    }
+
+    pub fn step(&mut self) -> Duration {
+        let dt = self.config.dt;
+        self.spawn(dt);
+
+        let wind = Vector3::unit_x() * self.config.wind_speed;
+        let tracer_mass = self.tracer_mass();
+        let relaxation = (dt / self.config.relaxation_time).min(1.0);
+        let mut impulse = Vector3::<f32>::zero();
+        for tracer in self.tracers.iter_mut() {
+            let start = tracer.position;
+            let end = start + tracer.velocity * dt;
+            if let Some((normal, fraction)) = get_hit(&self.object, self.bounds, start, end) {
+                let velocity_normal = tracer.velocity.dot(normal) * normal;
+                let velocity_tangent = tracer.velocity - velocity_normal;
+                let velocity_response_tangent = if velocity_tangent.is_zero() {
+                    velocity_tangent
+                } else {
+                    velocity_tangent
+                        - velocity_tangent.normalize()
+                            * f32::min(
+                                self.config.coefficient_of_friction * velocity_normal.magnitude(),
+                                velocity_tangent.magnitude(),
+                            )
+                };
+                let velocity_response = -velocity_normal * self.config.coefficient_of_restitution
+                    + velocity_response_tangent;
+                // Only each tracer's first impact is measured. The deflected tracer is pushed
+                // back against the object as it relaxes toward the wind, which would otherwise
+                // count the wind's push on it again and again.
+                if !tracer.deflected {
+                    impulse += tracer_mass * (tracer.velocity - velocity_response);
+                    tracer.deflected = true;
+                }
+                // The tracer is left at the hit, just off of the face on the side it came from.
+                let side = -tracer.velocity.dot(normal).signum();
+                tracer.position =
+                    start + (end - start) * fraction + normal * side * consts::EPSILON;
+                tracer.velocity = velocity_response;
+            } else {
+                tracer.position = end;
+                tracer.velocity += (wind - tracer.velocity) * relaxation;
+            }
+        }
+        self.tracers.retain(|tracer| {
+            tracer.position.x <= OUTLET
+                && tracer.position.x >= INLET
+                && tracer.position.y.abs() <= HALF_WIDTH
+                && tracer.position.z.abs() <= HALF_WIDTH
+        });
+
+        self.force = impulse / dt;
+        self.time += dt;
+        self.drag_coefficient += (self.get_instantaneous_drag_coefficient()
+            - self.drag_coefficient)
+            * (dt / AVERAGING_TIME).min(1.0);
+        self.sample_budget += dt;
+        if self.sample_budget >= SAMPLE_INTERVAL {
+            self.sample_budget -= SAMPLE_INTERVAL;
+            if self.drag_history.len() == MAX_SAMPLES {
+                self.drag_history.pop_front();
+            }
+            self.drag_history
+                .push_back((self.time, self.drag_coefficient));
+        }
+
+        Duration::from_secs_f32(dt)
+    }
+
+    /// Spawns tracers at the inlet, spread over the distance the wind blows in a step so that
+    /// they don't travel in sheets.
+    fn spawn(&mut self, dt: f32) {
+        self.spawn_budget += self.config.tracer_rate * dt;
+        let mut rng = rand::thread_rng();
+        while self.spawn_budget >= 1.0 && self.tracers.len() < MAX_TRACERS {
+            self.spawn_budget -= 1.0;
+            self.tracers.push(Tracer {
+                position: Vector3::new(
+                    INLET + rng.gen_range(0.0..=self.config.wind_speed * dt),
+                    rng.gen_range(-HALF_WIDTH..=HALF_WIDTH),
+                    rng.gen_range(-HALF_WIDTH..=HALF_WIDTH),
+                ),
+                velocity: Vector3::unit_x() * self.config.wind_speed,
+                deflected: false,
+            });
+        }
+        // Tracers which couldn't be spawned are dropped, rather than all spawning at once later.
+        self.spawn_budget = self.spawn_budget.min(1.0);
+    }
+
+    /// The mass of air each tracer carries, such that the tracers' mass flows through the
+    /// inlet at the air's density.
+    fn tracer_mass(&self) -> f32 {
+        if self.config.tracer_rate <= 0.0 {
+            return 0.0;
+        }
+        let inlet_area = (2.0 * HALF_WIDTH) * (2.0 * HALF_WIDTH);
+        self.config.air_density * self.config.wind_speed * inlet_area / self.config.tracer_rate
+    }
+
+    /// The drag coefficient from the force over the last step alone.
+    fn get_instantaneous_drag_coefficient(&self) -> f32 {
+        let dynamic_pressure =
+            0.5 * self.config.air_density * self.config.wind_speed * self.config.wind_speed;
+        if dynamic_pressure * self.reference_area == 0.0 {
+            return 0.0;
+        }
+        self.force.x / (dynamic_pressure * self.reference_area)
+    }
+
+    pub fn get_timestep(&self) -> Duration {
+        Duration::from_secs_f32(self.config.dt)
+    }
+
+    pub fn get_object(&self) -> &CollidableMesh {
+        &self.object
+    }
+
+    pub fn get_tracers(&self) -> &Vec<Tracer> {
+        &self.tracers
+    }
+
+    /// The net force on the object over the last step.
+    pub fn get_force(&self) -> Vector3<f32> {
+        self.force
+    }
+
+    /// The drag coefficient, relative to the area of the object's silhouette facing the wind,
+    /// averaged over the last AVERAGING_TIME seconds.
+    pub fn get_drag_coefficient(&self) -> f32 {
+        self.drag_coefficient
+    }
+
+    /// The time and averaged drag coefficient of each recent sample, oldest first.
+    pub fn get_drag_history(&self) -> &VecDeque<(f32, f32)> {
+        &self.drag_history
+    }
+}
+
+impl Configurable for Simulation {
+    type Config = Config;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn sync_config(&mut self, config: &Config) {
+        self.config.dt = config.dt;
+        self.config.shape = config.shape;
+        self.config.wind_speed = config.wind_speed;
+        self.config.air_density = config.air_density;
+        self.config.tracer_rate = config.tracer_rate;
+        self.config.coefficient_of_restitution = config.coefficient_of_restitution;
+        self.config.coefficient_of_friction = config.coefficient_of_friction;
+        self.config.relaxation_time = config.relaxation_time;
+    }
+}
+
+/// Gets the normal of the first face of the object the segment crosses, and how far along
+/// the segment the crossing is, if any.
+fn get_hit(
+    object: &CollidableMesh,
+    (min, max): (Vector3<f32>, Vector3<f32>),
+    start: Vector3<f32>,
+    end: Vector3<f32>,
+) -> Option<(Vector3<f32>, f32)> {
+    if start.x.min(end.x) > max.x
+        || start.x.max(end.x) < min.x
+        || start.y.min(end.y) > max.y
+        || start.y.max(end.y) < min.y
+        || start.z.min(end.z) > max.z
+        || start.z.max(end.z) < min.z
+    {
+        return None;
+    }
+    let length = (end - start).magnitude();
+    if length == 0.0 {
+        return None;
+    }
+    let direction = (end - start) / length;
+    object
+        .get_faces()
+        .iter()
+        .filter_map(|face| {
+            face.ray_intersection(start, direction)
+                .filter(|distance| *distance <= length)
+                .map(|distance| (face.normal(), distance / length))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_cube() -> CollidableMesh {
+        let (vertex_positions, vertex_indices) = crate::simulation::geometry::get_cube_vertices();
+        CollidableMesh::new(vertex_positions, vertex_indices)
+    }
+
+    #[test]
+    fn flat_face_inelastic_drag() {
+        // Each tracer loses all of its momentum against the cube's upstream face, so the force
+        // is the momentum flux through the face, rho U^2 A, and the drag coefficient is 2.
+        let mut simulation = Simulation::new(get_cube());
+        simulation.config.tracer_rate = 6000.0;
+        for _ in 0..1500 {
+            simulation.step();
+        }
+        let drag_coefficient = simulation.get_drag_coefficient();
+        assert!(
+            (drag_coefficient - 2.0).abs() < 0.3,
+            "drag coefficient {}",
+            drag_coefficient
+        );
+    }
+
+    #[test]
+    fn tracers_do_not_pass_through() {
+        let mut simulation = Simulation::new(get_cube());
+        for _ in 0..1500 {
+            simulation.step();
+        }
+        assert!(!simulation.get_tracers().is_empty());
+        let inside = simulation.get_tracers().iter().filter(|tracer| {
+            tracer.position.x.abs() < 0.5 - consts::EPSILON
+                && tracer.position.y.abs() < 0.5 - consts::EPSILON
+                && tracer.position.z.abs() < 0.5 - consts::EPSILON
+        });
+        assert_eq!(inside.count(), 0);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut simulation = Simulation::new(get_cube());
+        simulation.config.tracer_rate = 0.0;
+        let steps = (MAX_SAMPLES as f32 * 2.0 * SAMPLE_INTERVAL / simulation.config.dt) as usize;
+        for _ in 0..steps {
+            simulation.step();
+        }
+        assert_eq!(simulation.get_drag_history().len(), MAX_SAMPLES);
+    }
+}